@@ -0,0 +1,91 @@
+//! パーサーと端末の状態のファジング
+//!
+//! 任意のバイト列を `AnsiParser::process` で `Terminal` に流し、
+//! panic しないこと・カーソルとスクロール領域が画面内にあること（`Terminal::assert_invariants`）を確かめる
+//!
+//! 入力の先頭 4 バイトで最初の大きさと途中で変える大きさを決め、残りを半分ずつ流す
+//!
+//! ```sh
+//! cargo +nightly fuzz run parser
+//! ```
+
+#![no_main]
+#![allow(dead_code)]
+
+use libfuzzer_sys::fuzz_target;
+
+// 本体は実行ファイルだけのクレートなので、パーサーと端末の状態のモジュールを直接読み込む
+#[path = "../../src/grid.rs"]
+mod grid;
+#[path = "../../src/parser.rs"]
+mod parser;
+#[path = "../../src/scrollback.rs"]
+mod scrollback;
+#[path = "../../src/terminal.rs"]
+mod terminal;
+#[path = "../../src/wsl.rs"]
+mod wsl;
+
+/// 設定モジュールの代わり（設定ファイルは読まず、環境変数だけを見る）
+///
+/// 本体の `config` は UI 全体に依存するので、端末の状態が使う分だけを用意する
+mod config {
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    /// `[scrollback]`
+    #[derive(Debug, Default)]
+    pub struct ScrollbackConfig {
+        pub max_mb: Option<u64>,
+        pub lines: Option<usize>,
+        pub spill: Option<bool>,
+    }
+
+    /// `[profiles.<名前>]`
+    #[derive(Debug, Default)]
+    pub struct ProfileConfig {
+        pub scrollback: ScrollbackConfig,
+    }
+
+    /// 設定ファイルの内容
+    #[derive(Debug, Default)]
+    pub struct Config {
+        pub scrollback: ScrollbackConfig,
+        pub profiles: BTreeMap<String, ProfileConfig>,
+    }
+
+    pub fn var(name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+
+    pub fn home_dir() -> Option<PathBuf> {
+        std::env::var_os("HOME").map(PathBuf::from)
+    }
+}
+
+use parser::AnsiParser;
+use terminal::Terminal;
+
+/// 画面の大きさ（1〜120 桁、1〜50 行）
+fn size(cols: u8, rows: u8) -> (usize, usize) {
+    (1 + cols as usize % 120, 1 + rows as usize % 50)
+}
+
+fuzz_target!(|data: &[u8]| {
+    let [cols, rows, resize_cols, resize_rows, rest @ ..] = data else { return };
+
+    let (cols, rows) = size(*cols, *rows);
+    let mut terminal = Terminal::new(cols, rows);
+    let mut parser = AnsiParser::new();
+
+    let (first, second) = rest.split_at(rest.len() / 2);
+    parser.process(&mut terminal, first);
+    terminal.assert_invariants();
+
+    let (cols, rows) = size(*resize_cols, *resize_rows);
+    terminal.resize(cols, rows);
+    terminal.assert_invariants();
+
+    parser.process(&mut terminal, second);
+    terminal.assert_invariants();
+});
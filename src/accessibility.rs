@@ -0,0 +1,330 @@
+//! アクセシビリティモジュール
+//!
+//! AccessKit でウィンドウの中身をスクリーンリーダー（VoiceOver・Orca・ナレーター）に公開する
+//! 表示中のタブのペインをそれぞれ1つのターミナルとして画面の各行とカーソルの位置を渡し、
+//! フォーカス中のペインで出力し終えた行はライブリージョンに入れて読み上げてもらう
+//!
+//! スクリーンリーダーが繋がるまでは何もしない
+
+use std::collections::HashMap;
+
+use accesskit::{
+    Action, ActionRequest, Live, Node, NodeId, Rect as NodeRect, Role, TextPosition, TextSelection, Tree, TreeUpdate,
+};
+use accesskit_winit::Adapter;
+use winit::event::WindowEvent;
+use winit::event_loop::{ActiveEventLoop, EventLoopProxy};
+use winit::window::Window;
+
+use crate::grid::mark_wide_spacers;
+use crate::pane::{PaneId, Rect};
+use crate::snapshot::TerminalSnapshot;
+use crate::terminal::Terminal;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 定数
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// ウィンドウのノード
+const ROOT: NodeId = NodeId(1);
+
+/// 新しい出力を読み上げるライブリージョンのノード
+const ANNOUNCEMENT: NodeId = NodeId(2);
+
+/// 一度に読み上げる行の上限（大量の出力は最後の行だけ）
+const ANNOUNCE_LINES: usize = 10;
+
+/// ペインのノードIDの間隔（ペインのノードの後ろに行のノードが続く）
+const PANE_STRIDE: u64 = 1 << 16;
+
+/// ペインのノード（行のノードと重ならないよう PANE_STRIDE ずつ離す）
+fn pane_node(pane: PaneId) -> NodeId {
+    NodeId((pane.0 + 1) * PANE_STRIDE)
+}
+
+/// ペインの画面の行のノード
+fn row_node(pane: PaneId, row: usize) -> NodeId {
+    NodeId(pane_node(pane).0 + 1 + row as u64)
+}
+
+/// ノードが属するペイン（ペインとその行のノードのみ）
+fn node_pane(node: NodeId) -> Option<PaneId> {
+    (node.0 / PANE_STRIDE).checked_sub(1).map(PaneId)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 公開する中身
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// スクリーンリーダーに渡すペインの中身
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaneView {
+    /// ペイン
+    pub id: PaneId,
+    /// 名前（タイトルと作業ディレクトリ）
+    pub label: String,
+    /// ウィンドウの中のターミナル部分の位置（物理ピクセル）
+    pub bounds: NodeRect,
+    /// 画面の各行（右端の空白は除く）
+    pub lines: Vec<String>,
+    /// カーソルの位置（行と、その行の何文字目か、見えなければ None）
+    pub cursor: Option<(usize, usize)>,
+}
+
+impl PaneView {
+    /// スナップショットから作る（rect は画面に対する割合、screen は画面の大きさ）
+    pub fn new(id: PaneId, label: String, rect: Rect, screen: (u32, u32), snapshot: &TerminalSnapshot) -> Self {
+        let (width, height) = (screen.0 as f64, screen.1 as f64);
+        let bounds = NodeRect::new(
+            rect.x as f64 * width,
+            rect.y as f64 * height,
+            (rect.x + rect.width) as f64 * width,
+            (rect.y + rect.height) as f64 * height,
+        );
+        let cursor = &snapshot.cursor;
+        let mut lines = Vec::with_capacity(snapshot.rows);
+        let mut cursor_index = None;
+        for row in 0..snapshot.rows {
+            let chars = (0..snapshot.cols).map(|col| snapshot[(col, row)].character).collect();
+            let cursor_col = (cursor.visible && cursor.row == row).then_some(cursor.col);
+            let (line, index) = line_text(chars, cursor_col);
+            if let Some(index) = index {
+                cursor_index = Some((row, index));
+            }
+            lines.push(line);
+        }
+        Self { id, label, bounds, lines, cursor: cursor_index }
+    }
+}
+
+/// 画面の1行の文字列と、列 col がその何文字目か
+///
+/// 全角文字の2セル目は数えない。右端の空白は除くが、カーソルのある列までは残す
+fn line_text(mut chars: Vec<char>, col: Option<usize>) -> (String, Option<usize>) {
+    mark_wide_spacers(&mut chars);
+    let index = col.map(|col| chars[..col.min(chars.len())].iter().filter(|c| **c != '\0').count());
+    let chars: Vec<char> = chars.into_iter().filter(|c| *c != '\0').collect();
+    let used = chars.iter().rposition(|c| *c != ' ').map_or(0, |last| last + 1);
+    let len = used.max(index.unwrap_or(0)).min(chars.len());
+    (chars[..len].iter().collect(), index.map(|index| index.min(len)))
+}
+
+/// 読み上げる出力（空行を除いた最後の ANNOUNCE_LINES 行、なければ None）
+fn announcement_text(lines: &[(u64, String)]) -> Option<String> {
+    let lines: Vec<&str> = lines.iter().map(|(_, line)| line.as_str()).filter(|line| !line.trim().is_empty()).collect();
+    let skip = lines.len().saturating_sub(ANNOUNCE_LINES);
+    (!lines.is_empty()).then(|| lines[skip..].join("\n"))
+}
+
+/// スクリーンリーダーに渡すウィンドウの中身
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct WindowView {
+    /// ウィンドウのタイトル
+    pub title: String,
+    /// 表示中のタブのペイン
+    pub panes: Vec<PaneView>,
+    /// フォーカス中のペイン
+    pub focused: Option<PaneId>,
+}
+
+/// 木全体を作る（イベントループのプロキシで繋いだアダプターには毎回すべてのノードを渡す）
+fn tree_update(view: &WindowView, announcement: &str) -> TreeUpdate {
+    let mut nodes = Vec::new();
+    let mut root = Node::new(Role::Window);
+    root.set_label(view.title.as_str());
+
+    for pane in &view.panes {
+        let id = pane_node(pane.id);
+        let mut terminal = Node::new(Role::Terminal);
+        terminal.set_label(pane.label.as_str());
+        terminal.set_bounds(pane.bounds);
+        terminal.add_action(Action::Focus);
+
+        // 行ごとに1つのテキストにし、最後の行以外は改行で終える
+        let row_height = pane.bounds.height() / pane.lines.len().max(1) as f64;
+        let last = pane.lines.len().saturating_sub(1);
+        for (row, line) in pane.lines.iter().enumerate() {
+            let mut value = line.clone();
+            let mut lengths: Vec<u8> = line.chars().map(|c| c.len_utf8() as u8).collect();
+            if row < last {
+                value.push('\n');
+                lengths.push(1);
+            }
+            let mut text = Node::new(Role::TextRun);
+            text.set_value(value);
+            text.set_character_lengths(lengths);
+            let y0 = pane.bounds.y0 + row_height * row as f64;
+            text.set_bounds(NodeRect::new(pane.bounds.x0, y0, pane.bounds.x1, y0 + row_height));
+            terminal.push_child(row_node(pane.id, row));
+            nodes.push((row_node(pane.id, row), text));
+        }
+
+        // カーソルは幅のない選択として渡す
+        if let Some((row, index)) = pane.cursor {
+            let position = TextPosition { node: row_node(pane.id, row), character_index: index };
+            terminal.set_text_selection(TextSelection { anchor: position, focus: position });
+        }
+        root.push_child(id);
+        nodes.push((id, terminal));
+    }
+
+    let mut live = Node::new(Role::Label);
+    live.set_value(announcement);
+    live.set_live(Live::Polite);
+    root.push_child(ANNOUNCEMENT);
+    nodes.push((ANNOUNCEMENT, live));
+    nodes.push((ROOT, root));
+
+    let focus = view.focused.filter(|focused| view.panes.iter().any(|pane| pane.id == *focused));
+    TreeUpdate { nodes, tree: Some(Tree::new(ROOT)), focus: focus.map_or(ROOT, pane_node) }
+}
+
+/// フォーカスを求められたペイン（スクリーンリーダーでペインやその行を選んだとき）
+pub fn focus_target(request: &ActionRequest) -> Option<PaneId> {
+    (request.action == Action::Focus).then(|| node_pane(request.target)).flatten()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ウィンドウごとの状態
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// ウィンドウとスクリーンリーダーのつなぎ
+pub struct Accessibility {
+    /// AccessKit のアダプター
+    adapter: Adapter,
+    /// スクリーンリーダーが繋がっているか
+    active: bool,
+    /// 最後に渡した中身と読み上げ（変わったときだけ渡し直す）
+    last: Option<(WindowView, String)>,
+    /// 読み上げている出力
+    announcement: String,
+    /// ペインごとの、次に読み上げる行の通し番号
+    announced: HashMap<PaneId, u64>,
+}
+
+impl Accessibility {
+    /// アダプターを作る（ウィンドウを表示する前に呼ぶ）
+    pub fn new<T: From<accesskit_winit::Event> + Send + 'static>(
+        event_loop: &ActiveEventLoop,
+        window: &Window,
+        proxy: EventLoopProxy<T>,
+    ) -> Self {
+        Self {
+            adapter: Adapter::with_event_loop_proxy(event_loop, window, proxy),
+            active: false,
+            last: None,
+            announcement: String::new(),
+            announced: HashMap::new(),
+        }
+    }
+
+    /// ウィンドウのイベントをアダプターに渡す（処理する前に呼ぶ）
+    pub fn process_event(&mut self, window: &Window, event: &WindowEvent) {
+        self.adapter.process_event(window, event);
+    }
+
+    /// スクリーンリーダーが繋がっているか
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// スクリーンリーダーが繋がった（次の update で木全体を渡す、それまでの出力は読み上げない）
+    pub fn activate(&mut self) {
+        self.active = true;
+        self.last = None;
+        self.announcement.clear();
+        self.announced.clear();
+    }
+
+    /// スクリーンリーダーが離れた
+    pub fn deactivate(&mut self) {
+        self.active = false;
+        self.last = None;
+    }
+
+    /// ペインで前回から出力し終えた行を読み上げに入れる（初めて見るペインはこれからの出力だけ）
+    pub fn read_output(&mut self, pane: PaneId, terminal: &Terminal) {
+        let start = self.announced.get(&pane).copied().unwrap_or(u64::MAX);
+        let (lines, next) = terminal.output_lines_from(start);
+        self.announced.insert(pane, next);
+        if let Some(text) = announcement_text(&lines) {
+            self.announcement = text;
+        }
+    }
+
+    /// 中身を渡す（前回と同じなら何もしない）
+    pub fn update(&mut self, view: WindowView) {
+        if !self.active {
+            return;
+        }
+        // 表示しなくなったペインは、また表示したときの出力から読み上げる
+        self.announced.retain(|id, _| view.panes.iter().any(|pane| pane.id == *id));
+        if self.last.as_ref().is_some_and(|(last, announcement)| *last == view && *announcement == self.announcement) {
+            return;
+        }
+        self.adapter.update_if_active(|| tree_update(&view, &self.announcement));
+        self.last = Some((view, self.announcement.clone()));
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::AnsiParser;
+
+    #[test]
+    fn test_line_text() {
+        // 全角文字は1文字（グリッドの2セル目は空白）、右端の空白は除く
+        let chars: Vec<char> = "あ い  b   ".chars().collect();
+        assert_eq!(line_text(chars.clone(), None), ("あい b".to_string(), None));
+        // カーソルのある列までは空白を残し、全角文字の2セル目は数えない
+        assert_eq!(line_text(chars.clone(), Some(8)), ("あい b  ".to_string(), Some(6)));
+        assert_eq!(line_text(chars.clone(), Some(2)), ("あい b".to_string(), Some(1)));
+        assert_eq!(line_text(chars, Some(20)), ("あい b   ".to_string(), Some(7)));
+
+        // 読み上げは空行を除いた最後の10行
+        let lines: Vec<(u64, String)> = (0..15).map(|n| (n, if n == 14 { String::new() } else { n.to_string() })).collect();
+        assert_eq!(announcement_text(&lines).unwrap(), (4..14).map(|n| n.to_string()).collect::<Vec<_>>().join("\n"));
+        assert_eq!(announcement_text(&lines[14..]), None);
+    }
+
+    #[test]
+    fn test_tree_update() {
+        let mut terminal = Terminal::new(6, 2);
+        AnsiParser::new().process(&mut terminal, "$ ls\r\né".as_bytes());
+        let mut snapshot = TerminalSnapshot::new();
+        snapshot.update(&mut terminal);
+        let rect = Rect { x: 0.5, y: 0.0, width: 0.5, height: 1.0 };
+        let pane = PaneView::new(PaneId(3), "zsh".into(), rect, (200, 100), &snapshot);
+        assert_eq!(pane.lines, ["$ ls", "é"]);
+        assert_eq!(pane.cursor, Some((1, 1)));
+        assert_eq!(pane.bounds, NodeRect::new(100.0, 0.0, 200.0, 100.0));
+
+        let view = WindowView { title: "UmiTerm".into(), panes: vec![pane], focused: Some(PaneId(3)) };
+        let update = tree_update(&view, "hello");
+        assert_eq!(update.focus, pane_node(PaneId(3)));
+        let node = |id| &update.nodes.iter().find(|(node, _)| *node == id).unwrap().1;
+
+        assert_eq!(node(ROOT).children(), [pane_node(PaneId(3)), ANNOUNCEMENT]);
+        let terminal = node(pane_node(PaneId(3)));
+        assert_eq!(terminal.role(), Role::Terminal);
+        assert_eq!(terminal.children(), [row_node(PaneId(3), 0), row_node(PaneId(3), 1)]);
+        let cursor = TextPosition { node: row_node(PaneId(3), 1), character_index: 1 };
+        assert_eq!(terminal.text_selection(), Some(&TextSelection { anchor: cursor, focus: cursor }));
+
+        // 最後の行以外は改行で終わり、文字の長さはバイト数
+        assert_eq!(node(row_node(PaneId(3), 0)).value(), Some("$ ls\n"));
+        assert_eq!(node(row_node(PaneId(3), 1)).character_lengths(), [2]);
+        assert_eq!(node(ANNOUNCEMENT).value(), Some("hello"));
+        assert_eq!(node(ANNOUNCEMENT).live(), Some(Live::Polite));
+
+        // 行を選んでもそのペインにフォーカスする
+        let request = ActionRequest { action: Action::Focus, target: row_node(PaneId(3), 1), data: None };
+        assert_eq!(focus_target(&request), Some(PaneId(3)));
+        assert_eq!(focus_target(&ActionRequest { target: ROOT, ..request }), None);
+    }
+}
@@ -0,0 +1,186 @@
+//! エクスプローラーのブックマーク
+//!
+//! よく使うディレクトリのピン留めと、各ペインのシェルが移動した（OSC 7）最近の
+//! ディレクトリを覚えておき、エクスプローラーの上部からすぐ開けるようにする
+//! 内容は `~/.local/share/umiterm/bookmarks` に1行1つ、`pin` / `recent` とパスをタブで区切って保存し、
+//! 次に起動したときも使う
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// 覚えておく最近のディレクトリの数
+const MAX_RECENT: usize = 10;
+
+/// ブックマークの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaceKind {
+    /// ピン留めしたディレクトリ
+    Pinned,
+    /// 最近のディレクトリ
+    Recent,
+}
+
+/// ピン留めしたディレクトリと最近のディレクトリ
+#[derive(Debug, Default)]
+pub struct Bookmarks {
+    /// ピン留めしたディレクトリ（ピン留めした順）
+    pub pinned: Vec<PathBuf>,
+    /// 最近のディレクトリ（新しい順）
+    pub recent: Vec<PathBuf>,
+    /// 保存先（None なら保存しない）
+    file: Option<PathBuf>,
+}
+
+/// 保存先（`$XDG_DATA_HOME/umiterm/bookmarks`、なければ `~/.local/share/umiterm/bookmarks`）
+fn default_file() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(dir.join("umiterm").join("bookmarks"))
+}
+
+impl Bookmarks {
+    /// 保存先から読み込む
+    pub fn load_default() -> Self {
+        Self::load(default_file())
+    }
+
+    /// ファイルから読み込む（なければ空）
+    pub fn load(file: Option<PathBuf>) -> Self {
+        let mut bookmarks = Self { file, ..Self::default() };
+        bookmarks.reload();
+        bookmarks
+    }
+
+    /// ファイルから読み直す（別のウィンドウでの変更を取り込む）
+    pub fn reload(&mut self) {
+        let Some(file) = &self.file else { return };
+        let text = fs::read_to_string(file).unwrap_or_default();
+        self.pinned.clear();
+        self.recent.clear();
+        for line in text.lines() {
+            let Some((kind, path)) = line.split_once('\t') else { continue };
+            let path = PathBuf::from(path);
+            match kind {
+                "pin" if !self.pinned.contains(&path) => self.pinned.push(path),
+                "recent" if !self.recent.contains(&path) => self.recent.push(path),
+                _ => {}
+            }
+        }
+        self.recent.truncate(MAX_RECENT);
+    }
+
+    /// ファイルに保存
+    fn save(&self) -> Result<()> {
+        let Some(file) = &self.file else { return Ok(()) };
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("ディレクトリを作成できません: {}", dir.display()))?;
+        }
+        let mut text = String::new();
+        for path in &self.pinned {
+            text.push_str(&format!("pin\t{}\n", path.display()));
+        }
+        for path in &self.recent {
+            text.push_str(&format!("recent\t{}\n", path.display()));
+        }
+        fs::write(file, text).with_context(|| format!("ブックマークを保存できません: {}", file.display()))
+    }
+
+    /// 変更して保存する（別のウィンドウでの変更を消さないよう、読み直してから変更する）
+    fn update(&mut self, change: impl FnOnce(&mut Self)) {
+        self.reload();
+        change(self);
+        if let Err(e) = self.save() {
+            log::warn!("{:#}", e);
+        }
+    }
+
+    /// ピン留めする（ピン留め済みなら外す）
+    /// 戻り値: ピン留めしたか
+    pub fn toggle_pin(&mut self, path: &Path) -> bool {
+        let mut pinned = false;
+        self.update(|bookmarks| match bookmarks.pinned.iter().position(|p| p == path) {
+            Some(index) => {
+                bookmarks.pinned.remove(index);
+            }
+            None => {
+                bookmarks.pinned.push(path.to_path_buf());
+                pinned = true;
+            }
+        });
+        pinned
+    }
+
+    /// ピン留め・最近のディレクトリから外す
+    pub fn remove(&mut self, path: &Path) {
+        self.update(|bookmarks| {
+            bookmarks.pinned.retain(|p| p != path);
+            bookmarks.recent.retain(|p| p != path);
+        });
+    }
+
+    /// 最近のディレクトリの先頭に加える（すでに先頭なら何もしない）
+    pub fn visit(&mut self, path: &Path) {
+        if self.recent.first().is_some_and(|first| first == path) {
+            return;
+        }
+        self.update(|bookmarks| {
+            bookmarks.recent.retain(|p| p != path);
+            bookmarks.recent.insert(0, path.to_path_buf());
+            bookmarks.recent.truncate(MAX_RECENT);
+        });
+    }
+
+    /// 表示する一覧（ピン留め、続いてピン留めしていない最近のディレクトリ）
+    pub fn places(&self) -> Vec<(PlaceKind, &Path)> {
+        let pinned = self.pinned.iter().map(|path| (PlaceKind::Pinned, path.as_path()));
+        let recent = self
+            .recent
+            .iter()
+            .filter(|path| !self.pinned.contains(path))
+            .map(|path| (PlaceKind::Recent, path.as_path()));
+        pinned.chain(recent).collect()
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bookmarks() {
+        let dir = std::env::temp_dir().join(format!("umiterm-bookmarks-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let file = dir.join("umiterm/bookmarks");
+        let mut bookmarks = Bookmarks::load(Some(file.clone()));
+        assert!(bookmarks.places().is_empty());
+
+        // 最近のディレクトリは新しい順、ピン留めしたものは一覧の先頭に1度だけ
+        for i in 0..12 {
+            bookmarks.visit(Path::new(&format!("/tmp/{}", i)));
+        }
+        bookmarks.visit(Path::new("/tmp/5"));
+        assert!(bookmarks.toggle_pin(Path::new("/tmp/5")));
+        let places = bookmarks.places();
+        assert_eq!(places[0], (PlaceKind::Pinned, Path::new("/tmp/5")));
+        assert_eq!(places[1], (PlaceKind::Recent, Path::new("/tmp/11")));
+        assert_eq!(places.len(), MAX_RECENT);
+
+        // 保存され、次に読み込んだときも同じ
+        let reloaded = Bookmarks::load(Some(file.clone()));
+        assert_eq!(reloaded.pinned, bookmarks.pinned);
+        assert_eq!(reloaded.recent, bookmarks.recent);
+
+        assert!(!bookmarks.toggle_pin(Path::new("/tmp/5")));
+        bookmarks.remove(Path::new("/tmp/11"));
+        assert_eq!(bookmarks.places()[0], (PlaceKind::Recent, Path::new("/tmp/5")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
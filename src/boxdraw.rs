@@ -0,0 +1,458 @@
+//! 罫線・ブロック要素の描画モジュール
+//!
+//! フォントの罫線グリフはセルの端まで届かず、TUIの枠線に隙間ができる
+//! U+2500〜U+259F と Powerline の三角形はフォントを使わず、
+//! セルの大きさぴったりの矩形の組み合わせとして描画する
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 定数
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 罫線の太さ
+const NONE: u8 = 0;
+const LIGHT: u8 = 1;
+const HEAVY: u8 = 2;
+const DOUBLE: u8 = 3;
+
+/// 罫線（U+2500〜U+257F）の各方向の太さ [上, 右, 下, 左]
+///
+/// 破線は太さだけを持ち、分割数は `dash_count` で決める
+/// 斜線（U+2571〜U+2573）はすべて NONE（フォントで描画）
+#[rustfmt::skip]
+const LINES: [[u8; 4]; 128] = [
+    // ─ ━ │ ┃ ┄ ┅ ┆ ┇
+    [0, 1, 0, 1], [0, 2, 0, 2], [1, 0, 1, 0], [2, 0, 2, 0],
+    [0, 1, 0, 1], [0, 2, 0, 2], [1, 0, 1, 0], [2, 0, 2, 0],
+    // ┈ ┉ ┊ ┋ ┌ ┍ ┎ ┏
+    [0, 1, 0, 1], [0, 2, 0, 2], [1, 0, 1, 0], [2, 0, 2, 0],
+    [0, 1, 1, 0], [0, 2, 1, 0], [0, 1, 2, 0], [0, 2, 2, 0],
+    // ┐ ┑ ┒ ┓ └ ┕ ┖ ┗
+    [0, 0, 1, 1], [0, 0, 1, 2], [0, 0, 2, 1], [0, 0, 2, 2],
+    [1, 1, 0, 0], [1, 2, 0, 0], [2, 1, 0, 0], [2, 2, 0, 0],
+    // ┘ ┙ ┚ ┛ ├ ┝ ┞ ┟
+    [1, 0, 0, 1], [1, 0, 0, 2], [2, 0, 0, 1], [2, 0, 0, 2],
+    [1, 1, 1, 0], [1, 2, 1, 0], [2, 1, 1, 0], [1, 1, 2, 0],
+    // ┠ ┡ ┢ ┣ ┤ ┥ ┦ ┧
+    [2, 1, 2, 0], [2, 2, 1, 0], [1, 2, 2, 0], [2, 2, 2, 0],
+    [1, 0, 1, 1], [1, 0, 1, 2], [2, 0, 1, 1], [1, 0, 2, 1],
+    // ┨ ┩ ┪ ┫ ┬ ┭ ┮ ┯
+    [2, 0, 2, 1], [2, 0, 1, 2], [1, 0, 2, 2], [2, 0, 2, 2],
+    [0, 1, 1, 1], [0, 1, 1, 2], [0, 2, 1, 1], [0, 2, 1, 2],
+    // ┰ ┱ ┲ ┳ ┴ ┵ ┶ ┷
+    [0, 1, 2, 1], [0, 1, 2, 2], [0, 2, 2, 1], [0, 2, 2, 2],
+    [1, 1, 0, 1], [1, 1, 0, 2], [1, 2, 0, 1], [1, 2, 0, 2],
+    // ┸ ┹ ┺ ┻ ┼ ┽ ┾ ┿
+    [2, 1, 0, 1], [2, 1, 0, 2], [2, 2, 0, 1], [2, 2, 0, 2],
+    [1, 1, 1, 1], [1, 1, 1, 2], [1, 2, 1, 1], [1, 2, 1, 2],
+    // ╀ ╁ ╂ ╃ ╄ ╅ ╆ ╇
+    [2, 1, 1, 1], [1, 1, 2, 1], [2, 1, 2, 1], [2, 1, 1, 2],
+    [2, 2, 1, 1], [1, 1, 2, 2], [1, 2, 2, 1], [2, 2, 1, 2],
+    // ╈ ╉ ╊ ╋ ╌ ╍ ╎ ╏
+    [1, 2, 2, 2], [2, 1, 2, 2], [2, 2, 2, 1], [2, 2, 2, 2],
+    [0, 1, 0, 1], [0, 2, 0, 2], [1, 0, 1, 0], [2, 0, 2, 0],
+    // ═ ║ ╒ ╓ ╔ ╕ ╖ ╗
+    [0, 3, 0, 3], [3, 0, 3, 0], [0, 3, 1, 0], [0, 1, 3, 0],
+    [0, 3, 3, 0], [0, 0, 1, 3], [0, 0, 3, 1], [0, 0, 3, 3],
+    // ╘ ╙ ╚ ╛ ╜ ╝ ╞ ╟
+    [1, 3, 0, 0], [3, 1, 0, 0], [3, 3, 0, 0], [1, 0, 0, 3],
+    [3, 0, 0, 1], [3, 0, 0, 3], [1, 3, 1, 0], [3, 1, 3, 0],
+    // ╠ ╡ ╢ ╣ ╤ ╥ ╦ ╧
+    [3, 3, 3, 0], [1, 0, 1, 3], [3, 0, 3, 1], [3, 0, 3, 3],
+    [0, 3, 1, 3], [0, 1, 3, 1], [0, 3, 3, 3], [1, 3, 0, 3],
+    // ╨ ╩ ╪ ╫ ╬ ╭ ╮ ╯（円弧は角として描画）
+    [3, 1, 0, 1], [3, 3, 0, 3], [1, 3, 1, 3], [3, 1, 3, 1],
+    [3, 3, 3, 3], [0, 1, 1, 0], [0, 0, 1, 1], [1, 0, 0, 1],
+    // ╰ ╱ ╲ ╳ ╴ ╵ ╶ ╷
+    [1, 1, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0],
+    [0, 0, 0, 1], [1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0],
+    // ╸ ╹ ╺ ╻ ╼ ╽ ╾ ╿
+    [0, 0, 0, 2], [2, 0, 0, 0], [0, 2, 0, 0], [0, 0, 2, 0],
+    [0, 2, 0, 1], [1, 0, 2, 0], [0, 1, 0, 2], [2, 0, 1, 0],
+];
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 矩形
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// セル内の矩形（セル左上からのピクセル座標）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoxRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// 前景色の濃さ（網掛け用、通常は 1.0）
+    pub alpha: f32,
+}
+
+impl BoxRect {
+    fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { x, y, width, height, alpha: 1.0 }
+    }
+}
+
+/// 手続き的に描画する文字か
+pub fn is_box_char(c: char) -> bool {
+    matches!(c, '\u{2500}'..='\u{2570}' | '\u{2574}'..='\u{259F}' | '\u{E0B0}'..='\u{E0B3}')
+}
+
+/// 文字を矩形に分解して `out` に追加
+///
+/// 戻り値: 手続き的に描画したか（false ならフォントで描画する）
+pub fn draw(c: char, width: f32, height: f32, out: &mut Vec<BoxRect>) -> bool {
+    if !is_box_char(c) {
+        return false;
+    }
+    let code = c as u32;
+    match code {
+        0x2500..=0x257F => {
+            let arms = LINES[(code - 0x2500) as usize];
+            match dash_count(code) {
+                Some(n) => dashes(arms, n, width, height, out),
+                None => lines(arms, width, height, out),
+            }
+        }
+        0x2580..=0x259F => blocks(code, width, height, out),
+        _ => powerline(code, width, height, out),
+    }
+    true
+}
+
+/// 細線の太さ（ピクセル）
+fn thickness(width: f32) -> f32 {
+    (width / 12.0).round().max(1.0)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 罫線
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 破線の分割数
+fn dash_count(code: u32) -> Option<usize> {
+    match code {
+        0x2504..=0x2507 => Some(3),
+        0x2508..=0x250B => Some(4),
+        0x254C..=0x254F => Some(2),
+        _ => None,
+    }
+}
+
+/// 線の帯（開始位置, 太さ）を太さの種類から計算（中央寄せ、整数ピクセル）
+fn bands(weight: u8, len: f32, t: f32) -> Vec<(f32, f32)> {
+    let centered = |total: f32| ((len - total) / 2.0).floor();
+    match weight {
+        LIGHT => vec![(centered(t), t)],
+        HEAVY => vec![(centered(t * 2.0), t * 2.0)],
+        DOUBLE => {
+            let start = centered(t * 3.0);
+            vec![(start, t), (start + t * 2.0, t)]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// 帯の集合の範囲（最小の開始位置, 最大の終了位置）
+fn span(sets: &[&[(f32, f32)]]) -> Option<(f32, f32)> {
+    sets.iter()
+        .flat_map(|set| set.iter())
+        .fold(None, |acc, &(start, t)| match acc {
+            None => Some((start, start + t)),
+            Some((lo, hi)) => Some((lo.min(start), hi.max(start + t))),
+        })
+}
+
+/// 罫線を描画
+///
+/// 各方向の線は中央の交差部分まで伸ばす
+/// 二重線の内側・外側の線は、隣り合う方向の線があればその線まで、
+/// なければ交差する線の外側の端まで伸ばす（╔ の角や ╬ の隙間になる）
+fn lines(arms: [u8; 4], width: f32, height: f32, out: &mut Vec<BoxRect>) {
+    let t = thickness(width);
+    let [up, right, down, left] = arms;
+    // 縦線は x 方向の帯、横線は y 方向の帯
+    let up_b = bands(up, width, t);
+    let down_b = bands(down, width, t);
+    let left_b = bands(left, height, t);
+    let right_b = bands(right, height, t);
+    let vertical = span(&[&up_b, &down_b]);
+    let horizontal = span(&[&left_b, &right_b]);
+    let mid_x = (width / 2.0).floor();
+    let mid_y = (height / 2.0).floor();
+
+    // 右向きの線: 開始位置（上側の線は上の線、下側の線は下の線に接続）
+    for (i, &(y, th)) in right_b.iter().enumerate() {
+        let mut x = f32::MAX;
+        if i == 0 {
+            x = x.min(match up_b.last() {
+                Some(&(bx, _)) => bx,
+                None => vertical.map_or(mid_x, |(lo, _)| lo),
+            });
+        }
+        if i == right_b.len() - 1 {
+            x = x.min(match down_b.last() {
+                Some(&(bx, _)) => bx,
+                None => vertical.map_or(mid_x, |(lo, _)| lo),
+            });
+        }
+        if right != DOUBLE {
+            x = x.min(mid_x);
+        }
+        out.push(BoxRect::new(x, y, width - x, th));
+    }
+
+    // 左向きの線: 終了位置
+    for (i, &(y, th)) in left_b.iter().enumerate() {
+        let mut x = f32::MIN;
+        if i == 0 {
+            x = x.max(match up_b.first() {
+                Some(&(bx, bt)) => bx + bt,
+                None => vertical.map_or(mid_x, |(_, hi)| hi),
+            });
+        }
+        if i == left_b.len() - 1 {
+            x = x.max(match down_b.first() {
+                Some(&(bx, bt)) => bx + bt,
+                None => vertical.map_or(mid_x, |(_, hi)| hi),
+            });
+        }
+        if left != DOUBLE {
+            x = x.max(mid_x);
+        }
+        out.push(BoxRect::new(0.0, y, x, th));
+    }
+
+    // 上向きの線: 終了位置（左側の線は左の線、右側の線は右の線に接続）
+    for (i, &(x, tw)) in up_b.iter().enumerate() {
+        let mut y = f32::MIN;
+        if i == 0 {
+            y = y.max(match left_b.first() {
+                Some(&(by, bt)) => by + bt,
+                None => horizontal.map_or(mid_y, |(_, hi)| hi),
+            });
+        }
+        if i == up_b.len() - 1 {
+            y = y.max(match right_b.first() {
+                Some(&(by, bt)) => by + bt,
+                None => horizontal.map_or(mid_y, |(_, hi)| hi),
+            });
+        }
+        if up != DOUBLE {
+            y = y.max(mid_y);
+        }
+        out.push(BoxRect::new(x, 0.0, tw, y));
+    }
+
+    // 下向きの線: 開始位置
+    for (i, &(x, tw)) in down_b.iter().enumerate() {
+        let mut y = f32::MAX;
+        if i == 0 {
+            y = y.min(match left_b.last() {
+                Some(&(by, _)) => by,
+                None => horizontal.map_or(mid_y, |(lo, _)| lo),
+            });
+        }
+        if i == down_b.len() - 1 {
+            y = y.min(match right_b.last() {
+                Some(&(by, _)) => by,
+                None => horizontal.map_or(mid_y, |(lo, _)| lo),
+            });
+        }
+        if down != DOUBLE {
+            y = y.min(mid_y);
+        }
+        out.push(BoxRect::new(x, y, tw, height - y));
+    }
+}
+
+/// 破線を描画（各区間の前半に線を引く）
+fn dashes(arms: [u8; 4], count: usize, width: f32, height: f32, out: &mut Vec<BoxRect>) {
+    let t = thickness(width);
+    let [up, right, ..] = arms;
+    if right != NONE {
+        let segment = width / count as f32;
+        for &(y, th) in &bands(right, height, t) {
+            for i in 0..count {
+                let x = (i as f32 * segment).floor();
+                out.push(BoxRect::new(x, y, (segment / 2.0).ceil(), th));
+            }
+        }
+    } else {
+        let segment = height / count as f32;
+        for &(x, tw) in &bands(up, width, t) {
+            for i in 0..count {
+                let y = (i as f32 * segment).floor();
+                out.push(BoxRect::new(x, y, tw, (segment / 2.0).ceil()));
+            }
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ブロック要素
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// ブロック要素（U+2580〜U+259F）を描画
+fn blocks(code: u32, width: f32, height: f32, out: &mut Vec<BoxRect>) {
+    let eighth_y = |n: u32| (height * n as f32 / 8.0).round();
+    let eighth_x = |n: u32| (width * n as f32 / 8.0).round();
+    let half_x = (width / 2.0).round();
+    let half_y = (height / 2.0).round();
+
+    // 四分割ブロック [左上, 右上, 左下, 右下]
+    let quadrants = |q: [bool; 4], out: &mut Vec<BoxRect>| {
+        let cells = [
+            BoxRect::new(0.0, 0.0, half_x, half_y),
+            BoxRect::new(half_x, 0.0, width - half_x, half_y),
+            BoxRect::new(0.0, half_y, half_x, height - half_y),
+            BoxRect::new(half_x, half_y, width - half_x, height - half_y),
+        ];
+        out.extend(cells.iter().zip(q).filter(|(_, on)| *on).map(|(r, _)| *r));
+    };
+
+    match code {
+        // ▀ 上半分
+        0x2580 => out.push(BoxRect::new(0.0, 0.0, width, half_y)),
+        // ▁〜▇ 下から n/8、█ 全体
+        0x2581..=0x2588 => {
+            let y = height - eighth_y(code - 0x2580);
+            out.push(BoxRect::new(0.0, y, width, height - y));
+        }
+        // ▉〜▏ 左から n/8
+        0x2589..=0x258F => out.push(BoxRect::new(0.0, 0.0, eighth_x(0x2590 - code), height)),
+        // ▐ 右半分
+        0x2590 => out.push(BoxRect::new(half_x, 0.0, width - half_x, height)),
+        // ░ ▒ ▓ 網掛け
+        0x2591..=0x2593 => out.push(BoxRect {
+            alpha: (code - 0x2590) as f32 / 4.0,
+            ..BoxRect::new(0.0, 0.0, width, height)
+        }),
+        // ▔ 上 1/8
+        0x2594 => out.push(BoxRect::new(0.0, 0.0, width, eighth_y(1))),
+        // ▕ 右 1/8
+        0x2595 => {
+            let x = width - eighth_x(1);
+            out.push(BoxRect::new(x, 0.0, width - x, height));
+        }
+        0x2596 => quadrants([false, false, true, false], out), // ▖
+        0x2597 => quadrants([false, false, false, true], out), // ▗
+        0x2598 => quadrants([true, false, false, false], out), // ▘
+        0x2599 => quadrants([true, false, true, true], out),   // ▙
+        0x259A => quadrants([true, false, false, true], out),  // ▚
+        0x259B => quadrants([true, true, true, false], out),   // ▛
+        0x259C => quadrants([true, true, false, true], out),   // ▜
+        0x259D => quadrants([false, true, false, false], out), // ▝
+        0x259E => quadrants([false, true, true, false], out),  // ▞
+        _ => quadrants([false, true, true, true], out),        // ▟
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Powerline
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Powerline の三角形（U+E0B0〜U+E0B3）を1ピクセル行ずつ描画
+fn powerline(code: u32, width: f32, height: f32, out: &mut Vec<BoxRect>) {
+    let t = thickness(width);
+    let rows = height.ceil() as usize;
+    for row in 0..rows {
+        let y = row as f32;
+        let h = (height - y).min(1.0);
+        // 行の中心での三角形の幅（上下端で0、中央で最大）
+        let center = (y + h / 2.0) / height;
+        let reach = (width * (1.0 - (center * 2.0 - 1.0).abs())).round();
+        match code {
+            //  右向き（塗りつぶし）
+            0xE0B0 => out.push(BoxRect::new(0.0, y, reach, h)),
+            //  右向き（線）
+            0xE0B1 => {
+                let x = (reach - t).max(0.0);
+                out.push(BoxRect::new(x, y, reach - x, h));
+            }
+            //  左向き（塗りつぶし）
+            0xE0B2 => out.push(BoxRect::new(width - reach, y, reach, h)),
+            //  左向き（線）
+            _ => {
+                let x = width - reach;
+                out.push(BoxRect::new(x, y, (width - x).min(t), h));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 矩形を塗ったビットマップ（セル外は無視）
+    fn raster(c: char, width: usize, height: usize) -> Vec<Vec<bool>> {
+        let mut rects = Vec::new();
+        assert!(draw(c, width as f32, height as f32, &mut rects));
+        let mut bitmap = vec![vec![false; width]; height];
+        for r in rects {
+            let (x0, x1) = (r.x as usize, (r.x + r.width) as usize);
+            for line in &mut bitmap[r.y as usize..(r.y + r.height) as usize] {
+                line[x0..x1].fill(true);
+            }
+        }
+        bitmap
+    }
+
+    #[test]
+    fn test_lines_reach_cell_edges() {
+        // 横線・縦線はセルの端から端まで途切れない
+        let h = raster('─', 12, 24);
+        let row = h.iter().position(|r| r[0]).unwrap();
+        assert!(h[row].iter().all(|&p| p));
+
+        let v = raster('│', 12, 24);
+        let col = v[0].iter().position(|&p| p).unwrap();
+        assert!(v.iter().all(|r| r[col]));
+
+        // ┌ は右端と下端に届き、左端と上端には届かない
+        let corner = raster('┌', 12, 24);
+        assert!(corner[row][11] && corner[23][col]);
+        assert!(!corner[row][0] && !corner[0][col]);
+    }
+
+    #[test]
+    fn test_double_corner() {
+        // ╔ の外側の線は角でつながり、内側の線は角の内側から始まる
+        let bitmap = raster('╔', 12, 24);
+        let cols: Vec<usize> = (0..12).filter(|&x| bitmap[23][x]).collect();
+        let rows: Vec<usize> = (0..24).filter(|&y| bitmap[y][11]).collect();
+        assert_eq!((cols.len(), rows.len()), (2, 2));
+        let (outer_col, inner_col) = (cols[0], cols[1]);
+        let (outer_row, inner_row) = (rows[0], rows[1]);
+
+        assert!(bitmap[outer_row][outer_col]);
+        assert!(!bitmap[outer_row][outer_col - 1] && !bitmap[outer_row - 1][outer_col]);
+        assert!(bitmap[inner_row][inner_col]);
+        assert!(!bitmap[inner_row - 1][inner_col] && !bitmap[inner_row][inner_col - 1]);
+    }
+
+    #[test]
+    fn test_blocks() {
+        let full = raster('█', 10, 20);
+        assert!(full.iter().all(|r| r.iter().all(|&p| p)));
+
+        let lower = raster('▄', 10, 20);
+        assert!(!lower[9][0] && lower[10][0] && lower[19][9]);
+
+        let mut rects = Vec::new();
+        draw('▒', 10.0, 20.0, &mut rects);
+        assert_eq!(rects[0].alpha, 0.5);
+
+        // 斜線や通常の文字はフォントで描画
+        assert!(!draw('╱', 10.0, 20.0, &mut rects));
+        assert!(!draw('a', 10.0, 20.0, &mut rects));
+    }
+
+    #[test]
+    fn test_powerline_triangle() {
+        let bitmap = raster('\u{E0B0}', 10, 20);
+        // 中央の行はセルの右端まで、上下端はほぼ空
+        assert!(bitmap[10][9]);
+        assert!(!bitmap[0][5]);
+        assert!(bitmap.iter().all(|r| r[0] || !r[1]));
+    }
+}
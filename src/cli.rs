@@ -0,0 +1,325 @@
+//! コマンドライン引数
+//!
+//! ほかのツールやデスクトップのショートカットから起動されるターミナルとして、
+//! 最初のウィンドウで実行するコマンド・作業ディレクトリ・タイトルなどを受け取る
+//!
+//! ```text
+//! umiterm -e htop
+//! umiterm --working-directory ~/src --title build -e cargo watch
+//! umiterm --play session.cast
+//! umiterm --ssh prod
+//! umiterm --container web
+//! umiterm cli list-panes
+//! umiterm shell-integration install
+//! ```
+
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::config;
+use crate::editor::shell_escape;
+use crate::pty::PaneCommand;
+use crate::ssh;
+
+/// 起動時の引数
+#[derive(Debug, Default, Parser)]
+#[command(name = "umiterm", version, about = "水色テーマの高速ターミナルエミュレータ")]
+pub struct Args {
+    /// 最初のウィンドウで実行するコマンド（以降の引数はすべてコマンドの引数）
+    #[arg(
+        short = 'e',
+        long = "command",
+        value_name = "COMMAND",
+        num_args = 1..,
+        trailing_var_arg = true,
+        allow_hyphen_values = true
+    )]
+    pub command: Vec<String>,
+
+    /// 最初のウィンドウのシェル（またはコマンド）の作業ディレクトリ
+    #[arg(long, value_name = "DIR")]
+    pub working_directory: Option<PathBuf>,
+
+    /// 録画した asciicast のファイルを最初のウィンドウで再生する
+    #[arg(long, value_name = "FILE", conflicts_with = "command")]
+    pub play: Option<PathBuf>,
+
+    /// SSH の接続先（ssh を書いたプロファイルの名前か、ssh に渡すホスト）を最初のウィンドウで開く
+    #[arg(long, value_name = "HOST", conflicts_with_all = ["command", "play"])]
+    pub ssh: Option<String>,
+
+    /// 動いているコンテナ（名前か ID）のシェルを最初のウィンドウで開く（docker exec -it）
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["command", "play", "ssh"])]
+    pub container: Option<String>,
+
+    /// ウィンドウのタイトル
+    #[arg(long)]
+    pub title: Option<String>,
+
+    /// ウィンドウのクラス（X11 の WM_CLASS、Wayland の app_id）
+    #[arg(long, visible_alias = "app-id", value_name = "CLASS")]
+    pub class: Option<String>,
+
+    /// 設定ファイル（既定は ~/.config/umiterm/umiterm.toml）
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// すべての項目を説明した設定ファイルを書き出して終了する（省略時は標準出力）
+    #[arg(long, value_name = "PATH", num_args = 0..=1)]
+    pub generate_config: Option<Option<PathBuf>>,
+
+    /// chrome-trace 形式のプロファイルを書き出す（省略時はタイムスタンプ付きのファイル名）
+    #[arg(long, value_name = "PATH", num_args = 0..=1)]
+    pub profile: Option<Option<PathBuf>>,
+
+    /// 最初のペインの PTY の出力を参照テストのフィクスチャとして DIR に記録する
+    /// （シェルが終了するかペインを閉じたときに、その時点のグリッドも書き出す）
+    #[arg(long, value_name = "DIR")]
+    pub ref_test: Option<PathBuf>,
+
+    /// 設定の single_instance にかかわらず、起動中の UmiTerm に任せず新しいプロセスで起動する
+    #[arg(long)]
+    pub new_instance: bool,
+
+    /// セッションデーモンとして起動する（GUI が内部で使う）
+    #[arg(long, hide = true)]
+    pub daemon: bool,
+
+    #[command(subcommand)]
+    pub subcommand: Option<Command>,
+}
+
+/// サブコマンド
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum Command {
+    /// 起動中の UmiTerm を操作する
+    Cli {
+        #[command(subcommand)]
+        action: CliAction,
+    },
+    /// シェル統合（作業ディレクトリの OSC 7、プロンプトの印の OSC 133）
+    ShellIntegration {
+        #[command(subcommand)]
+        action: ShellIntegrationAction,
+    },
+}
+
+/// `umiterm shell-integration` の操作
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum ShellIntegrationAction {
+    /// スクリプトを書き出し、シェルの設定ファイル（~/.bashrc・~/.zshrc・fish の conf.d）から読み込むようにする
+    Install {
+        /// シェル（bash / zsh / fish、省略すると $SHELL）
+        shell: Option<String>,
+    },
+}
+
+/// `umiterm cli` の操作（`--pane-id` を省略するとペインの中では `$UMITERM_PANE_ID`、なければフォーカス中のペイン）
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum CliAction {
+    /// ペインの一覧を出す
+    ListPanes {
+        /// JSON で出す
+        #[arg(long)]
+        json: bool,
+    },
+    /// ペインを分割して、新しいペインのIDを出す
+    SplitPane {
+        /// 分割するペイン
+        #[arg(long)]
+        pane_id: Option<u64>,
+        /// 左右に分割する（既定は上下）
+        #[arg(long)]
+        horizontal: bool,
+        /// 新しいペインの作業ディレクトリ
+        #[arg(long, value_name = "DIR")]
+        cwd: Option<PathBuf>,
+        /// 新しいペインで実行するコマンド（`--` のあと、省略するとシェル）
+        #[arg(last = true, value_name = "COMMAND")]
+        command: Vec<String>,
+    },
+    /// ペインに文字列を送る（省略すると標準入力から）
+    SendText {
+        /// 送り先のペイン
+        #[arg(long)]
+        pane_id: Option<u64>,
+        /// ブラケットペーストにせず、そのまま送る
+        #[arg(long)]
+        no_paste: bool,
+        /// 送る文字列
+        text: Option<String>,
+    },
+    /// ペインにフォーカスする（タブを切り替え、ウィンドウを前に出す）
+    Activate {
+        /// フォーカスするペイン
+        #[arg(long)]
+        pane_id: Option<u64>,
+    },
+    /// ペインの画面の文字を出す
+    GetText {
+        /// 読むペイン
+        #[arg(long)]
+        pane_id: Option<u64>,
+        /// スクロールバックも含める
+        #[arg(long)]
+        scrollback: bool,
+    },
+    /// イベント（ペインの作成・終了、タイトルの変化、ベル、コマンドの完了）を1行1つの JSON で出し続ける
+    Subscribe,
+}
+
+impl Args {
+    /// 最初のウィンドウで起動するコマンド（-e も --play も --ssh も --container もなければログインシェル）
+    pub fn launch(&self) -> PaneCommand {
+        if let Some(path) = &self.play {
+            return PaneCommand::Play(std::env::current_dir().map(|cwd| cwd.join(path)).unwrap_or_else(|_| path.clone()));
+        }
+        if let Some(host) = &self.ssh {
+            return ssh::launch(&config::current(), host);
+        }
+        if let Some(name) = &self.container {
+            return PaneCommand::Container(name.clone());
+        }
+        self.command_line().map_or(PaneCommand::Shell, PaneCommand::Run)
+    }
+
+    /// -e のコマンドをシェルのコマンドラインにしたもの
+    pub fn command_line(&self) -> Option<String> {
+        if self.command.is_empty() {
+            return None;
+        }
+        let words: Vec<String> = self.command.iter().map(|word| shell_escape(word)).collect();
+        Some(words.join(" "))
+    }
+
+    /// 起動中の UmiTerm に新しいウィンドウを任せられるか
+    ///
+    /// プロセス全体に効く指定（タイトル・クラス・設定ファイル・プロファイリング）か再生・参照テストの記録・SSH・コンテナがあれば自分で起動する
+    pub fn single_instance(&self) -> bool {
+        !self.new_instance
+            && self.play.is_none()
+            && self.ref_test.is_none()
+            && self.ssh.is_none()
+            && self.container.is_none()
+            && self.title.is_none()
+            && self.class.is_none()
+            && self.config.is_none()
+            && self.profile.is_none()
+    }
+
+    /// 最初のウィンドウの作業ディレクトリ（相対パスは起動したディレクトリから）
+    pub fn cwd(&self) -> Option<PathBuf> {
+        let dir = self.working_directory.as_ref()?;
+        Some(std::env::current_dir().map(|cwd| cwd.join(dir)).unwrap_or_else(|_| dir.clone()))
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Args {
+        Args::try_parse_from(std::iter::once("umiterm").chain(args.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn test_command() {
+        assert_eq!(parse(&[]).launch(), PaneCommand::Shell);
+
+        // -e 以降はすべてコマンドの引数（- で始まっていても）
+        let args = parse(&["--title", "logs", "-e", "tail", "-f", "/var/log/my app.log"]);
+        assert_eq!(args.title.as_deref(), Some("logs"));
+        assert_eq!(args.launch(), PaneCommand::Run("tail -f '/var/log/my app.log'".into()));
+
+        assert_eq!(parse(&["--app-id", "dropdown"]).class.as_deref(), Some("dropdown"));
+        assert_eq!(parse(&["--working-directory", "/tmp"]).cwd(), Some(PathBuf::from("/tmp")));
+        assert!(Args::try_parse_from(["umiterm", "-e"]).is_err());
+
+        // 再生するファイルは起動したディレクトリから
+        assert_eq!(parse(&["--play", "/tmp/a.cast"]).launch(), PaneCommand::Play("/tmp/a.cast".into()));
+        assert!(!parse(&["--play", "a.cast"]).single_instance());
+        assert!(Args::try_parse_from(["umiterm", "--play", "a.cast", "-e", "vim"]).is_err());
+
+        // プロファイルでなければ ssh にホストを渡す
+        assert_eq!(parse(&["--ssh", "deploy@web1"]).launch(), PaneCommand::Run("ssh -- deploy@web1".into()));
+        assert!(!parse(&["--ssh", "web1"]).single_instance());
+        assert!(Args::try_parse_from(["umiterm", "--ssh", "web1", "-e", "vim"]).is_err());
+        assert!(Args::try_parse_from(["umiterm", "--ssh", "web1", "--play", "a.cast"]).is_err());
+
+        assert_eq!(parse(&["--container", "web"]).launch(), PaneCommand::Container("web".into()));
+        assert!(!parse(&["--container", "web"]).single_instance());
+        assert!(Args::try_parse_from(["umiterm", "--container", "web", "--ssh", "web1"]).is_err());
+    }
+
+    #[test]
+    fn test_profile() {
+        assert_eq!(parse(&[]).profile, None);
+        assert_eq!(parse(&["--profile", "out.json"]).profile, Some(Some(PathBuf::from("out.json"))));
+        assert_eq!(parse(&["--profile=a.json"]).profile, Some(Some(PathBuf::from("a.json"))));
+
+        // パス省略時は既定のファイル名（次の引数はオプションとして読む）
+        let args = parse(&["--profile", "-e", "vim"]);
+        assert_eq!(args.profile, Some(None));
+        assert_eq!(args.launch(), PaneCommand::Run("vim".into()));
+    }
+
+    #[test]
+    fn test_cli_subcommand() {
+        assert_eq!(parse(&[]).subcommand, None);
+        assert_eq!(
+            parse(&["cli", "list-panes", "--json"]).subcommand,
+            Some(Command::Cli { action: CliAction::ListPanes { json: true } })
+        );
+        assert_eq!(
+            parse(&["cli", "split-pane", "--horizontal", "--", "tail", "-f", "log"]).subcommand,
+            Some(Command::Cli {
+                action: CliAction::SplitPane {
+                    pane_id: None,
+                    horizontal: true,
+                    cwd: None,
+                    command: vec!["tail".into(), "-f".into(), "log".into()],
+                },
+            })
+        );
+        assert_eq!(
+            parse(&["cli", "send-text", "--pane-id", "3", "ls\n"]).subcommand,
+            Some(Command::Cli { action: CliAction::SendText { pane_id: Some(3), no_paste: false, text: Some("ls\n".into()) } })
+        );
+        // -e のあとの cli はコマンドの引数
+        assert_eq!(parse(&["-e", "cli", "list-panes"]).subcommand, None);
+
+        assert_eq!(
+            parse(&["shell-integration", "install", "fish"]).subcommand,
+            Some(Command::ShellIntegration { action: ShellIntegrationAction::Install { shell: Some("fish".into()) } })
+        );
+    }
+
+    #[test]
+    fn test_generate_config() {
+        assert_eq!(parse(&[]).generate_config, None);
+        assert_eq!(parse(&["--generate-config"]).generate_config, Some(None));
+        assert_eq!(
+            parse(&["--generate-config", "umiterm.toml"]).generate_config,
+            Some(Some(PathBuf::from("umiterm.toml")))
+        );
+    }
+
+    #[test]
+    fn test_single_instance() {
+        let args = parse(&["--working-directory", "/tmp", "-e", "htop"]);
+        assert!(args.single_instance());
+        assert_eq!(args.command_line().as_deref(), Some("htop"));
+        assert_eq!(parse(&[]).command_line(), None);
+
+        // プロセス全体に効く指定があれば自分で起動する
+        assert!(!parse(&["--new-instance"]).single_instance());
+        assert!(!parse(&["--class", "dropdown"]).single_instance());
+        assert!(!parse(&["--ref-test", "tests/ref/vim"]).single_instance());
+        assert!(!parse(&["--config", "other.toml"]).single_instance());
+    }
+}
@@ -0,0 +1,228 @@
+//! クリップボード
+//!
+//! arboard の薄いラッパー。クリップボードが使えない環境（ヘッドレスなど）では
+//! ログを残して何もしない
+//!
+//! X11 / Wayland では選択したテキストをプライマリセレクションにも置き、
+//! 他のアプリで中クリックすると貼り付けられるようにする
+//!
+//! UmiTerm の中でコピーしたテキストは履歴にも残し、Cmd+Shift+V で前にコピーしたものを選んでペーストできる
+
+use std::collections::VecDeque;
+
+use arboard::Clipboard;
+
+/// 履歴に残すコピーの数
+const HISTORY_LIMIT: usize = 50;
+
+/// UmiTerm の中でコピーしたテキストの履歴
+static HISTORY: parking_lot::Mutex<History> = parking_lot::Mutex::new(History::new(HISTORY_LIMIT));
+
+/// コピーしたテキストの履歴（古い順、同じテキストは新しい方だけ残す）
+struct History {
+    entries: VecDeque<String>,
+    limit: usize,
+}
+
+impl History {
+    const fn new(limit: usize) -> Self {
+        Self { entries: VecDeque::new(), limit }
+    }
+
+    /// 新しいコピーを足す（空白だけのテキストは残さない）
+    fn push(&mut self, text: &str) {
+        if text.trim().is_empty() {
+            return;
+        }
+        self.entries.retain(|entry| entry != text);
+        self.entries.push_back(text.to_string());
+        while self.entries.len() > self.limit {
+            self.entries.pop_front();
+        }
+    }
+}
+
+/// コピーしたテキストの履歴（古い順）
+pub fn history() -> Vec<String> {
+    HISTORY.lock().entries.iter().cloned().collect()
+}
+
+/// 履歴の候補に見せる1行（改行は ⏎、ほかの制御文字は · にして、長いものは切る）
+pub fn preview(text: &str) -> String {
+    const WIDTH: usize = 200;
+    let text = text.trim_end_matches(['\r', '\n']).replace("\r\n", "\n");
+    let mut line: String = text
+        .chars()
+        .map(|c| match c {
+            '\n' | '\r' => '⏎',
+            '\t' => ' ',
+            c if c.is_control() => '·',
+            c => c,
+        })
+        .take(WIDTH)
+        .collect();
+    if text.chars().count() > WIDTH {
+        line.push('…');
+    }
+    line
+}
+
+/// テキストをクリップボードにコピー
+/// 戻り値: コピーできたか
+pub fn copy(text: &str) -> bool {
+    let result = Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text));
+    match result {
+        Ok(()) => {
+            log::info!("Copied: {} chars", text.chars().count());
+            HISTORY.lock().push(text);
+            true
+        }
+        Err(e) => {
+            log::warn!("クリップボードへのコピーに失敗: {}", e);
+            false
+        }
+    }
+}
+
+/// テキストを色付きの HTML（macOS では RTF も）と一緒にコピー
+///
+/// 貼り付け先が色付きの形式に対応していればそちら、していなければテキストを使う
+/// 戻り値: コピーできたか
+#[cfg(target_os = "macos")]
+pub fn copy_rich(text: &str, html: &str, rtf: &str) -> bool {
+    // arboard は RTF を扱えないので、JXA から NSPasteboard に3つの形式を並べて書く
+    const SCRIPT: &str = "ObjC.import('AppKit');\
+        function run(argv) {\
+            const pasteboard = $.NSPasteboard.generalPasteboard;\
+            pasteboard.clearContents;\
+            pasteboard.setStringForType($(argv[0]), $.NSPasteboardTypeString);\
+            pasteboard.setStringForType($(argv[1]), $.NSPasteboardTypeHTML);\
+            pasteboard.setStringForType($(argv[2]), $.NSPasteboardTypeRTF);\
+        }";
+    let status = std::process::Command::new("osascript")
+        .args(["-l", "JavaScript", "-e", SCRIPT, text, html, rtf])
+        .stdout(std::process::Stdio::null())
+        .status();
+    match status {
+        Ok(status) if status.success() => {
+            log::info!("Copied with colors: {} chars", text.chars().count());
+            HISTORY.lock().push(text);
+            true
+        }
+        result => {
+            log::warn!("RTF のコピーに失敗したので HTML だけコピーします: {:?}", result);
+            copy_html(text, html)
+        }
+    }
+}
+
+/// テキストを色付きの HTML と一緒にコピー
+///
+/// 貼り付け先が HTML に対応していればそちら、していなければテキストを使う
+/// 戻り値: コピーできたか
+#[cfg(not(target_os = "macos"))]
+pub fn copy_rich(text: &str, html: &str, _rtf: &str) -> bool {
+    copy_html(text, html)
+}
+
+/// テキストと HTML をコピー
+fn copy_html(text: &str, html: &str) -> bool {
+    let result = Clipboard::new().and_then(|mut clipboard| clipboard.set_html(html, Some(text)));
+    match result {
+        Ok(()) => {
+            log::info!("Copied with colors: {} chars", text.chars().count());
+            HISTORY.lock().push(text);
+            true
+        }
+        Err(e) => {
+            log::warn!("クリップボードへのコピーに失敗: {}", e);
+            false
+        }
+    }
+}
+
+/// クリップボードのテキスト（テキストでなければ None）
+pub fn paste() -> Option<String> {
+    match Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+        Ok(text) => Some(text),
+        Err(e) => {
+            log::warn!("クリップボードの読み取りに失敗: {}", e);
+            None
+        }
+    }
+}
+
+/// プライマリセレクションを持ち続ける arboard
+///
+/// X11 では最後の `Clipboard` を捨てると置いたテキストも消える（クリップボードと違って
+/// プライマリセレクションを引き取るクリップボードマネージャーはまずない）ので、終了するまで持っておく
+#[cfg(all(unix, not(target_os = "macos")))]
+static PRIMARY: parking_lot::Mutex<Option<Clipboard>> = parking_lot::Mutex::new(None);
+
+/// プライマリセレクション（X11 / Wayland で選択中のテキスト）
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn primary() -> Option<String> {
+    use arboard::{GetExtLinux, LinuxClipboardKind};
+    let mut clipboard = Clipboard::new().ok()?;
+    clipboard.get().clipboard(LinuxClipboardKind::Primary).text().ok()
+}
+
+/// 選択したテキストをプライマリセレクションに置く
+/// 戻り値: 置けたか
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn set_primary(text: &str) -> bool {
+    use arboard::{LinuxClipboardKind, SetExtLinux};
+    let mut primary = PRIMARY.lock();
+    if primary.is_none() {
+        *primary = Clipboard::new().map_err(|e| log::warn!("プライマリセレクションを使えません: {}", e)).ok();
+    }
+    let Some(clipboard) = primary.as_mut() else { return false };
+    match clipboard.set().clipboard(LinuxClipboardKind::Primary).text(text) {
+        Ok(()) => true,
+        Err(e) => {
+            log::warn!("プライマリセレクションへのコピーに失敗: {}", e);
+            false
+        }
+    }
+}
+
+/// プライマリセレクションに置く（この OS にはないので何もしない）
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub fn set_primary(_text: &str) -> bool {
+    false
+}
+
+/// プライマリセレクション（この OS にはないので常に None）
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub fn primary() -> Option<String> {
+    None
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history() {
+        let mut history = History::new(3);
+        for text in ["a", "b", "  ", "c", "a"] {
+            history.push(text);
+        }
+        // 同じテキストは新しい方に移り、空白だけのものは残さない
+        assert_eq!(history.entries, ["b", "c", "a"]);
+
+        history.push("d");
+        assert_eq!(history.entries, ["c", "a", "d"]);
+    }
+
+    #[test]
+    fn test_preview() {
+        assert_eq!(preview("cargo build\r\ncargo test\n"), "cargo build⏎cargo test");
+        assert_eq!(preview("a\tb\x1b"), "a b·");
+        assert_eq!(preview(&"x".repeat(201)), format!("{}…", "x".repeat(200)));
+    }
+}
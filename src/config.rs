@@ -0,0 +1,1327 @@
+//! 設定ファイル
+//!
+//! `~/.config/umiterm/umiterm.toml`（`$XDG_CONFIG_HOME` があればその下）から、フォント・色・
+//! キー割り当て・スクロールバック・シェルなどを読み込む
+//! ファイルを監視しており、保存すると読み直して開いているウィンドウに反映する
+//! （書き間違いがあれば警告して前の設定のまま）
+//!
+//! これまでの環境変数 `UMITERM_*` もそのまま使え、設定ファイルより優先する
+//!
+//! ```toml
+//! [font]
+//! path = "/Library/Fonts/JetBrainsMono-Regular.ttf"
+//! size = 18
+//!
+//! [colors]
+//! foreground = "#50dcc8"
+//! background = "#0a0e14"
+//!
+//! [shell]
+//! program = "/bin/zsh"
+//! login = true
+//!
+//! [[keybinds]]
+//! key = "cmd+shift+k"
+//! action = "split-vertical"
+//!
+//! # 動いている OS のときだけ上の設定に重ねる（[macos] / [linux] / [windows]）
+//! [linux.font]
+//! size = 14
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use notify::event::EventKind;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::RwLock;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+
+use crate::explorer::{ExplorerFollow, ExplorerLayout};
+use crate::grid::{Color, Palette};
+use crate::hints;
+use crate::keybinds::{self, BindAction, KeyBinding};
+use crate::mousebinds::{self, MouseBinding};
+use crate::notification;
+use crate::palette::{self, PaletteEntry};
+use crate::pane::{ExitAction, LayoutPreset, PaneTitleMode};
+use crate::pipe::{self, Pipe};
+use crate::ssh;
+use crate::status;
+use crate::terminal::{CursorShape, CursorStyle, UnfocusedCursor};
+use crate::theme::{self, Appearance};
+use crate::triggers;
+use crate::{Decorations, DropFolderAction, FullscreenMode, StartupMode};
+
+/// 続けて押すキー割り当てで、続きのキーを待つ時間の既定値（ミリ秒）
+const DEFAULT_LEADER_TIMEOUT_MS: u64 = 1500;
+
+/// OS ごとの設定のセクション
+const PLATFORMS: &[&str] = &["macos", "linux", "windows"];
+
+/// 今の OS のセクション（macOS と Windows 以外の Unix 系は linux）
+const PLATFORM: &str = if cfg!(target_os = "macos") {
+    "macos"
+} else if cfg!(windows) {
+    "windows"
+} else {
+    "linux"
+};
+
+/// `--generate-config` で書き出す、すべての項目を説明した設定ファイル（すべてコメントなので読むと既定の設定）
+pub const DEFAULT_CONFIG: &str = include_str!("default_config.toml");
+
+/// 読み込んだ設定（最初に使うときに読み込む）
+static CURRENT: RwLock<Option<Arc<Config>>> = RwLock::new(None);
+
+/// 最後に設定ファイルを読めなかったときのエラー（読めたら消す）
+static LOAD_ERROR: RwLock<Option<String>> = RwLock::new(None);
+
+/// コマンドラインで指定した設定ファイル（`--config`）
+static PATH: OnceLock<PathBuf> = OnceLock::new();
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 名前で書く値
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 名前で書く設定の値（`startup_mode = "fullscreen"` など）
+pub trait Named: Sized + Copy + PartialEq + 'static {
+    /// 書ける名前と値
+    const NAMES: &'static [(&'static str, Self)];
+
+    /// 名前から取得
+    fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES.iter().find(|(n, _)| *n == name).map(|(_, value)| *value)
+    }
+
+    /// 値の名前
+    fn name(self) -> &'static str {
+        Self::NAMES.iter().find(|(_, value)| *value == self).map_or("", |(name, _)| name)
+    }
+
+    /// 書ける名前の一覧（エラーの表示用）
+    fn names() -> String {
+        Self::NAMES.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(" / ")
+    }
+}
+
+/// 名前で書く項目を読む（使えない名前は書き間違い）
+fn named<'de, D: Deserializer<'de>, T: Named>(deserializer: D) -> std::result::Result<Option<T>, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    T::from_name(name.trim())
+        .map(Some)
+        .ok_or_else(|| serde::de::Error::custom(format!("{:?} は使えません（{}）", name, T::names())))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 設定の内容
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 設定ファイルの内容（書かなかった項目は既定値）
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub font: FontConfig,
+    pub colors: ColorsConfig,
+    pub cursor: CursorConfig,
+    pub keyboard: KeyboardConfig,
+    pub banner: BannerConfig,
+    pub keybinds: Vec<KeybindConfig>,
+    pub mousebinds: Vec<MousebindConfig>,
+    pub commands: Vec<CommandConfig>,
+    pub pipes: Vec<PipeConfig>,
+    pub leader: LeaderConfig,
+    pub scrollback: ScrollbackConfig,
+    pub shell: ShellConfig,
+    pub window: WindowConfig,
+    pub terminal: TerminalConfig,
+    pub pane: PaneConfig,
+    pub explorer: ExplorerConfig,
+    pub editor: EditorConfig,
+    pub open: OpenConfig,
+    pub links: Vec<LinkConfig>,
+    pub hints: HintsConfig,
+    pub triggers: TriggersConfig,
+    pub ssh: SshConfig,
+    pub containers: ContainersConfig,
+    pub profiles: BTreeMap<String, ProfileConfig>,
+    /// `[colors]` から作ったパレット
+    #[serde(skip)]
+    pub palette: Palette,
+    /// `light_theme` から作ったパレット
+    #[serde(skip)]
+    pub light_palette: Option<Palette>,
+    /// `dark_theme` から作ったパレット
+    #[serde(skip)]
+    pub dark_palette: Option<Palette>,
+    /// `[[keybinds]]` から作ったキー割り当て
+    #[serde(skip)]
+    pub bindings: Vec<KeyBinding>,
+    /// `[[mousebinds]]` から作ったマウスの割り当て（既定の割り当ては含まない）
+    #[serde(skip)]
+    pub mouse_bindings: Vec<MouseBinding>,
+    /// `[[commands]]` から作ったコマンドパレットのコマンド
+    #[serde(skip)]
+    pub palette_commands: Vec<PaletteEntry>,
+    /// `[[pipes]]` から作ったパイプ
+    #[serde(skip)]
+    pub pipe_commands: Vec<Pipe>,
+    /// `[cursor]` から作ったカーソルの描き方
+    #[serde(skip)]
+    pub cursor_style: CursorStyle,
+    /// テーマを指定したプロファイルのパレット
+    #[serde(skip)]
+    pub profile_palettes: BTreeMap<String, Palette>,
+    /// 読んだ表（OS のセクションを重ねたもの、プラグインの `umiterm.config` が使う）
+    #[serde(skip)]
+    pub table: toml::Table,
+}
+
+/// `[font]`
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FontConfig {
+    /// フォントファイル（`UMITERM_FONT`、なければシステムのフォント）
+    pub path: Option<String>,
+    /// 大きさ（ピクセル）
+    pub size: Option<f32>,
+}
+
+/// `[colors]`（`#rrggbb` 形式、テーマファイルも同じ形式）
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorsConfig {
+    /// 元にするテーマ（組み込みのテーマか `themes/` のファイル名）
+    pub theme: Option<String>,
+    /// OS がライトの外観のときのテーマ
+    pub light_theme: Option<String>,
+    /// OS がダークの外観のときのテーマ
+    pub dark_theme: Option<String>,
+    pub foreground: Option<String>,
+    pub background: Option<String>,
+    pub cursor: Option<String>,
+    pub selection_foreground: Option<String>,
+    pub selection_background: Option<String>,
+    /// エクスプローラーやタブバーなどの強調色
+    pub accent: Option<String>,
+    /// ANSI 16色（標準8色、明るい8色の順）
+    pub ansi: Option<Vec<String>>,
+}
+
+/// `[[keybinds]]`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeybindConfig {
+    /// キーの組み合わせ（例: `cmd+shift+d`）
+    pub key: String,
+    /// 操作の名前（例: `split-vertical`、`none` で無効化）
+    pub action: String,
+    /// 開くときのプロファイル（`new-window` / `new-tab` / `split-*` のみ）
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// ペインに送る文字列（`send-text` のみ、`\u001b` でエスケープシーケンスも）、パイプの名前（`pipe` のみ）、保存先（`save-scrollback*` のみ）
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+/// `[[mousebinds]]`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MousebindConfig {
+    /// 修飾キーとボタン（例: `cmd+left`、`middle`）
+    pub button: String,
+    /// 操作の名前（例: `extend-selection`、キー割り当ての操作も、`none` で無効化）
+    pub action: String,
+}
+
+/// `[[commands]]`（コマンドパレットに足すコマンド）
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandConfig {
+    /// パレットに出す名前（例: `open dev layout`）
+    pub name: String,
+    /// 順に実行する操作
+    pub steps: Vec<CommandStepConfig>,
+}
+
+/// `[[commands]]` の `steps` の1つ（キー割り当てと同じ操作）
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandStepConfig {
+    /// 操作の名前（例: `split-vertical`、`send-text`）
+    pub action: String,
+    /// 開くときのプロファイル（`new-window` / `new-tab` / `split-*` のみ）
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// ペインに送る文字列（`send-text` のみ）、パイプの名前（`pipe` のみ）、保存先（`save-scrollback*` のみ）
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+/// `[[pipes]]`（画面・スクロールバックを渡す外部コマンド）
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipeConfig {
+    /// パレットや `pipe` の操作で使う名前（例: `pick a line`）
+    pub name: String,
+    /// シェルで実行するコマンド（例: `fzf`）
+    pub command: String,
+    /// 渡す文字（`screen` / `scrollback`、既定は `screen`）
+    #[serde(default)]
+    pub input: Option<String>,
+    /// 結果の扱い（`pane` でペインに表示、`insert` で元のペインに入力、既定は `pane`）
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
+/// `[profiles.<名前>]`（新しいウィンドウ・タブ・ペインを開くときに選ぶ）
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileConfig {
+    /// 起動するコマンド（ログインシェル経由、なければシェル）
+    pub command: Option<String>,
+    /// SSH の接続先（`user@host`、`ssh://user@host:port`、command の代わりに ssh で接続）
+    pub ssh: Option<String>,
+    /// ssh に渡すオプション（例: `["-A"]`）
+    pub ssh_args: Vec<String>,
+    /// WSL のディストリビューション（Windows のみ、`wsl.exe -d` で起動し、command はその中で実行する）
+    pub wsl: Option<String>,
+    /// ペインのタイトルの前に付ける名前（例: `PROD`）
+    pub title: Option<String>,
+    /// 作業ディレクトリ（`~/` はホーム、なければホーム、wsl では Linux のパス）
+    pub cwd: Option<String>,
+    /// テーマ（このプロファイルで開いたペインの色）
+    pub theme: Option<String>,
+    /// フォントの大きさ（このプロファイルで開いたウィンドウ）
+    pub font_size: Option<f32>,
+    /// 起動するコマンドに設定する環境変数
+    pub env: BTreeMap<String, String>,
+    /// スクロールバック（書いた項目は `[scrollback]` より優先）
+    pub scrollback: ScrollbackConfig,
+}
+
+/// `[leader]`（`ctrl+a %` のように続けて押すキー割り当て）
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LeaderConfig {
+    /// 続きのキーを待つ時間（ミリ秒）
+    pub timeout_ms: Option<u64>,
+    /// 続けて押せるキーの一覧を表示するか
+    pub which_key: Option<bool>,
+}
+
+impl LeaderConfig {
+    /// 続きのキーを待つ時間（既定は1.5秒）
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.unwrap_or(DEFAULT_LEADER_TIMEOUT_MS))
+    }
+
+    /// 続けて押せるキーの一覧を表示するか（既定は表示する）
+    pub fn which_key(&self) -> bool {
+        self.which_key.unwrap_or(true)
+    }
+}
+
+/// `[scrollback]`
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScrollbackConfig {
+    /// メモリ上限（MB、`UMITERM_SCROLLBACK_MB`）
+    pub max_mb: Option<u64>,
+    /// メモリに残す行数の上限（なければメモリ上限だけ）
+    pub lines: Option<usize>,
+    /// 上限を超えた行をディスクに書き出して残すか（無制限のスクロールバック）
+    pub spill: Option<bool>,
+}
+
+/// `[cursor]`（色は `[colors]` の `cursor`）
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CursorConfig {
+    /// アプリケーションが指定していないときの形状（block / underline / beam）
+    pub shape: Option<String>,
+    /// ビーム・下線・枠の太さ（セルの幅に対する割合）
+    pub thickness: Option<f32>,
+    /// フォーカスのないペインのカーソル（hollow / none）
+    pub unfocused: Option<String>,
+}
+
+impl CursorConfig {
+    /// カーソルの描き方
+    fn style(&self) -> Result<CursorStyle> {
+        let mut style = CursorStyle::default();
+        if let Some(name) = &self.shape {
+            style.shape = CursorShape::from_name(name).with_context(|| format!("cursor.shape が正しくありません: {:?}", name))?;
+        }
+        if let Some(thickness) = self.thickness {
+            anyhow::ensure!(thickness > 0.0 && thickness <= 1.0, "cursor.thickness は 0 より大きく 1 以下です: {}", thickness);
+            style.thickness = thickness;
+        }
+        if let Some(name) = &self.unfocused {
+            style.unfocused = UnfocusedCursor::from_name(name)
+                .with_context(|| format!("cursor.unfocused が正しくありません: {:?}", name))?;
+        }
+        Ok(style)
+    }
+}
+
+/// `[keyboard]`
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyboardConfig {
+    /// 左の Alt（Option）を Meta として ESC を前に付けて送るか
+    pub left_alt_as_meta: Option<bool>,
+    /// 右の Alt（Option）を Meta として ESC を前に付けて送るか
+    pub right_alt_as_meta: Option<bool>,
+}
+
+impl KeyboardConfig {
+    /// 左の Alt が Meta か（既定は macOS では文字の入力、ほかでは Meta）
+    pub fn left_alt_as_meta(&self) -> bool {
+        self.left_alt_as_meta.unwrap_or(!cfg!(target_os = "macos"))
+    }
+
+    /// 右の Alt が Meta か（既定は左と同じ）
+    pub fn right_alt_as_meta(&self) -> bool {
+        self.right_alt_as_meta.unwrap_or(!cfg!(target_os = "macos"))
+    }
+
+    /// 押している Alt が Meta か（左右が分からなければどちらかが Meta なら）
+    pub fn alt_is_meta(&self, left: bool, right: bool) -> bool {
+        match (left, right) {
+            (false, false) => self.left_alt_as_meta() || self.right_alt_as_meta(),
+            _ => (left && self.left_alt_as_meta()) || (right && self.right_alt_as_meta()),
+        }
+    }
+}
+
+/// `[banner]`（シェルを開いたペインに最初に出す文字）
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BannerConfig {
+    /// 出すか（既定は出す）
+    pub enabled: Option<bool>,
+    /// 組み込みのバナーの代わりに出すテキストファイル（エスケープシーケンスで色も付けられる）
+    pub path: Option<String>,
+}
+
+impl BannerConfig {
+    /// 出すか
+    pub fn enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    /// 代わりに出すファイル（`~/` はホーム、相対パスは設定ファイルのディレクトリから）
+    pub fn file(&self) -> Option<PathBuf> {
+        let file = self.path.as_deref()?;
+        match file.strip_prefix("~/") {
+            Some(rest) => std::env::var_os("HOME").map(|home| Path::new(&home).join(rest)),
+            None => Some(path().and_then(|path| Some(path.parent()?.join(file))).unwrap_or_else(|| file.into())),
+        }
+    }
+}
+
+/// `[shell]`
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShellConfig {
+    /// 起動するシェル（なければ `$SHELL`）
+    pub program: Option<String>,
+    /// シェルに渡す引数（ログインシェルの `-l` のあと）
+    pub args: Option<Vec<String>>,
+    /// ログインシェルとして起動するか（`-l`）
+    pub login: Option<bool>,
+    /// シェル（と起動するコマンド）に設定する環境変数（プロファイルの環境変数が優先）
+    pub env: BTreeMap<String, String>,
+    /// `UMITERM_SHELL_INTEGRATION`
+    pub integration: Option<bool>,
+}
+
+impl ProfileConfig {
+    /// 起動するコマンドライン（ssh の接続先なら ssh、なければ command）
+    pub fn command_line(&self) -> Option<String> {
+        match &self.ssh {
+            Some(destination) => Some(ssh::command_line(destination, &self.ssh_args)),
+            None => self.command.clone(),
+        }
+    }
+}
+
+impl ShellConfig {
+    /// ログインシェルとして起動するか（既定は args を書かなかったときだけ）
+    pub fn login(&self) -> bool {
+        self.login.unwrap_or(self.args.is_none())
+    }
+}
+
+/// `[window]`
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    /// `UMITERM_STARTUP_MODE`
+    #[serde(deserialize_with = "named")]
+    pub startup_mode: Option<StartupMode>,
+    /// `UMITERM_STATUS_BAR`
+    pub status_bar: Option<String>,
+    /// `UMITERM_IDLE_SECS`
+    pub idle_secs: Option<u64>,
+    /// `UMITERM_DROP_FOLDER`
+    #[serde(deserialize_with = "named")]
+    pub drop_folder: Option<DropFolderAction>,
+    /// `UMITERM_SINGLE_INSTANCE`
+    pub single_instance: Option<bool>,
+    /// `UMITERM_DECORATIONS`
+    #[serde(deserialize_with = "named")]
+    pub decorations: Option<Decorations>,
+    /// `UMITERM_FULLSCREEN`
+    #[serde(deserialize_with = "named")]
+    pub fullscreen: Option<FullscreenMode>,
+}
+
+/// `[terminal]`
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TerminalConfig {
+    /// `UMITERM_WORD_SEPARATORS`
+    pub word_separators: Option<String>,
+    /// `UMITERM_PROMPT_PATTERN`
+    pub prompt_pattern: Option<String>,
+}
+
+/// `[pane]`
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaneConfig {
+    /// `UMITERM_PANE_TITLES`
+    #[serde(deserialize_with = "named")]
+    pub titles: Option<PaneTitleMode>,
+    /// `UMITERM_ON_EXIT`
+    #[serde(deserialize_with = "named")]
+    pub on_exit: Option<ExitAction>,
+    /// `UMITERM_SILENCE_SECS`
+    pub silence_secs: Option<u64>,
+    /// `UMITERM_NOTIFY_SECS`
+    pub notify_secs: Option<u64>,
+}
+
+/// `[explorer]`
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExplorerConfig {
+    /// `UMITERM_EXPLORER_FOLLOW`
+    #[serde(deserialize_with = "named")]
+    pub follow: Option<ExplorerFollow>,
+    /// `UMITERM_EXPLORER_LAYOUT`
+    #[serde(deserialize_with = "named")]
+    pub layout: Option<ExplorerLayout>,
+}
+
+/// `[editor]`
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EditorConfig {
+    /// `UMITERM_EDITOR_COMMAND`
+    pub command: Option<String>,
+}
+
+/// `[open]`（ヒントモードや Cmd + クリックで開くコマンド、なければ OS の既定のアプリ）
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OpenConfig {
+    /// URL を開くコマンド（`{url}` を置き換え）
+    pub url: Option<String>,
+    /// ファイルを開くコマンド（`{file}` `{line}` `{col}` を置き換え）
+    pub file: Option<String>,
+}
+
+/// `[[links]]`（正規表現に一致した文字列を URL かコマンドで開く）
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LinkConfig {
+    /// 正規表現
+    pub regex: String,
+    /// 開く URL（`{0}` は一致全体、`{1}`〜`{9}` はグループ）
+    #[serde(default)]
+    pub url: Option<String>,
+    /// 実行するコマンド（置き換えは url と同じ、値はシェル用にクォートする）
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+/// `[hints]`
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HintsConfig {
+    /// `UMITERM_HINT_PATTERNS`（1行に1つ）
+    pub patterns: Option<String>,
+}
+
+/// `[triggers]`
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TriggersConfig {
+    /// `UMITERM_TRIGGERS`（1行に1つ）
+    pub rules: Option<String>,
+}
+
+/// `[ssh]`（SSH の接続先のランチャー）
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SshConfig {
+    /// `~/.ssh/config` の `Host` も接続先に出すか（既定は出す）
+    pub read_config: Option<bool>,
+}
+
+impl SshConfig {
+    /// `~/.ssh/config` を読むか
+    pub fn read_config(&self) -> bool {
+        self.read_config.unwrap_or(true)
+    }
+}
+
+/// `[containers]`（コンテナのランチャー）
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContainersConfig {
+    /// `docker` か `podman`（既定は PATH にあるほう、docker が先）
+    pub runtime: Option<String>,
+    /// コンテナで起動するシェル（既定は sh）
+    pub shell: Option<String>,
+}
+
+impl ContainersConfig {
+    /// コンテナで起動するシェル
+    pub fn shell(&self) -> &str {
+        self.shell.as_deref().unwrap_or("sh")
+    }
+}
+
+impl ColorsConfig {
+    /// パレットを作る（`theme` があればそのテーマ、書かなかった色はテーマか既定の色）
+    fn palette(&self) -> Result<Palette> {
+        self.palette_with(self.theme.as_deref())
+    }
+
+    /// theme を元にしてパレットを作る
+    fn palette_with(&self, theme: Option<&str>) -> Result<Palette> {
+        let base = match theme {
+            Some(name) => theme::load(name)?,
+            None => Palette::default(),
+        };
+        self.apply(base)
+    }
+
+    /// OS の外観に合わせてテーマを切り替えるか
+    pub fn follows_appearance(&self) -> bool {
+        self.light_theme.is_some() || self.dark_theme.is_some()
+    }
+
+    /// 書いた色で base を上書きする
+    pub fn apply(&self, base: Palette) -> Result<Palette> {
+        let color = |name: &str, value: &Option<String>, default: Color| match value {
+            Some(hex) => Color::from_hex(hex.trim())
+                .with_context(|| format!("colors.{} の色が正しくありません（#rrggbb）: {:?}", name, hex)),
+            None => Ok(default),
+        };
+        let mut ansi = base.ansi;
+        if let Some(colors) = &self.ansi {
+            anyhow::ensure!(colors.len() == 16, "colors.ansi には16色を書いてください（{}色あります）", colors.len());
+            for (slot, hex) in ansi.iter_mut().zip(colors) {
+                *slot = color("ansi", &Some(hex.clone()), *slot)?;
+            }
+        }
+        let foreground = color("foreground", &self.foreground, base.foreground)?;
+        // カーソルは指定がなければ文字色に合わせる
+        let cursor = if self.foreground.is_some() { foreground } else { base.cursor };
+        Ok(Palette {
+            foreground,
+            background: color("background", &self.background, base.background)?,
+            cursor: color("cursor", &self.cursor, cursor)?,
+            selection_fg: color("selection_foreground", &self.selection_foreground, base.selection_fg)?,
+            selection_bg: color("selection_background", &self.selection_background, base.selection_bg)?,
+            accent: color("accent", &self.accent, base.accent)?,
+            ansi,
+        })
+    }
+}
+
+impl Config {
+    /// 設定ファイルの文字列を読む
+    pub fn parse(text: &str) -> Result<Self> {
+        Self::parse_for(text, PLATFORM)
+    }
+
+    /// 設定ファイルの文字列を、指定の OS のセクションを重ねて読む
+    fn parse_for(text: &str, platform: &str) -> Result<Self> {
+        let mut table: toml::Table = toml::from_str(text)?;
+        let mut config: Self = if PLATFORMS.iter().any(|name| table.contains_key(*name)) {
+            let mut overrides = toml::Table::new();
+            for name in PLATFORMS {
+                match table.remove(*name) {
+                    Some(toml::Value::Table(section)) if *name == platform => overrides = section,
+                    Some(toml::Value::Table(_)) | None => {}
+                    Some(_) => anyhow::bail!("[{}] は表で書きます", name),
+                }
+            }
+            merge(&mut table, overrides);
+            // 重ねた表には行番号がないので、OS のセクションが始まる行を添える
+            table.clone().try_into().with_context(|| match section_line(text, platform) {
+                Some(line) => format!("{} 行目からの [{}] を重ねた設定が正しくありません", line, platform),
+                None => format!("[{}] を重ねた設定が正しくありません", platform),
+            })?
+        } else {
+            // 行番号付きのエラーにするため文字列から読む
+            toml::from_str(text)?
+        };
+        config.table = written(table);
+        config.palette = config.colors.palette()?;
+        let colors = &config.colors;
+        config.light_palette = colors.light_theme.as_deref().map(|name| colors.palette_with(Some(name))).transpose()?;
+        config.dark_palette = colors.dark_theme.as_deref().map(|name| colors.palette_with(Some(name))).transpose()?;
+        config.bindings = keybinds::parse_bindings(&config.keybinds)?;
+        config.mouse_bindings = mousebinds::parse_bindings(&config.mousebinds)?;
+        config.palette_commands = palette::parse_commands(&config.commands)?;
+        config.pipe_commands = pipe::parse_pipes(&config.pipes)?;
+        hints::link_rules(&config.links)?;
+        if let Some(names) = &config.window.status_bar {
+            status::check_names(names).context("window.status_bar が正しくありません")?;
+        }
+        if let Some(pattern) = &config.terminal.prompt_pattern {
+            Regex::new(pattern).context("terminal.prompt_pattern の正規表現が正しくありません")?;
+        }
+        if let Some(patterns) = &config.hints.patterns {
+            hints::check_patterns(patterns).context("hints.patterns が正しくありません")?;
+        }
+        if let Some(rules) = &config.triggers.rules {
+            triggers::check_rules(rules).context("triggers.rules が正しくありません")?;
+        }
+        config.cursor_style = config.cursor.style()?;
+        let steps = config.palette_commands.iter().flat_map(PaletteEntry::steps);
+        let profiles = config.bindings.iter().map(|binding| &binding.profile).chain(steps.map(|step| &step.profile));
+        for name in profiles.flatten() {
+            anyhow::ensure!(config.profiles.contains_key(name), "プロファイルが見つかりません: {}", name);
+        }
+        let steps = config.palette_commands.iter().flat_map(PaletteEntry::steps);
+        let pipes = config
+            .bindings
+            .iter()
+            .map(|binding| (binding.action, &binding.text))
+            .chain(steps.map(|step| (step.action, &step.text)))
+            .filter_map(|(action, text)| text.as_ref().filter(|_| action == BindAction::Pipe));
+        for name in pipes {
+            anyhow::ensure!(config.pipe(name).is_some(), "パイプが見つかりません: {}", name);
+        }
+        let steps = config.palette_commands.iter().flat_map(PaletteEntry::steps);
+        let layouts = config
+            .bindings
+            .iter()
+            .map(|binding| (binding.action, &binding.text))
+            .chain(steps.map(|step| (step.action, &step.text)))
+            .filter_map(|(action, text)| text.as_ref().filter(|_| action == BindAction::ApplyLayout));
+        for name in layouts {
+            anyhow::ensure!(
+                LayoutPreset::from_name(name).is_some(),
+                "レイアウトの名前が正しくありません（{}）: {}",
+                LayoutPreset::ALL.map(LayoutPreset::name).join(" / "),
+                name
+            );
+        }
+        for (name, profile) in &config.profiles {
+            anyhow::ensure!(
+                profile.ssh.is_none() || profile.command.is_none(),
+                "profiles.{} には command と ssh の片方だけを書きます",
+                name
+            );
+            anyhow::ensure!(
+                profile.ssh.is_none() || profile.wsl.is_none(),
+                "profiles.{} には ssh と wsl の片方だけを書きます",
+                name
+            );
+            anyhow::ensure!(
+                profile.ssh.is_some() || profile.ssh_args.is_empty(),
+                "profiles.{} の ssh_args は ssh と一緒に書きます",
+                name
+            );
+            if let Some(theme) = &profile.theme {
+                let palette = theme::load(theme).with_context(|| format!("profiles.{} のテーマを読み込めません", name))?;
+                config.profile_palettes.insert(name.clone(), palette);
+            }
+        }
+        Ok(config)
+    }
+
+    /// ファイルから読み込む（なければ既定の設定）
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text).with_context(|| format!("設定ファイルを読み込めません: {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("設定ファイルを開けません: {}", path.display())),
+        }
+    }
+
+    /// 設定ファイルに書いた値（`font.size` のように `.` で区切った場所、書いていなければ None）
+    pub fn get(&self, path: &str) -> Option<&toml::Value> {
+        let mut keys = path.split('.');
+        let mut value = self.table.get(keys.next()?)?;
+        for key in keys {
+            value = value.get(key)?;
+        }
+        Some(value)
+    }
+
+    /// 名前のパイプ
+    pub fn pipe(&self, name: &str) -> Option<&Pipe> {
+        self.pipe_commands.iter().find(|pipe| pipe.name == name)
+    }
+
+    /// 名前のプロファイル
+    pub fn profile(&self, name: &str) -> Result<&ProfileConfig> {
+        self.profiles.get(name).with_context(|| format!("プロファイルが見つかりません: {}", name))
+    }
+
+    /// 今使うパレット（OS の外観に合わせたテーマがあればそちら）
+    pub fn active_palette(&self) -> &Palette {
+        self.palette_for(theme::appearance())
+    }
+
+    /// 指定の外観のときに使うパレット（外観が分からなければ `[colors]` のパレット）
+    fn palette_for(&self, appearance: Option<Appearance>) -> &Palette {
+        let themed = match appearance {
+            Some(Appearance::Light) => self.light_palette.as_ref(),
+            Some(Appearance::Dark) => self.dark_palette.as_ref(),
+            None => None,
+        };
+        themed.unwrap_or(&self.palette)
+    }
+
+    /// 環境変数の名前に当たる設定の値
+    fn value(&self, name: &str) -> Option<String> {
+        fn name_of<T: Named>(value: Option<T>) -> Option<String> {
+            value.map(|value| value.name().to_string())
+        }
+        let number = |value: Option<u64>| value.map(|value| value.to_string());
+        match name {
+            "UMITERM_FONT" => self.font.path.clone(),
+            "UMITERM_SCROLLBACK_MB" => number(self.scrollback.max_mb),
+            "UMITERM_STARTUP_MODE" => name_of(self.window.startup_mode),
+            "UMITERM_STATUS_BAR" => self.window.status_bar.clone(),
+            "UMITERM_IDLE_SECS" => number(self.window.idle_secs),
+            "UMITERM_DROP_FOLDER" => name_of(self.window.drop_folder),
+            "UMITERM_SINGLE_INSTANCE" => self.window.single_instance.map(|on| on.to_string()),
+            "UMITERM_DECORATIONS" => name_of(self.window.decorations),
+            "UMITERM_FULLSCREEN" => name_of(self.window.fullscreen),
+            "UMITERM_SHELL_INTEGRATION" => self.shell.integration.map(|on| on.to_string()),
+            "UMITERM_WORD_SEPARATORS" => self.terminal.word_separators.clone(),
+            "UMITERM_PROMPT_PATTERN" => self.terminal.prompt_pattern.clone(),
+            "UMITERM_PANE_TITLES" => name_of(self.pane.titles),
+            "UMITERM_ON_EXIT" => name_of(self.pane.on_exit),
+            "UMITERM_SILENCE_SECS" => number(self.pane.silence_secs),
+            "UMITERM_NOTIFY_SECS" => number(self.pane.notify_secs),
+            "UMITERM_EXPLORER_FOLLOW" => name_of(self.explorer.follow),
+            "UMITERM_EXPLORER_LAYOUT" => name_of(self.explorer.layout),
+            "UMITERM_EDITOR_COMMAND" => self.editor.command.clone(),
+            "UMITERM_HINT_PATTERNS" => self.hints.patterns.clone(),
+            "UMITERM_TRIGGERS" => self.triggers.rules.clone(),
+            _ => None,
+        }
+    }
+}
+
+/// OS ごとのセクションを重ねる（表は項目ごとに、`[[keybinds]]` などの表の配列は後ろに足し、それ以外は置き換える）
+fn merge(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(value)) => merge(base, value),
+            (Some(toml::Value::Array(base)), toml::Value::Array(value))
+                if base.iter().chain(&value).all(toml::Value::is_table) =>
+            {
+                base.extend(value)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// 値を1つも書いていない表を除いた表（項目がすべてコメントの `[font]` などは書かなかったのと同じ）
+fn written(table: toml::Table) -> toml::Table {
+    table
+        .into_iter()
+        .filter_map(|(key, value)| match value {
+            toml::Value::Table(table) => Some(written(table)).filter(|table| !table.is_empty()).map(|table| (key, table.into())),
+            value => Some((key, value)),
+        })
+        .collect()
+}
+
+/// OS のセクションが始まる行（1 から、`[linux.font]` `[[linux.keybinds]]` `linux.font.size = 14` など）
+fn section_line(text: &str, platform: &str) -> Option<usize> {
+    text.lines().position(|line| {
+        let key = line.trim_start().trim_start_matches('[').trim_start();
+        key.strip_prefix(platform).is_some_and(|rest| rest.trim_start().starts_with(['.', ']', '=']))
+    })
+    .map(|index| index + 1)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 読み込みと監視
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 設定ファイルの場所を変える（最初に設定を読むより前に呼ぶ、相対パスは今の作業ディレクトリから）
+pub fn set_path(path: PathBuf) {
+    let path = std::env::current_dir().map(|cwd| cwd.join(&path)).unwrap_or(path);
+    if PATH.set(path).is_err() {
+        log::warn!("設定ファイルの場所はすでに決まっています");
+    }
+}
+
+/// 設定ファイルの場所（`--config`、`$XDG_CONFIG_HOME/umiterm/umiterm.toml`、なければ `~/.config/umiterm/umiterm.toml`）
+pub fn path() -> Option<PathBuf> {
+    if let Some(path) = PATH.get() {
+        return Some(path.clone());
+    }
+    let dir = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => home_dir()?.join(".config"),
+    };
+    Some(dir.join("umiterm").join("umiterm.toml"))
+}
+
+/// ホームディレクトリ（`$HOME`、Windows でなければ `%USERPROFILE%`）
+pub fn home_dir() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").filter(|home| !home.is_empty());
+    let home = if cfg!(windows) { home.or_else(|| std::env::var_os("USERPROFILE")) } else { home };
+    home.map(PathBuf::from)
+}
+
+/// 設定ファイルを読み込む（読めなければ警告して既定の設定）
+fn load_or_default() -> Config {
+    let Some(path) = path() else { return Config::default() };
+    checked(Config::load(&path)).unwrap_or_default()
+}
+
+/// 読み込んだ結果を覚える（書き間違いはログとデスクトップ通知に出す）
+fn checked(result: Result<Config>) -> Option<Config> {
+    match result {
+        Ok(config) => {
+            *LOAD_ERROR.write() = None;
+            Some(config)
+        }
+        Err(e) => {
+            let message = format!("{:#}", e);
+            log::error!("{}", message);
+            notification::notify("UmiTerm: 設定ファイルの書き間違い", &message);
+            *LOAD_ERROR.write() = Some(message);
+            None
+        }
+    }
+}
+
+/// 最後に設定ファイルを読めなかったときのエラー（ファイルと行番号付き）
+pub fn load_error() -> Option<String> {
+    LOAD_ERROR.read().clone()
+}
+
+/// 説明付きの設定ファイルを書き出す（path がなければ標準出力、すでにあるファイルは上書きしない）
+pub fn generate(path: Option<&Path>) -> Result<()> {
+    let Some(path) = path else {
+        print!("{}", DEFAULT_CONFIG);
+        return Ok(());
+    };
+    anyhow::ensure!(!path.exists(), "すでにファイルがあります: {}", path.display());
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).with_context(|| format!("ディレクトリを作れません: {}", dir.display()))?;
+    }
+    fs::write(path, DEFAULT_CONFIG).with_context(|| format!("設定ファイルを書き出せません: {}", path.display()))?;
+    println!("設定ファイルを書き出しました: {}", path.display());
+    Ok(())
+}
+
+/// 現在の設定
+pub fn current() -> Arc<Config> {
+    if let Some(config) = CURRENT.read().as_ref() {
+        return config.clone();
+    }
+    let config = Arc::new(load_or_default());
+    CURRENT.write().get_or_insert(config).clone()
+}
+
+/// 設定ファイルを読み直す
+///
+/// 戻り値: 変わった設定（変わっていない・読めなかったときは None で、前の設定のまま）
+pub fn reload() -> Option<Arc<Config>> {
+    let path = path()?;
+    let config = checked(Config::load(&path))?;
+    if *current() == config {
+        return None;
+    }
+    log::info!("設定ファイルを読み直しました: {}", path.display());
+    let config = Arc::new(config);
+    *CURRENT.write() = Some(config.clone());
+    Some(config)
+}
+
+/// テーマを切り替える（設定ファイルを読み直すとファイルのテーマに戻る）
+///
+/// 切り替えている間は OS の外観に合わせたテーマより優先する
+/// 戻り値: 切り替えた後の設定
+pub fn set_theme(name: &str) -> Result<Arc<Config>> {
+    let mut config = (*current()).clone();
+    config.colors.theme = Some(name.to_string());
+    config.colors.light_theme = None;
+    config.colors.dark_theme = None;
+    config.palette = config.colors.palette()?;
+    config.light_palette = None;
+    config.dark_palette = None;
+    let config = Arc::new(config);
+    *CURRENT.write() = Some(config.clone());
+    Ok(config)
+}
+
+/// 設定の値（環境変数があればそちらを優先）
+pub fn var(name: &str) -> Option<String> {
+    std::env::var(name).ok().or_else(|| current().value(name))
+}
+
+/// 名前で書く設定の値（環境変数があればそちらを優先、使えない名前なら警告して設定ファイルの値）
+pub fn named_var<T: Named>(name: &str, value: impl FnOnce(&Config) -> Option<T>) -> Option<T> {
+    if let Ok(text) = std::env::var(name) {
+        match T::from_name(text.trim()) {
+            Some(value) => return Some(value),
+            None => log::warn!("{} の値が正しくありません: {:?}（{}）", name, text, T::names()),
+        }
+    }
+    value(&current())
+}
+
+/// 設定ファイルを監視し、変わったら on_change を呼ぶ（監視スレッドから呼ばれる）
+///
+/// エディタは別名で書いてから置き換えることが多いため、ディレクトリごと監視する
+/// 戻り値の監視を破棄すると監視をやめる
+pub fn watch(on_change: impl Fn() + Send + 'static) -> Option<RecommendedWatcher> {
+    let path = path()?;
+    let dir = path.parent()?.to_path_buf();
+    let watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+        let Ok(event) = result else { return };
+        let written = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_));
+        if written && event.paths.iter().any(|changed| changed == &path) {
+            on_change();
+        }
+    });
+    let mut watcher = watcher
+        .inspect_err(|e| log::warn!("設定ファイルの監視を開始できません: {}", e))
+        .ok()?;
+    if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+        // ディレクトリがなければ設定ファイルもない（作ったら次の起動から読む）
+        log::debug!("設定ファイルのディレクトリを監視できません: {:?} ({})", dir, e);
+        return None;
+    }
+    Some(watcher)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipe::{PipeInput, PipeOutput};
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse(
+            r##"
+            [font]
+            size = 18
+
+            [colors]
+            foreground = "#ffffff"
+            background = "#101010"
+
+            [scrollback]
+            max_mb = 64
+
+            [shell]
+            program = "/bin/zsh"
+            args = []
+
+            [explorer]
+            layout = "sidebar"
+
+            [[keybinds]]
+            key = "cmd+shift+k"
+            action = "split-vertical"
+
+            [leader]
+            timeout_ms = 800
+            "##,
+        )
+        .unwrap();
+        assert_eq!(config.font.size, Some(18.0));
+        assert_eq!(config.palette.foreground, Color::WHITE);
+        assert_eq!(config.palette.background, Color::rgb(16, 16, 16));
+        // カーソルは文字色に合わせ、書かなかった色は既定の色
+        assert_eq!(config.palette.cursor, Color::WHITE);
+        assert_eq!(config.palette.ansi, Palette::default().ansi);
+        assert_eq!(config.shell.program.as_deref(), Some("/bin/zsh"));
+        assert_eq!(config.shell.args, Some(vec![]));
+        assert!(!config.shell.login());
+        assert_eq!(config.bindings.len(), 1);
+        assert_eq!(config.leader.timeout(), Duration::from_millis(800));
+        assert!(config.leader.which_key());
+        assert_eq!(config.value("UMITERM_SCROLLBACK_MB").as_deref(), Some("64"));
+        assert_eq!(config.explorer.layout, Some(ExplorerLayout::Sidebar));
+        assert_eq!(config.value("UMITERM_EXPLORER_LAYOUT").as_deref(), Some("sidebar"));
+        assert_eq!(config.value("UMITERM_EXPLORER_FOLLOW"), None);
+        assert_eq!(config.get("font.size"), Some(&toml::Value::Integer(18)));
+        assert_eq!(config.get("leader").and_then(|leader| leader.get("timeout_ms")), Some(&toml::Value::Integer(800)));
+        assert_eq!(config.get("font.family"), None);
+        assert_eq!(config.get("font.size.x"), None);
+
+        // 空のファイルは既定の設定
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+
+        // 書き間違いはエラー（読み直しでは前の設定のまま）
+        assert!(Config::parse("[font]\nsise = 18").is_err());
+        assert!(Config::parse("[colors]\nforeground = \"white\"").is_err());
+        assert!(Config::parse("[colors]\nansi = [\"#000000\"]").is_err());
+
+        // 使えない値もエラー
+        let message = format!("{:#}", Config::parse("[window]\nstartup_mode = \"fulscreen\"").unwrap_err());
+        assert!(message.contains("windowed / maximized / fullscreen"), "{}", message);
+        assert!(Config::parse("[pane]\non_exit = \"keep\"").is_err());
+        assert!(Config::parse("[window]\nstatus_bar = \"cwd,branch\"").is_err());
+        assert!(Config::parse("[terminal]\nprompt_pattern = \"(\"").is_err());
+        assert!(Config::parse("[hints]\npatterns = \"[a-\"").is_err());
+        assert!(Config::parse("[triggers]\nrules = \"error => beep\"").is_err());
+        let config = Config::parse("[window]\nstatus_bar = \"cwd, git\"\nstartup_mode = \"fullscreen\"").unwrap();
+        assert_eq!(config.window.startup_mode, Some(StartupMode::Fullscreen));
+        assert!(Config::parse("[[keybinds]]\nkey = \"cmd+k\"\naction = \"fly\"").is_err());
+        assert!(Config::parse("[[keybinds]]\nkey = \"cmd+k\"\naction = \"apply-layout\"").is_err());
+        assert!(Config::parse("[[keybinds]]\nkey = \"cmd+k\"\naction = \"apply-layout\"\ntext = \"tiled\"").is_err());
+        let config = Config::parse("[[keybinds]]\nkey = \"cmd+k\"\naction = \"apply-layout\"\ntext = \"grid\"").unwrap();
+        assert_eq!(config.bindings[0].action, BindAction::ApplyLayout);
+        assert!(Config::parse("[colors]\ntheme = \"no-such-theme\"").is_err());
+    }
+
+    #[test]
+    fn test_platform_sections() {
+        let text = r#"
+            [font]
+            path = "/fonts/a.ttf"
+            size = 18
+
+            [[keybinds]]
+            key = "cmd+t"
+            action = "new-tab"
+
+            [linux.font]
+            size = 14
+
+            [[linux.keybinds]]
+            key = "ctrl+shift+t"
+            action = "new-tab"
+
+            [linux.shell]
+            args = ["-l"]
+
+            [macos.shell]
+            program = "/bin/zsh"
+        "#;
+
+        // 表は項目ごとに重ね、表の配列は後ろに足す
+        let linux = Config::parse_for(text, "linux").unwrap();
+        assert_eq!(linux.font.path.as_deref(), Some("/fonts/a.ttf"));
+        assert_eq!(linux.font.size, Some(14.0));
+        assert_eq!(linux.get("font.size"), Some(&toml::Value::Integer(14)));
+        assert_eq!(linux.bindings.len(), 2);
+        assert_eq!(linux.shell.args, Some(vec!["-l".to_string()]));
+        assert_eq!(linux.shell.program, None);
+
+        // ほかの OS のセクションは使わない
+        let macos = Config::parse_for(text, "macos").unwrap();
+        assert_eq!(macos.font.size, Some(18.0));
+        assert_eq!(macos.bindings.len(), 1);
+        assert_eq!(macos.shell.program.as_deref(), Some("/bin/zsh"));
+        assert_eq!(Config::parse_for(text, "windows").unwrap().shell, ShellConfig::default());
+
+        // 重ねたあとの書き間違いもエラー
+        assert!(Config::parse_for("[linux.font]\nsise = 14", "linux").is_err());
+        assert!(Config::parse("linux = 1").is_err());
+
+        // 重ねたあとのエラーには OS のセクションの行
+        let error = Config::parse_for("[font]\nsize = 18\n\n[linux.font]\nsise = 14", "linux").unwrap_err();
+        assert!(format!("{:#}", error).starts_with("4 行目からの [linux] を"), "{:#}", error);
+        assert_eq!(section_line("[linux]\nfont.size = 1", "linux"), Some(1));
+        assert_eq!(section_line("a = 1\n  linux.font.size = 1", "linux"), Some(2));
+        assert_eq!(section_line("[linuxbrew]\n[[ linux.keybinds ]]", "linux"), Some(2));
+    }
+
+    #[test]
+    fn test_generate() {
+        // 説明付きの設定ファイルは読むと既定の設定、書き間違いは行番号付き
+        assert_eq!(Config::parse(DEFAULT_CONFIG).unwrap(), Config::default());
+        let uncommented = DEFAULT_CONFIG.replacen("# size = 22", "sise = 22", 1);
+        let error = format!("{:#}", Config::parse(&uncommented).unwrap_err());
+        assert!(error.contains("line 11") && error.contains("sise"), "{}", error);
+
+        let dir = std::env::temp_dir().join(format!("umiterm-generate-{}", std::process::id()));
+        let path = dir.join("umiterm").join("umiterm.toml");
+        generate(Some(&path)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), DEFAULT_CONFIG);
+        // すでにあるファイルは上書きしない
+        assert!(generate(Some(&path)).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_theme() {
+        // テーマの色に [colors] の色を重ねる
+        let config = Config::parse("[colors]\ntheme = \"dracula\"\nforeground = \"#ffffff\"").unwrap();
+        let dracula = theme::load("dracula").unwrap();
+        assert_eq!(config.palette.foreground, Color::WHITE);
+        assert_eq!(config.palette.background, dracula.background);
+        assert_eq!(config.palette.accent, dracula.accent);
+        assert_eq!(config.palette.ansi, dracula.ansi);
+
+        // OS の外観に合わせたテーマ（ライト / ダークそれぞれに [colors] の色を重ねる）
+        let config = Config::parse(
+            "[colors]\nlight_theme = \"solarized-light\"\ndark_theme = \"nord\"\ncursor = \"#ff0000\"",
+        )
+        .unwrap();
+        assert!(config.colors.follows_appearance());
+        let light = config.light_palette.as_ref().unwrap();
+        assert_eq!(light.background, theme::load("solarized-light").unwrap().background);
+        assert_eq!(light.cursor, Color::rgb(255, 0, 0));
+        assert_eq!(config.palette_for(Some(Appearance::Dark)).background, theme::load("nord").unwrap().background);
+        assert_eq!(config.palette_for(Some(Appearance::Light)), light);
+        assert_eq!(config.palette_for(None), &config.palette);
+        assert!(!Config::default().colors.follows_appearance());
+    }
+
+    #[test]
+    fn test_profiles() {
+        let config = Config::parse(
+            "[profiles.prod]\ncommand = \"ssh prod\"\ntheme = \"dracula\"\nfont_size = 18\nenv = { STAGE = \"prod\" }\n\n\
+             [[keybinds]]\nkey = \"cmd+shift+p\"\naction = \"new-tab\"\nprofile = \"prod\"",
+        )
+        .unwrap();
+        let prod = config.profile("prod").unwrap();
+        assert_eq!(prod.command.as_deref(), Some("ssh prod"));
+        assert_eq!(prod.font_size, Some(18.0));
+        assert_eq!(prod.env["STAGE"], "prod");
+        assert_eq!(config.profile_palettes["prod"], theme::load("dracula").unwrap());
+        assert_eq!(config.bindings[0].profile.as_deref(), Some("prod"));
+        assert!(config.profile("local").is_err());
+
+        // キー割り当てのプロファイルは [profiles] にあるものだけ
+        assert!(Config::parse("[[keybinds]]\nkey = \"cmd+p\"\naction = \"new-tab\"\nprofile = \"prod\"").is_err());
+        assert!(Config::parse("[[commands]]\nname = \"prod\"\nsteps = [{ action = \"new-tab\", profile = \"prod\" }]").is_err());
+        assert!(Config::parse("[profiles.prod]\ntheme = \"no-such-theme\"").is_err());
+        assert!(Config::parse("[profiles.prod]\nshell = \"zsh\"").is_err());
+
+        // SSH の接続先のプロファイル
+        let config = Config::parse("[profiles.prod]\nssh = \"ssh://deploy@prod:2222\"\nssh_args = [\"-A\"]\ntitle = \"PROD\"").unwrap();
+        let prod = config.profile("prod").unwrap();
+        assert_eq!(prod.command_line().as_deref(), Some("ssh -A -- ssh://deploy@prod:2222"));
+        assert_eq!(prod.title.as_deref(), Some("PROD"));
+        assert!(config.ssh.read_config());
+        assert!(Config::parse("[profiles.prod]\nssh = \"prod\"\ncommand = \"htop\"").is_err());
+        assert!(Config::parse("[profiles.prod]\nssh_args = [\"-A\"]").is_err());
+
+        // WSL のディストリビューションのプロファイル
+        let config = Config::parse("[profiles.ubuntu]\nwsl = \"Ubuntu\"\ncommand = \"htop\"\ncwd = \"/home/umi\"").unwrap();
+        assert_eq!(config.profile("ubuntu").unwrap().wsl.as_deref(), Some("Ubuntu"));
+        assert!(Config::parse("[profiles.prod]\nssh = \"prod\"\nwsl = \"Ubuntu\"").is_err());
+    }
+
+    #[test]
+    fn test_containers() {
+        let config = Config::parse("[containers]\nruntime = \"podman\"\nshell = \"bash\"").unwrap();
+        assert_eq!(config.containers.runtime.as_deref(), Some("podman"));
+        assert_eq!(config.containers.shell(), "bash");
+        assert_eq!(Config::default().containers.shell(), "sh");
+        assert!(Config::parse("[containers]\nimage = \"alpine\"").is_err());
+    }
+
+    #[test]
+    fn test_pipes() {
+        let config = Config::parse(
+            "[[pipes]]\nname = \"pick\"\ncommand = \"fzf\"\ninput = \"scrollback\"\noutput = \"insert\"\n\n\
+             [[keybinds]]\nkey = \"cmd+shift+f\"\naction = \"pipe\"\ntext = \"pick\"",
+        )
+        .unwrap();
+        let pick = config.pipe("pick").unwrap();
+        assert_eq!(pick.command, "fzf");
+        assert_eq!((pick.input, pick.output), (PipeInput::Scrollback, PipeOutput::Insert));
+        assert!(config.pipe("gist").is_none());
+
+        // pipe の操作のパイプは [[pipes]] にあるものだけ
+        assert!(Config::parse("[[keybinds]]\nkey = \"cmd+p\"\naction = \"pipe\"\ntext = \"pick\"").is_err());
+        assert!(Config::parse("[[commands]]\nname = \"x\"\nsteps = [{ action = \"pipe\", text = \"pick\" }]").is_err());
+        assert!(Config::parse("[[pipes]]\nname = \"pick\"\ncommand = \"fzf\"\noutput = \"clipboard\"").is_err());
+    }
+
+    #[test]
+    fn test_shell() {
+        // 既定はログインシェル
+        assert!(Config::default().shell.login());
+        let config = Config::parse("[shell]\nargs = [\"-i\"]\nlogin = true\nenv = { EDITOR = \"nvim\" }").unwrap();
+        assert!(config.shell.login());
+        assert_eq!(config.shell.args, Some(vec!["-i".to_string()]));
+        assert_eq!(config.shell.env["EDITOR"], "nvim");
+        assert!(!Config::parse("[shell]\nlogin = false").unwrap().shell.login());
+    }
+
+    #[test]
+    fn test_cursor() {
+        assert_eq!(Config::default().cursor_style, CursorStyle::default());
+        let config = Config::parse("[cursor]\nshape = \"beam\"\nthickness = 0.25\nunfocused = \"none\"").unwrap();
+        assert_eq!(config.cursor_style.shape, CursorShape::Beam);
+        assert_eq!(config.cursor_style.thickness, 0.25);
+        assert_eq!(config.cursor_style.unfocused, UnfocusedCursor::Hidden);
+        assert!(Config::parse("[cursor]\nshape = \"bar\"").is_err());
+        assert!(Config::parse("[cursor]\nthickness = 0.0").is_err());
+        assert!(Config::parse("[cursor]\nunfocused = \"outline\"").is_err());
+    }
+
+    #[test]
+    fn test_keyboard() {
+        let config = Config::parse("[keyboard]\nleft_alt_as_meta = true\nright_alt_as_meta = false").unwrap();
+        assert!(config.keyboard.alt_is_meta(true, false));
+        assert!(!config.keyboard.alt_is_meta(false, true));
+        assert!(config.keyboard.alt_is_meta(true, true));
+        // 左右が分からないときはどちらかが Meta なら
+        assert!(config.keyboard.alt_is_meta(false, false));
+        let config = Config::parse("[keyboard]\nleft_alt_as_meta = false\nright_alt_as_meta = false").unwrap();
+        assert!(!config.keyboard.alt_is_meta(false, false));
+        assert!(Config::parse("[keyboard]\nalt_as_meta = true").is_err());
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = std::env::temp_dir().join(format!("umiterm-config-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let file = dir.join("umiterm.toml");
+        // ファイルがなければ既定の設定
+        assert_eq!(Config::load(&file).unwrap(), Config::default());
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&file, "[pane]\non_exit = \"hold\"\n").unwrap();
+        assert_eq!(Config::load(&file).unwrap().pane.on_exit, Some(ExitAction::Hold));
+
+        // エラーにはファイル名と行が入る
+        fs::write(&file, "[pane]\non_exit = hold\n").unwrap();
+        let message = format!("{:#}", Config::load(&file).unwrap_err());
+        assert!(message.contains("umiterm.toml"), "{}", message);
+        assert!(message.contains("line 2"), "{}", message);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,126 @@
+//! コンテナのモジュール
+//!
+//! 動いている Docker / Podman のコンテナを `container-launcher` のコマンドパレットに並べ、
+//! 選んだコンテナで `docker exec -it <名前> sh` を新しいタブで起動する（ペインのタイトルにコンテナの名前を出す）
+//!
+//! ```toml
+//! [containers]
+//! runtime = "podman"
+//! shell = "bash"
+//! ```
+
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{Context, Result};
+
+use crate::config::ContainersConfig;
+use crate::editor::shell_escape;
+
+/// 設定になければ PATH から探すランタイム（前にあるほうを使う）
+const RUNTIMES: &[&str] = &["docker", "podman"];
+
+/// 動いているコンテナ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Container {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+}
+
+/// 使うランタイム（設定の runtime、なければ PATH にある docker か podman）
+pub fn runtime(config: &ContainersConfig) -> Result<String> {
+    if let Some(runtime) = &config.runtime {
+        return Ok(runtime.clone());
+    }
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    RUNTIMES
+        .iter()
+        .find(|runtime| std::env::split_paths(&path).any(|dir| is_program(&dir.join(runtime))))
+        .map(|runtime| runtime.to_string())
+        .context("docker も podman も見つかりません（[containers] runtime で指定できます）")
+}
+
+/// 実行できるファイルか
+fn is_program(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata().is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.with_extension("exe").is_file()
+    }
+}
+
+/// 動いているコンテナの一覧（`docker ps`）
+pub fn list(config: &ContainersConfig) -> Result<Vec<Container>> {
+    let runtime = runtime(config)?;
+    let output = Command::new(&runtime)
+        .args(["ps", "--format", "{{.ID}}\t{{.Names}}\t{{.Image}}"])
+        .output()
+        .with_context(|| format!("{} を実行できません", runtime))?;
+    anyhow::ensure!(
+        output.status.success(),
+        "{} ps が失敗しました: {}",
+        runtime,
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(parse_ps(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// `ps --format` の出力を読む（1行に ID・名前・イメージをタブ区切り）
+pub fn parse_ps(text: &str) -> Vec<Container> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.trim_end_matches('\r').split('\t');
+            let (id, name, image) = (fields.next()?.trim(), fields.next()?.trim(), fields.next().unwrap_or_default().trim());
+            // Podman は名前を空白区切りで複数返すことがある
+            let name = name.split([' ', ',']).find(|name| !name.is_empty())?;
+            (!id.is_empty()).then(|| Container { id: id.into(), name: name.into(), image: image.into() })
+        })
+        .collect()
+}
+
+/// コンテナでシェルを起動するコマンドライン
+pub fn exec_line(config: &ContainersConfig, name: &str) -> Result<String> {
+    Ok(command_line(&runtime(config)?, name, config.shell()))
+}
+
+/// `<runtime> exec -it <name> <shell>`
+fn command_line(runtime: &str, name: &str, shell: &str) -> String {
+    [runtime, "exec", "-it", name, shell].map(shell_escape).join(" ")
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ps() {
+        let text = "3f2a9c1b7d4e\tweb\tnginx:1.27\n\
+                    8c1d2e3f4a5b\tdb,db-alias\tpostgres:16\r\n\
+                    \n\
+                    9a8b7c6d5e4f\tworker\n";
+        assert_eq!(
+            parse_ps(text),
+            [
+                Container { id: "3f2a9c1b7d4e".into(), name: "web".into(), image: "nginx:1.27".into() },
+                Container { id: "8c1d2e3f4a5b".into(), name: "db".into(), image: "postgres:16".into() },
+                Container { id: "9a8b7c6d5e4f".into(), name: "worker".into(), image: String::new() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exec_line() {
+        let config = ContainersConfig { runtime: Some("podman".into()), shell: None };
+        assert_eq!(exec_line(&config, "web").unwrap(), "podman exec -it web sh");
+        assert_eq!(command_line("docker", "my app", "bash"), "docker exec -it 'my app' bash");
+    }
+}
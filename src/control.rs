@@ -0,0 +1,490 @@
+//! リモート操作モジュール
+//!
+//! 起動中の UmiTerm を `umiterm cli <操作>` で操作する（wezterm cli や kitty @ のように）
+//! スクリプトやエディタからペインの分割・文字の送信・画面の読み取りができる
+//!
+//! GUI は Unix ドメインソケットで待ち受け、1行1つの JSON で要求を受けて応答を返す
+//! ペインのシェルには環境変数 `UMITERM_CONTROL_SOCKET` と `UMITERM_PANE_ID` を設定するので、
+//! ペインの中で実行すると `--pane-id` を省略したときはそのペインが対象になる
+//! シングルインスタンス（`[window] single_instance`）では、2つ目の起動が新しいウィンドウの要求をここに送る
+//!
+//! ```text
+//! umiterm cli list-panes
+//! umiterm cli split-pane --horizontal -- htop
+//! echo make | umiterm cli send-text --pane-id 3
+//! umiterm cli subscribe | jq -c 'select(.event == "bell")'
+//! ```
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use crossbeam_channel::{bounded, Sender};
+use serde::{Deserialize, Serialize};
+
+use crate::cli::CliAction;
+use crate::editor::shell_escape;
+use crate::events;
+use crate::pane::PaneId;
+use crate::private_socket;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 定数
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// ソケットの場所を指定する環境変数（ペインのシェルにも設定する）
+const SOCKET_ENV: &str = "UMITERM_CONTROL_SOCKET";
+
+/// ペインのIDを入れる環境変数
+const PANE_ENV: &str = "UMITERM_PANE_ID";
+
+/// イベントループの応答を待つ時間
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 1つの要求の上限（バイト、send-text の文字列を含む）
+const MAX_REQUEST: u64 = 16 * 1024 * 1024;
+
+/// ソケットのパス（`UMITERM_CONTROL_SOCKET` > 自分専用のディレクトリの `control.sock`）
+pub fn socket_path() -> PathBuf {
+    match std::env::var_os(SOCKET_ENV) {
+        Some(path) => PathBuf::from(path),
+        None => private_socket::default_path("control.sock"),
+    }
+}
+
+/// ペインのシェルに設定する環境変数
+pub fn pane_env(id: PaneId) -> Vec<(String, String)> {
+    vec![
+        (SOCKET_ENV.to_string(), socket_path().display().to_string()),
+        (PANE_ENV.to_string(), id.0.to_string()),
+    ]
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// プロトコル
+// ═══════════════════════════════════════════════════════════════════════════
+//
+// 要求: {"type":"send-text","pane":3,"text":"ls\r","paste":false}
+// 応答: "ok" / {"pane":4} / {"text":"..."} / {"panes":[...]} / {"error":"..."}
+// 購読: {"type":"subscribe"} → "ok" → {"event":"bell","pane":3} ...
+
+/// 要求（pane が None ならフォーカス中のペイン）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Request {
+    /// ペインの一覧
+    ListPanes,
+    /// ペインを分割（horizontal なら左右、command が None ならシェル）
+    SplitPane { pane: Option<u64>, horizontal: bool, cwd: Option<PathBuf>, command: Option<String> },
+    /// ペインに文字列を送る（paste ならブラケットペースト）
+    SendText { pane: Option<u64>, text: String, paste: bool },
+    /// ペインにフォーカスする
+    Activate { pane: Option<u64> },
+    /// ペインの画面（scrollback ならスクロールバックも）の文字
+    GetText { pane: Option<u64>, scrollback: bool },
+    /// 新しいウィンドウを開く（シングルインスタンスで起動したとき、command が None ならシェル）
+    NewWindow { cwd: Option<PathBuf>, command: Option<String> },
+    /// イベントを購読する（"ok" のあと、接続を閉じるまでイベントを1行ずつ書く）
+    Subscribe,
+}
+
+/// 応答
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Reply {
+    /// 成功
+    Ok,
+    /// ペインの一覧
+    Panes(Vec<PaneInfo>),
+    /// 作ったペイン（新しいウィンドウなら最初のペイン）のID
+    Pane(u64),
+    /// 画面の文字
+    Text(String),
+    /// 失敗
+    Error(String),
+}
+
+/// 一覧の1つのペイン
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaneInfo {
+    pub window: u64,
+    pub tab: usize,
+    pub pane: u64,
+    pub title: String,
+    pub cwd: PathBuf,
+    pub cols: u16,
+    pub rows: u16,
+    /// フォーカス中のウィンドウの表示中のタブでフォーカス中か
+    pub focused: bool,
+}
+
+/// イベントループで処理する要求（応答を送り返すと接続に書き戻す）
+#[derive(Debug, Clone)]
+pub struct Pending {
+    pub request: Request,
+    reply: Sender<Reply>,
+}
+
+impl Pending {
+    /// 応答を送り返す
+    pub fn reply(self, reply: Reply) {
+        let _ = self.reply.send(reply);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// サーバー（GUI）
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 待ち受け中のソケット（破棄するとソケットのファイルを消す）
+pub struct Server {
+    path: PathBuf,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        fs::remove_file(&self.path).ok();
+    }
+}
+
+/// ソケットで待ち受け、届いた要求を on_request に渡す
+///
+/// 戻り値: ほかの UmiTerm がすでに待ち受けていれば None
+pub fn serve(on_request: impl Fn(Pending) + Send + Sync + 'static) -> Result<Option<Server>> {
+    serve_at(socket_path(), on_request)
+}
+
+fn serve_at(path: PathBuf, on_request: impl Fn(Pending) + Send + Sync + 'static) -> Result<Option<Server>> {
+    // 前回の UmiTerm が残したソケットは、応答がなければ作り直す
+    if private_socket::connect(&path).is_ok() {
+        log::info!("ほかの UmiTerm がリモート操作を受け付けています: {}", path.display());
+        return Ok(None);
+    }
+    let listener = private_socket::bind(&path)?;
+    log::info!("リモート操作を受け付けます: {}", path.display());
+
+    let on_request = std::sync::Arc::new(on_request);
+    std::thread::Builder::new()
+        .name("control-listener".into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        log::warn!("接続の受け付けに失敗: {}", e);
+                        continue;
+                    }
+                };
+                let on_request = on_request.clone();
+                let spawned = std::thread::Builder::new()
+                    .name("control-client".into())
+                    .spawn(move || {
+                        if let Err(e) = handle_client(stream, &*on_request) {
+                            log::debug!("リモート操作の通信が終了: {}", e);
+                        }
+                    });
+                if let Err(e) = spawned {
+                    log::warn!("リモート操作のスレッドを起動できません: {}", e);
+                }
+            }
+        })?;
+    Ok(Some(Server { path }))
+}
+
+/// 1つの接続を処理（要求を1つ読み、応答を1つ書く）
+///
+/// ほかのユーザーの接続は断る（文字の送信・コマンドの実行・画面の読み取りができるため）
+fn handle_client(stream: UnixStream, on_request: &dyn Fn(Pending)) -> Result<()> {
+    private_socket::check_peer(&stream).context("リモート操作の接続を断りました")?;
+    let mut line = String::new();
+    BufReader::new((&stream).take(MAX_REQUEST)).read_line(&mut line)?;
+    let reply = match serde_json::from_str::<Request>(&line) {
+        Ok(Request::Subscribe) => return Ok(stream_events(&stream)?),
+        Ok(request) => {
+            let (reply, replied) = bounded(1);
+            on_request(Pending { request, reply });
+            replied
+                .recv_timeout(REPLY_TIMEOUT)
+                .unwrap_or_else(|_| Reply::Error("UmiTerm が応答しません".to_string()))
+        }
+        Err(e) => Reply::Error(format!("要求が正しくありません: {}", e)),
+    };
+    Ok(write_line(&stream, &reply)?)
+}
+
+/// イベントを接続が閉じるまで書き続ける（閉じたことは次のイベントを書くときに分かる）
+fn stream_events(stream: &UnixStream) -> io::Result<()> {
+    let events = events::subscribe();
+    write_line(stream, &Reply::Ok)?;
+    for event in events {
+        write_line(stream, &event)?;
+    }
+    Ok(())
+}
+
+/// JSON を1行書く
+fn write_line(mut w: impl Write, value: &impl Serialize) -> io::Result<()> {
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+    w.write_all(&line)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// クライアント（umiterm cli）
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 起動中の UmiTerm に要求を送って応答を受け取る
+pub fn send(request: &Request) -> Result<Reply> {
+    send_to(&socket_path(), request)
+}
+
+fn send_to(path: &Path, request: &Request) -> Result<Reply> {
+    let stream = private_socket::connect(path).context("起動中の UmiTerm に接続できません")?;
+    exchange(&stream, request)
+}
+
+/// 要求を書いて応答を1行読む
+fn exchange(stream: &UnixStream, request: &Request) -> Result<Reply> {
+    write_line(stream, request)?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    serde_json::from_str(&line).context("UmiTerm の応答が正しくありません")
+}
+
+/// 起動中の UmiTerm に新しいウィンドウを開いてもらう（シングルインスタンス）
+///
+/// 戻り値: 起動中の UmiTerm がなければ false（自分でウィンドウを開く）
+pub fn open_window(command: Option<String>, cwd: Option<PathBuf>) -> Result<bool> {
+    open_window_at(&socket_path(), command, cwd)
+}
+
+fn open_window_at(path: &Path, command: Option<String>, cwd: Option<PathBuf>) -> Result<bool> {
+    // 前回の UmiTerm が残したソケットには接続できない
+    let Ok(stream) = private_socket::connect(path) else { return Ok(false) };
+    match exchange(&stream, &Request::NewWindow { cwd, command })? {
+        Reply::Pane(id) => {
+            log::info!("起動中の UmiTerm で新しいウィンドウを開きました（ペイン {}）", id);
+            Ok(true)
+        }
+        Reply::Error(message) => bail!("起動中の UmiTerm でウィンドウを開けません: {}", message),
+        reply => bail!("UmiTerm の応答が正しくありません: {:?}", reply),
+    }
+}
+
+/// `umiterm cli` を実行する
+pub fn run_cli(action: &CliAction) -> Result<()> {
+    if *action == CliAction::Subscribe {
+        return subscribe_to(&socket_path(), io::stdout().lock());
+    }
+    let reply = send(&request(action, default_pane())?)?;
+    match (action, reply) {
+        (_, Reply::Error(message)) => bail!("{}", message),
+        (CliAction::ListPanes { json: true }, Reply::Panes(panes)) => println!("{}", serde_json::to_string_pretty(&panes)?),
+        (CliAction::ListPanes { json: false }, Reply::Panes(panes)) => print!("{}", format_panes(&panes)),
+        (_, Reply::Pane(id)) => println!("{}", id),
+        (_, Reply::Text(text)) => println!("{}", text),
+        (_, Reply::Ok) => {}
+        (_, reply) => bail!("UmiTerm の応答が正しくありません: {:?}", reply),
+    }
+    Ok(())
+}
+
+/// イベントを購読し、届いた JSON の行をそのまま out に書く（UmiTerm が終了するまで）
+fn subscribe_to(path: &Path, mut out: impl Write) -> Result<()> {
+    let stream = private_socket::connect(path).context("起動中の UmiTerm に接続できません")?;
+    write_line(&stream, &Request::Subscribe)?;
+    let mut lines = BufReader::new(&stream).lines();
+    let first = lines.next().context("UmiTerm が応答しません")??;
+    match serde_json::from_str(&first).context("UmiTerm の応答が正しくありません")? {
+        Reply::Ok => {}
+        Reply::Error(message) => bail!("{}", message),
+        reply => bail!("UmiTerm の応答が正しくありません: {:?}", reply),
+    }
+    for line in lines {
+        writeln!(out, "{}", line?)?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+/// `--pane-id` を省略したときのペイン（ペインの中で実行したならそのペイン）
+fn default_pane() -> Option<u64> {
+    std::env::var(PANE_ENV).ok()?.parse().ok()
+}
+
+/// 操作を要求にする（相対パスは今の作業ディレクトリから、send-text の文字列がなければ標準入力）
+fn request(action: &CliAction, default_pane: Option<u64>) -> Result<Request> {
+    let pane = |pane_id: &Option<u64>| pane_id.or(default_pane);
+    Ok(match action {
+        CliAction::ListPanes { .. } => Request::ListPanes,
+        CliAction::SplitPane { pane_id, horizontal, cwd, command } => Request::SplitPane {
+            pane: pane(pane_id),
+            horizontal: *horizontal,
+            cwd: cwd.as_ref().map(|dir| std::env::current_dir().map(|cwd| cwd.join(dir)).unwrap_or_else(|_| dir.clone())),
+            command: (!command.is_empty()).then(|| command.iter().map(|word| shell_escape(word)).collect::<Vec<_>>().join(" ")),
+        },
+        CliAction::SendText { pane_id, no_paste, text } => {
+            let text = match text {
+                Some(text) => text.clone(),
+                None => {
+                    let mut text = String::new();
+                    io::stdin().read_to_string(&mut text).context("標準入力を読めません")?;
+                    text
+                }
+            };
+            Request::SendText { pane: pane(pane_id), text, paste: !no_paste }
+        }
+        CliAction::Activate { pane_id } => Request::Activate { pane: pane(pane_id) },
+        CliAction::GetText { pane_id, scrollback } => Request::GetText { pane: pane(pane_id), scrollback: *scrollback },
+        CliAction::Subscribe => Request::Subscribe,
+    })
+}
+
+/// ペインの一覧を表にする
+fn format_panes(panes: &[PaneInfo]) -> String {
+    let mut text = format!("{:<8}{:<5}{:<6}{:<9}{:<24}{}\n", "WINDOW", "TAB", "PANE", "SIZE", "TITLE", "CWD");
+    for info in panes {
+        let size = format!("{}x{}", info.cols, info.rows);
+        let pane = format!("{}{}", info.pane, if info.focused { "*" } else { "" });
+        text += &format!(
+            "{:<8}{:<5}{:<6}{:<9}{:<24}{}\n",
+            info.window,
+            info.tab,
+            pane,
+            size,
+            info.title,
+            info.cwd.display()
+        );
+    }
+    text
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol() {
+        let request = Request::SendText { pane: Some(3), text: "ls\r".into(), paste: false };
+        let json = serde_json::to_string(&request).unwrap();
+        assert_eq!(json, r#"{"type":"send-text","pane":3,"text":"ls\r","paste":false}"#);
+        assert_eq!(serde_json::from_str::<Request>(&json).unwrap(), request);
+        assert_eq!(serde_json::from_str::<Request>(r#"{"type":"list-panes"}"#).unwrap(), Request::ListPanes);
+        assert!(serde_json::from_str::<Request>(r#"{"type":"fly"}"#).is_err());
+
+        assert_eq!(serde_json::to_string(&Reply::Pane(4)).unwrap(), r#"{"pane":4}"#);
+        assert_eq!(serde_json::to_string(&Reply::Ok).unwrap(), r#""ok""#);
+    }
+
+    #[test]
+    fn test_request() {
+        // --pane-id がなければペインの中のペイン、コマンドはシェル用にクォート
+        let split = CliAction::SplitPane {
+            pane_id: None,
+            horizontal: true,
+            cwd: Some("/tmp".into()),
+            command: vec!["tail".into(), "-f".into(), "my log".into()],
+        };
+        assert_eq!(
+            request(&split, Some(7)).unwrap(),
+            Request::SplitPane {
+                pane: Some(7),
+                horizontal: true,
+                cwd: Some("/tmp".into()),
+                command: Some("tail -f 'my log'".into()),
+            }
+        );
+        let send = CliAction::SendText { pane_id: Some(2), no_paste: true, text: Some("make\r".into()) };
+        assert_eq!(
+            request(&send, Some(7)).unwrap(),
+            Request::SendText { pane: Some(2), text: "make\r".into(), paste: false }
+        );
+    }
+
+    #[test]
+    fn test_serve() {
+        let path = std::env::temp_dir().join(format!("umiterm-control-test-{}.sock", std::process::id()));
+        let server = serve_at(path.clone(), |pending: Pending| {
+            let reply = match &pending.request {
+                Request::GetText { pane, .. } => Reply::Text(format!("pane {:?}", pane)),
+                _ => Reply::Error("unsupported".into()),
+            };
+            pending.reply(reply);
+        })
+        .unwrap()
+        .unwrap();
+
+        // 2つ目は待ち受けない
+        assert!(serve_at(path.clone(), |_| {}).unwrap().is_none());
+        assert!(open_window_at(&path, None, None).is_err());
+        let get_text = Request::GetText { pane: Some(1), scrollback: false };
+        assert_eq!(send_to(&path, &get_text).unwrap(), Reply::Text("pane Some(1)".into()));
+        assert_eq!(send_to(&path, &Request::ListPanes).unwrap(), Reply::Error("unsupported".into()));
+
+        // 破棄するとソケットを消す
+        drop(server);
+        assert!(!path.exists());
+        assert!(send_to(&path, &Request::ListPanes).is_err());
+        assert!(!open_window_at(&path, None, None).unwrap());
+    }
+
+    #[test]
+    fn test_open_window() {
+        let path = std::env::temp_dir().join(format!("umiterm-control-window-{}.sock", std::process::id()));
+        let (opened, received) = crossbeam_channel::unbounded();
+        let _server = serve_at(path.clone(), move |pending: Pending| {
+            opened.send(pending.request.clone()).unwrap();
+            pending.reply(Reply::Pane(5));
+        })
+        .unwrap()
+        .unwrap();
+
+        // 起動中の UmiTerm にコマンドと作業ディレクトリを渡す
+        assert!(open_window_at(&path, Some("htop".into()), Some("/tmp".into())).unwrap());
+        assert_eq!(
+            received.recv().unwrap(),
+            Request::NewWindow { cwd: Some("/tmp".into()), command: Some("htop".into()) }
+        );
+        assert!(open_window_at(&path, None, None).unwrap());
+        assert_eq!(received.recv().unwrap(), Request::NewWindow { cwd: None, command: None });
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let path = std::env::temp_dir().join(format!("umiterm-control-events-{}.sock", std::process::id()));
+        let _server = serve_at(path.clone(), |_| {}).unwrap().unwrap();
+        let stream = UnixStream::connect(&path).unwrap();
+        write_line(&stream, &Request::Subscribe).unwrap();
+        let mut lines = BufReader::new(&stream).lines();
+        assert_eq!(lines.next().unwrap().unwrap(), r#""ok""#);
+
+        // ほかのテストのペインのイベントも届くので、このテストのイベントだけを見る
+        let bell = events::Event::Bell { pane: u64::MAX - 1 };
+        events::emit(bell.clone());
+        let json = serde_json::to_string(&bell).unwrap();
+        assert!(lines.any(|line| line.unwrap() == json));
+    }
+
+    #[test]
+    fn test_format_panes() {
+        let info = PaneInfo {
+            window: 1,
+            tab: 0,
+            pane: 3,
+            title: "zsh".into(),
+            cwd: "/tmp".into(),
+            cols: 80,
+            rows: 24,
+            focused: true,
+        };
+        let text = format_panes(&[info]);
+        assert_eq!(text.lines().nth(1), Some("1       0    3*    80x24    zsh                     /tmp"));
+    }
+}
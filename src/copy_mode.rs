@@ -0,0 +1,684 @@
+//! コピーモード（vi 風のキー操作で履歴を選択してコピー）
+//!
+//! ペインの履歴（スクロールバックと画面）を取り込み、仮想カーソルを
+//! h/j/k/l/w/b で動かし、v で選択を始めて y でコピーする
+//! マウスを使わずにスクロールバックの出力を選べる
+//!
+//! 取り込んだ行の番号は取り込んだときの history_cells の番号。コピーモードの間も出力は続き、
+//! スクロールバックの古い行が捨てられると番号がずれるため、ターミナルに渡すときは live_line で直す
+
+use crate::terminal::{Terminal, DEFAULT_WORD_SEPARATORS};
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 位置と文字の種類
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 履歴上の位置（行が先なので、比較すると前後関係になる）
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Point {
+    /// 履歴の行番号（古い順）
+    pub line: usize,
+    /// セルの列（全角文字は左側のセル）
+    pub col: usize,
+}
+
+impl Point {
+    pub fn new(line: usize, col: usize) -> Self {
+        Self { line, col }
+    }
+}
+
+/// 選択の種類
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectKind {
+    /// 文字単位（v）
+    Char,
+    /// 行単位（V）
+    Line,
+}
+
+/// 単語移動で使う文字の種類
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Space,
+    Word,
+    /// 単語の区切りの文字（続けば一つの単語）
+    Punct,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// コピーモード
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// コピーモードの状態
+pub struct CopyMode {
+    /// 取り込んだ履歴の行（古い順、セル単位で全角文字の後ろの空きは '\0'）
+    lines: Vec<Vec<char>>,
+    /// 仮想カーソル
+    cursor: Point,
+    /// 上下に移動するときに戻りたい列
+    want_col: usize,
+    /// 選択の起点と種類
+    anchor: Option<(Point, SelectKind)>,
+    /// 入力中の検索（文字列と、下へ探すか）
+    pub search_input: Option<(String, bool)>,
+    /// 最後に確定した検索（n / N で繰り返す）
+    last_search: Option<(String, bool)>,
+    /// 最後の検索で見つからなかったか
+    pub not_found: bool,
+    /// 単語の区切りとみなす文字（ダブルクリックの選択と同じ）
+    word_separators: String,
+    /// 取り込んだときの history_cells の0行目の通し番号
+    origin: u64,
+}
+
+impl CopyMode {
+    /// 履歴の行を取り込み、カーソルを指定の位置に置いて始める
+    pub fn new(lines: Vec<Vec<char>>, cursor: Point) -> Self {
+        let mut mode = Self {
+            lines,
+            cursor,
+            want_col: cursor.col,
+            anchor: None,
+            search_input: None,
+            last_search: None,
+            not_found: false,
+            word_separators: DEFAULT_WORD_SEPARATORS.to_string(),
+            origin: 0,
+        };
+        mode.set_cursor(cursor);
+        mode
+    }
+
+    /// 単語の移動で区切りとみなす文字を変える
+    pub fn with_word_separators(mut self, separators: &str) -> Self {
+        self.word_separators = separators.to_string();
+        self
+    }
+
+    /// 取り込んだ行の0行目の通し番号（Terminal::history_origin）を覚える
+    pub fn with_origin(mut self, origin: u64) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// 取り込んだ行の番号を、0行目の通し番号が origin の今の history_cells の番号に直す
+    /// （そのあとスクロールバックから捨てられた行は None）
+    pub fn live_line(&self, line: usize, origin: u64) -> Option<usize> {
+        let shift = usize::try_from(origin.checked_sub(self.origin)?).ok()?;
+        line.checked_sub(shift)
+    }
+
+    /// 仮想カーソルの行が画面に入るようにターミナルの表示位置を動かす（行が捨てられていれば何もしない）
+    pub fn reveal_cursor(&self, terminal: &mut Terminal) {
+        if let Some(line) = self.live_line(self.cursor.line, terminal.history_origin()) {
+            terminal.reveal_history_line(line);
+        }
+    }
+
+    /// 仮想カーソルの位置
+    pub fn cursor(&self) -> Point {
+        self.cursor
+    }
+
+    /// ペインの最下段に出す状態
+    pub fn status(&self) -> String {
+        let position = format!("[{}/{}]", self.cursor.line + 1, self.lines.len());
+        if let Some((query, forward)) = &self.search_input {
+            return format!("{}{}_", if *forward { '/' } else { '?' }, query);
+        }
+        let mode = match self.anchor {
+            Some((_, SelectKind::Char)) => "VISUAL",
+            Some((_, SelectKind::Line)) => "VISUAL LINE",
+            None => "COPY",
+        };
+        if self.not_found {
+            format!("{} (not found) {}", mode, position)
+        } else {
+            format!("{} {}", mode, position)
+        }
+    }
+
+    /// 指定の位置の文字（行の外は空白）
+    pub fn char_at(&self, point: Point) -> char {
+        self.lines
+            .get(point.line)
+            .and_then(|line| line.get(point.col))
+            .copied()
+            .unwrap_or(' ')
+    }
+
+    /// 単語の移動で使う文字の種類
+    fn char_class(&self, c: char) -> CharClass {
+        if c.is_whitespace() || c == '\0' {
+            CharClass::Space
+        } else if self.word_separators.contains(c) {
+            CharClass::Punct
+        } else {
+            CharClass::Word
+        }
+    }
+
+    // ───────────────────────────────────────────────────────────────────────
+    // カーソル移動
+    // ───────────────────────────────────────────────────────────────────────
+
+    /// 左へ1文字（h）
+    pub fn move_left(&mut self) {
+        if let Some(col) = (0..self.cursor.col).rev().find(|&col| !self.is_spacer(self.cursor.line, col)) {
+            self.set_cursor(Point::new(self.cursor.line, col));
+        }
+        self.want_col = self.cursor.col;
+    }
+
+    /// 右へ1文字（l、行の最後の文字で止まる）
+    pub fn move_right(&mut self) {
+        let last = self.last_col(self.cursor.line);
+        if let Some(col) = (self.cursor.col + 1..=last).find(|&col| !self.is_spacer(self.cursor.line, col)) {
+            self.set_cursor(Point::new(self.cursor.line, col));
+        }
+        self.want_col = self.cursor.col;
+    }
+
+    /// 上下に移動（j / k、正の値で下へ、端で止まる）
+    pub fn move_lines(&mut self, delta: isize) {
+        let last = self.lines.len().saturating_sub(1) as isize;
+        let line = (self.cursor.line as isize + delta).clamp(0, last) as usize;
+        let want_col = self.want_col;
+        self.set_cursor(Point::new(line, want_col));
+        self.want_col = want_col;
+    }
+
+    /// 行の先頭（0）
+    pub fn line_start(&mut self) {
+        self.set_cursor(Point::new(self.cursor.line, 0));
+        self.want_col = 0;
+    }
+
+    /// 行の最初の空白でない文字（^）
+    pub fn first_non_blank(&mut self) {
+        let line = self.cursor.line;
+        let col = (0..=self.last_col(line))
+            .find(|&col| self.char_class(self.char_at(Point::new(line, col))) != CharClass::Space)
+            .unwrap_or(0);
+        self.set_cursor(Point::new(line, col));
+        self.want_col = self.cursor.col;
+    }
+
+    /// 行の最後の文字（$）
+    pub fn line_end(&mut self) {
+        self.set_cursor(Point::new(self.cursor.line, self.last_col(self.cursor.line)));
+        self.want_col = self.cursor.col;
+    }
+
+    /// 履歴の先頭（g）
+    pub fn top(&mut self) {
+        self.set_cursor(Point::new(0, 0));
+        self.want_col = 0;
+    }
+
+    /// 履歴の末尾（G）
+    pub fn bottom(&mut self) {
+        self.set_cursor(Point::new(self.lines.len().saturating_sub(1), 0));
+        self.want_col = 0;
+    }
+
+    /// 次の単語の先頭（w、行をまたぐ）
+    pub fn word_forward(&mut self) {
+        let mut point = self.cursor;
+        let class = self.char_class(self.char_at(point));
+        // 同じ種類の文字を飛ばしてから空白を飛ばす（行の境目は空白と同じ扱い）
+        if class != CharClass::Space {
+            loop {
+                let Some(next) = self.next_point(point) else { return };
+                let crossed = next.line != point.line;
+                point = next;
+                if crossed || self.char_class(self.char_at(point)) != class {
+                    break;
+                }
+            }
+        }
+        while self.char_class(self.char_at(point)) == CharClass::Space {
+            let Some(next) = self.next_point(point) else { return };
+            point = next;
+        }
+        self.jump_to(point);
+    }
+
+    /// 前の単語の先頭（b、行をまたぐ）
+    pub fn word_backward(&mut self) {
+        let Some(mut point) = self.prev_point(self.cursor) else { return };
+        while self.char_class(self.char_at(point)) == CharClass::Space {
+            let Some(prev) = self.prev_point(point) else { return };
+            point = prev;
+        }
+        let class = self.char_class(self.char_at(point));
+        while let Some(prev) = self.prev_point(point) {
+            if prev.line != point.line || self.char_class(self.char_at(prev)) != class {
+                break;
+            }
+            point = prev;
+        }
+        self.jump_to(point);
+    }
+
+    /// 単語の末尾（e、行をまたぐ）
+    pub fn word_end(&mut self) {
+        let Some(mut point) = self.next_point(self.cursor) else { return };
+        while self.char_class(self.char_at(point)) == CharClass::Space {
+            let Some(next) = self.next_point(point) else { return };
+            point = next;
+        }
+        let class = self.char_class(self.char_at(point));
+        while let Some(next) = self.next_point(point) {
+            if next.line != point.line || self.char_class(self.char_at(next)) != class {
+                break;
+            }
+            point = next;
+        }
+        self.jump_to(point);
+    }
+
+    // ───────────────────────────────────────────────────────────────────────
+    // 選択とコピー
+    // ───────────────────────────────────────────────────────────────────────
+
+    /// 選択を始める（同じ種類で選択中なら解除、別の種類なら切り替え）
+    pub fn toggle_selection(&mut self, kind: SelectKind) {
+        self.anchor = match self.anchor {
+            Some((_, current)) if current == kind => None,
+            Some((anchor, _)) => Some((anchor, kind)),
+            None => Some((self.cursor, kind)),
+        };
+    }
+
+    /// 選択を解除
+    /// 戻り値: 選択中だったか
+    pub fn clear_selection(&mut self) -> bool {
+        self.anchor.take().is_some()
+    }
+
+    /// 選択範囲（前後を揃えた始点と終点、端を含む）
+    pub fn selection(&self) -> Option<(Point, Point, SelectKind)> {
+        let (anchor, kind) = self.anchor?;
+        let (start, end) = if anchor <= self.cursor { (anchor, self.cursor) } else { (self.cursor, anchor) };
+        Some((start, end, kind))
+    }
+
+    /// 選択範囲のテキスト（各行の末尾の空白は除き、行は改行でつなぐ）
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end, kind) = self.selection()?;
+        let text = (start.line..=end.line)
+            .map(|line| {
+                let cells = self.lines.get(line).map(Vec::as_slice).unwrap_or_default();
+                let (from, to) = match kind {
+                    SelectKind::Line => (0, cells.len()),
+                    SelectKind::Char => {
+                        let from = if line == start.line { start.col } else { 0 };
+                        let to = if line == end.line { self.char_end(end) } else { cells.len() };
+                        (from, to)
+                    }
+                };
+                cells[from.min(cells.len())..to.min(cells.len())]
+                    .iter()
+                    .filter(|c| **c != '\0')
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Some(text)
+    }
+
+    // ───────────────────────────────────────────────────────────────────────
+    // 検索
+    // ───────────────────────────────────────────────────────────────────────
+
+    /// 検索の入力を始める（/ で下へ、? で上へ）
+    pub fn start_search(&mut self, forward: bool) {
+        self.search_input = Some((String::new(), forward));
+        self.not_found = false;
+    }
+
+    /// 検索の入力に文字を足す
+    pub fn push_search(&mut self, text: &str) {
+        if let Some((query, _)) = &mut self.search_input {
+            query.push_str(text);
+        }
+    }
+
+    /// 検索の入力を1文字消す（空なら入力をやめる）
+    pub fn pop_search(&mut self) {
+        if let Some((query, _)) = &mut self.search_input {
+            if query.pop().is_none() {
+                self.search_input = None;
+            }
+        }
+    }
+
+    /// 検索の入力をやめる
+    pub fn cancel_search(&mut self) {
+        self.search_input = None;
+    }
+
+    /// 入力した検索を確定して、最初の一致へ移動
+    pub fn commit_search(&mut self) {
+        if let Some((query, forward)) = self.search_input.take() {
+            if !query.is_empty() {
+                self.last_search = Some((query, forward));
+                self.search_next(false);
+            }
+        }
+    }
+
+    /// 最後の検索を繰り返す（n、reverse が true なら逆向きの N）
+    pub fn search_next(&mut self, reverse: bool) {
+        let Some((query, forward)) = self.last_search.clone() else { return };
+        match self.find(&query, forward != reverse) {
+            Some(point) => {
+                self.not_found = false;
+                self.jump_to(point);
+            }
+            None => self.not_found = true,
+        }
+    }
+
+    /// カーソルの次（または前）の一致を探す（端まで行ったら反対の端から続ける）
+    fn find(&self, query: &str, forward: bool) -> Option<Point> {
+        let count = self.lines.len();
+        if count == 0 {
+            return None;
+        }
+        let cursor = self.cursor;
+        for step in 0..=count {
+            let line = if forward {
+                (cursor.line + step) % count
+            } else {
+                (cursor.line + count * 2 - step) % count
+            };
+            let matches = self.line_matches(line, query);
+            let found = match (forward, step) {
+                (true, 0) => matches.into_iter().find(|&col| col > cursor.col),
+                (false, 0) => matches.into_iter().rev().find(|&col| col < cursor.col),
+                (true, _) => matches.into_iter().next(),
+                (false, _) => matches.into_iter().next_back(),
+            };
+            if let Some(col) = found {
+                return Some(Point::new(line, col));
+            }
+        }
+        None
+    }
+
+    /// 行の中で文字列が一致する列（大文字を含むときだけ大文字・小文字を区別）
+    fn line_matches(&self, line: usize, query: &str) -> Vec<usize> {
+        let case_sensitive = query.chars().any(char::is_uppercase);
+        let normalize = |c: char| if case_sensitive { c } else { c.to_ascii_lowercase() };
+        let query: Vec<char> = query.chars().map(normalize).collect();
+        let (cols, chars): (Vec<usize>, Vec<char>) = self.lines[line]
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| **c != '\0')
+            .map(|(col, c)| (col, normalize(*c)))
+            .unzip();
+        if query.is_empty() || query.len() > chars.len() {
+            return Vec::new();
+        }
+        chars
+            .windows(query.len())
+            .enumerate()
+            .filter(|(_, window)| *window == query.as_slice())
+            .map(|(index, _)| cols[index])
+            .collect()
+    }
+
+    // ───────────────────────────────────────────────────────────────────────
+    // 内部処理
+    // ───────────────────────────────────────────────────────────────────────
+
+    /// カーソルを置く（行の最後の文字より右や、全角文字の後ろの空きには置かない）
+    fn set_cursor(&mut self, point: Point) {
+        let line = point.line.min(self.lines.len().saturating_sub(1));
+        let mut col = point.col.min(self.last_col(line));
+        while col > 0 && self.is_spacer(line, col) {
+            col -= 1;
+        }
+        self.cursor = Point::new(line, col);
+    }
+
+    /// 単語移動などで飛んだ先にカーソルを置く
+    fn jump_to(&mut self, point: Point) {
+        self.set_cursor(point);
+        self.want_col = self.cursor.col;
+    }
+
+    /// 行の最後の文字の列（空行は 0）
+    fn last_col(&self, line: usize) -> usize {
+        self.lines
+            .get(line)
+            .and_then(|cells| cells.iter().rposition(|c| *c != ' ' && *c != '\0'))
+            .unwrap_or(0)
+    }
+
+    /// 全角文字の後ろの空きか
+    fn is_spacer(&self, line: usize, col: usize) -> bool {
+        self.lines.get(line).and_then(|cells| cells.get(col)) == Some(&'\0')
+    }
+
+    /// 文字の右端の次の列（全角文字は2セル分）
+    pub fn char_end(&self, point: Point) -> usize {
+        let mut end = point.col + 1;
+        while self.is_spacer(point.line, end) {
+            end += 1;
+        }
+        end
+    }
+
+    /// 次の文字の位置（行末の次は次の行の先頭）
+    fn next_point(&self, point: Point) -> Option<Point> {
+        let len = self.lines.get(point.line)?.len();
+        let mut col = point.col + 1;
+        while col < len && self.is_spacer(point.line, col) {
+            col += 1;
+        }
+        if col < len {
+            Some(Point::new(point.line, col))
+        } else if point.line + 1 < self.lines.len() {
+            Some(Point::new(point.line + 1, 0))
+        } else {
+            None
+        }
+    }
+
+    /// 前の文字の位置（行頭の前は前の行の末尾）
+    fn prev_point(&self, point: Point) -> Option<Point> {
+        if let Some(col) = (0..point.col).rev().find(|&col| !self.is_spacer(point.line, col)) {
+            return Some(Point::new(point.line, col));
+        }
+        let line = point.line.checked_sub(1)?;
+        let len = self.lines[line].len();
+        let col = (0..len).rev().find(|&col| !self.is_spacer(line, col)).unwrap_or(0);
+        Some(Point::new(line, col))
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 表示
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// コピーモードの仮想カーソルの表示（レンダラーに渡す）
+pub struct CopyCursor {
+    /// ペインのターミナル部分の矩形
+    pub rect: crate::pane::Rect,
+    /// 画面上の列と行
+    pub col: usize,
+    pub row: usize,
+    /// カーソル位置の文字
+    pub character: char,
+    /// ペインの最下段に出す状態（モード名、検索の入力など）
+    pub status: String,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scrollback::Limits;
+
+    fn lines(text: &[&str]) -> Vec<Vec<char>> {
+        text.iter().map(|line| format!("{:<12}", line).chars().collect()).collect()
+    }
+
+    #[test]
+    fn test_motions() {
+        let mut mode = CopyMode::new(lines(&["$ cargo build", "foo.bar(baz)", "", "end"]), Point::new(3, 0))
+            .with_word_separators(".()");
+
+        // 行の末尾より右には行かない
+        mode.move_right();
+        mode.move_right();
+        mode.move_right();
+        assert_eq!(mode.cursor(), Point::new(3, 2));
+
+        // 上下の移動は元の列を覚えている（空行では先頭）
+        mode.move_lines(-1);
+        assert_eq!(mode.cursor(), Point::new(2, 0));
+        mode.move_lines(-1);
+        assert_eq!(mode.cursor(), Point::new(1, 2));
+        mode.move_lines(-10);
+        assert_eq!(mode.cursor(), Point::new(0, 2));
+
+        // 単語の移動（区切りの文字は別の単語）
+        mode.word_forward();
+        assert_eq!(mode.cursor(), Point::new(0, 8));
+        mode.word_forward();
+        assert_eq!(mode.cursor(), Point::new(1, 0));
+        mode.word_forward();
+        assert_eq!(mode.cursor(), Point::new(1, 3));
+        mode.word_end();
+        assert_eq!(mode.cursor(), Point::new(1, 6));
+        mode.word_backward();
+        assert_eq!(mode.cursor(), Point::new(1, 4));
+        mode.word_backward();
+        assert_eq!(mode.cursor(), Point::new(1, 3));
+        mode.word_backward();
+        assert_eq!(mode.cursor(), Point::new(1, 0));
+        mode.word_backward();
+        assert_eq!(mode.cursor(), Point::new(0, 8));
+
+        mode.line_end();
+        assert_eq!(mode.cursor(), Point::new(0, 12));
+        mode.first_non_blank();
+        assert_eq!(mode.cursor(), Point::new(0, 0));
+        mode.bottom();
+        assert_eq!(mode.cursor(), Point::new(3, 0));
+
+        // 既定の区切りでは . は単語の一部
+        let mut mode = CopyMode::new(lines(&["foo.bar(baz)"]), Point::new(0, 0));
+        mode.word_forward();
+        assert_eq!(mode.cursor(), Point::new(0, 7));
+        mode.word_end();
+        assert_eq!(mode.cursor(), Point::new(0, 10));
+    }
+
+    #[test]
+    fn test_wide_chars() {
+        // 全角文字の後ろの空きにはカーソルを置かない
+        let cells = vec![vec!['あ', '\0', 'い', '\0', 'x']];
+        let mut mode = CopyMode::new(cells, Point::new(0, 1));
+        assert_eq!(mode.cursor(), Point::new(0, 0));
+        mode.move_right();
+        assert_eq!(mode.cursor(), Point::new(0, 2));
+
+        mode.toggle_selection(SelectKind::Char);
+        mode.move_left();
+        assert_eq!(mode.selected_text().as_deref(), Some("あい"));
+    }
+
+    #[test]
+    fn test_selection() {
+        let mut mode = CopyMode::new(lines(&["one two", "three four"]), Point::new(0, 4));
+        assert_eq!(mode.selected_text(), None);
+
+        // 文字単位（起点より前に戻っても両端を含む）
+        mode.toggle_selection(SelectKind::Char);
+        mode.move_lines(1);
+        assert_eq!(mode.selected_text().as_deref(), Some("two\nthree"));
+        mode.move_lines(-1);
+        mode.line_start();
+        assert_eq!(mode.selected_text().as_deref(), Some("one t"));
+
+        // 行単位に切り替え、同じ種類でもう一度押すと解除
+        mode.toggle_selection(SelectKind::Line);
+        mode.move_lines(1);
+        assert_eq!(mode.selected_text().as_deref(), Some("one two\nthree four"));
+        mode.toggle_selection(SelectKind::Line);
+        assert_eq!(mode.selection(), None);
+    }
+
+    #[test]
+    fn test_search() {
+        let mut mode = CopyMode::new(lines(&["error: a", "ok", "Error: b", "error: c"]), Point::new(3, 0));
+
+        // 上へ探し、端まで行ったら反対の端から
+        mode.start_search(false);
+        mode.push_search("error");
+        mode.commit_search();
+        assert_eq!(mode.cursor(), Point::new(2, 0));
+        mode.search_next(false);
+        assert_eq!(mode.cursor(), Point::new(0, 0));
+        mode.search_next(false);
+        assert_eq!(mode.cursor(), Point::new(3, 0));
+        mode.search_next(true);
+        assert_eq!(mode.cursor(), Point::new(0, 0));
+
+        // 大文字を含むと区別する
+        mode.start_search(true);
+        mode.push_search("Error");
+        mode.commit_search();
+        assert_eq!(mode.cursor(), Point::new(2, 0));
+
+        mode.start_search(true);
+        mode.push_search("missing");
+        mode.commit_search();
+        assert!(mode.not_found);
+        assert_eq!(mode.cursor(), Point::new(2, 0));
+    }
+
+    #[test]
+    fn test_output_during_copy_mode() {
+        let feed = |term: &mut Terminal, text: &[&str]| {
+            for line in text {
+                line.chars().for_each(|c| term.input_char(c));
+                term.linefeed();
+                term.carriage_return();
+            }
+        };
+        let mut term = Terminal::new(4, 2);
+        term.set_scrollback_limits(Limits { max_bytes: 1 << 20, max_lines: Some(3), spill: false });
+        feed(&mut term, &["a0", "a1", "a2", "a3", "a4"]);
+
+        // "a2" を選んでいる間に出力が続き、古い行が捨てられる
+        let mut mode = CopyMode::new(term.history_cells(), Point::new(1, 0)).with_origin(term.history_origin());
+        assert_eq!(mode.char_at(Point::new(1, 1)), '2');
+        mode.toggle_selection(SelectKind::Line);
+        feed(&mut term, &["b0"]);
+        assert_ne!(term.history_cells()[1][..2], ['a', '2']);
+
+        // 今の番号に直すと同じ行を指す
+        let line = mode.live_line(1, term.history_origin()).unwrap();
+        assert_eq!(term.history_cells()[line][..2], ['a', '2']);
+        mode.reveal_cursor(&mut term);
+        assert_eq!(term.history_line_row(line), Some(0));
+        assert_eq!(mode.selected_text().as_deref(), Some("a2"));
+
+        // 捨てられた行は指さない
+        feed(&mut term, &["c0", "c1"]);
+        assert_eq!(mode.live_line(1, term.history_origin()), None);
+        mode.reveal_cursor(&mut term);
+    }
+}
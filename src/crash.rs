@@ -0,0 +1,472 @@
+//! クラッシュ時の後始末モジュール
+//!
+//! パニックしたら、クラッシュレポート（メッセージ・場所・バックトレース）と、
+//! ウィンドウ・タブ・ペインの分割・作業ディレクトリ・スクロールバックの末尾をディスクに書き出す
+//! 次に起動したとき、書き出したセッションが残っていれば元に戻すか尋ねる
+//!
+//! パニックしたスレッドがウィンドウやターミナルを触っている途中でも書き出せるよう、
+//! メインスレッドが定期的にレイアウトとターミナルの参照を預けておき、フックはそれだけを使う
+
+use std::fs;
+use std::io::{self, Write};
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::pane::{Pane, PaneId, PaneLayout};
+use crate::pty::PaneCommand;
+use crate::terminal::Terminal;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 定数
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 残すスクロールバックの末尾の行数（ペインごと）
+const SCROLLBACK_TAIL: usize = 200;
+
+/// 預けたレイアウトを更新する間隔
+pub const TRACK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// フックがロックを待つ時間（パニックしたスレッドが持っていれば諦める）
+const LOCK_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// 書き出すセッションのファイル名
+const SESSION_FILE: &str = "crash-session.json";
+
+// ═══════════════════════════════════════════════════════════════════════════
+// セッション
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// ペインの分割（PaneLayout と同じ形で、ペインの代わりに P を持つ）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Layout<P> {
+    Pane(P),
+    HSplit { left: Box<Layout<P>>, right: Box<Layout<P>>, ratio: f32 },
+    VSplit { top: Box<Layout<P>>, bottom: Box<Layout<P>>, ratio: f32 },
+}
+
+impl<P> Layout<P> {
+    /// ペインのレイアウトから作る（pane が None を返したペインは除き、1つも残らなければ None）
+    pub fn from_pane_layout(layout: &PaneLayout, pane: &mut impl FnMut(PaneId) -> Option<P>) -> Option<Self> {
+        match layout {
+            PaneLayout::Single(id) => pane(*id).map(Self::Pane),
+            PaneLayout::HSplit { left, right, ratio } => {
+                match (Self::from_pane_layout(left, pane), Self::from_pane_layout(right, pane)) {
+                    (Some(left), Some(right)) => Some(Self::HSplit { left: Box::new(left), right: Box::new(right), ratio: *ratio }),
+                    (one, other) => one.or(other),
+                }
+            }
+            PaneLayout::VSplit { top, bottom, ratio } => {
+                match (Self::from_pane_layout(top, pane), Self::from_pane_layout(bottom, pane)) {
+                    (Some(top), Some(bottom)) => Some(Self::VSplit { top: Box::new(top), bottom: Box::new(bottom), ratio: *ratio }),
+                    (one, other) => one.or(other),
+                }
+            }
+        }
+    }
+
+    /// ペインを変換する（失敗したらそこで止める）
+    pub fn try_map<Q>(self, f: &mut impl FnMut(P) -> Result<Q>) -> Result<Layout<Q>> {
+        Ok(match self {
+            Self::Pane(pane) => Layout::Pane(f(pane)?),
+            Self::HSplit { left, right, ratio } => {
+                Layout::HSplit { left: Box::new(left.try_map(f)?), right: Box::new(right.try_map(f)?), ratio }
+            }
+            Self::VSplit { top, bottom, ratio } => {
+                Layout::VSplit { top: Box::new(top.try_map(f)?), bottom: Box::new(bottom.try_map(f)?), ratio }
+            }
+        })
+    }
+
+    /// ペインを参照から変換したレイアウト
+    fn map_ref<Q>(&self, f: &mut impl FnMut(&P) -> Q) -> Layout<Q> {
+        match self {
+            Self::Pane(pane) => Layout::Pane(f(pane)),
+            Self::HSplit { left, right, ratio } => {
+                Layout::HSplit { left: Box::new(left.map_ref(f)), right: Box::new(right.map_ref(f)), ratio: *ratio }
+            }
+            Self::VSplit { top, bottom, ratio } => {
+                Layout::VSplit { top: Box::new(top.map_ref(f)), bottom: Box::new(bottom.map_ref(f)), ratio: *ratio }
+            }
+        }
+    }
+
+    /// ペインの数
+    pub fn pane_count(&self) -> usize {
+        match self {
+            Self::Pane(_) => 1,
+            Self::HSplit { left, right, .. } => left.pane_count() + right.pane_count(),
+            Self::VSplit { top, bottom, .. } => top.pane_count() + bottom.pane_count(),
+        }
+    }
+}
+
+impl Layout<PaneId> {
+    /// ペインのレイアウトにする
+    pub fn into_pane_layout(self) -> PaneLayout {
+        match self {
+            Self::Pane(id) => PaneLayout::Single(id),
+            Self::HSplit { left, right, ratio } => {
+                PaneLayout::HSplit { left: Box::new(left.into_pane_layout()), right: Box::new(right.into_pane_layout()), ratio }
+            }
+            Self::VSplit { top, bottom, ratio } => {
+                PaneLayout::VSplit { top: Box::new(top.into_pane_layout()), bottom: Box::new(bottom.into_pane_layout()), ratio }
+            }
+        }
+    }
+}
+
+/// 1つのウィンドウ
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Window<P> {
+    /// タブごとのペインの分割
+    pub tabs: Vec<Layout<P>>,
+    /// 表示中のタブ
+    pub active_tab: usize,
+}
+
+/// 書き出した1つのペイン
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedPane {
+    /// 起動したコマンド
+    pub command: PaneCommand,
+    /// 作業ディレクトリ
+    pub cwd: Option<PathBuf>,
+    /// スクロールバックと画面の末尾の行（古い順）
+    pub scrollback: Vec<String>,
+}
+
+/// 書き出したセッション
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    /// ウィンドウ
+    pub windows: Vec<Window<SavedPane>>,
+    /// クラッシュレポート
+    pub report: Option<PathBuf>,
+}
+
+impl Session {
+    /// 戻すかを尋ねるダイアログの行
+    pub fn dialog_lines(&self) -> Vec<String> {
+        let tabs: Vec<&Layout<SavedPane>> = self.windows.iter().flat_map(|window| &window.tabs).collect();
+        let panes: usize = tabs.iter().map(|tab| tab.pane_count()).sum();
+        let mut lines = vec![
+            "UmiTerm crashed last time.".to_string(),
+            format!("Restore {} windows, {} tabs, {} panes?", self.windows.len(), tabs.len(), panes),
+        ];
+        if let Some(report) = &self.report {
+            lines.push(format!("Report: {}", report.display()));
+        }
+        lines.push("Enter: restore   Esc: discard".to_string());
+        lines
+    }
+}
+
+/// 動いている1つのペイン（フックが書き出すときにターミナルを読む）
+pub struct LivePane {
+    /// ターミナル状態
+    pub terminal: Arc<Mutex<Terminal>>,
+    /// 起動したコマンド
+    pub command: PaneCommand,
+    /// 起動したときに指定した作業ディレクトリ
+    pub cwd: Option<PathBuf>,
+}
+
+impl LivePane {
+    /// 書き出す形にする（ターミナルを読めなければ作業ディレクトリは起動時のもの、スクロールバックはなし）
+    fn save(&self) -> SavedPane {
+        let Some(terminal) = self.terminal.try_lock_for(LOCK_TIMEOUT) else {
+            return SavedPane { command: self.command.clone(), cwd: self.cwd.clone(), scrollback: Vec::new() };
+        };
+        // OSC 7 で知らされていなければターミナルの作業ディレクトリはホームのままなので、起動時のものを使う
+        let cwd = match &self.cwd {
+            Some(cwd) if crate::config::home_dir().as_deref() == Some(terminal.cwd.as_path()) => cwd.clone(),
+            _ => terminal.cwd.clone(),
+        };
+        SavedPane { command: self.command.clone(), cwd: Some(cwd), scrollback: tail(terminal.history_text()) }
+    }
+}
+
+/// 履歴の末尾（最後の空行を除いて SCROLLBACK_TAIL 行まで）
+fn tail(mut lines: Vec<String>) -> Vec<String> {
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    let start = lines.len().saturating_sub(SCROLLBACK_TAIL);
+    lines.split_off(start)
+}
+
+/// フックのために預けたレイアウト
+static TRACKED: Mutex<Vec<Window<LivePane>>> = Mutex::new(Vec::new());
+
+/// 今のレイアウトを預ける（メインスレッドが TRACK_INTERVAL ごとに呼ぶ）
+pub fn track(windows: Vec<Window<LivePane>>) {
+    *TRACKED.lock() = windows;
+}
+
+/// ファイルの置き場所（`$XDG_DATA_HOME/umiterm`、なければ `~/.local/share/umiterm`）
+fn dir() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => crate::config::home_dir()?.join(".local/share"),
+    };
+    Some(dir.join("umiterm"))
+}
+
+/// 自分だけが使えるディレクトリを作る（Unix では 0700、スクロールバックや画面の文字を置くため）
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        builder.mode(0o700).create(dir)?;
+        // 前から広い権限で残っているディレクトリも絞る
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+    }
+    #[cfg(not(unix))]
+    builder.create(dir)
+}
+
+/// 自分だけが読めるファイルに書く（Unix では 0600）
+fn write_private(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let mut file = options.open(path)?;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        file.write_all(contents)
+    }
+    #[cfg(not(unix))]
+    options.open(path)?.write_all(contents)
+}
+
+/// セッションを書き出す
+fn write_session(dir: &Path, session: &Session) -> Result<PathBuf> {
+    create_private_dir(dir).with_context(|| format!("{} を作れません", dir.display()))?;
+    let path = dir.join(SESSION_FILE);
+    write_private(&path, &serde_json::to_vec(session)?).with_context(|| format!("{} に書き込めません", path.display()))?;
+    Ok(path)
+}
+
+/// 前回のクラッシュで書き出したセッションを取り出す（ファイルは消す）
+pub fn take_session() -> Option<Session> {
+    let path = dir()?.join(SESSION_FILE);
+    let text = fs::read(&path).ok()?;
+    fs::remove_file(&path).ok();
+    serde_json::from_slice(&text)
+        .inspect_err(|e| log::warn!("前回のセッションを読めません: {}: {}", path.display(), e))
+        .ok()
+}
+
+/// 正常に終了したので書き出したセッションを消す（メイン以外のスレッドのパニックで書いたものも）
+pub fn clear() {
+    if let Some(dir) = dir() {
+        fs::remove_file(dir.join(SESSION_FILE)).ok();
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// パニックフック
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 2つ目以降のパニック（フックの中のパニックも）では書き出さない
+static CRASHED: AtomicBool = AtomicBool::new(false);
+
+/// パニックフックを入れる（元のフックも呼ぶ）
+pub fn install() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        previous(info);
+        if CRASHED.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        save(info);
+    }));
+}
+
+/// クラッシュレポートとセッションを書き出す
+fn save(info: &PanicHookInfo) {
+    let Some(dir) = dir() else { return };
+    let thread = std::thread::current();
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "(不明)".to_string());
+    let location = info.location().map(|l| l.to_string()).unwrap_or_default();
+    let now = chrono::Local::now();
+    let text = report(
+        &now.format("%Y-%m-%d %H:%M:%S %z").to_string(),
+        thread.name().unwrap_or("<unnamed>"),
+        &location,
+        &message,
+        &std::backtrace::Backtrace::force_capture().to_string(),
+    );
+
+    let report_path = dir.join("crashes").join(format!("crash-{}.txt", now.format("%Y%m%d-%H%M%S")));
+    let report = create_private_dir(&dir.join("crashes"))
+        .and_then(|_| write_private(&report_path, text.as_bytes()))
+        .map(|_| report_path)
+        .inspect_err(|e| log::error!("クラッシュレポートを書き出せません: {}", e))
+        .ok();
+    if let Some(path) = &report {
+        log::error!("クラッシュレポートを書き出しました: {}", path.display());
+    }
+
+    let Some(tracked) = TRACKED.try_lock_for(LOCK_TIMEOUT) else { return };
+    let windows: Vec<Window<SavedPane>> = tracked
+        .iter()
+        .map(|window| Window {
+            tabs: window.tabs.iter().map(|tab| tab.map_ref(&mut LivePane::save)).collect(),
+            active_tab: window.active_tab,
+        })
+        .filter(|window| !window.tabs.is_empty())
+        .collect();
+    if windows.is_empty() {
+        return;
+    }
+    match write_session(&dir, &Session { windows, report }) {
+        Ok(path) => log::error!("次に起動したときに戻せるようセッションを書き出しました: {}", path.display()),
+        Err(e) => log::error!("セッションを書き出せません: {:#}", e),
+    }
+}
+
+/// クラッシュレポートの本文
+fn report(time: &str, thread: &str, location: &str, message: &str, backtrace: &str) -> String {
+    format!(
+        "UmiTerm {} crashed at {}\n{} {}\n\nthread '{}' panicked at {}:\n{}\n\nbacktrace:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        time,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        thread,
+        location,
+        message,
+        backtrace,
+    )
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 復元
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 書き出したタブのペインを起動し直す（大きさはウィンドウに合わせて後でリサイズされる）
+///
+/// 作業ディレクトリがなくなっていればホームで、コマンドを起動できなければシェルで開き、
+/// 残しておいたスクロールバックの末尾を先に書いておく
+/// 起動したペインは戻せたときだけ panes に加える
+pub fn restore_tab(layout: Layout<SavedPane>, panes: &mut Vec<Pane>) -> Result<PaneLayout> {
+    let (cols, rows) = (80, 24);
+    let mut spawned = Vec::new();
+    let layout = layout.try_map(&mut |saved| {
+        let cwd = saved.cwd.filter(|cwd| cwd.is_dir());
+        let mut pane = Pane::with_cwd(cols, rows, &saved.command, cwd.as_deref())
+            .inspect_err(|e| log::warn!("{:?} を起動できないのでシェルで開きます: {}", saved.command, e))
+            .or_else(|_| Pane::with_cwd(cols, rows, &PaneCommand::Shell, cwd.as_deref()))?;
+        pane.write_restored(&saved.scrollback);
+        let id = pane.id;
+        spawned.push(pane);
+        Ok(id)
+    })?;
+    panes.append(&mut spawned);
+    Ok(layout.into_pane_layout())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(name: &str) -> SavedPane {
+        SavedPane { command: PaneCommand::Run(name.into()), cwd: Some("/tmp".into()), scrollback: vec!["$ ls".into()] }
+    }
+
+    #[test]
+    fn test_from_pane_layout() {
+        let (a, b, c) = (PaneId::new(), PaneId::new(), PaneId::new());
+        let mut layout = PaneLayout::single(a);
+        layout.split_horizontal(a, b);
+        layout.split_vertical(b, c);
+
+        let all = Layout::from_pane_layout(&layout, &mut Some).unwrap();
+        assert_eq!(all.pane_count(), 3);
+        assert_eq!(all.clone().into_pane_layout().all_pane_ids(), layout.all_pane_ids());
+
+        // なくなったペインは分割ごと除く
+        let some = Layout::from_pane_layout(&layout, &mut |id| (id != c).then_some(id)).unwrap();
+        assert_eq!(some.into_pane_layout().all_pane_ids(), vec![a, b]);
+        assert!(Layout::from_pane_layout(&layout, &mut |_| None::<PaneId>).is_none());
+    }
+
+    #[test]
+    fn test_session_roundtrip() {
+        let session = Session {
+            windows: vec![Window {
+                tabs: vec![
+                    Layout::Pane(saved("htop")),
+                    Layout::VSplit { top: Box::new(Layout::Pane(saved("a"))), bottom: Box::new(Layout::Pane(saved("b"))), ratio: 0.3 },
+                ],
+                active_tab: 1,
+            }],
+            report: Some("/tmp/crash.txt".into()),
+        };
+        let json = serde_json::to_vec(&session).unwrap();
+        assert_eq!(serde_json::from_slice::<Session>(&json).unwrap(), session);
+
+        // 自分だけが読めるように書き出す（前から広い権限で残っていたファイルも絞る）
+        let dir = std::env::temp_dir().join(format!("umiterm-crash-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(SESSION_FILE), b"{}").unwrap();
+        let path = write_session(&dir, &session).unwrap();
+        assert_eq!(serde_json::from_slice::<Session>(&fs::read(&path).unwrap()).unwrap(), session);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            session.dialog_lines(),
+            [
+                "UmiTerm crashed last time.",
+                "Restore 1 windows, 2 tabs, 3 panes?",
+                "Report: /tmp/crash.txt",
+                "Enter: restore   Esc: discard",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tail() {
+        let mut lines: Vec<String> = (0..300).map(|i| i.to_string()).collect();
+        lines.extend(["".into(), "".into()]);
+        let tail = tail(lines);
+        assert_eq!(tail.len(), SCROLLBACK_TAIL);
+        assert_eq!(tail.first().unwrap(), "100");
+        assert_eq!(tail.last().unwrap(), "299");
+    }
+
+    #[test]
+    fn test_report() {
+        let text = report("2026-01-01 00:00:00 +0900", "main", "src/renderer.rs:10:5", "index out of bounds", "0: main");
+        assert!(text.starts_with(&format!("UmiTerm {} crashed at 2026-01-01", env!("CARGO_PKG_VERSION"))));
+        assert!(text.contains("thread 'main' panicked at src/renderer.rs:10:5:\nindex out of bounds"));
+    }
+}
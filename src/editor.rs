@@ -0,0 +1,175 @@
+//! エディタで開く
+//!
+//! コンパイラや ripgrep が出力する `path/to/file.rs:123:5` の形を見つけ、
+//! 設定したコマンドのテンプレートでエディタを起動するコマンドラインを作る
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::config;
+
+/// エディタのコマンドの既定のテンプレート（`{editor}` は $EDITOR > nvim > vim）
+const DEFAULT_TEMPLATE: &str = "{editor} +{line} {file}";
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ファイルの位置
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// ファイルと行・列
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileLocation {
+    pub path: PathBuf,
+    pub line: u32,
+    pub col: Option<u32>,
+}
+
+/// `path:line` / `path:line:col` の形（行番号は必須）
+fn location_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"(?:~|\.{1,2})?/?(?:[\w.@+-]+/)*[\w.@+-]+:(\d+)(?::(\d+))?").expect("組み込みの正規表現")
+    })
+}
+
+/// 画面の行（セル単位、全角文字の後ろの空きは '\0'）の指定の列にあるファイルの位置
+///
+/// 相対パスは cwd から解決し、実在するファイルの場合だけ返す
+pub fn location_at(cells: &[char], col: usize, cwd: &Path) -> Option<FileLocation> {
+    let (cols, text): (Vec<usize>, String) = cells
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| **c != '\0')
+        .map(|(col, c)| (col, *c))
+        .unzip();
+    let col_at = |byte: usize| cols.get(text[..byte].chars().count()).copied().unwrap_or(cells.len());
+
+    let caps = location_regex()
+        .captures_iter(&text)
+        .find(|caps| {
+            let m = caps.get(0).expect("一致全体");
+            col_at(m.start()) <= col && col < col_at(m.end())
+        })?;
+    let whole = caps.get(0)?.as_str();
+    let line_match = caps.get(1)?;
+    let path = &whole[..line_match.start() - caps.get(0)?.start() - 1];
+    let path = resolve(path, cwd)?;
+    path.is_file().then(|| FileLocation {
+        path,
+        line: line_match.as_str().parse().unwrap_or(1),
+        col: caps.get(2).and_then(|m| m.as_str().parse().ok()),
+    })
+}
+
+/// パスを絶対パスにする（`~/` はホーム、相対パスは cwd から）
+fn resolve(path: &str, cwd: &Path) -> Option<PathBuf> {
+    match path.strip_prefix("~/") {
+        Some(rest) => std::env::var_os("HOME").map(|home| Path::new(&home).join(rest)),
+        None => Some(cwd.join(path)),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// コマンド
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 使うエディタ（$EDITOR > nvim > vim）
+pub fn default_editor() -> String {
+    std::env::var("EDITOR").unwrap_or_else(|_| {
+        // nvimがあればそちらを優先
+        if std::process::Command::new("which").arg("nvim").output()
+            .map(|o| o.status.success()).unwrap_or(false) {
+            "nvim".to_string()
+        } else {
+            "vim".to_string()
+        }
+    })
+}
+
+/// エディタのコマンドのテンプレート（環境変数 `UMITERM_EDITOR_COMMAND` で変更可能）
+///
+/// `{file}` `{line}` `{col}` `{editor}` を置き換える（例: `code -g {file}:{line}:{col}`）
+pub fn command_template() -> String {
+    config::var("UMITERM_EDITOR_COMMAND").unwrap_or_else(|| DEFAULT_TEMPLATE.to_string())
+}
+
+/// テンプレートからコマンドラインを作る（ファイル名はシェル用にクォートする）
+pub fn command_line(template: &str, location: &FileLocation, editor: &str) -> String {
+    template
+        .replace("{editor}", editor)
+        .replace("{file}", &shell_quote(&location.path.display().to_string()))
+        .replace("{line}", &location.line.to_string())
+        .replace("{col}", &location.col.unwrap_or(1).to_string())
+}
+
+/// シェルの単一引用符でくくる
+pub fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// シェルで特別な意味を持つ文字があるときだけ単一引用符でくくる
+pub fn shell_escape(text: &str) -> String {
+    let plain = !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '/' | '.' | '_' | '-' | '+' | '@' | ':' | ',' | '%' | '='));
+    if plain { text.to_string() } else { shell_quote(text) }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_location_at() {
+        let root = std::env::temp_dir().join(format!("umiterm-editor-{}", std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+
+        let cells: Vec<char> = "error at src/main.rs:12:5: expected `;` (see localhost:8080)".chars().collect();
+        let expected = FileLocation { path: root.join("src/main.rs"), line: 12, col: Some(5) };
+
+        // 一致の中ならどの列でもよい
+        assert_eq!(location_at(&cells, 9, &root), Some(expected.clone()));
+        assert_eq!(location_at(&cells, 22, &root), Some(expected));
+        assert_eq!(location_at(&cells, 5, &root), None);
+
+        // 実在しないファイルは開かない
+        let col = cells.iter().position(|c| *c == 'l').unwrap();
+        assert_eq!(location_at(&cells, col, &root), None);
+
+        // ripgrep の形（列なし）、全角文字の後ろ
+        let cells: Vec<char> = ['警', '\0', ' ', 's', 'r', 'c', '/', 'm', 'a', 'i', 'n', '.', 'r', 's', ':', '3', ':']
+            .into_iter()
+            .collect();
+        let found = location_at(&cells, 4, &root).unwrap();
+        assert_eq!((found.line, found.col), (3, None));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_command_line() {
+        let location = FileLocation { path: PathBuf::from("/work/it's.rs"), line: 7, col: None };
+        assert_eq!(command_line(DEFAULT_TEMPLATE, &location, "vim"), r"vim +7 '/work/it'\''s.rs'");
+        assert_eq!(
+            command_line("code -g {file}:{line}:{col}", &location, "vim"),
+            "code -g '/work/it'\\''s.rs':7:1"
+        );
+    }
+
+    #[test]
+    fn test_shell_escape() {
+        assert_eq!(shell_escape("/Users/me/src/main.rs"), "/Users/me/src/main.rs");
+        assert_eq!(shell_escape("/Users/me/書類/a-1.txt"), "/Users/me/書類/a-1.txt");
+        assert_eq!(shell_escape("/tmp/My File (1).png"), "'/tmp/My File (1).png'");
+        assert_eq!(shell_escape("~/x"), "'~/x'");
+        assert_eq!(shell_escape(""), "''");
+    }
+}
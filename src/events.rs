@@ -0,0 +1,119 @@
+//! イベントの配信モジュール
+//!
+//! ペインの作成・終了、タイトルの変化、ベル、コマンドの完了を購読者に配る
+//! `umiterm cli subscribe` が1行1つの JSON として受け取る（ステータスバーや自動化のため）
+//!
+//! ```text
+//! {"event":"pane-created","pane":4}
+//! {"event":"command-finished","pane":4,"exit_code":0,"duration_ms":1520}
+//! ```
+
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::pane::{FinishedCommand, PaneId};
+
+/// 購読者ごとに溜めておくイベントの数（読まない購読者のイベントはこれを超えると捨てる）
+const QUEUE: usize = 1024;
+
+/// 購読者
+static SUBSCRIBERS: Mutex<Vec<Sender<Event>>> = Mutex::new(Vec::new());
+
+/// 配るイベント
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    /// ペインを作った
+    PaneCreated { pane: u64 },
+    /// ペインを閉じた
+    PaneClosed { pane: u64 },
+    /// ペインのタイトル（OSC 0/2 か実行中のコマンド）が変わった
+    TitleChanged { pane: u64, title: String },
+    /// ペインでベルが鳴った
+    Bell { pane: u64 },
+    /// ペインのコマンドが終わった（OSC 133、終了コードはシェルが送らなければ null）
+    CommandFinished { pane: u64, exit_code: Option<i32>, duration_ms: u64 },
+}
+
+impl Event {
+    /// コマンドの完了
+    pub fn command_finished(pane: PaneId, finished: &FinishedCommand) -> Self {
+        Self::CommandFinished {
+            pane: pane.0,
+            exit_code: finished.exit_code,
+            duration_ms: finished.duration.as_millis() as u64,
+        }
+    }
+}
+
+/// イベントを購読する（受け取り側を破棄すると次のイベントで購読をやめる）
+pub fn subscribe() -> Receiver<Event> {
+    let (sender, receiver) = bounded(QUEUE);
+    SUBSCRIBERS.lock().push(sender);
+    receiver
+}
+
+/// 購読者がいるか（調べるのに手間のかかるイベントの前に確かめる）
+pub fn has_subscribers() -> bool {
+    !SUBSCRIBERS.lock().is_empty()
+}
+
+/// イベントを購読者に配る
+pub fn emit(event: Event) {
+    send_all(&mut SUBSCRIBERS.lock(), event);
+}
+
+/// 購読者に送り、受け取り側を破棄した購読者を外す
+fn send_all(subscribers: &mut Vec<Sender<Event>>, event: Event) {
+    subscribers.retain(|subscriber| match subscriber.try_send(event.clone()) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            log::debug!("イベントの購読者が読まないため捨てました: {:?}", event);
+            true
+        }
+        Err(TrySendError::Disconnected(_)) => false,
+    });
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_event_json() {
+        let event = Event::command_finished(
+            PaneId(4),
+            &FinishedCommand { exit_code: Some(1), duration: Duration::from_millis(1520) },
+        );
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(json, r#"{"event":"command-finished","pane":4,"exit_code":1,"duration_ms":1520}"#);
+        assert_eq!(serde_json::from_str::<Event>(&json).unwrap(), event);
+        assert_eq!(serde_json::to_string(&Event::Bell { pane: 2 }).unwrap(), r#"{"event":"bell","pane":2}"#);
+    }
+
+    #[test]
+    fn test_emit() {
+        // ほかのテストのペインのイベントも届くので、このテストのイベントだけを見る
+        let events = subscribe();
+        let bell = Event::Bell { pane: u64::MAX };
+        emit(bell.clone());
+        assert!(has_subscribers());
+        assert_eq!(events.iter().find(|event| *event == bell), Some(bell.clone()));
+
+        // 破棄した購読者は外し、溜まりすぎたイベントは捨てる
+        let (full, _kept) = bounded(1);
+        let (closed, receiver) = bounded(1);
+        drop(receiver);
+        let mut subscribers = vec![full, closed];
+        send_all(&mut subscribers, bell.clone());
+        send_all(&mut subscribers, bell);
+        assert_eq!(subscribers.len(), 1);
+        assert_eq!(subscribers[0].len(), 1);
+    }
+}
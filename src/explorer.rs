@@ -2,8 +2,24 @@
 //!
 //! IDEライクなファイルツリーをターミナルに統合
 
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{bail, Context, Result};
+
+use crate::bookmarks::Bookmarks;
+use crate::config;
+use crate::finder::fuzzy_score;
+use crate::fswatch::DirWatcher;
+use crate::preview::Previewer;
+
+/// 絞り込みでたどるディレクトリの深さの上限
+const MAX_FILTER_DEPTH: usize = 8;
+
+/// 絞り込みで集めるエントリの数の上限（大きなディレクトリで固まらないように）
+const MAX_FILTER_ENTRIES: usize = 5000;
 
 /// ファイルエントリの種類
 #[derive(Debug, Clone, PartialEq)]
@@ -20,6 +36,7 @@ pub struct FileEntry {
     pub kind: EntryKind,
     pub depth: usize,
     pub expanded: bool,
+    #[allow(dead_code)]
     pub children_loaded: bool,
 }
 
@@ -49,8 +66,292 @@ impl FileEntry {
     pub fn is_dir(&self) -> bool {
         self.kind == EntryKind::Directory
     }
+
+    /// 種類・拡張子に応じたアイコン（Nerd Font の文字）
+    ///
+    /// フォントにない文字なら、描画側で ▶ / ▼ だけの表示に戻す
+    pub fn icon(&self) -> char {
+        if self.is_dir() {
+            return if self.expanded { '\u{f07c}' } else { '\u{f07b}' };
+        }
+        match self.name.as_str() {
+            "Cargo.toml" | "Cargo.lock" => return '\u{e7a8}',
+            "Dockerfile" => return '\u{f308}',
+            "Makefile" => return '\u{e615}',
+            ".gitignore" | ".gitmodules" | ".gitattributes" => return '\u{e702}',
+            _ => {}
+        }
+        let extension = self.path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+        match extension.to_ascii_lowercase().as_str() {
+            "rs" => '\u{e7a8}',
+            "py" => '\u{e73c}',
+            "js" | "mjs" | "cjs" => '\u{e74e}',
+            "ts" | "tsx" => '\u{e628}',
+            "go" => '\u{e627}',
+            "c" | "h" => '\u{e61e}',
+            "cpp" | "cc" | "hpp" => '\u{e61d}',
+            "java" => '\u{e738}',
+            "rb" => '\u{e739}',
+            "lua" => '\u{e620}',
+            "swift" => '\u{e755}',
+            "html" | "htm" => '\u{e736}',
+            "css" | "scss" => '\u{e749}',
+            "md" | "markdown" => '\u{e73e}',
+            "json" => '\u{e60b}',
+            "toml" | "yaml" | "yml" | "ini" | "conf" => '\u{e615}',
+            "sh" | "bash" | "zsh" | "fish" => '\u{f489}',
+            "png" | "jpg" | "jpeg" | "gif" | "svg" | "webp" | "ico" => '\u{f1c5}',
+            "zip" | "tar" | "gz" | "xz" | "bz2" | "7z" => '\u{f410}',
+            "pdf" => '\u{f1c1}',
+            "lock" => '\u{f023}',
+            "txt" | "log" => '\u{f15c}',
+            _ => '\u{f15b}',
+        }
+    }
+}
+
+/// シェルの作業ディレクトリが変わったときの動作（環境変数 `UMITERM_EXPLORER_FOLLOW` で変更可能）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExplorerFollow {
+    /// 何もしない
+    Off,
+    /// 作業ディレクトリをルートにする
+    Root,
+    /// ルートの下ならそこまで展開して選択する（ルートの外ならルートにする）
+    Reveal,
+}
+
+impl config::Named for ExplorerFollow {
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("off", Self::Off),
+        ("root", Self::Root),
+        ("reveal", Self::Reveal),
+    ];
+}
+
+impl ExplorerFollow {
+    /// 環境変数か設定ファイルから取得（既定はルートにする）
+    pub fn from_env() -> Self {
+        config::named_var("UMITERM_EXPLORER_FOLLOW", |config| config.explorer.follow).unwrap_or(Self::Root)
+    }
+}
+
+/// エクスプローラーの表示のしかた（環境変数 `UMITERM_EXPLORER_LAYOUT` で変更可能、`s` で切り替え）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExplorerLayout {
+    /// 画面中央のポップアップ（ペインの上に重ねる）
+    Popup,
+    /// 左端に固定幅で並べる（ペインの領域はその右の残り）
+    Sidebar,
+}
+
+impl config::Named for ExplorerLayout {
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("popup", Self::Popup),
+        ("sidebar", Self::Sidebar),
+    ];
+}
+
+impl ExplorerLayout {
+    /// 環境変数か設定ファイルから取得（既定はポップアップ）
+    pub fn from_env() -> Self {
+        config::named_var("UMITERM_EXPLORER_LAYOUT", |config| config.explorer.layout).unwrap_or(Self::Popup)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 絞り込み
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 絞り込みの候補（ルートの下のファイル・ディレクトリ）
+struct FilterCandidate {
+    path: PathBuf,
+    /// ルートからの相対パス（照合と表示に使う）
+    relative: String,
+    is_dir: bool,
+}
+
+/// ルートの下を名前であいまい検索する絞り込み（`/` で開く）
+pub struct ExplorerFilter {
+    /// 入力中の文字列
+    pub query: String,
+    /// 集めた候補（浅い順）
+    candidates: Vec<FilterCandidate>,
+    /// 絞り込んだ候補の番号（よく一致する順、同点なら浅い順）
+    matches: Vec<usize>,
+    /// 選択中の候補（matches の番号）
+    pub selected: usize,
+}
+
+impl ExplorerFilter {
+    /// ルートの下のエントリを集める（隠しファイルを除き、深さと数に上限あり）
+    fn new(root: &Path) -> Self {
+        let mut candidates = Vec::new();
+        let mut queue = std::collections::VecDeque::from([(root.to_path_buf(), 0)]);
+        'walk: while let Some((dir, depth)) = queue.pop_front() {
+            let Ok(read_dir) = fs::read_dir(&dir) else { continue };
+            let mut entries: Vec<_> = read_dir
+                .filter_map(|e| e.ok())
+                .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+                .collect();
+            entries.sort_by_key(|e| e.file_name());
+            for entry in entries {
+                if candidates.len() >= MAX_FILTER_ENTRIES {
+                    break 'walk;
+                }
+                let path = entry.path();
+                let is_dir = path.is_dir();
+                if is_dir && depth + 1 < MAX_FILTER_DEPTH {
+                    queue.push_back((path.clone(), depth + 1));
+                }
+                let relative = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().to_string();
+                candidates.push(FilterCandidate { path, relative, is_dir });
+            }
+        }
+        let mut filter = Self { query: String::new(), candidates, matches: Vec::new(), selected: 0 };
+        filter.refilter();
+        filter
+    }
+
+    /// 文字列を入力
+    pub fn push_str(&mut self, text: &str) {
+        self.query.push_str(text);
+        self.refilter();
+    }
+
+    /// 1文字削除
+    pub fn pop(&mut self) {
+        if self.query.pop().is_some() {
+            self.refilter();
+        }
+    }
+
+    /// 選択を移動（正の値で下へ、端で止まる）
+    pub fn move_selection(&mut self, delta: isize) {
+        let last = self.matches.len().saturating_sub(1) as isize;
+        self.selected = (self.selected as isize + delta).clamp(0, last) as usize;
+    }
+
+    /// 選択中の候補のパスとディレクトリか
+    fn selected_candidate(&self) -> Option<(&Path, bool)> {
+        let candidate = &self.candidates[*self.matches.get(self.selected)?];
+        Some((candidate.path.as_path(), candidate.is_dir))
+    }
+
+    /// 選択中の候補のパス
+    pub fn selected_path(&self) -> Option<&Path> {
+        let index = *self.matches.get(self.selected)?;
+        Some(&self.candidates[index].path)
+    }
+
+    /// 絞り込んだ候補（ルートからの相対パスとディレクトリか、よく一致する順）
+    pub fn results(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.matches.iter().map(|&index| {
+            let candidate = &self.candidates[index];
+            (candidate.relative.as_str(), candidate.is_dir)
+        })
+    }
+
+    /// 絞り込んだ候補の数と、集めた候補の数
+    pub fn counts(&self) -> (usize, usize) {
+        (self.matches.len(), self.candidates.len())
+    }
+
+    /// クエリで絞り込み直す（選択は先頭に戻す）
+    fn refilter(&mut self) {
+        let mut scored: Vec<(i32, usize)> = self
+            .candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(index, candidate)| Some((fuzzy_score(&self.query, &candidate.relative)?, index)))
+            .collect();
+        scored.sort_by_key(|&(score, index)| (std::cmp::Reverse(score), index));
+        self.matches = scored.into_iter().map(|(_, index)| index).collect();
+        self.selected = 0;
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// メタデータ
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// エントリの大きさと更新日時
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryMetadata {
+    /// 大きさ（ディレクトリなら None）
+    pub size: Option<u64>,
+    /// 更新日時
+    pub modified: Option<SystemTime>,
+}
+
+impl EntryMetadata {
+    /// ファイルのメタデータを読む
+    fn read(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            size: (!metadata.is_dir()).then_some(metadata.len()),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    /// 表示用の文字列（「12.3 KB  2026-10-16 14:03」）
+    pub fn describe(&self) -> String {
+        let size = self.size.map(format_size);
+        let modified = self.modified.map(|time| {
+            chrono::DateTime::<chrono::Local>::from(time).format("%Y-%m-%d %H:%M").to_string()
+        });
+        [size, modified].into_iter().flatten().collect::<Vec<_>>().join("  ")
+    }
+}
+
+/// バイト数を読みやすくする（1024 ごとに KB / MB / GB）
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// ファイル操作の入力中の状態
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExplorerPrompt {
+    /// 新しいファイルの名前を入力中
+    NewFile(String),
+    /// 新しいディレクトリの名前を入力中
+    NewDirectory(String),
+    /// 選択中のエントリの新しい名前を入力中
+    Rename(String),
+    /// 選択中のエントリ（印を付けたエントリがあればそれらすべて）を削除してよいか確認中
+    ConfirmDelete,
+    /// 印を付けたエントリをこのディレクトリに移動してよいか確認中
+    ConfirmMove(PathBuf),
+}
+
+impl ExplorerPrompt {
+    /// 入力中の名前（削除の確認なら None）
+    fn name_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Self::NewFile(name) | Self::NewDirectory(name) | Self::Rename(name) => Some(name),
+            Self::ConfirmDelete | Self::ConfirmMove(_) => None,
+        }
+    }
+
+    /// 確認中か（y / Enter で実行、それ以外のキーでやめる）
+    pub fn is_confirmation(&self) -> bool {
+        matches!(self, Self::ConfirmDelete | Self::ConfirmMove(_))
+    }
 }
 
+/// 確認のダイアログに並べるエントリの数
+const SUMMARY_LINES: usize = 8;
+
 /// ファイルエクスプローラーの状態
 pub struct Explorer {
     /// ルートディレクトリ
@@ -61,10 +362,32 @@ pub struct Explorer {
     pub selected: usize,
     /// サイドバーの幅（文字数）
     pub width: usize,
+    /// 表示のしかた（ポップアップかサイドバーか）
+    pub layout: ExplorerLayout,
     /// 表示中かどうか
     pub visible: bool,
     /// スクロールオフセット
     pub scroll_offset: usize,
+    /// 一覧を表示できる行数（ヘッダーと入力欄を除く）
+    visible_rows: usize,
+    /// ファイル操作の入力中の状態
+    pub prompt: Option<ExplorerPrompt>,
+    /// 最後のファイル操作のエラー（次の操作まで表示）
+    pub message: Option<String>,
+    /// 名前での絞り込み（絞り込み中のみ）
+    pub filter: Option<ExplorerFilter>,
+    /// 選択中のファイルのプレビュー
+    pub preview: Previewer,
+    /// 選択中のエントリのパスとメタデータ（選択が変わったときだけ読み直す）
+    metadata: Option<(PathBuf, Option<EntryMetadata>)>,
+    /// 表示中のディレクトリ（ルートと展開中のもの）の監視
+    watcher: DirWatcher,
+    /// ピン留めしたディレクトリと最近のディレクトリ
+    pub bookmarks: Bookmarks,
+    /// ブックマークの一覧で選択中のインデックス（一覧を開いているときのみ）
+    pub places: Option<usize>,
+    /// Space で印を付けたエントリ（印を付けた順、まとめてコピー・削除・移動する）
+    pub marked: Vec<PathBuf>,
 }
 
 impl Explorer {
@@ -74,9 +397,20 @@ impl Explorer {
             root: root.clone(),
             entries: Vec::new(),
             selected: 0,
-            width: 25,
+            width: 30,
+            layout: ExplorerLayout::from_env(),
             visible: false,
             scroll_offset: 0,
+            visible_rows: 18,
+            prompt: None,
+            message: None,
+            filter: None,
+            preview: Previewer::new(),
+            metadata: None,
+            watcher: DirWatcher::new(),
+            bookmarks: Bookmarks::load_default(),
+            places: None,
+            marked: Vec::new(),
         };
         explorer.load_directory(&root, 0);
         explorer
@@ -114,6 +448,30 @@ impl Explorer {
         self.visible = !self.visible;
     }
 
+    /// サイドバーとして表示中か（ペインの領域を狭める）
+    pub fn docked(&self) -> bool {
+        self.visible && self.layout == ExplorerLayout::Sidebar
+    }
+
+    /// ポップアップとサイドバーを切り替え
+    pub fn toggle_layout(&mut self) {
+        self.layout = match self.layout {
+            ExplorerLayout::Popup => ExplorerLayout::Sidebar,
+            ExplorerLayout::Sidebar => ExplorerLayout::Popup,
+        };
+    }
+
+    /// 画面の行からエントリを選択する（row は一覧の先頭からの行）
+    /// 戻り値: 選択したか
+    pub fn select_row(&mut self, row: usize) -> bool {
+        let index = self.scroll_offset + row;
+        if row >= self.visible_rows || index >= self.entries.len() {
+            return false;
+        }
+        self.selected = index;
+        true
+    }
+
     /// 上に移動
     pub fn move_up(&mut self) {
         if self.selected > 0 {
@@ -224,8 +582,7 @@ impl Explorer {
 
     /// スクロール位置を調整して選択が見えるようにする
     fn ensure_visible(&mut self) {
-        // 表示可能な行数（仮に20行とする、後でrendererから設定）
-        let visible_rows = 20;
+        let visible_rows = self.visible_rows;
 
         if self.selected < self.scroll_offset {
             self.scroll_offset = self.selected;
@@ -234,11 +591,12 @@ impl Explorer {
         }
     }
 
-    /// 表示可能行数を設定
+    /// 表示可能行数を設定（rows はヘッダーと入力欄を含む高さ）
     pub fn set_visible_rows(&mut self, rows: usize) {
-        let visible_rows = rows.saturating_sub(2); // ヘッダー分
-        if self.selected >= self.scroll_offset + visible_rows {
-            self.scroll_offset = self.selected.saturating_sub(visible_rows - 1);
+        let visible_rows = rows.saturating_sub(2).max(1); // ヘッダーと入力欄の分
+        if visible_rows != self.visible_rows {
+            self.visible_rows = visible_rows;
+            self.ensure_visible();
         }
     }
 
@@ -248,6 +606,664 @@ impl Explorer {
         self.entries.clear();
         self.selected = 0;
         self.scroll_offset = 0;
+        self.prompt = None;
+        self.message = None;
+        self.filter = None;
+        self.places = None;
+        self.marked.clear();
         self.load_directory(&path, 0);
     }
+
+    /// 表示中のディレクトリを監視し、ファイルが作成・削除されていれば一覧を読み直す（閉じていれば監視しない）
+    /// 戻り値: 読み直したか
+    pub fn refresh_from_disk(&mut self) -> bool {
+        let dirs: Vec<PathBuf> = if self.visible {
+            std::iter::once(self.root.clone())
+                .chain(self.entries.iter().filter(|entry| entry.expanded).map(|entry| entry.path.clone()))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        self.watcher.sync(&dirs);
+        if !self.watcher.take_changed() || !self.visible {
+            return false;
+        }
+        self.refresh(None);
+        self.metadata = None;
+        true
+    }
+
+    /// 選択中のパスとディレクトリか（絞り込み中なら選択中の候補、閉じていれば None）
+    fn current_selection(&self) -> Option<(PathBuf, bool)> {
+        if !self.visible {
+            return None;
+        }
+        match &self.filter {
+            Some(filter) => filter.selected_candidate().map(|(path, is_dir)| (path.to_path_buf(), is_dir)),
+            None => self.selected_entry().map(|entry| (entry.path.clone(), entry.is_dir())),
+        }
+    }
+
+    /// 選択中のファイルのプレビューとメタデータを更新する（閉じていれば消す）
+    /// 戻り値: 表示が変わったか
+    pub fn refresh_selection(&mut self) -> bool {
+        let selection = self.current_selection();
+        let file = selection.as_ref().filter(|(_, is_dir)| !is_dir).map(|(path, _)| path.as_path());
+        let mut changed = self.preview.show(file);
+        changed |= self.preview.poll();
+
+        let path = selection.map(|(path, _)| path);
+        if self.metadata.as_ref().map(|(path, _)| path) != path.as_ref() {
+            self.metadata = path.map(|path| {
+                let metadata = EntryMetadata::read(&path);
+                (path, metadata)
+            });
+            changed = true;
+        }
+        changed
+    }
+
+    /// 選択中のエントリの名前とメタデータ
+    pub fn selected_metadata(&self) -> Option<(String, &EntryMetadata)> {
+        let (path, metadata) = self.metadata.as_ref()?;
+        let name = path.file_name()?.to_string_lossy().to_string();
+        Some((name, metadata.as_ref()?))
+    }
+
+    /// 名前での絞り込みを始める
+    pub fn open_filter(&mut self) {
+        self.message = None;
+        self.filter = Some(ExplorerFilter::new(&self.root));
+    }
+
+    /// 絞り込みで選んだエントリをツリーで選択して絞り込みを閉じる
+    /// 戻り値: 選んだエントリ（ファイルならエディタで開く）
+    pub fn accept_filter(&mut self) -> Option<FileEntry> {
+        let path = self.filter.take()?.selected_path()?.to_path_buf();
+        self.reveal(&path);
+        self.selected_entry().filter(|entry| entry.path == path).cloned()
+    }
+
+    /// ブックマークの一覧を開く（別のウィンドウでの変更も読み直す）
+    pub fn open_places(&mut self) {
+        self.message = None;
+        self.bookmarks.reload();
+        self.places = Some(0);
+    }
+
+    /// ブックマークの一覧の選択を移動
+    pub fn move_place(&mut self, delta: isize) {
+        let count = self.bookmarks.places().len();
+        if let Some(selected) = self.places.as_mut() {
+            *selected = selected.saturating_add_signed(delta).min(count.saturating_sub(1));
+        }
+    }
+
+    /// ブックマークの一覧で選択中のディレクトリ
+    pub fn selected_place(&self) -> Option<PathBuf> {
+        let selected = self.places?;
+        self.bookmarks.places().get(selected).map(|(_, path)| path.to_path_buf())
+    }
+
+    /// ブックマークの一覧で選んだディレクトリをルートにする（なくなっていればメッセージに残す）
+    pub fn accept_place(&mut self) {
+        let Some(path) = self.selected_place() else {
+            self.places = None;
+            return;
+        };
+        if path.is_dir() {
+            self.set_root(path);
+        } else {
+            self.places = None;
+            self.message = Some(format!("{} がありません", path.display()));
+        }
+    }
+
+    /// ブックマークの一覧で選択中のディレクトリを一覧から外す
+    pub fn remove_place(&mut self) {
+        if let Some(path) = self.selected_place() {
+            self.bookmarks.remove(&path);
+            self.move_place(0);
+        }
+    }
+
+    /// 選択中のディレクトリ（ファイルならその親）をピン留めする（ピン留め済みなら外す）
+    pub fn toggle_pin(&mut self) {
+        let path = self.target_directory();
+        let pinned = self.bookmarks.toggle_pin(&path);
+        let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        self.message = Some(if pinned { format!("{} をピン留めしました", name) } else { format!("{} のピン留めを外しました", name) });
+    }
+
+    /// シェルの作業ディレクトリに合わせる
+    pub fn follow(&mut self, mode: ExplorerFollow, cwd: &Path) {
+        match mode {
+            ExplorerFollow::Off => {}
+            ExplorerFollow::Root if self.root != cwd => self.set_root(cwd.to_path_buf()),
+            ExplorerFollow::Root => {}
+            ExplorerFollow::Reveal => self.reveal(cwd),
+        }
+    }
+
+    /// パスまでディレクトリを展開して選択する（ルートの外ならそこをルートにする）
+    pub fn reveal(&mut self, path: &Path) {
+        let Ok(rest) = path.strip_prefix(&self.root) else {
+            self.set_root(path.to_path_buf());
+            return;
+        };
+        let mut current = self.root.clone();
+        for component in rest.components() {
+            current.push(component);
+            // 隠しディレクトリなど一覧にないものの手前で止める
+            let Some(index) = self.entries.iter().position(|entry| entry.path == current) else { break };
+            self.selected = index;
+            self.expand_at(index);
+        }
+        self.ensure_visible();
+    }
+
+    // ───────────────────────────────────────────────────────────────────────
+    // ファイル操作
+    // ───────────────────────────────────────────────────────────────────────
+
+    /// 一覧を読み直す（展開していたディレクトリは展開したまま、select があればそれを選択）
+    pub fn refresh(&mut self, select: Option<&Path>) {
+        let expanded: HashSet<PathBuf> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.expanded)
+            .map(|entry| entry.path.clone())
+            .collect();
+        let selected = select
+            .map(Path::to_path_buf)
+            .or_else(|| self.selected_entry().map(|entry| entry.path.clone()));
+
+        let root = self.root.clone();
+        self.load_directory(&root, 0);
+        // 展開は子を後ろに挿入するので、前から順に展開すれば深い階層も復元できる
+        let mut index = 0;
+        while index < self.entries.len() {
+            if expanded.contains(&self.entries[index].path) {
+                self.expand_at(index);
+            }
+            index += 1;
+        }
+
+        let index = selected.and_then(|path| self.entries.iter().position(|entry| entry.path == path));
+        self.selected = index.unwrap_or(self.selected).min(self.entries.len().saturating_sub(1));
+        self.ensure_visible();
+        // なくなったエントリの印は外す
+        self.marked.retain(|path| path.exists());
+    }
+
+    /// 選択中のエントリに印を付けて（付いていれば外して）次のエントリに移る
+    pub fn toggle_mark(&mut self) {
+        let Some(path) = self.selected_entry().map(|entry| entry.path.clone()) else { return };
+        match self.marked.iter().position(|marked| *marked == path) {
+            Some(index) => {
+                self.marked.remove(index);
+            }
+            None => self.marked.push(path),
+        }
+        self.move_down();
+    }
+
+    /// 印が付いているか
+    pub fn is_marked(&self, path: &Path) -> bool {
+        self.marked.iter().any(|marked| marked == path)
+    }
+
+    /// まとめて操作するエントリ（印を付けたエントリ、なければ選択中のエントリ）
+    pub fn batch_targets(&self) -> Vec<PathBuf> {
+        if self.marked.is_empty() {
+            self.selected_entry().map(|entry| entry.path.clone()).into_iter().collect()
+        } else {
+            self.marked.clone()
+        }
+    }
+
+    /// 印を付けたエントリを選択中のディレクトリ（ファイルならその親）に移動する確認を始める
+    pub fn begin_move(&mut self) {
+        if self.marked.is_empty() {
+            self.message = Some("Space で移動するエントリに印を付けてください".to_string());
+            return;
+        }
+        let target = self.target_directory();
+        self.begin(ExplorerPrompt::ConfirmMove(target));
+    }
+
+    /// 複数のエントリの削除・移動の確認のダイアログの行（1行目が見出し、1つだけなら None で入力欄で確認）
+    pub fn summary_lines(&self) -> Option<Vec<String>> {
+        let prompt = self.prompt.as_ref().filter(|prompt| prompt.is_confirmation())?;
+        if !self.visible || self.marked.is_empty() {
+            return None;
+        }
+        let count = self.marked.len();
+        let (header, action) = match prompt {
+            ExplorerPrompt::ConfirmMove(target) => {
+                let name = target.strip_prefix(&self.root).unwrap_or(target);
+                (format!("Move {} items into {}/?", count, name.display()), "move")
+            }
+            _ => (format!("Delete {} items?", count), "delete"),
+        };
+        let mut lines = vec![header];
+        for path in self.marked.iter().take(SUMMARY_LINES) {
+            let name = path.strip_prefix(&self.root).unwrap_or(path);
+            let slash = if path.is_dir() { "/" } else { "" };
+            lines.push(format!("│ {}{}", name.display(), slash));
+        }
+        if count > SUMMARY_LINES {
+            lines.push(format!("│ … {} more", count - SUMMARY_LINES));
+        }
+        lines.push(format!("Enter / y: {}   Esc: cancel", action));
+        Some(lines)
+    }
+
+    /// 新しく作るエントリを置くディレクトリ（選択中がディレクトリならその中、ファイルなら同じ階層）
+    fn target_directory(&self) -> PathBuf {
+        match self.selected_entry() {
+            Some(entry) if entry.is_dir() => entry.path.clone(),
+            Some(entry) => entry.path.parent().map(Path::to_path_buf).unwrap_or_else(|| self.root.clone()),
+            None => self.root.clone(),
+        }
+    }
+
+    /// ファイル操作の入力を始める（n: 新規ファイル、N: 新規ディレクトリ、r: 名前の変更、d: 削除）
+    pub fn begin(&mut self, prompt: ExplorerPrompt) {
+        let needs_entry = match prompt {
+            ExplorerPrompt::Rename(_) => true,
+            ExplorerPrompt::ConfirmDelete => self.marked.is_empty(),
+            _ => false,
+        };
+        if needs_entry && self.selected_entry().is_none() {
+            return;
+        }
+        self.message = None;
+        self.prompt = Some(prompt);
+    }
+
+    /// 入力中の名前に文字を追加
+    pub fn push_str(&mut self, text: &str) {
+        if let Some(name) = self.prompt.as_mut().and_then(ExplorerPrompt::name_mut) {
+            name.push_str(text);
+        }
+    }
+
+    /// 入力中の名前の最後の文字を消す
+    pub fn backspace(&mut self) {
+        if let Some(name) = self.prompt.as_mut().and_then(ExplorerPrompt::name_mut) {
+            name.pop();
+        }
+    }
+
+    /// 入力をやめる
+    pub fn cancel(&mut self) {
+        self.prompt = None;
+    }
+
+    /// 入力した操作を実行して一覧を読み直す（失敗したらメッセージに残す）
+    pub fn confirm(&mut self) {
+        let Some(prompt) = self.prompt.take() else { return };
+        match self.apply(&prompt) {
+            Ok(select) => self.refresh(select.as_deref()),
+            Err(e) => {
+                log::warn!("ファイル操作に失敗: {:#}", e);
+                self.message = Some(format!("{:#}", e));
+                self.refresh(None);
+            }
+        }
+    }
+
+    /// ファイル操作を実行
+    /// 戻り値: 操作後に選択するパス
+    fn apply(&self, prompt: &ExplorerPrompt) -> Result<Option<PathBuf>> {
+        match prompt {
+            ExplorerPrompt::NewFile(name) => {
+                let path = self.target_directory().join(valid_name(name)?);
+                fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)
+                    .with_context(|| format!("{} を作成できません", path.display()))?;
+                Ok(Some(path))
+            }
+            ExplorerPrompt::NewDirectory(name) => {
+                let path = self.target_directory().join(valid_name(name)?);
+                fs::create_dir(&path).with_context(|| format!("{} を作成できません", path.display()))?;
+                Ok(Some(path))
+            }
+            ExplorerPrompt::Rename(name) => {
+                let Some(entry) = self.selected_entry() else { return Ok(None) };
+                let parent = entry.path.parent().unwrap_or(&self.root);
+                let path = parent.join(valid_name(name)?);
+                if path.exists() {
+                    bail!("{} はすでにあります", path.display());
+                }
+                fs::rename(&entry.path, &path).with_context(|| format!("{} の名前を変更できません", entry.name))?;
+                Ok(Some(path))
+            }
+            ExplorerPrompt::ConfirmDelete => {
+                Self::apply_each(&self.batch_targets(), |path| {
+                    let result = if path.is_dir() { fs::remove_dir_all(path) } else { fs::remove_file(path) };
+                    result.with_context(|| format!("{} を削除できません", path.display()))
+                })?;
+                Ok(None)
+            }
+            ExplorerPrompt::ConfirmMove(target) => {
+                Self::apply_each(&self.marked, |path| {
+                    let name = path.file_name().context("ルートは移動できません")?;
+                    let dest = target.join(name);
+                    if target.starts_with(path) {
+                        bail!("{} をその中に移動できません", path.display());
+                    }
+                    if dest.exists() {
+                        bail!("{} はすでにあります", dest.display());
+                    }
+                    fs::rename(path, &dest).with_context(|| format!("{} を移動できません", path.display()))
+                })?;
+                Ok(Some(target.clone()))
+            }
+        }
+    }
+
+    /// 複数のエントリに操作を行う（失敗しても残りは続け、最初のエラーに失敗した数を添える）
+    fn apply_each(paths: &[PathBuf], mut op: impl FnMut(&Path) -> Result<()>) -> Result<()> {
+        let mut errors = paths.iter().filter_map(|path| op(path).err());
+        let Some(first) = errors.next() else { return Ok(()) };
+        match errors.count() {
+            0 => Err(first),
+            rest => Err(first.context(format!("{} 件失敗しました", rest + 1))),
+        }
+    }
+}
+
+/// 入力した名前を確かめる（空やパスの区切りを含む名前は使わない）
+fn valid_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        bail!("名前が正しくありません: {:?}", name);
+    }
+    Ok(name)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_operations() {
+        let root = std::env::temp_dir().join(format!("umiterm-explorer-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("README.md"), "").unwrap();
+        let mut explorer = Explorer::new(root.clone());
+        let names = |explorer: &Explorer| -> Vec<String> { explorer.entries.iter().map(|e| e.name.clone()).collect() };
+
+        // 展開したディレクトリの中に作り、展開したまま作ったファイルを選ぶ
+        explorer.toggle_expand();
+        explorer.begin(ExplorerPrompt::NewFile(String::new()));
+        explorer.push_str("main");
+        explorer.push_str(".rs");
+        explorer.confirm();
+        assert!(root.join("src/main.rs").is_file());
+        assert_eq!(names(&explorer), ["src", "main.rs", "README.md"]);
+        assert_eq!(explorer.selected, 1);
+
+        // ファイルを選んでいれば同じ階層にディレクトリを作る
+        explorer.begin(ExplorerPrompt::NewDirectory("bin".to_string()));
+        explorer.confirm();
+        assert!(root.join("src/bin").is_dir());
+
+        // 名前の変更（既存の名前や区切りを含む名前は失敗してメッセージを残す）
+        explorer.refresh(Some(&root.join("README.md")));
+        explorer.begin(ExplorerPrompt::Rename("src".to_string()));
+        explorer.confirm();
+        assert!(explorer.message.is_some());
+        explorer.begin(ExplorerPrompt::Rename("a/b".to_string()));
+        explorer.confirm();
+        assert!(root.join("README.md").exists());
+        explorer.begin(ExplorerPrompt::Rename("NOTES.md".to_string()));
+        explorer.confirm();
+        assert!(root.join("NOTES.md").is_file());
+        assert_eq!(explorer.message, None);
+
+        // 削除
+        explorer.begin(ExplorerPrompt::ConfirmDelete);
+        explorer.confirm();
+        assert!(!root.join("NOTES.md").exists());
+        assert_eq!(names(&explorer), ["src", "bin", "main.rs"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_batch_operations() {
+        let root = std::env::temp_dir().join(format!("umiterm-explorer-batch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("dest")).unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(root.join(name), "").unwrap();
+        }
+        fs::write(root.join("dest/b.txt"), "").unwrap();
+        let mut explorer = Explorer::new(root.clone());
+        explorer.visible = true;
+
+        // Space で印を付けて次へ（もう一度で外す）
+        explorer.move_down();
+        explorer.toggle_mark();
+        explorer.toggle_mark();
+        explorer.toggle_mark();
+        assert_eq!(explorer.marked, [root.join("a.txt"), root.join("b.txt"), root.join("c.txt")]);
+        explorer.toggle_mark();
+        assert_eq!(explorer.batch_targets(), [root.join("a.txt"), root.join("b.txt")]);
+
+        // 移動先を選んで確認（移動先に同じ名前があるものは残してメッセージを出す）
+        explorer.selected = 0;
+        explorer.begin_move();
+        let summary = explorer.summary_lines().unwrap();
+        assert_eq!(summary[0], "Move 2 items into dest/?");
+        assert_eq!(summary[1], "│ a.txt");
+        explorer.confirm();
+        assert!(root.join("dest/a.txt").is_file());
+        assert!(root.join("b.txt").is_file());
+        assert!(explorer.message.is_some());
+        assert_eq!(explorer.marked, [root.join("b.txt")]);
+
+        // 印を付けたものをまとめて削除
+        explorer.marked.push(root.join("c.txt"));
+        explorer.begin(ExplorerPrompt::ConfirmDelete);
+        explorer.confirm();
+        assert!(!root.join("b.txt").exists() && !root.join("c.txt").exists());
+        assert!(root.join("dest").is_dir());
+        assert!(explorer.marked.is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_follow() {
+        let root = std::env::temp_dir().join(format!("umiterm-explorer-follow-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("a/b/c")).unwrap();
+        fs::create_dir_all(root.join("z")).unwrap();
+        let mut explorer = Explorer::new(root.clone());
+
+        // ルートの下なら展開して選択
+        explorer.follow(ExplorerFollow::Reveal, &root.join("a/b"));
+        assert_eq!(explorer.root, root);
+        assert_eq!(explorer.selected_entry().map(|e| e.path.clone()), Some(root.join("a/b")));
+        assert!(explorer.entries.iter().any(|e| e.path == root.join("a/b/c")));
+
+        // ルートの外やルートにする設定ならそこをルートにする
+        explorer.follow(ExplorerFollow::Reveal, &std::env::temp_dir());
+        assert_eq!(explorer.root, std::env::temp_dir());
+        explorer.follow(ExplorerFollow::Root, &root.join("z"));
+        assert_eq!(explorer.root, root.join("z"));
+        explorer.follow(ExplorerFollow::Off, &root);
+        assert_eq!(explorer.root, root.join("z"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_filter() {
+        let root = std::env::temp_dir().join(format!("umiterm-explorer-filter-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src/parser")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("src/parser/mod.rs"), "").unwrap();
+        fs::write(root.join("src/main.rs"), "").unwrap();
+        fs::write(root.join(".git/config"), "").unwrap();
+        let mut explorer = Explorer::new(root.clone());
+
+        // 下の階層まで集め、隠しディレクトリは除く
+        explorer.open_filter();
+        let filter = explorer.filter.as_mut().unwrap();
+        assert_eq!(filter.counts(), (4, 4));
+        filter.push_str("pamod");
+        assert_eq!(filter.results().collect::<Vec<_>>(), [("src/parser/mod.rs", false)]);
+
+        // 選ぶとツリーを展開して選択する
+        let entry = explorer.accept_filter().unwrap();
+        assert_eq!(entry.path, root.join("src/parser/mod.rs"));
+        assert!(explorer.filter.is_none());
+        assert_eq!(explorer.selected_entry().map(|e| e.path.clone()), Some(root.join("src/parser/mod.rs")));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_metadata() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MB");
+
+        let root = std::env::temp_dir().join(format!("umiterm-explorer-meta-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("dir")).unwrap();
+        fs::write(root.join("file.txt"), vec![b'x'; 2048]).unwrap();
+        let mut explorer = Explorer::new(root.clone());
+        explorer.visible = true;
+
+        // 選択が変わったときだけ読み直す
+        assert!(explorer.refresh_selection());
+        let (name, metadata) = explorer.selected_metadata().unwrap();
+        assert_eq!((name.as_str(), metadata.size), ("dir", None));
+        explorer.move_down();
+        explorer.refresh_selection();
+        let (name, metadata) = explorer.selected_metadata().unwrap();
+        assert_eq!((name.as_str(), metadata.size), ("file.txt", Some(2048)));
+        assert!(metadata.describe().starts_with("2.0 KB  20"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_refresh_from_disk() {
+        let root = std::env::temp_dir().join(format!("umiterm-explorer-watch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src")).unwrap();
+        let mut explorer = Explorer::new(root.clone());
+        explorer.visible = true;
+        explorer.toggle_expand();
+        assert!(!explorer.refresh_from_disk());
+
+        // 展開中のディレクトリにファイルが作られたら一覧に出る
+        fs::write(root.join("src/new.rs"), "").unwrap();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while !explorer.refresh_from_disk() && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(explorer.entries.iter().any(|entry| entry.path == root.join("src/new.rs")));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_places() {
+        let root = std::env::temp_dir().join(format!("umiterm-explorer-places-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src")).unwrap();
+        let mut explorer = Explorer::new(root.clone());
+        explorer.bookmarks = Bookmarks::load(None);
+
+        // 選択中のディレクトリをピン留めし、最近のディレクトリの前に並べる
+        explorer.bookmarks.visit(Path::new("/nonexistent/umiterm"));
+        explorer.toggle_pin();
+        explorer.open_places();
+        assert_eq!(explorer.selected_place(), Some(root.join("src")));
+        explorer.move_place(5);
+        assert_eq!(explorer.selected_place(), Some(PathBuf::from("/nonexistent/umiterm")));
+
+        // なくなったディレクトリは開かずにメッセージを出す
+        explorer.accept_place();
+        assert_eq!(explorer.places, None);
+        assert!(explorer.message.is_some());
+        assert_eq!(explorer.root, root);
+
+        explorer.places = Some(0);
+        explorer.accept_place();
+        assert_eq!(explorer.root, root.join("src"));
+        assert_eq!(explorer.places, None);
+
+        explorer.places = Some(1);
+        explorer.remove_place();
+        assert_eq!(explorer.places, Some(0));
+        assert_eq!(explorer.bookmarks.places().len(), 1);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_sidebar() {
+        let root = std::env::temp_dir().join(format!("umiterm-explorer-sidebar-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        for i in 0..10 {
+            fs::write(root.join(format!("{}.txt", i)), "").unwrap();
+        }
+        let mut explorer = Explorer::new(root.clone());
+        explorer.layout = ExplorerLayout::Popup;
+        explorer.visible = true;
+        assert!(!explorer.docked());
+        explorer.toggle_layout();
+        assert!(explorer.docked());
+
+        // 低いサイドバーでは選択が見えるようにスクロールする
+        explorer.set_visible_rows(6);
+        for _ in 0..5 {
+            explorer.move_down();
+        }
+        assert_eq!(explorer.scroll_offset, 2);
+
+        // クリックした行のエントリを選択
+        assert!(explorer.select_row(1));
+        assert_eq!(explorer.selected, 3);
+        assert!(!explorer.select_row(4));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_icon() {
+        let entry = |name: &str, kind: EntryKind| FileEntry {
+            name: name.to_string(),
+            path: PathBuf::from("/work").join(name),
+            kind,
+            depth: 0,
+            expanded: false,
+            children_loaded: false,
+        };
+        assert_eq!(entry("main.rs", EntryKind::File).icon(), '\u{e7a8}');
+        assert_eq!(entry("Cargo.toml", EntryKind::File).icon(), '\u{e7a8}');
+        assert_eq!(entry("PHOTO.JPG", EntryKind::File).icon(), '\u{f1c5}');
+        assert_eq!(entry("LICENSE", EntryKind::File).icon(), '\u{f15b}');
+
+        let mut dir = entry("src", EntryKind::Directory);
+        assert_eq!(dir.icon(), '\u{f07b}');
+        dir.expanded = true;
+        assert_eq!(dir.icon(), '\u{f07c}');
+    }
 }
@@ -0,0 +1,384 @@
+//! スクロールバック・選択範囲の書き出しモジュール
+//!
+//! フォーカス中のペインの履歴（スクロールバックとメイン画面）をテキストファイルに保存する
+//! ANSI の色を残すと `less -R` や `cat` で色付きのまま読める
+//!
+//! 選択範囲は色とスタイル付きの HTML・RTF にもして、ドキュメントやチャットに貼れるようにする
+//! （パレットの色は表示中の配色で実際の色にする）
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::grid::{self, Cell, CellFlags, Color, Palette};
+
+/// SGR の番号を持つスタイルフラグ
+const FLAG_CODES: &[(CellFlags, u8)] = &[
+    (CellFlags::BOLD, 1),
+    (CellFlags::ITALIC, 3),
+    (CellFlags::UNDERLINE, 4),
+    (CellFlags::BLINK, 5),
+    (CellFlags::INVERSE, 7),
+    (CellFlags::HIDDEN, 8),
+    (CellFlags::STRIKEOUT, 9),
+];
+
+/// 行をファイルに保存する（ansi なら色とスタイルを SGR で残す）
+pub fn save(path: &Path, rows: impl IntoIterator<Item = Vec<Cell>>, ansi: bool) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("ファイルを作れません: {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    write_rows(&mut writer, rows, ansi)
+        .and_then(|()| writer.flush())
+        .with_context(|| format!("ファイルに書けません: {}", path.display()))
+}
+
+/// 行を書き出す（末尾の空行は除く）
+pub fn write_rows<W: Write>(writer: &mut W, rows: impl IntoIterator<Item = Vec<Cell>>, ansi: bool) -> io::Result<()> {
+    // 空行は次に文字のある行が来たときにまとめて書く
+    let mut blank = 0;
+    for row in rows {
+        let line = if ansi { ansi_line(&row) } else { plain_line(&row) };
+        if line.is_empty() {
+            blank += 1;
+            continue;
+        }
+        for _ in 0..blank {
+            writeln!(writer)?;
+        }
+        blank = 0;
+        writeln!(writer, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// 行のテキスト（全角文字の後ろの空きと末尾の空白は除く）
+fn plain_line(cells: &[Cell]) -> String {
+    let mut chars: Vec<char> = cells.iter().map(|cell| cell.character).collect();
+    grid::mark_wide_spacers(&mut chars);
+    let text: String = chars.into_iter().filter(|c| *c != '\0').collect();
+    text.trim_end().to_string()
+}
+
+/// 行をスタイルの同じ文字の並びに分ける（全角文字の後ろの空きと、末尾の何も見えない空白は除く）
+fn runs(cells: &[Cell]) -> Vec<(Cell, String)> {
+    let mut chars: Vec<char> = cells.iter().map(|cell| cell.character).collect();
+    grid::mark_wide_spacers(&mut chars);
+    // 背景色や反転のある空白は見えるので残す
+    let end = cells
+        .iter()
+        .rposition(|cell| cell.character != ' ' || cell.bg != Color::DEFAULT_BG || cell.flags.contains(CellFlags::INVERSE))
+        .map_or(0, |last| last + 1);
+
+    let mut runs: Vec<(Cell, String)> = Vec::new();
+    for (cell, c) in cells[..end].iter().zip(chars) {
+        if c == '\0' {
+            continue;
+        }
+        match runs.last_mut() {
+            Some((style, text)) if same_style(style, cell) => text.push(c),
+            _ => runs.push((*cell, c.to_string())),
+        }
+    }
+    runs
+}
+
+/// 色とスタイルが同じか
+fn same_style(a: &Cell, b: &Cell) -> bool {
+    (a.fg, a.bg, a.flags) == (b.fg, b.bg, b.flags)
+}
+
+/// 行のテキストを SGR 付きで（スタイルが変わるところで切り替え、行末で元に戻す）
+fn ansi_line(cells: &[Cell]) -> String {
+    let default = Cell::default();
+    let mut current = default;
+    let mut line = String::new();
+    for (style, text) in runs(cells) {
+        if !same_style(&style, &current) {
+            line.push_str(&sgr(&style));
+            current = style;
+        }
+        line.push_str(&text);
+    }
+    if !same_style(&current, &default) {
+        line.push_str("\x1b[0m");
+    }
+    line
+}
+
+/// セルのスタイルにする SGR（一度リセットしてから指定する）
+fn sgr(cell: &Cell) -> String {
+    let mut codes = vec!["0".to_string()];
+    codes.extend(FLAG_CODES.iter().filter(|(flag, _)| cell.flags.contains(*flag)).map(|(_, code)| code.to_string()));
+    codes.extend(color_code(cell.fg, 30));
+    codes.extend(color_code(cell.bg, 40));
+    format!("\x1b[{}m", codes.join(";"))
+}
+
+/// 色の SGR（base は前景色なら 30、背景色なら 40、既定の色なら None）
+fn color_code(color: Color, base: u8) -> Option<String> {
+    match color.palette_index() {
+        Some(index @ 0..=7) => Some((base + index).to_string()),
+        Some(index @ 8..=15) => Some((base + 60 + index - 8).to_string()),
+        Some(_) => None,
+        None => Some(format!("{};2;{};{};{}", base + 8, color.r, color.g, color.b)),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// HTML・RTF
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 選択範囲の行の末尾の空行を除く
+fn trim_rows(rows: &[Vec<Cell>]) -> Vec<Vec<(Cell, String)>> {
+    let mut lines: Vec<_> = rows.iter().map(|row| runs(row)).collect();
+    while lines.last().is_some_and(Vec::is_empty) {
+        lines.pop();
+    }
+    lines
+}
+
+/// 表示する文字色と背景色（反転・非表示を反映した実際の色）
+fn colors(cell: &Cell, palette: &Palette) -> (Color, Color) {
+    let (mut fg, mut bg) = (palette.resolve(cell.fg), palette.resolve(cell.bg));
+    if cell.flags.contains(CellFlags::INVERSE) {
+        std::mem::swap(&mut fg, &mut bg);
+    }
+    if cell.flags.contains(CellFlags::HIDDEN) {
+        fg = bg;
+    }
+    (fg, bg)
+}
+
+/// `#rrggbb`
+fn hex(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+/// 選択範囲の HTML（等幅の `<pre>` に、既定と違うスタイルの文字だけ `<span>` で色を付ける）
+pub fn html(rows: &[Vec<Cell>], palette: &Palette) -> String {
+    let mut html = format!(
+        "<meta charset=\"utf-8\"><pre style=\"font-family: Menlo, Consolas, 'DejaVu Sans Mono', monospace; \
+         color: {}; background-color: {}; padding: 8px;\">",
+        hex(palette.foreground),
+        hex(palette.background)
+    );
+    for (index, line) in trim_rows(rows).iter().enumerate() {
+        if index > 0 {
+            html.push('\n');
+        }
+        for (style, text) in line {
+            let text = escape_html(text);
+            if same_style(style, &Cell::default()) {
+                html.push_str(&text);
+                continue;
+            }
+            let (fg, bg) = colors(style, palette);
+            let mut css = vec![format!("color: {}", hex(fg))];
+            if bg != palette.background {
+                css.push(format!("background-color: {}", hex(bg)));
+            }
+            if style.flags.contains(CellFlags::BOLD) {
+                css.push("font-weight: bold".into());
+            }
+            if style.flags.contains(CellFlags::ITALIC) {
+                css.push("font-style: italic".into());
+            }
+            let decorations: Vec<&str> = [(CellFlags::UNDERLINE, "underline"), (CellFlags::STRIKEOUT, "line-through")]
+                .into_iter()
+                .filter(|(flag, _)| style.flags.contains(*flag))
+                .map(|(_, name)| name)
+                .collect();
+            if !decorations.is_empty() {
+                css.push(format!("text-decoration: {}", decorations.join(" ")));
+            }
+            html.push_str(&format!("<span style=\"{}\">{}</span>", css.join("; "), text));
+        }
+    }
+    html.push_str("</pre>");
+    html
+}
+
+/// HTML の特別な文字を参照にする
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 選択範囲の RTF（macOS のアプリに貼るとき、文字の並びごとに色とスタイルのグループにする）
+pub fn rtf(rows: &[Vec<Cell>], palette: &Palette) -> String {
+    let lines = trim_rows(rows);
+    // 色の表（1 番が文字色、2 番が背景色）
+    let mut table = vec![palette.foreground, palette.background];
+    let mut body = String::new();
+    for (index, line) in lines.iter().enumerate() {
+        if index > 0 {
+            body.push_str("\\line\n");
+        }
+        for (style, text) in line {
+            let (fg, bg) = colors(style, palette);
+            let mut group = String::from("{");
+            for (prefix, color) in [("cf", fg), ("cb", bg)] {
+                let number = match table.iter().position(|c| *c == color) {
+                    Some(position) => position + 1,
+                    None => {
+                        table.push(color);
+                        table.len()
+                    }
+                };
+                group.push_str(&format!("\\{}{}", prefix, number));
+            }
+            for (flag, control) in [
+                (CellFlags::BOLD, "\\b"),
+                (CellFlags::ITALIC, "\\i"),
+                (CellFlags::UNDERLINE, "\\ul"),
+                (CellFlags::STRIKEOUT, "\\strike"),
+            ] {
+                if style.flags.contains(flag) {
+                    group.push_str(control);
+                }
+            }
+            group.push(' ');
+            group.push_str(&escape_rtf(text));
+            group.push('}');
+            body.push_str(&group);
+        }
+    }
+    let colors: String = table.iter().map(|c| format!("\\red{}\\green{}\\blue{};", c.r, c.g, c.b)).collect();
+    format!(
+        "{{\\rtf1\\ansi\\deff0{{\\fonttbl{{\\f0\\fmodern Menlo;}}}}{{\\colortbl;{}}}\\f0\\fs24\\cb2\\cf1\n{}}}",
+        colors, body
+    )
+}
+
+/// RTF の特別な文字をエスケープし、ASCII 以外は UTF-16 の `\\uN?` にする
+fn escape_rtf(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c.is_ascii() => escaped.push(c),
+            c => {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    escaped.push_str(&format!("\\u{}?", *unit as i16));
+                }
+            }
+        }
+    }
+    escaped
+}
+
+/// 保存先（`~/` はホーム、相対パスは dir から、指定がなければ dir にタイムスタンプ付きの名前）
+pub fn resolve_path(path: Option<&str>, dir: &Path) -> PathBuf {
+    let Some(path) = path else {
+        let stamp = chrono::Local::now().format("%Y-%m-%d %H.%M.%S");
+        return dir.join(format!("UmiTerm {}.txt", stamp));
+    };
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => Path::new(&home).join(rest),
+        _ => dir.join(path),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(text: &str, fg: Color, flags: CellFlags) -> Vec<Cell> {
+        let mut cells: Vec<Cell> = text.chars().map(|character| Cell { character, fg, flags, ..Cell::default() }).collect();
+        cells.resize(12, Cell::default());
+        cells
+    }
+
+    #[test]
+    fn test_write_rows() {
+        let mut cells = row("$ ls", Color::DEFAULT_FG, CellFlags::empty());
+        cells[2..4].copy_from_slice(&row("ls", Color::indexed(9), CellFlags::BOLD)[..2]);
+        // 全角文字の2セル目は空白で埋まっている
+        let wide = row("海  a", Color::rgb(1, 2, 3), CellFlags::empty());
+        let rows = vec![cells, row("", Color::DEFAULT_FG, CellFlags::empty()), wide, row("", Color::DEFAULT_FG, CellFlags::empty())];
+
+        let mut plain = Vec::new();
+        write_rows(&mut plain, rows.clone(), false).unwrap();
+        assert_eq!(String::from_utf8(plain).unwrap(), "$ ls\n\n海 a\n");
+
+        let mut ansi = Vec::new();
+        write_rows(&mut ansi, rows, true).unwrap();
+        assert_eq!(
+            String::from_utf8(ansi).unwrap(),
+            "$ \x1b[0;1;91mls\x1b[0m\n\n\x1b[0;38;2;1;2;3m海 a\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_ansi_line() {
+        // 背景色のある空白は残す
+        let mut cells = row("x", Color::DEFAULT_FG, CellFlags::empty());
+        cells[1].bg = Color::indexed(4);
+        assert_eq!(ansi_line(&cells), "x\x1b[0;44m \x1b[0m");
+        assert_eq!(ansi_line(&row("  ", Color::indexed(2), CellFlags::UNDERLINE)), "");
+    }
+
+    #[test]
+    fn test_html() {
+        let palette = Palette::default();
+        let mut cells = row("a<b", Color::DEFAULT_FG, CellFlags::empty());
+        cells[2] = Cell { character: 'b', fg: Color::indexed(1), flags: CellFlags::BOLD | CellFlags::UNDERLINE, ..Cell::default() };
+        let rows = vec![cells, row("x", Color::rgb(1, 2, 3), CellFlags::INVERSE), row("", Color::DEFAULT_FG, CellFlags::empty())];
+        let html = html(&rows, &palette);
+        assert!(html.starts_with("<meta charset=\"utf-8\"><pre style=\""));
+        assert!(html.contains(&format!("background-color: {};", hex(palette.background))));
+        // パレットの色は実際の色にする、末尾の空行は除く
+        let body = html.split_once("padding: 8px;\">").unwrap().1;
+        assert_eq!(
+            body,
+            format!(
+                "a&lt;<span style=\"color: {}; font-weight: bold; text-decoration: underline\">b</span>\n\
+                 <span style=\"color: {}; background-color: #010203\">x</span></pre>",
+                hex(palette.ansi[1]),
+                hex(palette.background)
+            )
+        );
+    }
+
+    #[test]
+    fn test_rtf() {
+        let palette = Palette::default();
+        let mut cells = row("{海 }", Color::DEFAULT_FG, CellFlags::empty());
+        cells[0].fg = Color::rgb(1, 2, 3);
+        let rtf = rtf(&[cells, row("ok", Color::DEFAULT_FG, CellFlags::ITALIC)], &palette);
+        let fg = palette.foreground;
+        assert!(rtf.starts_with("{\\rtf1\\ansi"));
+        assert!(rtf.contains(&format!("{{\\colortbl;\\red{}\\green{}\\blue{};", fg.r, fg.g, fg.b)));
+        assert!(rtf.contains("\\red1\\green2\\blue3;}"));
+        assert!(rtf.ends_with("{\\cf3\\cb2 \\{}{\\cf1\\cb2 \\u28023?\\}}\\line\n{\\cf1\\cb2\\i ok}}"));
+        assert_eq!(escape_rtf("😀"), "\\u-10179?\\u-8704?");
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let dir = Path::new("/tmp");
+        assert_eq!(resolve_path(Some("log.txt"), dir), Path::new("/tmp/log.txt"));
+        assert_eq!(resolve_path(Some("/var/log/a.txt"), dir), Path::new("/var/log/a.txt"));
+        let stamped = resolve_path(None, dir);
+        assert!(stamped.starts_with(dir) && stamped.to_string_lossy().ends_with(".txt"));
+    }
+}
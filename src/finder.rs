@@ -0,0 +1,232 @@
+//! スクロールバック検索（ファジーファインダー）
+//!
+//! ペインの履歴（スクロールバックとメイン画面）の全行を取り込み、
+//! 入力した文字列で絞り込む（fzf のように、文字が順に含まれていれば一致）
+//! コマンドパレットも同じ絞り込みで操作の名前を選ぶ
+
+// ═══════════════════════════════════════════════════════════════════════════
+// スコア
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 1文字一致するごとの点
+const SCORE_MATCH: i32 = 16;
+/// 直前の文字に続けて一致したときの加点
+const BONUS_CONSECUTIVE: i32 = 8;
+/// 単語の先頭で一致したときの加点
+const BONUS_WORD_START: i32 = 8;
+/// 一致の間に挟まった文字ごとの減点（上限あり）
+const PENALTY_GAP: i32 = 1;
+const MAX_GAP_PENALTY: i32 = 8;
+
+/// 文字列にクエリの文字が順に含まれていればスコアを返す（高いほどよく一致）
+///
+/// クエリに大文字が含まれていれば大文字・小文字を区別する（スマートケース）
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    let case_sensitive = query.chars().any(char::is_uppercase);
+    let normalize = |c: char| if case_sensitive { c } else { c.to_ascii_lowercase() };
+
+    let mut query = query.chars().map(normalize).peekable();
+    let mut score = 0;
+    let mut prev: Option<char> = None;
+    let mut last_match: Option<usize> = None;
+
+    for (i, c) in text.chars().enumerate() {
+        let Some(&wanted) = query.peek() else { break };
+        if normalize(c) == wanted {
+            score += SCORE_MATCH;
+            match last_match {
+                Some(last) if last + 1 == i => score += BONUS_CONSECUTIVE,
+                Some(last) => score -= (PENALTY_GAP * (i - last - 1) as i32).min(MAX_GAP_PENALTY),
+                None => {}
+            }
+            if prev.is_none_or(|p| !p.is_alphanumeric()) {
+                score += BONUS_WORD_START;
+            }
+            last_match = Some(i);
+            query.next();
+        }
+        prev = Some(c);
+    }
+
+    query.peek().is_none().then_some(score)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ファインダー
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// ファインダーで選ぶもの
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FinderKind {
+    /// スクロールバックの行
+    #[default]
+    History,
+    /// コマンドパレットの項目
+    Palette,
+    /// 前にコピーしたテキスト
+    ClipboardHistory,
+    /// 最近の警告・エラー
+    Log,
+}
+
+/// スクロールバック検索の状態
+#[derive(Default)]
+pub struct Finder {
+    /// 表示中かどうか
+    pub visible: bool,
+    /// 選んでいるもの
+    pub kind: FinderKind,
+    /// 入力中の文字列
+    pub query: String,
+    /// 取り込んだ履歴の行（古い順）
+    lines: Vec<String>,
+    /// 絞り込んだ行の番号（よく一致する順、同点なら新しい順）
+    matches: Vec<usize>,
+    /// 選択中の候補（matches の番号）
+    pub selected: usize,
+}
+
+impl Finder {
+    /// 履歴の行を取り込んで開く
+    pub fn open(&mut self, lines: Vec<String>) {
+        self.open_as(FinderKind::History, lines);
+    }
+
+    /// 選ぶものを指定して開く
+    pub fn open_as(&mut self, kind: FinderKind, lines: Vec<String>) {
+        self.visible = true;
+        self.kind = kind;
+        self.query.clear();
+        self.lines = lines;
+        self.refilter();
+    }
+
+    /// 閉じる（取り込んだ行も捨てる）
+    pub fn close(&mut self) {
+        self.visible = false;
+        self.kind = FinderKind::History;
+        self.query.clear();
+        self.lines = Vec::new();
+        self.matches = Vec::new();
+        self.selected = 0;
+    }
+
+    /// 文字列を入力
+    pub fn push_str(&mut self, text: &str) {
+        self.query.push_str(text);
+        self.refilter();
+    }
+
+    /// 1文字削除
+    pub fn pop(&mut self) {
+        if self.query.pop().is_some() {
+            self.refilter();
+        }
+    }
+
+    /// 選択を移動（正の値で下へ、端で止まる）
+    pub fn move_selection(&mut self, delta: isize) {
+        let last = self.matches.len().saturating_sub(1) as isize;
+        self.selected = (self.selected as isize + delta).clamp(0, last) as usize;
+    }
+
+    /// 選択中の行（履歴での番号とテキスト）
+    pub fn selected_line(&self) -> Option<(usize, &str)> {
+        let index = *self.matches.get(self.selected)?;
+        Some((index, &self.lines[index]))
+    }
+
+    /// 絞り込んだ行（よく一致する順）
+    pub fn results(&self) -> impl Iterator<Item = &str> {
+        self.matches.iter().map(|&index| self.lines[index].as_str())
+    }
+
+    /// 絞り込んだ行の数
+    pub fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    /// 取り込んだ行の数（空行を除く）
+    pub fn line_count(&self) -> usize {
+        self.lines.iter().filter(|line| !line.is_empty()).count()
+    }
+
+    /// クエリで絞り込み直す（選択は先頭に戻す）
+    fn refilter(&mut self) {
+        let mut scored: Vec<(i32, usize)> = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
+            .filter_map(|(index, line)| Some((fuzzy_score(&self.query, line)?, index)))
+            .collect();
+        scored.sort_by(|a, b| b.cmp(a));
+        self.matches = scored.into_iter().map(|(_, index)| index).collect();
+        self.selected = 0;
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        // 文字が順に含まれていれば一致
+        assert!(fuzzy_score("cgb", "cargo build").is_some());
+        assert_eq!(fuzzy_score("bc", "cargo"), None);
+
+        // 連続・単語の先頭での一致が高い
+        let tight = fuzzy_score("build", "cargo build").unwrap();
+        let loose = fuzzy_score("build", "b-u-i-l-d").unwrap();
+        assert!(tight > loose);
+
+        // スマートケース
+        assert!(fuzzy_score("error", "ERROR: failed").is_some());
+        assert_eq!(fuzzy_score("Error", "error: failed"), None);
+    }
+
+    #[test]
+    fn test_finder() {
+        let mut finder = Finder::default();
+        let lines = ["$ cargo build", "", "error: mismatched types", "$ cargo test", "test result: ok"];
+        finder.open(lines.iter().map(|s| s.to_string()).collect());
+
+        // 空のクエリでは空行以外を新しい順に
+        assert_eq!(finder.line_count(), 4);
+        assert_eq!(finder.results().next(), Some("test result: ok"));
+
+        // 絞り込むと選択は先頭に戻る
+        finder.move_selection(2);
+        finder.push_str("cargo");
+        assert_eq!(finder.match_count(), 2);
+        assert_eq!(finder.selected_line(), Some((3, "$ cargo test")));
+
+        // 選択は端で止まる
+        finder.move_selection(5);
+        assert_eq!(finder.selected_line(), Some((0, "$ cargo build")));
+
+        finder.push_str("x");
+        assert_eq!(finder.selected_line(), None);
+        finder.pop();
+        assert_eq!(finder.match_count(), 2);
+
+        finder.close();
+        assert!(!finder.visible);
+        assert_eq!(finder.match_count(), 0);
+
+        // コマンドパレットも同じように絞り込み、閉じるとスクロールバック検索に戻る
+        finder.open_as(FinderKind::Palette, vec!["split-vertical".into(), "new-tab".into()]);
+        finder.push_str("sv");
+        assert_eq!(finder.selected_line(), Some((0, "split-vertical")));
+        finder.close();
+        assert_eq!(finder.kind, FinderKind::History);
+    }
+}
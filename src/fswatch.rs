@@ -0,0 +1,80 @@
+//! ディレクトリの監視
+//!
+//! エクスプローラーで表示中のディレクトリを notify で監視し、ターミナルで
+//! 実行したコマンドがファイルを作成・削除・名前変更したら一覧を読み直せるようにする
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use notify::event::{EventKind, ModifyKind};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::idle;
+
+/// 表示中のディレクトリの監視（サブディレクトリは含めない）
+pub struct DirWatcher {
+    /// 監視（作れなかった環境では None で、何もしない）
+    watcher: Option<RecommendedWatcher>,
+    /// 監視中のディレクトリ
+    watched: HashSet<PathBuf>,
+    /// 前回取り出してから一覧が変わったか（監視スレッドが立てる）
+    changed: Arc<AtomicBool>,
+}
+
+impl DirWatcher {
+    pub fn new() -> Self {
+        let changed = Arc::new(AtomicBool::new(false));
+        let flag = changed.clone();
+        let watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+            let Ok(event) = result else { return };
+            // 中身の書き込みでは一覧は変わらない
+            let listing = matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_))
+            );
+            if listing && !flag.swap(true, Ordering::AcqRel) {
+                // 省電力モードで眠っているイベントループを起こす
+                idle::notify_output();
+            }
+        });
+        let watcher = match watcher {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                log::warn!("ディレクトリの監視を開始できません: {}", e);
+                None
+            }
+        };
+        Self { watcher, watched: HashSet::new(), changed }
+    }
+
+    /// 監視するディレクトリを合わせる（増えたものを監視し、減ったものをやめる）
+    pub fn sync(&mut self, dirs: &[PathBuf]) {
+        let Some(watcher) = self.watcher.as_mut() else { return };
+        let wanted: HashSet<&PathBuf> = dirs.iter().collect();
+        self.watched.retain(|dir| {
+            let keep = wanted.contains(dir);
+            if !keep {
+                let _ = watcher.unwatch(dir);
+            }
+            keep
+        });
+        for dir in dirs {
+            if self.watched.contains(dir) {
+                continue;
+            }
+            match watcher.watch(dir, RecursiveMode::NonRecursive) {
+                Ok(()) => {
+                    self.watched.insert(dir.clone());
+                }
+                Err(e) => log::debug!("ディレクトリを監視できません: {:?} ({})", dir, e),
+            }
+        }
+    }
+
+    /// 前回から一覧が変わったかを取り出してクリア
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::AcqRel)
+    }
+}
@@ -6,6 +6,8 @@
 
 use std::ops::{Index, IndexMut};
 
+use unicode_width::UnicodeWidthChar;
+
 // ═══════════════════════════════════════════════════════════════════════════
 // セル（1文字分のデータ）
 // ═══════════════════════════════════════════════════════════════════════════
@@ -28,19 +30,34 @@ impl Default for Cell {
     fn default() -> Self {
         Self {
             character: ' ',
-            fg: Color::EMERALD, // エメラルドブルー
-            bg: Color::BLACK,
+            fg: Color::DEFAULT_FG,
+            bg: Color::DEFAULT_BG,
             flags: CellFlags::empty(),
         }
     }
 }
 
+/// 行の文字のうち、全角文字の後ろの空き（2セル目）を '\0' にする
+///
+/// グリッドでは2セル目は空白で埋まっているため、テキストとして取り出すときに区別する
+pub fn mark_wide_spacers(chars: &mut [char]) {
+    let mut prev_wide = false;
+    for c in chars.iter_mut() {
+        if prev_wide {
+            *c = '\0';
+            prev_wide = false;
+        } else {
+            prev_wide = c.width() == Some(2);
+        }
+    }
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 // カラー
 // ═══════════════════════════════════════════════════════════════════════════
 
 /// RGBA カラー（各チャンネル 8bit）
-#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -59,12 +76,40 @@ impl Color {
     pub const MAGENTA: Self = Self { r: 255, g: 0, b: 255, a: 255 };
     /// エメラルドブルー（デフォルト文字色）
     pub const EMERALD: Self = Self { r: 80, g: 220, b: 200, a: 255 };
+    /// パレットの文字色（描画時に設定の色になる）
+    pub const DEFAULT_FG: Self = Self::indexed(16);
+    /// パレットの背景色（描画時に設定の色になる）
+    pub const DEFAULT_BG: Self = Self::indexed(17);
 
     /// RGB から生成
     pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
         Self { r, g, b, a: 255 }
     }
 
+    /// パレットの色（ANSI 16色と文字色・背景色）を指す色
+    ///
+    /// セルには番号だけを入れておき、描画時に `Palette::resolve` で実際の色にする
+    /// （設定ファイルで色を変えると、すでに表示している文字にも反映される）
+    /// アルファ 0 で区別する（セルの色は常に不透明）
+    pub const fn indexed(index: u8) -> Self {
+        Self { r: index, g: 0, b: 0, a: 0 }
+    }
+
+    /// パレットの色なら番号
+    pub fn palette_index(self) -> Option<u8> {
+        (self.a == 0).then_some(self.r)
+    }
+
+    /// `#rrggbb` 形式の文字列から変換
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#')?;
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some(Self::rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+
     /// ANSI 256色パレットから変換
     pub fn from_ansi256(code: u8) -> Self {
         match code {
@@ -103,8 +148,14 @@ impl Color {
         }
     }
 
+    /// 暗い色か（ウィンドウの枠をライト・ダークのどちらで描くかに使う）
+    pub fn is_dark(self) -> bool {
+        // ITU-R BT.709 の輝度
+        0.2126 * f32::from(self.r) + 0.7152 * f32::from(self.g) + 0.0722 * f32::from(self.b) < 128.0
+    }
+
     /// 浮動小数点数の配列に変換（GPU用）
-    pub fn to_f32_array(&self) -> [f32; 4] {
+    pub fn to_f32_array(self) -> [f32; 4] {
         [
             self.r as f32 / 255.0,
             self.g as f32 / 255.0,
@@ -114,13 +165,75 @@ impl Color {
     }
 }
 
+/// 描画に使う色の組（設定ファイルの `[colors]` やテーマで変更できる）
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+    /// 文字色
+    pub foreground: Color,
+    /// 背景色
+    pub background: Color,
+    /// カーソルの色
+    pub cursor: Color,
+    /// 選択範囲の文字色
+    pub selection_fg: Color,
+    /// 選択範囲の背景色
+    pub selection_bg: Color,
+    /// エクスプローラーやタブバーなどの強調色
+    pub accent: Color,
+    /// ANSI 16色（0〜7 が標準、8〜15 が明るい色）
+    pub ansi: [Color; 16],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            foreground: Color::EMERALD,
+            background: Color::BLACK,
+            cursor: Color::EMERALD,
+            selection_fg: Color::WHITE,
+            selection_bg: Color::rgb(51, 128, 179),
+            accent: Color::EMERALD,
+            ansi: [
+                Color::BLACK,
+                Color::RED,
+                Color::GREEN,
+                Color::YELLOW,
+                Color::BLUE,
+                Color::MAGENTA,
+                Color::CYAN,
+                Color::WHITE,
+                Color::rgb(128, 128, 128),
+                Color::rgb(255, 0, 0),
+                Color::rgb(0, 255, 0),
+                Color::rgb(255, 255, 0),
+                Color::rgb(0, 0, 255),
+                Color::rgb(255, 0, 255),
+                Color::rgb(0, 255, 255),
+                Color::rgb(255, 255, 255),
+            ],
+        }
+    }
+}
+
+impl Palette {
+    /// セルの色を実際の色にする（パレットの色以外はそのまま）
+    pub fn resolve(&self, color: Color) -> Color {
+        match color.palette_index() {
+            Some(16) => self.foreground,
+            Some(17) => self.background,
+            Some(index) => self.ansi.get(index as usize).copied().unwrap_or(self.foreground),
+            None => color,
+        }
+    }
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 // セルフラグ（ビットフラグで効率的に管理）
 // ═══════════════════════════════════════════════════════════════════════════
 
 bitflags::bitflags! {
     /// セルのスタイルフラグ
-    #[derive(Clone, Copy, Debug, PartialEq, Default)]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
     pub struct CellFlags: u8 {
         const BOLD       = 0b0000_0001;
         const ITALIC     = 0b0000_0010;
@@ -193,6 +306,7 @@ impl Grid {
 
     /// 指定位置のセルを変更可能な参照で取得
     #[inline]
+    #[allow(dead_code)]
     pub fn get_mut(&mut self, col: usize, row: usize) -> Option<&mut Cell> {
         if col < self.cols && row < self.rows {
             self.dirty_lines[row] = true;
@@ -231,6 +345,7 @@ impl Grid {
     }
 
     /// 行をスクロールアップ（最下行が空になる）
+    #[allow(dead_code)]
     pub fn scroll_up(&mut self, amount: usize) {
         if amount >= self.rows {
             self.clear();
@@ -270,6 +385,13 @@ impl Grid {
         let start = row * self.cols;
         &self.cells[start..start + self.cols]
     }
+
+    /// 行全体の可変スライスを取得（行をダーティにする）
+    pub fn row_slice_mut(&mut self, row: usize) -> &mut [Cell] {
+        self.dirty_lines[row] = true;
+        let start = row * self.cols;
+        &mut self.cells[start..start + self.cols]
+    }
 }
 
 // インデックスアクセスを実装（grid[(col, row)] でアクセス可能に）
@@ -292,6 +414,21 @@ impl IndexMut<(usize, usize)> for Grid {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_mark_wide_spacers() {
+        let mut chars: Vec<char> = "日 本語 a".chars().collect();
+        mark_wide_spacers(&mut chars);
+        assert_eq!(chars, ['日', '\0', '本', '\0', ' ', 'a']);
+    }
+
+    #[test]
+    fn test_is_dark() {
+        assert!(Color::BLACK.is_dark());
+        assert!(Color::rgb(0x2e, 0x34, 0x40).is_dark());
+        assert!(!Color::WHITE.is_dark());
+        assert!(!Color::rgb(0xfd, 0xf6, 0xe3).is_dark());
+    }
+
     #[test]
     fn test_grid_basic() {
         let mut grid = Grid::new(80, 24);
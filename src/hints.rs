@@ -0,0 +1,568 @@
+//! ヒントモード（画面上の URL・パス・ハッシュを文字ラベルで選ぶ）
+//!
+//! 表示中の画面を正規表現で走査し、一致した箇所に短いラベルを重ねる
+//! ラベルを入力すると一致した文字列をコピーする（大文字で入力すると開く）
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use regex::{Captures, Regex};
+
+use crate::config::{self, LinkConfig};
+use crate::editor::shell_quote;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 規則
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// ラベルに使う文字（打ちやすい順）
+const LABEL_CHARS: &str = "asdfghjklqwertyuiopzxcvbnm";
+
+/// 一致の種類
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HintKind {
+    Url,
+    Path,
+    Hash,
+    Ip,
+    /// 環境変数で追加した規則
+    Custom,
+    /// 設定ファイルの `[[links]]` の規則（URL かコマンドで開く）
+    Link,
+}
+
+/// リンクの規則の開き方（`{0}` は一致全体、`{1}`〜`{9}` は正規表現のグループ）
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LinkTemplate {
+    Url(String),
+    Command(String),
+}
+
+impl LinkTemplate {
+    /// 一致したグループを埋め込んで開く対象にする（コマンドの値はシェル用にクォートする）
+    fn expand(&self, caps: &Captures) -> OpenTarget {
+        let fill = |template: &str, quote: bool| {
+            (0..10).fold(template.to_string(), |text, index| {
+                let value = caps.get(index).map_or("", |m| m.as_str());
+                let value = if quote { shell_quote(value) } else { value.to_string() };
+                text.replace(&format!("{{{}}}", index), &value)
+            })
+        };
+        match self {
+            Self::Url(template) => OpenTarget::Url(fill(template, false)),
+            Self::Command(template) => OpenTarget::Command(fill(template, true)),
+        }
+    }
+}
+
+/// 一致を探す規則
+pub struct HintRule {
+    pub kind: HintKind,
+    regex: Regex,
+    /// リンクの規則の開き方
+    link: Option<LinkTemplate>,
+}
+
+impl HintRule {
+    fn new(kind: HintKind, pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self { kind, regex: Regex::new(pattern)?, link: None })
+    }
+}
+
+/// 組み込みの規則（前にあるものほど優先）
+fn builtin_rules() -> Vec<HintRule> {
+    [
+        (HintKind::Url, r#"(?:https?|ftp|file)://[^\s<>"'`()\[\]{}]+"#),
+        (HintKind::Ip, r"\b(?:\d{1,3}\.){3}\d{1,3}(?::\d{1,5})?\b"),
+        (HintKind::Path, r"(?:~|\.{1,2})?/?(?:[\w.-]+/)+[\w.-]+(?::\d+(?::\d+)?)?"),
+        (HintKind::Hash, r"\b[0-9a-f]{7,40}\b"),
+    ]
+    .into_iter()
+    .map(|(kind, pattern)| HintRule::new(kind, pattern).expect("組み込みの正規表現"))
+    .collect()
+}
+
+/// 規則の一覧（環境変数 `UMITERM_HINT_PATTERNS` に改行区切りで正規表現を書くと先頭に追加される）
+///
+/// 設定ファイルの `[[links]]` の規則は、追加の規則と組み込みの規則の間
+pub fn rules_from_env() -> Vec<HintRule> {
+    let custom = config::var("UMITERM_HINT_PATTERNS").unwrap_or_default();
+    let links = link_rules(&config::current().links).unwrap_or_else(|e| {
+        log::warn!("{:#}", e);
+        Vec::new()
+    });
+    parse_custom_rules(&custom).into_iter().chain(links).chain(builtin_rules()).collect()
+}
+
+/// 設定ファイルの `[[links]]` を読む（url か command のどちらか一つ）
+pub fn link_rules(configs: &[LinkConfig]) -> Result<Vec<HintRule>> {
+    configs
+        .iter()
+        .map(|config| {
+            let link = match (&config.url, &config.command) {
+                (Some(url), None) => LinkTemplate::Url(url.clone()),
+                (None, Some(command)) => LinkTemplate::Command(command.clone()),
+                _ => anyhow::bail!("links には url か command のどちらか一つを書きます: {:?}", config.regex),
+            };
+            let regex = Regex::new(&config.regex)
+                .with_context(|| format!("links の正規表現が正しくありません: {:?}", config.regex))?;
+            Ok(HintRule { kind: HintKind::Link, regex, link: Some(link) })
+        })
+        .collect()
+}
+
+/// 追加の規則がすべて正しいか（設定ファイルを読むときに確かめる）
+pub fn check_patterns(value: &str) -> Result<()> {
+    for pattern in value.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        Regex::new(pattern).with_context(|| format!("ヒントの正規表現が正しくありません: {:?}", pattern))?;
+    }
+    Ok(())
+}
+
+/// 追加の規則を読む（正しくない正規表現は警告して飛ばす）
+fn parse_custom_rules(value: &str) -> Vec<HintRule> {
+    value
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .filter_map(|pattern| match HintRule::new(HintKind::Custom, pattern) {
+            Ok(rule) => Some(rule),
+            Err(e) => {
+                log::warn!("ヒントの正規表現が正しくありません: {:?} ({})", pattern, e);
+                None
+            }
+        })
+        .collect()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 走査
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 画面上の一致
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hint {
+    pub kind: HintKind,
+    /// 一致した文字列
+    pub text: String,
+    /// 画面上の行と先頭の列
+    pub row: usize,
+    pub col: usize,
+    /// 入力して選ぶラベル
+    pub label: String,
+    /// リンクの規則で開く対象
+    pub link: Option<OpenTarget>,
+}
+
+/// 画面の行（セル単位、全角文字の後ろの空きは '\0'）から一致を探してラベルを付ける
+///
+/// 重なった一致は先に書かれた規則を優先し、同じ文字列には同じラベルを付ける
+/// ラベルは画面の下（新しい出力）にあるものほど短く打ちやすい
+pub fn scan(rows: &[Vec<char>], rules: &[HintRule]) -> Vec<Hint> {
+    let mut hints = Vec::new();
+    for (row, cells) in rows.iter().enumerate() {
+        let (cols, text): (Vec<usize>, String) = cells
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| **c != '\0')
+            .map(|(col, c)| (col, *c))
+            .unzip();
+        // 文字列のバイト位置から列を引く
+        let col_at = |byte: usize| cols[text[..byte].chars().count()];
+
+        let mut taken: Vec<(usize, usize)> = Vec::new();
+        let mut found = Vec::new();
+        for rule in rules {
+            for caps in rule.regex.captures_iter(&text) {
+                let m = caps.get(0).expect("一致全体");
+                let matched = trim_match(rule.kind, m.as_str());
+                if matched.is_empty() || !is_valid(rule.kind, matched) {
+                    continue;
+                }
+                let span = (m.start(), m.start() + matched.len());
+                if taken.iter().any(|&(start, end)| span.0 < end && start < span.1) {
+                    continue;
+                }
+                taken.push(span);
+                found.push(Hint {
+                    kind: rule.kind,
+                    text: matched.to_string(),
+                    row,
+                    col: col_at(span.0),
+                    label: String::new(),
+                    link: rule.link.as_ref().map(|link| link.expand(&caps)),
+                });
+            }
+        }
+        found.sort_by_key(|hint| hint.col);
+        hints.extend(found);
+    }
+    assign_labels(&mut hints);
+    hints
+}
+
+/// 末尾の句読点など、一致に含めない文字を落とす
+fn trim_match(kind: HintKind, text: &str) -> &str {
+    match kind {
+        HintKind::Url | HintKind::Path => text.trim_end_matches(['.', ',', ';', ':', '!', '?']),
+        _ => text,
+    }
+}
+
+/// 正規表現だけでは判定できない条件（数字だけのハッシュ、範囲外のIP）
+fn is_valid(kind: HintKind, text: &str) -> bool {
+    match kind {
+        HintKind::Hash => text.chars().any(|c| c.is_ascii_alphabetic()) && text.chars().any(|c| c.is_ascii_digit()),
+        HintKind::Ip => {
+            let address = text.split(':').next().unwrap_or(text);
+            address.split('.').all(|octet| octet.parse::<u8>().is_ok())
+        }
+        _ => true,
+    }
+}
+
+/// ラベルを付ける（下の一致から順に。26個までは1文字、それより多ければすべて2文字）
+fn assign_labels(hints: &mut [Hint]) {
+    let chars: Vec<char> = LABEL_CHARS.chars().collect();
+    let mut distinct: Vec<String> = Vec::new();
+    for hint in hints.iter().rev() {
+        if !distinct.contains(&hint.text) {
+            distinct.push(hint.text.clone());
+        }
+    }
+    let two = distinct.len() > chars.len();
+    let label = |index: usize| -> Option<String> {
+        if two {
+            let (first, second) = (index / chars.len(), index % chars.len());
+            Some(format!("{}{}", chars.get(first)?, chars[second]))
+        } else {
+            Some(chars[index].to_string())
+        }
+    };
+    for hint in hints.iter_mut() {
+        let index = distinct.iter().position(|text| *text == hint.text).unwrap_or(0);
+        hint.label = label(index).unwrap_or_default();
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ヒントモード
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// ラベルを選んだ後の動作
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HintAction {
+    Copy,
+    Open,
+}
+
+/// ヒントモードの状態
+pub struct HintMode {
+    /// ラベル付きの一致（ラベルが付かなかったものは除く）
+    hints: Vec<Hint>,
+    /// 入力中のラベル
+    pub typed: String,
+}
+
+impl HintMode {
+    pub fn new(hints: Vec<Hint>) -> Self {
+        Self {
+            hints: hints.into_iter().filter(|hint| !hint.label.is_empty()).collect(),
+            typed: String::new(),
+        }
+    }
+
+    /// 一致がなかったか
+    pub fn is_empty(&self) -> bool {
+        self.hints.is_empty()
+    }
+
+    /// 入力中のラベルで始まる一致
+    pub fn visible(&self) -> impl Iterator<Item = &Hint> {
+        self.hints.iter().filter(|hint| hint.label.starts_with(&self.typed))
+    }
+
+    /// ラベルの1文字を入力
+    /// 戻り値: ラベルが決まったらその一致、どのラベルにも合わなくなったら入力を取り消して None
+    pub fn input(&mut self, c: char) -> Option<&Hint> {
+        self.typed.push(c.to_ascii_lowercase());
+        if self.visible().next().is_none() {
+            self.typed.pop();
+            return None;
+        }
+        self.hints.iter().find(|hint| hint.label == self.typed)
+    }
+
+    /// 入力を1文字取り消す
+    pub fn backspace(&mut self) {
+        self.typed.pop();
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 開く
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 開く対象
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OpenTarget {
+    Url(String),
+    /// ファイル（行・列は `path:12:3` の形のときだけ）
+    File { path: PathBuf, line: Option<u32>, col: Option<u32> },
+    /// シェルで実行するコマンド（リンクの規則）
+    Command(String),
+}
+
+impl OpenTarget {
+    /// OS の既定のアプリに渡す文字列（URL かパス）
+    pub fn text(&self) -> String {
+        match self {
+            Self::Url(url) => url.clone(),
+            Self::File { path, .. } => path.display().to_string(),
+            Self::Command(command) => command.clone(),
+        }
+    }
+}
+
+/// 開く対象（パスは作業ディレクトリから解決し、行番号を分ける。開けない種類は None）
+pub fn open_target(hint: &Hint, cwd: &Path) -> Option<OpenTarget> {
+    match hint.kind {
+        HintKind::Url => Some(OpenTarget::Url(hint.text.clone())),
+        HintKind::Path => {
+            let (path, line, col) = split_line_number(&hint.text);
+            let path = match path.strip_prefix("~/") {
+                Some(rest) => std::env::var_os("HOME").map(|home| Path::new(&home).join(rest))?,
+                None => cwd.join(path),
+            };
+            Some(OpenTarget::File { path, line, col })
+        }
+        HintKind::Link => hint.link.clone(),
+        HintKind::Hash | HintKind::Ip | HintKind::Custom => None,
+    }
+}
+
+/// `path:12` / `path:12:3` をパスと行・列に分ける
+fn split_line_number(text: &str) -> (&str, Option<u32>, Option<u32>) {
+    let mut path = text;
+    let mut numbers = Vec::new();
+    for _ in 0..2 {
+        match path.rsplit_once(':') {
+            Some((rest, number)) if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) => {
+                numbers.insert(0, number.parse().ok());
+                path = rest;
+            }
+            _ => break,
+        }
+    }
+    (path, numbers.first().copied().flatten(), numbers.get(1).copied().flatten())
+}
+
+/// 画面の行（セル単位、全角文字の後ろの空きは '\0'）の指定の列にある URL・リンクの規則の一致の開く対象
+pub fn link_at(cells: &[char], col: usize, rules: &[HintRule]) -> Option<OpenTarget> {
+    let (cols, text): (Vec<usize>, String) =
+        cells.iter().enumerate().filter(|(_, c)| **c != '\0').map(|(col, c)| (col, *c)).unzip();
+    let col_at = |byte: usize| cols.get(text[..byte].chars().count()).copied().unwrap_or(cells.len());
+    rules.iter().filter(|rule| matches!(rule.kind, HintKind::Url | HintKind::Link)).find_map(|rule| {
+        rule.regex.captures_iter(&text).find_map(|caps| {
+            let m = caps.get(0)?;
+            let matched = trim_match(rule.kind, m.as_str());
+            if !(col_at(m.start()) <= col && col < col_at(m.start() + matched.len())) {
+                return None;
+            }
+            Some(match &rule.link {
+                Some(link) => link.expand(&caps),
+                None => OpenTarget::Url(matched.to_string()),
+            })
+        })
+    })
+}
+
+/// 設定ファイルの `[open]` のテンプレートからコマンドラインを作る（URL・ファイル名はシェル用にクォートする）
+pub fn opener_command(template: &str, target: &OpenTarget) -> String {
+    match target {
+        OpenTarget::Url(url) => template.replace("{url}", &shell_quote(url)),
+        OpenTarget::File { path, line, col } => template
+            .replace("{file}", &shell_quote(&path.display().to_string()))
+            .replace("{line}", &line.unwrap_or(1).to_string())
+            .replace("{col}", &col.unwrap_or(1).to_string()),
+        OpenTarget::Command(command) => command.clone(),
+    }
+}
+
+/// 開く（`[open]` のコマンドがあればシェルで、なければ OS 標準のコマンドで。終了は待たない）
+pub fn open(target: &OpenTarget) {
+    let config = config::current();
+    let template = match target {
+        OpenTarget::Url(_) => config.open.url.as_deref(),
+        OpenTarget::File { .. } => config.open.file.as_deref(),
+        OpenTarget::Command(_) => Some("{command}"),
+    };
+    let mut cmd = match template {
+        Some(template) => {
+            let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+            let mut cmd = Command::new(shell);
+            cmd.args(["-c", &opener_command(template, target)]);
+            cmd
+        }
+        None => {
+            let mut cmd = if cfg!(target_os = "macos") {
+                Command::new("open")
+            } else if cfg!(target_os = "windows") {
+                let mut cmd = Command::new("cmd");
+                cmd.args(["/C", "start", ""]);
+                cmd
+            } else {
+                Command::new("xdg-open")
+            };
+            cmd.arg(target.text());
+            cmd
+        }
+    };
+
+    cmd.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    match cmd.spawn() {
+        // 終了を待たずにゾンビ化を防ぐため別スレッドで回収
+        Ok(mut child) => {
+            std::thread::spawn(move || {
+                let _ = child.wait();
+            });
+        }
+        Err(e) => log::warn!("{} を開けませんでした: {}", target.text(), e),
+    }
+}
+
+/// 画面に重ねるラベル（レンダラーに渡す）
+pub struct HintLabels {
+    /// ペインのターミナル部分の矩形
+    pub rect: crate::pane::Rect,
+    /// 画面上の列・行と、ラベル・入力済みの文字数
+    pub labels: Vec<(usize, usize, String, usize)>,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(text: &[&str]) -> Vec<Vec<char>> {
+        text.iter().map(|line| line.chars().collect()).collect()
+    }
+
+    #[test]
+    fn test_scan() {
+        let screen = rows(&[
+            "see https://example.com/docs. and src/main.rs:42",
+            "commit 3a7819b at 192.168.0.1:8080, not 1234567 or 999.1.1.1",
+            "again https://example.com/docs",
+        ]);
+        let hints = scan(&screen, &builtin_rules());
+        let found: Vec<(HintKind, &str, usize, usize)> =
+            hints.iter().map(|h| (h.kind, h.text.as_str(), h.row, h.col)).collect();
+        assert_eq!(
+            found,
+            vec![
+                (HintKind::Url, "https://example.com/docs", 0, 4),
+                (HintKind::Path, "src/main.rs:42", 0, 34),
+                (HintKind::Hash, "3a7819b", 1, 7),
+                (HintKind::Ip, "192.168.0.1:8080", 1, 18),
+                (HintKind::Url, "https://example.com/docs", 2, 6),
+            ]
+        );
+
+        // 下の一致ほど短いラベル、同じ文字列は同じラベル
+        assert_eq!(hints[4].label, "a");
+        assert_eq!(hints[0].label, "a");
+        assert_eq!(hints[3].label, "s");
+    }
+
+    #[test]
+    fn test_wide_chars_and_custom_rules() {
+        // 全角文字の後ろでも列はセル単位
+        let screen = vec!["日本 JIRA-123".chars().flat_map(|c| if c.is_ascii() { vec![c] } else { vec![c, '\0'] }).collect()];
+        let rules = parse_custom_rules("[A-Z]+-\\d+\n(unclosed\n");
+        assert_eq!(rules.len(), 1);
+        let hints = scan(&screen, &rules);
+        assert_eq!(hints.len(), 1);
+        assert_eq!((hints[0].kind, hints[0].col), (HintKind::Custom, 5));
+    }
+
+    #[test]
+    fn test_labels() {
+        // 27個以上ならすべて2文字
+        let screen: Vec<Vec<char>> = (0..30).map(|i| format!("/tmp/file{}", i).chars().collect()).collect();
+        let hints = scan(&screen, &builtin_rules());
+        assert_eq!(hints.len(), 30);
+        assert!(hints.iter().all(|hint| hint.label.len() == 2));
+        assert_eq!(hints[29].label, "aa");
+
+        let mut mode = HintMode::new(hints);
+        assert!(mode.input('x').is_none());
+        assert_eq!(mode.typed, "");
+        assert!(mode.input('a').is_none());
+        assert_eq!(mode.visible().count(), 26);
+        let hint = mode.input('S').unwrap();
+        assert_eq!(hint.text, "/tmp/file28");
+    }
+
+    #[test]
+    fn test_open_target() {
+        let cwd = Path::new("/work");
+        let hint = |kind, text: &str| Hint { kind, text: text.to_string(), row: 0, col: 0, label: String::new(), link: None };
+        let file = |path: &str, line, col| Some(OpenTarget::File { path: path.into(), line, col });
+        assert_eq!(open_target(&hint(HintKind::Path, "src/main.rs:42:7"), cwd), file("/work/src/main.rs", Some(42), Some(7)));
+        assert_eq!(open_target(&hint(HintKind::Path, "src/lib.rs:9"), cwd), file("/work/src/lib.rs", Some(9), None));
+        assert_eq!(open_target(&hint(HintKind::Path, "/etc/hosts"), cwd), file("/etc/hosts", None, None));
+        assert_eq!(open_target(&hint(HintKind::Url, "https://a.b/c"), cwd), Some(OpenTarget::Url("https://a.b/c".into())));
+        assert_eq!(open_target(&hint(HintKind::Hash, "3a7819b"), cwd), None);
+    }
+
+    #[test]
+    fn test_opener_command() {
+        let url = OpenTarget::Url("https://a.b/?q=it's".into());
+        assert_eq!(opener_command("firefox --new-tab {url}", &url), r"firefox --new-tab 'https://a.b/?q=it'\''s'");
+        let file = OpenTarget::File { path: "/work/my file.rs".into(), line: Some(12), col: None };
+        assert_eq!(opener_command("code -g {file}:{line}:{col}", &file), "code -g '/work/my file.rs':12:1");
+
+        // 全角文字のあとの列も画面のセルで数える
+        let cells: Vec<char> = "日\0本\0 see https://example.com/a. now".chars().collect();
+        let url = Some(OpenTarget::Url("https://example.com/a".into()));
+        assert_eq!(link_at(&cells, 12, &builtin_rules()), url);
+        assert_eq!(link_at(&cells, 4, &builtin_rules()), None);
+        assert_eq!(link_at(&cells, 30, &builtin_rules()), None);
+    }
+
+    #[test]
+    fn test_link_rules() {
+        let link = |regex: &str, url: Option<&str>, command: Option<&str>| LinkConfig {
+            regex: regex.into(),
+            url: url.map(Into::into),
+            command: command.map(Into::into),
+        };
+        let rules = link_rules(&[
+            link(r"\b([A-Z]+)-(\d+)\b", Some("https://jira.example.com/browse/{1}-{2}"), None),
+            link(r"#(\d+)", None, Some("gh issue view {1} --web")),
+        ])
+        .unwrap();
+        let rules: Vec<HintRule> = rules.into_iter().chain(builtin_rules()).collect();
+
+        // ヒントモードの一致は開く対象を持つ（リンクの規則が組み込みの規則より先）
+        let screen: Vec<Vec<char>> = vec!["fix UMI-42 (#7)".chars().collect()];
+        let hints = scan(&screen, &rules);
+        assert_eq!(hints.len(), 2);
+        let jira = OpenTarget::Url("https://jira.example.com/browse/UMI-42".into());
+        assert_eq!(open_target(&hints[0], Path::new("/")), Some(jira.clone()));
+        assert_eq!(hints[1].link, Some(OpenTarget::Command("gh issue view '7' --web".into())));
+
+        // クリックと同じ位置の一致
+        assert_eq!(link_at(&screen[0], 6, &rules), Some(jira));
+        assert_eq!(link_at(&screen[0], 1, &rules), None);
+
+        assert!(link_rules(&[link("(", Some("x"), None)]).is_err());
+        assert!(link_rules(&[link("x", None, None)]).is_err());
+        assert!(link_rules(&[link("x", Some("a"), Some("b"))]).is_err());
+    }
+}
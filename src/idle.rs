@@ -0,0 +1,160 @@
+//! 省電力モジュール
+//!
+//! すべてのペインで一定時間、PTY出力も入力もなければイベントループを眠らせ、
+//! 再描画を低頻度のティックだけに落とす
+//! 眠っている間にPTY出力が届いたら、パーサースレッドからイベントループを即座に起こす
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::config;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 定数
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// アイドルとみなすまでの秒数の既定値
+const DEFAULT_IDLE_SECS: u64 = 5;
+
+/// アイドル中の再描画間隔
+pub const IDLE_REDRAW_INTERVAL: Duration = Duration::from_secs(1);
+
+/// アイドルとみなすまでの時間（環境変数 `UMITERM_IDLE_SECS` で変更可能）
+pub fn default_timeout() -> Duration {
+    let secs = config::var("UMITERM_IDLE_SECS")
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_IDLE_SECS);
+    Duration::from_secs(secs)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// スリープ状態
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// イベントループが眠っているか
+///
+/// パーサースレッドは眠っているときだけ起こせばよいので、
+/// 出力のたびにイベントを送らずに済む
+pub struct SleepFlag(AtomicBool);
+
+impl SleepFlag {
+    pub const fn new() -> Self {
+        Self(AtomicBool::new(false))
+    }
+
+    /// 眠りにつく
+    pub fn enter(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// 起きる
+    pub fn leave(&self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+
+    /// 眠っていれば起こす
+    /// 戻り値: 眠っていたか（起こす必要があったか）
+    pub fn wake(&self) -> bool {
+        self.0.swap(false, Ordering::SeqCst)
+    }
+}
+
+/// イベントループのスリープ状態（プロセス全体で1つ）
+static SLEEPING: SleepFlag = SleepFlag::new();
+
+/// イベントループを起こす関数（EventLoopProxy 経由）
+static WAKER: OnceLock<Box<dyn Fn() + Send + Sync>> = OnceLock::new();
+
+/// イベントループを起こす関数を登録（起動時に1回だけ）
+pub fn set_waker(waker: impl Fn() + Send + Sync + 'static) {
+    if WAKER.set(Box::new(waker)).is_err() {
+        log::warn!("省電力モードの起こし方はすでに登録されています");
+    }
+}
+
+/// イベントループを眠らせる
+pub fn enter_sleep() {
+    SLEEPING.enter();
+}
+
+/// イベントループが起きたことを記録
+pub fn leave_sleep() {
+    SLEEPING.leave();
+}
+
+/// PTY出力があったことを通知（パーサースレッドから呼ぶ）
+pub fn notify_output() {
+    if SLEEPING.wake() {
+        if let Some(waker) = WAKER.get() {
+            waker();
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// アイドル判定
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 最後の操作からの経過時間でアイドルを判定
+pub struct IdleTracker {
+    /// 最後に入力または出力があった時刻
+    last_activity: Instant,
+    /// アイドルとみなすまでの時間
+    timeout: Duration,
+}
+
+impl IdleTracker {
+    pub fn new(now: Instant, timeout: Duration) -> Self {
+        Self { last_activity: now, timeout }
+    }
+
+    /// 入力・出力があったことを記録
+    #[inline]
+    pub fn touch(&mut self, now: Instant) {
+        self.last_activity = now;
+    }
+
+    /// アイドル状態かどうか
+    #[inline]
+    pub fn is_idle(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.last_activity) >= self.timeout
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracker() {
+        let start = Instant::now();
+        let mut tracker = IdleTracker::new(start, Duration::from_secs(5));
+        assert!(!tracker.is_idle(start + Duration::from_secs(4)));
+        assert!(tracker.is_idle(start + Duration::from_secs(5)));
+
+        // 操作があればアイドル解除
+        tracker.touch(start + Duration::from_secs(6));
+        assert!(!tracker.is_idle(start + Duration::from_secs(7)));
+        assert!(tracker.is_idle(start + Duration::from_secs(11)));
+    }
+
+    #[test]
+    fn test_wake_only_while_sleeping() {
+        let flag = SleepFlag::new();
+        assert!(!flag.wake());
+
+        flag.enter();
+        assert!(flag.wake());
+        // 一度起こしたら、次に眠るまで起こさない
+        assert!(!flag.wake());
+
+        flag.enter();
+        flag.leave();
+        assert!(!flag.wake());
+    }
+}
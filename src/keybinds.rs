@@ -0,0 +1,554 @@
+//! キー割り当て
+//!
+//! 設定ファイルの `[[keybinds]]` で、キーの組み合わせに操作を割り当てる
+//! 組み込みのショートカットより先に調べるので、上書きや（`none` で）無効化もできる
+//!
+//! tmux のプレフィックスのように、空白で区切って続けて押すキーも割り当てられる
+//! （`ctrl+a %`）。途中まで押した状態は `[leader] timeout_ms` で取り消し、
+//! 続けて押せるキーの一覧を画面に出す（`[leader] which_key`）
+
+use anyhow::{Context, Result};
+use winit::keyboard::{Key, ModifiersState, NamedKey};
+
+use crate::config::KeybindConfig;
+
+/// 修飾キーの組み合わせ
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Mods {
+    /// Cmd（macOS）/ Super
+    pub super_key: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+impl Mods {
+    /// winit の修飾キーの状態から作る
+    pub fn from_state(state: ModifiersState) -> Self {
+        Self {
+            super_key: state.super_key(),
+            ctrl: state.control_key(),
+            alt: state.alt_key(),
+            shift: state.shift_key(),
+        }
+    }
+}
+
+/// 割り当てるキー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindKey {
+    /// 文字キー（小文字）
+    Char(String),
+    /// 名前付きキー
+    Named(NamedKey),
+}
+
+/// 1回のキー入力（修飾キーとキー）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyStroke {
+    pub mods: Mods,
+    pub key: BindKey,
+}
+
+impl KeyStroke {
+    /// 押されたキーから作る（修飾キーだけの入力は None）
+    pub fn from_event(mods: Mods, key: &Key) -> Option<Self> {
+        let key = match key {
+            Key::Character(c) => BindKey::Char(c.to_lowercase()),
+            Key::Named(
+                NamedKey::Shift
+                | NamedKey::Control
+                | NamedKey::Alt
+                | NamedKey::AltGraph
+                | NamedKey::Super
+                | NamedKey::Meta
+                | NamedKey::Hyper
+                | NamedKey::Fn
+                | NamedKey::CapsLock,
+            ) => return None,
+            Key::Named(named) => BindKey::Named(*named),
+            _ => return None,
+        };
+        Some(Self { mods, key })
+    }
+
+    /// 押されたキーがこの割り当てに当たるか
+    ///
+    /// 記号は Shift を押して入力することが多いため（`%` など）、英字以外の文字は Shift を区別しない
+    fn matches(&self, pressed: &KeyStroke) -> bool {
+        if self.key != pressed.key {
+            return false;
+        }
+        match &self.key {
+            BindKey::Char(c) if !c.chars().any(char::is_alphabetic) => {
+                Mods { shift: false, ..self.mods } == Mods { shift: false, ..pressed.mods }
+            }
+            _ => self.mods == pressed.mods,
+        }
+    }
+
+    /// 表示用の名前（`ctrl+a` など）
+    pub fn label(&self) -> String {
+        let mut label = String::new();
+        for (on, name) in [
+            (self.mods.super_key, "cmd+"),
+            (self.mods.ctrl, "ctrl+"),
+            (self.mods.alt, "alt+"),
+            (self.mods.shift, "shift+"),
+        ] {
+            if on {
+                label.push_str(name);
+            }
+        }
+        match &self.key {
+            BindKey::Char(c) => label.push_str(c),
+            BindKey::Named(named) => {
+                let name = NAMED_KEYS.iter().find(|(_, key)| key == named).map_or("?", |(name, _)| name);
+                label.push_str(name);
+            }
+        }
+        label
+    }
+}
+
+/// 割り当てる操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindAction {
+    NewWindow,
+    NewTab,
+    NextTab,
+    PrevTab,
+    ClosePane,
+    SplitHorizontal,
+    SplitVertical,
+    FocusNextPane,
+    FocusPrevPane,
+    SwapNextPane,
+    SwapPrevPane,
+    BalancePanes,
+    /// プリセットのレイアウトに並べ直す（text はプリセットの名前）
+    ApplyLayout,
+    Copy,
+    /// 選択テキストを色とスタイル付きの HTML（macOS では RTF も）と一緒にコピーする
+    CopyRich,
+    Paste,
+    /// 前にコピーしたテキストを選んでペーストする
+    ClipboardHistory,
+    ToggleExplorer,
+    OpenFinder,
+    /// コマンドパレットを開く
+    CommandPalette,
+    /// SSH の接続先だけのコマンドパレットを開く
+    SshLauncher,
+    /// 動いているコンテナのコマンドパレットを開く
+    ContainerLauncher,
+    /// WSL のディストリビューションのコマンドパレットを開く（Windows のみ）
+    WslLauncher,
+    /// 最近の警告・エラーのログビューアを開く
+    LogViewer,
+    CopyMode,
+    HintMode,
+    ToggleFullscreen,
+    ScreenshotPane,
+    ScreenshotWindow,
+    RespawnPane,
+    CopyLastOutput,
+    TogglePerfHud,
+    ScrollPageUp,
+    ScrollPageDown,
+    /// フォーカス中のペインのスクロールバックのディスクへの書き出しを切り替える
+    ToggleScrollbackSpill,
+    /// フォーカス中のペインの出力の録画（asciicast）を切り替える
+    ToggleRecording,
+    /// フォーカス中のペインのスクロールバックをテキストファイルに保存する
+    SaveScrollback,
+    /// フォーカス中のペインのスクロールバックを ANSI の色付きで保存する
+    SaveScrollbackAnsi,
+    /// 次のテーマに切り替える
+    NextTheme,
+    /// 決めた文字列（エスケープシーケンスも）をフォーカス中のペインに送る
+    SendText,
+    /// フォーカス中のペインの画面・スクロールバックを `[[pipes]]` のコマンドに渡す
+    Pipe,
+    /// SSH の接続先（プロファイルの名前か `~/.ssh/config` のホスト）を新しいタブで開く
+    Ssh,
+    /// 動いているコンテナ（名前か ID）のシェルを新しいタブで開く
+    Container,
+    /// WSL のディストリビューションのシェルを新しいタブで開く（Windows のみ）
+    Wsl,
+    /// 何もしない（組み込みのショートカットを無効にする）
+    None,
+}
+
+/// 操作の名前
+const ACTIONS: &[(&str, BindAction)] = &[
+    ("new-window", BindAction::NewWindow),
+    ("new-tab", BindAction::NewTab),
+    ("next-tab", BindAction::NextTab),
+    ("prev-tab", BindAction::PrevTab),
+    ("close-pane", BindAction::ClosePane),
+    ("split-horizontal", BindAction::SplitHorizontal),
+    ("split-vertical", BindAction::SplitVertical),
+    ("focus-next-pane", BindAction::FocusNextPane),
+    ("focus-prev-pane", BindAction::FocusPrevPane),
+    ("swap-next-pane", BindAction::SwapNextPane),
+    ("swap-prev-pane", BindAction::SwapPrevPane),
+    ("balance-panes", BindAction::BalancePanes),
+    ("apply-layout", BindAction::ApplyLayout),
+    ("copy", BindAction::Copy),
+    ("copy-rich", BindAction::CopyRich),
+    ("paste", BindAction::Paste),
+    ("clipboard-history", BindAction::ClipboardHistory),
+    ("toggle-explorer", BindAction::ToggleExplorer),
+    ("open-finder", BindAction::OpenFinder),
+    ("command-palette", BindAction::CommandPalette),
+    ("ssh-launcher", BindAction::SshLauncher),
+    ("container-launcher", BindAction::ContainerLauncher),
+    ("wsl-launcher", BindAction::WslLauncher),
+    ("log-viewer", BindAction::LogViewer),
+    ("copy-mode", BindAction::CopyMode),
+    ("hint-mode", BindAction::HintMode),
+    ("toggle-fullscreen", BindAction::ToggleFullscreen),
+    ("screenshot-pane", BindAction::ScreenshotPane),
+    ("screenshot-window", BindAction::ScreenshotWindow),
+    ("respawn-pane", BindAction::RespawnPane),
+    ("copy-last-output", BindAction::CopyLastOutput),
+    ("toggle-perf-hud", BindAction::TogglePerfHud),
+    ("scroll-page-up", BindAction::ScrollPageUp),
+    ("scroll-page-down", BindAction::ScrollPageDown),
+    ("toggle-scrollback-spill", BindAction::ToggleScrollbackSpill),
+    ("toggle-recording", BindAction::ToggleRecording),
+    ("save-scrollback", BindAction::SaveScrollback),
+    ("save-scrollback-ansi", BindAction::SaveScrollbackAnsi),
+    ("next-theme", BindAction::NextTheme),
+    ("send-text", BindAction::SendText),
+    ("pipe", BindAction::Pipe),
+    ("ssh", BindAction::Ssh),
+    ("container", BindAction::Container),
+    ("wsl", BindAction::Wsl),
+    ("none", BindAction::None),
+];
+
+impl BindAction {
+    /// 名前から取得（split-vertical など）
+    pub fn from_name(name: &str) -> Option<Self> {
+        ACTIONS.iter().find(|(n, _)| *n == name).map(|(_, action)| *action)
+    }
+
+    /// 名前（設定ファイルに書く名前）
+    pub fn name(self) -> &'static str {
+        ACTIONS.iter().find(|(_, action)| *action == self).map_or("none", |(name, _)| name)
+    }
+
+    /// すべての操作（設定ファイルに書く名前の順）
+    pub fn all() -> impl Iterator<Item = Self> {
+        ACTIONS.iter().map(|(_, action)| *action)
+    }
+}
+
+/// キー割り当て
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBinding {
+    /// 続けて押すキー（1つなら普通のショートカット）
+    pub keys: Vec<KeyStroke>,
+    pub action: BindAction,
+    /// 開くときのプロファイル（新しいウィンドウ・タブ・ペインのみ）
+    pub profile: Option<String>,
+    /// 送る文字列（send-text のみ）、パイプの名前（pipe のみ）、接続先（ssh のみ）、コンテナ（container のみ）、ディストリビューション（wsl のみ）、プリセットの名前（apply-layout のみ）、保存先（save-scrollback のみ）
+    pub text: Option<String>,
+}
+
+impl BindAction {
+    /// プロファイルを指定できる操作か（新しいウィンドウ・タブ・ペインを開く操作）
+    pub fn accepts_profile(self) -> bool {
+        matches!(self, Self::NewWindow | Self::NewTab | Self::SplitHorizontal | Self::SplitVertical)
+    }
+
+    /// text が必須の操作か（send-text は送る文字列、pipe はパイプの名前、ssh は接続先、container はコンテナ、wsl はディストリビューション、apply-layout はプリセットの名前）
+    pub fn requires_text(self) -> bool {
+        matches!(self, Self::SendText | Self::Pipe | Self::Ssh | Self::Container | Self::Wsl | Self::ApplyLayout)
+    }
+
+    /// text を指定できる操作か（save-scrollback は保存先、なければタイムスタンプ付きの名前）
+    pub fn accepts_text(self) -> bool {
+        self.requires_text() || matches!(self, Self::SaveScrollback | Self::SaveScrollbackAnsi)
+    }
+}
+
+/// 押したキーの割り当てを調べた結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyMatch<'a> {
+    /// 割り当て
+    Action(&'a KeyBinding),
+    /// 続きのキーを待つ
+    Pending,
+    /// 割り当てなし
+    NoMatch,
+}
+
+/// 名前付きキーの名前
+const NAMED_KEYS: &[(&str, NamedKey)] = &[
+    ("enter", NamedKey::Enter),
+    ("return", NamedKey::Enter),
+    ("tab", NamedKey::Tab),
+    ("space", NamedKey::Space),
+    ("escape", NamedKey::Escape),
+    ("esc", NamedKey::Escape),
+    ("backspace", NamedKey::Backspace),
+    ("delete", NamedKey::Delete),
+    ("insert", NamedKey::Insert),
+    ("up", NamedKey::ArrowUp),
+    ("down", NamedKey::ArrowDown),
+    ("left", NamedKey::ArrowLeft),
+    ("right", NamedKey::ArrowRight),
+    ("home", NamedKey::Home),
+    ("end", NamedKey::End),
+    ("pageup", NamedKey::PageUp),
+    ("pagedown", NamedKey::PageDown),
+    ("f1", NamedKey::F1),
+    ("f2", NamedKey::F2),
+    ("f3", NamedKey::F3),
+    ("f4", NamedKey::F4),
+    ("f5", NamedKey::F5),
+    ("f6", NamedKey::F6),
+    ("f7", NamedKey::F7),
+    ("f8", NamedKey::F8),
+    ("f9", NamedKey::F9),
+    ("f10", NamedKey::F10),
+    ("f11", NamedKey::F11),
+    ("f12", NamedKey::F12),
+    ("f13", NamedKey::F13),
+    ("f14", NamedKey::F14),
+    ("f15", NamedKey::F15),
+    ("f16", NamedKey::F16),
+    ("f17", NamedKey::F17),
+    ("f18", NamedKey::F18),
+    ("f19", NamedKey::F19),
+    ("f20", NamedKey::F20),
+];
+
+/// キーの組み合わせを読む（`cmd+shift+d`、`ctrl+alt+enter` など）
+pub fn parse_key(spec: &str) -> Option<KeyStroke> {
+    let spec = spec.trim().to_lowercase();
+    // 最後の `+` 以降がキー（`cmd++` は `+` キー）
+    let (mods_part, key) = match spec.strip_suffix("++") {
+        Some(rest) => (rest, "+"),
+        None => spec.rsplit_once('+').unwrap_or(("", &spec)),
+    };
+    let mods = parse_mods(mods_part)?;
+    let key = match NAMED_KEYS.iter().find(|(name, _)| *name == key) {
+        Some((_, named)) => BindKey::Named(*named),
+        None if key.chars().count() == 1 => BindKey::Char(key.to_string()),
+        None => return None,
+    };
+    Some(KeyStroke { mods, key })
+}
+
+/// `+` でつないだ修飾キーを読む（`cmd+shift` など、空なら修飾キーなし）
+pub fn parse_mods(spec: &str) -> Option<Mods> {
+    let mut mods = Mods::default();
+    for modifier in spec.split('+').filter(|m| !m.is_empty()) {
+        match modifier {
+            "cmd" | "super" => mods.super_key = true,
+            "ctrl" | "control" => mods.ctrl = true,
+            "alt" | "option" => mods.alt = true,
+            "shift" => mods.shift = true,
+            _ => return None,
+        }
+    }
+    Some(mods)
+}
+
+/// 続けて押すキーを読む（空白区切り、`ctrl+a %` など）
+pub fn parse_keys(spec: &str) -> Option<Vec<KeyStroke>> {
+    let keys = spec.split_whitespace().map(parse_key).collect::<Option<Vec<_>>>()?;
+    (!keys.is_empty()).then_some(keys)
+}
+
+/// 既定の割り当て（設定ファイルで同じキーに割り当てると上書きできる）
+pub fn defaults() -> Vec<KeyBinding> {
+    let binding = |spec: &str, action| KeyBinding {
+        keys: parse_keys(spec).expect("既定のキー"),
+        action,
+        profile: None,
+        text: None,
+    };
+    vec![binding("cmd+enter", BindAction::ToggleFullscreen)]
+}
+
+/// 既定の割り当てのあとに設定ファイルの割り当てをつなげる
+pub fn with_defaults(bindings: &[KeyBinding]) -> Vec<KeyBinding> {
+    defaults().into_iter().chain(bindings.iter().cloned()).collect()
+}
+
+/// 設定ファイルのキー割り当てを読む
+pub fn parse_bindings(configs: &[KeybindConfig]) -> Result<Vec<KeyBinding>> {
+    configs
+        .iter()
+        .map(|config| {
+            let keys = parse_keys(&config.key)
+                .with_context(|| format!("キーの組み合わせが正しくありません: {:?}", config.key))?;
+            let action = parse_action(&config.action, config.profile.is_some(), config.text.is_some())
+                .with_context(|| format!("キー割り当てが正しくありません: {:?}", config.key))?;
+            Ok(KeyBinding { keys, action, profile: config.profile.clone(), text: config.text.clone() })
+        })
+        .collect()
+}
+
+/// 操作の名前を読み、profile と text を指定できる操作か確かめる（キー割り当てとコマンドパレットで共通）
+pub fn parse_action(name: &str, has_profile: bool, has_text: bool) -> Result<BindAction> {
+    let action = BindAction::from_name(name.trim()).with_context(|| format!("操作の名前が正しくありません: {:?}", name))?;
+    anyhow::ensure!(
+        !has_profile || action.accepts_profile(),
+        "profile を指定できるのは new-window / new-tab / split-horizontal / split-vertical だけです: {:?}",
+        name
+    );
+    anyhow::ensure!(
+        if has_text { action.accepts_text() } else { !action.requires_text() },
+        "text は send-text・pipe・ssh・container・wsl・apply-layout（必須）と save-scrollback・save-scrollback-ansi（保存先）に指定します: {:?}",
+        name
+    );
+    Ok(action)
+}
+
+/// これまでに押したキー（最後が今押したキー）に割り当てた操作
+///
+/// ちょうど当たる割り当てがあればその操作（同じキーなら後に書いたもの）、
+/// 続きのある割り当ての途中なら Pending
+pub fn lookup<'a>(bindings: &'a [KeyBinding], pressed: &[KeyStroke]) -> KeyMatch<'a> {
+    let starts_with = |binding: &KeyBinding| {
+        binding.keys.len() >= pressed.len() && binding.keys.iter().zip(pressed).all(|(key, p)| key.matches(p))
+    };
+    if let Some(binding) = bindings.iter().rev().find(|b| b.keys.len() == pressed.len() && starts_with(b)) {
+        return KeyMatch::Action(binding);
+    }
+    if bindings.iter().any(starts_with) {
+        KeyMatch::Pending
+    } else {
+        KeyMatch::NoMatch
+    }
+}
+
+/// 途中まで押したときの案内（1行目が押したキー、続けて押せるキーと操作）
+pub fn continuations(bindings: &[KeyBinding], pressed: &[KeyStroke]) -> Vec<String> {
+    let labels = |keys: &[KeyStroke]| keys.iter().map(KeyStroke::label).collect::<Vec<_>>().join(" ");
+    let rest: Vec<(String, String)> = bindings
+        .iter()
+        .filter(|b| b.keys.len() > pressed.len() && b.keys.iter().zip(pressed).all(|(key, p)| key.matches(p)))
+        .map(|b| {
+            let action = match (&b.profile, &b.text) {
+                (Some(profile), _) => format!("{} ({})", b.action.name(), profile),
+                (None, Some(text)) => format!("{} {:?}", b.action.name(), text),
+                (None, None) => b.action.name().to_string(),
+            };
+            (labels(&b.keys[pressed.len()..]), action)
+        })
+        .collect();
+    let width = rest.iter().map(|(keys, _)| keys.chars().count()).max().unwrap_or(0);
+    std::iter::once(format!("{} …", labels(pressed)))
+        .chain(rest.into_iter().map(|(keys, action)| format!("{:<width$}  {}", keys, action)))
+        .collect()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_key() {
+        let stroke = |mods, key| Some(KeyStroke { mods, key });
+        let cmd_shift = Mods { super_key: true, shift: true, ..Mods::default() };
+        assert_eq!(parse_key("cmd+shift+D"), stroke(cmd_shift, BindKey::Char("d".into())));
+        assert_eq!(
+            parse_key("ctrl+alt+enter"),
+            stroke(Mods { ctrl: true, alt: true, ..Mods::default() }, BindKey::Named(NamedKey::Enter))
+        );
+        assert_eq!(parse_key("cmd++"), stroke(Mods { super_key: true, ..Mods::default() }, BindKey::Char("+".into())));
+        assert_eq!(parse_key("f5"), stroke(Mods::default(), BindKey::Named(NamedKey::F5)));
+        assert_eq!(parse_key("hyper+k"), None);
+        assert_eq!(parse_key("cmd+kk"), None);
+        assert_eq!(parse_keys("ctrl+a  %").map(|keys| keys.len()), Some(2));
+        assert_eq!(parse_keys(" "), None);
+        assert_eq!(parse_key("ctrl+pagedown").unwrap().label(), "ctrl+pagedown");
+    }
+
+    #[test]
+    fn test_lookup() {
+        let configs = [
+            KeybindConfig { key: "cmd+k".into(), action: "split-vertical".into(), profile: None, text: None },
+            KeybindConfig { key: "cmd+k".into(), action: "none".into(), profile: None, text: None },
+            KeybindConfig { key: "f5".into(), action: "respawn-pane".into(), profile: None, text: None },
+        ];
+        let bindings = parse_bindings(&configs).unwrap();
+        let cmd = Mods { super_key: true, ..Mods::default() };
+        let press = |mods, key: Key| vec![KeyStroke::from_event(mods, &key).unwrap()];
+        // 同じキーなら後に書いたもの
+        assert_eq!(lookup(&bindings, &press(cmd, Key::Character("K".into()))), KeyMatch::Action(&bindings[1]));
+        assert_eq!(
+            lookup(&bindings, &press(Mods::default(), Key::Named(NamedKey::F5))),
+            KeyMatch::Action(&bindings[2])
+        );
+        // 修飾キーが違えば割り当てなし
+        assert_eq!(lookup(&bindings, &press(Mods::default(), Key::Character("k".into()))), KeyMatch::NoMatch);
+        // 修飾キーだけの入力は数えない
+        assert_eq!(KeyStroke::from_event(cmd, &Key::Named(NamedKey::Shift)), None);
+
+        // 既定の割り当ては設定ファイルの割り当てで上書きできる
+        let enter = press(cmd, Key::Named(NamedKey::Enter));
+        let defaults = with_defaults(&bindings);
+        assert!(matches!(lookup(&defaults, &enter), KeyMatch::Action(b) if b.action == BindAction::ToggleFullscreen));
+        let overridden = KeybindConfig { key: "cmd+enter".into(), action: "none".into(), profile: None, text: None };
+        let defaults = with_defaults(&parse_bindings(&[overridden]).unwrap());
+        assert!(matches!(lookup(&defaults, &enter), KeyMatch::Action(b) if b.action == BindAction::None));
+
+        // プロファイルは開く操作にだけ指定できる
+        let with_profile = |action: &str| KeybindConfig { key: "cmd+p".into(), action: action.into(), profile: Some("prod".into()), text: None };
+        let bindings = parse_bindings(&[with_profile("new-tab")]).unwrap();
+        assert_eq!(bindings[0].profile.as_deref(), Some("prod"));
+        assert!(parse_bindings(&[with_profile("copy")]).is_err());
+
+        // 文字列は send-text・pipe・save-scrollback にだけ指定でき、send-text と pipe には必須
+        let send = |action: &str, text: Option<&str>| KeybindConfig {
+            key: "f13".into(),
+            action: action.into(),
+            profile: None,
+            text: text.map(Into::into),
+        };
+        let bindings = parse_bindings(&[send("send-text", Some("git status\r"))]).unwrap();
+        assert_eq!(bindings[0].text.as_deref(), Some("git status\r"));
+        assert_eq!(lookup(&bindings, &press(Mods::default(), Key::Named(NamedKey::F13))), KeyMatch::Action(&bindings[0]));
+        assert!(parse_bindings(&[send("send-text", None)]).is_err());
+        assert!(parse_bindings(&[send("copy", Some("x"))]).is_err());
+        assert_eq!(parse_bindings(&[send("pipe", Some("fzf"))]).unwrap()[0].action, BindAction::Pipe);
+        assert!(parse_bindings(&[send("pipe", None)]).is_err());
+        // 保存先は省略できる
+        assert!(parse_bindings(&[send("save-scrollback", Some("~/log.txt")), send("save-scrollback-ansi", None)]).is_ok());
+    }
+
+    #[test]
+    fn test_sequence() {
+        let configs = [
+            KeybindConfig { key: "ctrl+a %".into(), action: "split-horizontal".into(), profile: None, text: None },
+            KeybindConfig { key: "ctrl+a \"".into(), action: "split-vertical".into(), profile: None, text: None },
+            KeybindConfig { key: "ctrl+a c".into(), action: "new-tab".into(), profile: Some("prod".into()), text: None },
+        ];
+        let bindings = parse_bindings(&configs).unwrap();
+        let ctrl = Mods { ctrl: true, ..Mods::default() };
+        let shift = Mods { shift: true, ..Mods::default() };
+        let prefix = KeyStroke::from_event(ctrl, &Key::Character("a".into())).unwrap();
+        assert_eq!(lookup(&bindings, std::slice::from_ref(&prefix)), KeyMatch::Pending);
+
+        // 記号は Shift を押していても当たる（英字は区別する）
+        let percent = KeyStroke::from_event(shift, &Key::Character("%".into())).unwrap();
+        assert_eq!(lookup(&bindings, &[prefix.clone(), percent]), KeyMatch::Action(&bindings[0]));
+        let upper_c = KeyStroke::from_event(shift, &Key::Character("C".into())).unwrap();
+        assert_eq!(lookup(&bindings, &[prefix.clone(), upper_c]), KeyMatch::NoMatch);
+
+        let lines = continuations(&bindings, &[prefix]);
+        assert_eq!(lines, ["ctrl+a …", "%  split-horizontal", "\"  split-vertical", "c  new-tab (prod)"]);
+    }
+}
@@ -0,0 +1,120 @@
+//! キー入力のエンコード
+//!
+//! 押されたキーを PTY に送るバイト列にする（xterm と同じ）
+//! 矢印などの修飾キーは `ESC [ 1 ; <修飾> A` の形で送るので、PowerShell（PSReadLine）の
+//! Ctrl + 矢印での単語移動や、ConPTY のキーイベントへの変換も正しく動く
+
+use winit::keyboard::NamedKey;
+
+use crate::keybinds::Mods;
+
+/// xterm の修飾キーの番号（1 は修飾なし）
+fn modifier_param(mods: Mods) -> u8 {
+    1 + mods.shift as u8 + (mods.alt as u8) * 2 + (mods.ctrl as u8) * 4
+}
+
+/// 名前付きキーのバイト列（送らないキーは None）
+pub fn named_key(named: &NamedKey, mods: Mods) -> Option<Vec<u8>> {
+    let param = modifier_param(mods);
+    // `ESC [ 1 ; m X`（修飾なしは `ESC [ X`、F1〜F4 は `ESC O X`）
+    let letter = |ss3: bool, c: char| match (param, ss3) {
+        (1, false) => format!("\x1b[{}", c),
+        (1, true) => format!("\x1bO{}", c),
+        _ => format!("\x1b[1;{}{}", param, c),
+    };
+    // `ESC [ n ; m ~`
+    let tilde = |n: u8| match param {
+        1 => format!("\x1b[{}~", n),
+        _ => format!("\x1b[{};{}~", n, param),
+    };
+    let text = match named {
+        NamedKey::Space if mods.ctrl => return Some(vec![0x00]),
+        NamedKey::Space => " ".to_string(),
+        NamedKey::Enter => "\r".to_string(),
+        // Ctrl + Backspace は ^H（PowerShell・readline の1単語削除）
+        NamedKey::Backspace if mods.ctrl => return Some(vec![0x08]),
+        NamedKey::Backspace => return Some(vec![0x7f]),
+        NamedKey::Tab if mods.shift => "\x1b[Z".to_string(),
+        NamedKey::Tab => "\t".to_string(),
+        NamedKey::Escape => "\x1b".to_string(),
+        NamedKey::ArrowUp => letter(false, 'A'),
+        NamedKey::ArrowDown => letter(false, 'B'),
+        NamedKey::ArrowRight => letter(false, 'C'),
+        NamedKey::ArrowLeft => letter(false, 'D'),
+        NamedKey::Home => letter(false, 'H'),
+        NamedKey::End => letter(false, 'F'),
+        NamedKey::Insert => tilde(2),
+        NamedKey::Delete => tilde(3),
+        NamedKey::PageUp => tilde(5),
+        NamedKey::PageDown => tilde(6),
+        NamedKey::F1 => letter(true, 'P'),
+        NamedKey::F2 => letter(true, 'Q'),
+        NamedKey::F3 => letter(true, 'R'),
+        NamedKey::F4 => letter(true, 'S'),
+        NamedKey::F5 => tilde(15),
+        NamedKey::F6 => tilde(17),
+        NamedKey::F7 => tilde(18),
+        NamedKey::F8 => tilde(19),
+        NamedKey::F9 => tilde(20),
+        NamedKey::F10 => tilde(21),
+        NamedKey::F11 => tilde(23),
+        NamedKey::F12 => tilde(24),
+        _ => return None,
+    };
+    Some(text.into_bytes())
+}
+
+/// Ctrl + 文字の制御文字（Ctrl + A は 0x01、Ctrl + [ は ESC など）
+pub fn ctrl_char(c: char) -> Option<u8> {
+    match c.to_ascii_lowercase() {
+        c @ 'a'..='z' => Some(c as u8 & 0x1f),
+        '@' | '2' => Some(0x00),
+        '[' | '3' => Some(0x1b),
+        '\\' | '4' => Some(0x1c),
+        ']' | '5' => Some(0x1d),
+        '^' | '6' => Some(0x1e),
+        '_' | '-' | '7' => Some(0x1f),
+        '?' | '8' => Some(0x7f),
+        _ => None,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_named_key() {
+        let none = Mods::default();
+        let ctrl = Mods { ctrl: true, ..Mods::default() };
+        let shift = Mods { shift: true, ..Mods::default() };
+        let bytes = |named, mods| named_key(&named, mods).map(|bytes| String::from_utf8(bytes).unwrap());
+        assert_eq!(bytes(NamedKey::ArrowUp, none).as_deref(), Some("\x1b[A"));
+        assert_eq!(bytes(NamedKey::ArrowLeft, ctrl).as_deref(), Some("\x1b[1;5D"));
+        assert_eq!(bytes(NamedKey::End, Mods { alt: true, shift: true, ..Mods::default() }).as_deref(), Some("\x1b[1;4F"));
+        assert_eq!(bytes(NamedKey::Delete, ctrl).as_deref(), Some("\x1b[3;5~"));
+        assert_eq!(bytes(NamedKey::PageDown, none).as_deref(), Some("\x1b[6~"));
+        assert_eq!(bytes(NamedKey::F1, none).as_deref(), Some("\x1bOP"));
+        assert_eq!(bytes(NamedKey::F4, shift).as_deref(), Some("\x1b[1;2S"));
+        assert_eq!(bytes(NamedKey::F12, none).as_deref(), Some("\x1b[24~"));
+        assert_eq!(bytes(NamedKey::Tab, shift).as_deref(), Some("\x1b[Z"));
+        assert_eq!(named_key(&NamedKey::Backspace, none), Some(vec![0x7f]));
+        assert_eq!(named_key(&NamedKey::Backspace, ctrl), Some(vec![0x08]));
+        assert_eq!(named_key(&NamedKey::Space, ctrl), Some(vec![0x00]));
+        assert_eq!(named_key(&NamedKey::CapsLock, none), None);
+    }
+
+    #[test]
+    fn test_ctrl_char() {
+        assert_eq!(ctrl_char('c'), Some(0x03));
+        assert_eq!(ctrl_char('R'), Some(0x12));
+        assert_eq!(ctrl_char('['), Some(0x1b));
+        assert_eq!(ctrl_char('_'), Some(0x1f));
+        assert_eq!(ctrl_char('?'), Some(0x7f));
+        assert_eq!(ctrl_char('あ'), None);
+    }
+}
@@ -0,0 +1,250 @@
+//! ログモジュール
+//!
+//! `log` の出力を、これまでどおり標準エラー（`RUST_LOG` で絞り込み）に出すのに加えて、
+//! 設定ディレクトリの `logs/` のファイルに書き、大きくなったら古いものから順に回す
+//! 最近の警告・エラーはメモリにも残し、ログビューア（Cmd+Shift+L）で見られるようにする
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use parking_lot::Mutex;
+
+use crate::config;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 定数
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 1つのログファイルの上限（バイト、超えたら回す）
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// 残す古いログファイルの数（`umiterm.log.1` 〜）
+const KEEP_FILES: usize = 3;
+
+/// メモリに残す警告・エラーの数
+const RECENT_LIMIT: usize = 500;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 最近の警告・エラー
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// メモリに残した1つの記録
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// 時刻（`HH:MM:SS`）
+    pub time: String,
+    /// レベル
+    pub level: Level,
+    /// 出したモジュール
+    pub target: String,
+    /// 本文
+    pub message: String,
+}
+
+impl Entry {
+    /// ログビューアの1行（改行はつなげる）
+    pub fn line(&self) -> String {
+        format!("{} {:<5} {}: {}", self.time, self.level, self.target, self.message.replace('\n', " ⏎ "))
+    }
+}
+
+/// 最近の警告・エラー（古い順）
+static RECENT: Mutex<VecDeque<Entry>> = Mutex::new(VecDeque::new());
+
+/// 警告・エラーを残す（上限を超えたら古いものから捨てる）
+fn remember(recent: &mut VecDeque<Entry>, entry: Entry, limit: usize) {
+    recent.push_back(entry);
+    while recent.len() > limit {
+        recent.pop_front();
+    }
+}
+
+/// 最近の警告・エラー（古い順）
+pub fn recent() -> Vec<Entry> {
+    RECENT.lock().iter().cloned().collect()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ログファイル
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 大きくなったら回すログファイル
+struct RotatingFile {
+    /// 書き込み先（`umiterm.log`）
+    path: PathBuf,
+    /// 開いているファイル
+    file: File,
+    /// 今のファイルの大きさ
+    size: u64,
+    /// 上限
+    max_size: u64,
+    /// 残す古いファイルの数
+    keep: usize,
+}
+
+impl RotatingFile {
+    /// 開く（ディレクトリがなければ作り、続きに書く）
+    fn open(path: PathBuf, max_size: u64, keep: usize) -> std::io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size, max_size, keep })
+    }
+
+    /// 古いファイルの名前（`umiterm.log.1` など）
+    fn rotated(path: &Path, index: usize) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    /// 1行書く（上限を超えていたら先に回す）
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// `.1` → `.2` … と1つずつずらし、今のファイルを `.1` にして新しく開く
+    fn rotate(&mut self) -> std::io::Result<()> {
+        if self.keep == 0 {
+            self.file.set_len(0)?;
+            self.size = 0;
+            return Ok(());
+        }
+        fs::remove_file(Self::rotated(&self.path, self.keep)).ok();
+        for index in (1..self.keep).rev() {
+            fs::rename(Self::rotated(&self.path, index), Self::rotated(&self.path, index + 1)).ok();
+        }
+        fs::rename(&self.path, Self::rotated(&self.path, 1))?;
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// ログファイルの場所（設定ファイルと同じディレクトリの `logs/<name>.log`）
+pub fn file_path(name: &str) -> Option<PathBuf> {
+    Some(config::path()?.parent()?.join("logs").join(format!("{}.log", name)))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ロガー
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 標準エラー・ファイル・メモリに出すロガー
+struct Logger {
+    /// 標準エラーへの出力と絞り込み（`RUST_LOG`、既定は info）
+    stderr: env_logger::Logger,
+    /// ログファイル（開けなければ None）
+    file: Option<Mutex<RotatingFile>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.stderr.enabled(metadata) || metadata.level() <= Level::Warn
+    }
+
+    fn log(&self, record: &Record) {
+        let now = chrono::Local::now();
+        if self.stderr.matches(record) {
+            self.stderr.log(record);
+            if let Some(file) = &self.file {
+                let line = format!(
+                    "{} {:<5} {}: {}\n",
+                    now.format("%Y-%m-%d %H:%M:%S%.3f"),
+                    record.level(),
+                    record.target(),
+                    record.args()
+                );
+                // 書けなくても標準エラーには出ているので止めない
+                let _ = file.lock().write_line(&line);
+            }
+        }
+        // 絞り込みに関わらず警告・エラーはログビューアに残す
+        if record.level() <= Level::Warn {
+            let entry = Entry {
+                time: now.format("%H:%M:%S").to_string(),
+                level: record.level(),
+                target: record.target().to_string(),
+                message: record.args().to_string(),
+            };
+            remember(&mut RECENT.lock(), entry, RECENT_LIMIT);
+        }
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+        if let Some(file) = &self.file {
+            let _ = file.lock().file.flush();
+        }
+    }
+}
+
+/// ロガーを入れる（name はログファイルの名前、GUI とデーモンで分ける）
+///
+/// 設定ファイルの場所（`--config`）を決めてから呼ぶ
+pub fn init(name: &str) {
+    let stderr = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).build();
+    let file = file_path(name).and_then(|path| {
+        RotatingFile::open(path.clone(), MAX_FILE_SIZE, KEEP_FILES)
+            .inspect_err(|e| eprintln!("ログファイルを開けません: {}: {}", path.display(), e))
+            .ok()
+    });
+    let max_level = stderr.filter().max(LevelFilter::Warn);
+    if log::set_boxed_logger(Box::new(Logger { stderr, file: file.map(Mutex::new) })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(message: &str) -> Entry {
+        Entry { time: "12:34:56".into(), level: Level::Warn, target: "umiterm::renderer".into(), message: message.into() }
+    }
+
+    #[test]
+    fn test_remember() {
+        let mut recent = VecDeque::new();
+        for message in ["a", "b", "c"] {
+            remember(&mut recent, entry(message), 2);
+        }
+        assert_eq!(recent.iter().map(|e| e.message.as_str()).collect::<Vec<_>>(), ["b", "c"]);
+        assert_eq!(entry("x\ny").line(), "12:34:56 WARN  umiterm::renderer: x ⏎ y");
+    }
+
+    #[test]
+    fn test_rotate() {
+        let dir = std::env::temp_dir().join(format!("umiterm-logging-{}", std::process::id()));
+        let path = dir.join("umiterm.log");
+        let mut file = RotatingFile::open(path.clone(), 10, 2).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_line(line).unwrap();
+        }
+        // 上限を超える前に回し、古いものは2つまで残す
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(fs::read_to_string(RotatingFile::rotated(&path, 1)).unwrap(), "third\n");
+        assert_eq!(fs::read_to_string(RotatingFile::rotated(&path, 2)).unwrap(), "second\n");
+        assert!(!RotatingFile::rotated(&path, 3).exists());
+
+        // 開き直すと続きに書く
+        let mut file = RotatingFile::open(path.clone(), 100, 2).unwrap();
+        file.write_line("fifth\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\nfifth\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
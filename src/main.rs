@@ -7,13 +7,15 @@
 //! │                         UmiTerm                             │
 //! ├─────────────────────────────────────────────────────────────┤
 //! │  App                                                        │
+//! │  ├─ render_context: RenderContext (デバイス・フォント共有)   │
 //! │  └─ windows: HashMap<WindowId, WindowState>                 │
 //! │       └─ WindowState                                        │
 //! │            ├─ window: Arc<Window>                           │
 //! │            ├─ renderer: Renderer                            │
-//! │            ├─ terminal: Terminal                            │
-//! │            ├─ parser: AnsiParser                            │
-//! │            └─ pty: Pty                                      │
+//! │            ├─ tabs: Vec<Tab>  (PaneLayout + フォーカス)      │
+//! │            └─ panes: HashMap<PaneId, Pane>                  │
+//! │                 ├─ terminal: Terminal                       │
+//! │                 └─ pty: Pty                                 │
 //! └─────────────────────────────────────────────────────────────┘
 //! ```
 //!
@@ -24,39 +26,136 @@
 //! 3. **ゼロコピーI/O**: チャネルベースの非同期PTY通信
 //! 4. **差分更新**: ダーティフラグで変更箇所のみ更新
 //! 5. **グリフキャッシュ**: フォントラスタライズは1回だけ
+//! 6. **省電力モード**: 入出力がしばらくなければイベントループを眠らせる
 //!
 //! # キーバインド
 //!
 //! - `Cmd+N`: 新規ウィンドウを開く
-//! - `Cmd+W`: 現在のウィンドウを閉じる
-
+//! - `Cmd+Shift+N`: フォーカス中のペインを新しいウィンドウに切り離す
+//! - `Cmd+Shift+M`: フォーカス中のペインを直前にフォーカスしていたウィンドウに移す
+//! - `Cmd+T`: 新規タブを開く
+//! - `Cmd+Shift+]` / `Cmd+Shift+[`: 次/前のタブ
+//! - `Cmd+1`〜`Cmd+9`: 番号のタブ（`Cmd+9` は最後のタブ）
+//! - `Cmd+Ctrl+]` / `Cmd+Ctrl+[`: フォーカス中のペインを次/前のペインと入れ替え
+//! - `Cmd+Ctrl+=`: すべてのペインを均等な大きさに
+//! - `Cmd+Ctrl+-`: フォーカス中のペインの段だけ均等な大きさに
+//! - `Cmd+Ctrl+1`〜`4`: プリセットレイアウト（main-vertical / main-horizontal / grid / columns）
+//! - `Cmd+W`: 現在のペインを閉じる（最後の1つならタブ、ウィンドウの順に閉じる）
+//! - `Cmd+Shift+S`: フォーカス中のペインをPNGで保存
+//! - `Cmd+Ctrl+Shift+S`: ウィンドウ全体をPNGで保存
+//! - `Cmd+Shift+H`: パフォーマンスHUDの表示切り替え
+//!
+//! # コマンドライン
+//!
+//! - `--profile [PATH]`: フレームの計測結果を chrome-trace 形式で書き出す
+
+mod accessibility;
+mod bookmarks;
+mod boxdraw;
+mod cli;
+mod clipboard;
+mod config;
+mod container;
+#[cfg(unix)]
+mod control;
+mod copy_mode;
+mod crash;
+mod editor;
+mod events;
 mod explorer;
+mod export;
+mod finder;
+mod fswatch;
 mod grid;
+mod hints;
+mod idle;
+mod keybinds;
+mod keys;
+mod logging;
+mod mousebinds;
+mod notification;
+mod palette;
 mod pane;
 mod parser;
+mod perf;
+mod pipe;
+mod playback;
+mod plugins;
+mod preview;
+#[cfg(unix)]
+mod private_socket;
+mod profiling;
 mod pty;
+mod recording;
+mod reftest;
 mod renderer;
+mod screenshot;
+mod scrollback;
+mod scrollbar;
+#[cfg(unix)]
+mod session;
+mod shell_integration;
+mod snapshot;
+mod ssh;
+mod status;
+mod tab;
 mod terminal;
+mod theme;
+mod theme_import;
+mod triggers;
+mod wsl;
 
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
-use arboard::Clipboard;
+use clap::Parser;
+use regex::Regex;
 use winit::{
     application::ApplicationHandler,
     dpi::{PhysicalPosition, PhysicalSize},
-    event::{ElementState, Ime, KeyEvent, Modifiers, MouseButton, MouseScrollDelta, WindowEvent},
-    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
-    keyboard::{Key, NamedKey},
-    window::{CursorIcon, Window, WindowId},
+    event::{ElementState, Ime, KeyEvent, Modifiers, MouseButton, MouseScrollDelta, StartCause, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy},
+    keyboard::{Key, ModifiersKeyState, NamedKey},
+    monitor::MonitorHandle,
+    window::{CursorIcon, Fullscreen, Theme, Window, WindowId},
 };
-
-use crate::explorer::Explorer;
-use crate::pane::{BorderHit, Pane, PaneId, PaneLayout, Rect};
-use crate::renderer::Renderer;
-use crate::terminal::Terminal;
+#[cfg(all(unix, not(target_os = "macos")))]
+use winit::platform::wayland::{ActiveEventLoopExtWayland, WindowAttributesExtWayland};
+#[cfg(target_os = "macos")]
+use winit::platform::macos::{OptionAsAlt, WindowAttributesExtMacOS, WindowExtMacOS};
+
+use crate::accessibility::{Accessibility, PaneView, WindowView};
+use crate::config::{BannerConfig, Config, KeyboardConfig};
+#[cfg(unix)]
+use crate::control::{PaneInfo, Reply, Request};
+use crate::copy_mode::{CopyCursor, CopyMode, Point, SelectKind};
+use crate::editor::FileLocation;
+use crate::events::Event;
+use crate::explorer::{Explorer, ExplorerFollow, ExplorerLayout, ExplorerPrompt};
+use crate::finder::{Finder, FinderKind};
+use crate::hints::{HintAction, HintLabels, HintMode, HintRule, OpenTarget};
+use crate::idle::IdleTracker;
+use crate::keybinds::{BindAction, KeyBinding, KeyMatch, KeyStroke, Mods};
+use crate::mousebinds::{MouseAction, MouseBinding};
+use crate::palette::{PaletteCommand, PaletteEntry};
+use crate::pane::{BorderHit, ExitAction, LayoutPreset, MonitorKind, Pane, PaneId, PaneTitleMode, Rect, TitleBar};
+use crate::parser::AnsiParser;
+use crate::perf::PerfStats;
+use crate::pipe::{Pipe, PipeInput, PipeOutput, PipeRun};
+use crate::plugins::{PluginCommand, PluginPane, PluginTab, Plugins};
+use crate::pty::PaneCommand;
+use crate::renderer::{Overlays, RenderContext, Renderer};
+use crate::scrollback::ScrollbackUsage;
+use crate::scrollbar::{Drag, NewLines, Scrollbar, Scrollbars};
+use crate::snapshot::TerminalSnapshot;
+use crate::status::{StatusBar, StatusContext};
+use crate::tab::{Tab, TabHit, TabLabel};
+use crate::theme::Appearance;
+use crate::triggers::Trigger;
 
 // ═══════════════════════════════════════════════════════════════════════════
 // 定数
@@ -89,6 +188,27 @@ const STARTUP_BANNER: &str = concat!(
     "\r\n",
 );
 
+/// 出す起動バナー（無効なら None、ファイルが読めなければ組み込みのバナー）
+fn startup_banner(config: &BannerConfig) -> Option<String> {
+    if !config.enabled() {
+        return None;
+    }
+    let Some(file) = config.file() else { return Some(STARTUP_BANNER.to_string()) };
+    match std::fs::read_to_string(&file) {
+        // 改行だけでは行頭に戻らないので CR を補う
+        Ok(text) => Some(text.replace("\r\n", "\n").replace('\n', "\r\n")),
+        Err(e) => {
+            log::warn!("バナーのファイルを読めません: {}: {}", file.display(), e);
+            Some(STARTUP_BANNER.to_string())
+        }
+    }
+}
+
+/// 設定ファイルの書き間違いのお知らせ（赤字、改行は CR を補う）
+fn config_error_notice(error: &str) -> String {
+    format!("\x1b[31m設定ファイルを読めません（直して保存するまで前の設定か既定の設定のまま）:\r\n{}\x1b[0m\r\n\r\n", error.replace('\n', "\r\n"))
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 // アプリケーション状態
 // ═══════════════════════════════════════════════════════════════════════════
@@ -99,16 +219,18 @@ struct WindowState {
     window: Arc<Window>,
     /// GPU レンダラー
     renderer: Renderer,
-    /// ペイン群（PaneIdで管理）
+    /// ペイン群（全タブ分をPaneIdで管理）
     panes: std::collections::HashMap<PaneId, Pane>,
-    /// ペインレイアウト
-    layout: PaneLayout,
-    /// フォーカス中のペインID
-    focused_pane: PaneId,
+    /// タブ（それぞれがペインレイアウトを持つ）
+    tabs: Vec<Tab>,
+    /// 表示中のタブ
+    active_tab: usize,
     /// 最後のフレーム時刻
     last_frame: Instant,
     /// IME入力中フラグ
     ime_active: bool,
+    /// IME に最後に知らせたカーソルの位置と大きさ（変わったときだけ知らせ直す）
+    ime_cursor_area: Option<(PhysicalPosition<u32>, PhysicalSize<u32>)>,
     /// 修飾キーの状態
     modifiers: Modifiers,
     /// マウス位置（正規化座標 0.0-1.0）
@@ -119,15 +241,339 @@ struct WindowState {
     dragging_border: Option<BorderHit>,
     /// テキスト選択ドラッグ中
     selecting_text: bool,
+    /// 選択ドラッグでペインの外に出たときの自動スクロール（1回の行数、正で過去へ）と前回の時刻
+    selection_autoscroll: isize,
+    last_autoscroll: Instant,
+    /// ペインのスクロールバー（表示・ドラッグの状態）と、前のフレームで表示していたか
+    scrollbars: Scrollbars,
+    scrollbar_visible: bool,
+    /// 直前のクリック（時刻・ペイン・セル）と連続回数（ダブル・トリプルクリックの判定）
+    last_click: Option<(Instant, PaneId, (usize, usize))>,
+    click_count: u32,
+    /// ダブルクリックで単語を区切る文字
+    word_separators: String,
+    /// OSC 133 のマークがないときにプロンプトとみなす行の正規表現
+    prompt_pattern: Option<Regex>,
     /// ファイルエクスプローラー
     explorer: Explorer,
     /// エクスプローラーにフォーカス中か
     explorer_focused: bool,
+    /// シェルの作業ディレクトリが変わったときのエクスプローラーの動作と、最後に合わせた作業ディレクトリ
+    explorer_follow: ExplorerFollow,
+    explorer_cwd: Option<PathBuf>,
+    /// エクスプローラーをサイドバーとしてペインの領域から除いているか（変わったらペインの大きさを合わせる）
+    explorer_docked: bool,
+    /// ペインごとの最後に見た作業ディレクトリ（変わったらエクスプローラーの最近のディレクトリに加える）
+    pane_cwds: std::collections::HashMap<PaneId, PathBuf>,
+    /// スクロールバック検索・コマンドパレット
+    finder: Finder,
+    /// コマンドパレットの項目（開いている間のみ、finder の行と同じ順）
+    palette: Vec<PaletteEntry>,
+    /// コピーの履歴のテキスト（開いている間のみ、finder の行と同じ順）
+    clipboard_history: Vec<String>,
+    /// 設定ファイルの `[[commands]]`
+    palette_commands: Vec<PaletteEntry>,
+    /// 設定ファイルの `[[pipes]]`
+    pipes: Vec<Pipe>,
+    /// 実行中のパイプ（コマンドのペイン → 元のペインと一時ファイル）
+    pipe_runs: std::collections::HashMap<PaneId, (PaneId, PipeRun)>,
+    /// コピーモード（対象のペインと状態、コピーモード中のみ）
+    copy_mode: Option<(PaneId, CopyMode)>,
+    /// ヒントモード（対象のペインと状態、ヒントモード中のみ）
+    hint_mode: Option<(PaneId, HintMode)>,
+    /// ヒントモードで一致を探す規則
+    hint_rules: Vec<HintRule>,
+    /// 出力の行に一致したら動作する規則
+    triggers: Vec<Trigger>,
+    /// プラグイン（App と共有）
+    plugins: Rc<Plugins>,
+    /// パフォーマンス統計
+    perf: PerfStats,
+    /// パフォーマンスHUDを表示中か
+    perf_visible: bool,
+    /// PTY出力以外の理由で再描画が必要か（スクロールバック操作など）
+    needs_redraw: bool,
+    /// 最後に入力・出力があった時刻（省電力モードの判定）
+    activity: IdleTracker,
+    /// ペインのタイトルバーを表示する条件
+    pane_titles: PaneTitleMode,
+    /// ステータスバー（表示しない場合は None）
+    status_bar: Option<StatusBar>,
+    /// 閉じる前の確認（表示中の場合のみ）
+    close_confirm: Option<CloseConfirm>,
+    /// 複数行のペーストの確認（表示中の場合のみ）
+    paste_confirm: Option<PasteConfirm>,
+    /// 前回クラッシュしたセッションを戻すかの確認（表示中の場合のみ、ダイアログの行）
+    restore_confirm: Option<Vec<String>>,
+    /// ウィンドウがフォーカス中か
+    window_focused: bool,
+    /// 完了を通知するコマンドの実行時間
+    notify_after: Duration,
+    /// 無音の監視で警告するまでの時間
+    silence_after: Duration,
+    /// シェルが終了したときの動作
+    on_exit: ExitAction,
+    /// フォルダをドロップしたときの動作
+    drop_folder: DropFolderAction,
+    /// キー割り当て（既定の割り当てのあとに設定ファイルの割り当て）
+    keybinds: Vec<KeyBinding>,
+    /// マウスの割り当て（既定の割り当てのあとに設定ファイルの割り当て）
+    mousebinds: Vec<MouseBinding>,
+    /// 続きを待っているキー列と最後に押した時刻
+    pending_keys: Option<(Vec<KeyStroke>, Instant)>,
+    /// 続きのキーを待つ時間
+    key_timeout: Duration,
+    /// 続けて押せるキーの一覧を出すか
+    which_key: bool,
+    /// Alt（Option）を Meta にするか
+    keyboard: KeyboardConfig,
+    /// スクリーンリーダーへの公開
+    accessibility: Accessibility,
+    /// 開いたときのプロファイル（フォントの大きさ用）
+    profile: Option<String>,
+    /// Wayland のウィンドウか（フォントに拡大率を掛け、枠の色を合わせる）
+    wayland: bool,
 }
 
 /// 境界線判定の閾値（正規化座標）
 const BORDER_THRESHOLD: f32 = 0.01;
 
+/// 同じセルでこの時間内にクリックするとダブル・トリプルクリック
+const MULTI_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// 選択ドラッグの自動スクロールの間隔
+const AUTOSCROLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 起動時のウィンドウの状態（環境変数 `UMITERM_STARTUP_MODE` で変更可能）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StartupMode {
+    /// 通常のウィンドウ
+    Windowed,
+    /// 最大化
+    Maximized,
+    /// ボーダーレスのフルスクリーン
+    Fullscreen,
+}
+
+impl config::Named for StartupMode {
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("windowed", Self::Windowed),
+        ("maximized", Self::Maximized),
+        ("fullscreen", Self::Fullscreen),
+    ];
+}
+
+impl StartupMode {
+    /// 環境変数か設定ファイルから取得（既定は通常のウィンドウ）
+    fn from_env() -> Self {
+        config::named_var("UMITERM_STARTUP_MODE", |config| config.window.startup_mode).unwrap_or(Self::Windowed)
+    }
+}
+
+/// フルスクリーンのしかた（環境変数 `UMITERM_FULLSCREEN` で変更可能）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FullscreenMode {
+    /// 解像度を変えずにウィンドウを画面いっぱいに広げる
+    Borderless,
+    /// モニターのビデオモードを切り替えて画面を占有する（Wayland では使えない）
+    Exclusive,
+}
+
+impl config::Named for FullscreenMode {
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("borderless", Self::Borderless),
+        ("exclusive", Self::Exclusive),
+    ];
+}
+
+impl FullscreenMode {
+    /// 環境変数か設定ファイルから取得（既定はボーダーレス）
+    fn from_env() -> Self {
+        config::named_var("UMITERM_FULLSCREEN", |config| config.window.fullscreen).unwrap_or(Self::Borderless)
+    }
+
+    /// モニターでのフルスクリーン（排他はいちばん大きく色数・リフレッシュレートの高いビデオモード、なければボーダーレス）
+    fn fullscreen(self, monitor: Option<MonitorHandle>) -> Fullscreen {
+        if self == Self::Borderless {
+            return Fullscreen::Borderless(None);
+        }
+        let best = monitor.and_then(|monitor| {
+            monitor.video_modes().max_by_key(|mode| {
+                let size = mode.size();
+                (u64::from(size.width) * u64::from(size.height), mode.bit_depth(), mode.refresh_rate_millihertz())
+            })
+        });
+        match best {
+            Some(mode) => Fullscreen::Exclusive(mode),
+            None => {
+                log::warn!("モニターのビデオモードが分からないため、ボーダーレスのフルスクリーンにします");
+                Fullscreen::Borderless(None)
+            }
+        }
+    }
+}
+
+/// ウィンドウの枠（環境変数 `UMITERM_DECORATIONS` で変更可能）
+///
+/// Wayland ではコンポジターが描く枠（サーバー側）があればそれを、なければ UmiTerm が描く枠（クライアント側）を使う
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Decorations {
+    /// タイトルバーと枠を付ける
+    Full,
+    /// 枠なし
+    None,
+}
+
+impl config::Named for Decorations {
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("full", Self::Full),
+        ("none", Self::None),
+    ];
+}
+
+impl Decorations {
+    /// 環境変数か設定ファイルから取得（既定は full）
+    fn from_env() -> Self {
+        config::named_var("UMITERM_DECORATIONS", |config| config.window.decorations).unwrap_or(Self::Full)
+    }
+}
+
+/// Wayland で動いているか
+fn is_wayland(event_loop: &ActiveEventLoop) -> bool {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        event_loop.is_wayland()
+    }
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    {
+        let _ = event_loop;
+        false
+    }
+}
+
+/// フォルダをドロップしたときの動作（環境変数 `UMITERM_DROP_FOLDER` で変更可能）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DropFolderAction {
+    /// そのフォルダに cd する
+    Cd,
+    /// ファイルと同じくパスを入力する
+    Insert,
+}
+
+impl config::Named for DropFolderAction {
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("cd", Self::Cd),
+        ("insert", Self::Insert),
+    ];
+}
+
+impl DropFolderAction {
+    /// 環境変数か設定ファイルから取得（既定は cd）
+    fn from_env() -> Self {
+        config::named_var("UMITERM_DROP_FOLDER", |config| config.window.drop_folder).unwrap_or(Self::Cd)
+    }
+}
+
+/// 閉じる対象
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CloseTarget {
+    /// フォーカス中のペイン
+    Pane,
+    /// タブ（番号）
+    Tab(usize),
+    /// ウィンドウ全体
+    Window,
+}
+
+impl CloseTarget {
+    /// 確認ダイアログの見出し
+    fn question(self) -> &'static str {
+        match self {
+            Self::Pane => "Close this pane?",
+            Self::Tab(_) => "Close this tab?",
+            Self::Window => "Close this window?",
+        }
+    }
+}
+
+/// 実行中のコマンドがあるときの、閉じる前の確認
+struct CloseConfirm {
+    /// 閉じる対象
+    target: CloseTarget,
+    /// 実行中のコマンド
+    commands: Vec<String>,
+}
+
+impl CloseConfirm {
+    /// ダイアログに表示する行
+    fn lines(&self) -> Vec<String> {
+        vec![
+            self.target.question().to_string(),
+            format!("Running: {}", self.commands.join(", ")),
+            "Enter: close   Esc: cancel".to_string(),
+        ]
+    }
+}
+
+/// 複数行のテキストをペーストする前の確認（誤ってコマンドを実行しないように）
+struct PasteConfirm {
+    /// ペースト先のペイン
+    pane_id: PaneId,
+    /// ペーストするテキスト
+    text: String,
+}
+
+impl PasteConfirm {
+    /// プレビューする行数
+    const PREVIEW_LINES: usize = 5;
+    /// プレビューの1行の最大文字数
+    const PREVIEW_WIDTH: usize = 60;
+
+    /// ダイアログに表示する行（制御文字は記号にして見せる）
+    fn lines(&self) -> Vec<String> {
+        let lines: Vec<&str> = self.text.trim_end_matches(['\r', '\n']).lines().collect();
+        let mut dialog = vec![format!("Paste {} lines?", lines.len())];
+        for line in lines.iter().take(Self::PREVIEW_LINES) {
+            let mut preview: String = line
+                .chars()
+                .map(|c| if c.is_control() && c != '\t' { '·' } else { c })
+                .take(Self::PREVIEW_WIDTH)
+                .collect();
+            if line.chars().count() > Self::PREVIEW_WIDTH {
+                preview.push('…');
+            }
+            dialog.push(format!("│ {}", preview.replace('\t', "    ")));
+        }
+        if lines.len() > Self::PREVIEW_LINES {
+            dialog.push(format!("│ … {} more lines", lines.len() - Self::PREVIEW_LINES));
+        }
+        dialog.push("Enter: paste   Esc: cancel".to_string());
+        dialog
+    }
+}
+
+/// イベントループに送るユーザーイベント
+#[derive(Debug)]
+enum UserEvent {
+    /// 省電力モード中にPTY出力が届いた
+    Wake,
+    /// 設定ファイルが変わった
+    ConfigChanged,
+    /// OS の外観が変わった（Linux の監視スレッドから）
+    AppearanceChanged(Appearance),
+    /// `umiterm cli` からの要求
+    #[cfg(unix)]
+    Control(control::Pending),
+    /// スクリーンリーダーからの要求（AccessKit）
+    Accessibility(accesskit_winit::Event),
+}
+
+impl From<accesskit_winit::Event> for UserEvent {
+    fn from(event: accesskit_winit::Event) -> Self {
+        Self::Accessibility(event)
+    }
+}
+
 /// アプリケーション全体の状態
 struct App {
     /// ウィンドウ群（WindowIdで管理）
@@ -136,28 +582,322 @@ struct App {
     instance: wgpu::Instance,
     /// wgpu アダプター（ウィンドウ間で共有）
     adapter: Option<wgpu::Adapter>,
+    /// デバイス・フォント・グリフアトラス（ウィンドウ間で共有）
+    render_context: Option<Arc<RenderContext>>,
+    /// フォーカス中のウィンドウ
+    focused_window: Option<WindowId>,
+    /// 直前にフォーカスしていたウィンドウ（ペインの移動先）
+    previous_window: Option<WindowId>,
+    /// 起動時のウィンドウの状態
+    startup_mode: StartupMode,
+    /// 最初のウィンドウで起動するコマンドと作業ディレクトリ（`-e`、`--working-directory`）
+    launch: Option<(PaneCommand, Option<PathBuf>)>,
+    /// ウィンドウのタイトル（`--title`）
+    title: String,
+    /// ウィンドウのクラス（`--class`）
+    class: Option<String>,
+    /// プラグイン（`plugins/*.lua`）
+    plugins: Rc<Plugins>,
+    /// 前回クラッシュしたときに書き出したセッション（戻すか尋ねている間のみ）
+    crashed: Option<crash::Session>,
+    /// パニックフックにレイアウトを預けた時刻
+    last_tracked: Instant,
+    /// イベントループへのプロキシ（スクリーンリーダーの要求を受け取る）
+    proxy: EventLoopProxy<UserEvent>,
     /// 終了フラグ
     should_exit: bool,
 }
 
 impl WindowState {
-    /// 起動バナーを表示
+    /// 起動バナーを表示（設定ファイルの `[banner]` で差し替え・無効化）
+    ///
+    /// 設定ファイルに書き間違いがあれば、直すまで新しいペインにそのエラーも出す
     fn show_startup_banner(pane: &mut Pane) {
+        let config = config::current();
+        let banner = startup_banner(&config.banner).into_iter().chain(config::load_error().map(|e| config_error_notice(&e)));
         let mut terminal = pane.terminal.lock();
-        pane.parser.process(&mut terminal, STARTUP_BANNER.as_bytes());
+        let mut parser = AnsiParser::new();
+        for text in banner {
+            parser.process(&mut terminal, text.as_bytes());
+        }
+    }
+
+    /// 表示中のタブ
+    fn tab(&self) -> &Tab {
+        &self.tabs[self.active_tab]
+    }
+
+    /// 表示中のタブ（可変）
+    fn tab_mut(&mut self) -> &mut Tab {
+        &mut self.tabs[self.active_tab]
+    }
+
+    /// フォーカス中のペインID
+    fn focused_pane(&self) -> PaneId {
+        self.tab().focused_pane
+    }
+
+    /// タブバーを表示するか（タブが2つ以上のとき）
+    fn tab_bar_visible(&self) -> bool {
+        self.tabs.len() > 1
+    }
+
+    /// ペインを並べる領域（タブバーの下、ステータスバーの上、サイドバーの右）
+    fn pane_area(&self) -> Rect {
+        Self::pane_area_for(&self.renderer, self.tabs.len(), self.status_bar.is_some(), self.sidebar_cols())
+    }
+
+    /// サイドバーとして表示中のエクスプローラーの幅（文字数、表示していなければ0）
+    fn sidebar_cols(&self) -> usize {
+        if self.explorer.docked() { self.explorer.width } else { 0 }
+    }
+
+    /// タブの数とステータスバーの有無、サイドバーの幅に応じたペインの領域
+    fn pane_area_for(renderer: &Renderer, tab_count: usize, status_bar: bool, sidebar_cols: usize) -> Rect {
+        if tab_count <= 1 && !status_bar && sidebar_cols == 0 {
+            return Rect::full();
+        }
+        let (width, height) = renderer.screen_size();
+        let (cell_width, cell_height) = renderer.cell_size();
+        let height = height.max(1) as f32;
+        // ペインが1列以上残るように（狭いウィンドウではサイドバーを縮める）
+        let left = (sidebar_cols as f32 * cell_width / width.max(1) as f32).min(0.9);
+        let top = if tab_count > 1 { (cell_height / height).min(1.0) } else { 0.0 };
+        // ステータスバーは最後の丸ごと入る行（端数の行はその下に余る）
+        let bottom = if status_bar {
+            let (_, rows) = renderer.calculate_terminal_size();
+            (rows.saturating_sub(1) as f32 * cell_height / height).clamp(top, 1.0)
+        } else {
+            1.0
+        };
+        Rect {
+            x: left,
+            y: top,
+            width: 1.0 - left,
+            height: bottom - top,
+        }
+    }
+
+    /// ステータスバーの表示内容を更新
+    /// 戻り値: 表示が変わったか
+    fn refresh_status(&mut self) -> bool {
+        if self.status_bar.is_none() {
+            return false;
+        }
+        let focused = self.focused_pane();
+        let pane_ids = self.tab().layout.all_pane_ids();
+        let Some(cwd) = self.panes.get(&focused).map(|pane| pane.terminal.lock().cwd.clone()) else {
+            return false;
+        };
+        let ctx = StatusContext {
+            cwd,
+            pane_index: pane_ids.iter().position(|id| *id == focused).unwrap_or(0),
+            pane_count: pane_ids.len(),
+        };
+        self.status_bar
+            .as_mut()
+            .is_some_and(|bar| bar.refresh(ctx, Instant::now()))
+    }
+
+    /// タブのペインにタイトルバーを表示するか
+    fn title_bars_visible(&self, tab: &Tab) -> bool {
+        self.pane_titles.visible(tab.layout.pane_count())
+    }
+
+    /// 表示中のタブの各ペインのターミナル部分の矩形
+    fn terminal_rects(&self) -> Vec<(PaneId, Rect)> {
+        self.terminal_rects_of(self.tab())
+    }
+
+    /// タブの各ペインのターミナル部分の矩形（タイトルバーの1行分を除く）
+    fn terminal_rects_of(&self, tab: &Tab) -> Vec<(PaneId, Rect)> {
+        let rects = tab.layout.calculate_rects(self.pane_area());
+        if !self.title_bars_visible(tab) {
+            return rects;
+        }
+        let (_, height) = self.renderer.screen_size();
+        let (_, cell_height) = self.renderer.cell_size();
+        let title = cell_height / height.max(1) as f32;
+        rects
+            .into_iter()
+            .map(|(pane_id, rect)| {
+                let title = title.min(rect.height);
+                (pane_id, Rect { y: rect.y + title, height: rect.height - title, ..rect })
+            })
+            .collect()
     }
 
     /// フレームを更新
     /// 戻り値: 再描画が必要か
     fn update(&mut self) -> bool {
-        let mut needs_redraw = false;
-        // すべてのペインを更新
-        for pane in self.panes.values_mut() {
-            if pane.update() {
-                needs_redraw = true;
+        let now = Instant::now();
+        let mut bytes = 0;
+        let mut changed = false;
+        // すべてのタブのペインを更新
+        for (index, tab) in self.tabs.iter_mut().enumerate() {
+            let active = index == self.active_tab;
+            for pane_id in tab.layout.all_pane_ids() {
+                let Some(pane) = self.panes.get_mut(&pane_id) else { continue };
+                let pane_bytes = pane.update();
+                if pane_bytes > 0 && (!self.triggers.is_empty() || self.plugins.wants_output()) {
+                    let lines = pane.terminal.lock().take_output_lines();
+                    triggers::apply(&mut self.triggers, pane, &lines, now);
+                    self.plugins.output_lines(pane_id, &lines);
+                }
+                let bell = pane.take_bell();
+                bytes += pane_bytes;
+
+                // umiterm cli subscribe に知らせる
+                if bell {
+                    events::emit(Event::Bell { pane: pane_id.0 });
+                }
+                if pane_bytes > 0 && events::has_subscribers() {
+                    if let Some(title) = pane.take_title_change() {
+                        events::emit(Event::TitleChanged { pane: pane_id.0, title });
+                    }
+                }
+
+                // 見ていないペインで長時間のコマンドが終わったら通知してバッジを付ける
+                let seen = self.window_focused && active && pane_id == tab.focused_pane;
+                if let Some(finished) = pane.take_finished() {
+                    events::emit(Event::command_finished(pane_id, &finished));
+                    if !seen && finished.duration >= self.notify_after {
+                        notification::notify_command_finished(&pane.title().name, &finished);
+                        pane.done = Some(finished);
+                        if !active {
+                            tab.done = Some(finished);
+                        }
+                        changed = true;
+                    }
+                }
+                // 見たらバッジを消す
+                if seen && pane.done.take().is_some() {
+                    changed = true;
+                }
+
+                // 出力・無音の監視（見ているペインの警告は消える）
+                let had_alert = pane.monitor.alert.is_some();
+                if let Some(kind) = pane.monitor.check(pane_bytes > 0, seen, now, self.silence_after) {
+                    log::info!("ペインの監視: {} ({:?})", kind.name(), pane_id);
+                    notification::notify_monitor_alert(&pane.title().name, kind, self.silence_after);
+                    changed = true;
+                }
+                changed |= had_alert != pane.monitor.alert.is_some();
+
+                if active {
+                    changed |= pane_bytes > 0;
+                } else {
+                    // 非表示のタブはバッジが変わったときだけ描き直す
+                    changed |= (pane_bytes > 0 && !tab.activity) || (bell && !tab.bell);
+                    tab.activity |= pane_bytes > 0;
+                    tab.bell |= bell;
+                }
+            }
+        }
+        self.perf.record_pty_bytes(now, bytes);
+        if bytes > 0 {
+            self.activity.touch(now);
+        }
+        changed
+    }
+
+    /// シェルが終了したペインを設定に従って閉じる・残す・再起動する
+    /// 戻り値: ウィンドウを閉じるべきか（最後のペインを閉じた場合）
+    fn handle_exited_panes(&mut self) -> bool {
+        let exited: Vec<_> = self
+            .panes
+            .iter_mut()
+            .filter_map(|(pane_id, pane)| Some((*pane_id, pane.take_exited()?)))
+            .collect();
+
+        for (pane_id, exit) in exited {
+            log::info!("ペイン {:?} のシェルが終了しました: {:?}", pane_id, exit.code);
+            self.needs_redraw = true;
+            // パイプのコマンドは on_exit によらず、結果を元のペインに入力して閉じるか表示したまま残す
+            if let Some((origin, run)) = self.pipe_runs.remove(&pane_id) {
+                if run.output == PipeOutput::Insert && exit.code == Some(0) {
+                    self.take_pane(pane_id);
+                    self.select_pane(origin);
+                    if let Some(text) = run.take_output() {
+                        self.write_paste(origin, &text);
+                    }
+                } else if let Some(pane) = self.panes.get_mut(&pane_id) {
+                    pane.hold(&exit);
+                }
+                continue;
+            }
+            match self.on_exit {
+                ExitAction::Close => {
+                    self.take_pane(pane_id);
+                    if self.tabs.is_empty() {
+                        return true;
+                    }
+                }
+                ExitAction::Hold => {
+                    if let Some(pane) = self.panes.get_mut(&pane_id) {
+                        pane.hold(&exit);
+                    }
+                }
+                ExitAction::Restart => {
+                    let Some(pane) = self.panes.get_mut(&pane_id) else { continue };
+                    if !pane.can_restart() {
+                        log::warn!("起動直後に終了したため再起動しません: {:?}", pane_id);
+                        pane.hold(&exit);
+                    } else if let Err(e) = pane.restart() {
+                        log::error!("シェルの再起動に失敗: {}", e);
+                        pane.hold(&exit);
+                    }
+                }
             }
         }
-        needs_redraw
+        false
+    }
+
+    /// フルスクリーンを切り替える（ボーダーレスか排他かは `UMITERM_FULLSCREEN`）
+    /// （レンダラーとペインの大きさは続いて届く Resized で合わせ直す）
+    fn toggle_fullscreen(&mut self) {
+        let fullscreen = self
+            .window
+            .fullscreen()
+            .is_none()
+            .then(|| FullscreenMode::from_env().fullscreen(self.window.current_monitor()));
+        log::info!("フルスクリーン: {}", fullscreen.is_some());
+        self.window.set_fullscreen(fullscreen);
+    }
+
+    /// 省電力モードに入ってよいか
+    ///
+    /// 入出力がしばらくなく、ドラッグやHUDなど毎フレーム描画する状態でもないとき
+    fn is_idle(&self, now: Instant) -> bool {
+        self.activity.is_idle(now)
+            && !self.needs_redraw
+            && !self.selecting_text
+            && self.dragging_border.is_none()
+            && !self.perf_visible
+            && !self.panes.values().any(|p| p.has_pending_output())
+    }
+
+    /// スクリーンリーダーに表示中のタブの中身を渡す（繋がっていなければ何もしない）
+    fn update_accessibility(&mut self) {
+        if !self.accessibility.is_active() {
+            return;
+        }
+        let focused = self.focused_pane();
+        if let Some(pane) = self.panes.get(&focused) {
+            self.accessibility.read_output(focused, &pane.terminal.lock());
+        }
+        let screen = self.renderer.screen_size();
+        let panes = self
+            .terminal_rects()
+            .into_iter()
+            .filter_map(|(pane_id, rect)| {
+                let pane = self.panes.get(&pane_id)?;
+                let title = pane.title();
+                let label = if title.name.is_empty() { title.cwd } else { format!("{} ({})", title.name, title.cwd) };
+                Some(PaneView::new(pane_id, label, rect, screen, &pane.snapshot))
+            })
+            .collect();
+        self.accessibility.update(WindowView { title: self.window.title(), panes, focused: Some(focused) });
     }
 
     /// 描画
@@ -168,174 +908,629 @@ impl WindowState {
             return true;
         }
         self.last_frame = now;
+        self.needs_redraw = false;
+
+        let _span = tracing::trace_span!("frame").entered();
+        let result = self.with_frame(|renderer, panes, overlays| {
+            renderer.render_panes(panes, overlays)
+        });
+        self.perf.record_frame(now, now.elapsed());
+        // 出力・分割・スクロールでカーソルが動いたら、IME の候補の位置も追従させる
+        if self.window_focused {
+            self.update_ime_cursor_area();
+        }
+
+        match result {
+            Ok(_) => true,
+            Err(wgpu::SurfaceError::Lost) => {
+                let size = self.window.inner_size();
+                self.renderer.resize(size.width, size.height);
+                true
+            }
+            Err(wgpu::SurfaceError::OutOfMemory) => {
+                log::error!("GPUメモリ不足");
+                false
+            }
+            Err(e) => {
+                log::warn!("描画エラー: {:?}", e);
+                true
+            }
+        }
+    }
 
+    /// 描画用のデータを組み立ててレンダラーに渡す
+    fn with_frame<R>(
+        &mut self,
+        f: impl FnOnce(&mut Renderer, &[(&TerminalSnapshot, Rect, bool)], &Overlays) -> R,
+    ) -> R {
         // ペインの矩形領域を計算
-        let rects = self.layout.calculate_rects(Rect::full());
+        let rects = self.terminal_rects();
+        let title_bars_visible = self.title_bars_visible(self.tab());
+
+        // コピーモードの選択はスナップショットを取る前にターミナルへ反映する
+        let copy_cursor = self.sync_copy_mode(&rects);
+        let hint_labels = self.hint_labels(&rects);
+
+        // 表示中のペインだけ、変更行をスナップショットにコピー（描画中はロックしない）
+        for (pane_id, _) in &rects {
+            if let Some(pane) = self.panes.get_mut(pane_id) {
+                pane.refresh_snapshot();
+                if title_bars_visible {
+                    pane.refresh_command();
+                }
+            }
+        }
+        let focused_pane = self.focused_pane();
 
-        // 描画用のデータを構築
-        let render_data: Vec<_> = rects
+        // スクロールしたペインのスクロールバー（しばらく操作がなければ隠す）
+        let now = Instant::now();
+        self.scrollbars.retain(|id| self.panes.contains_key(id));
+        let scrollbars: Vec<Scrollbar> = rects
             .iter()
             .filter_map(|(pane_id, rect)| {
-                self.panes.get(pane_id).map(|pane| {
-                    let is_focused = *pane_id == self.focused_pane;
-                    (pane, *rect, is_focused)
+                let (history, rows, offset) = {
+                    let terminal = self.panes.get(pane_id)?.terminal.lock();
+                    (terminal.history_base(), terminal.active_grid().rows, terminal.display_offset)
+                };
+                let visible = self.scrollbars.update(*pane_id, offset, now);
+                let (top, height) = scrollbar::thumb(history, rows, offset)?;
+                visible.then(|| Scrollbar {
+                    rect: *rect,
+                    top,
+                    height,
+                    dragging: self.scrollbars.drag.is_some_and(|drag| drag.pane_id == *pane_id),
                 })
             })
             .collect();
+        self.scrollbar_visible = !scrollbars.is_empty();
 
-        // ターミナルをロックして描画
-        let terminals: Vec<_> = render_data
+        // さかのぼって表示している間に出力が届いたペイン
+        let new_lines: Vec<NewLines> = rects
             .iter()
-            .map(|(pane, rect, is_focused)| {
-                let terminal = pane.terminal.lock();
-                (terminal, *rect, *is_focused)
+            .filter_map(|(pane_id, rect)| {
+                let terminal = self.panes.get(pane_id)?.terminal.lock();
+                let count = terminal.unseen_lines();
+                (count > 0).then(|| NewLines {
+                    rect: *rect,
+                    cols: terminal.active_grid().cols,
+                    rows: terminal.active_grid().rows,
+                    count,
+                })
             })
             .collect();
 
+        // タイトルバーはペイン全体の矩形の最上段に描く
+        let title_bars: Option<Vec<TitleBar>> = title_bars_visible.then(|| {
+            self.tab()
+                .layout
+                .calculate_rects(self.pane_area())
+                .into_iter()
+                .filter_map(|(pane_id, rect)| {
+                    self.panes.get(&pane_id).map(|pane| TitleBar {
+                        rect,
+                        title: pane.title(),
+                        focused: pane_id == focused_pane,
+                        done: pane.done,
+                        monitor: pane.monitor.label(),
+                        recording: pane.is_recording(),
+                    })
+                })
+                .collect()
+        });
+
         // 参照のベクターを作成
-        let terminal_refs: Vec<(&Terminal, Rect, bool)> = terminals
+        let terminal_refs: Vec<(&TerminalSnapshot, Rect, bool)> = rects
             .iter()
-            .map(|(t, r, f)| (&**t, *r, *f))
+            .filter_map(|(pane_id, rect)| {
+                self.panes.get(pane_id).map(|pane| {
+                    let is_focused = *pane_id == focused_pane;
+                    (&pane.snapshot, *rect, is_focused)
+                })
+            })
             .collect();
 
-        // エクスプローラーが表示中なら渡す
-        let explorer_ref = if self.explorer.visible {
-            Some(&self.explorer)
-        } else {
-            None
+        // パフォーマンスHUDの内容は直前のフレームの統計から作る
+        let hud = self.perf_visible.then(|| {
+            let scrollback = self
+                .panes
+                .values()
+                .map(|pane| pane.snapshot.scrollback)
+                .fold(ScrollbackUsage::default(), |a, b| a + b);
+            self.perf
+                .hud_lines(Instant::now(), &self.renderer.frame_stats(), &scrollback)
+        });
+
+        // タブバーのタイトルは各タブでフォーカス中のペインのタイトル（なければ作業ディレクトリ）
+        let tab_labels: Option<Vec<TabLabel>> = self.tab_bar_visible().then(|| {
+            self.tabs
+                .iter()
+                .enumerate()
+                .map(|(index, tab)| {
+                    let title = self
+                        .panes
+                        .get(&tab.focused_pane)
+                        .map(|pane| {
+                            let title = pane.title();
+                            if title.name.is_empty() { title.cwd } else { title.name }
+                        })
+                        .unwrap_or_default();
+                    let active = index == self.active_tab;
+                    // プラグインの format-tab-title があればそのタイトル
+                    let plugin_tab = PluginTab { index, active, pane: tab.focused_pane, title: &title };
+                    TabLabel {
+                        title: self.plugins.format_tab_title(&plugin_tab).unwrap_or(title),
+                        active,
+                        activity: tab.activity,
+                        bell: tab.bell,
+                        done: tab.done.map(|done| done.succeeded()),
+                    }
+                })
+                .collect()
+        });
+
+        // 監視の警告が出ているペイン（タイトルバーを含むペイン全体を枠で囲む）
+        let pane_alerts: Vec<(Rect, MonitorKind)> = self
+            .tab()
+            .layout
+            .calculate_rects(self.pane_area())
+            .into_iter()
+            .filter_map(|(pane_id, rect)| {
+                let kind = self.panes.get(&pane_id)?.monitor.alert?;
+                Some((rect, kind))
+            })
+            .collect();
+
+        let dialog = self
+            .restore_confirm
+            .clone()
+            .or_else(|| self.close_confirm.as_ref().map(CloseConfirm::lines))
+            .or_else(|| self.paste_confirm.as_ref().map(PasteConfirm::lines))
+            .or_else(|| self.explorer.summary_lines());
+
+        // 途中まで押したキー列に続けて押せるキーの一覧
+        let key_hints = self
+            .pending_keys
+            .as_ref()
+            .filter(|_| self.which_key)
+            .map(|(keys, _)| keybinds::continuations(&self.keybinds, keys));
+
+        let overlays = Overlays {
+            explorer: Some(&self.explorer),
+            finder: Some(&self.finder),
+            perf_hud: hud.as_deref(),
+            tab_bar: tab_labels.as_deref(),
+            title_bars: title_bars.as_deref(),
+            status_bar: self.status_bar.as_ref().map(StatusBar::line),
+            dialog: dialog.as_deref(),
+            pane_alerts: Some(&pane_alerts),
+            copy_cursor: copy_cursor.as_ref(),
+            hint_labels: hint_labels.as_ref(),
+            scrollbars: Some(&scrollbars),
+            new_lines: Some(&new_lines),
+            key_hints: key_hints.as_deref(),
         };
 
-        match self.renderer.render_panes_with_explorer(&terminal_refs, explorer_ref) {
-            Ok(_) => true,
-            Err(wgpu::SurfaceError::Lost) => {
-                let size = self.window.inner_size();
-                self.renderer.resize(size.width, size.height);
-                true
-            }
-            Err(wgpu::SurfaceError::OutOfMemory) => {
-                log::error!("GPUメモリ不足");
-                false
-            }
-            Err(e) => {
-                log::warn!("描画エラー: {:?}", e);
-                true
+        f(&mut self.renderer, &terminal_refs, &overlays)
+    }
+
+    /// スクリーンショットを撮ってPNGで保存
+    /// 戻り値: 保存先のパス
+    fn capture_screenshot(&mut self, whole_window: bool) -> Result<PathBuf> {
+        let shot = self.with_frame(|renderer, panes, overlays| {
+            renderer.capture_presented(panes, overlays)
+        })?;
+
+        // ペインのみの場合はフォーカス中のペインの矩形で切り出す
+        let shot = if whole_window {
+            shot
+        } else {
+            let rects = self.tab().layout.calculate_rects(self.pane_area());
+            match rects.iter().find(|(id, _)| *id == self.focused_pane()) {
+                Some((_, rect)) => {
+                    let (width, height) = (shot.width as f32, shot.height as f32);
+                    shot.crop(
+                        (rect.x * width) as u32,
+                        (rect.y * height) as u32,
+                        (rect.width * width).round() as u32,
+                        (rect.height * height).round() as u32,
+                    )
+                }
+                None => shot,
             }
-        }
+        };
+
+        let path = screenshot::timestamped_path(&screenshot::default_dir());
+        shot.save_png(&path)?;
+        Ok(path)
     }
 
-    /// 縦分割（左右に分割）
-    fn split_horizontal(&mut self) -> anyhow::Result<()> {
+    /// 縦分割（左右に分割し、新しいペインで command を起動）
+    fn split_horizontal(&mut self, command: &PaneCommand, cwd: Option<&Path>) -> anyhow::Result<PaneId> {
         let (screen_width, screen_height) = self.renderer.screen_size();
-        let rects = self.layout.calculate_rects(Rect::full());
+        let rects = self.terminal_rects();
 
         // フォーカス中のペインのサイズを取得
         let focused_rect = rects
             .iter()
-            .find(|(id, _)| *id == self.focused_pane)
+            .find(|(id, _)| *id == self.focused_pane())
             .map(|(_, r)| *r)
-            .unwrap_or(Rect::full());
+            .unwrap_or(self.pane_area());
 
         // 新しいペインのサイズを計算（分割後の右半分）
         let new_width = focused_rect.width / 2.0 * screen_width as f32;
         let new_height = focused_rect.height * screen_height as f32;
         let (cols, rows) = self.renderer.calculate_terminal_size_for_viewport(new_width, new_height);
 
-        // 新しいペインを作成
-        let mut new_pane = Pane::new(cols, rows)?;
+        // 新しいペインを作成（バナーはシェルのときだけ）
+        let mut new_pane = Pane::with_cwd(cols, rows, command, cwd)?;
         let new_id = new_pane.id;
-        Self::show_startup_banner(&mut new_pane);
+        if *command == PaneCommand::Shell {
+            Self::show_startup_banner(&mut new_pane);
+        }
 
         // 既存のペインもリサイズ
-        if let Some(pane) = self.panes.get_mut(&self.focused_pane) {
+        let focused = self.focused_pane();
+        if let Some(pane) = self.panes.get_mut(&focused) {
             pane.resize(cols, rows);
         }
 
         // レイアウトを更新
-        self.layout.split_horizontal(self.focused_pane, new_id);
+        self.tab_mut().layout.split_horizontal(focused, new_id);
         self.panes.insert(new_id, new_pane);
 
-        log::info!("縦分割: {:?} -> {:?}", self.focused_pane, new_id);
-        Ok(())
+        // タイトルバーの表示が変わることがあるので全体を合わせ直す
+        self.resize_all_panes();
+
+        log::info!("縦分割: {:?} -> {:?}", focused, new_id);
+        Ok(new_id)
     }
 
-    /// 横分割（上下に分割）
-    fn split_vertical(&mut self) -> anyhow::Result<()> {
+    /// 横分割（上下に分割し、新しいペインで command を起動）
+    fn split_vertical(&mut self, command: &PaneCommand, cwd: Option<&Path>) -> anyhow::Result<PaneId> {
         let (screen_width, screen_height) = self.renderer.screen_size();
-        let rects = self.layout.calculate_rects(Rect::full());
+        let rects = self.terminal_rects();
 
         // フォーカス中のペインのサイズを取得
         let focused_rect = rects
             .iter()
-            .find(|(id, _)| *id == self.focused_pane)
+            .find(|(id, _)| *id == self.focused_pane())
             .map(|(_, r)| *r)
-            .unwrap_or(Rect::full());
+            .unwrap_or(self.pane_area());
 
         // 新しいペインのサイズを計算（分割後の下半分）
         let new_width = focused_rect.width * screen_width as f32;
         let new_height = focused_rect.height / 2.0 * screen_height as f32;
         let (cols, rows) = self.renderer.calculate_terminal_size_for_viewport(new_width, new_height);
 
-        // 新しいペインを作成
-        let mut new_pane = Pane::new(cols, rows)?;
+        // 新しいペインを作成（バナーはシェルのときだけ）
+        let mut new_pane = Pane::with_cwd(cols, rows, command, cwd)?;
         let new_id = new_pane.id;
-        Self::show_startup_banner(&mut new_pane);
+        if *command == PaneCommand::Shell {
+            Self::show_startup_banner(&mut new_pane);
+        }
 
         // 既存のペインもリサイズ
-        if let Some(pane) = self.panes.get_mut(&self.focused_pane) {
+        let focused = self.focused_pane();
+        if let Some(pane) = self.panes.get_mut(&focused) {
             pane.resize(cols, rows);
         }
 
         // レイアウトを更新
-        self.layout.split_vertical(self.focused_pane, new_id);
+        self.tab_mut().layout.split_vertical(focused, new_id);
         self.panes.insert(new_id, new_pane);
 
-        log::info!("横分割: {:?} -> {:?}", self.focused_pane, new_id);
-        Ok(())
+        // タイトルバーの表示が変わることがあるので全体を合わせ直す
+        self.resize_all_panes();
+
+        log::info!("横分割: {:?} -> {:?}", focused, new_id);
+        Ok(new_id)
     }
 
-    /// 現在のペインを閉じる
-    fn close_pane(&mut self) -> bool {
-        // ペインが1つしかない場合はウィンドウを閉じる
-        if self.panes.len() <= 1 {
-            return true; // ウィンドウを閉じる
+    /// 閉じる操作を受け付ける（実行中のコマンドがあれば確認ダイアログを出す）
+    /// 戻り値: ウィンドウを閉じるべきか
+    fn request_close(&mut self, target: CloseTarget) -> bool {
+        let pane_ids = match target {
+            CloseTarget::Pane => vec![self.focused_pane()],
+            CloseTarget::Tab(index) => match self.tabs.get(index) {
+                Some(tab) => tab.layout.all_pane_ids(),
+                None => return false,
+            },
+            CloseTarget::Window => self.panes.keys().copied().collect(),
+        };
+        let commands: Vec<String> = pane_ids
+            .iter()
+            .filter_map(|id| self.panes.get(id)?.running_command())
+            .collect();
+
+        if commands.is_empty() {
+            return self.close(target);
         }
+        log::info!("実行中のコマンドがあるため確認します: {:?}", commands);
+        self.close_confirm = Some(CloseConfirm { target, commands });
+        self.needs_redraw = true;
+        false
+    }
 
-        // 次のフォーカス先を決定
-        let next_focus = self.layout.next_pane(self.focused_pane);
+    /// 確認ダイアログへの応答
+    /// 戻り値: ウィンドウを閉じるべきか
+    fn answer_close(&mut self, accept: bool) -> bool {
+        self.needs_redraw = true;
+        match self.close_confirm.take() {
+            Some(confirm) if accept => self.close(confirm.target),
+            _ => false,
+        }
+    }
 
-        // レイアウトからペインを削除
-        if let Some(new_layout) = self.layout.remove_pane(self.focused_pane) {
-            self.layout = new_layout;
+    /// 確認なしで閉じる
+    /// 戻り値: ウィンドウを閉じるべきか
+    fn close(&mut self, target: CloseTarget) -> bool {
+        match target {
+            CloseTarget::Pane => self.close_pane(),
+            CloseTarget::Tab(index) => self.close_tab(index),
+            CloseTarget::Window => true,
         }
+    }
 
-        // ペインを削除
-        self.panes.remove(&self.focused_pane);
+    /// 現在のペインを閉じる
+    /// 戻り値: ウィンドウを閉じるべきか
+    fn close_pane(&mut self) -> bool {
+        // タブのペインが1つしかない場合はタブを閉じる
+        if self.tab().layout.pane_count() <= 1 {
+            // 最後のペインならウィンドウごと閉じる（ウィンドウを閉じるだけだとデーモン上の
+            // セッションはデタッチになるので、ここでシェルを終了させておく）
+            if self.tabs.len() <= 1 {
+                let focused = self.focused_pane();
+                if let Some(pane) = self.panes.get_mut(&focused) {
+                    pane.terminate();
+                }
+            }
+            return self.close_tab(self.active_tab);
+        }
 
-        // フォーカスを移動
-        if let Some(next) = next_focus {
-            self.focused_pane = next;
-        } else if let Some(id) = self.panes.keys().next().copied() {
-            self.focused_pane = id;
+        // ペインを取り外して終了させる
+        if let Some(mut pane) = self.take_focused_pane() {
+            pane.terminate();
         }
 
-        log::info!("ペインを閉じました。残り: {}", self.panes.len());
+        log::info!("ペインを閉じました。残り: {}", self.tab().layout.pane_count());
         false // ウィンドウは閉じない
     }
 
-    /// 次のペインにフォーカス
-    fn focus_next_pane(&mut self) {
-        if let Some(next) = self.layout.next_pane(self.focused_pane) {
-            self.focused_pane = next;
-            log::info!("フォーカス移動: {:?}", self.focused_pane);
+    /// フォーカス中のペインをこのウィンドウから取り外す（PTYとスクロールバックはそのまま）
+    /// タブの最後のペインならタブも取り除く
+    /// 最後のタブの最後のペインを取り外すと tabs は空になるので、呼び出し側でウィンドウを閉じること
+    fn take_focused_pane(&mut self) -> Option<Pane> {
+        self.take_pane(self.focused_pane())
+    }
+
+    /// ペインをこのウィンドウから取り外す（表示中でないタブのペインでもよい）
+    fn take_pane(&mut self, pane_id: PaneId) -> Option<Pane> {
+        let index = self
+            .tabs
+            .iter()
+            .position(|tab| tab.layout.all_pane_ids().contains(&pane_id))?;
+        self.selecting_text = false;
+        self.dragging_border = None;
+        self.pipe_runs.remove(&pane_id);
+
+        if self.tabs[index].layout.pane_count() > 1 {
+            let tab = &mut self.tabs[index];
+
+            // 次のフォーカス先を決定
+            let next_focus = tab.layout.next_pane(pane_id);
+
+            // レイアウトからペインを削除
+            if let Some(new_layout) = tab.layout.remove_pane(pane_id) {
+                tab.layout = new_layout;
+            }
+
+            // フォーカス中のペインだったらフォーカスを移動
+            if tab.focused_pane == pane_id {
+                if let Some(next) = next_focus {
+                    tab.focused_pane = next;
+                } else if let Some(id) = tab.layout.all_pane_ids().first().copied() {
+                    tab.focused_pane = id;
+                }
+            }
+        } else {
+            self.tabs.remove(index);
+            if self.tabs.is_empty() {
+                return self.panes.remove(&pane_id);
+            }
+            // 表示中のタブより前を取り除いたら番号がずれる
+            if index < self.active_tab || self.active_tab >= self.tabs.len() {
+                self.active_tab = self.active_tab.saturating_sub(1);
+            }
+            self.select_tab(self.active_tab);
+        }
+
+        let pane = self.panes.remove(&pane_id);
+        // 残ったペインを空いた領域に広げる
+        self.resize_all_panes();
+        self.needs_redraw = true;
+        pane
+    }
+
+    /// 他のウィンドウから移ってきたペインを、フォーカス中のペインの右に分割して追加
+    fn insert_pane(&mut self, pane: Pane) {
+        let pane_id = pane.id;
+        let tab = self.tab_mut();
+        let focused = tab.focused_pane;
+        tab.layout.split_horizontal(focused, pane_id);
+        tab.focused_pane = pane_id;
+        self.panes.insert(pane_id, pane);
+
+        // 新しい配置の大きさに合わせる（移ってきたペインも含む）
+        self.resize_all_panes();
+        self.needs_redraw = true;
+    }
+
+    /// ウィンドウに残っているペインが1つだけか
+    fn has_single_pane(&self) -> bool {
+        self.panes.len() <= 1
+    }
+
+    /// 次のペインにフォーカス
+    fn focus_next_pane(&mut self) {
+        let tab = self.tab_mut();
+        if let Some(next) = tab.layout.next_pane(tab.focused_pane) {
+            tab.focused_pane = next;
+            log::info!("フォーカス移動: {:?}", next);
         }
     }
 
     /// 前のペインにフォーカス
     fn focus_prev_pane(&mut self) {
-        if let Some(prev) = self.layout.prev_pane(self.focused_pane) {
-            self.focused_pane = prev;
-            log::info!("フォーカス移動: {:?}", self.focused_pane);
+        let tab = self.tab_mut();
+        if let Some(prev) = tab.layout.prev_pane(tab.focused_pane) {
+            tab.focused_pane = prev;
+            log::info!("フォーカス移動: {:?}", prev);
+        }
+    }
+
+    /// フォーカス中のペインを次（forward=false なら前）のペインと入れ替える
+    /// フォーカスは移動したペインに付いていく
+    fn swap_pane(&mut self, forward: bool) {
+        let tab = self.tab_mut();
+        let focused = tab.focused_pane;
+        let other = if forward {
+            tab.layout.next_pane(focused)
+        } else {
+            tab.layout.prev_pane(focused)
+        };
+        let Some(other) = other else { return };
+
+        if tab.layout.swap_panes(focused, other) {
+            // 入れ替わった位置の大きさに合わせる
+            self.resize_all_panes();
+            self.needs_redraw = true;
+            log::info!("ペインを入れ替え: {:?} <-> {:?}", focused, other);
+        }
+    }
+
+    /// ペインの大きさを均等にする（level=true ならフォーカス中のペインの段だけ）
+    fn balance_panes(&mut self, level: bool) {
+        let tab = self.tab_mut();
+        if level {
+            let focused = tab.focused_pane;
+            if !tab.layout.balance_level(focused) {
+                return;
+            }
+        } else {
+            tab.layout.balance();
+        }
+        self.resize_all_panes();
+        self.needs_redraw = true;
+    }
+
+    /// 表示中のタブのペインを名前付きのレイアウトに並べ直す（フォーカス中のペインがメイン）
+    fn apply_layout(&mut self, preset: LayoutPreset) {
+        let tab = self.tab_mut();
+        let focused = tab.focused_pane;
+        let mut ids = vec![focused];
+        ids.extend(tab.layout.all_pane_ids().into_iter().filter(|&id| id != focused));
+
+        if let Some(layout) = preset.build(&ids) {
+            tab.layout = layout;
+            self.resize_all_panes();
+            self.needs_redraw = true;
+            log::info!("レイアウトを適用: {}", preset.name());
+        }
+    }
+
+    /// 新しいタブを開いて表示（command を起動する）
+    fn new_tab(&mut self, command: &PaneCommand) -> Result<()> {
+        // タブバーの分だけ狭い領域でペインを作る
+        let area = Self::pane_area_for(&self.renderer, self.tabs.len() + 1, self.status_bar.is_some(), self.sidebar_cols());
+        let (screen_width, screen_height) = self.renderer.screen_size();
+        let (cols, rows) = self.renderer.calculate_terminal_size_for_viewport(
+            area.width * screen_width as f32,
+            area.height * screen_height as f32,
+        );
+
+        let mut pane = Pane::with_command(cols, rows, command)?;
+        Self::show_startup_banner(&mut pane);
+        self.add_tab(pane);
+
+        log::info!("新しいタブ: {}", self.tabs.len());
+        Ok(())
+    }
+
+    /// ペインを新しいタブとして追加して表示
+    fn add_tab(&mut self, pane: Pane) {
+        let pane_id = pane.id;
+        self.panes.insert(pane_id, pane);
+
+        self.tabs.push(Tab::new(pane_id));
+        self.select_tab(self.tabs.len() - 1);
+
+        // タブバーが現れたら既存のペインも縮める
+        self.resize_all_panes();
+    }
+
+    /// タブを閉じる（タブ内のペインもすべて閉じる）
+    /// 戻り値: ウィンドウを閉じるべきか（最後のタブの場合）
+    fn close_tab(&mut self, index: usize) -> bool {
+        if self.tabs.len() <= 1 {
+            return true;
+        }
+        if index >= self.tabs.len() {
+            return false;
+        }
+
+        let tab = self.tabs.remove(index);
+        for pane_id in tab.layout.all_pane_ids() {
+            if let Some(mut pane) = self.panes.remove(&pane_id) {
+                pane.terminate();
+            }
+        }
+
+        // 表示中のタブより前を閉じたら番号がずれる
+        if index < self.active_tab || self.active_tab >= self.tabs.len() {
+            self.active_tab = self.active_tab.saturating_sub(1);
+        }
+        self.select_tab(self.active_tab);
+
+        // タブバーが消えたらペインを広げる
+        self.resize_all_panes();
+
+        log::info!("タブを閉じました。残り: {}", self.tabs.len());
+        false
+    }
+
+    /// タブを切り替える
+    fn select_tab(&mut self, index: usize) {
+        if index >= self.tabs.len() {
+            return;
         }
+        // 切り替え前のタブの選択・ドラッグは中断
+        self.selecting_text = false;
+        self.dragging_border = None;
+
+        self.active_tab = index;
+        self.tab_mut().clear_badges();
+        self.needs_redraw = true;
+    }
+
+    /// 次のタブに切り替える（末尾からは先頭へ）
+    fn select_next_tab(&mut self) {
+        self.select_tab((self.active_tab + 1) % self.tabs.len());
+    }
+
+    /// 前のタブに切り替える（先頭からは末尾へ）
+    fn select_prev_tab(&mut self) {
+        self.select_tab((self.active_tab + self.tabs.len() - 1) % self.tabs.len());
+    }
+
+    /// ペインのあるタブに切り替えてフォーカスする
+    /// 戻り値: このウィンドウのペインか
+    fn select_pane(&mut self, pane_id: PaneId) -> bool {
+        let Some(index) = self.tabs.iter().position(|tab| tab.layout.all_pane_ids().contains(&pane_id)) else {
+            return false;
+        };
+        self.select_tab(index);
+        self.tab_mut().focused_pane = pane_id;
+        true
     }
 
     /// キー入力を処理
@@ -359,30 +1554,214 @@ impl WindowState {
         let ctrl = self.modifiers.state().control_key();
         let super_key = self.modifiers.state().super_key();
         let shift = self.modifiers.state().shift_key();
+        // Meta にする側の Alt なら ESC を前に付けて送る（シェルの alt+b / alt+f など）
+        let meta = self.modifiers.state().alt_key()
+            && self.keyboard.alt_is_meta(
+                self.modifiers.lalt_state() == ModifiersKeyState::Pressed,
+                self.modifiers.ralt_state() == ModifiersKeyState::Pressed,
+            );
+
+        // クラッシュしたセッションの確認中は Enter / y で戻し、Esc / n で捨てる（他のキーは無視）
+        if self.restore_confirm.is_some() {
+            return match &event.logical_key {
+                Key::Named(NamedKey::Enter) => WindowCommand::AnswerRestore(true),
+                Key::Character(c) if c == "y" => WindowCommand::AnswerRestore(true),
+                Key::Named(NamedKey::Escape) => WindowCommand::AnswerRestore(false),
+                Key::Character(c) if c == "n" => WindowCommand::AnswerRestore(false),
+                _ => WindowCommand::None,
+            };
+        }
 
-        // エクスプローラーにフォーカス中の場合
-        if self.explorer_focused && self.explorer.visible {
+        // 閉じる前の確認中は Enter / y で閉じ、Esc / n で取り消す（他のキーは無視）
+        if self.close_confirm.is_some() {
+            return match &event.logical_key {
+                Key::Named(NamedKey::Enter) => WindowCommand::AnswerClose(true),
+                Key::Character(c) if c == "y" => WindowCommand::AnswerClose(true),
+                Key::Named(NamedKey::Escape) => WindowCommand::AnswerClose(false),
+                Key::Character(c) if c == "n" => WindowCommand::AnswerClose(false),
+                _ => WindowCommand::None,
+            };
+        }
+
+        // ペーストの確認中は Enter / y でペーストし、Esc / n で取り消す（他のキーは無視）
+        if self.paste_confirm.is_some() {
             match &event.logical_key {
-                Key::Named(NamedKey::ArrowUp) => return WindowCommand::ExplorerUp,
-                Key::Named(NamedKey::ArrowDown) => return WindowCommand::ExplorerDown,
-                Key::Named(NamedKey::Enter) => return WindowCommand::ExplorerEnter,
-                Key::Named(NamedKey::Escape) => return WindowCommand::ToggleExplorer,
-                Key::Character(c) if c == "g" => return WindowCommand::ExplorerGo, // g: cd実行
+                Key::Named(NamedKey::Enter) => self.answer_paste(true),
+                Key::Character(c) if c == "y" => self.answer_paste(true),
+                Key::Named(NamedKey::Escape) => self.answer_paste(false),
+                Key::Character(c) if c == "n" => self.answer_paste(false),
                 _ => {}
             }
+            return WindowCommand::None;
+        }
+
+        // スクロールバック検索・コマンドパレット中はすべてのキーを絞り込みに使う
+        if self.finder.visible {
+            return self.handle_finder_key(event, ctrl, shift);
+        }
+
+        // コピーモード中はすべてのキーをカーソル移動・選択に使う
+        if self.copy_mode.is_some() {
+            self.handle_copy_mode_key(event, ctrl);
+            return WindowCommand::None;
+        }
+
+        // ヒントモード中はすべてのキーをラベルの入力に使う
+        if self.hint_mode.is_some() {
+            self.handle_hint_key(event);
+            return WindowCommand::None;
+        }
+
+        // エクスプローラーにフォーカス中は Cmd のショートカット以外をシェルに送らない
+        if self.explorer_focused && self.explorer.visible && !super_key {
+            // ファイル操作の名前の入力中・削除の確認中
+            if self.explorer.prompt.is_some() {
+                self.handle_explorer_prompt_key(event, ctrl);
+                return WindowCommand::None;
+            }
+            // ブックマークの一覧を表示中
+            if self.explorer.places.is_some() {
+                self.handle_explorer_places_key(event, ctrl);
+                return WindowCommand::None;
+            }
+            // 名前での絞り込み中
+            if self.explorer.filter.is_some() {
+                return self.handle_explorer_filter_key(event, ctrl);
+            }
+            if let Key::Character(c) = &event.logical_key {
+                if self.begin_explorer_prompt(c) || self.use_explorer_path(c) {
+                    return WindowCommand::None;
+                }
+            }
+            return match &event.logical_key {
+                Key::Named(NamedKey::ArrowUp) => WindowCommand::ExplorerUp,
+                Key::Named(NamedKey::ArrowDown) => WindowCommand::ExplorerDown,
+                Key::Named(NamedKey::Enter) => WindowCommand::ExplorerEnter,
+                // 印を付けていれば閉じずに印を外す
+                Key::Named(NamedKey::Escape) if !self.explorer.marked.is_empty() => {
+                    self.explorer.marked.clear();
+                    self.needs_redraw = true;
+                    WindowCommand::None
+                }
+                Key::Named(NamedKey::Space) => {
+                    self.explorer.toggle_mark();
+                    self.needs_redraw = true;
+                    WindowCommand::None
+                }
+                // サイドバーなら閉じずにペインにフォーカスを戻す
+                Key::Named(NamedKey::Escape) if self.explorer.docked() => {
+                    self.explorer_focused = false;
+                    self.needs_redraw = true;
+                    WindowCommand::None
+                }
+                Key::Named(NamedKey::Escape) => WindowCommand::ToggleExplorer,
+                Key::Character(c) if c == "g" => WindowCommand::ExplorerGo, // g: cd実行
+                _ => WindowCommand::None,
+            };
+        }
+
+        // キー割り当て（既定の割り当てと設定ファイル、組み込みのショートカットより優先）
+        let mods = Mods::from_state(self.modifiers.state());
+        if let Some(stroke) = KeyStroke::from_event(mods, &event.logical_key) {
+            // プラグインの key フックが true を返したらキーを使ったとみなす（続けて押すキーの途中は除く）
+            if self.pending_keys.is_none() && self.plugins.key(&stroke.label(), self.focused_pane()) {
+                return WindowCommand::None;
+            }
+            let mut pressed = self.take_pending_keys();
+            let waiting = !pressed.is_empty();
+            pressed.push(stroke);
+            match keybinds::lookup(&self.keybinds, &pressed) {
+                KeyMatch::Action(binding) => {
+                    let binding = binding.clone();
+                    self.needs_redraw |= waiting;
+                    return self.bound_command(binding.action, binding.profile.as_deref(), binding.text.as_deref());
+                }
+                KeyMatch::Pending => {
+                    self.pending_keys = Some((pressed, Instant::now()));
+                    self.needs_redraw = true;
+                    return WindowCommand::None;
+                }
+                // 続きとして割り当てのないキーは、列ごと捨てる（シェルには送らない）
+                KeyMatch::NoMatch if waiting => {
+                    self.needs_redraw = true;
+                    return WindowCommand::None;
+                }
+                KeyMatch::NoMatch => {}
+            }
+        }
+
+        // Shift+PageUp/PageDown: スクロールバックを1画面分スクロール
+        if shift && !super_key {
+            let direction = match &event.logical_key {
+                Key::Named(NamedKey::PageUp) => Some(1),
+                Key::Named(NamedKey::PageDown) => Some(-1),
+                _ => None,
+            };
+            if let Some(direction) = direction {
+                self.scroll_display_page(direction);
+                return WindowCommand::None;
+            }
         }
 
         // macOSのCmd+キーを処理
         if super_key {
+            // Cmd+Shift+Space: ヒントモード
+            if let Key::Named(NamedKey::Space) = &event.logical_key {
+                if shift {
+                    return WindowCommand::EnterHintMode;
+                }
+            }
+            // Cmd+↑ / Cmd+↓: 前/次のプロンプトへ
+            let prompt_direction = match &event.logical_key {
+                Key::Named(NamedKey::ArrowUp) => Some(-1),
+                Key::Named(NamedKey::ArrowDown) => Some(1),
+                _ => None,
+            };
+            if let Some(direction) = prompt_direction {
+                self.jump_to_prompt(direction);
+                return WindowCommand::None;
+            }
             if let Key::Character(c) = &event.logical_key {
                 match c.to_lowercase().as_str() {
-                    "n" => return WindowCommand::NewWindow,
-                    "d" if shift => return WindowCommand::SplitVertical,   // Cmd+Shift+D: 横分割
-                    "d" => return WindowCommand::SplitHorizontal,          // Cmd+D: 縦分割
+                    "n" if shift => return WindowCommand::DetachPane,      // Cmd+Shift+N: ペインを新しいウィンドウへ
+                    "m" if shift => return WindowCommand::MergePane,       // Cmd+Shift+M: ペインを直前のウィンドウへ
+                    "n" => return WindowCommand::NewWindow(PaneCommand::Shell),
+                    "t" => return WindowCommand::NewTab(PaneCommand::Shell),                   // Cmd+T: 新規タブ
+                    "}" | "]" if shift => return WindowCommand::NextTab,   // Cmd+Shift+]: 次のタブ
+                    "{" | "[" if shift => return WindowCommand::PrevTab,   // Cmd+Shift+[: 前のタブ
+                    // Cmd+1〜8: 番号のタブ、Cmd+9: 最後のタブ
+                    // Cmd+Ctrl+1〜4: プリセットレイアウト
+                    digit @ ("1" | "2" | "3" | "4") if ctrl => {
+                        let index = digit.parse::<usize>().unwrap_or(1) - 1;
+                        return WindowCommand::ApplyLayout(LayoutPreset::ALL[index]);
+                    }
+                    "9" => return WindowCommand::SelectTab(self.tabs.len() - 1),
+                    digit @ ("1" | "2" | "3" | "4" | "5" | "6" | "7" | "8") => {
+                        let index = digit.parse::<usize>().unwrap_or(1) - 1;
+                        return WindowCommand::SelectTab(index);
+                    }
+                    "s" if shift && ctrl => return WindowCommand::ScreenshotWindow, // Cmd+Ctrl+Shift+S: ウィンドウを保存
+                    "s" if shift => return WindowCommand::ScreenshotPane,           // Cmd+Shift+S: ペインを保存
+                    "h" if shift => return WindowCommand::TogglePerfHud,            // Cmd+Shift+H: パフォーマンスHUD
+                    "r" if shift => return WindowCommand::RespawnPane,             // Cmd+Shift+R: ペインのシェルを起動し直す
+                    "a" if shift => return WindowCommand::ToggleMonitor(MonitorKind::Activity), // Cmd+Shift+A: 出力を監視
+                    "i" if shift => return WindowCommand::ToggleMonitor(MonitorKind::Silence),  // Cmd+Shift+I: 無音を監視
+                    "c" if shift => return WindowCommand::EnterCopyMode,            // Cmd+Shift+C: コピーモード
+                    "o" if shift => return WindowCommand::CopyLastOutput,           // Cmd+Shift+O: 最後の出力をコピー
+                    "d" if shift => return WindowCommand::SplitVertical(PaneCommand::Shell), // Cmd+Shift+D: 横分割
+                    "d" => return WindowCommand::SplitHorizontal(PaneCommand::Shell),        // Cmd+D: 縦分割
                     "w" => return WindowCommand::ClosePane,                // Cmd+W: ペインを閉じる
                     "c" => return WindowCommand::Copy,                     // Cmd+C: コピー
+                    "v" if shift => return WindowCommand::OpenClipboardHistory, // Cmd+Shift+V: コピーの履歴からペースト
+                    "l" if shift => return WindowCommand::OpenLogViewer,   // Cmd+Shift+L: ログビューア
                     "v" => return WindowCommand::Paste,                    // Cmd+V: ペースト
-                    "b" => return WindowCommand::ToggleExplorer,           // Cmd+B: エクスプローラー
+                    "e" | "b" => return WindowCommand::ToggleExplorer,     // Cmd+E / Cmd+B: エクスプローラー
+                    "f" => return WindowCommand::OpenFinder,               // Cmd+F: スクロールバック検索
+                    "p" if shift => return WindowCommand::OpenPalette,     // Cmd+Shift+P: コマンドパレット
+                    "=" if ctrl => return WindowCommand::BalancePanes,     // Cmd+Ctrl+=: すべてのペインを均等に
+                    "-" if ctrl => return WindowCommand::BalanceLevel,     // Cmd+Ctrl+-: 現在の段だけ均等に
+                    "]" if ctrl => return WindowCommand::SwapNextPane,     // Cmd+Ctrl+]: 次のペインと入れ替え
+                    "[" if ctrl => return WindowCommand::SwapPrevPane,     // Cmd+Ctrl+[: 前のペインと入れ替え
                     "]" => return WindowCommand::FocusNextPane,            // Cmd+]: 次のペイン
                     "[" => return WindowCommand::FocusPrevPane,            // Cmd+[: 前のペイン
                     _ => {}
@@ -392,25 +1771,8 @@ impl WindowState {
 
         // キーをバイト列に変換してPTYに送信
         let bytes: Option<Vec<u8>> = match &event.logical_key {
-            // 名前付きキー
-            Key::Named(named) => match named {
-                NamedKey::Space => Some(b" ".to_vec()),
-                NamedKey::Enter => Some(b"\r".to_vec()),
-                NamedKey::Backspace => Some(b"\x7f".to_vec()),
-                NamedKey::Tab => Some(b"\t".to_vec()),
-                NamedKey::Escape => Some(b"\x1b".to_vec()),
-                NamedKey::ArrowUp => Some(b"\x1b[A".to_vec()),
-                NamedKey::ArrowDown => Some(b"\x1b[B".to_vec()),
-                NamedKey::ArrowRight => Some(b"\x1b[C".to_vec()),
-                NamedKey::ArrowLeft => Some(b"\x1b[D".to_vec()),
-                NamedKey::Home => Some(b"\x1b[H".to_vec()),
-                NamedKey::End => Some(b"\x1b[F".to_vec()),
-                NamedKey::PageUp => Some(b"\x1b[5~".to_vec()),
-                NamedKey::PageDown => Some(b"\x1b[6~".to_vec()),
-                NamedKey::Insert => Some(b"\x1b[2~".to_vec()),
-                NamedKey::Delete => Some(b"\x1b[3~".to_vec()),
-                _ => None,
-            },
+            // 名前付きキー（修飾キー付きの矢印なども）
+            Key::Named(named) => keys::named_key(named, Mods::from_state(self.modifiers.state())),
             // 文字キー（Ctrl修飾キーの処理を含む）
             Key::Character(c) => {
                 // Cmd+キーは既に処理済み
@@ -428,7 +1790,7 @@ impl WindowState {
                 if !ctrl {
                     if let Some(ch) = c.chars().next() {
                         // ASCII印刷可能文字（0x20-0x7E）以外はスキップ
-                        if !(ch >= ' ' && ch <= '~') {
+                        if !(' '..='~').contains(&ch) {
                             log::info!("Skipping non-ASCII char: '{}' U+{:04X}", ch, ch as u32);
                             return WindowCommand::None;
                         }
@@ -437,20 +1799,7 @@ impl WindowState {
 
                 if ctrl {
                     // Ctrl+文字 の処理
-                    let ch = c.chars().next().unwrap_or(' ');
-                    match ch.to_ascii_lowercase() {
-                        'c' => Some(vec![0x03]), // Ctrl+C (ETX)
-                        'd' => Some(vec![0x04]), // Ctrl+D (EOT)
-                        'z' => Some(vec![0x1a]), // Ctrl+Z (SUB)
-                        'l' => Some(vec![0x0c]), // Ctrl+L (FF - clear screen)
-                        'a' => Some(vec![0x01]), // Ctrl+A
-                        'e' => Some(vec![0x05]), // Ctrl+E
-                        'u' => Some(vec![0x15]), // Ctrl+U
-                        'k' => Some(vec![0x0b]), // Ctrl+K
-                        'w' => Some(vec![0x17]), // Ctrl+W
-                        'r' => Some(vec![0x12]), // Ctrl+R
-                        _ => None,
-                    }
+                    c.chars().next().and_then(keys::ctrl_char).map(|byte| vec![byte])
                 } else {
                     // 通常の文字入力（textフィールドを使用）
                     event.text.as_ref().map(|t| t.as_bytes().to_vec())
@@ -461,9 +1810,17 @@ impl WindowState {
             _ => None,
         };
 
+        // Meta は ESC を前に付ける（矢印などのシーケンスはそのまま）
+        let bytes = bytes.map(|bytes| match meta && !bytes.starts_with(b"\x1b[") {
+            true => [b"\x1b".as_slice(), &bytes].concat(),
+            false => bytes,
+        });
+
         // フォーカス中のペインにキー入力を送信
         if let Some(bytes) = bytes {
-            if let Some(pane) = self.panes.get(&self.focused_pane) {
+            if let Some(pane) = self.panes.get(&self.focused_pane()) {
+                // 入力時はスクロールバック表示から最下部に戻る
+                Self::reset_display_offset(pane);
                 if bytes.len() == 1 && bytes[0] > 0x7f {
                     log::warn!("Sending non-ASCII byte: 0x{:02X}", bytes[0]);
                 } else if bytes.iter().any(|&b| b > 0x7f) {
@@ -476,6 +1833,102 @@ impl WindowState {
         WindowCommand::None
     }
 
+    /// キー割り当ての操作を実行する（profile は新しく開くペインのプロファイル、text は send-text で送る文字列）
+    fn bound_command(&mut self, action: BindAction, profile: Option<&str>, text: Option<&str>) -> WindowCommand {
+        let launch = || profile.map_or(PaneCommand::Shell, |name| PaneCommand::Profile(name.to_string()));
+        match action {
+            BindAction::NewWindow => WindowCommand::NewWindow(launch()),
+            BindAction::NewTab => WindowCommand::NewTab(launch()),
+            BindAction::NextTab => WindowCommand::NextTab,
+            BindAction::PrevTab => WindowCommand::PrevTab,
+            BindAction::ClosePane => WindowCommand::ClosePane,
+            BindAction::SplitHorizontal => WindowCommand::SplitHorizontal(launch()),
+            BindAction::SplitVertical => WindowCommand::SplitVertical(launch()),
+            BindAction::FocusNextPane => WindowCommand::FocusNextPane,
+            BindAction::FocusPrevPane => WindowCommand::FocusPrevPane,
+            BindAction::SwapNextPane => WindowCommand::SwapNextPane,
+            BindAction::SwapPrevPane => WindowCommand::SwapPrevPane,
+            BindAction::BalancePanes => WindowCommand::BalancePanes,
+            BindAction::Copy => WindowCommand::Copy,
+            BindAction::CopyRich => {
+                self.copy_selection_rich();
+                WindowCommand::None
+            }
+            BindAction::Paste => WindowCommand::Paste,
+            BindAction::ClipboardHistory => WindowCommand::OpenClipboardHistory,
+            BindAction::ToggleExplorer => WindowCommand::ToggleExplorer,
+            BindAction::OpenFinder => WindowCommand::OpenFinder,
+            BindAction::CommandPalette => WindowCommand::OpenPalette,
+            BindAction::SshLauncher => {
+                self.open_ssh_launcher();
+                WindowCommand::None
+            }
+            BindAction::ContainerLauncher => {
+                self.open_container_launcher();
+                WindowCommand::None
+            }
+            BindAction::WslLauncher => {
+                self.open_wsl_launcher();
+                WindowCommand::None
+            }
+            BindAction::LogViewer => WindowCommand::OpenLogViewer,
+            BindAction::CopyMode => WindowCommand::EnterCopyMode,
+            BindAction::HintMode => WindowCommand::EnterHintMode,
+            BindAction::ToggleFullscreen => WindowCommand::ToggleFullscreen,
+            BindAction::ScreenshotPane => WindowCommand::ScreenshotPane,
+            BindAction::ScreenshotWindow => WindowCommand::ScreenshotWindow,
+            BindAction::RespawnPane => WindowCommand::RespawnPane,
+            BindAction::CopyLastOutput => WindowCommand::CopyLastOutput,
+            BindAction::TogglePerfHud => WindowCommand::TogglePerfHud,
+            BindAction::NextTheme => WindowCommand::NextTheme,
+            BindAction::ScrollPageUp => {
+                self.scroll_display_page(1);
+                WindowCommand::None
+            }
+            BindAction::ScrollPageDown => {
+                self.scroll_display_page(-1);
+                WindowCommand::None
+            }
+            BindAction::ToggleScrollbackSpill => {
+                self.toggle_scrollback_spill();
+                WindowCommand::None
+            }
+            BindAction::ToggleRecording => {
+                self.toggle_recording();
+                WindowCommand::None
+            }
+            BindAction::SaveScrollback | BindAction::SaveScrollbackAnsi => {
+                self.save_scrollback(text, action == BindAction::SaveScrollbackAnsi);
+                WindowCommand::None
+            }
+            BindAction::SendText => {
+                if let (Some(pane), Some(text)) = (self.panes.get(&self.focused_pane()), text) {
+                    Self::reset_display_offset(pane);
+                    let _ = pane.pty.write(text.as_bytes());
+                }
+                WindowCommand::None
+            }
+            BindAction::Pipe => {
+                if let Err(e) = self.run_pipe(text.unwrap_or_default()) {
+                    log::error!("パイプを実行できません: {:#}", e);
+                }
+                WindowCommand::None
+            }
+            BindAction::Ssh => WindowCommand::NewTab(ssh::launch(&config::current(), text.unwrap_or_default())),
+            BindAction::Container => WindowCommand::NewTab(PaneCommand::Container(text.unwrap_or_default().to_string())),
+            BindAction::Wsl => WindowCommand::NewTab(PaneCommand::Wsl(text.unwrap_or_default().to_string())),
+            // 設定ファイルの名前は読み込むときに確かめる（プラグインからは正しくない名前も来る）
+            BindAction::ApplyLayout => match text.and_then(LayoutPreset::from_name) {
+                Some(preset) => WindowCommand::ApplyLayout(preset),
+                None => {
+                    log::warn!("レイアウトの名前が正しくありません: {:?}", text.unwrap_or_default());
+                    WindowCommand::None
+                }
+            },
+            BindAction::None => WindowCommand::None,
+        }
+    }
+
     /// IME入力を処理（日本語入力など）
     fn handle_ime(&mut self, ime: &Ime) {
         match ime {
@@ -488,8 +1941,16 @@ impl WindowState {
                 let filtered: String = text.chars()
                     .filter(|&c| c >= ' ' && c != '\u{2020}' && c != '\u{2021}')
                     .collect();
-                if !filtered.is_empty() {
-                    if let Some(pane) = self.panes.get(&self.focused_pane) {
+                if !filtered.is_empty() && self.finder.visible {
+                    // スクロールバック検索中は検索文字列に入れる
+                    self.finder.push_str(&filtered);
+                    self.needs_redraw = true;
+                } else if let Some((_, mode)) = self.copy_mode.as_mut().filter(|_| !filtered.is_empty()) {
+                    // コピーモード中は検索の入力にだけ使う
+                    mode.push_search(&filtered);
+                    self.needs_redraw = true;
+                } else if !filtered.is_empty() {
+                    if let Some(pane) = self.panes.get(&self.focused_pane()) {
                         let _ = pane.pty.write(filtered.as_bytes());
                     }
                 }
@@ -501,36 +1962,43 @@ impl WindowState {
             }
             Ime::Enabled => {
                 self.ime_active = true;
+                self.ime_cursor_area = None;
                 self.update_ime_cursor_area();
             }
             Ime::Disabled => {
                 self.ime_active = false;
+                self.ime_cursor_area = None;
             }
         }
     }
 
     /// IMEカーソルエリアを更新
-    fn update_ime_cursor_area(&self) {
-        if let Some(pane) = self.panes.get(&self.focused_pane) {
-            let terminal = pane.terminal.lock();
-            let (cell_width, cell_height) = self.renderer.cell_size();
-
-            // ペインの矩形領域を取得
-            let rects = self.layout.calculate_rects(Rect::full());
-            let (screen_width, screen_height) = self.renderer.screen_size();
-
-            if let Some((_, rect)) = rects.iter().find(|(id, _)| *id == self.focused_pane) {
-                let vp_x = rect.x * screen_width as f32;
-                let vp_y = rect.y * screen_height as f32;
-
-                let x = terminal.cursor.col as f32 * cell_width + vp_x;
-                let y = terminal.cursor.row as f32 * cell_height + vp_y;
-
-                let position = PhysicalPosition::new(x as u32, y as u32);
-                let size = PhysicalSize::new(cell_width as u32, cell_height as u32);
+    ///
+    /// 候補のポップアップを出す位置になる（Wayland の text-input はフォーカスを得るたびに知らせ直す必要がある）
+    fn update_ime_cursor_area(&mut self) {
+        let focused = self.focused_pane();
+        let Some(pane) = self.panes.get(&focused) else { return };
+        let rects = self.terminal_rects();
+        let Some((_, rect)) = rects.iter().find(|(id, _)| *id == focused) else { return };
+        let (cell_width, cell_height) = self.renderer.cell_size();
+        let (screen_width, screen_height) = self.renderer.screen_size();
 
-                self.window.set_ime_cursor_area(position, size);
-            }
+        // スクロールバックを表示中はカーソルの行も下にずれる
+        let (col, row) = {
+            let terminal = pane.terminal.lock();
+            let row = (terminal.cursor.row + terminal.display_offset).min(terminal.grid.rows.saturating_sub(1));
+            (terminal.cursor.col, row)
+        };
+        let x = col as f32 * cell_width + rect.x * screen_width as f32;
+        let y = row as f32 * cell_height + rect.y * screen_height as f32;
+
+        let area = (
+            PhysicalPosition::new(x.round() as u32, y.round() as u32),
+            PhysicalSize::new(cell_width.round() as u32, cell_height.round() as u32),
+        );
+        if self.ime_cursor_area != Some(area) {
+            self.ime_cursor_area = Some(area);
+            self.window.set_ime_cursor_area(area.0, area.1);
         }
     }
 
@@ -543,15 +2011,7 @@ impl WindowState {
         self.renderer.resize(width, height);
 
         // 各ペインをリサイズ
-        let rects = self.layout.calculate_rects(Rect::full());
-        for (pane_id, rect) in rects {
-            if let Some(pane) = self.panes.get_mut(&pane_id) {
-                let vp_width = rect.width * width as f32;
-                let vp_height = rect.height * height as f32;
-                let (cols, rows) = self.renderer.calculate_terminal_size_for_viewport(vp_width, vp_height);
-                pane.resize(cols, rows);
-            }
-        }
+        self.resize_all_panes();
     }
 
     /// マウス位置をターミナルセル座標に変換
@@ -574,6 +2034,61 @@ impl WindowState {
         (col, row)
     }
 
+    /// クリックした位置の `path:line:col` をエディタで、URL をブラウザで開く
+    /// 戻り値: 開いたか
+    fn open_location_at(&mut self, norm_x: f32, norm_y: f32) -> bool {
+        let (x, y) = self.mouse_pixel_pos;
+        let Some(pane_id) = self.tab().layout.pane_at(norm_x, norm_y, self.pane_area()) else { return false };
+        // タイトルバー上は対象外
+        let rects = self.terminal_rects();
+        let Some((pane_id, rect)) = rects.iter().find(|(id, rect)| *id == pane_id && norm_y >= rect.y) else {
+            return false;
+        };
+        let Some(pane) = self.panes.get(pane_id) else { return false };
+        let (col, row) = self.mouse_to_cell(x, y, rect);
+        let (location, link) = {
+            let terminal = pane.terminal.lock();
+            let rows = terminal.visible_cells();
+            let Some(cells) = rows.get(row) else { return false };
+            // 相対パスは OSC 7 で届いた作業ディレクトリから
+            (editor::location_at(cells, col, &terminal.cwd), hints::link_at(cells, col, &self.hint_rules))
+        };
+        // URL・リンクの規則の一致と、[open] にファイルを開くコマンドがあればファイルもそのコマンドで開く
+        let target = match (location, link) {
+            (Some(location), _) if config::current().open.file.is_some() => {
+                OpenTarget::File { path: location.path, line: Some(location.line), col: location.col }
+            }
+            (Some(location), _) => return self.open_in_editor(*pane_id, &location),
+            (None, Some(link)) => link,
+            (None, None) => return false,
+        };
+        log::info!("Open: {}", target.text());
+        hints::open(&target);
+        true
+    }
+
+    /// ファイルの位置をエディタで開く（新しいペインに縦分割して起動）
+    fn open_in_editor(&mut self, pane_id: PaneId, location: &FileLocation) -> bool {
+        let line = editor::command_line(&editor::command_template(), location, &editor::default_editor());
+        log::info!("エディタで開きます: {}", line);
+        self.tab_mut().focused_pane = pane_id;
+        if let Err(e) = self.split_horizontal(&PaneCommand::Run(line), None) {
+            log::error!("エディタの起動に失敗: {}", e);
+        }
+        true
+    }
+
+    /// クリックを数える（同じペインの同じセルを続けてクリックすると 2, 3、その次は 1 に戻る）
+    fn count_click(&mut self, pane_id: PaneId, cell: (usize, usize)) -> u32 {
+        let now = Instant::now();
+        let repeated = self.last_click.is_some_and(|(time, last_pane, last_cell)| {
+            now - time <= MULTI_CLICK_INTERVAL && last_pane == pane_id && last_cell == cell
+        });
+        self.click_count = if repeated && self.click_count < 3 { self.click_count + 1 } else { 1 };
+        self.last_click = Some((now, pane_id, cell));
+        self.click_count
+    }
+
     /// マウス移動を処理
     fn handle_cursor_moved(&mut self, x: f64, y: f64) {
         let (width, height) = self.renderer.screen_size();
@@ -584,14 +2099,37 @@ impl WindowState {
         let norm_y = (y as f32) / (height as f32);
         self.mouse_pos = (norm_x, norm_y);
 
+        // スクロールバーのつまみをドラッグ中
+        if let Some(drag) = self.scrollbars.drag {
+            self.drag_scrollbar(drag, norm_y);
+            return;
+        }
+
+        // スクロールバーに近づいたら表示する
+        if let Some((pane_id, _)) = self.scrollbar_at(x, norm_y) {
+            self.scrollbars.touch(pane_id, Instant::now());
+        }
+
         // テキスト選択ドラッグ中
         if self.selecting_text {
-            let rects = self.layout.calculate_rects(Rect::full());
-            if let Some((_, rect)) = rects.iter().find(|(id, _)| *id == self.focused_pane) {
+            let rects = self.terminal_rects();
+            if let Some((_, rect)) = rects.iter().find(|(id, _)| *id == self.focused_pane()) {
+                // ペインの上下の外に出たら、離れた距離に応じた速さで自動スクロール
+                let (_, cell_height) = self.renderer.cell_size();
+                let top = rect.y * height as f32;
+                let bottom = (rect.y + rect.height) * height as f32;
+                let y_px = y as f32;
+                self.selection_autoscroll = if y_px < top {
+                    1 + ((top - y_px) / cell_height) as isize
+                } else if y_px > bottom {
+                    -(1 + ((y_px - bottom) / cell_height) as isize)
+                } else {
+                    0
+                };
+
                 let (col, row) = self.mouse_to_cell(x, y, rect);
-                if let Some(pane) = self.panes.get(&self.focused_pane) {
-                    let mut terminal = pane.terminal.lock();
-                    terminal.selection.extend_to(col, row);
+                if let Some(pane) = self.panes.get(&self.focused_pane()) {
+                    pane.terminal.lock().extend_selection(col, row);
                 }
             }
             return;
@@ -600,12 +2138,14 @@ impl WindowState {
         // ドラッグ中なら境界線を移動
         if let Some(ref border) = self.dragging_border {
             let path = border.path().to_vec();
+            // 比率はペイン領域（タブバーの下）に対する位置
+            let area = self.pane_area();
             let new_ratio = if border.is_vertical() {
-                norm_x
+                (norm_x - area.x) / area.width
             } else {
-                norm_y
+                (norm_y - area.y) / area.height
             };
-            self.layout.update_ratio(&path, new_ratio);
+            self.tab_mut().layout.update_ratio(&path, new_ratio);
 
             // ペインをリサイズ
             self.resize_all_panes();
@@ -613,7 +2153,7 @@ impl WindowState {
         }
 
         // 境界線上ならカーソルを変更
-        if let Some(border) = self.layout.border_at(norm_x, norm_y, Rect::full(), BORDER_THRESHOLD) {
+        if let Some(border) = self.tab().layout.border_at(norm_x, norm_y, self.pane_area(), BORDER_THRESHOLD) {
             let cursor = if border.is_vertical() {
                 CursorIcon::ColResize
             } else {
@@ -625,53 +2165,154 @@ impl WindowState {
         }
     }
 
-    /// マウスボタンを処理
-    fn handle_mouse_input(&mut self, button: MouseButton, state: ElementState) {
-        if button != MouseButton::Left {
+    /// 選択ドラッグでペインの外にいる間、一定間隔でスクロールして選択を広げる
+    fn autoscroll_selection(&mut self) {
+        if !self.selecting_text || self.selection_autoscroll == 0 {
+            return;
+        }
+        let now = Instant::now();
+        if now - self.last_autoscroll < AUTOSCROLL_INTERVAL {
             return;
         }
+        self.last_autoscroll = now;
 
-        let (norm_x, norm_y) = self.mouse_pos;
         let (x, y) = self.mouse_pixel_pos;
+        let rects = self.terminal_rects();
+        let Some((_, rect)) = rects.iter().find(|(id, _)| *id == self.focused_pane()) else { return };
+        let (col, row) = self.mouse_to_cell(x, y, rect);
+        if let Some(pane) = self.panes.get(&self.focused_pane()) {
+            let mut terminal = pane.terminal.lock();
+            terminal.scroll_display(self.selection_autoscroll);
+            terminal.extend_selection(col, row);
+        }
+        self.needs_redraw = true;
+    }
+
+    /// 届いた行の数の表示の上なら最新の表示に戻す
+    /// 戻り値: 表示の上だったか
+    fn click_new_lines(&mut self, pane_id: PaneId, x: f64, y: f64) -> bool {
+        let rects = self.terminal_rects();
+        let Some((_, rect)) = rects.iter().find(|(id, _)| *id == pane_id) else { return false };
+        let (col, row) = self.mouse_to_cell(x, y, rect);
+        let Some(pane) = self.panes.get(&pane_id) else { return false };
+        let mut terminal = pane.terminal.lock();
+        let count = terminal.unseen_lines();
+        let (cols, rows) = (terminal.active_grid().cols, terminal.active_grid().rows);
+        let label = scrollbar::new_lines_label(count);
+        if count == 0 || row + 1 != rows || !scrollbar::new_lines_columns(cols, &label).contains(&col) {
+            return false;
+        }
+        terminal.scroll_to_bottom();
+        self.needs_redraw = true;
+        true
+    }
+
+    /// マウスの位置にあるスクロールバー（x: ピクセル座標、norm_y: 正規化座標）
+    fn scrollbar_at(&self, x: f64, norm_y: f32) -> Option<(PaneId, Rect)> {
+        let (width, _) = self.renderer.screen_size();
+        let x = x as f32;
+        self.terminal_rects().into_iter().find(|(_, rect)| {
+            let right = (rect.x + rect.width) * width as f32;
+            // 細いので少し広めに判定する
+            (right - scrollbar::WIDTH - 6.0..=right).contains(&x) && (rect.y..rect.y + rect.height).contains(&norm_y)
+        })
+    }
+
+    /// スクロールバーを押したらつまみのドラッグを始める（つまみ以外を押したらそこへ移動）
+    /// 戻り値: スクロールバーの上だったか
+    fn start_scrollbar_drag(&mut self, x: f64, norm_y: f32) -> bool {
+        let Some((pane_id, rect)) = self.scrollbar_at(x, norm_y) else { return false };
+        let Some(pane) = self.panes.get(&pane_id) else { return false };
+        let thumb = {
+            let terminal = pane.terminal.lock();
+            scrollbar::thumb(terminal.history_base(), terminal.active_grid().rows, terminal.display_offset)
+        };
+        let Some((top, height)) = thumb else { return false };
+
+        let y = (norm_y - rect.y) / rect.height;
+        let grab = if (top..=top + height).contains(&y) { y - top } else { height / 2.0 };
+        let drag = Drag { pane_id, grab };
+        self.scrollbars.drag = Some(drag);
+        self.drag_scrollbar(drag, norm_y);
+        true
+    }
+
+    /// つまみをマウスの位置に合わせてスクロール
+    fn drag_scrollbar(&mut self, drag: Drag, norm_y: f32) {
+        let rects = self.terminal_rects();
+        let Some((_, rect)) = rects.iter().find(|(id, _)| *id == drag.pane_id) else { return };
+        let Some(pane) = self.panes.get(&drag.pane_id) else { return };
+        let mut terminal = pane.terminal.lock();
+        let top = (norm_y - rect.y) / rect.height - drag.grab;
+        let offset = scrollbar::offset_at(terminal.history_base(), terminal.active_grid().rows, top);
+        let delta = offset as isize - terminal.display_offset as isize;
+        terminal.scroll_display(delta);
+        self.needs_redraw = true;
+    }
+
+    /// マウスボタンを処理
+    fn handle_mouse_input(&mut self, button: MouseButton, state: ElementState) -> WindowCommand {
+        let (norm_x, norm_y) = self.mouse_pos;
+        let (x, _) = self.mouse_pixel_pos;
 
         match state {
             ElementState::Pressed => {
-                // 境界線上ならドラッグ開始
-                if let Some(border) = self.layout.border_at(norm_x, norm_y, Rect::full(), BORDER_THRESHOLD) {
-                    self.dragging_border = Some(border);
-                    return;
+                // コピーモード・ヒントモード中にクリックしたら抜ける
+                self.exit_copy_mode();
+                if self.hint_mode.take().is_some() {
+                    self.needs_redraw = true;
                 }
 
-                // ペイン上ならフォーカス切り替えと選択開始
-                if let Some(pane_id) = self.layout.pane_at(norm_x, norm_y, Rect::full()) {
-                    if pane_id != self.focused_pane {
-                        // 前のペインの選択をクリア
-                        if let Some(prev_pane) = self.panes.get(&self.focused_pane) {
-                            prev_pane.terminal.lock().selection.clear();
-                        }
-                        self.focused_pane = pane_id;
-                        log::info!("クリックでフォーカス切り替え: {:?}", pane_id);
+                if button == MouseButton::Left {
+                    // タブバー上ならタブの切り替え・閉じる
+                    if self.tab_bar_visible() && norm_y < self.pane_area().y {
+                        self.handle_tab_bar_click(x);
+                        return WindowCommand::None;
                     }
 
-                    // テキスト選択を開始
-                    let rects = self.layout.calculate_rects(Rect::full());
-                    if let Some((_, rect)) = rects.iter().find(|(id, _)| *id == pane_id) {
-                        let (col, row) = self.mouse_to_cell(x, y, rect);
-                        if let Some(pane) = self.panes.get(&pane_id) {
-                            let mut terminal = pane.terminal.lock();
-                            terminal.selection.start_at(col, row);
-                        }
-                        self.selecting_text = true;
+                    // サイドバーのエクスプローラー上ならエントリを選択
+                    if self.click_explorer_sidebar(x, norm_y) {
+                        return WindowCommand::None;
+                    }
+
+                    // 表示中のスクロールバー上ならつまみのドラッグ開始
+                    if self.scrollbar_visible && self.start_scrollbar_drag(x, norm_y) {
+                        return WindowCommand::None;
+                    }
+
+                    // 境界線上ならドラッグ開始
+                    if let Some(border) =
+                        self.tab().layout.border_at(norm_x, norm_y, self.pane_area(), BORDER_THRESHOLD)
+                    {
+                        self.dragging_border = Some(border);
+                        return WindowCommand::None;
                     }
                 }
+
+                // ペインの上はマウスの割り当て（設定ファイルの [[mousebinds]]）
+                let mods = Mods::from_state(self.modifiers.state());
+                if let Some(action) = mousebinds::lookup(&self.mousebinds, mods, button) {
+                    return self.mouse_command(action, norm_x, norm_y);
+                }
             }
             ElementState::Released => {
-                // テキスト選択終了
+                // テキスト選択終了（ドラッグして選んだテキストはプライマリセレクションにも置く）
                 if self.selecting_text {
-                    if let Some(pane) = self.panes.get(&self.focused_pane) {
-                        pane.terminal.lock().selection.finish();
+                    let dragged = self.panes.get(&self.focused_pane()).is_some_and(|pane| {
+                        let mut terminal = pane.terminal.lock();
+                        terminal.selection.finish();
+                        terminal.selection.start != terminal.selection.end
+                    });
+                    if dragged {
+                        self.set_primary_selection();
                     }
                     self.selecting_text = false;
+                    self.selection_autoscroll = 0;
+                }
+
+                // スクロールバーのドラッグ終了
+                if self.scrollbars.drag.take().is_some() {
+                    self.needs_redraw = true;
                 }
 
                 // ドラッグ終了
@@ -681,6 +2322,99 @@ impl WindowState {
                 }
             }
         }
+        WindowCommand::None
+    }
+
+    /// マウスの割り当ての操作を実行する
+    fn mouse_command(&mut self, action: MouseAction, norm_x: f32, norm_y: f32) -> WindowCommand {
+        match action {
+            MouseAction::Bind(action) => return self.bound_command(action, None, None),
+            MouseAction::PastePrimary => self.paste_primary_selection(),
+            // ファイル:行 でなければ普通のクリック
+            MouseAction::OpenLocation if self.open_location_at(norm_x, norm_y) => {}
+            MouseAction::OpenLocation => self.click_pane(norm_x, norm_y, MouseAction::Select),
+            MouseAction::Select | MouseAction::SelectBlock | MouseAction::ExtendSelection => {
+                self.click_pane(norm_x, norm_y, action)
+            }
+        }
+        WindowCommand::None
+    }
+
+    /// ペインのクリック（フォーカスを移して選択を始める・広げる）
+    fn click_pane(&mut self, norm_x: f32, norm_y: f32, action: MouseAction) {
+        let (x, y) = self.mouse_pixel_pos;
+        // ペイン上ならフォーカス切り替えと選択開始（エクスプローラーからもフォーカスを戻す）
+        let Some(pane_id) = self.tab().layout.pane_at(norm_x, norm_y, self.pane_area()) else { return };
+        self.explorer_focused = false;
+        if pane_id != self.focused_pane() {
+            // 前のペインの選択をクリア
+            if let Some(prev_pane) = self.panes.get(&self.focused_pane()) {
+                prev_pane.terminal.lock().selection.clear();
+            }
+            self.tab_mut().focused_pane = pane_id;
+            log::info!("クリックでフォーカス切り替え: {:?}", pane_id);
+        }
+
+        // 届いた行の数の表示をクリックしたら最新の表示に戻る
+        if self.click_new_lines(pane_id, x, y) {
+            return;
+        }
+
+        // テキスト選択を開始（タイトルバー上ならフォーカスのみ）
+        // ダブルクリックで単語、トリプルクリックで行を選択
+        let rects = self.terminal_rects();
+        let Some((_, rect)) = rects.iter().find(|(id, rect)| *id == pane_id && norm_y >= rect.y) else { return };
+        let (col, row) = self.mouse_to_cell(x, y, rect);
+        let clicks = match action {
+            MouseAction::ExtendSelection => 1,
+            _ => self.count_click(pane_id, (col, row)),
+        };
+        if let Some(pane) = self.panes.get(&pane_id) {
+            let mut terminal = pane.terminal.lock();
+            match (clicks, action) {
+                (_, MouseAction::ExtendSelection) => {
+                    terminal.continue_selection(col, row);
+                    self.selecting_text = true;
+                }
+                (2, _) => terminal.select_word(col, row, &self.word_separators),
+                (3, _) => terminal.select_line(row),
+                _ => {
+                    terminal.start_selection(col, row, action == MouseAction::SelectBlock);
+                    self.selecting_text = true;
+                }
+            }
+        }
+        // ダブル・トリプルクリックの選択はその場で確定する
+        if !self.selecting_text && clicks > 1 {
+            self.set_primary_selection();
+        }
+        self.needs_redraw = true;
+    }
+
+    /// フォーカス中のペインの選択テキストをプライマリセレクションに置く（X11 / Wayland で中クリックすると貼り付けられる）
+    fn set_primary_selection(&self) {
+        let Some(pane) = self.panes.get(&self.focused_pane()) else { return };
+        // クリップボード操作の前にロックを解除
+        let text = pane.terminal.lock().get_selected_text();
+        if let Some(text) = text {
+            clipboard::set_primary(&text);
+        }
+    }
+
+    /// タブバーのクリックを処理（x: ピクセル座標）
+    fn handle_tab_bar_click(&mut self, x: f64) {
+        let (cell_width, _) = self.renderer.cell_size();
+        let (screen_cols, _) = self.renderer.calculate_terminal_size();
+        let col = (x as f32 / cell_width) as usize;
+
+        match tab::hit_test(self.tabs.len(), screen_cols as usize, col) {
+            Some(TabHit::Select(index)) => self.select_tab(index),
+            // タブバーはタブが2つ以上のときだけ表示されるので、ウィンドウは閉じない
+            Some(TabHit::Close(index)) => {
+                self.request_close(CloseTarget::Tab(index));
+            }
+            None => {}
+        }
     }
 
     /// マウスホイール/トラックパッドスクロールを処理
@@ -699,9 +2433,10 @@ impl WindowState {
         }
 
         // フォーカスされたペインにスクロールイベントを送信
-        if let Some(pane) = self.panes.get(&self.focused_pane) {
+        if let Some(pane) = self.panes.get(&self.focused_pane()) {
             let terminal = pane.terminal.lock();
             let mouse_tracking = terminal.mode.contains(terminal::TerminalMode::MOUSE_TRACKING);
+            let terminal_alt_screen = terminal.is_alt_screen();
             drop(terminal);
 
             let abs_lines = lines.unsigned_abs() as usize;
@@ -709,8 +2444,8 @@ impl WindowState {
             if mouse_tracking {
                 // マウストラッキング有効時: SGRマウスエスケープシーケンスを送信
                 let (x, y) = self.mouse_pixel_pos;
-                let rects = self.layout.calculate_rects(Rect::full());
-                let (col, row) = if let Some((_, rect)) = rects.iter().find(|(id, _)| *id == self.focused_pane) {
+                let rects = self.terminal_rects();
+                let (col, row) = if let Some((_, rect)) = rects.iter().find(|(id, _)| *id == self.focused_pane()) {
                     self.mouse_to_cell(x, y, rect)
                 } else {
                     (0, 0)
@@ -722,23 +2457,843 @@ impl WindowState {
                     let seq = format!("\x1b[<{};{};{}M", button, col + 1, row + 1);
                     let _ = pane.pty.write(seq.as_bytes());
                 }
-            } else {
-                // マウストラッキング無効時: 矢印キーを送信
+            } else if terminal_alt_screen {
+                // 代替スクリーン（less等）: 矢印キーを送信
                 let key = if lines > 0 { b"\x1b[A" } else { b"\x1b[B" }; // 上/下矢印
                 for _ in 0..abs_lines {
                     let _ = pane.pty.write(key);
                 }
+            } else {
+                // メイン画面: スクロールバックをスクロール
+                pane.terminal.lock().scroll_display(lines as isize);
+                self.needs_redraw = true;
             }
         }
     }
 
-    /// すべてのペインをリサイズ
-    fn resize_all_panes(&mut self) {
-        let (width, height) = self.renderer.screen_size();
-        let rects = self.layout.calculate_rects(Rect::full());
+    /// フォーカス中のペインのスクロールバックを1画面分スクロール
+    /// direction: 1 で過去へ、-1 で最新へ
+    fn scroll_display_page(&mut self, direction: isize) {
+        if let Some(pane) = self.panes.get(&self.focused_pane()) {
+            let mut terminal = pane.terminal.lock();
+            let page = terminal.active_grid().rows.saturating_sub(1).max(1) as isize;
+            terminal.scroll_display(direction * page);
+            self.needs_redraw = true;
+        }
+    }
 
-        for (pane_id, rect) in rects {
-            if let Some(pane) = self.panes.get_mut(&pane_id) {
+    /// フォーカス中のペインのスクロールバックのディスクへの書き出しを切り替える
+    /// （設定ファイルを保存し直すと設定の値に戻る）
+    fn toggle_scrollback_spill(&mut self) {
+        let Some(pane) = self.panes.get(&self.focused_pane()) else { return };
+        let mut terminal = pane.terminal.lock();
+        let mut limits = terminal.scrollback.limits();
+        limits.spill = !limits.spill;
+        terminal.set_scrollback_limits(limits);
+        log::info!("スクロールバックのディスクへの書き出し: {}", if terminal.scrollback.limits().spill { "on" } else { "off" });
+        self.needs_redraw = true;
+    }
+
+    /// フォーカス中のペインの出力の録画（asciicast）を始める・やめる
+    fn toggle_recording(&mut self) {
+        let Some(pane) = self.panes.get_mut(&self.focused_pane()) else { return };
+        if pane.is_recording() {
+            match pane.stop_recording() {
+                Ok(Some(path)) => {
+                    log::info!("録画を保存しました: {:?}", path);
+                    notification::notify("録画を保存しました", &path.display().to_string());
+                }
+                Ok(None) => {}
+                Err(e) => log::error!("録画の保存に失敗: {}", e),
+            }
+        } else {
+            let path = recording::timestamped_path(&screenshot::default_dir());
+            match pane.start_recording(&path) {
+                Ok(()) => log::info!("録画を始めました: {:?}", path),
+                Err(e) => log::error!("{:#}", e),
+            }
+        }
+        self.needs_redraw = true;
+    }
+
+    /// フォーカス中のペインのスクロールバックをファイルに保存（path がなければデスクトップにタイムスタンプ付きの名前）
+    fn save_scrollback(&mut self, path: Option<&str>, ansi: bool) {
+        let Some(pane) = self.panes.get(&self.focused_pane()) else { return };
+        let path = export::resolve_path(path, &screenshot::default_dir());
+        let result = export::save(&path, pane.terminal.lock().history_rows(), ansi);
+        match result {
+            Ok(()) => {
+                log::info!("スクロールバックを保存しました: {:?}", path);
+                notification::notify("スクロールバックを保存しました", &path.display().to_string());
+            }
+            Err(e) => log::error!("{:#}", e),
+        }
+    }
+
+    /// フォーカス中のペインの画面・スクロールバックを名前のパイプのコマンドに渡し、下に分割したペインで実行する
+    fn run_pipe(&mut self, name: &str) -> anyhow::Result<()> {
+        let pipe = self.pipes.iter().find(|pipe| pipe.name == name).with_context(|| format!("パイプが見つかりません: {}", name))?;
+        let origin = self.focused_pane();
+        let pane = self.panes.get(&origin).context("ペインがありません")?;
+        let lines = match pipe.input {
+            PipeInput::Screen => pane.terminal.lock().screen_text(),
+            PipeInput::Scrollback => pane.terminal.lock().history_text(),
+        };
+        let run = PipeRun::prepare(pipe, &pipe::input_text(&lines))?;
+        let pane_id = self.split_vertical(&PaneCommand::Run(run.line.clone()), None)?;
+        log::info!("パイプ {:?} を実行します: {:?} -> {:?}", name, origin, pane_id);
+        self.pipe_runs.insert(pane_id, (origin, run));
+        Ok(())
+    }
+
+    /// フォーカス中のペインで前（direction < 0）または次のプロンプトへ移動
+    fn jump_to_prompt(&mut self, direction: isize) {
+        let Some(pane) = self.panes.get(&self.focused_pane()) else { return };
+        let mut terminal = pane.terminal.lock();
+        let mut prompts = terminal.prompt_lines(self.prompt_pattern.as_ref());
+        // トリガーで印を付けた行にも止まる
+        prompts.extend(terminal.marked_lines());
+        prompts.sort_unstable();
+        prompts.dedup();
+        if terminal.jump_to_prompt(&prompts, direction) {
+            self.needs_redraw = true;
+        }
+    }
+
+    /// フォーカス中のペインの履歴でスクロールバック検索を開く
+    fn open_finder(&mut self) {
+        let Some(pane) = self.panes.get(&self.focused_pane()) else { return };
+        let lines = pane.terminal.lock().history_text();
+        self.finder.open(lines);
+        self.needs_redraw = true;
+    }
+
+    /// コマンドパレットを開く（組み込みの操作と、設定ファイル・プラグインで足したコマンド）
+    fn open_palette(&mut self) {
+        let hosts = ssh::hosts(&config::current());
+        self.palette = palette::entries(&self.palette_commands, &self.pipes, &hosts, self.plugins.palette_commands());
+        let names = self.palette.iter().map(|entry| entry.name.clone()).collect();
+        self.finder.open_as(FinderKind::Palette, names);
+        self.needs_redraw = true;
+    }
+
+    /// コピーの履歴を開く（候補は1行に縮めて見せ、選んだらテキスト全体をペーストする）
+    fn open_clipboard_history(&mut self) {
+        let history = clipboard::history();
+        if history.is_empty() {
+            log::info!("コピーの履歴がありません");
+            return;
+        }
+        let previews = history.iter().map(|text| clipboard::preview(text)).collect();
+        self.clipboard_history = history;
+        self.finder.open_as(FinderKind::ClipboardHistory, previews);
+        self.needs_redraw = true;
+    }
+
+    /// 最近の警告・エラーのログビューアを開く（新しいものが上）
+    fn open_log_viewer(&mut self) {
+        let lines = logging::recent().iter().map(logging::Entry::line).collect();
+        self.finder.open_as(FinderKind::Log, lines);
+        self.needs_redraw = true;
+    }
+
+    /// SSH の接続先だけのコマンドパレットを開く
+    fn open_ssh_launcher(&mut self) {
+        let hosts = ssh::hosts(&config::current());
+        if hosts.is_empty() {
+            log::info!("SSH の接続先がありません（プロファイルの ssh か ~/.ssh/config の Host を書いてください）");
+            return;
+        }
+        self.palette = palette::ssh_entries(&hosts).collect();
+        let names = self.palette.iter().map(|entry| entry.name.clone()).collect();
+        self.finder.open_as(FinderKind::Palette, names);
+        self.needs_redraw = true;
+    }
+
+    /// 動いているコンテナのコマンドパレットを開く
+    fn open_container_launcher(&mut self) {
+        let containers = match container::list(&config::current().containers) {
+            Ok(containers) => containers,
+            Err(e) => {
+                log::error!("コンテナの一覧を取得できません: {:#}", e);
+                return;
+            }
+        };
+        if containers.is_empty() {
+            log::info!("動いているコンテナがありません");
+            return;
+        }
+        self.palette = palette::container_entries(&containers);
+        let names = self.palette.iter().map(|entry| entry.name.clone()).collect();
+        self.finder.open_as(FinderKind::Palette, names);
+        self.needs_redraw = true;
+    }
+
+    /// WSL のディストリビューションのコマンドパレットを開く
+    fn open_wsl_launcher(&mut self) {
+        let distros = match wsl::distros() {
+            Ok(distros) => distros,
+            Err(e) => {
+                log::error!("WSL のディストリビューションを取得できません: {:#}", e);
+                return;
+            }
+        };
+        if distros.is_empty() {
+            log::info!("WSL のディストリビューションがありません");
+            return;
+        }
+        self.palette = palette::wsl_entries(&distros);
+        let names = self.palette.iter().map(|entry| entry.name.clone()).collect();
+        self.finder.open_as(FinderKind::Palette, names);
+        self.needs_redraw = true;
+    }
+
+    /// スクロールバック検索・コマンドパレット中のキー入力
+    /// Enter で選んだ行へ移動（パレットなら選んだコマンドを実行、コピーの履歴ならペースト、ログビューアならコピー）、Shift+Enter で行をコピー、Esc で閉じる
+    fn handle_finder_key(&mut self, event: &KeyEvent, ctrl: bool, shift: bool) -> WindowCommand {
+        self.needs_redraw = true;
+        match &event.logical_key {
+            Key::Named(NamedKey::Escape) => {
+                self.finder.close();
+                self.palette.clear();
+                self.clipboard_history.clear();
+            }
+            Key::Named(NamedKey::Enter) if self.finder.kind == FinderKind::Palette => {
+                let selected = self.finder.selected_line().map(|(index, _)| index);
+                self.finder.close();
+                let palette = std::mem::take(&mut self.palette);
+                if let Some(entry) = selected.and_then(|index| palette.into_iter().nth(index)) {
+                    log::info!("コマンドパレット: {}", entry.name);
+                    return WindowCommand::RunPalette(entry.command);
+                }
+            }
+            Key::Named(NamedKey::Enter) if self.finder.kind == FinderKind::Log => {
+                let selected = self.finder.selected_line().map(|(_, line)| line.to_string());
+                self.finder.close();
+                if let Some(line) = selected {
+                    clipboard::copy(&line);
+                }
+            }
+            Key::Named(NamedKey::Enter) if self.finder.kind == FinderKind::ClipboardHistory => {
+                let selected = self.finder.selected_line().map(|(index, _)| index);
+                self.finder.close();
+                let history = std::mem::take(&mut self.clipboard_history);
+                if let Some(text) = selected.and_then(|index| history.get(index)) {
+                    self.paste_text(text);
+                }
+            }
+            Key::Named(NamedKey::Enter) => {
+                let selected = self.finder.selected_line().map(|(index, line)| (index, line.to_string()));
+                self.finder.close();
+                let Some((index, line)) = selected else { return WindowCommand::None };
+                if shift {
+                    clipboard::copy(line.trim());
+                } else if let Some(pane) = self.panes.get(&self.focused_pane()) {
+                    pane.terminal.lock().scroll_to_history_line(index);
+                }
+            }
+            Key::Named(NamedKey::ArrowUp) => self.finder.move_selection(-1),
+            Key::Named(NamedKey::ArrowDown) => self.finder.move_selection(1),
+            Key::Character(c) if ctrl && c == "p" => self.finder.move_selection(-1),
+            Key::Character(c) if ctrl && c == "n" => self.finder.move_selection(1),
+            Key::Named(NamedKey::Backspace) => self.finder.pop(),
+            Key::Named(NamedKey::Space) => self.finder.push_str(" "),
+            Key::Character(c) if !ctrl && !self.modifiers.state().super_key() => self.finder.push_str(c),
+            _ => {}
+        }
+        WindowCommand::None
+    }
+
+    /// フォーカス中のペインでコピーモードに入る（カーソルはターミナルのカーソル位置から）
+    fn enter_copy_mode(&mut self) {
+        let pane_id = self.focused_pane();
+        let Some(pane) = self.panes.get(&pane_id) else { return };
+        let mut terminal = pane.terminal.lock();
+        let rows = terminal.active_grid().rows;
+        let row = (terminal.cursor.row + terminal.display_offset).min(rows.saturating_sub(1));
+        let col = if terminal.display_offset == 0 { terminal.cursor.col } else { 0 };
+        let cursor = Point::new(terminal.row_history_line(row), col);
+        terminal.selection.clear();
+        let mode = CopyMode::new(terminal.history_cells(), cursor)
+            .with_word_separators(&self.word_separators)
+            .with_origin(terminal.history_origin());
+        drop(terminal);
+
+        log::info!("コピーモードに入りました: {:?}", pane_id);
+        self.copy_mode = Some((pane_id, mode));
+        self.needs_redraw = true;
+    }
+
+    /// コピーモードを抜ける（選択を消して最下部に戻す）
+    fn exit_copy_mode(&mut self) {
+        let Some((pane_id, _)) = self.copy_mode.take() else { return };
+        if let Some(pane) = self.panes.get(&pane_id) {
+            let mut terminal = pane.terminal.lock();
+            terminal.selection.clear();
+            terminal.scroll_to_bottom();
+        }
+        self.needs_redraw = true;
+    }
+
+    /// コピーモード中のキー入力
+    /// h/j/k/l/w/b/e で移動、v / V で選択、y / Enter でコピーして抜ける、/ ? n N で検索、Esc / q で抜ける
+    fn handle_copy_mode_key(&mut self, event: &KeyEvent, ctrl: bool) {
+        self.needs_redraw = true;
+        let Some((pane_id, mode)) = self.copy_mode.as_mut() else { return };
+        let Some(pane) = self.panes.get(pane_id) else {
+            self.copy_mode = None;
+            return;
+        };
+        let page = pane.terminal.lock().active_grid().rows.saturating_sub(1).max(1) as isize;
+
+        // 検索の入力中
+        if mode.search_input.is_some() {
+            match &event.logical_key {
+                Key::Named(NamedKey::Escape) => mode.cancel_search(),
+                Key::Named(NamedKey::Enter) => mode.commit_search(),
+                Key::Named(NamedKey::Backspace) => mode.pop_search(),
+                Key::Named(NamedKey::Space) => mode.push_search(" "),
+                Key::Character(c) if !ctrl && event.text.is_some() => mode.push_search(c),
+                _ => {}
+            }
+            mode.reveal_cursor(&mut pane.terminal.lock());
+            return;
+        }
+
+        match &event.logical_key {
+            Key::Named(NamedKey::Escape) => {
+                if !mode.clear_selection() {
+                    self.exit_copy_mode();
+                }
+                return;
+            }
+            Key::Character(c) if c == "q" || (ctrl && c == "c") => {
+                self.exit_copy_mode();
+                return;
+            }
+            Key::Character(c) if c == "y" => {
+                let Some(text) = mode.selected_text() else { return };
+                clipboard::copy(&text);
+                self.exit_copy_mode();
+                return;
+            }
+            Key::Named(NamedKey::Enter) => {
+                if let Some(text) = mode.selected_text() {
+                    clipboard::copy(&text);
+                    self.exit_copy_mode();
+                    return;
+                }
+            }
+            Key::Character(c) if ctrl && c == "u" => mode.move_lines(-(page / 2).max(1)),
+            Key::Character(c) if ctrl && c == "d" => mode.move_lines((page / 2).max(1)),
+            Key::Named(NamedKey::PageUp) => mode.move_lines(-page),
+            Key::Named(NamedKey::PageDown) => mode.move_lines(page),
+            Key::Named(NamedKey::ArrowLeft) => mode.move_left(),
+            Key::Named(NamedKey::ArrowRight) => mode.move_right(),
+            Key::Named(NamedKey::ArrowUp) => mode.move_lines(-1),
+            Key::Named(NamedKey::ArrowDown) => mode.move_lines(1),
+            Key::Named(NamedKey::Home) => mode.line_start(),
+            Key::Named(NamedKey::End) => mode.line_end(),
+            Key::Character(c) if !ctrl => match c.as_str() {
+                "h" => mode.move_left(),
+                "l" => mode.move_right(),
+                "j" => mode.move_lines(1),
+                "k" => mode.move_lines(-1),
+                "w" => mode.word_forward(),
+                "b" => mode.word_backward(),
+                "e" => mode.word_end(),
+                "0" => mode.line_start(),
+                "^" => mode.first_non_blank(),
+                "$" => mode.line_end(),
+                "g" => mode.top(),
+                "G" => mode.bottom(),
+                "v" => mode.toggle_selection(SelectKind::Char),
+                "V" => mode.toggle_selection(SelectKind::Line),
+                "/" => mode.start_search(true),
+                "?" => mode.start_search(false),
+                "n" => mode.search_next(false),
+                "N" => mode.search_next(true),
+                _ => {}
+            },
+            _ => {}
+        }
+        mode.reveal_cursor(&mut pane.terminal.lock());
+    }
+
+    /// コピーモードの選択をターミナルの選択に映し、仮想カーソルの表示を作る
+    /// （対象のペインが閉じられていたらコピーモードを抜ける）
+    fn sync_copy_mode(&mut self, rects: &[(PaneId, Rect)]) -> Option<CopyCursor> {
+        let pane_id = self.copy_mode.as_ref()?.0;
+        let Some(pane) = self.panes.get(&pane_id) else {
+            self.copy_mode = None;
+            return None;
+        };
+        let (_, mode) = self.copy_mode.as_ref()?;
+        let mut terminal = pane.terminal.lock();
+        let cols = terminal.active_grid().cols;
+
+        // 選択の行は取り込んだときの history_cells の番号なので、捨てられた行の分をずらす
+        // （始点が捨てられていたら残っている先頭から）
+        let origin = terminal.history_origin();
+        terminal.selection.clear();
+        if let Some((start, end, kind)) = mode.selection() {
+            let (start_col, end_col) = match kind {
+                SelectKind::Char => (start.col, mode.char_end(end).saturating_sub(1)),
+                SelectKind::Line => (0, cols.saturating_sub(1)),
+            };
+            if let Some(end_line) = mode.live_line(end.line, origin) {
+                let start = mode.live_line(start.line, origin).map_or((0, 0), |line| (start_col, line));
+                terminal.selection.select(start, (end_col, end_line));
+            }
+        }
+
+        let cursor = mode.cursor();
+        let row = terminal.history_line_row(mode.live_line(cursor.line, origin)?)?;
+        let (_, rect) = rects.iter().find(|(id, _)| *id == pane_id)?;
+        Some(CopyCursor {
+            rect: *rect,
+            col: cursor.col,
+            row,
+            character: mode.char_at(cursor),
+            status: mode.status(),
+        })
+    }
+
+    /// フォーカス中のペインの表示中の画面でヒントモードに入る（一致がなければ何もしない）
+    fn enter_hint_mode(&mut self) {
+        let pane_id = self.focused_pane();
+        let Some(pane) = self.panes.get(&pane_id) else { return };
+        let rows = pane.terminal.lock().visible_cells();
+        let mode = HintMode::new(hints::scan(&rows, &self.hint_rules));
+        if mode.is_empty() {
+            log::info!("ヒントモード: 一致がありません");
+            return;
+        }
+        self.hint_mode = Some((pane_id, mode));
+        self.needs_redraw = true;
+    }
+
+    /// ヒントモード中のキー入力
+    /// ラベルを入力するとコピー（大文字で入力すると開く）、Backspace で1文字戻す、Esc で抜ける
+    fn handle_hint_key(&mut self, event: &KeyEvent) {
+        self.needs_redraw = true;
+        let Some((pane_id, mode)) = self.hint_mode.as_mut() else { return };
+        let selected = match &event.logical_key {
+            Key::Named(NamedKey::Escape) => {
+                self.hint_mode = None;
+                return;
+            }
+            Key::Named(NamedKey::Backspace) => {
+                mode.backspace();
+                return;
+            }
+            Key::Character(c) => {
+                let Some(ch) = c.chars().next().filter(char::is_ascii_alphabetic) else { return };
+                let action = if ch.is_ascii_uppercase() { HintAction::Open } else { HintAction::Copy };
+                match mode.input(ch) {
+                    Some(hint) => (hint.clone(), action),
+                    None => return,
+                }
+            }
+            _ => return,
+        };
+        let pane_id = *pane_id;
+        self.hint_mode = None;
+
+        let (hint, action) = selected;
+        let target = (action == HintAction::Open)
+            .then(|| {
+                let pane = self.panes.get(&pane_id)?;
+                let cwd = pane.pty.get_cwd().unwrap_or_else(|| pane.terminal.lock().cwd.clone());
+                hints::open_target(&hint, &cwd)
+            })
+            .flatten();
+        match target {
+            Some(target) => {
+                log::info!("Open: {}", target.text());
+                hints::open(&target);
+            }
+            None => {
+                clipboard::copy(&hint.text);
+            }
+        }
+    }
+
+    /// ヒントモードのラベルの表示（対象のペインが表示中の場合のみ）
+    fn hint_labels(&self, rects: &[(PaneId, Rect)]) -> Option<HintLabels> {
+        let (pane_id, mode) = self.hint_mode.as_ref()?;
+        let (_, rect) = rects.iter().find(|(id, _)| id == pane_id)?;
+        let typed = mode.typed.chars().count();
+        Some(HintLabels {
+            rect: *rect,
+            labels: mode.visible().map(|hint| (hint.col, hint.row, hint.label.clone(), typed)).collect(),
+        })
+    }
+
+    /// フォーカス中のペインの選択をクリップボードにコピー（選択がなければ Ctrl+C を送る）
+    fn copy_selection(&mut self) {
+        let Some(pane) = self.panes.get(&self.focused_pane()) else { return };
+        // クリップボード操作の前にロックを解除
+        let text = pane.terminal.lock().get_selected_text();
+        match text {
+            Some(text) => {
+                clipboard::copy(&text);
+            }
+            None => {
+                let _ = pane.pty.write(&[0x03]);
+            }
+        }
+    }
+
+    /// 選択テキストを色とスタイル付きの HTML・RTF と一緒にコピー（パレットの色はペインの配色で実際の色にする）
+    fn copy_selection_rich(&mut self) {
+        let Some(pane) = self.panes.get(&self.focused_pane()) else { return };
+        let (text, cells) = {
+            let terminal = pane.terminal.lock();
+            (terminal.get_selected_text(), terminal.get_selected_cells())
+        };
+        let (Some(text), Some(cells)) = (text, cells) else { return };
+        let palette = pane.snapshot.palette.as_deref().unwrap_or(self.renderer.palette());
+        clipboard::copy_rich(&text, &export::html(&cells, palette), &export::rtf(&cells, palette));
+    }
+
+    /// エクスプローラーのファイル操作を始める（n: 新規ファイル、N: 新規ディレクトリ、r: 名前の変更、d: 削除、
+    /// m: 印を付けたエントリの移動）
+    /// `/` なら名前での絞り込み、b ならブックマークの一覧、B なら選択中のディレクトリのピン留め、
+    /// s ならポップアップとサイドバーの切り替え
+    /// 戻り値: キーを使ったか
+    fn begin_explorer_prompt(&mut self, key: &str) -> bool {
+        self.needs_redraw = true;
+        let prompt = match key {
+            "/" => {
+                self.explorer.open_filter();
+                return true;
+            }
+            "b" => {
+                self.explorer.open_places();
+                return true;
+            }
+            "B" => {
+                self.explorer.toggle_pin();
+                return true;
+            }
+            "s" => {
+                self.explorer.toggle_layout();
+                return true;
+            }
+            "n" => ExplorerPrompt::NewFile(String::new()),
+            "N" => ExplorerPrompt::NewDirectory(String::new()),
+            "r" => match self.explorer.selected_entry() {
+                Some(entry) => ExplorerPrompt::Rename(entry.name.clone()),
+                None => return true,
+            },
+            "d" => ExplorerPrompt::ConfirmDelete,
+            "m" => {
+                self.explorer.begin_move();
+                return true;
+            }
+            _ => return false,
+        };
+        self.explorer.begin(prompt);
+        true
+    }
+
+    /// 選択中のエントリ（印を付けていればそれらすべて）のパス（シェル用にエスケープ）を y でコピー、p でフォーカス中のペインに入力
+    /// 入力したらペインにフォーカスを戻し、続けてコマンドを打てるようにする
+    /// 戻り値: キーを使ったか
+    fn use_explorer_path(&mut self, key: &str) -> bool {
+        if key != "y" && key != "p" {
+            return false;
+        }
+        let targets = self.explorer.batch_targets();
+        if targets.is_empty() {
+            return true;
+        }
+        let escaped: Vec<String> = targets.iter().map(|path| editor::shell_escape(&path.to_string_lossy())).collect();
+        let escaped = escaped.join(" ");
+        if key == "y" {
+            clipboard::copy(&escaped);
+        } else {
+            self.paste_text(&format!("{} ", escaped));
+            self.explorer_focused = false;
+        }
+        self.needs_redraw = true;
+        true
+    }
+
+    /// エクスプローラーの絞り込み中のキー入力
+    /// Enter で選んだエントリをツリーで選択（ファイルならエディタで開く）、Esc でやめる
+    fn handle_explorer_filter_key(&mut self, event: &KeyEvent, ctrl: bool) -> WindowCommand {
+        self.needs_redraw = true;
+        let Some(filter) = self.explorer.filter.as_mut() else { return WindowCommand::None };
+        match &event.logical_key {
+            Key::Named(NamedKey::Escape) => self.explorer.filter = None,
+            Key::Named(NamedKey::Enter) => {
+                let open = self.explorer.accept_filter().is_some_and(|entry| !entry.is_dir());
+                return if open { WindowCommand::ExplorerEnter } else { WindowCommand::None };
+            }
+            Key::Named(NamedKey::ArrowUp) => filter.move_selection(-1),
+            Key::Named(NamedKey::ArrowDown) => filter.move_selection(1),
+            Key::Character(c) if ctrl && c == "p" => filter.move_selection(-1),
+            Key::Character(c) if ctrl && c == "n" => filter.move_selection(1),
+            Key::Named(NamedKey::Backspace) => filter.pop(),
+            Key::Named(NamedKey::Space) => filter.push_str(" "),
+            Key::Character(c) if !ctrl => filter.push_str(c),
+            _ => {}
+        }
+        WindowCommand::None
+    }
+
+    /// エクスプローラーのブックマークの一覧のキー入力
+    /// Enter で選んだディレクトリをルートにする、x で一覧から外す、Esc / b で閉じる
+    fn handle_explorer_places_key(&mut self, event: &KeyEvent, ctrl: bool) {
+        self.needs_redraw = true;
+        match &event.logical_key {
+            Key::Named(NamedKey::Escape) => self.explorer.places = None,
+            Key::Named(NamedKey::Enter) => self.explorer.accept_place(),
+            Key::Named(NamedKey::ArrowUp) => self.explorer.move_place(-1),
+            Key::Named(NamedKey::ArrowDown) => self.explorer.move_place(1),
+            Key::Character(c) if ctrl && c == "p" => self.explorer.move_place(-1),
+            Key::Character(c) if ctrl && c == "n" => self.explorer.move_place(1),
+            Key::Named(NamedKey::Delete) | Key::Named(NamedKey::Backspace) => self.explorer.remove_place(),
+            Key::Character(c) if c == "x" => self.explorer.remove_place(),
+            Key::Character(c) if c == "b" => self.explorer.places = None,
+            _ => {}
+        }
+    }
+
+    /// エクスプローラーのファイル操作の入力中のキー入力
+    /// Enter で実行、Esc でやめる（削除の確認は y / Enter で削除、それ以外のキーでやめる）
+    fn handle_explorer_prompt_key(&mut self, event: &KeyEvent, ctrl: bool) {
+        self.needs_redraw = true;
+        if self.explorer.prompt.as_ref().is_some_and(ExplorerPrompt::is_confirmation) {
+            match &event.logical_key {
+                Key::Named(NamedKey::Enter) => self.explorer.confirm(),
+                Key::Character(c) if c == "y" => self.explorer.confirm(),
+                _ => self.explorer.cancel(),
+            }
+            return;
+        }
+        match &event.logical_key {
+            Key::Named(NamedKey::Escape) => self.explorer.cancel(),
+            Key::Named(NamedKey::Enter) => self.explorer.confirm(),
+            Key::Named(NamedKey::Backspace) => self.explorer.backspace(),
+            Key::Named(NamedKey::Space) => self.explorer.push_str(" "),
+            Key::Character(c) if !ctrl => self.explorer.push_str(c),
+            _ => {}
+        }
+    }
+
+    /// 表示中のエクスプローラーをフォーカス中のペインの作業ディレクトリ（OSC 7）に合わせる
+    fn follow_explorer_cwd(&mut self) {
+        let busy = self.explorer.prompt.is_some() || self.explorer.filter.is_some() || self.explorer.places.is_some();
+        if !self.explorer.visible || self.explorer_follow == ExplorerFollow::Off || busy {
+            return;
+        }
+        let Some(cwd) = self.panes.get(&self.focused_pane()).map(|pane| pane.terminal.lock().cwd.clone()) else {
+            return;
+        };
+        if self.explorer_cwd.as_ref() == Some(&cwd) || !cwd.is_dir() {
+            return;
+        }
+        log::info!("Explorer follows cwd: {:?}", cwd);
+        self.explorer.follow(self.explorer_follow, &cwd);
+        self.explorer_cwd = Some(cwd);
+        self.needs_redraw = true;
+    }
+
+    /// エクスプローラーの表示できる行数を合わせ、サイドバーを開閉したらペインの大きさを合わせる
+    fn sync_explorer_layout(&mut self) {
+        let docked = self.explorer.docked();
+        let rows = self.renderer.calculate_terminal_size().1 as usize;
+        let height = if docked {
+            // タブバーとステータスバーの間
+            rows.saturating_sub(self.tab_bar_visible() as usize + self.status_bar.is_some() as usize)
+        } else {
+            // 描画側のポップアップの高さと同じ
+            20.min(rows.saturating_sub(4))
+        };
+        self.explorer.set_visible_rows(height);
+        if docked != self.explorer_docked {
+            self.explorer_docked = docked;
+            self.resize_all_panes();
+            self.needs_redraw = true;
+        }
+    }
+
+    /// サイドバーのクリックでエクスプローラーにフォーカスし、クリックした行のエントリを選択する
+    /// 戻り値: サイドバー上だったか
+    fn click_explorer_sidebar(&mut self, x: f64, norm_y: f32) -> bool {
+        let (cell_width, _) = self.renderer.cell_size();
+        if !self.explorer.docked() || x >= (self.explorer.width as f32 * cell_width) as f64 {
+            return false;
+        }
+        let area = self.pane_area();
+        let (_, height) = self.renderer.screen_size();
+        let (_, cell_height) = self.renderer.cell_size();
+        // ヘッダーの1行の下から
+        let row = ((norm_y - area.y) * height as f32 / cell_height).floor();
+        if row >= 1.0 && self.explorer.places.is_none() && self.explorer.filter.is_none() {
+            self.explorer.select_row(row as usize - 1);
+        }
+        self.explorer_focused = true;
+        self.needs_redraw = true;
+        true
+    }
+
+    /// 各ペインのシェルが移動したディレクトリをエクスプローラーの最近のディレクトリに加える
+    /// （ペインを開いたときの作業ディレクトリは加えない）
+    fn record_recent_dirs(&mut self) {
+        let panes = &self.panes;
+        self.pane_cwds.retain(|id, _| panes.contains_key(id));
+        for (id, pane) in &self.panes {
+            let cwd = pane.terminal.lock().cwd.clone();
+            match self.pane_cwds.insert(*id, cwd.clone()) {
+                Some(previous) if previous != cwd && cwd.is_dir() => self.explorer.bookmarks.visit(&cwd),
+                _ => {}
+            }
+        }
+    }
+
+    /// エクスプローラーの一覧（ディレクトリが変わっていれば）と、選択中のファイルのプレビュー・メタデータを更新
+    /// 続きを待っているキー列を取り出す（時間切れなら空）
+    fn take_pending_keys(&mut self) -> Vec<KeyStroke> {
+        match self.pending_keys.take() {
+            Some((keys, at)) if at.elapsed() < self.key_timeout => keys,
+            _ => Vec::new(),
+        }
+    }
+
+    /// 時間切れになったキー列を取り消す
+    fn expire_pending_keys(&mut self) {
+        if self.pending_keys.as_ref().is_some_and(|(_, at)| at.elapsed() >= self.key_timeout) {
+            self.pending_keys = None;
+            self.needs_redraw = true;
+        }
+    }
+
+    fn refresh_explorer(&mut self) {
+        if self.explorer.refresh_from_disk() | self.explorer.refresh_selection() {
+            self.needs_redraw = true;
+        }
+    }
+
+    /// エクスプローラーを開く/閉じる（開くときはシェルの作業ディレクトリを表示）
+    fn toggle_explorer(&mut self) {
+        // 表示する前に、シェルの現在の作業ディレクトリを取得
+        if !self.explorer.visible {
+            if let Some(pane) = self.panes.get(&self.focused_pane()) {
+                // PTYからシェルのcwdを直接取得（lsof使用）
+                if let Some(cwd) = pane.pty.get_cwd() {
+                    if cwd.exists() {
+                        self.explorer.set_root(cwd.clone());
+                        log::info!("Explorer root set to shell cwd: {:?}", cwd);
+                    }
+                } else {
+                    // フォールバック: ターミナルのcwd（OSC 7から）
+                    let terminal = pane.terminal.lock();
+                    let cwd = terminal.cwd.clone();
+                    drop(terminal);
+                    if cwd.exists() {
+                        self.explorer.set_root(cwd.clone());
+                        log::info!("Explorer root set to terminal cwd: {:?}", cwd);
+                    }
+                }
+            }
+        }
+        self.explorer.toggle();
+        self.explorer_focused = self.explorer.visible;
+        self.explorer_cwd = self.panes.get(&self.focused_pane()).map(|pane| pane.terminal.lock().cwd.clone());
+        log::info!("Explorer toggled: visible={}, entries={}", self.explorer.visible, self.explorer.entries.len());
+    }
+
+    /// フォーカス中のペインの最後のコマンドの出力を選択してコピー（OSC 133 のマークが必要）
+    fn copy_last_output(&mut self) {
+        let Some(pane) = self.panes.get(&self.focused_pane()) else { return };
+        let text = pane.terminal.lock().select_last_output();
+        match text {
+            Some(text) => {
+                clipboard::copy(&text);
+            }
+            None => log::info!("コピーできるコマンドの出力がありません"),
+        }
+        self.needs_redraw = true;
+    }
+
+    /// フォーカス中のペインにテキストをペースト（ブラケットペーストモードなら囲んで送る）
+    ///
+    /// ブラケットペーストに対応していないシェルに改行を含むテキストを送ると
+    /// その場で実行されてしまうので、先に確認ダイアログで中身を見せる
+    fn paste_text(&mut self, text: &str) {
+        let pane_id = self.focused_pane();
+        let Some(pane) = self.panes.get(&pane_id) else { return };
+        let bracketed = pane.terminal.lock().mode.contains(terminal::TerminalMode::BRACKETED_PASTE);
+        if !bracketed && text.contains(['\n', '\r']) {
+            log::info!("複数行のペーストを確認します: {} bytes", text.len());
+            self.paste_confirm = Some(PasteConfirm { pane_id, text: text.to_string() });
+            self.needs_redraw = true;
+            return;
+        }
+        self.write_paste(pane_id, text);
+    }
+
+    /// ドロップされたファイルのパスをフォーカス中のペインに入力（複数ならファイルごとに届く）
+    ///
+    /// フォルダは設定により、そのフォルダに cd する
+    fn handle_dropped_file(&mut self, path: &std::path::Path) {
+        log::info!("ファイルがドロップされました: {:?}", path);
+        let escaped = editor::shell_escape(&path.to_string_lossy());
+        if path.is_dir() && self.drop_folder == DropFolderAction::Cd {
+            let Some(pane) = self.panes.get(&self.focused_pane()) else { return };
+            Self::reset_display_offset(pane);
+            let _ = pane.pty.write(format!("cd {}\n", escaped).as_bytes());
+            return;
+        }
+        self.paste_text(&format!("{} ", escaped));
+    }
+
+    /// ペーストの確認への応答
+    fn answer_paste(&mut self, accept: bool) {
+        self.needs_redraw = true;
+        match self.paste_confirm.take() {
+            Some(confirm) if accept => self.write_paste(confirm.pane_id, &confirm.text),
+            _ => {}
+        }
+    }
+
+    /// ペインの PTY にペーストするテキストを書き込む
+    fn write_paste(&mut self, pane_id: PaneId, text: &str) {
+        let Some(pane) = self.panes.get(&pane_id) else { return };
+        Self::reset_display_offset(pane);
+        let bytes = pane.terminal.lock().paste_bytes(text);
+        let _ = pane.pty.write(&bytes);
+    }
+
+    /// マウスの下のペインにフォーカスして、プライマリセレクションをペースト（中クリック）
+    fn paste_primary_selection(&mut self) {
+        let (norm_x, norm_y) = self.mouse_pos;
+        let Some(pane_id) = self.tab().layout.pane_at(norm_x, norm_y, self.pane_area()) else { return };
+        let Some(text) = clipboard::primary() else { return };
+        self.tab_mut().focused_pane = pane_id;
+        self.paste_text(&text);
+        self.needs_redraw = true;
+    }
+
+    /// スクロールバック表示中なら最下部に戻す
+    fn reset_display_offset(pane: &Pane) {
+        let mut terminal = pane.terminal.lock();
+        if terminal.display_offset > 0 {
+            terminal.scroll_to_bottom();
+        }
+    }
+
+    /// すべてのペインをリサイズ（非表示のタブも含む）
+    fn resize_all_panes(&mut self) {
+        let (width, height) = self.renderer.screen_size();
+        let rects: Vec<(PaneId, Rect)> = self.tabs.iter().flat_map(|tab| self.terminal_rects_of(tab)).collect();
+
+        for (pane_id, rect) in rects {
+            if let Some(pane) = self.panes.get_mut(&pane_id) {
                 let vp_width = rect.width * width as f32;
                 let vp_height = rect.height * height as f32;
                 let (cols, rows) = self.renderer.calculate_terminal_size_for_viewport(vp_width, vp_height);
@@ -746,18 +3301,108 @@ impl WindowState {
             }
         }
     }
+
+    /// プロファイルで決めたウィンドウのフォントの大きさ
+    fn profile_font_size(config: &Config, profile: Option<&str>) -> Option<f32> {
+        config.profiles.get(profile?)?.font_size
+    }
+
+    /// クライアント側で描くウィンドウの枠（Wayland）のライト・ダークを背景色に合わせる
+    fn sync_frame_theme(&self) {
+        if self.wayland {
+            let dark = self.renderer.palette().background.is_dark();
+            self.window.set_theme(Some(if dark { Theme::Dark } else { Theme::Light }));
+        }
+    }
+
+    /// ウィンドウの拡大率が変わったら、フォントをその大きさで読み込み直す（Wayland のみ）
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        if !self.wayland {
+            return;
+        }
+        match self.renderer.set_scale(scale_factor as f32) {
+            Ok(true) => self.resize_all_panes(),
+            Ok(false) => {}
+            Err(e) => log::error!("拡大率に合わせたフォントを読み込めません: {}", e),
+        }
+        self.update_ime_cursor_area();
+        self.needs_redraw = true;
+        self.window.request_redraw();
+    }
+
+    /// 読み直した設定を反映する（シェルは次に起動するペインから）
+    fn apply_config(&mut self, config: &Config) {
+        self.renderer.set_palette(config.active_palette().clone());
+        self.sync_frame_theme();
+        self.window.set_decorations(Decorations::from_env() == Decorations::Full);
+        self.keybinds = keybinds::with_defaults(&config.bindings);
+        self.palette_commands = config.palette_commands.clone();
+        self.pipes = config.pipe_commands.clone();
+        self.mousebinds = mousebinds::with_defaults(&config.mouse_bindings);
+        self.pending_keys = None;
+        self.key_timeout = config.leader.timeout();
+        self.which_key = config.leader.which_key();
+        self.keyboard = config.keyboard.clone();
+        #[cfg(target_os = "macos")]
+        self.window.set_option_as_alt(option_as_alt(&config.keyboard));
+        self.word_separators = terminal::word_separators();
+        self.prompt_pattern = terminal::prompt_pattern();
+        self.explorer_follow = ExplorerFollow::from_env();
+        self.explorer.layout = ExplorerLayout::from_env();
+        self.hint_rules = hints::rules_from_env();
+        self.triggers = triggers::triggers_from_env();
+        self.activity = IdleTracker::new(Instant::now(), idle::default_timeout());
+        self.pane_titles = PaneTitleMode::from_env();
+        self.status_bar = Plugins::status_bar(&self.plugins);
+        self.notify_after = notification::long_command_threshold();
+        self.silence_after = pane::silence_threshold();
+        self.on_exit = ExitAction::from_env();
+        self.drop_folder = DropFolderAction::from_env();
+        for pane in self.panes.values_mut() {
+            pane.refresh_scrollback(config);
+            pane.refresh_palette(config);
+            pane.refresh_label(config);
+        }
+        self.renderer.set_cursor_style(config.cursor_style);
+        // フォントが変わったらセルサイズに合わせてペインの行数・列数を変える
+        if let Err(e) = self.renderer.set_font_size(Self::profile_font_size(config, self.profile.as_deref())) {
+            log::error!("フォントの大きさを変えられません: {}", e);
+        }
+        self.resize_all_panes();
+        self.needs_redraw = true;
+        self.window.request_redraw();
+    }
 }
 
 /// ウィンドウコマンド（キー入力の結果）
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum WindowCommand {
     None,
-    NewWindow,
+    /// 新しいウィンドウ（最初のペインで起動するコマンド）
+    NewWindow(PaneCommand),
+    DetachPane,
+    MergePane,
+    /// 新しいタブ（起動するコマンド）
+    NewTab(PaneCommand),
+    NextTab,
+    PrevTab,
+    SelectTab(usize),
     ClosePane,
-    SplitHorizontal,
-    SplitVertical,
+    /// 閉じる前の確認への応答（true で閉じる）
+    AnswerClose(bool),
+    /// クラッシュしたセッションを戻すかの確認への応答（true で戻す）
+    AnswerRestore(bool),
+    /// 縦分割（新しいペインで起動するコマンド）
+    SplitHorizontal(PaneCommand),
+    /// 横分割（新しいペインで起動するコマンド）
+    SplitVertical(PaneCommand),
     FocusNextPane,
     FocusPrevPane,
+    SwapNextPane,
+    SwapPrevPane,
+    BalancePanes,
+    BalanceLevel,
+    ApplyLayout(LayoutPreset),
     Copy,
     Paste,
     ToggleExplorer,
@@ -765,11 +3410,38 @@ enum WindowCommand {
     ExplorerDown,
     ExplorerEnter,
     ExplorerGo,
+    ScreenshotPane,
+    ScreenshotWindow,
+    TogglePerfHud,
+    /// フォーカス中のペインの監視を切り替え
+    ToggleMonitor(MonitorKind),
+    /// フォーカス中のペインのプロセスを終了させて起動し直す
+    RespawnPane,
+    /// フルスクリーンを切り替える
+    ToggleFullscreen,
+    /// スクロールバック検索を開く
+    OpenFinder,
+    /// コマンドパレットを開く
+    OpenPalette,
+    /// コピーの履歴を開く
+    OpenClipboardHistory,
+    /// ログビューアを開く
+    OpenLogViewer,
+    /// コマンドパレットで選んだコマンドを実行
+    RunPalette(PaletteCommand),
+    /// フォーカス中のペインでコピーモードに入る
+    EnterCopyMode,
+    /// 画面の URL・パスなどにラベルを付けるヒントモードに入る
+    EnterHintMode,
+    /// 最後のコマンドの出力をコピー
+    CopyLastOutput,
+    /// 次のテーマに切り替える（すべてのウィンドウ）
+    NextTheme,
 }
 
 impl App {
     /// 新しいアプリケーションを作成
-    fn new() -> Self {
+    fn new(args: &cli::Args, proxy: EventLoopProxy<UserEvent>) -> Self {
         // wgpu インスタンスを作成
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
             backends: wgpu::Backends::all(),
@@ -780,92 +3452,908 @@ impl App {
             windows: HashMap::new(),
             instance,
             adapter: None,
+            render_context: None,
+            focused_window: None,
+            previous_window: None,
+            startup_mode: StartupMode::from_env(),
+            launch: Some((args.launch(), args.cwd())),
+            title: args.title.clone().unwrap_or_else(|| "UmiTerm".to_string()),
+            class: args.class.clone(),
+            plugins: Rc::new(Plugins::load()),
+            crashed: crash::take_session(),
+            last_tracked: Instant::now(),
+            proxy,
             should_exit: false,
         }
     }
 
-    /// 新しいウィンドウを作成
-    fn create_window(&mut self, event_loop: &ActiveEventLoop) -> Result<WindowId> {
-        // ウィンドウを作成
-        let window_attrs = Window::default_attributes()
-            .with_title("UmiTerm")
-            .with_inner_size(winit::dpi::LogicalSize::new(INITIAL_WIDTH, INITIAL_HEIGHT));
-
-        let window = Arc::new(event_loop.create_window(window_attrs)?);
-        let window_id = window.id();
-        let size = window.inner_size();
-
-        // サーフェスを作成
-        let surface: wgpu::Surface<'static> = unsafe {
-            std::mem::transmute(self.instance.create_surface(Arc::clone(&window))?)
+    /// 起動時のウィンドウを作成
+    /// コマンドライン引数でコマンドか作業ディレクトリを指定されていればそれを起動し、
+    /// なければセッションデーモン上のデタッチ中のセッションを、それぞれタブとしてアタッチし直す
+    fn create_initial_window(&mut self, event_loop: &ActiveEventLoop) -> Result<WindowId> {
+        let (command, cwd) = self.launch.take().unwrap_or_default();
+        if command != PaneCommand::Shell || cwd.is_some() {
+            return self.create_window_with(event_loop, &mut None, &command, cwd.as_deref());
+        }
+
+        #[cfg(unix)]
+        if session::enabled() {
+            // 大きさはウィンドウに合わせて後でリサイズされる
+            let (cols, rows) = (80, 24);
+            let mut panes = session::detached_sessions().into_iter().filter_map(|id| {
+                Pane::attach(id, cols, rows)
+                    .inspect_err(|e| log::warn!("セッション {} にアタッチできません: {}", id, e))
+                    .ok()
+            });
+            if let Some(first) = panes.next() {
+                let window_id = self.create_window_with(event_loop, &mut Some(first), &PaneCommand::Shell, None)?;
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    for pane in panes {
+                        state.add_tab(pane);
+                    }
+                }
+                return Ok(window_id);
+            }
+        }
+        self.create_window(event_loop, &PaneCommand::Shell)
+    }
+
+    /// 新しいウィンドウを作成（最初のペインで command を起動する）
+    fn create_window(&mut self, event_loop: &ActiveEventLoop, command: &PaneCommand) -> Result<WindowId> {
+        self.create_window_with(event_loop, &mut None, command, None)
+    }
+
+    /// 新しいウィンドウを作成（pane があればそのペインを移して使い、なければ command を cwd で起動する）
+    ///
+    /// ペインはウィンドウの作成に成功したときだけ取り出すので、
+    /// 失敗した場合は呼び出し側で元のウィンドウに戻せる
+    fn create_window_with(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        pane: &mut Option<Pane>,
+        command: &PaneCommand,
+        cwd: Option<&Path>,
+    ) -> Result<WindowId> {
+        // ウィンドウを作成
+        let window_attrs = Window::default_attributes()
+            .with_title(self.title.as_str())
+            .with_inner_size(winit::dpi::LogicalSize::new(INITIAL_WIDTH, INITIAL_HEIGHT))
+            .with_maximized(self.startup_mode == StartupMode::Maximized)
+            .with_fullscreen(
+                (self.startup_mode == StartupMode::Fullscreen)
+                    .then(|| FullscreenMode::from_env().fullscreen(event_loop.primary_monitor())),
+            )
+            .with_decorations(Decorations::from_env() == Decorations::Full)
+            // スクリーンリーダーのアダプターは表示する前に作る
+            .with_visible(false);
+        // デスクトップのウィンドウのまとめ方・ルールに使うクラス（X11 の WM_CLASS、Wayland の app_id）
+        #[cfg(all(unix, not(target_os = "macos")))]
+        let window_attrs = match &self.class {
+            Some(class) => WindowAttributesExtWayland::with_name(window_attrs, class, class),
+            None => window_attrs,
+        };
+        // Meta にする Option キーでは macOS の文字の合成をしない
+        #[cfg(target_os = "macos")]
+        let window_attrs = window_attrs.with_option_as_alt(option_as_alt(&config::current().keyboard));
+
+        let window = Arc::new(event_loop.create_window(window_attrs)?);
+        let window_id = window.id();
+        let accessibility = Accessibility::new(event_loop, &window, self.proxy.clone());
+        window.set_visible(true);
+        let size = window.inner_size();
+        // 外観に合わせた色で描けるよう、レンダラーより先に OS の外観を調べる
+        if let Some(os_theme) = window.theme() {
+            self.set_appearance(os_theme.into());
+        }
+
+        // サーフェスを作成
+        let surface: wgpu::Surface<'static> = unsafe {
+            std::mem::transmute(self.instance.create_surface(Arc::clone(&window))?)
+        };
+
+        // アダプターを取得（初回のみ）
+        if self.adapter.is_none() {
+            self.adapter = Some(pollster::block_on(self.instance.request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface: Some(&surface),
+                force_fallback_adapter: false,
+            }))?);
+        }
+
+        let adapter = self.adapter.as_ref().context("GPUアダプターが見つかりません")?;
+
+        // 描画リソースを作成（初回のみ、以降のウィンドウはフォントとアトラスを共有）
+        if self.render_context.is_none() {
+            self.render_context = Some(pollster::block_on(RenderContext::new(&surface, adapter))?);
+        }
+        let context = Arc::clone(self.render_context.as_ref().context("描画リソースがありません")?);
+
+        // レンダラーを作成（プロファイルにフォントの大きさがあればこのウィンドウだけ変える）
+        // Wayland ではウィンドウの拡大率（125% などの小数も）を掛けた大きさでラスタライズする
+        let mut renderer = Renderer::new(context, surface, size.width, size.height, adapter)?;
+        let wayland = is_wayland(event_loop);
+        if wayland {
+            renderer.set_scale(window.scale_factor() as f32)?;
+        }
+        let profile = command.profile().map(String::from);
+        renderer.set_font_size(WindowState::profile_font_size(&config::current(), profile.as_deref()))?;
+
+        // ターミナルサイズを計算
+        let (cols, rows) = renderer.calculate_terminal_size();
+
+        // 初期ペインを作成（移ってきたペインはウィンドウの大きさに合わせる）
+        let initial_pane = match pane.take() {
+            Some(mut pane) => {
+                pane.resize(cols, rows);
+                pane
+            }
+            None => {
+                // バナーはシェルのときだけ（参照テストの記録には PTY の出力だけを残す）
+                let mut pane = Pane::with_cwd(cols, rows, command, cwd)?;
+                if *command == PaneCommand::Shell && !pane.is_ref_testing() {
+                    WindowState::show_startup_banner(&mut pane);
+                }
+                pane
+            }
+        };
+        let initial_pane_id = initial_pane.id;
+
+        // ペインを登録
+        let mut panes = std::collections::HashMap::new();
+        panes.insert(initial_pane_id, initial_pane);
+
+        // IME（日本語入力）を有効化
+        window.set_ime_allowed(true);
+
+        // ファイルエクスプローラーを初期化（ホームディレクトリ）
+        let home = std::env::var("HOME")
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|_| std::path::PathBuf::from("/"));
+        let explorer = Explorer::new(home);
+
+        // WindowStateを作成
+        let mut state = WindowState {
+            window,
+            renderer,
+            panes,
+            tabs: vec![Tab::new(initial_pane_id)],
+            active_tab: 0,
+            last_frame: Instant::now(),
+            ime_active: false,
+            ime_cursor_area: None,
+            modifiers: Modifiers::default(),
+            mouse_pos: (0.0, 0.0),
+            mouse_pixel_pos: (0.0, 0.0),
+            dragging_border: None,
+            selecting_text: false,
+            last_click: None,
+            click_count: 0,
+            selection_autoscroll: 0,
+            last_autoscroll: Instant::now(),
+            scrollbars: Scrollbars::default(),
+            scrollbar_visible: false,
+            word_separators: terminal::word_separators(),
+            prompt_pattern: terminal::prompt_pattern(),
+            explorer,
+            explorer_focused: false,
+            explorer_follow: ExplorerFollow::from_env(),
+            explorer_cwd: None,
+            explorer_docked: false,
+            pane_cwds: std::collections::HashMap::new(),
+            finder: Finder::default(),
+            palette: Vec::new(),
+            clipboard_history: Vec::new(),
+            palette_commands: config::current().palette_commands.clone(),
+            pipes: config::current().pipe_commands.clone(),
+            pipe_runs: std::collections::HashMap::new(),
+            copy_mode: None,
+            hint_mode: None,
+            hint_rules: hints::rules_from_env(),
+            triggers: triggers::triggers_from_env(),
+            plugins: self.plugins.clone(),
+            perf: PerfStats::new(),
+            perf_visible: false,
+            needs_redraw: false,
+            activity: IdleTracker::new(Instant::now(), idle::default_timeout()),
+            pane_titles: PaneTitleMode::from_env(),
+            status_bar: Plugins::status_bar(&self.plugins),
+            close_confirm: None,
+            paste_confirm: None,
+            restore_confirm: None,
+            window_focused: true,
+            notify_after: notification::long_command_threshold(),
+            silence_after: pane::silence_threshold(),
+            on_exit: ExitAction::from_env(),
+            drop_folder: DropFolderAction::from_env(),
+            keybinds: keybinds::with_defaults(&config::current().bindings),
+            mousebinds: mousebinds::with_defaults(&config::current().mouse_bindings),
+            pending_keys: None,
+            key_timeout: config::current().leader.timeout(),
+            which_key: config::current().leader.which_key(),
+            keyboard: config::current().keyboard.clone(),
+            accessibility,
+            profile,
+            wayland,
+        };
+        state.sync_frame_theme();
+        // タイトルバーの分だけペインを縮める
+        state.resize_all_panes();
+
+        // ウィンドウを登録
+        self.windows.insert(window_id, state);
+
+        log::info!("新しいウィンドウを作成しました: {:?}", window_id);
+
+        Ok(window_id)
+    }
+
+    /// ウィンドウコマンド（キー入力・プラグインの操作の結果）を処理
+    fn run_window_command(&mut self, event_loop: &ActiveEventLoop, window_id: WindowId, command: WindowCommand) {
+        match command {
+            WindowCommand::NewWindow(launch) => {
+                if let Err(e) = self.create_window(event_loop, &launch) {
+                    log::error!("新規ウィンドウの作成に失敗: {}", e);
+                }
+            }
+            WindowCommand::DetachPane => {
+                self.detach_pane(event_loop, window_id);
+            }
+            WindowCommand::MergePane => {
+                self.merge_pane(window_id);
+            }
+            WindowCommand::NewTab(launch) => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    if let Err(e) = state.new_tab(&launch) {
+                        log::error!("新規タブの作成に失敗: {}", e);
+                    }
+                }
+            }
+            WindowCommand::NextTab => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.select_next_tab();
+                }
+            }
+            WindowCommand::PrevTab => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.select_prev_tab();
+                }
+            }
+            WindowCommand::SelectTab(index) => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.select_tab(index);
+                }
+            }
+            WindowCommand::ClosePane => {
+                // ペインを閉じる（タブの最後のペインならタブを、最後のタブならウィンドウを閉じる）
+                // 実行中のコマンドがあれば確認ダイアログを出す
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    if state.request_close(CloseTarget::Pane) {
+                        self.close_window(window_id);
+                    }
+                }
+            }
+            WindowCommand::AnswerClose(accept) => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    if state.answer_close(accept) {
+                        self.close_window(window_id);
+                    }
+                }
+            }
+            WindowCommand::AnswerRestore(accept) => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.restore_confirm = None;
+                    state.needs_redraw = true;
+                }
+                match self.crashed.take() {
+                    Some(session) if accept => self.restore_session(event_loop, window_id, session),
+                    _ => log::info!("前回のセッションを捨てました"),
+                }
+            }
+            WindowCommand::SplitHorizontal(pane_command) => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    if let Err(e) = state.split_horizontal(&pane_command, None) {
+                        log::error!("縦分割に失敗: {}", e);
+                    }
+                }
+            }
+            WindowCommand::SplitVertical(pane_command) => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    if let Err(e) = state.split_vertical(&pane_command, None) {
+                        log::error!("横分割に失敗: {}", e);
+                    }
+                }
+            }
+            WindowCommand::FocusNextPane => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.focus_next_pane();
+                }
+            }
+            WindowCommand::FocusPrevPane => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.focus_prev_pane();
+                }
+            }
+            WindowCommand::SwapNextPane | WindowCommand::SwapPrevPane => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.swap_pane(command == WindowCommand::SwapNextPane);
+                }
+            }
+            WindowCommand::BalancePanes | WindowCommand::BalanceLevel => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.balance_panes(command == WindowCommand::BalanceLevel);
+                }
+            }
+            WindowCommand::ApplyLayout(preset) => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.apply_layout(preset);
+                }
+            }
+            WindowCommand::Copy => {
+                // 選択テキストをクリップボードにコピー
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.copy_selection();
+                }
+            }
+            WindowCommand::Paste => {
+                // クリップボードからペースト
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    if let Some(text) = clipboard::paste() {
+                        state.paste_text(&text);
+                    }
+                }
+            }
+            WindowCommand::ToggleExplorer => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    // 表示中でペインにフォーカスがあれば、閉じずにエクスプローラーにフォーカスを移す
+                    if state.explorer.visible && !state.explorer_focused {
+                        state.explorer_focused = true;
+                    } else {
+                        state.toggle_explorer();
+                    }
+                    state.window.request_redraw();
+                }
+            }
+            WindowCommand::ExplorerUp => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.explorer.move_up();
+                    state.window.request_redraw();
+                }
+            }
+            WindowCommand::ExplorerDown => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.explorer.move_down();
+                    state.window.request_redraw();
+                }
+            }
+            WindowCommand::ExplorerEnter => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    if let Some(entry) = state.explorer.selected_entry().cloned() {
+                        if entry.is_dir() {
+                            // ディレクトリは展開/折りたたみ
+                            state.explorer.toggle_expand();
+                        } else if entry.path.extension().is_some_and(|ext| ext == "cast") {
+                            // 録画は新しいタブで再生する
+                            if let Err(e) = state.new_tab(&PaneCommand::Play(entry.path.clone())) {
+                                log::error!("録画を再生できません: {:#}", e);
+                            }
+                            state.explorer.visible = state.explorer.docked();
+                            state.explorer_focused = false;
+                        } else {
+                            // ファイルはエディタで開く（$EDITOR > nvim > vim）
+                            let editor = editor::default_editor();
+                            let open_cmd = format!("{} {}\n", editor, editor::shell_escape(&entry.path.display().to_string()));
+                            if let Some(pane) = state.panes.get_mut(&state.focused_pane()) {
+                                let _ = pane.pty.write(open_cmd.as_bytes());
+                            }
+                            // サイドバーは開いたままにする
+                            state.explorer.visible = state.explorer.docked();
+                            state.explorer_focused = false;
+                        }
+                    }
+                    state.window.request_redraw();
+                }
+            }
+            WindowCommand::ExplorerGo => {
+                // 選択中のディレクトリにcdして閉じる
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    if let Some(path) = state.explorer.get_cd_path() {
+                        let cd_cmd = format!("cd {}\n", editor::shell_escape(&path.display().to_string()));
+                        if let Some(pane) = state.panes.get_mut(&state.focused_pane()) {
+                            let _ = pane.pty.write(cd_cmd.as_bytes());
+                        }
+                    }
+                    state.explorer.visible = state.explorer.docked();
+                    state.explorer_focused = false;
+                    state.window.request_redraw();
+                }
+            }
+            WindowCommand::ScreenshotPane | WindowCommand::ScreenshotWindow => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    let whole_window = command == WindowCommand::ScreenshotWindow;
+                    match state.capture_screenshot(whole_window) {
+                        Ok(path) => {
+                            log::info!("スクリーンショットを保存しました: {:?}", path);
+                            notification::notify("スクリーンショットを保存しました", &path.display().to_string());
+                        }
+                        Err(e) => log::error!("スクリーンショットの保存に失敗: {}", e),
+                    }
+                }
+            }
+            WindowCommand::NextTheme => {
+                self.next_theme();
+            }
+            WindowCommand::TogglePerfHud => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.perf_visible = !state.perf_visible;
+                }
+            }
+            WindowCommand::OpenFinder => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.open_finder();
+                    state.window.request_redraw();
+                }
+            }
+            WindowCommand::OpenPalette => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.open_palette();
+                    state.window.request_redraw();
+                }
+            }
+            WindowCommand::OpenClipboardHistory => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.open_clipboard_history();
+                    state.window.request_redraw();
+                }
+            }
+            WindowCommand::OpenLogViewer => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.open_log_viewer();
+                    state.window.request_redraw();
+                }
+            }
+            WindowCommand::RunPalette(PaletteCommand::Steps(steps)) => {
+                // 前の操作で開いたタブやペインに続けて操作する
+                for step in steps {
+                    let Some(state) = self.windows.get_mut(&window_id) else { break };
+                    let command = state.bound_command(step.action, step.profile.as_deref(), step.text.as_deref());
+                    state.window.request_redraw();
+                    self.run_window_command(event_loop, window_id, command);
+                }
+            }
+            WindowCommand::RunPalette(PaletteCommand::Plugin(index)) => {
+                // 関数が頼んだ操作は次のイベントループで実行する
+                if let Some(state) = self.windows.get(&window_id) {
+                    self.plugins.run_palette_command(index, state.focused_pane());
+                }
+            }
+            WindowCommand::EnterCopyMode => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.enter_copy_mode();
+                    state.window.request_redraw();
+                }
+            }
+            WindowCommand::CopyLastOutput => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.copy_last_output();
+                    state.window.request_redraw();
+                }
+            }
+            WindowCommand::EnterHintMode => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.enter_hint_mode();
+                    state.window.request_redraw();
+                }
+            }
+            WindowCommand::ToggleFullscreen => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    state.toggle_fullscreen();
+                }
+            }
+            WindowCommand::RespawnPane => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    let focused = state.focused_pane();
+                    if let Some(pane) = state.panes.get_mut(&focused) {
+                        match pane.restart() {
+                            Ok(()) => log::info!("ペインを起動し直しました: {:?}", focused),
+                            Err(e) => log::error!("ペインの再起動に失敗: {}", e),
+                        }
+                        state.window.request_redraw();
+                    }
+                }
+            }
+            WindowCommand::ToggleMonitor(kind) => {
+                if let Some(state) = self.windows.get_mut(&window_id) {
+                    let focused = state.focused_pane();
+                    if let Some(pane) = state.panes.get_mut(&focused) {
+                        let enabled = pane.monitor.toggle(kind, Instant::now());
+                        log::info!("ペインの監視 {}: {}", kind.name(), if enabled { "on" } else { "off" });
+                        state.window.request_redraw();
+                    }
+                }
+            }
+            WindowCommand::None => {}
+        }
+    }
+
+    /// `umiterm cli` の要求を処理する
+    #[cfg(unix)]
+    fn control(&mut self, event_loop: &ActiveEventLoop, request: &Request) -> Result<Reply> {
+        match request {
+            Request::ListPanes => Ok(Reply::Panes(self.list_panes())),
+            Request::SplitPane { pane, horizontal, cwd, command } => {
+                let (window_id, pane_id) = self.control_target(*pane)?;
+                let state = self.windows.get_mut(&window_id).context("ウィンドウがありません")?;
+                state.select_pane(pane_id);
+                let command = command.clone().map_or(PaneCommand::Shell, PaneCommand::Run);
+                let new_id = if *horizontal {
+                    state.split_horizontal(&command, cwd.as_deref())?
+                } else {
+                    state.split_vertical(&command, cwd.as_deref())?
+                };
+                state.window.request_redraw();
+                Ok(Reply::Pane(new_id.0))
+            }
+            Request::SendText { pane, text, paste } => {
+                let (window_id, pane_id) = self.control_target(*pane)?;
+                let pane = self.windows.get(&window_id).and_then(|state| state.panes.get(&pane_id)).context("ペインがありません")?;
+                WindowState::reset_display_offset(pane);
+                let bytes = if *paste { pane.terminal.lock().paste_bytes(text) } else { text.as_bytes().to_vec() };
+                pane.pty.write(&bytes)?;
+                Ok(Reply::Ok)
+            }
+            Request::Activate { pane } => {
+                let (window_id, pane_id) = self.control_target(*pane)?;
+                let state = self.windows.get_mut(&window_id).context("ウィンドウがありません")?;
+                state.select_pane(pane_id);
+                state.window.focus_window();
+                state.window.request_redraw();
+                Ok(Reply::Ok)
+            }
+            Request::GetText { pane, scrollback } => {
+                let (window_id, pane_id) = self.control_target(*pane)?;
+                let pane = self.windows.get(&window_id).and_then(|state| state.panes.get(&pane_id)).context("ペインがありません")?;
+                let terminal = pane.terminal.lock();
+                let lines = if *scrollback { terminal.history_text() } else { terminal.screen_text() };
+                Ok(Reply::Text(lines.join("\n").trim_end().to_string()))
+            }
+            Request::NewWindow { cwd, command } => {
+                let command = command.clone().map_or(PaneCommand::Shell, PaneCommand::Run);
+                let window_id = self.create_window_with(event_loop, &mut None, &command, cwd.as_deref())?;
+                let state = &self.windows[&window_id];
+                state.window.focus_window();
+                Ok(Reply::Pane(state.focused_pane().0))
+            }
+            // 購読は接続のスレッドで処理するのでここには届かない
+            Request::Subscribe => anyhow::bail!("イベントの購読はイベントループでは処理できません"),
+        }
+    }
+
+    /// `umiterm cli`・プラグインの要求の対象のペイン（ID がなければフォーカス中のウィンドウのフォーカス中のペイン）
+    fn control_target(&self, pane: Option<u64>) -> Result<(WindowId, PaneId)> {
+        if let Some(id) = pane {
+            let pane_id = PaneId(id);
+            let window_id = self
+                .windows
+                .iter()
+                .find(|(_, state)| state.panes.contains_key(&pane_id))
+                .map(|(window_id, _)| *window_id)
+                .with_context(|| format!("ペイン {} はありません", id))?;
+            return Ok((window_id, pane_id));
+        }
+        let window_id = self
+            .focused_window
+            .filter(|id| self.windows.contains_key(id))
+            .or_else(|| self.windows.keys().next().copied())
+            .context("ウィンドウがありません")?;
+        Ok((window_id, self.windows[&window_id].focused_pane()))
+    }
+
+    /// すべてのウィンドウのペインの一覧（ウィンドウ・タブ・レイアウトの順）
+    #[cfg(unix)]
+    fn list_panes(&self) -> Vec<PaneInfo> {
+        self.all_panes()
+            .into_iter()
+            .map(|(window_id, tab, pane, focused)| {
+                let (cols, rows) = pane.pty.size();
+                PaneInfo {
+                    window: u64::from(window_id),
+                    tab,
+                    pane: pane.id.0,
+                    title: pane.title().name,
+                    cwd: pane.terminal.lock().cwd.clone(),
+                    cols,
+                    rows,
+                    focused,
+                }
+            })
+            .collect()
+    }
+
+    /// すべてのウィンドウのペイン（ウィンドウ・タブ・レイアウトの順、
+    /// フォーカス中のウィンドウの表示中のタブでフォーカス中か）
+    fn all_panes(&self) -> Vec<(WindowId, usize, &Pane, bool)> {
+        let mut windows: Vec<_> = self.windows.iter().collect();
+        windows.sort_by_key(|(window_id, _)| u64::from(**window_id));
+        let mut panes = Vec::new();
+        for (window_id, state) in windows {
+            for (tab_index, tab) in state.tabs.iter().enumerate() {
+                for pane_id in tab.layout.all_pane_ids() {
+                    let Some(pane) = state.panes.get(&pane_id) else { continue };
+                    let focused = self.focused_window == Some(*window_id)
+                        && state.active_tab == tab_index
+                        && tab.focused_pane == pane_id;
+                    panes.push((*window_id, tab_index, pane, focused));
+                }
+            }
+        }
+        panes
+    }
+
+    /// プラグインのフックを呼び、頼まれた操作を実行する（umiterm.panes() の一覧もここで新しくする）
+    fn run_plugins(&mut self, event_loop: &ActiveEventLoop) {
+        if self.plugins.is_empty() {
+            return;
+        }
+        let panes = self
+            .all_panes()
+            .into_iter()
+            .map(|(window_id, tab, pane, focused)| PluginPane {
+                id: pane.id.0,
+                window: u64::from(window_id),
+                tab,
+                title: pane.title().name,
+                cwd: pane.terminal.lock().cwd.display().to_string(),
+                focused,
+            })
+            .collect();
+        self.plugins.set_panes(panes);
+        self.plugins.dispatch_events();
+        for command in self.plugins.take_commands() {
+            if let Err(e) = self.run_plugin_command(event_loop, command) {
+                log::error!("プラグインの操作に失敗: {:#}", e);
+            }
+        }
+    }
+
+    /// プラグインから頼まれた操作を1つ実行する
+    fn run_plugin_command(&mut self, event_loop: &ActiveEventLoop, command: PluginCommand) -> Result<()> {
+        match command {
+            PluginCommand::SendText { pane, text } => {
+                let (window_id, pane_id) = self.control_target(pane)?;
+                let pane = self.windows.get(&window_id).and_then(|state| state.panes.get(&pane_id)).context("ペインがありません")?;
+                WindowState::reset_display_offset(pane);
+                pane.pty.write(text.as_bytes())?;
+            }
+            PluginCommand::Split { pane, horizontal, command, cwd } => {
+                let (window_id, pane_id) = self.control_target(pane)?;
+                let state = self.windows.get_mut(&window_id).context("ウィンドウがありません")?;
+                state.select_pane(pane_id);
+                if horizontal {
+                    state.split_horizontal(&command, cwd.as_deref())?;
+                } else {
+                    state.split_vertical(&command, cwd.as_deref())?;
+                }
+                state.window.request_redraw();
+            }
+            PluginCommand::Activate { pane } => {
+                let (window_id, pane_id) = self.control_target(pane)?;
+                let state = self.windows.get_mut(&window_id).context("ウィンドウがありません")?;
+                state.select_pane(pane_id);
+                state.window.focus_window();
+                state.window.request_redraw();
+            }
+            PluginCommand::Action { action, text } => {
+                let (window_id, _) = self.control_target(None)?;
+                let state = self.windows.get_mut(&window_id).context("ウィンドウがありません")?;
+                let command = state.bound_command(action, None, text.as_deref());
+                state.window.request_redraw();
+                self.run_window_command(event_loop, window_id, command);
+            }
+            PluginCommand::NewWindow { command, cwd } => {
+                self.create_window_with(event_loop, &mut None, &command, cwd.as_deref())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 設定ファイルを読み直して、すべてのウィンドウに反映する
+    fn reload_config(&mut self) {
+        let Some(config) = config::reload() else { return };
+        if let Some(context) = &self.render_context {
+            if let Err(e) = context.reload_font() {
+                log::warn!("{:#}", e);
+            }
+        }
+        for state in self.windows.values_mut() {
+            state.apply_config(&config);
+        }
+    }
+
+    /// 次のテーマに切り替える（設定ファイルを保存し直すまで）
+    fn next_theme(&mut self) {
+        let names = theme::names();
+        let Some(name) = theme::next(&names, config::current().colors.theme.as_deref()) else { return };
+        let config = match config::set_theme(&name) {
+            Ok(config) => config,
+            Err(e) => {
+                log::warn!("{:#}", e);
+                return;
+            }
         };
+        log::info!("テーマを切り替えました: {}", name);
+        self.refresh_palettes(&config);
+    }
 
-        // アダプターを取得（初回のみ）
-        if self.adapter.is_none() {
-            self.adapter = Some(pollster::block_on(self.instance.request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            }))?);
+    /// OS の外観が変わったら、合わせたテーマに切り替える
+    fn set_appearance(&mut self, appearance: Appearance) {
+        if !theme::set_appearance(appearance) {
+            return;
         }
+        log::info!("OS の外観: {:?}", appearance);
+        let config = config::current();
+        if config.colors.follows_appearance() {
+            self.refresh_palettes(&config);
+        }
+    }
 
-        let adapter = self.adapter.as_ref().context("GPUアダプターが見つかりません")?;
-
-        // レンダラーを作成
-        let renderer = pollster::block_on(Renderer::new(
-            surface,
-            size.width,
-            size.height,
-            adapter,
-        ))?;
+    /// すべてのウィンドウの色を設定に合わせる
+    fn refresh_palettes(&mut self, config: &Config) {
+        for state in self.windows.values_mut() {
+            state.renderer.set_palette(config.active_palette().clone());
+            state.sync_frame_theme();
+            state.needs_redraw = true;
+            state.window.request_redraw();
+        }
+    }
 
-        // ターミナルサイズを計算
-        let (cols, rows) = renderer.calculate_terminal_size();
+    /// すべてのウィンドウが省電力モードに入ってよいか
+    fn all_idle(&self, now: Instant) -> bool {
+        !self.windows.is_empty() && self.windows.values().all(|state| state.is_idle(now))
+    }
 
-        // 初期ペインを作成
-        let mut initial_pane = Pane::new(cols, rows)?;
-        let initial_pane_id = initial_pane.id;
-        WindowState::show_startup_banner(&mut initial_pane);
+    /// フォーカス中のペインを新しいウィンドウに切り離す
+    fn detach_pane(&mut self, event_loop: &ActiveEventLoop, window_id: WindowId) {
+        let Some(state) = self.windows.get_mut(&window_id) else { return };
+        if state.has_single_pane() {
+            log::info!("ペインが1つしかないため切り離しません");
+            return;
+        }
 
-        // ペインを登録
-        let mut panes = std::collections::HashMap::new();
-        panes.insert(initial_pane_id, initial_pane);
+        let mut pane = state.take_focused_pane();
+        if let Err(e) = self.create_window_with(event_loop, &mut pane, &PaneCommand::Shell, None) {
+            log::error!("ペインの切り離しに失敗: {}", e);
+        }
 
-        // IME（日本語入力）を有効化
-        window.set_ime_allowed(true);
+        // ウィンドウを作れなかったら元に戻す
+        if let Some(pane) = pane {
+            if let Some(state) = self.windows.get_mut(&window_id) {
+                state.insert_pane(pane);
+            }
+        }
+    }
 
-        // ファイルエクスプローラーを初期化（ホームディレクトリ）
-        let home = std::env::var("HOME")
-            .map(std::path::PathBuf::from)
-            .unwrap_or_else(|_| std::path::PathBuf::from("/"));
-        let explorer = Explorer::new(home);
+    /// フォーカス中のペインを別のウィンドウ（直前にフォーカスしていたウィンドウ）に移す
+    /// ペインがなくなったウィンドウは閉じる
+    fn merge_pane(&mut self, window_id: WindowId) {
+        let target = self
+            .previous_window
+            .filter(|id| *id != window_id && self.windows.contains_key(id))
+            .or_else(|| self.windows.keys().find(|id| **id != window_id).copied());
+        let Some(target) = target else {
+            log::info!("移動先のウィンドウがありません");
+            return;
+        };
 
-        // WindowStateを作成
-        let state = WindowState {
-            window,
-            renderer,
-            panes,
-            layout: PaneLayout::single(initial_pane_id),
-            focused_pane: initial_pane_id,
-            last_frame: Instant::now(),
-            ime_active: false,
-            modifiers: Modifiers::default(),
-            mouse_pos: (0.0, 0.0),
-            mouse_pixel_pos: (0.0, 0.0),
-            dragging_border: None,
-            selecting_text: false,
-            explorer,
-            explorer_focused: false,
+        let Some(pane) = self.windows.get_mut(&window_id).and_then(|state| state.take_focused_pane()) else {
+            return;
         };
+        let pane_id = pane.id;
+        if let Some(state) = self.windows.get_mut(&target) {
+            state.insert_pane(pane);
+            state.window.focus_window();
+            state.window.request_redraw();
+        }
+        log::info!("ペインを移動: {:?} ({:?} -> {:?})", pane_id, window_id, target);
 
-        // ウィンドウを登録
-        self.windows.insert(window_id, state);
+        if self.windows.get(&window_id).is_some_and(|state| state.tabs.is_empty()) {
+            self.close_window(window_id);
+        }
+    }
 
-        log::info!("新しいウィンドウを作成しました: {:?}", window_id);
+    /// 前回クラッシュしたときのセッションがあれば、最初のウィンドウで戻すか尋ねる
+    fn offer_restore(&mut self) {
+        let Some(session) = &self.crashed else { return };
+        let Some(state) = self.windows.values_mut().next() else { return };
+        log::info!("前回クラッシュしたときのセッションがあります");
+        state.restore_confirm = Some(session.dialog_lines());
+        state.needs_redraw = true;
+    }
+
+    /// クラッシュしたときのセッションを新しいウィンドウに戻し、尋ねたウィンドウ（起動したばかりのもの）を閉じる
+    fn restore_session(&mut self, event_loop: &ActiveEventLoop, window_id: WindowId, session: crash::Session) {
+        let mut restored = false;
+        for window in session.windows {
+            match self.restore_window(event_loop, window) {
+                Ok(_) => restored = true,
+                Err(e) => log::error!("ウィンドウを戻せません: {:#}", e),
+            }
+        }
+        if restored {
+            self.close_window(window_id);
+        }
+    }
 
+    /// 書き出したウィンドウのタブとペインを起動し直す
+    fn restore_window(&mut self, event_loop: &ActiveEventLoop, window: crash::Window<crash::SavedPane>) -> Result<WindowId> {
+        let mut panes = Vec::new();
+        let mut layouts = Vec::new();
+        for tab in window.tabs {
+            match crash::restore_tab(tab, &mut panes) {
+                Ok(layout) => layouts.push(layout),
+                Err(e) => log::warn!("タブを戻せません: {:#}", e),
+            }
+        }
+        let mut panes = panes.into_iter();
+        let first = panes.next().context("戻せるペインがありません")?;
+        let window_id = self.create_window_with(event_loop, &mut Some(first), &PaneCommand::Shell, None)?;
+        let state = self.windows.get_mut(&window_id).context("ウィンドウがありません")?;
+        state.panes.extend(panes.map(|pane| (pane.id, pane)));
+        state.tabs = layouts.into_iter().map(Tab::with_layout).collect();
+        state.select_tab(window.active_tab.min(state.tabs.len() - 1));
+        state.resize_all_panes();
+        log::info!("前回のセッションのウィンドウを戻しました: {} タブ", state.tabs.len());
         Ok(window_id)
     }
 
+    /// スクリーンリーダーからの要求を処理
+    fn accessibility_event(&mut self, event: accesskit_winit::Event) {
+        let Some(state) = self.windows.get_mut(&event.window_id) else { return };
+        match event.window_event {
+            accesskit_winit::WindowEvent::InitialTreeRequested => {
+                log::info!("スクリーンリーダーが繋がりました: {:?}", event.window_id);
+                state.accessibility.activate();
+                state.update_accessibility();
+            }
+            accesskit_winit::WindowEvent::ActionRequested(request) => {
+                // ペインを選んだらそのペインにフォーカスする
+                if let Some(pane_id) = accessibility::focus_target(&request) {
+                    state.select_pane(pane_id);
+                    state.needs_redraw = true;
+                }
+            }
+            accesskit_winit::WindowEvent::AccessibilityDeactivated => {
+                state.accessibility.deactivate();
+            }
+        }
+    }
+
+    /// パニックフックに今のレイアウトとターミナルを預ける
+    fn track_session(&self) {
+        let windows = self
+            .windows
+            .values()
+            .map(|state| crash::Window {
+                tabs: state
+                    .tabs
+                    .iter()
+                    .filter_map(|tab| {
+                        crash::Layout::from_pane_layout(&tab.layout, &mut |id| {
+                            let pane = state.panes.get(&id)?;
+                            let (command, cwd) = pane.launch();
+                            Some(crash::LivePane {
+                                terminal: pane.terminal.clone(),
+                                command: command.clone(),
+                                cwd: cwd.map(Path::to_path_buf),
+                            })
+                        })
+                    })
+                    .collect(),
+                active_tab: state.active_tab,
+            })
+            .collect();
+        crash::track(windows);
+    }
+
     /// ウィンドウを閉じる
     fn close_window(&mut self, window_id: WindowId) {
         if let Some(_state) = self.windows.remove(&window_id) {
@@ -881,14 +4369,18 @@ impl App {
 }
 
 // winit のイベントハンドラーを実装
-impl ApplicationHandler for App {
+impl ApplicationHandler<UserEvent> for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         // 初回起動時にウィンドウを作成
         if self.windows.is_empty() {
-            if let Err(e) = self.create_window(event_loop) {
+            if let Err(e) = self.create_initial_window(event_loop) {
                 log::error!("初期化エラー: {}", e);
                 event_loop.exit();
+                return;
             }
+            self.offer_restore();
+            self.plugins.startup();
+            self.run_plugins(event_loop);
         }
     }
 
@@ -901,17 +4393,44 @@ impl ApplicationHandler for App {
         // ウィンドウコマンド（新規作成・閉じるなど）を一時保存
         let mut command = WindowCommand::None;
 
+        // OS の外観が変わったら、すべてのウィンドウの色を切り替える
+        if let WindowEvent::ThemeChanged(os_theme) = event {
+            self.set_appearance(os_theme.into());
+        }
+
         // 対象ウィンドウの処理
         if let Some(state) = self.windows.get_mut(&window_id) {
+            state.accessibility.process_event(&state.window, &event);
+
+            // 描画以外のイベントは操作とみなして省電力モードを解除
+            if !matches!(event, WindowEvent::RedrawRequested) {
+                state.activity.touch(Instant::now());
+            }
+
+            if let WindowEvent::Focused(focused) = event {
+                state.window_focused = focused;
+            }
+
             match event {
                 WindowEvent::CloseRequested => {
-                    // ウィンドウの閉じるボタンはウィンドウ全体を閉じる
-                    self.close_window(window_id);
+                    // ウィンドウの閉じるボタンはウィンドウ全体を閉じる（実行中のコマンドがあれば確認）
+                    if state.request_close(CloseTarget::Window) {
+                        self.close_window(window_id);
+                    }
                     return;
                 }
                 WindowEvent::Resized(size) => {
                     state.handle_resize(size.width, size.height);
                 }
+                // 大きさは続く Resized で変わる
+                WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                    state.set_scale_factor(scale_factor);
+                }
+                // ペインの移動先として直前のウィンドウを覚えておく
+                WindowEvent::Focused(true) if self.focused_window != Some(window_id) => {
+                    self.previous_window = self.focused_window;
+                    self.focused_window = Some(window_id);
+                }
                 WindowEvent::KeyboardInput { event, .. } => {
                     command = state.handle_key(&event);
                 }
@@ -925,196 +4444,121 @@ impl ApplicationHandler for App {
                     state.handle_cursor_moved(position.x, position.y);
                 }
                 WindowEvent::MouseInput { button, state: btn_state, .. } => {
-                    state.handle_mouse_input(button, btn_state);
+                    command = state.handle_mouse_input(button, btn_state);
                 }
                 WindowEvent::MouseWheel { delta, .. } => {
                     state.handle_mouse_wheel(delta);
                 }
+                WindowEvent::DroppedFile(path) => {
+                    state.handle_dropped_file(&path);
+                }
                 WindowEvent::RedrawRequested => {
+                    state.autoscroll_selection();
                     let has_output = state.update();
+                    if state.handle_exited_panes() {
+                        // 最後のペインのシェルが終了したらウィンドウを閉じる
+                        self.close_window(window_id);
+                        if self.should_exit {
+                            event_loop.exit();
+                        }
+                        return;
+                    }
+                    // 時刻などの変化（省電力モード中も低頻度ティックで更新される）
+                    let status_changed = state.refresh_status();
+                    state.follow_explorer_cwd();
+                    state.sync_explorer_layout();
+                    state.record_recent_dirs();
+                    state.refresh_explorer();
+                    state.expire_pending_keys();
 
                     // 出力があるか、フォーカスペインがアクティブなら描画
                     // アイドル時（500ms以上出力なし）は描画頻度を下げる
                     let any_active = state.panes.values().any(|p| !p.is_idle(500));
                     let explorer_visible = state.explorer.visible;
 
-                    if has_output || any_active || state.selecting_text || state.dragging_border.is_some() || explorer_visible {
-                        if !state.render() {
-                            self.should_exit = true;
-                        }
+                    // 省電力モード中は出力か再描画要求があるときだけ描画（インスタンスも組み立てない）
+                    let sleeping = state.is_idle(Instant::now());
+
+                    // HUD表示中は計測のため毎フレーム描画
+                    if (has_output
+                        || status_changed
+                        || state.needs_redraw
+                        || (!sleeping
+                            && (any_active
+                                || state.selecting_text
+                                || state.scrollbar_visible
+                                || state.dragging_border.is_some()
+                                || explorer_visible
+                                || state.perf_visible)))
+                        && !state.render()
+                    {
+                        self.should_exit = true;
                     }
-
-                    // 次のフレームをリクエスト
-                    state.window.request_redraw();
+                    state.update_accessibility();
                 }
                 _ => {}
             }
         }
 
         // ウィンドウコマンドを処理（borrowを避けるため別途処理）
-        match command {
-            WindowCommand::NewWindow => {
-                if let Err(e) = self.create_window(event_loop) {
-                    log::error!("新規ウィンドウの作成に失敗: {}", e);
-                }
-            }
-            WindowCommand::ClosePane => {
-                // ペインを閉じる（ペインが1つならウィンドウを閉じる）
-                if let Some(state) = self.windows.get_mut(&window_id) {
-                    if state.close_pane() {
-                        self.close_window(window_id);
-                    }
-                }
-            }
-            WindowCommand::SplitHorizontal => {
-                if let Some(state) = self.windows.get_mut(&window_id) {
-                    if let Err(e) = state.split_horizontal() {
-                        log::error!("縦分割に失敗: {}", e);
-                    }
-                }
-            }
-            WindowCommand::SplitVertical => {
-                if let Some(state) = self.windows.get_mut(&window_id) {
-                    if let Err(e) = state.split_vertical() {
-                        log::error!("横分割に失敗: {}", e);
-                    }
-                }
-            }
-            WindowCommand::FocusNextPane => {
-                if let Some(state) = self.windows.get_mut(&window_id) {
-                    state.focus_next_pane();
-                }
-            }
-            WindowCommand::FocusPrevPane => {
-                if let Some(state) = self.windows.get_mut(&window_id) {
-                    state.focus_prev_pane();
-                }
-            }
-            WindowCommand::Copy => {
-                // 選択テキストをクリップボードにコピー
-                if let Some(state) = self.windows.get_mut(&window_id) {
-                    if let Some(pane) = state.panes.get(&state.focused_pane) {
-                        let terminal = pane.terminal.lock();
-                        if let Some(text) = terminal.get_selected_text() {
-                            drop(terminal); // クリップボード操作前にロックを解除
-                            if let Ok(mut clipboard) = Clipboard::new() {
-                                let _ = clipboard.set_text(&text);
-                                log::info!("Copied: {:?}", text);
-                            }
-                        } else {
-                            drop(terminal);
-                            // 選択がない場合は、Ctrl+Cとして送信
-                            if let Some(pane) = state.panes.get(&state.focused_pane) {
-                                let _ = pane.pty.write(&[0x03]); // Ctrl+C
-                            }
-                        }
-                    }
-                }
-            }
-            WindowCommand::Paste => {
-                // クリップボードからペースト
-                if let Some(state) = self.windows.get_mut(&window_id) {
-                    if let Ok(mut clipboard) = Clipboard::new() {
-                        if let Ok(text) = clipboard.get_text() {
-                            if let Some(pane) = state.panes.get_mut(&state.focused_pane) {
-                                pane.pty.write(text.as_bytes());
-                            }
-                        }
-                    }
-                }
-            }
-            WindowCommand::ToggleExplorer => {
-                if let Some(state) = self.windows.get_mut(&window_id) {
-                    // 表示する前に、シェルの現在の作業ディレクトリを取得
-                    if !state.explorer.visible {
-                        if let Some(pane) = state.panes.get(&state.focused_pane) {
-                            // PTYからシェルのcwdを直接取得（lsof使用）
-                            if let Some(cwd) = pane.pty.get_cwd() {
-                                if cwd.exists() {
-                                    state.explorer.set_root(cwd.clone());
-                                    log::info!("Explorer root set to shell cwd: {:?}", cwd);
-                                }
-                            } else {
-                                // フォールバック: ターミナルのcwd（OSC 7から）
-                                let terminal = pane.terminal.lock();
-                                let cwd = terminal.cwd.clone();
-                                drop(terminal);
-                                if cwd.exists() {
-                                    state.explorer.set_root(cwd.clone());
-                                    log::info!("Explorer root set to terminal cwd: {:?}", cwd);
-                                }
-                            }
-                        }
-                    }
-                    state.explorer.toggle();
-                    state.explorer_focused = state.explorer.visible;
-                    log::info!("Explorer toggled: visible={}, entries={}", state.explorer.visible, state.explorer.entries.len());
-                    state.window.request_redraw();
-                }
-            }
-            WindowCommand::ExplorerUp => {
-                if let Some(state) = self.windows.get_mut(&window_id) {
-                    state.explorer.move_up();
-                    state.window.request_redraw();
-                }
-            }
-            WindowCommand::ExplorerDown => {
-                if let Some(state) = self.windows.get_mut(&window_id) {
-                    state.explorer.move_down();
-                    state.window.request_redraw();
-                }
-            }
-            WindowCommand::ExplorerEnter => {
-                if let Some(state) = self.windows.get_mut(&window_id) {
-                    if let Some(entry) = state.explorer.selected_entry().cloned() {
-                        if entry.is_dir() {
-                            // ディレクトリは展開/折りたたみ
-                            state.explorer.toggle_expand();
-                        } else {
-                            // ファイルはエディタで開く（$EDITOR > nvim > vim）
-                            let editor = std::env::var("EDITOR").unwrap_or_else(|_| {
-                                // nvimがあればそちらを優先
-                                if std::process::Command::new("which").arg("nvim").output()
-                                    .map(|o| o.status.success()).unwrap_or(false) {
-                                    "nvim".to_string()
-                                } else {
-                                    "vim".to_string()
-                                }
-                            });
-                            let open_cmd = format!("{} \"{}\"\n", editor, entry.path.display());
-                            if let Some(pane) = state.panes.get_mut(&state.focused_pane) {
-                                let _ = pane.pty.write(open_cmd.as_bytes());
-                            }
-                            state.explorer.visible = false;
-                            state.explorer_focused = false;
-                        }
-                    }
+        self.run_window_command(event_loop, window_id, command);
+
+        if self.should_exit {
+            event_loop.exit();
+        }
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: UserEvent) {
+        match event {
+            UserEvent::Wake => {
+                for state in self.windows.values() {
                     state.window.request_redraw();
                 }
             }
-            WindowCommand::ExplorerGo => {
-                // 選択中のディレクトリにcdして閉じる
-                if let Some(state) = self.windows.get_mut(&window_id) {
-                    if let Some(path) = state.explorer.get_cd_path() {
-                        let cd_cmd = format!("cd \"{}\"\n", path.display());
-                        if let Some(pane) = state.panes.get_mut(&state.focused_pane) {
-                            let _ = pane.pty.write(cd_cmd.as_bytes());
-                        }
-                    }
-                    state.explorer.visible = false;
-                    state.explorer_focused = false;
-                    state.window.request_redraw();
-                }
+            UserEvent::ConfigChanged => self.reload_config(),
+            UserEvent::AppearanceChanged(appearance) => self.set_appearance(appearance),
+            #[cfg(unix)]
+            UserEvent::Control(pending) => {
+                let reply = self.control(event_loop, &pending.request).unwrap_or_else(|e| control::Reply::Error(format!("{:#}", e)));
+                pending.reply(reply);
             }
-            WindowCommand::None => {}
+            UserEvent::Accessibility(event) => self.accessibility_event(event),
         }
+    }
 
-        if self.should_exit {
-            event_loop.exit();
+    fn new_events(&mut self, _event_loop: &ActiveEventLoop, cause: StartCause) {
+        // 省電力モードの低頻度ティック
+        if let StartCause::ResumeTimeReached { .. } = cause {
+            for state in self.windows.values() {
+                state.window.request_redraw();
+            }
         }
     }
 
-    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        // プラグインのフックから頼まれた操作（キー・出力のフックは各ウィンドウの処理中に呼ばれる）
+        self.run_plugins(event_loop);
+
+        let now = Instant::now();
+
+        // クラッシュしても戻せるよう、ときどきレイアウトをパニックフックに預ける
+        if now.duration_since(self.last_tracked) >= crash::TRACK_INTERVAL {
+            self.track_session();
+            self.last_tracked = now;
+        }
+
+        // すべてのウィンドウがアイドルなら省電力モード（低頻度の再描画だけ）
+        if self.all_idle(now) {
+            idle::enter_sleep();
+            // 眠った印をつけてからもう一度確かめ、その間に届いた出力を取りこぼさない
+            if self.all_idle(now) {
+                event_loop.set_control_flow(ControlFlow::WaitUntil(now + idle::IDLE_REDRAW_INTERVAL));
+                return;
+            }
+        }
+        idle::leave_sleep();
+        event_loop.set_control_flow(ControlFlow::Poll);
+
         // 継続的な更新をリクエスト
         for state in self.windows.values() {
             state.window.request_redraw();
@@ -1126,20 +4570,132 @@ impl ApplicationHandler for App {
 // メイン関数
 // ═══════════════════════════════════════════════════════════════════════════
 
+/// Meta にする Option キー（macOS ではこのキーの文字の合成を止める）
+#[cfg(target_os = "macos")]
+fn option_as_alt(keyboard: &KeyboardConfig) -> OptionAsAlt {
+    match (keyboard.left_alt_as_meta(), keyboard.right_alt_as_meta()) {
+        (true, true) => OptionAsAlt::Both,
+        (true, false) => OptionAsAlt::OnlyLeft,
+        (false, true) => OptionAsAlt::OnlyRight,
+        (false, false) => OptionAsAlt::None,
+    }
+}
+
 fn main() -> Result<()> {
-    // ログを初期化
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    // 引数を解析（--help・--version はここで終了）
+    let args = cli::Args::parse();
+
+    // 説明付きの設定ファイルを書き出して終了
+    if let Some(path) = &args.generate_config {
+        return config::generate(path.as_deref());
+    }
+
+    // 起動中の UmiTerm を操作して終了
+    if let Some(cli::Command::Cli { action }) = &args.subcommand {
+        #[cfg(unix)]
+        return control::run_cli(action);
+        #[cfg(not(unix))]
+        anyhow::bail!("umiterm cli は Unix 系のみ対応しています: {:?}", action);
+    }
+
+    // シェル統合のスクリプトを入れて終了
+    if let Some(cli::Command::ShellIntegration { action: cli::ShellIntegrationAction::Install { shell } }) = &args.subcommand {
+        if let Some(path) = &args.config {
+            config::set_path(path.clone());
+        }
+        let name = match shell {
+            Some(name) => name.clone(),
+            None => std::env::var("SHELL").context("SHELL がないので、シェルを指定してください（bash / zsh / fish）")?,
+        };
+        let shell = shell_integration::Shell::from_program(&name)
+            .with_context(|| format!("{} のシェル統合はありません（bash / zsh / fish）", name))?;
+        println!("{}", shell_integration::install(shell)?);
+        return Ok(());
+    }
+
+    // ログファイルは設定ファイルの隣に置くので、先に設定ファイルの場所を決める
+    if let Some(path) = &args.config {
+        config::set_path(path.clone());
+    }
+
+    // ログを初期化（標準エラーと、デーモンとは別のログファイル）
+    logging::init(if args.daemon { "umiterm-daemon" } else { "umiterm" });
 
     log::info!("UmiTerm を起動中...");
 
+    // パニックしたらクラッシュレポートとセッションを書き出す
+    crash::install();
+
+    // セッションデーモンとして起動された場合はウィンドウを作らない
+    #[cfg(unix)]
+    if args.daemon {
+        return session::run_daemon();
+    }
+
+    // シングルインスタンス: 起動中の UmiTerm があればそちらでウィンドウを開いて終了
+    #[cfg(unix)]
+    if args.single_instance()
+        && config::var("UMITERM_SINGLE_INSTANCE").is_some_and(|on| matches!(on.trim(), "true" | "1" | "on"))
+        && control::open_window(args.command_line(), args.cwd())?
+    {
+        return Ok(());
+    }
+
+    // プロファイリング（ガードの破棄時にトレースを書き出す）
+    let _profile_guard = match &args.profile {
+        Some(path) => Some(profiling::init(&path.clone().unwrap_or_else(profiling::default_path))?),
+        None => None,
+    };
+
     // イベントループを作成
-    let event_loop = EventLoop::new()?;
+    let event_loop = EventLoop::<UserEvent>::with_user_event().build()?;
     event_loop.set_control_flow(ControlFlow::Poll);
 
+    // 省電力モード中にPTY出力が届いたらイベントループを起こす
+    let proxy = event_loop.create_proxy();
+    idle::set_waker(move || {
+        let _ = proxy.send_event(UserEvent::Wake);
+    });
+
+    // 設定ファイルが保存されたら読み直す（監視はイベントループが終わるまで続ける）
+    let proxy = event_loop.create_proxy();
+    let _config_watcher = config::watch(move || {
+        let _ = proxy.send_event(UserEvent::ConfigChanged);
+    });
+
+    // umiterm cli からの要求を受け付ける（終了するとソケットを消す）
+    #[cfg(unix)]
+    let _control_server = {
+        let proxy = event_loop.create_proxy();
+        control::serve(move |pending| {
+            let _ = proxy.send_event(UserEvent::Control(pending));
+        })
+        .unwrap_or_else(|e| {
+            log::warn!("リモート操作を受け付けられません: {:#}", e);
+            None
+        })
+    };
+
+    // OS の外観の変化（macOS・Windows はウィンドウのイベントで届く）
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        let proxy = event_loop.create_proxy();
+        theme::watch_appearance(move |appearance| {
+            let _ = proxy.send_event(UserEvent::AppearanceChanged(appearance));
+        });
+    }
+
+    // 最初のペインを参照テストのフィクスチャとして記録する
+    if let Some(dir) = &args.ref_test {
+        reftest::arm(dir.clone());
+    }
+
     // アプリケーションを作成して実行
-    let mut app = App::new();
+    let mut app = App::new(&args, event_loop.create_proxy());
     event_loop.run_app(&mut app)?;
 
+    // 正常に終了したので、次の起動で戻すセッションは残さない
+    crash::clear();
     log::info!("UmiTerm を終了しました");
 
     Ok(())
@@ -1152,7 +4708,7 @@ fn main() -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::parser::AnsiParser;
+    use crate::terminal::Terminal;
 
     #[test]
     fn test_terminal_creation() {
@@ -1161,6 +4717,45 @@ mod tests {
         assert_eq!(terminal.active_grid().rows, 24);
     }
 
+    #[test]
+    fn test_paste_confirm_lines() {
+        let text = format!("echo {}\r\nls\x1b\n\tcd\n4\n5\n6\n7\n", "x".repeat(70));
+        let confirm = PasteConfirm { pane_id: PaneId(0), text };
+        let lines = confirm.lines();
+
+        assert_eq!(lines[0], "Paste 7 lines?");
+        // 長い行は省略、制御文字は記号、タブは空白
+        assert_eq!(lines[1], format!("│ echo {}…", "x".repeat(55)));
+        assert_eq!(lines[2], "│ ls·");
+        assert_eq!(lines[3], "│     cd");
+        assert_eq!(lines[6], "│ … 2 more lines");
+        assert_eq!(lines.last().unwrap(), "Enter: paste   Esc: cancel");
+    }
+
+    #[test]
+    fn test_config_error_notice() {
+        let notice = config_error_notice("TOML parse error at line 2, column 1\n  |\n2 | sise = 18");
+        assert!(notice.starts_with("\x1b[31m"));
+        assert!(notice.contains(":\r\nTOML parse error at line 2, column 1\r\n  |\r\n2 | sise = 18\x1b[0m"));
+    }
+
+    #[test]
+    fn test_startup_banner() {
+        let banner = |enabled, path: Option<&Path>| BannerConfig {
+            enabled,
+            path: path.map(|path| path.display().to_string()),
+        };
+        assert_eq!(startup_banner(&banner(None, None)).as_deref(), Some(STARTUP_BANNER));
+        assert_eq!(startup_banner(&banner(Some(false), None)), None);
+
+        // ファイルの改行は CR LF にする（読めなければ組み込みのバナー）
+        let file = std::env::temp_dir().join(format!("umiterm-banner-{}.txt", std::process::id()));
+        std::fs::write(&file, "hello\r\n\x1b[1mworld\x1b[0m\n").unwrap();
+        assert_eq!(startup_banner(&banner(None, Some(&file))).as_deref(), Some("hello\r\n\x1b[1mworld\x1b[0m\r\n"));
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(startup_banner(&banner(None, Some(&file))).as_deref(), Some(STARTUP_BANNER));
+    }
+
     #[test]
     fn test_parser_integration() {
         let mut terminal = Terminal::new(80, 24);
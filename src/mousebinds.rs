@@ -0,0 +1,176 @@
+//! マウスの割り当て
+//!
+//! 設定ファイルの `[[mousebinds]]` で、修飾キーとボタンの組み合わせに操作を割り当てる
+//! 既定の割り当て（クリックで選択、Alt + ドラッグで矩形選択、Cmd + クリックでエディタ、
+//! 中クリックでプライマリセレクションのペースト）のあとに足すので、上書きや（`none` で）無効化もできる
+//!
+//! 押している修飾キーを含む割り当てのうち、修飾キーの一番多いものを使う
+//! （`alt+left` の割り当ては Alt + Shift + クリックにも当たる）
+
+use anyhow::{Context, Result};
+use winit::event::MouseButton;
+
+use crate::config::MousebindConfig;
+use crate::keybinds::{self, BindAction, Mods};
+
+/// マウスで行う操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseAction {
+    /// フォーカスを移して選択を始める（ダブルクリックで単語、トリプルクリックで行）
+    Select,
+    /// 矩形選択を始める
+    SelectBlock,
+    /// 今の選択をクリックした位置まで広げる
+    ExtendSelection,
+    /// クリックした `ファイル:行` をエディタで、URL をブラウザで開く（当たらなければ選択）
+    OpenLocation,
+    /// プライマリセレクションをペースト
+    PastePrimary,
+    /// キー割り当てと同じ操作（`paste`、`new-tab` など、`none` で何もしない）
+    Bind(BindAction),
+}
+
+/// マウスだけの操作の名前
+const ACTIONS: &[(&str, MouseAction)] = &[
+    ("select", MouseAction::Select),
+    ("select-block", MouseAction::SelectBlock),
+    ("extend-selection", MouseAction::ExtendSelection),
+    ("open-location", MouseAction::OpenLocation),
+    ("paste-primary", MouseAction::PastePrimary),
+];
+
+impl MouseAction {
+    /// 名前から取得（マウスだけの操作のほか、text の要らないキー割り当ての操作）
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Some((_, action)) = ACTIONS.iter().find(|(n, _)| *n == name) {
+            return Some(*action);
+        }
+        let action = BindAction::from_name(name)?;
+        (!action.requires_text()).then_some(Self::Bind(action))
+    }
+}
+
+/// ボタンの名前
+const BUTTONS: &[(&str, MouseButton)] = &[
+    ("left", MouseButton::Left),
+    ("middle", MouseButton::Middle),
+    ("right", MouseButton::Right),
+    ("back", MouseButton::Back),
+    ("forward", MouseButton::Forward),
+];
+
+/// マウスの割り当て
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MouseBinding {
+    pub mods: Mods,
+    pub button: MouseButton,
+    pub action: MouseAction,
+}
+
+/// 修飾キーとボタンを読む（`cmd+left`、`middle` など）
+pub fn parse_button(spec: &str) -> Option<(Mods, MouseButton)> {
+    let spec = spec.trim().to_lowercase();
+    let (mods, button) = spec.rsplit_once('+').unwrap_or(("", &spec));
+    let (_, button) = BUTTONS.iter().find(|(name, _)| *name == button)?;
+    Some((keybinds::parse_mods(mods)?, *button))
+}
+
+/// 既定の割り当て
+pub fn defaults() -> Vec<MouseBinding> {
+    let binding = |mods, button, action| MouseBinding { mods, button, action };
+    vec![
+        binding(Mods::default(), MouseButton::Left, MouseAction::Select),
+        binding(Mods { alt: true, ..Mods::default() }, MouseButton::Left, MouseAction::SelectBlock),
+        binding(Mods { super_key: true, ..Mods::default() }, MouseButton::Left, MouseAction::OpenLocation),
+        binding(Mods::default(), MouseButton::Middle, MouseAction::PastePrimary),
+    ]
+}
+
+/// 既定の割り当てのあとに設定ファイルの割り当てをつなげる
+pub fn with_defaults(bindings: &[MouseBinding]) -> Vec<MouseBinding> {
+    defaults().into_iter().chain(bindings.iter().cloned()).collect()
+}
+
+/// 設定ファイルの `[[mousebinds]]` を読む
+pub fn parse_bindings(configs: &[MousebindConfig]) -> Result<Vec<MouseBinding>> {
+    configs
+        .iter()
+        .map(|config| {
+            let (mods, button) = parse_button(&config.button)
+                .with_context(|| format!("マウスのボタンが正しくありません: {:?}", config.button))?;
+            let action = MouseAction::from_name(config.action.trim())
+                .with_context(|| format!("マウスの操作の名前が正しくありません: {:?}", config.action))?;
+            Ok(MouseBinding { mods, button, action })
+        })
+        .collect()
+}
+
+/// 押したボタンに割り当てた操作（修飾キーの多い割り当て、同じなら後に書いたもの）
+pub fn lookup(bindings: &[MouseBinding], pressed: Mods, button: MouseButton) -> Option<MouseAction> {
+    let flags = |mods: Mods| [mods.super_key, mods.ctrl, mods.alt, mods.shift];
+    let held = flags(pressed);
+    bindings
+        .iter()
+        .enumerate()
+        .filter(|(_, b)| b.button == button && flags(b.mods).iter().zip(held).all(|(need, on)| !need || on))
+        .max_by_key(|(index, b)| (flags(b.mods).iter().filter(|on| **on).count(), *index))
+        .map(|(_, b)| b.action)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(button: &str, action: &str) -> MousebindConfig {
+        MousebindConfig { button: button.into(), action: action.into() }
+    }
+
+    #[test]
+    fn test_parse_button() {
+        assert_eq!(parse_button("middle"), Some((Mods::default(), MouseButton::Middle)));
+        assert_eq!(
+            parse_button("Ctrl+Shift+Right"),
+            Some((Mods { ctrl: true, shift: true, ..Mods::default() }, MouseButton::Right))
+        );
+        assert_eq!(parse_button("cmd+wheel"), None);
+        assert_eq!(parse_button("hyper+left"), None);
+        assert!(parse_bindings(&[config("right", "send-text")]).is_err());
+        assert!(parse_bindings(&[config("right", "pipe")]).is_err());
+        assert!(parse_bindings(&[config("right", "fly")]).is_err());
+    }
+
+    #[test]
+    fn test_lookup() {
+        let alt_shift = Mods { alt: true, shift: true, ..Mods::default() };
+        let cmd = Mods { super_key: true, ..Mods::default() };
+        let ctrl = Mods { ctrl: true, ..Mods::default() };
+        let bindings = defaults();
+        assert_eq!(lookup(&bindings, Mods::default(), MouseButton::Left), Some(MouseAction::Select));
+        // 修飾キーの多い割り当てが優先、足りない修飾キーは無視
+        assert_eq!(lookup(&bindings, alt_shift, MouseButton::Left), Some(MouseAction::SelectBlock));
+        assert_eq!(lookup(&bindings, cmd, MouseButton::Left), Some(MouseAction::OpenLocation));
+        assert_eq!(lookup(&bindings, Mods::default(), MouseButton::Right), None);
+
+        // 設定ファイルの割り当ては既定より後（同じ組み合わせなら上書き）
+        let bindings = with_defaults(
+            &parse_bindings(&[
+                config("right", "extend-selection"),
+                config("ctrl+left", "open-location"),
+                config("cmd+left", "select"),
+                config("middle", "none"),
+            ])
+            .unwrap(),
+        );
+        assert_eq!(lookup(&bindings, Mods::default(), MouseButton::Right), Some(MouseAction::ExtendSelection));
+        assert_eq!(lookup(&bindings, ctrl, MouseButton::Left), Some(MouseAction::OpenLocation));
+        assert_eq!(lookup(&bindings, cmd, MouseButton::Left), Some(MouseAction::Select));
+        assert_eq!(
+            lookup(&bindings, Mods::default(), MouseButton::Middle),
+            Some(MouseAction::Bind(BindAction::None))
+        );
+    }
+}
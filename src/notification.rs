@@ -0,0 +1,134 @@
+//! デスクトップ通知モジュール
+//!
+//! OS標準の通知コマンドを別プロセスで起動する（UIスレッドはブロックしない）
+
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::config;
+use crate::pane::{FinishedCommand, MonitorKind};
+
+/// 完了を通知するコマンドの実行時間の既定値（秒）
+const DEFAULT_LONG_COMMAND_SECS: u64 = 10;
+
+/// 完了を通知するコマンドの実行時間（環境変数 `UMITERM_NOTIFY_SECS` で変更可能）
+pub fn long_command_threshold() -> Duration {
+    let secs = config::var("UMITERM_NOTIFY_SECS")
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_LONG_COMMAND_SECS);
+    Duration::from_secs(secs)
+}
+
+/// 長時間実行したコマンドの完了を通知
+pub fn notify_command_finished(name: &str, finished: &FinishedCommand) {
+    let name = if name.is_empty() { "コマンド" } else { name };
+    notify("UmiTerm", &command_finished_message(name, finished));
+}
+
+/// 完了通知の本文
+fn command_finished_message(name: &str, finished: &FinishedCommand) -> String {
+    let status = match finished.exit_code {
+        Some(0) | None => "完了しました".to_string(),
+        Some(code) => format!("失敗しました（終了コード {}）", code),
+    };
+    format!("{} が{}（{}）", name, status, format_duration(finished.duration))
+}
+
+/// ペインの監視の警告を通知
+pub fn notify_monitor_alert(name: &str, kind: MonitorKind, silence: Duration) {
+    let name = if name.is_empty() { "ペイン" } else { name };
+    notify("UmiTerm", &monitor_alert_message(name, kind, silence));
+}
+
+/// 監視の警告の本文
+fn monitor_alert_message(name: &str, kind: MonitorKind, silence: Duration) -> String {
+    match kind {
+        MonitorKind::Activity => format!("{} に出力がありました", name),
+        MonitorKind::Silence => format!("{} の出力が{}止まっています", name, format_duration(silence)),
+    }
+}
+
+/// 出力のトリガーに一致した行を通知
+pub fn notify_trigger(name: &str, line: &str) {
+    let name = if name.is_empty() { "ペイン" } else { name };
+    notify("UmiTerm", &format!("{}: {}", name, line.trim()));
+}
+
+/// 経過時間を「1時間2分」「3分4秒」「5秒」の形にする
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{}秒", s),
+        (0, m, s) => format!("{}分{}秒", m, s),
+        (h, m, _) => format!("{}時間{}分", h, m),
+    }
+}
+
+/// デスクトップ通知を表示
+pub fn notify(title: &str, body: &str) {
+    let mut cmd = if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification \"{}\" with title \"{}\"",
+            escape_applescript(body),
+            escape_applescript(title)
+        );
+        let mut cmd = Command::new("osascript");
+        cmd.args(["-e", &script]);
+        cmd
+    } else if cfg!(target_os = "windows") {
+        // Windowsはトースト通知の代わりにログのみ
+        log::info!("{}: {}", title, body);
+        return;
+    } else {
+        let mut cmd = Command::new("notify-send");
+        cmd.args(["--app-name=UmiTerm", title, body]);
+        cmd
+    };
+
+    cmd.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    match cmd.spawn() {
+        // 終了を待たずにゾンビ化を防ぐため別スレッドで回収
+        Ok(mut child) => {
+            std::thread::spawn(move || {
+                let _ = child.wait();
+            });
+        }
+        Err(e) => log::warn!("通知の表示に失敗: {}", e),
+    }
+}
+
+/// AppleScript の文字列リテラル用にエスケープ
+fn escape_applescript(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_finished_message() {
+        let finished = FinishedCommand {
+            exit_code: Some(0),
+            duration: Duration::from_secs(75),
+        };
+        assert_eq!(command_finished_message("make", &finished), "make が完了しました（1分15秒）");
+
+        let failed = FinishedCommand {
+            exit_code: Some(2),
+            duration: Duration::from_secs(3725),
+        };
+        assert_eq!(command_finished_message("cargo", &failed), "cargo が失敗しました（終了コード 2）（1時間2分）");
+    }
+
+    #[test]
+    fn test_monitor_alert_message() {
+        let silence = Duration::from_secs(30);
+        assert_eq!(monitor_alert_message("tail", MonitorKind::Activity, silence), "tail に出力がありました");
+        assert_eq!(monitor_alert_message("make", MonitorKind::Silence, silence), "make の出力が30秒止まっています");
+    }
+}
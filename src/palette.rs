@@ -0,0 +1,236 @@
+//! コマンドパレット
+//!
+//! Cmd+Shift+P で開き、組み込みの操作と、設定ファイルの `[[commands]]`・プラグインで足した
+//! コマンドを名前で絞り込んで実行する（チームで「開発用のレイアウトを開く」などを共有できるように）
+//!
+//! ```toml
+//! [[commands]]
+//! name = "open dev layout"
+//! steps = [
+//!   { action = "new-tab" },
+//!   { action = "send-text", text = "npm run dev\r" },
+//!   { action = "split-horizontal", profile = "dev" },
+//! ]
+//! ```
+
+use anyhow::{Context, Result};
+
+use crate::config::CommandConfig;
+use crate::container::Container;
+use crate::keybinds::{self, BindAction};
+use crate::pane::LayoutPreset;
+use crate::pipe::Pipe;
+use crate::ssh::SshHost;
+
+/// コマンドの1つの操作（キー割り当てと同じ）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaletteStep {
+    pub action: BindAction,
+    /// 開くときのプロファイル（新しいウィンドウ・タブ・ペインのみ）
+    pub profile: Option<String>,
+    /// 送る文字列（send-text のみ）、パイプの名前（pipe のみ）、接続先（ssh のみ）、コンテナ（container のみ）、ディストリビューション（wsl のみ）、プリセットの名前（apply-layout のみ）、保存先（save-scrollback のみ）
+    pub text: Option<String>,
+}
+
+/// 選んだときに実行するもの
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteCommand {
+    /// 操作を順に実行する
+    Steps(Vec<PaletteStep>),
+    /// プラグインが `umiterm.add_command` で足した関数（足した順の番号）
+    Plugin(usize),
+}
+
+/// パレットの項目
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaletteEntry {
+    pub name: String,
+    pub command: PaletteCommand,
+}
+
+impl PaletteEntry {
+    /// 実行する操作（プラグインの関数なら空）
+    pub fn steps(&self) -> &[PaletteStep] {
+        match &self.command {
+            PaletteCommand::Steps(steps) => steps,
+            PaletteCommand::Plugin(_) => &[],
+        }
+    }
+}
+
+/// 設定ファイルのコマンドを読む
+pub fn parse_commands(configs: &[CommandConfig]) -> Result<Vec<PaletteEntry>> {
+    configs
+        .iter()
+        .map(|config| {
+            let name = config.name.trim();
+            anyhow::ensure!(!name.is_empty(), "コマンドの名前が空です");
+            anyhow::ensure!(!config.steps.is_empty(), "コマンドの steps が空です: {:?}", name);
+            let steps = config
+                .steps
+                .iter()
+                .map(|step| {
+                    let action = keybinds::parse_action(&step.action, step.profile.is_some(), step.text.is_some())?;
+                    Ok(PaletteStep { action, profile: step.profile.clone(), text: step.text.clone() })
+                })
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| format!("コマンドが正しくありません: {:?}", name))?;
+            Ok(PaletteEntry { name: name.to_string(), command: PaletteCommand::Steps(steps) })
+        })
+        .collect()
+}
+
+/// パレットに出す項目（組み込みの操作、プリセットのレイアウト、パイプ、SSH の接続先、プラグインのコマンド、設定ファイルのコマンドの順）
+///
+/// ファインダーは同点なら後ろの項目を上に出すので、空のクエリでは足したコマンドが先頭に並ぶ
+pub fn entries(
+    commands: &[PaletteEntry],
+    pipes: &[Pipe],
+    hosts: &[SshHost],
+    plugin_commands: Vec<String>,
+) -> Vec<PaletteEntry> {
+    let builtin = BindAction::all()
+        .filter(|action| {
+            !action.requires_text() && !matches!(action, BindAction::None | BindAction::CommandPalette | BindAction::SshLauncher)
+        })
+        .map(|action| PaletteEntry {
+            name: action.name().to_string(),
+            command: PaletteCommand::Steps(vec![PaletteStep { action, profile: None, text: None }]),
+        });
+    let layouts = LayoutPreset::ALL.into_iter().map(|preset| PaletteEntry {
+        name: format!("apply-layout {}", preset.name()),
+        command: PaletteCommand::Steps(vec![PaletteStep {
+            action: BindAction::ApplyLayout,
+            profile: None,
+            text: Some(preset.name().to_string()),
+        }]),
+    });
+    let pipes = pipes.iter().map(|pipe| PaletteEntry {
+        name: format!("| {}", pipe.name),
+        command: PaletteCommand::Steps(vec![PaletteStep { action: BindAction::Pipe, profile: None, text: Some(pipe.name.clone()) }]),
+    });
+    let hosts = ssh_entries(hosts);
+    let plugin = plugin_commands
+        .into_iter()
+        .enumerate()
+        .map(|(index, name)| PaletteEntry { name, command: PaletteCommand::Plugin(index) });
+    builtin.chain(layouts).chain(pipes).chain(hosts).chain(plugin).chain(commands.iter().cloned()).collect()
+}
+
+/// SSH の接続先の項目（`ssh-launcher` ではこれだけを出す）
+pub fn ssh_entries(hosts: &[SshHost]) -> impl Iterator<Item = PaletteEntry> + '_ {
+    hosts.iter().map(|host| PaletteEntry {
+        name: if host.name == host.destination {
+            format!("ssh {}", host.name)
+        } else {
+            format!("ssh {} ({})", host.name, host.destination)
+        },
+        command: PaletteCommand::Steps(vec![PaletteStep { action: BindAction::Ssh, profile: None, text: Some(host.name.clone()) }]),
+    })
+}
+
+/// 動いているコンテナの項目（`container-launcher` で出す）
+pub fn container_entries(containers: &[Container]) -> Vec<PaletteEntry> {
+    containers
+        .iter()
+        .map(|container| PaletteEntry {
+            name: format!("container {} ({})", container.name, container.image),
+            command: PaletteCommand::Steps(vec![PaletteStep {
+                action: BindAction::Container,
+                profile: None,
+                text: Some(container.name.clone()),
+            }]),
+        })
+        .collect()
+}
+
+/// WSL のディストリビューションの項目（`wsl-launcher` で出す）
+pub fn wsl_entries(distros: &[String]) -> Vec<PaletteEntry> {
+    distros
+        .iter()
+        .map(|distro| PaletteEntry {
+            name: format!("wsl {}", distro),
+            command: PaletteCommand::Steps(vec![PaletteStep { action: BindAction::Wsl, profile: None, text: Some(distro.clone()) }]),
+        })
+        .collect()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CommandStepConfig;
+
+    fn step(action: &str, profile: Option<&str>, text: Option<&str>) -> CommandStepConfig {
+        CommandStepConfig { action: action.into(), profile: profile.map(Into::into), text: text.map(Into::into) }
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        let configs = [CommandConfig {
+            name: " open dev layout ".into(),
+            steps: vec![
+                step("new-tab", None, None),
+                step("send-text", None, Some("npm run dev\r")),
+                step("split-horizontal", Some("dev"), None),
+            ],
+        }];
+        let commands = parse_commands(&configs).unwrap();
+        assert_eq!(commands[0].name, "open dev layout");
+        assert_eq!(
+            commands[0].steps(),
+            [
+                PaletteStep { action: BindAction::NewTab, profile: None, text: None },
+                PaletteStep { action: BindAction::SendText, profile: None, text: Some("npm run dev\r".into()) },
+                PaletteStep { action: BindAction::SplitHorizontal, profile: Some("dev".into()), text: None },
+            ]
+        );
+
+        // 操作はキー割り当てと同じ規則で確かめる
+        let command = |name: &str, steps| CommandConfig { name: name.into(), steps };
+        assert!(parse_commands(&[command("x", vec![step("fly", None, None)])]).is_err());
+        assert!(parse_commands(&[command("x", vec![step("copy", Some("dev"), None)])]).is_err());
+        assert!(parse_commands(&[command("x", vec![step("send-text", None, None)])]).is_err());
+        assert!(parse_commands(&[command("x", Vec::new())]).is_err());
+        assert!(parse_commands(&[command(" ", vec![step("copy", None, None)])]).is_err());
+    }
+
+    #[test]
+    fn test_entries() {
+        let commands = parse_commands(&[CommandConfig { name: "dev".into(), steps: vec![step("new-tab", None, None)] }]).unwrap();
+        let pipes = [Pipe { name: "fzf".into(), command: "fzf".into(), input: Default::default(), output: Default::default() }];
+        let hosts = [
+            SshHost { name: "prod".into(), destination: "deploy@prod.example.com".into() },
+            SshHost { name: "web1".into(), destination: "web1".into() },
+        ];
+        let entries = entries(&commands, &pipes, &hosts, vec!["deploy".into()]);
+        let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names[names.len() - 5..], ["| fzf", "ssh prod (deploy@prod.example.com)", "ssh web1", "deploy", "dev"]);
+        assert!(names.contains(&"split-vertical"));
+        assert!(!names.contains(&"send-text") && !names.contains(&"pipe") && !names.contains(&"command-palette"));
+        assert!(!names.contains(&"ssh") && !names.contains(&"ssh-launcher"));
+        assert!(names.contains(&"container-launcher") && !names.contains(&"container"));
+        assert!(names.contains(&"apply-layout grid") && !names.contains(&"apply-layout"));
+        assert_eq!(entries[entries.len() - 2].command, PaletteCommand::Plugin(0));
+        assert_eq!(
+            entries[entries.len() - 5].steps(),
+            [PaletteStep { action: BindAction::Pipe, profile: None, text: Some("fzf".into()) }]
+        );
+        assert_eq!(
+            entries[entries.len() - 4].steps(),
+            [PaletteStep { action: BindAction::Ssh, profile: None, text: Some("prod".into()) }]
+        );
+
+        let containers = [Container { id: "3f2a9c1b7d4e".into(), name: "web".into(), image: "nginx:1.27".into() }];
+        let entries = container_entries(&containers);
+        assert_eq!(entries[0].name, "container web (nginx:1.27)");
+        assert_eq!(entries[0].steps(), [PaletteStep { action: BindAction::Container, profile: None, text: Some("web".into()) }]);
+
+        let entries = wsl_entries(&["Ubuntu-24.04".into()]);
+        assert_eq!(entries[0].name, "wsl Ubuntu-24.04");
+        assert_eq!(entries[0].steps(), [PaletteStep { action: BindAction::Wsl, profile: None, text: Some("Ubuntu-24.04".into()) }]);
+    }
+}
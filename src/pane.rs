@@ -1,16 +1,27 @@
 //! ペイン管理モジュール
 //!
 //! ウィンドウ内の画面分割を管理
+//! PTY出力の解析はペインごとのパーサースレッドで行い、UIスレッドをブロックしない
 
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
+use crossbeam_channel::{Receiver, Sender};
 use parking_lot::Mutex;
 
+use crate::config::{self, Config};
+use crate::events::{self, Event};
+use crate::idle;
 use crate::parser::AnsiParser;
-use crate::pty::Pty;
-use crate::terminal::Terminal;
+use crate::pty::{ChildExit, PaneCommand, Pty};
+use crate::recording::{Recorder, SharedRecorder};
+use crate::reftest::{self, RefRecorder, SharedRefRecorder};
+use crate::scrollback::Limits;
+use crate::snapshot::TerminalSnapshot;
+use crate::terminal::{ShellMark, Terminal};
 
 // ═══════════════════════════════════════════════════════════════════════════
 // ペインID
@@ -29,6 +40,290 @@ impl PaneId {
     }
 }
 
+// ═══════════════════════════════════════════════════════════════════════════
+// タイトルバー
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 実行中のコマンドを調べ直す間隔
+const COMMAND_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// ペインのタイトルバーを表示する条件（環境変数 `UMITERM_PANE_TITLES` で変更可能）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaneTitleMode {
+    /// 常に表示
+    Always,
+    /// タブにペインが2つ以上あるときだけ表示
+    MultiPane,
+    /// 表示しない
+    Never,
+}
+
+impl config::Named for PaneTitleMode {
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("always", Self::Always),
+        ("multi", Self::MultiPane),
+        ("never", Self::Never),
+    ];
+}
+
+impl PaneTitleMode {
+    /// 環境変数か設定ファイルから取得（既定は常に表示）
+    pub fn from_env() -> Self {
+        config::named_var("UMITERM_PANE_TITLES", |config| config.pane.titles).unwrap_or(Self::Always)
+    }
+
+    /// 指定したペイン数のときに表示するか
+    pub fn visible(self, pane_count: usize) -> bool {
+        match self {
+            Self::Always => true,
+            Self::MultiPane => pane_count > 1,
+            Self::Never => false,
+        }
+    }
+}
+
+/// ペインのタイトル（タイトルバー・タブバーに表示）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PaneTitle {
+    /// 名前（OSCタイトル、なければ実行中のコマンド）
+    pub name: String,
+    /// 作業ディレクトリ（ホームは ~ で表示）
+    pub cwd: String,
+}
+
+/// 終了したコマンド（シェル統合の OSC 133 で検出）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FinishedCommand {
+    /// 終了コード（シェルが送らなければ None）
+    pub exit_code: Option<i32>,
+    /// 実行にかかった時間
+    pub duration: Duration,
+}
+
+impl FinishedCommand {
+    /// 成功したか（終了コードが不明なら成功とみなす）
+    pub fn succeeded(&self) -> bool {
+        self.exit_code.is_none_or(|code| code == 0)
+    }
+}
+
+/// ペインのタイトルバー（レンダラーに渡す）
+pub struct TitleBar {
+    /// ペイン全体の矩形（タイトルバーはこの最上段）
+    pub rect: Rect,
+    /// タイトル
+    pub title: PaneTitle,
+    /// フォーカス中のペインか
+    pub focused: bool,
+    /// 見ていない間に終了したコマンド（バッジ表示）
+    pub done: Option<FinishedCommand>,
+    /// 監視中の印（[act] / [sil]）
+    pub monitor: Option<&'static str>,
+    /// 録画中か
+    pub recording: bool,
+}
+
+/// 表示名を決める（OSCタイトル > 実行中のコマンド、プロファイルのタイトルがあれば前に付ける）
+fn display_name(label: Option<&str>, osc_title: &str, command: Option<&str>) -> String {
+    let name = if !osc_title.is_empty() { osc_title } else { command.unwrap_or_default() };
+    match label {
+        Some(label) if name.is_empty() => label.to_string(),
+        Some(label) => format!("{}: {}", label, name),
+        None => name.to_string(),
+    }
+}
+
+/// タイトルの前に付ける印（起動したプロファイルのタイトルか、接続したコンテナ・WSL のディストリビューションの名前）
+fn launch_label(config: &Config, launch: &PaneCommand) -> Option<String> {
+    match launch {
+        PaneCommand::Container(name) | PaneCommand::Wsl(name) => Some(name.clone()),
+        _ => config.profiles.get(launch.profile()?)?.title.clone(),
+    }
+}
+
+/// シェルの代わりに起動したコマンド（シェルを起動したペインは None）
+fn launched_command(config: &Config, launch: &PaneCommand) -> Option<String> {
+    match launch {
+        PaneCommand::Run(line) => Some(line.clone()),
+        PaneCommand::Container(name) => Some(name.clone()),
+        PaneCommand::Profile(name) => {
+            let profile = config.profiles.get(name)?;
+            profile.command.clone().or_else(|| profile.ssh.as_ref().map(|host| format!("ssh {}", host)))
+        }
+        PaneCommand::Shell | PaneCommand::Play(_) | PaneCommand::Wsl(_) => None,
+    }
+}
+
+/// パスを表示用の文字列にする（ホーム以下は ~ で始める）
+pub fn shorten_home(path: &Path, home: Option<&Path>) -> String {
+    match home.and_then(|home| path.strip_prefix(home).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 出力の監視
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 無音とみなすまでの既定の秒数
+const DEFAULT_SILENCE_SECS: u64 = 30;
+
+/// 無音の監視で警告するまでの時間（環境変数 `UMITERM_SILENCE_SECS`、既定30秒）
+pub fn silence_threshold() -> Duration {
+    let secs = config::var("UMITERM_SILENCE_SECS")
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_SILENCE_SECS);
+    Duration::from_secs(secs)
+}
+
+/// 監視の種類（tmux の monitor-activity / monitor-silence）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonitorKind {
+    /// 出力があったら警告
+    Activity,
+    /// 一定時間出力がなかったら警告
+    Silence,
+}
+
+impl MonitorKind {
+    /// 通知・ログ用の名前
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Activity => "activity",
+            Self::Silence => "silence",
+        }
+    }
+}
+
+/// ペインの出力の監視状態
+#[derive(Debug, Clone)]
+pub struct Monitor {
+    /// 出力を監視するか
+    activity: bool,
+    /// 無音を監視するか
+    silence: bool,
+    /// 無音の起点（最後の出力、または監視を始めた時刻）
+    quiet_since: Instant,
+    /// 今回の無音をすでに警告したか（出力があれば戻す）
+    silence_alerted: bool,
+    /// 発生した警告（ペインを見るまで枠に表示）
+    pub alert: Option<MonitorKind>,
+}
+
+impl Monitor {
+    /// 監視なしで作成
+    pub fn new(now: Instant) -> Self {
+        Self {
+            activity: false,
+            silence: false,
+            quiet_since: now,
+            silence_alerted: false,
+            alert: None,
+        }
+    }
+
+    /// 監視を切り替える
+    /// 戻り値: 切り替え後に監視しているか
+    pub fn toggle(&mut self, kind: MonitorKind, now: Instant) -> bool {
+        let enabled = match kind {
+            MonitorKind::Activity => &mut self.activity,
+            MonitorKind::Silence => {
+                // 無音は監視を始めた時点から数える
+                self.quiet_since = now;
+                self.silence_alerted = false;
+                &mut self.silence
+            }
+        };
+        *enabled = !*enabled;
+        let enabled = *enabled;
+        if !enabled && self.alert == Some(kind) {
+            self.alert = None;
+        }
+        enabled
+    }
+
+    /// タイトルバーに表示する監視中の印
+    pub fn label(&self) -> Option<&'static str> {
+        match (self.activity, self.silence) {
+            (true, true) => Some("[act+sil]"),
+            (true, false) => Some("[act]"),
+            (false, true) => Some("[sil]"),
+            (false, false) => None,
+        }
+    }
+
+    /// 出力の状況から警告を判定（見ているペインでは警告せず、出ている警告も消す）
+    /// 戻り値: 新しく出た警告
+    pub fn check(&mut self, had_output: bool, seen: bool, now: Instant, silence: Duration) -> Option<MonitorKind> {
+        if had_output {
+            self.quiet_since = now;
+            self.silence_alerted = false;
+        }
+        if seen {
+            self.alert = None;
+            return None;
+        }
+
+        let kind = if had_output && self.activity {
+            MonitorKind::Activity
+        } else if self.silence
+            && !self.silence_alerted
+            && now.saturating_duration_since(self.quiet_since) >= silence
+        {
+            self.silence_alerted = true;
+            MonitorKind::Silence
+        } else {
+            return None;
+        };
+
+        // 同じ警告が出たままなら新しい警告とはみなさない
+        if self.alert == Some(kind) {
+            return None;
+        }
+        self.alert = Some(kind);
+        Some(kind)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// シェルの終了
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 再起動するのに必要な稼働時間（すぐ終了するコマンドを再起動し続けないため）
+const MIN_RESTART_UPTIME: Duration = Duration::from_secs(1);
+
+/// 再起動するときに前のプロセスが残した端末のモードを戻す
+/// （代替スクリーン・カーソルキー・ブラケットペースト・マウス報告・カーソル非表示）
+const MODE_RESET: &str = "\x1b[?1049l\x1b[?1l\x1b[?2004l\x1b[?1000l\x1b[?1002l\x1b[?1003l\x1b[?1006l\x1b[?25h";
+
+/// シェルが終了したときの動作（環境変数 `UMITERM_ON_EXIT` で変更可能）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitAction {
+    /// ペインを閉じる
+    Close,
+    /// 終了コードを表示してペインを残す
+    Hold,
+    /// 同じコマンドを起動し直す
+    Restart,
+}
+
+impl config::Named for ExitAction {
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("close", Self::Close),
+        ("hold", Self::Hold),
+        ("restart", Self::Restart),
+    ];
+}
+
+impl ExitAction {
+    /// 環境変数か設定ファイルから取得（既定は閉じる）
+    pub fn from_env() -> Self {
+        config::named_var("UMITERM_ON_EXIT", |config| config.pane.on_exit).unwrap_or(Self::Close)
+    }
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 // 矩形領域
 // ═══════════════════════════════════════════════════════════════════════════
@@ -53,6 +348,7 @@ impl Rect {
     }
 
     /// 左半分
+    #[allow(dead_code)]
     pub fn left_half(&self) -> Self {
         Self {
             x: self.x,
@@ -63,6 +359,7 @@ impl Rect {
     }
 
     /// 右半分
+    #[allow(dead_code)]
     pub fn right_half(&self) -> Self {
         Self {
             x: self.x + self.width / 2.0,
@@ -73,6 +370,7 @@ impl Rect {
     }
 
     /// 上半分
+    #[allow(dead_code)]
     pub fn top_half(&self) -> Self {
         Self {
             x: self.x,
@@ -83,6 +381,7 @@ impl Rect {
     }
 
     /// 下半分
+    #[allow(dead_code)]
     pub fn bottom_half(&self) -> Self {
         Self {
             x: self.x,
@@ -101,58 +400,372 @@ impl Rect {
 pub struct Pane {
     /// ペインID
     pub id: PaneId,
-    /// ターミナル状態
+    /// ターミナル状態（パーサースレッドと共有）
     pub terminal: Arc<Mutex<Terminal>>,
-    /// ANSI パーサー
-    pub parser: AnsiParser,
+    /// パーサースレッドが解析したバイト数（前回の update 以降）
+    processed: Arc<AtomicUsize>,
+    /// 描画用のスナップショット
+    pub snapshot: TerminalSnapshot,
     /// PTY（擬似端末）
     pub pty: Pty,
     /// 最後のフレーム時刻
+    #[allow(dead_code)]
     pub last_frame: Instant,
     /// 最後に出力があった時刻
     pub last_output: Instant,
     /// 再描画が必要か（ダーティフラグ）
     pub dirty: bool,
+    /// 前回取り出してからベルが鳴ったか
+    bell: bool,
+    /// フォアグラウンドで実行中のコマンド名（タイトル表示用）
+    command: Option<String>,
+    /// 最後にコマンド名を調べた時刻
+    command_checked: Option<Instant>,
+    /// 実行中のコマンドの開始時刻（OSC 133 の C）
+    command_started: Option<Instant>,
+    /// 終了したコマンド（取り出すまで保持）
+    finished: Option<FinishedCommand>,
+    /// 見ていない間に終了したコマンド（ペインを見るまでバッジを表示）
+    pub done: Option<FinishedCommand>,
+    /// 出力・無音の監視
+    pub monitor: Monitor,
+    /// 起動したコマンド（再起動用）
+    launch: PaneCommand,
+    /// 起動したときに指定した作業ディレクトリ（再起動用）
+    launch_cwd: Option<PathBuf>,
+    /// シェルを起動した時刻
+    started: Instant,
+    /// パーサースレッドとの合図
+    parser: Arc<ParserSignals>,
+    /// シェルの終了を取り出したか
+    exited: bool,
+    /// 最後にイベントで知らせたタイトル
+    reported_title: String,
+    /// プロファイルのタイトルかコンテナの名前（タイトルの前に付ける）
+    label: Option<String>,
+    /// 録画（パーサースレッドが出力を書き込む）
+    recorder: SharedRecorder,
+    /// 参照テストの記録（`--ref-test`、パーサースレッドが出力を書き込む）
+    ref_test: SharedRefRecorder,
+}
+
+/// シェルに追加する環境変数（リモート操作の接続先とペインのID）
+#[cfg(unix)]
+fn shell_env(id: PaneId) -> Vec<(String, String)> {
+    crate::control::pane_env(id)
+}
+
+#[cfg(not(unix))]
+fn shell_env(_id: PaneId) -> Vec<(String, String)> {
+    Vec::new()
 }
 
 impl Pane {
-    /// 新しいペインを作成
-    pub fn new(cols: u16, rows: u16) -> Result<Self> {
+    /// 指定したコマンドを実行するペインを作成
+    pub fn with_command(cols: u16, rows: u16, command: &PaneCommand) -> Result<Self> {
+        Self::with_cwd(cols, rows, command, None)
+    }
+
+    /// 作業ディレクトリを指定してコマンドを実行するペインを作成（None でプロファイルかホーム）
+    pub fn with_cwd(cols: u16, rows: u16, command: &PaneCommand, cwd: Option<&Path>) -> Result<Self> {
+        let id = PaneId::new();
+        let pty = Pty::open(cols, rows, command, cwd, &shell_env(id))?;
+        let ref_test = reftest::take_armed(cols as usize, rows as usize);
+        let mut pane = Self::with_pty(id, cols, rows, pty, command.clone(), ref_test)?;
+        pane.launch_cwd = cwd.map(Path::to_path_buf);
+        Ok(pane)
+    }
+
+    /// デーモン上のデタッチ中のセッションにアタッチするペインを作成
+    /// （起動したコマンドは分からないので、再起動するとログインシェルになる）
+    #[cfg(unix)]
+    pub fn attach(session: u64, cols: u16, rows: u16) -> Result<Self> {
+        Self::with_pty(PaneId::new(), cols, rows, crate::session::attach(session, cols, rows)?, PaneCommand::Shell, None)
+    }
+
+    /// 開いたPTYでペインを作成
+    /// （ref_test があれば最初の出力から参照テストの記録をする）
+    fn with_pty(
+        id: PaneId,
+        cols: u16,
+        rows: u16,
+        pty: Pty,
+        launch: PaneCommand,
+        ref_test: Option<RefRecorder>,
+    ) -> Result<Self> {
         let terminal = Arc::new(Mutex::new(Terminal::new(cols as usize, rows as usize)));
-        let pty = Pty::spawn(cols, rows, None)?;
+        let processed = Arc::new(AtomicUsize::new(0));
+        let parser = Arc::new(ParserSignals::default());
+        let recorder = SharedRecorder::default();
+        let ref_test = Arc::new(Mutex::new(ref_test));
+        spawn_parser(
+            pty.output_receiver(),
+            pty.input_sender(),
+            terminal.clone(),
+            processed.clone(),
+            parser.clone(),
+            recorder.clone(),
+            ref_test.clone(),
+        )?;
         let now = Instant::now();
+        let config = config::current();
+        let mut snapshot = TerminalSnapshot::new();
+        snapshot.palette = launch.profile().and_then(|name| config.profile_palettes.get(name).cloned()).map(Arc::new);
+        terminal.lock().set_scrollback_limits(Limits::from_config(&config, launch.profile()));
+        terminal.lock().wsl_distro = launch.wsl(&config).map(String::from);
+        let label = launch_label(&config, &launch);
 
         Ok(Self {
-            id: PaneId::new(),
+            id,
             terminal,
-            parser: AnsiParser::new(),
+            processed,
+            snapshot,
             pty,
             last_frame: now,
             last_output: now,
             dirty: true, // 初期状態は描画が必要
+            bell: false,
+            command: None,
+            command_checked: None,
+            command_started: None,
+            finished: None,
+            done: None,
+            monitor: Monitor::new(now),
+            launch,
+            launch_cwd: None,
+            started: now,
+            parser,
+            exited: false,
+            reported_title: String::new(),
+            label,
+            recorder,
+            ref_test,
         })
+        .inspect(|pane| events::emit(Event::PaneCreated { pane: pane.id.0 }))
     }
 
-    /// フレームを更新（PTYからの出力を読み取り）
-    /// 戻り値: 出力があったかどうか
-    pub fn update(&mut self) -> bool {
-        if let Some(data) = self.pty.read() {
-            let mut terminal = self.terminal.lock();
-            self.parser.process(&mut terminal, &data);
+    /// フレームを更新（パーサースレッドの進捗を取り込む）
+    /// 戻り値: 前回から解析されたバイト数（出力がなければ0）
+    pub fn update(&mut self) -> usize {
+        let bytes = self.processed.swap(0, Ordering::AcqRel);
+        if bytes > 0 {
+            self.last_output = Instant::now();
+            self.dirty = true;
+            let marks = {
+                let mut terminal = self.terminal.lock();
+                self.bell |= terminal.take_bell();
+                terminal.take_shell_marks()
+            };
+            self.track_commands(&marks);
+        }
+        bytes
+    }
 
-            // DSR等の応答があればPTYに送信
-            if let Some(response) = terminal.take_response() {
-                let _ = self.pty.write(&response);
+    /// シェル統合のマークからコマンドの開始・終了を追う
+    fn track_commands(&mut self, marks: &[ShellMark]) {
+        for mark in marks {
+            match mark {
+                ShellMark::OutputStart => self.command_started = Some(Instant::now()),
+                ShellMark::CommandFinished(exit_code) => {
+                    // 開始が分からない終了（空のコマンドラインなど）は無視
+                    if let Some(started) = self.command_started.take() {
+                        self.finished = Some(FinishedCommand {
+                            exit_code: *exit_code,
+                            duration: started.elapsed(),
+                        });
+                    }
+                }
+                ShellMark::PromptStart | ShellMark::CommandStart => {}
             }
+        }
+    }
 
-            self.last_output = Instant::now();
+    /// 終了したコマンドを取り出す
+    pub fn take_finished(&mut self) -> Option<FinishedCommand> {
+        self.finished.take()
+    }
+
+    /// シェルが終了し、出力も最後まで解析し終えたら、その終了を1回だけ取り出す
+    pub fn take_exited(&mut self) -> Option<ChildExit> {
+        if self.exited || !self.parser.done.load(Ordering::Acquire) {
+            return None;
+        }
+        let exit = self.pty.exit()?;
+        self.exited = true;
+        Some(exit)
+    }
+
+    /// 終了したシェルを再起動してよいか（起動直後に終了したものは再起動しない）
+    pub fn can_restart(&self) -> bool {
+        self.started.elapsed() >= MIN_RESTART_UPTIME
+    }
+
+    /// 終了メッセージを表示してペインを残す
+    pub fn hold(&mut self, exit: &ChildExit) {
+        self.write_notice(&exit.message());
+    }
+
+    /// 同じコマンドを同じ大きさで起動し直す（画面とスクロールバックはそのまま）
+    /// まだ動いていれば前のプロセスは終了させる
+    pub fn restart(&mut self) -> Result<()> {
+        let (cols, rows) = self.pty.size();
+        let pty = Pty::open(cols, rows, &self.launch, self.launch_cwd.as_deref(), &shell_env(self.id))?;
+
+        // 前のパーサーを止めてから古いPTYを閉じる（まだ動いていれば終了させる）
+        // 止めたパーサーはターミナルのロックの中で確かめるので、下のお知らせより後に古い出力は書かれない
+        self.parser.stop.store(true, Ordering::Release);
+        self.pty.terminate();
+        self.pty = pty;
+        self.parser = Arc::new(ParserSignals::default());
+        self.exited = false;
+        self.started = Instant::now();
+        self.command = None;
+        self.command_checked = None;
+        self.command_started = None;
+
+        self.terminal.lock().scroll_to_bottom();
+        self.write_notice(&format!("{}[process restarted]", MODE_RESET));
+
+        // 新しいプロセスの出力はお知らせの後から解析する
+        spawn_parser(
+            self.pty.output_receiver(),
+            self.pty.input_sender(),
+            self.terminal.clone(),
+            self.processed.clone(),
+            self.parser.clone(),
+            self.recorder.clone(),
+            self.ref_test.clone(),
+        )
+    }
+
+    /// 起動したコマンドと、起動したときに指定した作業ディレクトリ
+    pub fn launch(&self) -> (&PaneCommand, Option<&Path>) {
+        (&self.launch, self.launch_cwd.as_deref())
+    }
+
+    /// クラッシュ前のスクロールバックの末尾を書き込む（シェルの出力より前に、区切りのお知らせを付けて）
+    pub fn write_restored(&mut self, lines: &[String]) {
+        if lines.is_empty() {
+            return;
+        }
+        let mut terminal = self.terminal.lock();
+        let mut parser = AnsiParser::new();
+        for line in lines {
+            parser.process(&mut terminal, line.as_bytes());
+            parser.process(&mut terminal, b"\r\n");
+        }
+        drop(terminal);
+        self.write_notice("── restored after a crash ──");
+    }
+
+    /// ターミナルにお知らせの行を書き込む（淡い色で、前の出力と行を分ける）
+    fn write_notice(&mut self, text: &str) {
+        let mut terminal = self.terminal.lock();
+        AnsiParser::new().process(&mut terminal, format!("\x1b[0m\r\n\x1b[2m{}\x1b[0m\r\n", text).as_bytes());
+        self.dirty = true;
+    }
+
+    /// ベルを鳴らす（出力のトリガーから）
+    pub fn ring_bell(&mut self) {
+        self.bell = true;
+    }
+
+    /// ベルが鳴ったかを取り出してクリア
+    pub fn take_bell(&mut self) -> bool {
+        std::mem::take(&mut self.bell)
+    }
+
+    /// タイトルが前回知らせたときから変わっていれば取り出す（イベントの購読者がいるとき、出力のあとで調べる）
+    pub fn take_title_change(&mut self) -> Option<String> {
+        let title = self.title().name;
+        (title != self.reported_title).then(|| {
+            self.reported_title = title.clone();
+            title
+        })
+    }
+
+    /// 録画中か
+    pub fn is_recording(&self) -> bool {
+        self.recorder.lock().is_some()
+    }
+
+    /// 参照テストの記録中か（`--ref-test`）
+    pub fn is_ref_testing(&self) -> bool {
+        self.ref_test.lock().is_some()
+    }
+
+    /// 出力の録画を始める（asciicast v2）
+    pub fn start_recording(&mut self, path: &Path) -> Result<()> {
+        let (cols, rows) = self.pty.size();
+        let recorder = Recorder::create(path, cols, rows, &self.title().name)?;
+        *self.recorder.lock() = Some(recorder);
+        Ok(())
+    }
+
+    /// 録画をやめる
+    /// 戻り値: 保存したファイル（録画していなければ None）
+    pub fn stop_recording(&mut self) -> Result<Option<PathBuf>> {
+        let Some(recorder) = self.recorder.lock().take() else { return Ok(None) };
+        let path = recorder.path().map(Path::to_path_buf);
+        recorder.finish()?;
+        Ok(path)
+    }
+
+    /// プロファイルのテーマを読み込み直す（設定ファイルが変わったとき）
+    pub fn refresh_palette(&mut self, config: &Config) {
+        let palette = self.launch.profile().and_then(|name| config.profile_palettes.get(name));
+        if self.snapshot.palette.as_deref() != palette {
+            self.snapshot.palette = palette.cloned().map(Arc::new);
             self.dirty = true;
-            true
-        } else {
-            false
         }
     }
 
+    /// プロファイルのタイトルを読み込み直す（設定ファイルが変わったとき）
+    pub fn refresh_label(&mut self, config: &Config) {
+        self.label = launch_label(config, &self.launch);
+    }
+
+    /// スクロールバックの上限を設定に合わせる（設定ファイルが変わったとき）
+    pub fn refresh_scrollback(&mut self, config: &Config) {
+        let limits = Limits::from_config(config, self.launch.profile());
+        self.terminal.lock().set_scrollback_limits(limits);
+    }
+
+    /// 変更された行をスナップショットに取り込む（ロックは行のコピー中のみ）
+    pub fn refresh_snapshot(&mut self) {
+        let _span = tracing::trace_span!("snapshot").entered();
+        let mut terminal = self.terminal.lock();
+        self.snapshot.update(&mut terminal);
+    }
+
+    /// 実行中のコマンド名を調べ直す（前回から出力があり、一定時間たったときだけ）
+    pub fn refresh_command(&mut self) {
+        let now = Instant::now();
+        if let Some(checked) = self.command_checked {
+            if checked >= self.last_output || now - checked < COMMAND_REFRESH_INTERVAL {
+                return;
+            }
+        }
+        self.command_checked = Some(now);
+        self.command = self.pty.foreground_command();
+    }
+
+    /// 表示用のタイトル
+    pub fn title(&self) -> PaneTitle {
+        let terminal = self.terminal.lock();
+        let home = config::home_dir();
+        PaneTitle {
+            name: display_name(self.label.as_deref(), &terminal.title, self.command.as_deref()),
+            cwd: shorten_home(&terminal.cwd, home.as_deref()),
+        }
+    }
+
+    /// 取り込んでいない出力があるか（前回の update 以降に解析された）
+    #[inline]
+    pub fn has_pending_output(&self) -> bool {
+        self.processed.load(Ordering::SeqCst) > 0
+    }
+
     /// アイドル状態かどうか（指定時間出力がない）
     #[inline]
     pub fn is_idle(&self, idle_threshold_ms: u64) -> bool {
@@ -161,20 +774,149 @@ impl Pane {
 
     /// ダーティフラグをクリア
     #[inline]
+    #[allow(dead_code)]
     pub fn clear_dirty(&mut self) {
         self.dirty = false;
     }
 
+    /// シェル以外に実行中のコマンドがあればその名前（閉じる前の確認用）
+    ///
+    /// シェル以外を起動したペイン（コマンド・SSH・コンテナ）は、終了するまで常に実行中とみなす
+    pub fn running_command(&self) -> Option<String> {
+        if let Some(launched) = launched_command(&config::current(), &self.launch).filter(|_| self.pty.exit().is_none()) {
+            return Some(self.pty.running_command().or_else(|| self.pty.foreground_command()).unwrap_or(launched));
+        }
+        self.pty.running_command()
+    }
+
+    /// シェルを終了させる（閉じる操作。ウィンドウを閉じただけならデーモン上のセッションは残る）
+    pub fn terminate(&mut self) {
+        self.pty.terminate();
+    }
+
     /// リサイズ
     pub fn resize(&mut self, cols: u16, rows: u16) {
         {
             let mut terminal = self.terminal.lock();
+            // サイズが変わらなければシェルに通知しない
+            let grid = terminal.active_grid();
+            if (grid.cols, grid.rows) == (cols as usize, rows as usize) {
+                return;
+            }
             terminal.resize(cols as usize, rows as usize);
+            if let Some(ref_test) = self.ref_test.lock().as_mut() {
+                ref_test.resize(cols as usize, rows as usize);
+            }
         }
         let _ = self.pty.resize(cols, rows);
+        if let Some(recorder) = self.recorder.lock().as_mut() {
+            let _ = recorder.resize(cols, rows);
+        }
+    }
+}
+
+impl Drop for Pane {
+    fn drop(&mut self) {
+        // シェルが終了する前に閉じたら、その時点のグリッドで参照テストを書き出す
+        if self.is_ref_testing() {
+            reftest::finish(&self.ref_test, &self.terminal.lock());
+        }
+        events::emit(Event::PaneClosed { pane: self.id.0 });
     }
 }
 
+// ═══════════════════════════════════════════════════════════════════════════
+// パーサースレッド
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 1回のロックで解析する最大バイト数（描画側を長く待たせない）
+const MAX_PARSE_CHUNK: usize = 64 * 1024;
+
+/// パーサースレッドとペインの間の合図
+#[derive(Debug, Default)]
+struct ParserSignals {
+    /// PTYの出力を最後まで解析した
+    done: AtomicBool,
+    /// 止めるよう頼まれた（これより後の出力は解析しない）
+    stop: AtomicBool,
+}
+
+/// PTY出力を解析してターミナルに反映するスレッドを起動
+///
+/// PTYが閉じて出力チャネルが切断されるか、止められると終了する
+fn spawn_parser(
+    output_rx: Receiver<Vec<u8>>,
+    input_tx: Sender<Vec<u8>>,
+    terminal: Arc<Mutex<Terminal>>,
+    processed: Arc<AtomicUsize>,
+    signals: Arc<ParserSignals>,
+    recorder: SharedRecorder,
+    ref_test: SharedRefRecorder,
+) -> Result<()> {
+    std::thread::Builder::new()
+        .name("pty-parser".into())
+        .spawn(move || {
+            let mut parser = AnsiParser::new();
+
+            while let Ok(mut data) = output_rx.recv() {
+                // 溜まっている出力はまとめて解析（ロック回数を減らす）
+                while data.len() < MAX_PARSE_CHUNK {
+                    match output_rx.try_recv() {
+                        Ok(more) => data.extend(more),
+                        Err(_) => break,
+                    }
+                }
+
+                let response = {
+                    let _span = tracing::trace_span!("parse", bytes = data.len()).entered();
+                    let mut terminal = terminal.lock();
+                    // 止められたら残りの出力は捨てる（起動し直したペインに古い出力を書かない）
+                    if signals.stop.load(Ordering::Acquire) {
+                        return;
+                    }
+                    // 参照テストの記録はグリッドと食い違わないようロックの中で書く
+                    let mut ref_recording = ref_test.lock();
+                    if let Some(Err(e)) = ref_recording.as_mut().map(|recorder| recorder.output(&data)) {
+                        log::error!("参照テストの記録を書き出せないためやめました: {}", e);
+                        *ref_recording = None;
+                    }
+                    drop(ref_recording);
+                    parser.process(&mut terminal, &data);
+                    terminal.take_response()
+                };
+
+                // DSR等の応答があればPTYに送信
+                if let Some(response) = response {
+                    let _ = input_tx.send(response);
+                }
+
+                // 録画中なら出力をそのまま書き出す（書けなくなったら録画をやめる）
+                let mut recording = recorder.lock();
+                if let Some(Err(e)) = recording.as_mut().map(|recorder| recorder.output(&data)) {
+                    log::error!("録画を書き出せないためやめました: {}", e);
+                    *recording = None;
+                }
+                drop(recording);
+
+                processed.fetch_add(data.len(), Ordering::SeqCst);
+                // 省電力モードで眠っているイベントループを起こす
+                idle::notify_output();
+            }
+
+            // PTYが閉じた（シェルの終了は UI スレッドが拾う）
+            // 参照テストは終了のお知らせを書き込む前のグリッドで書き出す
+            let terminal = terminal.lock();
+            if signals.stop.load(Ordering::Acquire) {
+                return;
+            }
+            reftest::finish(&ref_test, &terminal);
+            drop(terminal);
+            signals.done.store(true, Ordering::Release);
+            idle::notify_output();
+        })?;
+    Ok(())
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 // ペインレイアウト
 // ═══════════════════════════════════════════════════════════════════════════
@@ -269,11 +1011,11 @@ impl PaneLayout {
                     }
                 }
                 if let Some(new_left) = left.remove_pane(target_id) {
-                    *left = Box::new(new_left);
+                    **left = new_left;
                     return Some(std::mem::replace(self, PaneLayout::Single(PaneId(0))));
                 }
                 if let Some(new_right) = right.remove_pane(target_id) {
-                    *right = Box::new(new_right);
+                    **right = new_right;
                     return Some(std::mem::replace(self, PaneLayout::Single(PaneId(0))));
                 }
                 None
@@ -290,11 +1032,11 @@ impl PaneLayout {
                     }
                 }
                 if let Some(new_top) = top.remove_pane(target_id) {
-                    *top = Box::new(new_top);
+                    **top = new_top;
                     return Some(std::mem::replace(self, PaneLayout::Single(PaneId(0))));
                 }
                 if let Some(new_bottom) = bottom.remove_pane(target_id) {
-                    *bottom = Box::new(new_bottom);
+                    **bottom = new_bottom;
                     return Some(std::mem::replace(self, PaneLayout::Single(PaneId(0))));
                 }
                 None
@@ -399,7 +1141,116 @@ impl PaneLayout {
         Some(ids[prev_idx])
     }
 
+    /// 2つのペインの位置を入れ替える（ペイン自体のPTYやスクロールバックはそのまま）
+    /// 戻り値: 入れ替えたか
+    pub fn swap_panes(&mut self, a: PaneId, b: PaneId) -> bool {
+        let ids = self.all_pane_ids();
+        if a == b || !ids.contains(&a) || !ids.contains(&b) {
+            return false;
+        }
+        self.swap_panes_inner(a, b);
+        true
+    }
+
+    fn swap_panes_inner(&mut self, a: PaneId, b: PaneId) {
+        match self {
+            PaneLayout::Single(id) => {
+                if *id == a {
+                    *id = b;
+                } else if *id == b {
+                    *id = a;
+                }
+            }
+            PaneLayout::HSplit { left, right, .. } => {
+                left.swap_panes_inner(a, b);
+                right.swap_panes_inner(a, b);
+            }
+            PaneLayout::VSplit { top, bottom, .. } => {
+                top.swap_panes_inner(a, b);
+                bottom.swap_panes_inner(a, b);
+            }
+        }
+    }
+
+    /// すべての分割を均等にする（同じ向きに並んだペインが同じ大きさになる）
+    pub fn balance(&mut self) {
+        self.balance_inner(true);
+    }
+
+    /// 指定したペインが属する段（同じ向きに続く分割）だけを均等にする
+    /// 戻り値: 均等にする分割があったか
+    pub fn balance_level(&mut self, target_id: PaneId) -> bool {
+        let horizontal = match self {
+            PaneLayout::Single(_) => return false,
+            PaneLayout::HSplit { .. } => true,
+            PaneLayout::VSplit { .. } => false,
+        };
+        // 同じ向きの分割だけをたどって届くなら、ここがその段の一番上
+        if self.reaches(target_id, horizontal) {
+            self.balance_inner(false);
+            return true;
+        }
+        match self {
+            PaneLayout::HSplit { left, right, .. } => {
+                left.balance_level(target_id) || right.balance_level(target_id)
+            }
+            PaneLayout::VSplit { top, bottom, .. } => {
+                top.balance_level(target_id) || bottom.balance_level(target_id)
+            }
+            PaneLayout::Single(_) => false,
+        }
+    }
+
+    /// recursive: 向きの違う子の分割も均等にするか
+    fn balance_inner(&mut self, recursive: bool) {
+        match self {
+            PaneLayout::Single(_) => {}
+            PaneLayout::HSplit { left, right, ratio } => {
+                let (l, r) = (left.weight(true), right.weight(true));
+                *ratio = l as f32 / (l + r) as f32;
+                for child in [left, right] {
+                    if recursive || matches!(**child, PaneLayout::HSplit { .. }) {
+                        child.balance_inner(recursive);
+                    }
+                }
+            }
+            PaneLayout::VSplit { top, bottom, ratio } => {
+                let (t, b) = (top.weight(false), bottom.weight(false));
+                *ratio = t as f32 / (t + b) as f32;
+                for child in [top, bottom] {
+                    if recursive || matches!(**child, PaneLayout::VSplit { .. }) {
+                        child.balance_inner(recursive);
+                    }
+                }
+            }
+        }
+    }
+
+    /// 同じ向きに並ぶペインの数（均等にするときの重み）
+    fn weight(&self, horizontal: bool) -> usize {
+        match self {
+            PaneLayout::HSplit { left, right, .. } if horizontal => left.weight(true) + right.weight(true),
+            PaneLayout::VSplit { top, bottom, .. } if !horizontal => top.weight(false) + bottom.weight(false),
+            _ => 1,
+        }
+    }
+
+    /// 同じ向きの分割だけをたどって指定したペインに届くか
+    fn reaches(&self, target_id: PaneId, horizontal: bool) -> bool {
+        match self {
+            PaneLayout::Single(id) => *id == target_id,
+            PaneLayout::HSplit { left, right, .. } if horizontal => {
+                left.reaches(target_id, true) || right.reaches(target_id, true)
+            }
+            PaneLayout::VSplit { top, bottom, .. } if !horizontal => {
+                top.reaches(target_id, false) || bottom.reaches(target_id, false)
+            }
+            _ => false,
+        }
+    }
+
     /// ペイン数を取得
+    #[allow(dead_code)]
     pub fn pane_count(&self) -> usize {
         self.all_pane_ids().len()
     }
@@ -568,6 +1419,101 @@ impl PaneLayout {
     }
 }
 
+// ═══════════════════════════════════════════════════════════════════════════
+// プリセットレイアウト
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// メインペインの比率（main-vertical / main-horizontal）
+const MAIN_PANE_RATIO: f32 = 0.6;
+
+/// 名前付きのレイアウト（tmux の select-layout 相当）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutPreset {
+    /// 左にメインペイン、右に残りを上下に並べる
+    MainVertical,
+    /// 上にメインペイン、下に残りを左右に並べる
+    MainHorizontal,
+    /// 格子状（4ペインなら2x2）
+    Grid,
+    /// すべて左右に並べる
+    Columns,
+}
+
+impl LayoutPreset {
+    /// すべてのプリセット
+    pub const ALL: [LayoutPreset; 4] = [
+        LayoutPreset::MainVertical,
+        LayoutPreset::MainHorizontal,
+        LayoutPreset::Grid,
+        LayoutPreset::Columns,
+    ];
+
+    /// 名前
+    pub fn name(self) -> &'static str {
+        match self {
+            LayoutPreset::MainVertical => "main-vertical",
+            LayoutPreset::MainHorizontal => "main-horizontal",
+            LayoutPreset::Grid => "grid",
+            LayoutPreset::Columns => "columns",
+        }
+    }
+
+    /// 名前から取得
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+
+    /// 指定したペインを並べたレイアウトを作る（先頭がメインペイン）
+    pub fn build(self, ids: &[PaneId]) -> Option<PaneLayout> {
+        let (&main, rest) = ids.split_first()?;
+        if rest.is_empty() {
+            return Some(PaneLayout::Single(main));
+        }
+        let layout = match self {
+            LayoutPreset::MainVertical => PaneLayout::HSplit {
+                left: Box::new(PaneLayout::Single(main)),
+                right: Box::new(even_chain(rest, false)),
+                ratio: MAIN_PANE_RATIO,
+            },
+            LayoutPreset::MainHorizontal => PaneLayout::VSplit {
+                top: Box::new(PaneLayout::Single(main)),
+                bottom: Box::new(even_chain(rest, true)),
+                ratio: MAIN_PANE_RATIO,
+            },
+            LayoutPreset::Grid => {
+                // 列数は平方根の切り上げ、最後の行は残りのペインで等分
+                let cols = (ids.len() as f32).sqrt().ceil() as usize;
+                let rows: Vec<PaneLayout> = ids.chunks(cols).map(|row| even_chain(row, true)).collect();
+                even_chain_of(rows, false)
+            }
+            LayoutPreset::Columns => even_chain(ids, true),
+        };
+        Some(layout)
+    }
+}
+
+/// ペインを同じ大きさで一列に並べる（horizontal: 左右に並べるか）
+fn even_chain(ids: &[PaneId], horizontal: bool) -> PaneLayout {
+    even_chain_of(ids.iter().map(|&id| PaneLayout::Single(id)).collect(), horizontal)
+}
+
+/// レイアウトを同じ大きさで一列に並べる（空でないこと）
+fn even_chain_of(mut items: Vec<PaneLayout>, horizontal: bool) -> PaneLayout {
+    let mut layout = items.pop().expect("並べるレイアウトがありません");
+    // 後ろから包んでいき、包むたびに先頭の比率を 1/(並んだ数) にする
+    let mut count = 1;
+    while let Some(item) = items.pop() {
+        count += 1;
+        let ratio = 1.0 / count as f32;
+        layout = if horizontal {
+            PaneLayout::HSplit { left: Box::new(item), right: Box::new(layout), ratio }
+        } else {
+            PaneLayout::VSplit { top: Box::new(item), bottom: Box::new(layout), ratio }
+        };
+    }
+    layout
+}
+
 /// 境界線の方向
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BorderDirection {
@@ -579,6 +1525,7 @@ pub enum BorderDirection {
 
 /// 境界線のヒット情報
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 pub enum BorderHit {
     /// 垂直境界線（左右分割の境界）
     Vertical {
@@ -617,3 +1564,264 @@ impl BorderHit {
         matches!(self, BorderHit::Vertical { .. })
     }
 }
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Named;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parser_thread() {
+        let (output_tx, output_rx) = crossbeam_channel::unbounded();
+        let (input_tx, input_rx) = crossbeam_channel::unbounded();
+        let terminal = Arc::new(Mutex::new(Terminal::new(20, 5)));
+        let processed = Arc::new(AtomicUsize::new(0));
+        let signals = Arc::new(ParserSignals::default());
+        let dir = std::env::temp_dir().join(format!("umiterm-pane-reftest-{}", std::process::id()));
+        let ref_test = Arc::new(Mutex::new(Some(RefRecorder::create(&dir, 20, 5).unwrap())));
+        spawn_parser(
+            output_rx,
+            input_tx,
+            terminal.clone(),
+            processed.clone(),
+            signals.clone(),
+            SharedRecorder::default(),
+            ref_test.clone(),
+        )
+        .unwrap();
+
+        // 通常の出力とカーソル位置の問い合わせ（DSR）
+        output_tx.send(b"Hi".to_vec()).unwrap();
+        output_tx.send(b"\x1b[6n".to_vec()).unwrap();
+
+        let response = input_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(response, b"\x1b[1;3R");
+
+        drop(output_tx);
+        while processed.load(Ordering::Acquire) < 6 {
+            std::thread::yield_now();
+        }
+        assert_eq!(terminal.lock().active_grid()[(1, 0)].character, 'i');
+
+        // 出力チャネルが閉じたら最後まで解析したことを知らせる
+        while !signals.done.load(Ordering::Acquire) {
+            std::thread::yield_now();
+        }
+
+        // 参照テストの記録は生の出力と、閉じたときのグリッドを書き出している
+        assert!(ref_test.lock().is_none());
+        assert_eq!(std::fs::read(dir.join("recording")).unwrap(), b"Hi\x1b[6n");
+        assert!(std::fs::read_to_string(dir.join("grid.json")).unwrap().contains("\"Hi\""));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // 止めたあとの出力は解析しない（起動し直したペインに古い出力を書かない）
+        let (output_tx, output_rx) = crossbeam_channel::unbounded();
+        let (input_tx, _input_rx) = crossbeam_channel::unbounded();
+        let signals = Arc::new(ParserSignals::default());
+        spawn_parser(
+            output_rx,
+            input_tx,
+            terminal.clone(),
+            processed.clone(),
+            signals.clone(),
+            SharedRecorder::default(),
+            Arc::default(),
+        )
+        .unwrap();
+        let stale = terminal.lock();
+        output_tx.send(b"\r\nstale".to_vec()).unwrap();
+        signals.stop.store(true, Ordering::Release);
+        drop(stale);
+        drop(output_tx);
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(processed.load(Ordering::Acquire), 6);
+        assert_eq!(terminal.lock().active_grid()[(0, 1)].character, ' ');
+        assert!(!signals.done.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn test_title_text() {
+        assert_eq!(display_name(None, "vim main.rs", Some("vim")), "vim main.rs");
+        assert_eq!(display_name(None, "", Some("htop")), "htop");
+        assert_eq!(display_name(None, "", None), "");
+        assert_eq!(display_name(Some("PROD"), "deploy@web1: ~", None), "PROD: deploy@web1: ~");
+        assert_eq!(display_name(Some("PROD"), "", None), "PROD");
+
+        let config = Config::parse("[profiles.prod]\nssh = \"prod\"\ntitle = \"PROD\"").unwrap();
+        assert_eq!(launch_label(&config, &PaneCommand::Profile("prod".into())).as_deref(), Some("PROD"));
+        assert_eq!(launch_label(&config, &PaneCommand::Container("web".into())).as_deref(), Some("web"));
+        assert_eq!(launch_label(&config, &PaneCommand::Wsl("Ubuntu".into())).as_deref(), Some("Ubuntu"));
+        assert_eq!(launch_label(&config, &PaneCommand::Shell), None);
+
+        // シェルの代わりに起動したコマンドは、閉じる前に確認する
+        assert_eq!(launched_command(&config, &PaneCommand::Run("htop".into())).as_deref(), Some("htop"));
+        assert_eq!(launched_command(&config, &PaneCommand::Container("web".into())).as_deref(), Some("web"));
+        assert_eq!(launched_command(&config, &PaneCommand::Profile("prod".into())).as_deref(), Some("ssh prod"));
+        assert_eq!(launched_command(&config, &PaneCommand::Shell), None);
+        assert_eq!(launched_command(&config, &PaneCommand::Wsl("Ubuntu".into())), None);
+
+        let home = Path::new("/Users/umi");
+        assert_eq!(shorten_home(Path::new("/Users/umi"), Some(home)), "~");
+        assert_eq!(shorten_home(Path::new("/Users/umi/src"), Some(home)), "~/src");
+        assert_eq!(shorten_home(Path::new("/tmp"), Some(home)), "/tmp");
+        assert_eq!(shorten_home(Path::new("/Users/umi2"), Some(home)), "/Users/umi2");
+
+        assert_eq!(PaneTitleMode::from_name("multi"), Some(PaneTitleMode::MultiPane));
+        assert!(!PaneTitleMode::MultiPane.visible(1));
+        assert!(PaneTitleMode::MultiPane.visible(2));
+        assert!(PaneTitleMode::Always.visible(1));
+
+        assert_eq!(ExitAction::from_name("hold"), Some(ExitAction::Hold));
+        assert_eq!(ExitAction::from_name("restart"), Some(ExitAction::Restart));
+        assert_eq!(ExitAction::from_name("keep"), None);
+        assert_eq!(ExitAction::Restart.name(), "restart");
+    }
+
+    #[test]
+    fn test_monitor() {
+        let start = Instant::now();
+        let silence = Duration::from_secs(30);
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut monitor = Monitor::new(start);
+
+        // 監視していなければ警告しない
+        assert_eq!(monitor.check(true, false, at(1), silence), None);
+
+        // 出力の監視: 見ていない間の出力で1回だけ警告し、見たら消える
+        assert!(monitor.toggle(MonitorKind::Activity, at(2)));
+        assert_eq!(monitor.check(true, false, at(3), silence), Some(MonitorKind::Activity));
+        assert_eq!(monitor.check(true, false, at(4), silence), None);
+        assert_eq!(monitor.alert, Some(MonitorKind::Activity));
+        assert_eq!(monitor.check(false, true, at(5), silence), None);
+        assert_eq!(monitor.alert, None);
+        assert!(!monitor.toggle(MonitorKind::Activity, at(6)));
+
+        // 無音の監視: 監視を始めてから数え、出力があるまで1回だけ警告
+        assert!(monitor.toggle(MonitorKind::Silence, at(10)));
+        assert_eq!(monitor.label(), Some("[sil]"));
+        assert_eq!(monitor.check(false, false, at(39), silence), None);
+        assert_eq!(monitor.check(false, false, at(40), silence), Some(MonitorKind::Silence));
+        assert_eq!(monitor.check(false, true, at(41), silence), None);
+        assert_eq!(monitor.check(false, false, at(80), silence), None);
+        assert_eq!(monitor.check(true, false, at(81), silence), None);
+        assert_eq!(monitor.check(false, false, at(111), silence), Some(MonitorKind::Silence));
+
+        // 監視をやめたらその警告も消える
+        assert!(!monitor.toggle(MonitorKind::Silence, at(112)));
+        assert_eq!(monitor.alert, None);
+        assert_eq!(monitor.label(), None);
+    }
+
+    #[test]
+    fn test_swap_panes() {
+        let (a, b, c) = (PaneId(1), PaneId(2), PaneId(3));
+        let mut layout = PaneLayout::single(a);
+        layout.split_horizontal(a, b);
+        layout.split_vertical(b, c);
+        let before = layout.calculate_rects(Rect::full());
+
+        // 位置（矩形）だけが入れ替わる
+        assert!(layout.swap_panes(a, c));
+        assert_eq!(layout.all_pane_ids(), vec![c, b, a]);
+        let after = layout.calculate_rects(Rect::full());
+        assert_eq!(after[0].1.width, before[0].1.width);
+        assert_eq!(after[2].1.y, before[2].1.y);
+
+        // 同じペインや存在しないペインとは入れ替えない
+        assert!(!layout.swap_panes(a, a));
+        assert!(!layout.swap_panes(a, PaneId(9)));
+        assert_eq!(layout.all_pane_ids(), vec![c, b, a]);
+    }
+
+    /// 左右に a | b | (c / d) と並べたレイアウトの比率を偏らせて作る
+    fn uneven_layout() -> PaneLayout {
+        let (a, b, c, d) = (PaneId(1), PaneId(2), PaneId(3), PaneId(4));
+        let mut layout = PaneLayout::single(a);
+        layout.split_horizontal(a, b);
+        layout.split_horizontal(b, c);
+        layout.split_vertical(c, d);
+        layout.update_ratio(&[], 0.7);
+        layout.update_ratio(&[BorderDirection::Right], 0.2);
+        layout.update_ratio(&[BorderDirection::Right, BorderDirection::Right], 0.8);
+        layout
+    }
+
+    fn widths_and_heights(layout: &PaneLayout) -> Vec<(f32, f32)> {
+        layout
+            .calculate_rects(Rect::full())
+            .iter()
+            .map(|(_, r)| ((r.width * 100.0).round(), (r.height * 100.0).round()))
+            .collect()
+    }
+
+    #[test]
+    fn test_balance() {
+        let mut layout = uneven_layout();
+        layout.balance();
+
+        // 3列が同じ幅、右端の列は上下に等分
+        assert_eq!(
+            widths_and_heights(&layout),
+            vec![(33.0, 100.0), (33.0, 100.0), (33.0, 50.0), (33.0, 50.0)]
+        );
+    }
+
+    #[test]
+    fn test_balance_level() {
+        // c の段（上下分割）だけが均等になる
+        let mut layout = uneven_layout();
+        assert!(layout.balance_level(PaneId(3)));
+        assert_eq!(
+            widths_and_heights(&layout),
+            vec![(70.0, 100.0), (6.0, 100.0), (24.0, 50.0), (24.0, 50.0)]
+        );
+
+        // a の段（左右に並んだ3列）だけが均等になる
+        let mut layout = uneven_layout();
+        assert!(layout.balance_level(PaneId(1)));
+        assert_eq!(
+            widths_and_heights(&layout),
+            vec![(33.0, 100.0), (33.0, 100.0), (33.0, 80.0), (33.0, 20.0)]
+        );
+
+        // 分割がなければ何もしない
+        assert!(!PaneLayout::single(PaneId(1)).balance_level(PaneId(1)));
+    }
+
+    #[test]
+    fn test_layout_presets() {
+        let ids: Vec<PaneId> = (1..=4).map(PaneId).collect();
+        let build = |preset: LayoutPreset, n: usize| {
+            let layout = preset.build(&ids[..n]).unwrap();
+            // どのプリセットでもペインの数と順番は変わらない
+            assert_eq!(layout.all_pane_ids(), ids[..n].to_vec());
+            widths_and_heights(&layout)
+        };
+
+        assert_eq!(
+            build(LayoutPreset::MainVertical, 3),
+            vec![(60.0, 100.0), (40.0, 50.0), (40.0, 50.0)]
+        );
+        assert_eq!(
+            build(LayoutPreset::MainHorizontal, 3),
+            vec![(100.0, 60.0), (50.0, 40.0), (50.0, 40.0)]
+        );
+        assert_eq!(build(LayoutPreset::Grid, 4), vec![(50.0, 50.0); 4]);
+        assert_eq!(
+            build(LayoutPreset::Grid, 3),
+            vec![(50.0, 50.0), (50.0, 50.0), (100.0, 50.0)]
+        );
+        assert_eq!(build(LayoutPreset::Columns, 3), vec![(33.0, 100.0); 3]);
+        assert_eq!(build(LayoutPreset::Columns, 1), vec![(100.0, 100.0)]);
+        assert!(LayoutPreset::Grid.build(&[]).is_none());
+
+        for preset in LayoutPreset::ALL {
+            assert_eq!(LayoutPreset::from_name(preset.name()), Some(preset));
+        }
+    }
+}
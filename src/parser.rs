@@ -7,17 +7,43 @@ use std::path::PathBuf;
 use vte::{Params, Parser, Perform};
 
 use crate::grid::{CellFlags, Color};
-use crate::terminal::{CursorShape, Terminal, TerminalMode};
+use crate::terminal::{CursorShape, ShellMark, Terminal, TerminalMode};
+use crate::wsl;
 
 // ═══════════════════════════════════════════════════════════════════════════
 // パーサー構造体
 // ═══════════════════════════════════════════════════════════════════════════
 
+/// 警告を出した未対応のシーケンス（同じものは2回目からデバッグのログにする）
+static UNSUPPORTED: parking_lot::Mutex<Vec<String>> = parking_lot::Mutex::new(Vec::new());
+
+/// 覚えておく未対応のシーケンスの数（超えたらすべてデバッグのログにする）
+const UNSUPPORTED_LIMIT: usize = 256;
+
+/// 未対応のシーケンスを知らせる（ログビューアに出るよう、種類ごとに最初の1回だけ警告にする）
+fn unsupported(sequence: String) {
+    let first = {
+        let mut seen = UNSUPPORTED.lock();
+        let first = seen.len() < UNSUPPORTED_LIMIT && !seen.contains(&sequence);
+        if first {
+            seen.push(sequence.clone());
+        }
+        first
+    };
+    if first {
+        log::warn!("{}", sequence);
+    } else {
+        log::debug!("{}", sequence);
+    }
+}
+
 /// ANSIパーサー
 /// vteパーサーとターミナルをつなぐアダプター
 pub struct AnsiParser {
     /// vte パーサー（状態マシン）
     parser: Parser,
+    /// vte が Ground 状態にあることが確定しているか
+    ground: bool,
 }
 
 impl AnsiParser {
@@ -25,15 +51,43 @@ impl AnsiParser {
     pub fn new() -> Self {
         Self {
             parser: Parser::new(),
+            ground: true,
         }
     }
 
     /// バイト列をパースしてターミナルに適用
+    ///
+    /// Ground 状態では印字可能なASCIIの連続を vte を通さずまとめて書き込む
+    /// （大量出力時の1バイトごとのオーバーヘッドを削減）
     pub fn process(&mut self, terminal: &mut Terminal, data: &[u8]) {
-        let mut performer = TerminalPerformer { terminal };
-        for byte in data {
-            self.parser.advance(&mut performer, *byte);
+        let mut performer = TerminalPerformer {
+            terminal,
+            ground: self.ground,
+            was_ground: self.ground,
+        };
+
+        let mut i = 0;
+        while i < data.len() {
+            if performer.ground {
+                let run = data[i..]
+                    .iter()
+                    .position(|b| !(0x20..=0x7e).contains(b))
+                    .unwrap_or(data.len() - i);
+                if run > 0 {
+                    performer.terminal.input_ascii(&data[i..i + run]);
+                    i += run;
+                    continue;
+                }
+            }
+
+            // 制御文字・エスケープシーケンス・UTF-8は vte で処理
+            performer.was_ground = performer.ground;
+            performer.ground = false;
+            self.parser.advance(&mut performer, data[i]);
+            i += 1;
         }
+
+        self.ground = performer.ground;
     }
 }
 
@@ -99,11 +153,18 @@ fn url_decode(s: &str) -> String {
 /// パーサーからのコールバックを受け取り、ターミナルを操作
 struct TerminalPerformer<'a> {
     terminal: &'a mut Terminal,
+    /// 直前のバイトで Ground 状態に戻ったことが確定したか
+    /// （print / CSI・ESC のディスパッチ後は必ず Ground、判定できない場合は false）
+    ground: bool,
+    /// 直前のバイトを渡す前に Ground 状態だったか
+    was_ground: bool,
 }
 
 impl<'a> Perform for TerminalPerformer<'a> {
     /// 通常の文字を処理
     fn print(&mut self, c: char) {
+        self.ground = true;
+
         // †（U+2020）などの特殊文字をスキップ（Claude Code等が送信する場合がある）
         if c == '\u{2020}' || c == '\u{2021}' {
             return;
@@ -113,11 +174,13 @@ impl<'a> Perform for TerminalPerformer<'a> {
 
     /// 制御文字を処理（C0/C1）
     fn execute(&mut self, byte: u8) {
+        // Ground 状態での制御文字は状態を変えない
+        self.ground = self.was_ground;
         match byte {
-            0x07 => {} // BEL (ベル) - 無視
+            0x07 => self.terminal.ring_bell(), // BEL (ベル)
             0x08 => self.terminal.backspace(),
             0x09 => self.terminal.tab(),
-            0x0A | 0x0B | 0x0C => self.terminal.linefeed(),
+            0x0A..=0x0C => self.terminal.linefeed(),
             0x0D => self.terminal.carriage_return(),
             _ => {}
         }
@@ -131,6 +194,7 @@ impl<'a> Perform for TerminalPerformer<'a> {
         _ignore: bool,
         action: char,
     ) {
+        self.ground = true;
         // DEC private mode（?がある場合）
         let is_private = intermediates.contains(&b'?');
         // パラメータを Vec に変換（複数のパラメータに対応）
@@ -183,7 +247,8 @@ impl<'a> Perform for TerminalPerformer<'a> {
             'G' => {
                 // CHA: カーソルを指定列に移動
                 let col = get(0, 1).saturating_sub(1);
-                self.terminal.cursor.col = col;
+                let row = self.terminal.cursor.row;
+                self.terminal.move_cursor_to(col, row);
             }
             'H' | 'f' => {
                 // CUP: カーソルを指定位置に移動
@@ -200,7 +265,8 @@ impl<'a> Perform for TerminalPerformer<'a> {
                 match get(0, 0) {
                     0 => self.terminal.erase_display_to_end(),
                     1 => self.terminal.erase_display_to_start(),
-                    2 | 3 => self.terminal.erase_display(),
+                    2 => self.terminal.erase_display(),
+                    3 => self.terminal.clear_scrollback(), // スクロールバックを消去
                     _ => {}
                 }
             }
@@ -238,12 +304,18 @@ impl<'a> Perform for TerminalPerformer<'a> {
             // ─────────────────────────────────────────────────────────────────
             'r' => {
                 // DECSTBM: スクロール領域を設定
+                // xterm と同じく、0 は省略と同じ扱いで下端は画面内に丸め、2行に満たない領域は無視する
                 let rows = self.terminal.active_grid().rows;
-                let top = get(0, 1).saturating_sub(1);
-                let bottom = get(1, rows as u16) as usize;
-                self.terminal.scroll_top = top;
-                self.terminal.scroll_bottom = bottom.saturating_sub(1).min(rows - 1);
-                self.terminal.move_cursor_to(0, 0);
+                let top = get(0, 1).max(1) - 1;
+                let bottom = match get(1, 0) {
+                    0 => rows,
+                    bottom => bottom.min(rows),
+                } - 1;
+                if top < bottom {
+                    self.terminal.scroll_top = top;
+                    self.terminal.scroll_bottom = bottom;
+                    self.terminal.move_cursor_to(0, 0);
+                }
             }
 
             // ─────────────────────────────────────────────────────────────────
@@ -263,12 +335,13 @@ impl<'a> Perform for TerminalPerformer<'a> {
             // ─────────────────────────────────────────────────────────────────
             'q' => {
                 // DECSCUSR: カーソル形状を設定
+                // 0 は設定ファイルの形状に戻す
                 let shape = match get(0, 0) {
-                    0 | 1 => CursorShape::Block,
-                    2 => CursorShape::Block,
-                    3 | 4 => CursorShape::Underline,
-                    5 | 6 => CursorShape::Beam,
-                    _ => CursorShape::Block,
+                    0 => None,
+                    1 | 2 => Some(CursorShape::Block),
+                    3 | 4 => Some(CursorShape::Underline),
+                    5 | 6 => Some(CursorShape::Beam),
+                    _ => None,
                 };
                 self.terminal.cursor.shape = shape;
             }
@@ -291,7 +364,7 @@ impl<'a> Perform for TerminalPerformer<'a> {
             }
 
             _ => {
-                log::debug!("未対応のCSI: {}", action);
+                unsupported(format!("未対応のCSI: {}", action));
             }
         }
     }
@@ -309,24 +382,43 @@ impl<'a> Perform for TerminalPerformer<'a> {
 
         match code_num {
             // ウィンドウタイトル
-            0 | 2 => {
-                if params.len() > 1 {
-                    if let Ok(title) = std::str::from_utf8(params[1]) {
-                        self.terminal.title = title.to_string();
-                    }
+            0 | 2 if params.len() > 1 => {
+                if let Ok(title) = std::str::from_utf8(params[1]) {
+                    self.terminal.title = title.to_string();
                 }
             }
             // 現在の作業ディレクトリ（OSC 7）
             // 形式: file://hostname/path または file:///path
-            7 => {
-                if params.len() > 1 {
-                    if let Ok(url) = std::str::from_utf8(params[1]) {
-                        if let Some(path) = parse_osc7_path(url) {
-                            self.terminal.cwd = path;
-                        }
+            7 if params.len() > 1 => {
+                if let Ok(url) = std::str::from_utf8(params[1]) {
+                    if let Some(path) = parse_osc7_path(url) {
+                        self.terminal.cwd = match &self.terminal.wsl_distro {
+                            Some(distro) => wsl::to_windows_path(distro, &path.to_string_lossy()),
+                            None => path,
+                        };
                     }
                 }
             }
+            // シェル統合（OSC 133）: プロンプト・コマンドの区切りと終了コード
+            // 形式: 133;A / 133;B / 133;C / 133;D;終了コード
+            133 if params.len() > 1 => {
+                let mark = match params[1] {
+                    b"A" => Some(ShellMark::PromptStart),
+                    b"B" => Some(ShellMark::CommandStart),
+                    b"C" => Some(ShellMark::OutputStart),
+                    b"D" => {
+                        let exit_code = params
+                            .get(2)
+                            .and_then(|p| std::str::from_utf8(p).ok())
+                            .and_then(|s| s.parse().ok());
+                        Some(ShellMark::CommandFinished(exit_code))
+                    }
+                    _ => None,
+                };
+                if let Some(mark) = mark {
+                    self.terminal.push_shell_mark(mark);
+                }
+            }
             // その他のOSCは無視
             _ => {}
         }
@@ -343,6 +435,7 @@ impl<'a> Perform for TerminalPerformer<'a> {
 
     /// ESC シーケンス
     fn esc_dispatch(&mut self, _intermediates: &[u8], _ignore: bool, byte: u8) {
+        self.ground = true;
         match byte {
             b'7' => self.terminal.save_cursor(),    // DECSC
             b'8' => self.terminal.restore_cursor(), // DECRC
@@ -369,8 +462,8 @@ impl<'a> TerminalPerformer<'a> {
     fn handle_sgr(&mut self, params: &[u16]) {
         if params.is_empty() {
             // パラメータなしはリセット
-            self.terminal.current_style.fg = Color::EMERALD;
-            self.terminal.current_style.bg = Color::BLACK;
+            self.terminal.current_style.fg = Color::DEFAULT_FG;
+            self.terminal.current_style.bg = Color::DEFAULT_BG;
             self.terminal.current_style.flags = CellFlags::empty();
             return;
         }
@@ -380,8 +473,8 @@ impl<'a> TerminalPerformer<'a> {
             match params[i] {
                 // リセット
                 0 => {
-                    self.terminal.current_style.fg = Color::EMERALD;
-                    self.terminal.current_style.bg = Color::BLACK;
+                    self.terminal.current_style.fg = Color::DEFAULT_FG;
+                    self.terminal.current_style.bg = Color::DEFAULT_BG;
                     self.terminal.current_style.flags = CellFlags::empty();
                 }
                 // スタイル設定
@@ -401,14 +494,7 @@ impl<'a> TerminalPerformer<'a> {
                 28 => self.terminal.current_style.flags.remove(CellFlags::HIDDEN),
                 29 => self.terminal.current_style.flags.remove(CellFlags::STRIKEOUT),
                 // 前景色（標準8色）
-                30 => self.terminal.current_style.fg = Color::BLACK,
-                31 => self.terminal.current_style.fg = Color::RED,
-                32 => self.terminal.current_style.fg = Color::GREEN,
-                33 => self.terminal.current_style.fg = Color::YELLOW,
-                34 => self.terminal.current_style.fg = Color::BLUE,
-                35 => self.terminal.current_style.fg = Color::MAGENTA,
-                36 => self.terminal.current_style.fg = Color::CYAN,
-                37 => self.terminal.current_style.fg = Color::WHITE,
+                30..=37 => self.terminal.current_style.fg = Color::indexed((params[i] - 30) as u8),
                 // 拡張前景色
                 38 => {
                     if let Some(color) = self.parse_extended_color(&params[i..]) {
@@ -416,16 +502,9 @@ impl<'a> TerminalPerformer<'a> {
                         i += self.extended_color_params(&params[i..]);
                     }
                 }
-                39 => self.terminal.current_style.fg = Color::EMERALD, // デフォルト前景色
+                39 => self.terminal.current_style.fg = Color::DEFAULT_FG, // デフォルト前景色
                 // 背景色（標準8色）
-                40 => self.terminal.current_style.bg = Color::BLACK,
-                41 => self.terminal.current_style.bg = Color::RED,
-                42 => self.terminal.current_style.bg = Color::GREEN,
-                43 => self.terminal.current_style.bg = Color::YELLOW,
-                44 => self.terminal.current_style.bg = Color::BLUE,
-                45 => self.terminal.current_style.bg = Color::MAGENTA,
-                46 => self.terminal.current_style.bg = Color::CYAN,
-                47 => self.terminal.current_style.bg = Color::WHITE,
+                40..=47 => self.terminal.current_style.bg = Color::indexed((params[i] - 40) as u8),
                 // 拡張背景色
                 48 => {
                     if let Some(color) = self.parse_extended_color(&params[i..]) {
@@ -433,35 +512,11 @@ impl<'a> TerminalPerformer<'a> {
                         i += self.extended_color_params(&params[i..]);
                     }
                 }
-                49 => self.terminal.current_style.bg = Color::BLACK, // デフォルト背景色
+                49 => self.terminal.current_style.bg = Color::DEFAULT_BG, // デフォルト背景色
                 // 明るい前景色
-                90..=97 => {
-                    let bright_colors = [
-                        Color::rgb(128, 128, 128), // 明るい黒
-                        Color::rgb(255, 0, 0),     // 明るい赤
-                        Color::rgb(0, 255, 0),     // 明るい緑
-                        Color::rgb(255, 255, 0),   // 明るい黄
-                        Color::rgb(0, 0, 255),     // 明るい青
-                        Color::rgb(255, 0, 255),   // 明るいマゼンタ
-                        Color::rgb(0, 255, 255),   // 明るいシアン
-                        Color::rgb(255, 255, 255), // 明るい白
-                    ];
-                    self.terminal.current_style.fg = bright_colors[(params[i] - 90) as usize];
-                }
+                90..=97 => self.terminal.current_style.fg = Color::indexed((params[i] - 90 + 8) as u8),
                 // 明るい背景色
-                100..=107 => {
-                    let bright_colors = [
-                        Color::rgb(128, 128, 128),
-                        Color::rgb(255, 0, 0),
-                        Color::rgb(0, 255, 0),
-                        Color::rgb(255, 255, 0),
-                        Color::rgb(0, 0, 255),
-                        Color::rgb(255, 0, 255),
-                        Color::rgb(0, 255, 255),
-                        Color::rgb(255, 255, 255),
-                    ];
-                    self.terminal.current_style.bg = bright_colors[(params[i] - 100) as usize];
-                }
+                100..=107 => self.terminal.current_style.bg = Color::indexed((params[i] - 100 + 8) as u8),
                 _ => {}
             }
             i += 1;
@@ -475,10 +530,11 @@ impl<'a> TerminalPerformer<'a> {
         }
 
         match params[1] {
-            // 256色モード
+            // 256色モード（0〜15 は設定のパレットの色）
             5 => {
                 if params.len() >= 3 {
-                    Some(Color::from_ansi256(params[2] as u8))
+                    let code = params[2] as u8;
+                    Some(if code < 16 { Color::indexed(code) } else { Color::from_ansi256(code) })
                 } else {
                     None
                 }
@@ -558,7 +614,7 @@ impl<'a> TerminalPerformer<'a> {
                         }
                     }
                     _ => {
-                        log::debug!("未対応のDEC private mode: {}", param);
+                        unsupported(format!("未対応のDEC private mode: {}", param));
                     }
                 }
             } else {
@@ -573,7 +629,7 @@ impl<'a> TerminalPerformer<'a> {
                         }
                     }
                     _ => {
-                        log::debug!("未対応のstandard mode: {}", param);
+                        unsupported(format!("未対応のstandard mode: {}", param));
                     }
                 }
             }
@@ -584,6 +640,7 @@ impl<'a> TerminalPerformer<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::grid::Palette;
 
     #[test]
     fn test_cursor_movement() {
@@ -605,7 +662,18 @@ mod tests {
         // 赤い前景色を設定
         parser.process(&mut terminal, b"\x1b[31m");
 
-        assert_eq!(terminal.current_style.fg, Color::RED);
+        assert_eq!(terminal.current_style.fg, Color::indexed(1));
+        assert_eq!(Palette::default().resolve(terminal.current_style.fg), Color::RED);
+
+        // 明るい色・256色の 0〜15 もパレットの色
+        parser.process(&mut terminal, b"\x1b[91;48;5;4m");
+        assert_eq!(terminal.current_style.fg, Color::indexed(9));
+        assert_eq!(terminal.current_style.bg, Color::indexed(4));
+
+        // 文字色・背景色を戻すとパレットの既定の色
+        parser.process(&mut terminal, b"\x1b[39;49m");
+        assert_eq!(terminal.current_style.fg, Color::DEFAULT_FG);
+        assert_eq!(terminal.current_style.bg, Color::DEFAULT_BG);
     }
 
     #[test]
@@ -619,4 +687,159 @@ mod tests {
 
         assert_eq!(terminal.grid[(0, 0)].character, ' ');
     }
+
+    #[test]
+    fn test_bell() {
+        let mut terminal = Terminal::new(80, 24);
+        let mut parser = AnsiParser::new();
+
+        // OSCの終端のBELはベルではない
+        parser.process(&mut terminal, b"\x1b]0;title\x07");
+        assert!(!terminal.take_bell());
+
+        parser.process(&mut terminal, b"done\x07");
+        assert!(terminal.take_bell());
+        assert!(!terminal.take_bell());
+    }
+
+    #[test]
+    fn test_shell_marks() {
+        let mut terminal = Terminal::new(80, 24);
+        let mut parser = AnsiParser::new();
+
+        parser.process(&mut terminal, b"\x1b]133;A\x07$ \x1b]133;B\x07ls\r\n\x1b]133;C\x07");
+        parser.process(&mut terminal, b"out\r\n\x1b]133;D;2\x1b\\\x1b]133;D\x07");
+        assert_eq!(
+            terminal.take_shell_marks(),
+            vec![
+                ShellMark::PromptStart,
+                ShellMark::CommandStart,
+                ShellMark::OutputStart,
+                ShellMark::CommandFinished(Some(2)),
+                ShellMark::CommandFinished(None),
+            ]
+        );
+        assert!(terminal.take_shell_marks().is_empty());
+    }
+
+    #[test]
+    fn test_ascii_fast_path_matches_vte() {
+        // 途中で分割されたシーケンス・UTF-8・折り返しを含む入力
+        let chunks: [&[u8]; 6] = [
+            b"abc\x1b[3",
+            b"1mRed\x1b[0m\r\nline2 ",
+            "日本\u{e8}".as_bytes(),
+            b"\x1b]0;title\x07after",
+            b"\x1b[?7l0123456789ABCDEFGHIJ\x1b[?7h",
+            b"\r\nwrap-wrap-wrap-wrap-wrap",
+        ];
+
+        let mut fast = Terminal::new(10, 4);
+        let mut parser = AnsiParser::new();
+        for chunk in chunks {
+            parser.process(&mut fast, chunk);
+        }
+
+        // vte に1バイトずつ渡した場合と同じ結果になる
+        let mut slow = Terminal::new(10, 4);
+        let mut vte_parser = Parser::new();
+        let mut performer = TerminalPerformer {
+            terminal: &mut slow,
+            ground: false,
+            was_ground: false,
+        };
+        for chunk in chunks {
+            for byte in chunk {
+                vte_parser.advance(&mut performer, *byte);
+            }
+        }
+
+        for row in 0..4 {
+            assert_eq!(fast.grid.row_slice(row), slow.grid.row_slice(row), "row {}", row);
+        }
+        assert_eq!((fast.cursor.col, fast.cursor.row), (slow.cursor.col, slow.cursor.row));
+        assert_eq!(fast.title, "title");
+    }
+
+    #[test]
+    fn test_scroll_region() {
+        let mut terminal = Terminal::new(80, 24);
+        let mut parser = AnsiParser::new();
+
+        parser.process(&mut terminal, b"\x1b[5;10r");
+        assert_eq!((terminal.scroll_top, terminal.scroll_bottom), (4, 9));
+
+        // 上下が逆・1行だけ・画面外の上端の領域は無視する
+        parser.process(&mut terminal, b"\x1b[3;3H\x1b[10;5r\x1b[7;7r\x1b[30;40r");
+        assert_eq!((terminal.scroll_top, terminal.scroll_bottom), (4, 9));
+        assert_eq!((terminal.cursor.col, terminal.cursor.row), (2, 2));
+
+        // 下端は画面内に丸め、0 は省略と同じ
+        parser.process(&mut terminal, b"\x1b[2;99r");
+        assert_eq!((terminal.scroll_top, terminal.scroll_bottom), (1, 23));
+        parser.process(&mut terminal, b"\x1b[0;0r");
+        assert_eq!((terminal.scroll_top, terminal.scroll_bottom), (0, 23));
+
+        // 領域の行数より多くスクロールしても領域が空になるだけ
+        parser.process(&mut terminal, b"\x1b[2;4r\x1b[2;1Hx\x1b[99S\x1b[99T");
+        assert_eq!(terminal.grid[(0, 1)].character, ' ');
+        terminal.assert_invariants();
+
+        // 縮めたら全画面に戻し、保存したカーソルも画面内に収める
+        parser.process(&mut terminal, b"\x1b[20;30H\x1b7");
+        terminal.resize(10, 3);
+        parser.process(&mut terminal, b"\x1b8\x1b[99G");
+        assert_eq!((terminal.scroll_top, terminal.scroll_bottom), (0, 2));
+        assert_eq!((terminal.cursor.col, terminal.cursor.row), (9, 2));
+        terminal.assert_invariants();
+    }
+
+    #[test]
+    fn test_random_input_keeps_invariants() {
+        // 乱数で組み立てたシーケンスで panic せず、状態が壊れないこと（fuzz/ の簡易版）
+        let finals = b"@ABCDEFGHIJKLMPSTXZ`abcdefghlmnqrstu";
+        for seed in 1..2000u64 {
+            let mut x = seed;
+            let mut next = || {
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                x
+            };
+            let mut bytes = Vec::new();
+            for _ in 0..40 {
+                match next() % 7 {
+                    0 => bytes.extend_from_slice(b"\r\n"),
+                    1 => bytes.extend_from_slice("あ".as_bytes()),
+                    2 => bytes.extend_from_slice(b"\x1bM\x1bD\x1bE\x1b7\x1b8"),
+                    3 => {
+                        let mode = [1049, 47, 1047, 7, 25][(next() % 5) as usize];
+                        let set = if next() % 2 == 0 { 'h' } else { 'l' };
+                        bytes.extend_from_slice(format!("\x1b[?{}{}", mode, set).as_bytes());
+                    }
+                    4 => bytes.push((next() % 128) as u8),
+                    _ => {
+                        let (a, b) = (next() % 70, next() % 70);
+                        let f = finals[(next() % finals.len() as u64) as usize] as char;
+                        let seq = match next() % 4 {
+                            0 => format!("\x1b[{}", f),
+                            1 => format!("\x1b[{}{}", a, f),
+                            2 => format!("\x1b[{};{}{}", a, b, f),
+                            _ => format!("\x1b[65535;{}{}", a, f),
+                        };
+                        bytes.extend_from_slice(seq.as_bytes());
+                    }
+                }
+            }
+
+            let mut terminal = Terminal::new(1 + (seed % 9) as usize * 5, 1 + (seed % 7) as usize * 3);
+            let mut parser = AnsiParser::new();
+            let (first, rest) = bytes.split_at(bytes.len() / 2);
+            parser.process(&mut terminal, first);
+            terminal.assert_invariants();
+            terminal.resize(1 + (seed % 5) as usize * 7, 1 + (seed % 3) as usize * 4);
+            parser.process(&mut terminal, rest);
+            terminal.assert_invariants();
+        }
+    }
 }
@@ -0,0 +1,168 @@
+//! パフォーマンス計測モジュール
+//!
+//! フレーム時間やPTYスループットを集計し、HUDに表示する文字列を作る
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::scrollback::ScrollbackUsage;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 定数
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 集計に使う期間（FPS・スループット）
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// フレーム時間を保持する最大数
+const MAX_SAMPLES: usize = 120;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 集計
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// レンダラー側の統計（直前のフレーム）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameStats {
+    /// 描画したインスタンス数（背景＋テキスト）
+    pub instances: usize,
+    /// インスタンスバッファの上限
+    pub max_instances: usize,
+    /// グリフアトラスの使用率（0.0〜1.0）
+    pub atlas_usage: f32,
+    /// キャッシュ済みグリフ数
+    pub glyphs: usize,
+}
+
+/// パフォーマンス統計
+pub struct PerfStats {
+    /// フレームの開始時刻と描画にかかった時間
+    frames: VecDeque<(Instant, Duration)>,
+    /// PTYから受信した時刻とバイト数
+    pty_bytes: VecDeque<(Instant, usize)>,
+}
+
+impl PerfStats {
+    pub fn new() -> Self {
+        Self {
+            frames: VecDeque::with_capacity(MAX_SAMPLES),
+            pty_bytes: VecDeque::new(),
+        }
+    }
+
+    /// 1フレームの描画時間を記録
+    pub fn record_frame(&mut self, start: Instant, elapsed: Duration) {
+        if self.frames.len() == MAX_SAMPLES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back((start, elapsed));
+    }
+
+    /// PTYから受信したバイト数を記録
+    pub fn record_pty_bytes(&mut self, now: Instant, bytes: usize) {
+        if bytes == 0 {
+            return;
+        }
+        self.pty_bytes.push_back((now, bytes));
+        while let Some(&(t, _)) = self.pty_bytes.front() {
+            if now.duration_since(t) > WINDOW {
+                self.pty_bytes.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// 直近1秒間のフレーム数
+    pub fn fps(&self, now: Instant) -> usize {
+        self.frames
+            .iter()
+            .filter(|(t, _)| now.duration_since(*t) <= WINDOW)
+            .count()
+    }
+
+    /// フレーム時間の平均と最大（ミリ秒）
+    pub fn frame_time_ms(&self) -> (f32, f32) {
+        if self.frames.is_empty() {
+            return (0.0, 0.0);
+        }
+        let total: Duration = self.frames.iter().map(|(_, d)| *d).sum();
+        let max = self.frames.iter().map(|(_, d)| *d).max().unwrap_or_default();
+        let avg = total.as_secs_f32() * 1000.0 / self.frames.len() as f32;
+        (avg, max.as_secs_f32() * 1000.0)
+    }
+
+    /// 直近1秒間のPTYスループット（バイト/秒）
+    pub fn pty_throughput(&self, now: Instant) -> usize {
+        self.pty_bytes
+            .iter()
+            .filter(|(t, _)| now.duration_since(*t) <= WINDOW)
+            .map(|(_, n)| n)
+            .sum()
+    }
+
+    /// HUDに表示する行を生成
+    pub fn hud_lines(&self, now: Instant, frame: &FrameStats, scrollback: &ScrollbackUsage) -> Vec<String> {
+        let (avg, max) = self.frame_time_ms();
+        vec![
+            " PERF".to_string(),
+            format!(" fps    {:>6}", self.fps(now)),
+            format!(" frame  {:>6.2}ms  max {:.2}ms", avg, max),
+            format!(" inst   {:>6} / {}", frame.instances, frame.max_instances),
+            format!(" atlas  {:>5.1}%  {} glyphs", frame.atlas_usage * 100.0, frame.glyphs),
+            format!(" pty    {:>6}/s", format_bytes(self.pty_throughput(now))),
+            format!(
+                " sb     {:>6} lines  {} / {}",
+                scrollback.lines,
+                format_bytes(scrollback.bytes),
+                format_bytes(scrollback.max_bytes)
+            ),
+            format!(" disk   {:>6} lines", scrollback.spilled),
+        ]
+    }
+}
+
+/// バイト数を読みやすい単位に変換
+fn format_bytes(bytes: usize) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1}MB", bytes as f32 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1}KB", bytes as f32 / 1024.0)
+    } else {
+        format!("{}B", bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fps_and_throughput() {
+        let mut stats = PerfStats::new();
+        let start = Instant::now();
+
+        // 古いサンプルは集計から外れる
+        stats.record_pty_bytes(start, 4096);
+        for i in 0..30 {
+            let t = start + Duration::from_millis(1000 + i * 33);
+            stats.record_frame(t, Duration::from_millis(2 + i % 3));
+        }
+        let now = start + Duration::from_millis(2000);
+        stats.record_pty_bytes(now, 512);
+
+        assert_eq!(stats.fps(now), 30);
+        assert_eq!(stats.pty_throughput(now), 512);
+
+        let (avg, max) = stats.frame_time_ms();
+        assert!((avg - 3.0).abs() < 0.1);
+        assert_eq!(max, 4.0);
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(100), "100B");
+        assert_eq!(format_bytes(1536), "1.5KB");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.0MB");
+    }
+}
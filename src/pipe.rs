@@ -0,0 +1,209 @@
+//! 画面・スクロールバックを外部コマンドに渡すモジュール
+//!
+//! フォーカス中のペインの文字を一時ファイルに書き、`[[pipes]]` のコマンドの標準入力にして
+//! 下に分割した新しいペインで実行する（`fzf` のように端末を使うコマンドもそのまま動く）
+//! 結果はそのペインに表示するか、コマンドが終わったら元のペインに入力する
+//!
+//! ```toml
+//! [[pipes]]
+//! name = "pick a line"
+//! command = "fzf"
+//! input = "scrollback"
+//! output = "insert"
+//! ```
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{Context, Result};
+
+use crate::config::PipeConfig;
+use crate::editor::shell_escape;
+
+/// 一時ファイルの名前に付ける番号
+static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+/// コマンドに渡す文字
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PipeInput {
+    /// 見えている画面
+    #[default]
+    Screen,
+    /// スクロールバックと画面
+    Scrollback,
+}
+
+impl PipeInput {
+    /// 名前から取得（screen / scrollback）
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "screen" => Some(Self::Screen),
+            "scrollback" => Some(Self::Scrollback),
+            _ => None,
+        }
+    }
+}
+
+/// コマンドの結果の扱い
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PipeOutput {
+    /// 実行したペインに表示したまま残す
+    #[default]
+    Pane,
+    /// 終わったらペインを閉じ、標準出力を元のペインに入力する
+    Insert,
+}
+
+impl PipeOutput {
+    /// 名前から取得（pane / insert）
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pane" => Some(Self::Pane),
+            "insert" => Some(Self::Insert),
+            _ => None,
+        }
+    }
+}
+
+/// `[[pipes]]` の1つ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pipe {
+    pub name: String,
+    /// シェルのコマンドライン
+    pub command: String,
+    pub input: PipeInput,
+    pub output: PipeOutput,
+}
+
+/// 設定ファイルのパイプを読む
+pub fn parse_pipes(configs: &[PipeConfig]) -> Result<Vec<Pipe>> {
+    configs
+        .iter()
+        .map(|config| {
+            let name = config.name.trim();
+            anyhow::ensure!(!name.is_empty(), "パイプの名前が空です");
+            anyhow::ensure!(!config.command.trim().is_empty(), "パイプのコマンドが空です: {:?}", name);
+            let input = match config.input.as_deref() {
+                Some(input) => PipeInput::from_name(input.trim())
+                    .with_context(|| format!("input は screen か scrollback です: {:?}", input))?,
+                None => PipeInput::default(),
+            };
+            let output = match config.output.as_deref() {
+                Some(output) => PipeOutput::from_name(output.trim())
+                    .with_context(|| format!("output は pane か insert です: {:?}", output))?,
+                None => PipeOutput::default(),
+            };
+            Ok(Pipe { name: name.to_string(), command: config.command.clone(), input, output })
+        })
+        .collect()
+}
+
+/// コマンドに渡す文字（行末の空白と末尾の空行は除く）
+pub fn input_text(lines: &[String]) -> String {
+    let lines: Vec<&str> = lines.iter().map(|line| line.trim_end()).collect();
+    let end = lines.iter().rposition(|line| !line.is_empty()).map_or(0, |last| last + 1);
+    lines[..end].iter().map(|line| format!("{}\n", line)).collect()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 実行
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 実行中のパイプ（一時ファイルは破棄するときに消す）
+#[derive(Debug)]
+pub struct PipeRun {
+    /// ペインで実行するコマンドライン
+    pub line: String,
+    pub output: PipeOutput,
+    input_file: PathBuf,
+    /// 標準出力を受けるファイル（insert のみ）
+    output_file: Option<PathBuf>,
+}
+
+impl PipeRun {
+    /// 渡す文字を一時ファイルに書き、コマンドラインを作る
+    pub fn prepare(pipe: &Pipe, text: &str) -> Result<Self> {
+        let stem = format!("umiterm-pipe-{}-{}", std::process::id(), NEXT_FILE.fetch_add(1, Ordering::Relaxed));
+        let input_file = std::env::temp_dir().join(format!("{}.txt", stem));
+        fs::write(&input_file, text).with_context(|| format!("一時ファイルに書けません: {}", input_file.display()))?;
+        let output_file = (pipe.output == PipeOutput::Insert).then(|| std::env::temp_dir().join(format!("{}.out", stem)));
+
+        // パイプでつないだ行もまとめて付け替えられるよう sh で実行する（ログインシェルが fish などでも同じ書き方）
+        let mut line = format!(
+            "exec /bin/sh -c {} < {}",
+            shell_escape(&pipe.command),
+            shell_escape(&input_file.to_string_lossy())
+        );
+        if let Some(output_file) = &output_file {
+            line.push_str(&format!(" > {}", shell_escape(&output_file.to_string_lossy())));
+        }
+        Ok(Self { line, output: pipe.output, input_file, output_file })
+    }
+
+    /// コマンドの標準出力（insert のみ、末尾の改行は除く）
+    pub fn take_output(&self) -> Option<String> {
+        let text = fs::read_to_string(self.output_file.as_ref()?).ok()?;
+        let text = text.trim_end_matches(['\n', '\r']);
+        (!text.is_empty()).then(|| text.to_string())
+    }
+}
+
+impl Drop for PipeRun {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.input_file);
+        if let Some(output_file) = &self.output_file {
+            let _ = fs::remove_file(output_file);
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(input: Option<&str>, output: Option<&str>) -> PipeConfig {
+        PipeConfig {
+            name: "pick".into(),
+            command: "sort | uniq".into(),
+            input: input.map(Into::into),
+            output: output.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn test_parse_pipes() {
+        let pipes = parse_pipes(&[config(None, None), config(Some("scrollback"), Some("insert"))]).unwrap();
+        assert_eq!((pipes[0].input, pipes[0].output), (PipeInput::Screen, PipeOutput::Pane));
+        assert_eq!((pipes[1].input, pipes[1].output), (PipeInput::Scrollback, PipeOutput::Insert));
+        assert!(parse_pipes(&[config(Some("history"), None)]).is_err());
+        assert!(parse_pipes(&[config(None, Some("clipboard"))]).is_err());
+        assert!(parse_pipes(&[PipeConfig { command: " ".into(), ..config(None, None) }]).is_err());
+    }
+
+    #[test]
+    fn test_input_text() {
+        let lines = ["$ ls  ".to_string(), "".into(), "a.txt".into(), "   ".into(), "".into()];
+        assert_eq!(input_text(&lines), "$ ls\n\na.txt\n");
+        assert_eq!(input_text(&["  ".to_string()]), "");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run() {
+        let pipe = &parse_pipes(&[config(None, Some("insert"))]).unwrap()[0];
+        let run = PipeRun::prepare(pipe, "b\na\nb\n").unwrap();
+        let status = std::process::Command::new("/bin/sh").arg("-c").arg(&run.line).status().unwrap();
+        assert!(status.success());
+        assert_eq!(run.take_output().as_deref(), Some("a\nb"));
+
+        // 破棄すると一時ファイルを消す
+        let files = [run.input_file.clone(), run.output_file.clone().unwrap()];
+        drop(run);
+        assert!(files.iter().all(|file| !file.exists()));
+    }
+}
@@ -0,0 +1,338 @@
+//! asciicast の再生モジュール
+//!
+//! 録画した asciicast v2 のファイルを、シェルの代わりにペインで再生する
+//! 出力はそのまま `AnsiParser` / `Terminal` に流すので、ふつうのペインと同じように描画・検索・コピーできる
+//!
+//! ペインに入力したキーで操作する（状態はペインのタイトルに出す）
+//!
+//! | キー | 操作 |
+//! |------|------|
+//! | Space | 一時停止・再開（終わっていれば最初から） |
+//! | → / ← （l / h） | 5秒進む・戻る |
+//! | + / - | 速く・遅く（0.25〜8倍） |
+//! | 0 | 最初に戻る |
+//! | q | 再生をやめる（ペインはシェルが終了したときと同じ扱い） |
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use crossbeam_channel::{bounded, RecvTimeoutError};
+use serde::Deserialize;
+
+use crate::idle;
+use crate::pty::{ChildExit, ExitSlot, Pty};
+
+/// 再生の速さ（+ / - で切り替える）
+const SPEEDS: &[f64] = &[0.25, 0.5, 1.0, 1.5, 2.0, 4.0, 8.0];
+
+/// → / ← で動く時間
+const SEEK_STEP: Duration = Duration::from_secs(5);
+
+/// 再生中にタイトルの時刻を更新する間隔
+const TICK: Duration = Duration::from_millis(250);
+
+/// 巻き戻すときに送る、ターミナルのリセット（RIS）と画面・スクロールバックの消去
+const RESET: &str = "\x1bc\x1b[2J\x1b[3J\x1b[H";
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ファイル
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// asciicast v2 のヘッダー（使う項目のみ）
+#[derive(Debug, Deserialize)]
+struct Header {
+    version: u32,
+    /// 記録の中の無音の上限（秒、これより長い間は詰める）
+    #[serde(default)]
+    idle_time_limit: Option<f64>,
+}
+
+/// 読み込んだ記録（出力のイベントのみ、時刻は先頭から）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cast {
+    pub events: Vec<(Duration, String)>,
+}
+
+impl Cast {
+    /// ファイルから読む
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("録画を開けません: {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("録画を読めません: {}", path.display()))
+    }
+
+    /// 文字列から読む（大きさの変化 `r` や入力 `i` などのイベントは使わない）
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+        let (_, header) = lines.next().context("ファイルが空です")?;
+        let header: Header = serde_json::from_str(header).context("1 行目のヘッダーが正しくありません")?;
+        anyhow::ensure!(header.version == 2, "asciicast v2 だけに対応しています（version {}）", header.version);
+        let idle_limit = header.idle_time_limit.filter(|limit| *limit > 0.0);
+
+        let mut events = Vec::new();
+        let (mut last, mut shift) = (0.0, 0.0);
+        for (index, line) in lines {
+            let (time, kind, data): (f64, String, String) =
+                serde_json::from_str(line).with_context(|| format!("{} 行目のイベントが正しくありません", index + 1))?;
+            // 無音の上限を超えた分は、それ以降の時刻から引く
+            if let Some(limit) = idle_limit {
+                shift += (time - last - limit).max(0.0);
+            }
+            last = time;
+            if kind == "o" {
+                events.push((Duration::from_secs_f64((time - shift).max(0.0)), data));
+            }
+        }
+        Ok(Self { events })
+    }
+
+    /// 記録の長さ（最後の出力の時刻）
+    pub fn duration(&self) -> Duration {
+        self.events.last().map_or(Duration::ZERO, |(time, _)| *time)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 再生の状態
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 再生の状態（時刻を進めるとその間の出力を返す）
+pub struct Player {
+    cast: Cast,
+    /// 記録の中の今の時刻
+    position: Duration,
+    /// 次に出すイベント
+    next: usize,
+    /// SPEEDS の番号
+    speed: usize,
+    paused: bool,
+}
+
+impl Player {
+    pub fn new(cast: Cast) -> Self {
+        let speed = SPEEDS.iter().position(|speed| *speed == 1.0).unwrap_or(0);
+        Self { cast, position: Duration::ZERO, next: 0, speed, paused: false }
+    }
+
+    /// 最後まで出したか
+    pub fn finished(&self) -> bool {
+        self.next >= self.cast.events.len()
+    }
+
+    /// 実時間で elapsed たった（止めていれば進まない）
+    pub fn advance(&mut self, elapsed: Duration) -> String {
+        if !self.paused {
+            self.position = (self.position + elapsed.mul_f64(SPEEDS[self.speed])).min(self.cast.duration());
+        }
+        self.take_output()
+    }
+
+    /// 記録の中の時刻へ移る（戻るときはターミナルをリセットして最初から出し直す）
+    pub fn seek(&mut self, target: Duration) -> String {
+        let target = target.min(self.cast.duration());
+        let mut output = String::new();
+        if target < self.position || (target.is_zero() && self.next > 0) {
+            output.push_str(RESET);
+            self.next = 0;
+        }
+        self.position = target;
+        output + &self.take_output()
+    }
+
+    /// 次の出力までの実時間（止めているか終わっていれば None）
+    pub fn wait(&self) -> Option<Duration> {
+        let (time, _) = self.cast.events.get(self.next).filter(|_| !self.paused)?;
+        Some(time.saturating_sub(self.position).div_f64(SPEEDS[self.speed]))
+    }
+
+    /// キー入力で操作する
+    /// 戻り値: 出力（None なら再生をやめる）
+    pub fn key(&mut self, key: &[u8]) -> Option<String> {
+        let output = match key {
+            b" " if self.finished() => {
+                self.paused = false;
+                self.seek(Duration::ZERO)
+            }
+            b" " => {
+                self.paused = !self.paused;
+                String::new()
+            }
+            b"\x1b[C" | b"\x1bOC" | b"l" => self.seek(self.position + SEEK_STEP),
+            b"\x1b[D" | b"\x1bOD" | b"h" => self.seek(self.position.saturating_sub(SEEK_STEP)),
+            b"0" => self.seek(Duration::ZERO),
+            b"+" | b"=" => {
+                self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+                String::new()
+            }
+            b"-" => {
+                self.speed = self.speed.saturating_sub(1);
+                String::new()
+            }
+            b"q" | b"\x03" => return None,
+            // ほかのキーやターミナルの応答（DSR など）は使わない
+            _ => String::new(),
+        };
+        Some(output)
+    }
+
+    /// タイトルに出す状態（`▶ 0:12 / 1:30 2x`）
+    pub fn status(&self) -> String {
+        let icon = if self.finished() {
+            "■"
+        } else if self.paused {
+            "⏸"
+        } else {
+            "▶"
+        };
+        let clock = |time: Duration| format!("{}:{:02}", time.as_secs() / 60, time.as_secs() % 60);
+        format!("{} {} / {} {}x", icon, clock(self.position), clock(self.cast.duration()), SPEEDS[self.speed])
+    }
+
+    /// 今の時刻までのイベントを取り出す
+    fn take_output(&mut self) -> String {
+        let mut output = String::new();
+        while let Some((time, data)) = self.cast.events.get(self.next) {
+            if *time > self.position {
+                break;
+            }
+            output.push_str(data);
+            self.next += 1;
+        }
+        output
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 再生スレッド
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 再生を止めるハンドル（PTY が持ち、破棄すると再生スレッドも終わる）
+pub struct Handle {
+    stop: Arc<AtomicBool>,
+}
+
+impl Handle {
+    /// 再生をやめる
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Release);
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// ファイルを読み込み、再生するスレッドにつながった PTY を作る
+pub fn spawn(path: &Path, cols: u16, rows: u16) -> Result<Pty> {
+    let mut player = Player::new(Cast::load(path)?);
+    let (output_tx, output_rx) = bounded::<Vec<u8>>(256);
+    let (input_tx, input_rx) = bounded::<Vec<u8>>(256);
+    let exit = ExitSlot::default();
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let exit_writer = exit.clone();
+    let stopped = stop.clone();
+    std::thread::Builder::new()
+        .name("cast-player".into())
+        .spawn(move || {
+            let mut last = Instant::now();
+            let mut title = String::new();
+            while !stopped.load(Ordering::Acquire) {
+                let timeout = player.wait().map_or(TICK, |wait| wait.min(TICK));
+                let output = match input_rx.recv_timeout(timeout) {
+                    Ok(key) => match player.key(&key) {
+                        Some(output) => output,
+                        None => break,
+                    },
+                    Err(RecvTimeoutError::Timeout) => String::new(),
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                let now = Instant::now();
+                let mut output = output + &player.advance(now - last);
+                last = now;
+                // 状態が変わったらペインのタイトル（OSC 2）で知らせる
+                let status = player.status();
+                if status != title {
+                    output.push_str(&format!("\x1b]2;{}\x07", status));
+                    title = status;
+                }
+                if !output.is_empty() && output_tx.send(output.into_bytes()).is_err() {
+                    break;
+                }
+            }
+            let _ = exit_writer.set(ChildExit { code: Some(0) });
+            idle::notify_output();
+        })?;
+
+    Ok(Pty::playback(Handle { stop }, output_rx, input_tx, exit, cols, rows))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAST: &str = r#"{"version": 2, "width": 80, "height": 24}
+[0.5, "o", "$ ls\r\n"]
+[1.0, "r", "100x30"]
+[2.0, "o", "a.txt\r\n"]
+[10.0, "o", "$ "]
+"#;
+
+    #[test]
+    fn test_parse() {
+        let cast = Cast::parse(CAST).unwrap();
+        assert_eq!(cast.events.len(), 3);
+        assert_eq!(cast.events[1], (Duration::from_secs(2), "a.txt\r\n".to_string()));
+        assert_eq!(cast.duration(), Duration::from_secs(10));
+
+        // 無音の上限より長い間は詰める
+        let limited = CAST.replacen("\"height\": 24", "\"height\": 24, \"idle_time_limit\": 1.5", 1);
+        assert_eq!(Cast::parse(&limited).unwrap().duration(), Duration::from_secs_f64(3.5));
+
+        assert!(Cast::parse("").is_err());
+        assert!(Cast::parse(r#"{"version": 1}"#).is_err());
+        let error = format!("{:#}", Cast::parse(&format!("{}[oops]", CAST)).unwrap_err());
+        assert!(error.contains("6 行目"), "{}", error);
+    }
+
+    #[test]
+    fn test_player() {
+        let mut player = Player::new(Cast::parse(CAST).unwrap());
+        assert_eq!(player.wait(), Some(Duration::from_millis(500)));
+        assert_eq!(player.advance(Duration::from_secs(1)), "$ ls\r\n");
+        assert_eq!(player.status(), "▶ 0:01 / 0:10 1x");
+
+        // 速くすると待ち時間が縮む
+        player.key(b"+").unwrap();
+        player.key(b"+").unwrap();
+        assert_eq!(player.wait(), Some(Duration::from_millis(500)));
+
+        // 止めている間は進まない
+        player.key(b" ").unwrap();
+        assert_eq!(player.advance(Duration::from_secs(5)), "");
+        assert_eq!(player.wait(), None);
+        assert!(player.status().starts_with("⏸ 0:01"));
+
+        // 進めると間の出力をまとめて出し、戻るとリセットして出し直す
+        assert_eq!(player.key(b"\x1b[C").unwrap(), "a.txt\r\n");
+        assert_eq!(player.key(b"\x1bOD").unwrap(), format!("{}$ ls\r\n", RESET));
+        assert_eq!(player.key(b"\x1b[5;1R").unwrap(), "");
+
+        // 最後まで出したら Space で最初から
+        player.key(b"l").unwrap();
+        player.key(b"l").unwrap();
+        assert!(player.finished());
+        assert!(player.status().starts_with("■ 0:10 / 0:10"));
+        assert_eq!(player.key(b" ").unwrap(), RESET);
+        assert_eq!(player.advance(Duration::from_millis(500)), "$ ls\r\n");
+        assert_eq!(player.key(b"q"), None);
+    }
+}
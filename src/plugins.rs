@@ -0,0 +1,846 @@
+//! プラグインモジュール
+//!
+//! 設定ファイルと同じ場所の `plugins/*.lua` を起動時に読み込み、フックで UmiTerm を拡張する
+//! （Lua 5.4、フォークせずにキー・出力・タブのタイトルなどを変えられるように）
+//!
+//! ```lua
+//! umiterm.on("output-line", function(line, pane)
+//!   if line:find("BUILD FAILED") then umiterm.notify("ビルドが失敗しました") end
+//! end)
+//! umiterm.on("key", function(key, pane)
+//!   if key == "ctrl+alt+d" then umiterm.split({ pane = pane, horizontal = true }); return true end
+//! end)
+//! umiterm.add_command("open dev layout", function(pane)
+//!   umiterm.action("new-tab")
+//!   umiterm.send_text("npm run dev\r")
+//! end)
+//! umiterm.add_status_segment(function(status) return os.getenv("AWS_PROFILE") end, "right")
+//! ```
+//!
+//! ペインやウィンドウを操作する関数は要求を溜めるだけで、フックから戻ったあとでイベントループが実行する
+//! Lua は描画と同じスレッドで動くため、1回の呼び出しは HOOK_TIME_LIMIT までで打ち切り、
+//! 打ち切ったフック・コマンドは外す（無限ループで UmiTerm を止めさせない）
+
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use crossbeam_channel::Receiver;
+use mlua::{Function, HookTriggers, Lua, MultiValue, Table, Value};
+
+use crate::config;
+use crate::events::{self, Event};
+use crate::keybinds::BindAction;
+use crate::notification;
+use crate::pane::PaneId;
+use crate::pty::PaneCommand;
+use crate::status::{Align, StatusBar, StatusContext, StatusSegment};
+
+/// フックを入れておく Lua のレジストリの名前
+const HOOKS: &str = "umiterm.hooks";
+
+/// `umiterm.add_command` で足したコマンドを入れておく Lua のレジストリの名前
+const PALETTE: &str = "umiterm.palette";
+
+/// `umiterm.add_status_segment` で足したセグメントを入れておく Lua のレジストリの名前
+const STATUS: &str = "umiterm.status";
+
+/// Lua を1回呼んだときに使ってよい時間（ファイルの読み込み・フック・コマンドのそれぞれ）
+const HOOK_TIME_LIMIT: Duration = Duration::from_millis(200);
+
+/// 時間を確かめる間隔（Lua の命令の数）
+const HOOK_CHECK_INSTRUCTIONS: u32 = 10_000;
+
+/// フックの名前
+const HOOK_NAMES: &[&str] = &["startup", "output-line", "key", "format-tab-title"];
+
+/// イベント（`umiterm cli subscribe` と同じもの）から呼ぶフックの名前
+const EVENT_HOOK_NAMES: &[&str] = &["pane-open", "pane-closed", "title-changed", "bell", "command-finished"];
+
+/// イベントから呼ぶフックの名前
+fn event_hook(event: &Event) -> &'static str {
+    match event {
+        Event::PaneCreated { .. } => "pane-open",
+        Event::PaneClosed { .. } => "pane-closed",
+        Event::TitleChanged { .. } => "title-changed",
+        Event::Bell { .. } => "bell",
+        Event::CommandFinished { .. } => "command-finished",
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// プラグインからの要求
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// プラグインがイベントループに頼む操作（pane が None ならフォーカス中のペイン）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginCommand {
+    /// ペインに文字列を送る
+    SendText { pane: Option<u64>, text: String },
+    /// ペインを分割（horizontal なら左右）
+    Split { pane: Option<u64>, horizontal: bool, command: PaneCommand, cwd: Option<PathBuf> },
+    /// ペインにフォーカスする
+    Activate { pane: Option<u64> },
+    /// フォーカス中のウィンドウでキー割り当てと同じ操作をする（send-text は text を送る、pipe は text のパイプ）
+    Action { action: BindAction, text: Option<String> },
+    /// 新しいウィンドウを開く
+    NewWindow { command: PaneCommand, cwd: Option<PathBuf> },
+}
+
+/// `umiterm.panes()` に渡すペイン（直前のフレームのもの）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginPane {
+    pub id: u64,
+    pub window: u64,
+    pub tab: usize,
+    pub title: String,
+    pub cwd: String,
+    pub focused: bool,
+}
+
+/// `format-tab-title` に渡すタブ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginTab<'a> {
+    pub index: usize,
+    pub active: bool,
+    pub pane: PaneId,
+    /// 組み込みのタイトル
+    pub title: &'a str,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 読み込み
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 読み込んだプラグイン（ウィンドウ間で共有する）
+pub struct Plugins {
+    lua: Lua,
+    /// フックから頼まれた操作
+    commands: Rc<RefCell<Vec<PluginCommand>>>,
+    /// `umiterm.panes()` が返すペイン
+    panes: Rc<RefCell<Vec<PluginPane>>>,
+    /// 読み込んだファイルの数
+    loaded: usize,
+    /// イベントのフックがあれば購読する
+    events: Option<Receiver<Event>>,
+    /// 今の呼び出しを打ち切る時刻（Lua を呼んでいないときは None）
+    deadline: Rc<Cell<Option<Instant>>>,
+    /// 今の呼び出しを時間切れで打ち切ったか
+    timed_out: Rc<Cell<bool>>,
+}
+
+/// プラグインを置く場所（設定ファイルと同じ場所の `plugins/`）
+pub fn plugins_dir() -> Option<PathBuf> {
+    Some(config::path()?.parent()?.join("plugins"))
+}
+
+impl Plugins {
+    /// `plugins/*.lua` をファイル名の順に読み込む（読み込めないファイルは警告して飛ばす）
+    pub fn load() -> Self {
+        let mut files: Vec<PathBuf> = plugins_dir()
+            .and_then(|dir| fs::read_dir(dir).ok())
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "lua"))
+            .collect();
+        files.sort();
+        let sources = files.iter().filter_map(|path| match fs::read_to_string(path) {
+            Ok(source) => Some((path.clone(), source)),
+            Err(e) => {
+                log::warn!("プラグインを開けません: {} ({})", path.display(), e);
+                None
+            }
+        });
+        Self::from_sources(sources)
+    }
+
+    /// ファイル名と中身から読み込む
+    fn from_sources(sources: impl IntoIterator<Item = (PathBuf, String)>) -> Self {
+        let mut plugins = Self {
+            lua: Lua::new(),
+            commands: Rc::default(),
+            panes: Rc::default(),
+            loaded: 0,
+            events: None,
+            deadline: Rc::default(),
+            timed_out: Rc::default(),
+        };
+        plugins.install_limit();
+        if let Err(e) = plugins.install_api() {
+            log::error!("プラグインの API を用意できません: {}", e);
+            return plugins;
+        }
+        for (path, source) in sources {
+            match plugins.exec(&path, &source) {
+                Ok(()) => {
+                    log::info!("プラグインを読み込みました: {}", path.display());
+                    plugins.loaded += 1;
+                }
+                Err(e) => {
+                    log::error!("{:#}", e);
+                    notification::notify("UmiTerm", &format!("プラグインを読み込めません: {}", path.display()));
+                }
+            }
+        }
+        if EVENT_HOOK_NAMES.iter().any(|name| plugins.has_hook(name)) {
+            plugins.events = Some(events::subscribe());
+        }
+        plugins
+    }
+
+    /// 1つのファイルを実行する
+    fn exec(&self, path: &Path, source: &str) -> Result<()> {
+        let (result, _) = self.limited(|| self.lua.load(source).set_name(path.display().to_string()).exec());
+        result.with_context(|| format!("プラグインの実行に失敗: {}", path.display()))
+    }
+
+    /// 決まった命令の数ごとに、打ち切る時刻を過ぎていないか確かめる
+    fn install_limit(&self) {
+        let triggers = HookTriggers::new().every_nth_instruction(HOOK_CHECK_INSTRUCTIONS);
+        self.lua.set_hook(triggers, limit_hook(self.deadline.clone(), self.timed_out.clone()));
+    }
+
+    /// HOOK_TIME_LIMIT までで打ち切って Lua を呼ぶ（戻り値: 結果と、打ち切ったか）
+    fn limited<R>(&self, run: impl FnOnce() -> mlua::Result<R>) -> (mlua::Result<R>, bool) {
+        self.timed_out.set(false);
+        self.deadline.set(Some(Instant::now() + HOOK_TIME_LIMIT));
+        let result = run();
+        self.deadline.set(None);
+        let timed_out = self.timed_out.get();
+        if timed_out {
+            self.install_limit();
+        }
+        (result, timed_out)
+    }
+
+    /// Lua から使う `umiterm` テーブルを作る
+    fn install_api(&self) -> mlua::Result<()> {
+        let lua = &self.lua;
+        let api = lua.create_table()?;
+        lua.set_named_registry_value(HOOKS, lua.create_table()?)?;
+        lua.set_named_registry_value(PALETTE, lua.create_table()?)?;
+        lua.set_named_registry_value(STATUS, lua.create_table()?)?;
+
+        api.set(
+            "on",
+            lua.create_function(|lua, (name, hook): (String, Function)| {
+                if !HOOK_NAMES.iter().chain(EVENT_HOOK_NAMES).any(|known| *known == name) {
+                    return Err(mlua::Error::runtime(format!("フックの名前が正しくありません: {}", name)));
+                }
+                let hooks: Table = lua.named_registry_value(HOOKS)?;
+                let list: Table = match hooks.get::<_, Option<Table>>(name.as_str())? {
+                    Some(list) => list,
+                    None => {
+                        let list = lua.create_table()?;
+                        hooks.set(name.as_str(), list.clone())?;
+                        list
+                    }
+                };
+                list.push(hook)
+            })?,
+        )?;
+
+        api.set(
+            "add_command",
+            lua.create_function(|lua, (name, command): (String, Function)| {
+                let entry = lua.create_table()?;
+                entry.set("name", name)?;
+                entry.set("command", command)?;
+                lua.named_registry_value::<Table>(PALETTE)?.push(entry)
+            })?,
+        )?;
+
+        api.set(
+            "add_status_segment",
+            lua.create_function(|lua, (segment, align): (Function, Option<String>)| {
+                if !matches!(align.as_deref(), None | Some("left") | Some("right")) {
+                    return Err(mlua::Error::runtime(format!("表示位置が正しくありません（left / right）: {:?}", align)));
+                }
+                let entry = lua.create_table()?;
+                entry.set("segment", segment)?;
+                entry.set("align", align.unwrap_or_else(|| "left".to_string()))?;
+                lua.named_registry_value::<Table>(STATUS)?.push(entry)
+            })?,
+        )?;
+
+        api.set(
+            "log",
+            lua.create_function(|_, message: String| {
+                log::info!("[plugin] {}", message);
+                Ok(())
+            })?,
+        )?;
+        api.set(
+            "notify",
+            lua.create_function(|_, message: String| {
+                notification::notify("UmiTerm", &message);
+                Ok(())
+            })?,
+        )?;
+        api.set("config", lua.create_function(|lua, name: String| config_value(lua, &name))?)?;
+
+        let panes = self.panes.clone();
+        api.set(
+            "panes",
+            lua.create_function(move |lua, ()| {
+                let tables = panes.borrow().iter().map(|pane| pane_table(lua, pane)).collect::<mlua::Result<Vec<_>>>()?;
+                lua.create_sequence_from(tables)
+            })?,
+        )?;
+        let panes = self.panes.clone();
+        api.set(
+            "pane",
+            lua.create_function(move |lua, id: u64| {
+                panes.borrow().iter().find(|pane| pane.id == id).map(|pane| pane_table(lua, pane)).transpose()
+            })?,
+        )?;
+
+        let commands = self.commands.clone();
+        api.set(
+            "send_text",
+            lua.create_function(move |_, (text, pane): (String, Option<u64>)| {
+                commands.borrow_mut().push(PluginCommand::SendText { pane, text });
+                Ok(())
+            })?,
+        )?;
+        let commands = self.commands.clone();
+        api.set(
+            "split",
+            lua.create_function(move |_, options: Option<Table>| {
+                commands.borrow_mut().push(split_command(options.as_ref())?);
+                Ok(())
+            })?,
+        )?;
+        let commands = self.commands.clone();
+        api.set(
+            "activate",
+            lua.create_function(move |_, pane: Option<u64>| {
+                commands.borrow_mut().push(PluginCommand::Activate { pane });
+                Ok(())
+            })?,
+        )?;
+        let commands = self.commands.clone();
+        api.set(
+            "action",
+            lua.create_function(move |_, (name, text): (String, Option<String>)| {
+                let action = BindAction::from_name(&name)
+                    .ok_or_else(|| mlua::Error::runtime(format!("操作の名前が正しくありません: {}", name)))?;
+                commands.borrow_mut().push(PluginCommand::Action { action, text });
+                Ok(())
+            })?,
+        )?;
+        let commands = self.commands.clone();
+        api.set(
+            "new_window",
+            lua.create_function(move |_, options: Option<Table>| {
+                let (command, cwd) = launch_options(options.as_ref())?;
+                commands.borrow_mut().push(PluginCommand::NewWindow { command, cwd });
+                Ok(())
+            })?,
+        )?;
+
+        lua.globals().set("umiterm", api)
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // フックの呼び出し
+    // ═══════════════════════════════════════════════════════════════════════
+
+    /// プラグインを1つも読み込んでいないか
+    pub fn is_empty(&self) -> bool {
+        self.loaded == 0
+    }
+
+    /// フックがあるか
+    pub fn has_hook(&self, name: &str) -> bool {
+        self.hooks(name).is_some_and(|hooks| !hooks.is_empty())
+    }
+
+    /// 名前のフック（登録した順）
+    fn hooks(&self, name: &str) -> Option<Vec<Function<'_>>> {
+        let hooks: Table = self.lua.named_registry_value(HOOKS).ok()?;
+        let list: Table = hooks.get(name).ok()?;
+        list.sequence_values::<Function>().collect::<mlua::Result<_>>().ok()
+    }
+
+    /// フックを順に呼び、戻り値を返す（エラーはログに出して飛ばす、時間切れのフックは外す）
+    fn call<'lua>(&'lua self, name: &str, args: impl Fn() -> mlua::Result<MultiValue<'lua>>) -> Vec<Value<'lua>> {
+        let Some(hooks) = self.hooks(name) else { return Vec::new() };
+        hooks
+            .into_iter()
+            .filter_map(|hook| match self.limited(|| args().and_then(|args| hook.call::<_, Value>(args))) {
+                (Ok(value), _) => Some(value),
+                (Err(e), timed_out) => {
+                    log::error!("プラグインの {} フックでエラー: {}", name, e);
+                    if timed_out {
+                        self.remove_hook(name, &hook);
+                        notification::notify("UmiTerm", &format!("時間がかかりすぎるプラグインの {} フックを止めました", name));
+                    }
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// フックを外す
+    fn remove_hook(&self, name: &str, hook: &Function) {
+        let Some(hooks) = self.hooks(name) else { return };
+        let Some(index) = hooks.iter().position(|registered| registered == hook) else { return };
+        let list = self.lua.named_registry_value::<Table>(HOOKS).and_then(|hooks| hooks.get::<_, Table>(name));
+        if let Err(e) = list.and_then(|list| list.raw_remove(index + 1)) {
+            log::error!("プラグインの {} フックを外せません: {}", name, e);
+        }
+    }
+
+    /// 起動したとき（最初のウィンドウを作ったあと）
+    pub fn startup(&self) {
+        self.call("startup", || Ok(MultiValue::new()));
+    }
+
+    /// 溜まったイベントのフックを呼ぶ
+    pub fn dispatch_events(&self) {
+        let Some(events) = &self.events else { return };
+        for event in events.try_iter() {
+            let name = event_hook(&event);
+            if self.has_hook(name) {
+                self.call(name, || self.lua.pack_multi(event_table(&self.lua, &event)?));
+            }
+        }
+    }
+
+    /// 出力のフックがあるか（出力し終えた行を取り出すか）
+    pub fn wants_output(&self) -> bool {
+        self.has_hook("output-line")
+    }
+
+    /// ペインに出力し終えた行
+    pub fn output_lines(&self, pane: PaneId, lines: &[(u64, String)]) {
+        for (_, line) in lines {
+            self.call("output-line", || self.lua.pack_multi((line.as_str(), pane.0)));
+        }
+    }
+
+    /// 押したキー（`ctrl+shift+k` の形）、true を返したフックがあればキーを使ったとみなす
+    pub fn key(&self, key: &str, pane: PaneId) -> bool {
+        self.call("key", || self.lua.pack_multi((key, pane.0)))
+            .iter()
+            .any(|value| matches!(value, Value::Boolean(true)))
+    }
+
+    /// タブのタイトル（文字列を返したフックがなければ None）
+    pub fn format_tab_title(&self, tab: &PluginTab) -> Option<String> {
+        self.call("format-tab-title", || {
+            let table = self.lua.create_table()?;
+            table.set("index", tab.index + 1)?;
+            table.set("active", tab.active)?;
+            table.set("pane", tab.pane.0)?;
+            table.set("title", tab.title)?;
+            self.lua.pack_multi(table)
+        })
+        .into_iter()
+        .find_map(|value| match value {
+            Value::String(title) => title.to_str().ok().map(str::to_string),
+            _ => None,
+        })
+    }
+
+    /// `umiterm.add_command` で足したコマンドの名前（足した順）
+    pub fn palette_commands(&self) -> Vec<String> {
+        self.palette_entries().into_iter().filter_map(|entry| entry.get("name").ok()).collect()
+    }
+
+    /// パレットで選んだコマンド（palette_commands の番号）を呼ぶ（時間切れのコマンドは外す）
+    pub fn run_palette_command(&self, index: usize, pane: PaneId) {
+        let Some(entry) = self.palette_entries().into_iter().nth(index) else { return };
+        let (result, timed_out) =
+            self.limited(|| entry.get::<_, Function>("command").and_then(|command| command.call::<_, ()>(pane.0)));
+        if let Err(e) = result {
+            log::error!("プラグインのコマンドでエラー: {}", e);
+        }
+        if timed_out {
+            let list = self.lua.named_registry_value::<Table>(PALETTE);
+            if let Err(e) = list.and_then(|list| list.raw_remove(index + 1)) {
+                log::error!("プラグインのコマンドを外せません: {}", e);
+            }
+            notification::notify("UmiTerm", "時間がかかりすぎるプラグインのコマンドを外しました");
+        }
+    }
+
+    /// `umiterm.add_command` で足したコマンドのテーブル
+    fn palette_entries(&self) -> Vec<Table<'_>> {
+        self.lua
+            .named_registry_value::<Table>(PALETTE)
+            .and_then(|list| list.sequence_values::<Table>().collect())
+            .unwrap_or_default()
+    }
+
+    /// 設定のステータスバー（表示しないなら None）に、`umiterm.add_status_segment` で足したセグメントを足したもの
+    pub fn status_bar(plugins: &Rc<Self>) -> Option<StatusBar> {
+        let mut bar = StatusBar::from_env()?;
+        for (index, entry) in plugins.status_entries().into_iter().enumerate() {
+            let align = match entry.get::<_, String>("align").as_deref() {
+                Ok("right") => Align::Right,
+                _ => Align::Left,
+            };
+            bar.add_segment(Box::new(PluginSegment { plugins: plugins.clone(), index, align }));
+        }
+        Some(bar)
+    }
+
+    /// `umiterm.add_status_segment` で足したセグメントの文字列（時間切れのセグメントは外す）
+    fn status_text(&self, index: usize, ctx: &StatusContext) -> Option<String> {
+        let entry = self.status_entries().into_iter().nth(index)?;
+        // 外したセグメントは nil
+        let segment: Function = entry.get("segment").ok()?;
+        let (result, timed_out) = self.limited(|| {
+            let status = self.lua.create_table()?;
+            status.set("cwd", ctx.cwd.to_string_lossy())?;
+            status.set("pane", ctx.pane_index + 1)?;
+            status.set("panes", ctx.pane_count)?;
+            segment.call::<_, Option<String>>(status)
+        });
+        if timed_out {
+            let _ = entry.set("segment", Value::Nil);
+            notification::notify("UmiTerm", "時間がかかりすぎるプラグインのステータスバーのセグメントを外しました");
+        }
+        result.inspect_err(|e| log::error!("プラグインのステータスバーのセグメントでエラー: {}", e)).ok().flatten()
+    }
+
+    /// `umiterm.add_status_segment` で足したセグメントのテーブル
+    fn status_entries(&self) -> Vec<Table<'_>> {
+        self.lua
+            .named_registry_value::<Table>(STATUS)
+            .and_then(|list| list.sequence_values::<Table>().collect())
+            .unwrap_or_default()
+    }
+
+    /// `umiterm.panes()` の一覧を新しくする
+    pub fn set_panes(&self, panes: Vec<PluginPane>) {
+        *self.panes.borrow_mut() = panes;
+    }
+
+    /// フックから頼まれた操作を取り出す
+    pub fn take_commands(&self) -> Vec<PluginCommand> {
+        std::mem::take(&mut self.commands.borrow_mut())
+    }
+}
+
+/// `umiterm.add_status_segment` で足したステータスバーのセグメント
+struct PluginSegment {
+    plugins: Rc<Plugins>,
+    /// 足した順の番号
+    index: usize,
+    align: Align,
+}
+
+impl StatusSegment for PluginSegment {
+    fn align(&self) -> Align {
+        self.align
+    }
+
+    fn text(&mut self, ctx: &StatusContext) -> Option<String> {
+        self.plugins.status_text(self.index, ctx)
+    }
+}
+
+/// ペインの Lua のテーブル
+fn pane_table<'lua>(lua: &'lua Lua, pane: &PluginPane) -> mlua::Result<Table<'lua>> {
+    let table = lua.create_table()?;
+    table.set("id", pane.id)?;
+    table.set("window", pane.window)?;
+    table.set("tab", pane.tab + 1)?;
+    table.set("title", pane.title.as_str())?;
+    table.set("cwd", pane.cwd.as_str())?;
+    table.set("focused", pane.focused)?;
+    Ok(table)
+}
+
+/// イベントの Lua のテーブル（`umiterm cli subscribe` の JSON と同じ項目）
+fn event_table<'lua>(lua: &'lua Lua, event: &Event) -> mlua::Result<Table<'lua>> {
+    let table = lua.create_table()?;
+    let json = serde_json::to_value(event).map_err(mlua::Error::external)?;
+    for (key, value) in json.as_object().into_iter().flatten() {
+        let value = match value {
+            serde_json::Value::String(text) => Value::String(lua.create_string(text)?),
+            serde_json::Value::Number(number) => number.as_i64().map_or(Value::Nil, Value::Integer),
+            _ => Value::Nil,
+        };
+        table.set(key.as_str(), value)?;
+    }
+    Ok(table)
+}
+
+/// 打ち切る時刻を過ぎたらエラーにする Lua のフック
+///
+/// 時間切れのあとは命令ごとに確かめる（pcall で受け止めても、その外の命令でまたエラーになる）
+fn limit_hook(
+    deadline: Rc<Cell<Option<Instant>>>,
+    timed_out: Rc<Cell<bool>>,
+) -> impl Fn(&Lua, mlua::Debug) -> mlua::Result<()> {
+    move |lua, _| {
+        if deadline.get().is_none_or(|deadline| Instant::now() < deadline) {
+            return Ok(());
+        }
+        if !timed_out.replace(true) {
+            lua.set_hook(HookTriggers::new().every_nth_instruction(1), limit_hook(deadline.clone(), timed_out.clone()));
+        }
+        Err(mlua::Error::runtime(format!("{}ms 以内に終わらないため止めました", HOOK_TIME_LIMIT.as_millis())))
+    }
+}
+
+/// `umiterm.config("font.size")` の値
+///
+/// 設定ファイルに書いた値（表は Lua のテーブル）、`UMITERM_FONT` のような環境変数の名前なら環境変数を優先した値
+fn config_value<'lua>(lua: &'lua Lua, name: &str) -> mlua::Result<Value<'lua>> {
+    if name.starts_with("UMITERM_") {
+        return config::var(name).map_or(Ok(Value::Nil), |value| Ok(Value::String(lua.create_string(value)?)));
+    }
+    config::current().get(name).map_or(Ok(Value::Nil), |value| toml_value(lua, value))
+}
+
+/// 設定ファイルの値の Lua の値（日時は文字列）
+fn toml_value<'lua>(lua: &'lua Lua, value: &toml::Value) -> mlua::Result<Value<'lua>> {
+    Ok(match value {
+        toml::Value::String(text) => Value::String(lua.create_string(text)?),
+        toml::Value::Integer(number) => Value::Integer(*number),
+        toml::Value::Float(number) => Value::Number(*number),
+        toml::Value::Boolean(flag) => Value::Boolean(*flag),
+        toml::Value::Datetime(datetime) => Value::String(lua.create_string(datetime.to_string())?),
+        toml::Value::Array(values) => {
+            let values = values.iter().map(|value| toml_value(lua, value)).collect::<mlua::Result<Vec<_>>>()?;
+            Value::Table(lua.create_sequence_from(values)?)
+        }
+        toml::Value::Table(table) => {
+            let lua_table = lua.create_table()?;
+            for (key, value) in table {
+                lua_table.set(key.as_str(), toml_value(lua, value)?)?;
+            }
+            Value::Table(lua_table)
+        }
+    })
+}
+
+/// `{ command = "htop", cwd = "~/src" }` を読む
+fn launch_options(options: Option<&Table>) -> mlua::Result<(PaneCommand, Option<PathBuf>)> {
+    let Some(options) = options else { return Ok((PaneCommand::Shell, None)) };
+    let command = options.get::<_, Option<String>>("command")?.map_or(PaneCommand::Shell, PaneCommand::Run);
+    let cwd = options.get::<_, Option<String>>("cwd")?.map(|cwd| expand_home(&cwd));
+    Ok((command, cwd))
+}
+
+/// `umiterm.split({ pane = 3, horizontal = true, command = "htop", cwd = "~/src" })` を読む
+fn split_command(options: Option<&Table>) -> mlua::Result<PluginCommand> {
+    let (command, cwd) = launch_options(options)?;
+    let (pane, horizontal) = match options {
+        Some(options) => (options.get("pane")?, options.get::<_, Option<bool>>("horizontal")?.unwrap_or(false)),
+        None => (None, false),
+    };
+    Ok(PluginCommand::Split { pane, horizontal, command, cwd })
+}
+
+/// 先頭の `~/` をホームディレクトリにする
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plugins(source: &str) -> Plugins {
+        Plugins::from_sources([(PathBuf::from("test.lua"), source.to_string())])
+    }
+
+    #[test]
+    fn test_hooks() {
+        let plugins = plugins(
+            r#"
+            umiterm.on("key", function(key, pane)
+              if key == "ctrl+alt+d" then
+                umiterm.split({ pane = pane, horizontal = true, command = "htop" })
+                return true
+              end
+            end)
+            umiterm.on("output-line", function(line, pane)
+              if line:find("FAIL") then umiterm.send_text("echo " .. pane .. "\r") end
+            end)
+            umiterm.on("format-tab-title", function(tab)
+              if tab.active then return tab.index .. ": " .. tab.title end
+            end)
+            "#,
+        );
+        assert!(!plugins.is_empty());
+        assert!(plugins.wants_output());
+        assert!(!plugins.has_hook("startup"));
+
+        // true を返したフックがあればキーを使う
+        assert!(plugins.key("ctrl+alt+d", PaneId(3)));
+        assert!(!plugins.key("ctrl+c", PaneId(3)));
+        plugins.output_lines(PaneId(3), &[(1, "ok".into()), (2, "FAIL tests".into())]);
+        assert_eq!(
+            plugins.take_commands(),
+            [
+                PluginCommand::Split {
+                    pane: Some(3),
+                    horizontal: true,
+                    command: PaneCommand::Run("htop".into()),
+                    cwd: None,
+                },
+                PluginCommand::SendText { pane: None, text: "echo 3\r".into() },
+            ]
+        );
+        assert!(plugins.take_commands().is_empty());
+
+        let tab = |active| PluginTab { index: 1, active, pane: PaneId(3), title: "zsh" };
+        assert_eq!(plugins.format_tab_title(&tab(true)).as_deref(), Some("2: zsh"));
+        assert_eq!(plugins.format_tab_title(&tab(false)), None);
+    }
+
+    #[test]
+    fn test_api() {
+        let plugins = plugins(
+            r#"
+            umiterm.on("startup", function()
+              for _, pane in ipairs(umiterm.panes()) do
+                if pane.focused then umiterm.activate(pane.id) end
+              end
+              umiterm.action("new-tab")
+              umiterm.new_window({ cwd = "/tmp" })
+            end)
+            umiterm.on("bell", function(event) umiterm.send_text(event.event, event.pane) end)
+            umiterm.add_command("dev layout", function(pane) umiterm.split({ pane = pane }) end)
+            "#,
+        );
+        plugins.set_panes(vec![PluginPane {
+            id: 7,
+            window: 1,
+            tab: 0,
+            title: "zsh".into(),
+            cwd: "~".into(),
+            focused: true,
+        }]);
+        plugins.startup();
+        assert_eq!(
+            plugins.take_commands(),
+            [
+                PluginCommand::Activate { pane: Some(7) },
+                PluginCommand::Action { action: BindAction::NewTab, text: None },
+                PluginCommand::NewWindow { command: PaneCommand::Shell, cwd: Some("/tmp".into()) },
+            ]
+        );
+
+        // パレットのコマンドは選ばれたときにフォーカス中のペインを受け取る
+        assert_eq!(plugins.palette_commands(), ["dev layout"]);
+        plugins.run_palette_command(0, PaneId(7));
+        plugins.run_palette_command(1, PaneId(7));
+        assert_eq!(
+            plugins.take_commands(),
+            [PluginCommand::Split { pane: Some(7), horizontal: false, command: PaneCommand::Shell, cwd: None }]
+        );
+
+        // イベントのフックは subscribe と同じ項目のテーブルを受け取る（ほかのテストのイベントは無視）
+        events::emit(Event::Bell { pane: 1 << 40 });
+        plugins.dispatch_events();
+        let commands = plugins.take_commands();
+        assert!(commands.contains(&PluginCommand::SendText { pane: Some(1 << 40), text: "bell".into() }));
+    }
+
+    #[test]
+    fn test_errors() {
+        // 読み込めないファイルは飛ばし、フックのエラーはほかのフックを止めない
+        let plugins = Plugins::from_sources([
+            (PathBuf::from("broken.lua"), "umiterm.on(".to_string()),
+            (PathBuf::from("bad-hook.lua"), r#"umiterm.on("no-such-hook", print)"#.to_string()),
+            (
+                PathBuf::from("ok.lua"),
+                r#"
+                umiterm.on("key", function() error("boom") end)
+                umiterm.on("key", function() umiterm.action("fly") end)
+                umiterm.on("key", function() return true end)
+                "#
+                .to_string(),
+            ),
+        ]);
+        assert!(!plugins.is_empty());
+        assert!(plugins.key("a", PaneId(1)));
+        assert!(plugins.take_commands().is_empty());
+        assert!(Plugins::from_sources([]).is_empty());
+    }
+
+    #[test]
+    fn test_status_segments() {
+        let plugins = Rc::new(plugins(
+            r#"
+            umiterm.add_status_segment(function(status) return "pane " .. status.pane .. "/" .. status.panes end, "right")
+            umiterm.add_status_segment(function(status) if status.cwd ~= "/" then return status.cwd end end)
+            umiterm.add_status_segment(function() error("boom") end)
+            "#,
+        ));
+        assert!(Plugins::from_sources([(PathBuf::from("bad.lua"), r#"umiterm.add_status_segment(print, "top")"#.to_string())])
+            .is_empty());
+
+        // 文字列を返さないセグメントとエラーのセグメントは出さない
+        let mut bar = StatusBar::from_names("");
+        for (index, align) in [(0, Align::Right), (1, Align::Left), (2, Align::Left)] {
+            bar.add_segment(Box::new(PluginSegment { plugins: plugins.clone(), index, align }));
+        }
+        let ctx = |cwd: &str| StatusContext { cwd: cwd.into(), pane_index: 1, pane_count: 2 };
+        assert!(bar.refresh(ctx("/"), Instant::now()));
+        assert_eq!(bar.line().right, ["pane 2/2"]);
+        assert!(bar.line().left.is_empty());
+        assert!(bar.refresh(ctx("/tmp"), Instant::now()));
+        assert_eq!(bar.line().left, ["/tmp"]);
+    }
+
+    #[test]
+    fn test_time_limit() {
+        // 終わらないファイルは読み込まず、終わらないフック・コマンドは打ち切って外す
+        let plugins = Plugins::from_sources([
+            (PathBuf::from("loop.lua"), "while true do end".to_string()),
+            (
+                PathBuf::from("ok.lua"),
+                r#"
+                umiterm.on("key", function() while true do pcall(function() end) end end)
+                umiterm.on("key", function() return true end)
+                umiterm.add_command("spin", function() while true do end end)
+                "#
+                .to_string(),
+            ),
+        ]);
+        assert_eq!(plugins.loaded, 1);
+        let started = Instant::now();
+        assert!(plugins.key("a", PaneId(1)));
+        assert!(started.elapsed() < HOOK_TIME_LIMIT * 5);
+        assert_eq!(plugins.hooks("key").map(|hooks| hooks.len()), Some(1));
+        assert!(plugins.key("a", PaneId(1)));
+
+        plugins.run_palette_command(0, PaneId(1));
+        assert!(plugins.palette_commands().is_empty());
+    }
+
+    #[test]
+    fn test_config_value() {
+        let lua = Lua::new();
+        let value = |text: &str| {
+            let table: toml::Table = toml::from_str(text).unwrap();
+            toml_value(&lua, &toml::Value::Table(table)).unwrap()
+        };
+        let Value::Table(table) = value("[font]\nsize = 18\n[window]\nopacity = 0.5\nargs = [\"-l\"]") else { panic!() };
+        let font: Table = table.get("font").unwrap();
+        assert_eq!(font.get::<_, i64>("size").unwrap(), 18);
+        let window: Table = table.get("window").unwrap();
+        assert_eq!(window.get::<_, f64>("opacity").unwrap(), 0.5);
+        assert_eq!(window.get::<_, Vec<String>>("args").unwrap(), ["-l"]);
+    }
+}
@@ -0,0 +1,412 @@
+//! ファイルのプレビュー
+//!
+//! エクスプローラーで選択中のファイルの先頭の行を、よく使う言語なら
+//! キーワード・文字列・コメント・数値を色分けして表示する
+//! 画像（png / jpg / gif の最初のフレーム）は縮小したサムネイルを表示する
+//! 大きなファイルでも UI スレッドを止めないよう、読み込みは別スレッドで行う
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::idle;
+
+/// プレビューする行数
+pub const PREVIEW_LINES: usize = 50;
+
+/// 読み込む最大バイト数（先頭だけ読めば足りる）
+const MAX_PREVIEW_BYTES: u64 = 64 * 1024;
+
+/// サムネイルの最大の大きさ（ピクセル、縦横比は保つ）
+pub const THUMBNAIL_SIZE: u32 = 96;
+
+/// サムネイルを作る画像ファイルの最大の大きさ（巨大な画像でメモリを使い切らないように）
+const MAX_IMAGE_BYTES: u64 = 32 * 1024 * 1024;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 色分け
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 色分けの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Plain,
+    Keyword,
+    String,
+    Comment,
+    Number,
+}
+
+/// プレビューの1行（色分けした断片）
+pub type PreviewLine = Vec<(TokenKind, String)>;
+
+/// 言語ごとの色分けの規則
+struct Syntax {
+    keywords: &'static [&'static str],
+    /// 行コメントの始まり
+    line_comment: &'static str,
+    /// 単一引用符を文字列とみなすか（Rust のライフタイムなどと区別）
+    single_quote: bool,
+}
+
+const RUST: Syntax = Syntax {
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "else", "enum", "false", "fn", "for", "if",
+        "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self",
+        "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+    ],
+    line_comment: "//",
+    single_quote: false,
+};
+
+const PYTHON: Syntax = Syntax {
+    keywords: &[
+        "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif", "else",
+        "except", "False", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "None",
+        "not", "or", "pass", "raise", "return", "True", "try", "while", "with", "yield",
+    ],
+    line_comment: "#",
+    single_quote: true,
+};
+
+const JAVASCRIPT: Syntax = Syntax {
+    keywords: &[
+        "async", "await", "break", "case", "catch", "class", "const", "continue", "default", "else", "export",
+        "extends", "false", "finally", "for", "from", "function", "if", "import", "interface", "let", "new",
+        "null", "return", "switch", "this", "throw", "true", "try", "type", "undefined", "var", "while",
+    ],
+    line_comment: "//",
+    single_quote: true,
+};
+
+const GO: Syntax = Syntax {
+    keywords: &[
+        "break", "case", "chan", "const", "continue", "default", "defer", "else", "false", "for", "func", "go",
+        "if", "import", "interface", "map", "nil", "package", "range", "return", "select", "struct", "switch",
+        "true", "type", "var",
+    ],
+    line_comment: "//",
+    single_quote: false,
+};
+
+const C: Syntax = Syntax {
+    keywords: &[
+        "break", "case", "char", "class", "const", "continue", "default", "do", "double", "else", "enum",
+        "extern", "float", "for", "if", "include", "int", "long", "namespace", "return", "sizeof", "static",
+        "struct", "switch", "typedef", "unsigned", "void", "while",
+    ],
+    line_comment: "//",
+    single_quote: false,
+};
+
+const SHELL: Syntax = Syntax {
+    keywords: &[
+        "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if", "in", "local",
+        "return", "then", "while",
+    ],
+    line_comment: "#",
+    single_quote: true,
+};
+
+const CONFIG: Syntax = Syntax { keywords: &["true", "false", "null"], line_comment: "#", single_quote: true };
+
+/// 拡張子から色分けの規則を選ぶ（知らない言語なら色分けしない）
+fn syntax_for(path: &Path) -> Option<&'static Syntax> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "rs" => Some(&RUST),
+        "py" => Some(&PYTHON),
+        "js" | "mjs" | "cjs" | "ts" | "tsx" | "jsx" => Some(&JAVASCRIPT),
+        "go" => Some(&GO),
+        "c" | "h" | "cpp" | "cc" | "hpp" => Some(&C),
+        "sh" | "bash" | "zsh" => Some(&SHELL),
+        "toml" | "yaml" | "yml" => Some(&CONFIG),
+        _ => None,
+    }
+}
+
+/// 1行を色分けする（文字列・コメントは行をまたがない簡易な判定）
+fn highlight(line: &str, syntax: Option<&Syntax>) -> PreviewLine {
+    let Some(syntax) = syntax else {
+        return vec![(TokenKind::Plain, line.to_string())];
+    };
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens: PreviewLine = Vec::new();
+    let mut push = |kind: TokenKind, text: String| match tokens.last_mut() {
+        Some((last, prev)) if *last == kind => prev.push_str(&text),
+        _ => tokens.push((kind, text)),
+    };
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let rest: String = chars[i..].iter().collect();
+        if rest.starts_with(syntax.line_comment) {
+            push(TokenKind::Comment, rest);
+            break;
+        }
+        if c == '"' || (c == '\'' && syntax.single_quote) {
+            // 閉じる引用符まで（\ でエスケープした引用符は飛ばす）
+            let mut end = i + 1;
+            while end < chars.len() && chars[end] != c {
+                end += if chars[end] == '\\' { 2 } else { 1 };
+            }
+            let end = (end + 1).min(chars.len());
+            push(TokenKind::String, chars[i..end].iter().collect());
+            i = end;
+            continue;
+        }
+        if c.is_alphanumeric() || c == '_' {
+            let mut end = i + 1;
+            let number = c.is_ascii_digit();
+            while end < chars.len() && (chars[end].is_alphanumeric() || chars[end] == '_' || (number && chars[end] == '.')) {
+                end += 1;
+            }
+            let word: String = chars[i..end].iter().collect();
+            let kind = if number {
+                TokenKind::Number
+            } else if syntax.keywords.contains(&word.as_str()) {
+                TokenKind::Keyword
+            } else {
+                TokenKind::Plain
+            };
+            push(kind, word);
+            i = end;
+            continue;
+        }
+        push(TokenKind::Plain, c.to_string());
+        i += 1;
+    }
+    tokens
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 読み込み
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// プレビューの内容
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PreviewContent {
+    /// 読み込み中
+    Loading,
+    /// テキスト（先頭の PREVIEW_LINES 行）
+    Text(Vec<PreviewLine>),
+    /// 画像のサムネイル
+    Image(Thumbnail),
+    /// バイナリファイル（表示しない）
+    Binary,
+    /// 読み込めなかった
+    Error(String),
+}
+
+/// 画像の縮小版（RGBA、左上から行ごと）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thumbnail {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 4]>,
+    /// 元の画像の大きさ
+    pub original: (u32, u32),
+}
+
+/// サムネイルを作る画像か（拡張子で判断）
+fn is_image(path: &Path) -> bool {
+    let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+    matches!(extension.as_deref(), Some("png" | "jpg" | "jpeg" | "gif"))
+}
+
+/// 画像を読み込んで縮小する（gif は最初のフレーム）
+fn load_image(path: &Path) -> PreviewContent {
+    let size = std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+    if size > MAX_IMAGE_BYTES {
+        return PreviewContent::Error("画像が大きすぎます".to_string());
+    }
+    let image = match image::open(path) {
+        Ok(image) => image,
+        Err(e) => return PreviewContent::Error(e.to_string()),
+    };
+    let original = (image.width(), image.height());
+    // 小さな画像は拡大しない（描画側でセルの範囲に合わせる）
+    let thumbnail = if original.0 > THUMBNAIL_SIZE || original.1 > THUMBNAIL_SIZE {
+        image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).into_rgba8()
+    } else {
+        image.into_rgba8()
+    };
+    PreviewContent::Image(Thumbnail {
+        width: thumbnail.width(),
+        height: thumbnail.height(),
+        pixels: thumbnail.pixels().map(|pixel| pixel.0).collect(),
+        original,
+    })
+}
+
+/// ファイルの先頭を読んで色分けする（画像ならサムネイルを作る）
+fn load(path: &Path) -> PreviewContent {
+    if is_image(path) {
+        return load_image(path);
+    }
+    let mut bytes = Vec::new();
+    let result = File::open(path).and_then(|file| file.take(MAX_PREVIEW_BYTES).read_to_end(&mut bytes));
+    if let Err(e) = result {
+        return PreviewContent::Error(e.to_string());
+    }
+    if bytes.contains(&0) {
+        return PreviewContent::Binary;
+    }
+    let syntax = syntax_for(path);
+    let text = String::from_utf8_lossy(&bytes);
+    let lines = text
+        .lines()
+        .take(PREVIEW_LINES)
+        .map(|line| highlight(&line.replace('\t', "    "), syntax))
+        .collect();
+    PreviewContent::Text(lines)
+}
+
+/// 表示中のプレビュー
+pub struct Preview {
+    pub path: PathBuf,
+    pub content: PreviewContent,
+}
+
+/// 選択中のファイルのプレビュー（読み込みは別スレッド）
+pub struct Previewer {
+    /// 表示中のプレビュー
+    pub current: Option<Preview>,
+    /// 読み込みスレッドからの結果
+    tx: Sender<(PathBuf, PreviewContent)>,
+    rx: Receiver<(PathBuf, PreviewContent)>,
+}
+
+impl Previewer {
+    pub fn new() -> Self {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        Self { current: None, tx, rx }
+    }
+
+    /// プレビューするファイルを変える（None なら表示しない）
+    /// 戻り値: 表示が変わったか
+    pub fn show(&mut self, path: Option<&Path>) -> bool {
+        if self.current.as_ref().map(|preview| preview.path.as_path()) == path {
+            return false;
+        }
+        self.current = path.map(|path| {
+            let path = path.to_path_buf();
+            let tx = self.tx.clone();
+            let target = path.clone();
+            std::thread::spawn(move || {
+                let content = load(&target);
+                let _ = tx.send((target, content));
+                // 省電力モードで眠っているイベントループを起こす
+                idle::notify_output();
+            });
+            Preview { path, content: PreviewContent::Loading }
+        });
+        true
+    }
+
+    /// 読み込み終わった結果を取り込む（もう選択していないファイルの結果は捨てる）
+    /// 戻り値: 表示が変わったか
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok((path, content)) = self.rx.try_recv() {
+            if let Some(preview) = self.current.as_mut().filter(|preview| preview.path == path) {
+                preview.content = content;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_highlight() {
+        let tokens = highlight(r#"let s = "a\"b"; // 42"#, Some(&RUST));
+        assert_eq!(
+            tokens,
+            [
+                (TokenKind::Keyword, "let".to_string()),
+                (TokenKind::Plain, " s = ".to_string()),
+                (TokenKind::String, r#""a\"b""#.to_string()),
+                (TokenKind::Plain, "; ".to_string()),
+                (TokenKind::Comment, "// 42".to_string()),
+            ]
+        );
+
+        // 数値と、Rust の ' は文字列にしない
+        let tokens = highlight("x: &'a f32 = 1.5", Some(&RUST));
+        assert!(tokens.contains(&(TokenKind::Number, "1.5".to_string())));
+        assert!(!tokens.iter().any(|(kind, _)| *kind == TokenKind::String));
+
+        // 知らない言語は色分けしない
+        assert_eq!(highlight("fn x", None), [(TokenKind::Plain, "fn x".to_string())]);
+    }
+
+    #[test]
+    fn test_load_in_background() {
+        let dir = std::env::temp_dir().join(format!("umiterm-preview-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let text = dir.join("a.py");
+        let binary = dir.join("b.bin");
+        std::fs::write(&text, "def f():\n\treturn None\n").unwrap();
+        std::fs::write(&binary, [0x7f, b'E', 0, 1]).unwrap();
+        assert_eq!(load(&binary), PreviewContent::Binary);
+
+        let mut previewer = Previewer::new();
+        assert!(previewer.show(Some(&text)));
+        assert!(!previewer.show(Some(&text)));
+        assert_eq!(previewer.current.as_ref().unwrap().content, PreviewContent::Loading);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !previewer.poll() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let PreviewContent::Text(lines) = &previewer.current.as_ref().unwrap().content else {
+            panic!("読み込めていない");
+        };
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1][1], (TokenKind::Keyword, "return".to_string()));
+
+        // 表示しなければ消える
+        assert!(previewer.show(None));
+        assert!(previewer.current.is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_image() {
+        let dir = std::env::temp_dir().join(format!("umiterm-preview-image-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // 小さな画像はそのまま
+        let small = dir.join("small.png");
+        image::RgbaImage::from_fn(4, 2, |x, _| image::Rgba([x as u8 * 60, 0, 0, 255])).save(&small).unwrap();
+        let PreviewContent::Image(thumbnail) = load(&small) else { panic!("サムネイルになっていない") };
+        assert_eq!((thumbnail.width, thumbnail.height, thumbnail.original), (4, 2, (4, 2)));
+        assert_eq!(thumbnail.pixels[1], [60, 0, 0, 255]);
+
+        // 大きな画像は縦横比を保って縮小
+        let large = dir.join("large.PNG");
+        image::RgbaImage::new(300, 150).save_with_format(&large, image::ImageFormat::Png).unwrap();
+        let PreviewContent::Image(thumbnail) = load(&large) else { panic!("サムネイルになっていない") };
+        assert_eq!((thumbnail.width, thumbnail.height), (THUMBNAIL_SIZE, THUMBNAIL_SIZE / 2));
+        assert_eq!(thumbnail.original, (300, 150));
+
+        // 壊れた画像はエラー
+        let broken = dir.join("broken.jpg");
+        std::fs::write(&broken, "not an image").unwrap();
+        assert!(matches!(load(&broken), PreviewContent::Error(_)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+//! 自分だけが使える Unix ドメインソケット
+//!
+//! セッションデーモンとリモート操作のソケットは、キー入力・画面の文字・コマンドの実行を扱うため、
+//! ほかのユーザーに先に作られたり接続されたりしないようにする
+//!
+//! - 既定の場所は自分が持ち主の 0700 のディレクトリの中（`$XDG_RUNTIME_DIR/umiterm`、なければ `/tmp/umiterm-<uid>`）
+//! - umask を絞ってから bind する（作ってから権限を変えるまでの隙間をなくす）
+//! - 接続の相手（待ち受け側・接続した側の両方）が自分と同じユーザーか確かめる
+
+use std::fs::{self, DirBuilder};
+use std::io;
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt, PermissionsExt};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use parking_lot::Mutex;
+
+/// umask を変えている間はほかのスレッドに変えさせない
+static UMASK: Mutex<()> = Mutex::new(());
+
+/// 自分のユーザーID
+pub fn uid() -> u32 {
+    // SAFETY: getuid は失敗しない
+    unsafe { libc::getuid() }
+}
+
+/// ソケットを置く自分専用のディレクトリ（作らない）
+pub fn dir() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime) => PathBuf::from(runtime).join("umiterm"),
+        None => std::env::temp_dir().join(format!("umiterm-{}", uid())),
+    }
+}
+
+/// 自分専用のディレクトリの中のソケットのパス
+pub fn default_path(name: &str) -> PathBuf {
+    dir().join(name)
+}
+
+/// 自分専用のディレクトリを 0700 で作り、ほかのユーザーが手を出せないか確かめる
+fn ensure_dir(dir: &Path) -> Result<()> {
+    match DirBuilder::new().mode(0o700).create(dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e).with_context(|| format!("ディレクトリを作れません: {}", dir.display())),
+    }
+    check_dir(dir)
+}
+
+/// ディレクトリが自分の持ち主で、ほかのユーザーが読み書きできないか（シンボリックリンクは辿らない）
+fn check_dir(dir: &Path) -> Result<()> {
+    let meta = fs::symlink_metadata(dir).with_context(|| format!("ディレクトリを調べられません: {}", dir.display()))?;
+    if !meta.is_dir() {
+        bail!("ディレクトリではありません: {}", dir.display());
+    }
+    if meta.uid() != uid() {
+        bail!("ほかのユーザーのディレクトリです（uid {}）: {}", meta.uid(), dir.display());
+    }
+    if meta.permissions().mode() & 0o077 != 0 {
+        bail!("ほかのユーザーも使えるディレクトリです（{:o}）: {}", meta.permissions().mode() & 0o777, dir.display());
+    }
+    Ok(())
+}
+
+/// 既定の場所なら、置き場所のディレクトリを確かめる（自分で指定した場所はそのまま使う）
+fn check_location(path: &Path, create: bool) -> Result<()> {
+    let dir = dir();
+    if path.parent() != Some(dir.as_path()) {
+        return Ok(());
+    }
+    if create {
+        ensure_dir(&dir)
+    } else {
+        check_dir(&dir)
+    }
+}
+
+/// 待ち受ける（ソケットは作ったときから 0600）
+///
+/// 前回残ったソケットは、自分のソケットのときだけ消す（ほかのユーザーのファイルには触らない）
+pub fn bind(path: &Path) -> Result<UnixListener> {
+    check_location(path, true)?;
+    if let Ok(meta) = fs::symlink_metadata(path) {
+        if !meta.file_type().is_socket() || meta.uid() != uid() {
+            bail!("自分のソケットではないファイルがあります: {}", path.display());
+        }
+        fs::remove_file(path).with_context(|| format!("古いソケットを消せません: {}", path.display()))?;
+    }
+    let _guard = UMASK.lock();
+    // SAFETY: umask は失敗しない（戻り値は前の値）
+    let previous = unsafe { libc::umask(0o177) };
+    let listener = UnixListener::bind(path);
+    unsafe { libc::umask(previous) };
+    listener.with_context(|| format!("ソケットの作成に失敗: {}", path.display()))
+}
+
+/// 接続する（相手が自分と同じユーザーでなければ切る）
+pub fn connect(path: &Path) -> Result<UnixStream> {
+    check_location(path, false)?;
+    let stream = UnixStream::connect(path).with_context(|| format!("接続できません: {}", path.display()))?;
+    check_peer(&stream)?;
+    Ok(stream)
+}
+
+/// 接続の相手が自分と同じユーザーか
+pub fn check_peer(stream: &UnixStream) -> Result<()> {
+    let peer = peer_uid(stream).context("接続の相手のユーザーを調べられません")?;
+    if peer != uid() {
+        bail!("ほかのユーザー（uid {}）からの接続です", peer);
+    }
+    Ok(())
+}
+
+/// 接続の相手のユーザーID
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let mut cred = libc::ucred { pid: 0, uid: 0, gid: 0 };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: cred と len は SO_PEERCRED の結果を入れるのに十分な大きさ
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut cred as *mut libc::ucred).cast(),
+            &mut len,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(cred.uid)
+}
+
+/// 接続の相手のユーザーID
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let (mut uid, mut gid) = (0, 0);
+    // SAFETY: uid と gid は getpeereid の結果を入れる変数
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(uid)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_private_socket() {
+        let dir = std::env::temp_dir().join(format!("umiterm-private-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        // 作ったディレクトリは 0700
+        ensure_dir(&dir).unwrap();
+        assert_eq!(fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+        // ほかのユーザーも使えるディレクトリは使わない
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(ensure_dir(&dir).is_err());
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700)).unwrap();
+
+        // ソケットは作ったときから 0600、相手は自分
+        let path = dir.join("test.sock");
+        let listener = bind(&path).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        let client = connect(&path).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        check_peer(&server).unwrap();
+        drop((client, server, listener));
+
+        // 残ったソケットは作り直せるが、ソケットでないファイルは消さない
+        bind(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        fs::write(&path, b"").unwrap();
+        assert!(bind(&path).is_err());
+        assert!(path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
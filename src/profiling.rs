@@ -0,0 +1,57 @@
+//! プロファイリングモジュール
+//!
+//! `--profile` 指定時に tracing のスパンを chrome-trace 形式（JSON）で書き出す
+//! 出力は chrome://tracing や Perfetto で開ける
+//!
+//! # 計測区間
+//!
+//! - `parse`: PTY出力のANSI解析
+//! - `build`: インスタンスデータの構築
+//! - `upload`: アトラス・インスタンスバッファの転送
+//! - `draw`: コマンドの記録と送信
+//! - `acquire` / `present`: サーフェスの取得と表示
+//! - `frame`: build〜present を含む1フレーム全体
+//! - `gpu`: GPU上の描画時間（タイムスタンプクエリ対応時のみ、イベントとして記録）
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context, Result};
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::prelude::*;
+
+/// プロファイリングが有効か
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 既定の出力先（カレントディレクトリにタイムスタンプ付きで保存）
+pub fn default_path() -> PathBuf {
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    PathBuf::from(format!("umiterm-trace-{}.json", stamp))
+}
+
+/// chrome-trace の出力を開始
+///
+/// 戻り値のガードを破棄した時点でファイルに書き出される
+pub fn init(path: &Path) -> Result<FlushGuard> {
+    let file = File::create(path)
+        .with_context(|| format!("トレースファイルの作成に失敗: {}", path.display()))?;
+    let (layer, guard) = ChromeLayerBuilder::new()
+        .writer(BufWriter::new(file))
+        .include_args(true)
+        .build();
+
+    // logクレートのロガー（env_logger）とは共存させる
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer))
+        .context("tracingのサブスクライバー設定に失敗")?;
+
+    ENABLED.store(true, Ordering::Relaxed);
+    log::info!("プロファイルを記録中: {}", path.display());
+    Ok(guard)
+}
+
+/// プロファイリングが有効か（GPUタイムスタンプの要否判定用）
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
@@ -4,40 +4,315 @@
 //! ノンブロッキングI/Oで高速に処理
 
 use std::io::{Read, Write};
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 
 use anyhow::{Context, Result};
 use crossbeam_channel::{bounded, Receiver, Sender};
 use parking_lot::Mutex;
-use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
+use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::container;
+use crate::playback;
+use crate::shell_integration;
+use crate::wsl;
+#[cfg(unix)]
+use crate::session::{self, RemoteSession};
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 起動するコマンド
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// ペインで起動するコマンド（クラッシュ時のセッションにも書き出す）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PaneCommand {
+    /// ログインシェル
+    #[default]
+    Shell,
+    /// ログインシェル経由で実行するコマンドライン（`htop`、`ssh host` など）
+    ///
+    /// 設定・キーバインド・IPC から指定する
+    Run(String),
+    /// 設定ファイルの `[profiles.<名前>]`（コマンド・作業ディレクトリ・環境変数）
+    Profile(String),
+    /// asciicast のファイルを再生する（シェルは起動しない）
+    Play(PathBuf),
+    /// 動いているコンテナ（名前か ID）で `docker exec` のシェルを起動する
+    Container(String),
+    /// WSL のディストリビューションの既定のシェルを起動する（Windows のみ）
+    Wsl(String),
+}
+
+impl PaneCommand {
+    /// 開くときのプロファイル
+    pub fn profile(&self) -> Option<&str> {
+        match self {
+            Self::Profile(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// 起動する WSL のディストリビューション（`wsl` を書いたプロファイルも）
+    pub fn wsl<'a>(&'a self, config: &'a config::Config) -> Option<&'a str> {
+        match self {
+            Self::Wsl(distro) => Some(distro),
+            Self::Profile(name) => config.profiles.get(name)?.wsl.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// シェルの種類（ログインシェルの指定とコマンドの渡し方が違う）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellKind {
+    /// bash・zsh・fish など（`-l`、`-c <コマンド>`）
+    Posix,
+    /// pwsh・powershell（`-NoLogo`、`-Command <コマンド>`）
+    PowerShell,
+    /// cmd（`/C <コマンド>`）
+    Cmd,
+}
+
+impl ShellKind {
+    /// シェルのプログラムから判断する（`C:\Program Files\PowerShell\7\pwsh.exe` なども）
+    pub fn from_program(program: &str) -> Self {
+        let name = program.rsplit(['/', '\\']).next().unwrap_or(program).to_ascii_lowercase();
+        match name.strip_suffix(".exe").unwrap_or(&name) {
+            "pwsh" | "powershell" => Self::PowerShell,
+            "cmd" => Self::Cmd,
+            _ => Self::Posix,
+        }
+    }
+
+    /// コマンドを実行させる引数
+    pub fn command_flag(self) -> &'static str {
+        match self {
+            Self::Posix => "-c",
+            Self::PowerShell => "-Command",
+            Self::Cmd => "/C",
+        }
+    }
+}
+
+/// 既定のシェル（Unix 系は $SHELL、なければ /bin/bash）
+#[cfg(not(windows))]
+fn default_shell() -> String {
+    std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string())
+}
+
+/// 既定のシェル（Windows は PATH にある pwsh、なければ Windows PowerShell、なければ %COMSPEC%）
+#[cfg(windows)]
+fn default_shell() -> String {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let found = |program: &str| std::env::split_paths(&path).any(|dir| dir.join(program).is_file());
+    ["pwsh.exe", "powershell.exe"]
+        .into_iter()
+        .find(|program| found(program))
+        .map(String::from)
+        .or_else(|| std::env::var("COMSPEC").ok())
+        .unwrap_or_else(|| "cmd.exe".to_string())
+}
+
+/// 終了したシェル（またはコマンド）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChildExit {
+    /// 終了コード（分からなければ None）
+    pub code: Option<u32>,
+}
+
+impl ChildExit {
+    /// ペインに残す終了メッセージ
+    pub fn message(&self) -> String {
+        match self.code {
+            Some(code) => format!("[process exited with code {}]", code),
+            None => "[process exited]".to_string(),
+        }
+    }
+}
+
+/// 終了を書き込む場所（待機スレッド・読み取りスレッドが1回だけ書く）
+pub type ExitSlot = Arc<OnceLock<ChildExit>>;
 
 // ═══════════════════════════════════════════════════════════════════════════
 // PTY マネージャー
 // ═══════════════════════════════════════════════════════════════════════════
 
+/// PTYの実体
+enum Backend {
+    /// このプロセスで開いたPTY
+    Local {
+        /// マスターPTY（リサイズ用）
+        master: Arc<Mutex<Box<dyn MasterPty + Send>>>,
+        /// シェルプロセスを終了させるハンドル
+        killer: Box<dyn ChildKiller + Send + Sync>,
+        /// シェルプロセスのPID
+        child_pid: Option<u32>,
+    },
+    /// セッションデーモンが持つPTY
+    #[cfg(unix)]
+    Remote(RemoteSession),
+    /// asciicast を再生するスレッド
+    Playback(playback::Handle),
+}
+
 /// PTY（擬似端末）を管理する構造体
 /// 別スレッドでI/Oを処理し、メインスレッドをブロックしない
 pub struct Pty {
-    /// マスターPTY（書き込み用）
-    master: Arc<Mutex<Box<dyn MasterPty + Send>>>,
+    /// PTYの実体（ローカルまたはデーモン上）
+    backend: Backend,
     /// シェルからの出力を受け取るレシーバー
     output_rx: Receiver<Vec<u8>>,
     /// シェルへの入力を送るセンダー
     input_tx: Sender<Vec<u8>>,
     /// 現在のサイズ
     size: PtySize,
-    /// シェルプロセスのPID
-    child_pid: Option<u32>,
+    /// シェルが終了したら書き込まれる
+    exit: ExitSlot,
 }
 
 impl Pty {
+    /// PTYを開く（セッションデーモンが有効ならデーモン上に作る）
+    ///
+    /// env はシェルに追加する環境変数（デーモン上のセッションはアタッチし直すとペインが変わるので渡さない）
+    pub fn open(cols: u16, rows: u16, command: &PaneCommand, cwd: Option<&Path>, env: &[(String, String)]) -> Result<Self> {
+        if let PaneCommand::Play(path) = command {
+            return playback::spawn(path, cols, rows);
+        }
+        #[cfg(unix)]
+        if session::enabled() {
+            return session::spawn(cols, rows, command, cwd);
+        }
+        Self::spawn(cols, rows, None, command, cwd, env)
+    }
+
+    /// デーモン上のセッションにつながったPTY（入出力のチャネルは session モジュールがつなぐ）
+    #[cfg(unix)]
+    pub fn remote(
+        remote: RemoteSession,
+        output_rx: Receiver<Vec<u8>>,
+        input_tx: Sender<Vec<u8>>,
+        exit: ExitSlot,
+        cols: u16,
+        rows: u16,
+    ) -> Self {
+        Self {
+            backend: Backend::Remote(remote),
+            output_rx,
+            input_tx,
+            size: PtySize { rows, cols, pixel_width: 0, pixel_height: 0 },
+            exit,
+        }
+    }
+
+    /// asciicast を再生するスレッドにつながったPTY（入力は再生の操作に使う）
+    pub fn playback(
+        handle: playback::Handle,
+        output_rx: Receiver<Vec<u8>>,
+        input_tx: Sender<Vec<u8>>,
+        exit: ExitSlot,
+        cols: u16,
+        rows: u16,
+    ) -> Self {
+        Self {
+            backend: Backend::Playback(handle),
+            output_rx,
+            input_tx,
+            size: PtySize { rows, cols, pixel_width: 0, pixel_height: 0 },
+            exit,
+        }
+    }
+
+    /// シェル（と起動するコマンド）を作る（shell がなければ設定ファイルの [shell]、なければ既定のシェル）
+    fn shell_command(
+        shell: Option<&str>,
+        command: &PaneCommand,
+        profile: Option<&config::ProfileConfig>,
+        cwd: Option<&Path>,
+        home: &str,
+        config: &config::Config,
+    ) -> Result<CommandBuilder> {
+        let shell_path = shell.map(String::from).or_else(|| config.shell.program.clone()).unwrap_or_else(default_shell);
+        let kind = ShellKind::from_program(&shell_path);
+
+        let line = match command {
+            PaneCommand::Run(line) => Some(line.clone()),
+            PaneCommand::Container(name) => Some(container::exec_line(&config.containers, name)?),
+            _ => profile.and_then(|p| p.command_line()),
+        };
+
+        let mut cmd = CommandBuilder::new(&shell_path);
+        // ログインシェルとして起動（.bash_profile等を読み込む、PowerShell・cmd にはない）
+        let login = kind == ShellKind::Posix && (shell.is_some() || config.shell.login());
+        // 対話シェルにはシェル統合を読み込ませる（設定ファイルはそのまま）
+        match line.is_none().then(|| Self::integration(&shell_path, login)).flatten() {
+            Some(injection) => {
+                cmd.args(injection.args);
+                for (key, value) in injection.env {
+                    cmd.env(key, value);
+                }
+            }
+            None if login => cmd.arg("-l"),
+            None => {}
+        }
+        if kind == ShellKind::PowerShell {
+            cmd.arg("-NoLogo");
+        }
+        if shell.is_none() {
+            cmd.args(config.shell.args.iter().flatten());
+        }
+        if let Some(line) = line {
+            // PATH などはログインシェルの設定を使う
+            cmd.arg(kind.command_flag());
+            cmd.arg(line);
+        }
+        let cwd = match (cwd, profile.and_then(|p| p.cwd.as_deref())) {
+            (Some(cwd), _) => cwd.to_path_buf(),
+            (None, Some(cwd)) => match cwd.strip_prefix("~/") {
+                Some(rest) => Path::new(home).join(rest),
+                None => cwd.into(),
+            },
+            (None, None) => home.into(),
+        };
+        cmd.cwd(cwd);
+        Ok(cmd)
+    }
+
+    /// シェル統合を読み込ませる引数と環境変数（有効で、対応しているシェルのときだけ）
+    fn integration(shell_path: &str, login: bool) -> Option<shell_integration::Injection> {
+        if !shell_integration::enabled() {
+            return None;
+        }
+        let shell = shell_integration::Shell::from_program(shell_path)?;
+        let dir = shell_integration::dir()?;
+        if let Err(e) = shell_integration::write_scripts(&dir) {
+            log::warn!("シェル統合のスクリプトを書き出せません: {:#}", e);
+            return None;
+        }
+        Some(shell_integration::injection(shell, &dir, login, |name| {
+            std::env::var(name).ok().filter(|value| !value.is_empty())
+        }))
+    }
+
     /// 新しいPTYを作成し、シェルを起動
     ///
     /// # Arguments
     /// * `cols` - 列数
     /// * `rows` - 行数
     /// * `shell` - 起動するシェル（Noneでデフォルト）
-    pub fn spawn(cols: u16, rows: u16, shell: Option<&str>) -> Result<Self> {
+    /// * `command` - シェルで実行するコマンド
+    /// * `cwd` - 作業ディレクトリ（Noneでプロファイルの指定、なければホーム）
+    /// * `env` - 追加する環境変数（設定ファイルの環境変数が優先）
+    pub fn spawn(
+        cols: u16,
+        rows: u16,
+        shell: Option<&str>,
+        command: &PaneCommand,
+        cwd: Option<&Path>,
+        env: &[(String, String)],
+    ) -> Result<Self> {
         // PTYシステムを取得
         let pty_system = native_pty_system();
 
@@ -53,27 +328,56 @@ impl Pty {
             .openpty(size)
             .context("PTYのオープンに失敗")?;
 
-        // シェルコマンドを構築
-        let shell_path = shell.map(String::from).unwrap_or_else(|| {
-            std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string())
-        });
-
-        let mut cmd = CommandBuilder::new(&shell_path);
-        cmd.arg("-l"); // ログインシェルとして起動（.bash_profile等を読み込む）
-        cmd.cwd(std::env::var("HOME").unwrap_or_else(|_| "/".into()));
+        let config = config::current();
+        let home = config::home_dir().map_or_else(|| "/".into(), |home| home.to_string_lossy().into_owned());
+        let profile = command.profile().map(|name| config.profile(name)).transpose()?;
+        let mut cmd = match command.wsl(&config) {
+            // WSL はシェルを介さずに起動し、作業ディレクトリは Linux のパスにして渡す
+            Some(distro) => {
+                let wsl_cwd = match cwd {
+                    Some(cwd) => Some(wsl::to_linux_path(cwd).unwrap_or_else(|| cwd.to_string_lossy().into_owned())),
+                    None => profile.and_then(|p| p.cwd.clone()),
+                };
+                let mut cmd = CommandBuilder::new(wsl::PROGRAM);
+                cmd.args(wsl::args(distro, wsl_cwd.as_deref(), profile.and_then(|p| p.command.as_deref())));
+                cmd.cwd(&home);
+                cmd
+            }
+            None => Self::shell_command(shell, command, profile, cwd, &home, &config)?,
+        };
 
         // 環境変数を設定
         cmd.env("TERM", "xterm-256color");
         cmd.env("COLORTERM", "truecolor");
+        cmd.env("TERM_PROGRAM", "UmiTerm");
+        cmd.env("TERM_PROGRAM_VERSION", env!("CARGO_PKG_VERSION"));
+        for (key, value) in env {
+            cmd.env(key, value);
+        }
+        for (key, value) in config.shell.env.iter().chain(profile.iter().flat_map(|p| &p.env)) {
+            cmd.env(key, value);
+        }
 
         // 子プロセスを起動
-        let child = pair
+        let mut child = pair
             .slave
             .spawn_command(cmd)
             .context("シェルの起動に失敗")?;
 
-        // シェルプロセスのPIDを取得
+        // シェルプロセスのPIDと、終了させるハンドルを取得
         let child_pid = child.process_id();
+        let killer = child.clone_killer();
+
+        // 待機スレッド（終了コードを記録し、眠っているイベントループを起こす）
+        let exit = ExitSlot::default();
+        let exit_writer = exit.clone();
+        std::thread::Builder::new()
+            .name("pty-waiter".into())
+            .spawn(move || {
+                let code = child.wait().ok().map(|status| status.exit_code());
+                let _ = exit_writer.set(ChildExit { code });
+                crate::idle::notify_output();
+            })?;
 
         // マスターPTYのリーダーとライターを取得
         let master = pair.master;
@@ -96,8 +400,10 @@ impl Pty {
                     match reader.read(&mut buffer) {
                         Ok(0) => break, // EOF
                         Ok(n) => {
-                            // チャネルに送信（満杯なら古いデータを捨てる）
-                            let _ = output_tx.try_send(buffer[..n].to_vec());
+                            // チャネルに送信（満杯ならパーサーが追いつくまで待つ）
+                            if output_tx.send(buffer[..n].to_vec()).is_err() {
+                                break;
+                            }
                         }
                         Err(e) => {
                             log::error!("PTY読み取りエラー: {}", e);
@@ -128,11 +434,15 @@ impl Pty {
             })?;
 
         Ok(Self {
-            master: master_arc,
+            backend: Backend::Local {
+                master: master_arc,
+                killer,
+                child_pid,
+            },
             output_rx,
             input_tx,
             size,
-            child_pid,
+            exit,
         })
     }
 
@@ -145,22 +455,14 @@ impl Pty {
         Ok(())
     }
 
-    /// シェルからのデータを受信（ノンブロッキング）
-    /// 利用可能なすべてのデータを返す
-    #[inline]
-    pub fn read(&self) -> Option<Vec<u8>> {
-        // ノンブロッキングで受信を試みる
-        let mut result = Vec::new();
-
-        while let Ok(data) = self.output_rx.try_recv() {
-            result.extend(data);
-        }
+    /// シェルからの出力を受け取るレシーバー（パーサースレッド用）
+    pub fn output_receiver(&self) -> Receiver<Vec<u8>> {
+        self.output_rx.clone()
+    }
 
-        if result.is_empty() {
-            None
-        } else {
-            Some(result)
-        }
+    /// シェルへの入力を送るセンダー（パーサースレッドからの応答用）
+    pub fn input_sender(&self) -> Sender<Vec<u8>> {
+        self.input_tx.clone()
     }
 
     /// PTYのサイズを変更
@@ -168,12 +470,28 @@ impl Pty {
         self.size.cols = cols;
         self.size.rows = rows;
 
-        let master = self.master.lock();
-        master
-            .resize(self.size)
-            .context("PTYのリサイズに失敗")?;
+        match &self.backend {
+            Backend::Local { master, .. } => master
+                .lock()
+                .resize(self.size)
+                .context("PTYのリサイズに失敗"),
+            #[cfg(unix)]
+            Backend::Remote(remote) => remote.resize(cols, rows),
+            Backend::Playback(_) => Ok(()),
+        }
+    }
 
-        Ok(())
+    /// シェルを終了させる（デーモン上のセッションも終了する）
+    pub fn terminate(&mut self) {
+        match &mut self.backend {
+            Backend::Local { killer, .. } => {
+                // すでに終了していれば失敗するが問題ない
+                let _ = killer.kill();
+            }
+            #[cfg(unix)]
+            Backend::Remote(remote) => remote.kill(),
+            Backend::Playback(handle) => handle.stop(),
+        }
     }
 
     /// 現在のサイズを取得
@@ -181,10 +499,54 @@ impl Pty {
         (self.size.cols, self.size.rows)
     }
 
+    /// シェルが終了していれば、その終了コード
+    pub fn exit(&self) -> Option<ChildExit> {
+        self.exit.get().copied()
+    }
+
+    /// フォアグラウンドで実行中のコマンド名を取得
+    /// PTYのフォアグラウンドプロセスグループのリーダーの名前を OS から読む（描画中に呼ぶので子プロセスは起動しない）
+    #[cfg(unix)]
+    pub fn foreground_command(&self) -> Option<String> {
+        // デーモン上のPTYはこのプロセスからは調べられない
+        let Backend::Local { master, .. } = &self.backend else { return None };
+        let pid = master.lock().process_group_leader()?;
+        process_name(pid)
+    }
+
+    #[cfg(not(unix))]
+    pub fn foreground_command(&self) -> Option<String> {
+        None
+    }
+
+    /// シェル以外のプロセスがフォアグラウンドで実行中なら、そのコマンド名
+    /// （閉じる前の確認用。シェルがプロンプトを出して待っているときは None）
+    #[cfg(unix)]
+    pub fn running_command(&self) -> Option<String> {
+        let (master, child_pid) = match &self.backend {
+            Backend::Local { master, child_pid, .. } => (master, child_pid),
+            // デーモン上のPTYはデーモンに尋ねる
+            Backend::Remote(remote) => return remote.running_command(),
+            Backend::Playback(_) => return None,
+        };
+        let leader = master.lock().process_group_leader()?;
+        let leader = u32::try_from(leader).ok()?;
+        if Some(leader) == *child_pid {
+            return None;
+        }
+        Some(self.foreground_command().unwrap_or_else(|| format!("pid {}", leader)))
+    }
+
+    #[cfg(not(unix))]
+    pub fn running_command(&self) -> Option<String> {
+        None
+    }
+
     /// シェルの現在の作業ディレクトリを取得（macOS用）
     /// lsofコマンドを使用してPIDからcwdを取得
     pub fn get_cwd(&self) -> Option<std::path::PathBuf> {
-        let pid = self.child_pid?;
+        let Backend::Local { child_pid, .. } = &self.backend else { return None };
+        let pid = (*child_pid)?;
 
         // lsofでシェルプロセスのcwdを取得
         let output = std::process::Command::new("lsof")
@@ -212,6 +574,36 @@ impl Pty {
     }
 }
 
+/// プロセスの名前（/proc/<pid>/comm）
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn process_name(pid: libc::pid_t) -> Option<String> {
+    let comm = std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+    command_name(comm.trim())
+}
+
+/// プロセスの名前（proc_name）
+#[cfg(target_os = "macos")]
+fn process_name(pid: libc::pid_t) -> Option<String> {
+    let mut buffer = [0u8; 256];
+    // SAFETY: buffer の大きさを渡しており、書かれるのは戻り値のバイト数まで
+    let len = unsafe { libc::proc_name(pid, buffer.as_mut_ptr().cast(), buffer.len() as u32) };
+    let len = usize::try_from(len).ok().filter(|len| *len > 0)?;
+    command_name(&String::from_utf8_lossy(&buffer[..len]))
+}
+
+/// プロセスの名前（ほかの Unix では調べない）
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android", target_os = "macos"))))]
+fn process_name(_pid: libc::pid_t) -> Option<String> {
+    None
+}
+
+/// プロセスの comm からコマンド名を取り出す（パスとログインシェルの先頭の - を除く）
+fn command_name(comm: &str) -> Option<String> {
+    let name = comm.rsplit('/').next().unwrap_or(comm);
+    let name = name.strip_prefix('-').unwrap_or(name);
+    (!name.is_empty()).then(|| name.to_string())
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 // テスト
 // ═══════════════════════════════════════════════════════════════════════════
@@ -223,7 +615,76 @@ mod tests {
     #[test]
     fn test_pty_spawn() {
         // PTYが作成できることを確認
-        let pty = Pty::spawn(80, 24, Some("/bin/echo")).unwrap();
+        let pty = Pty::spawn(80, 24, Some("/bin/echo"), &PaneCommand::Shell, None, &[]).unwrap();
         assert_eq!(pty.size(), (80, 24));
     }
+
+    #[test]
+    fn test_shell_kind() {
+        assert_eq!(ShellKind::from_program("/bin/zsh"), ShellKind::Posix);
+        assert_eq!(ShellKind::from_program("pwsh.exe"), ShellKind::PowerShell);
+        assert_eq!(ShellKind::from_program(r"C:\Windows\System32\WindowsPowerShell\v1.0\PowerShell.exe"), ShellKind::PowerShell);
+        assert_eq!(ShellKind::from_program(r"C:\Windows\system32\cmd.exe"), ShellKind::Cmd);
+        assert_eq!(ShellKind::from_program(r"C:\Program Files\Git\bin\bash.exe"), ShellKind::Posix);
+        assert_eq!(ShellKind::Cmd.command_flag(), "/C");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_running_command() {
+        // 対話シェルはジョブ制御でコマンドをフォアグラウンドにする
+        let pty = Pty::spawn(80, 24, Some("/bin/sh"), &PaneCommand::Shell, None, &[]).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        assert_eq!(pty.running_command(), None);
+
+        pty.write(b"sleep 5\n").unwrap();
+        let mut running = None;
+        for _ in 0..30 {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            running = pty.running_command();
+            if running.is_some() {
+                break;
+            }
+        }
+        assert_eq!(running.as_deref(), Some("sleep"));
+        assert_eq!(pty.foreground_command().as_deref(), Some("sleep"));
+    }
+
+    #[test]
+    fn test_pty_spawn_command() {
+        // シェル経由でコマンドを実行し、その出力が届く
+        let pty = Pty::spawn(80, 24, Some("/bin/sh"), &PaneCommand::Run("echo umiterm-split".into()), None, &[]).unwrap();
+        let mut output = Vec::new();
+        while let Ok(chunk) = pty.output_receiver().recv_timeout(std::time::Duration::from_secs(5)) {
+            output.extend_from_slice(&chunk);
+            if String::from_utf8_lossy(&output).contains("umiterm-split") {
+                break;
+            }
+        }
+        assert!(String::from_utf8_lossy(&output).contains("umiterm-split"));
+    }
+
+    #[test]
+    fn test_child_exit() {
+        let pty = Pty::spawn(80, 24, Some("/bin/sh"), &PaneCommand::Run("exit 3".into()), None, &[]).unwrap();
+        let mut exit = None;
+        for _ in 0..50 {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            exit = pty.exit();
+            if exit.is_some() {
+                break;
+            }
+        }
+        assert_eq!(exit, Some(ChildExit { code: Some(3) }));
+        assert_eq!(exit.unwrap().message(), "[process exited with code 3]");
+        assert_eq!(ChildExit { code: None }.message(), "[process exited]");
+    }
+
+    #[test]
+    fn test_command_name() {
+        assert_eq!(command_name("/usr/bin/vim").as_deref(), Some("vim"));
+        assert_eq!(command_name("-zsh").as_deref(), Some("zsh"));
+        assert_eq!(command_name("htop").as_deref(), Some("htop"));
+        assert_eq!(command_name(""), None);
+    }
 }
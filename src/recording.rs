@@ -0,0 +1,173 @@
+//! ペインの録画モジュール
+//!
+//! PTY の出力を時刻付きで asciicast v2（asciinema の形式）に書き出す
+//! `asciinema play` で再生したり、asciinema.org に上げて共有したりできる
+//!
+//! ```text
+//! {"version":2,"width":80,"height":24,"timestamp":1760000000,"title":"zsh","env":{"TERM":"xterm-256color"}}
+//! [0.125000, "o", "$ ls\r\n"]
+//! [1.500000, "r", "120x40"]
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+
+/// 録画中のファイル（パーサースレッドと共有、録画していなければ None）
+pub type SharedRecorder = Arc<Mutex<Option<Recorder<BufWriter<File>>>>>;
+
+/// asciicast v2 の書き出し
+pub struct Recorder<W: Write> {
+    writer: W,
+    /// 録画を始めた時刻（イベントの時刻はここからの秒数）
+    started: Instant,
+    /// 途中で切れた UTF-8 の文字（次の出力とつなげて書く）
+    pending: Vec<u8>,
+    /// 保存先（ファイルに書くときのみ）
+    path: Option<PathBuf>,
+}
+
+impl Recorder<BufWriter<File>> {
+    /// ファイルを作って録画を始める
+    pub fn create(path: &Path, cols: u16, rows: u16, title: &str) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("録画のファイルを作れません: {}", path.display()))?;
+        let mut recorder = Self::new(BufWriter::new(file), cols, rows, title)?;
+        recorder.path = Some(path.to_path_buf());
+        Ok(recorder)
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    /// ヘッダーを書いて録画を始める
+    pub fn new(mut writer: W, cols: u16, rows: u16, title: &str) -> io::Result<Self> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let header = serde_json::json!({
+            "version": 2,
+            "width": cols,
+            "height": rows,
+            "timestamp": timestamp,
+            "title": title,
+            "env": { "TERM": "xterm-256color", "SHELL": std::env::var("SHELL").unwrap_or_default() },
+        });
+        writeln!(writer, "{}", header)?;
+        Ok(Self { writer, started: Instant::now(), pending: Vec::new(), path: None })
+    }
+
+    /// 保存先
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// PTY の出力
+    pub fn output(&mut self, data: &[u8]) -> io::Result<()> {
+        self.output_at(self.started.elapsed(), data)
+    }
+
+    /// 始めてから elapsed たったときの出力
+    fn output_at(&mut self, elapsed: Duration, data: &[u8]) -> io::Result<()> {
+        let text = self.decode(data);
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.event(elapsed, "o", &text)
+    }
+
+    /// ペインの大きさが変わった
+    pub fn resize(&mut self, cols: u16, rows: u16) -> io::Result<()> {
+        self.event(self.started.elapsed(), "r", &format!("{}x{}", cols, rows))
+    }
+
+    /// 1つのイベントを書く（時刻はマイクロ秒まで）
+    fn event(&mut self, elapsed: Duration, kind: &str, data: &str) -> io::Result<()> {
+        let data = serde_json::to_string(data).map_err(io::Error::other)?;
+        writeln!(self.writer, "[{:.6}, \"{}\", {}]", elapsed.as_secs_f64(), kind, data)
+    }
+
+    /// 書き終える
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.pending.is_empty() {
+            let rest = String::from_utf8_lossy(&std::mem::take(&mut self.pending)).into_owned();
+            self.event(self.started.elapsed(), "o", &rest)?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    /// 文字列にする（末尾で切れた UTF-8 の文字は次の出力まで待つ、正しくないバイトは U+FFFD）
+    fn decode(&mut self, data: &[u8]) -> String {
+        self.pending.extend_from_slice(data);
+        let complete = match std::str::from_utf8(&self.pending) {
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            _ => self.pending.len(),
+        };
+        let rest = self.pending.split_off(complete);
+        let text = String::from_utf8_lossy(&self.pending).into_owned();
+        self.pending = rest;
+        text
+    }
+}
+
+/// タイムスタンプ付きの保存先（スクリーンショットと同じ名前の付け方）
+pub fn timestamped_path(dir: &Path) -> PathBuf {
+    let stamp = chrono::Local::now().format("%Y-%m-%d %H.%M.%S");
+    dir.join(format!("UmiTerm {}.cast", stamp))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asciicast() {
+        let mut recorder = Recorder::new(Vec::new(), 80, 24, "zsh").unwrap();
+        recorder.output_at(Duration::from_millis(125), b"$ ls\r\n").unwrap();
+        // 途中で切れた文字は次の出力とつなげる
+        recorder.output_at(Duration::from_millis(250), "海\"".as_bytes().split_at(2).0).unwrap();
+        recorder.output_at(Duration::from_millis(500), &"海\"".as_bytes()[2..]).unwrap();
+        recorder.output_at(Duration::from_millis(600), b"\xff\x1b[0m").unwrap();
+        let text = String::from_utf8(recorder.finish().unwrap()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        let header: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(header["version"], 2);
+        assert_eq!((header["width"].as_u64(), header["height"].as_u64()), (Some(80), Some(24)));
+        assert_eq!(header["title"], "zsh");
+        assert_eq!(
+            lines[1..],
+            [r#"[0.125000, "o", "$ ls\r\n"]"#, r#"[0.500000, "o", "海\""]"#, r#"[0.600000, "o", "�\u001b[0m"]"#]
+        );
+        for line in &lines[1..] {
+            let event: (f64, String, String) = serde_json::from_str(line).unwrap();
+            assert_eq!(event.1, "o");
+        }
+    }
+
+    #[test]
+    fn test_file() {
+        let path = std::env::temp_dir().join(format!("umiterm-record-{}.cast", std::process::id()));
+        let mut recorder = Recorder::create(&path, 100, 30, "htop").unwrap();
+        assert_eq!(recorder.path(), Some(path.as_path()));
+        recorder.output(b"hello").unwrap();
+        recorder.resize(120, 40).unwrap();
+        recorder.finish().unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].ends_with(r#""o", "hello"]"#));
+        assert!(lines[2].ends_with(r#""r", "120x40"]"#));
+        std::fs::remove_file(&path).unwrap();
+        assert!(timestamped_path(Path::new("/tmp")).to_string_lossy().ends_with(".cast"));
+    }
+}
@@ -0,0 +1,389 @@
+//! 参照テストモジュール
+//!
+//! PTY の出力を生のバイト列のまま記録し、記録を終えた時点のグリッドと一緒にフィクスチャとして保存する
+//! テストではそれを AnsiParser と Terminal に流し直し、同じグリッドになるか確かめる（alacritty の ref test と同じ考え方）
+//!
+//! フィクスチャは `tests/ref/<名前>/` に置く
+//!
+//! ```text
+//! recording   PTY の出力（生のバイト列）
+//! size.json   最初の大きさと、途中のリサイズ（何バイト目で変わったか）
+//! grid.json   最後のグリッド（各行の文字・色とスタイル・カーソル）
+//! ```
+//!
+//! `umiterm --ref-test <DIR>` で起動すると最初のペインを記録し、
+//! シェルが終了するかペインを閉じたときに DIR に書き出す
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::grid::Color;
+use crate::terminal::Terminal;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// グリッドのスナップショット
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 文字色・背景色・スタイルが既定でないセルの並び（1行の中で続くもの）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StyleRun {
+    /// 行
+    pub row: usize,
+    /// 最初の列
+    pub col: usize,
+    /// セルの数
+    pub len: usize,
+    /// 文字色（パレットの色は番号、それ以外は `#rrggbb`、既定の文字色なら None）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fg: Option<String>,
+    /// 背景色（既定の背景色なら None）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bg: Option<String>,
+    /// スタイル（`BOLD` など）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<String>,
+}
+
+/// 比べるためのグリッドの中身
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GridSnapshot {
+    /// 列数
+    pub cols: usize,
+    /// 行数
+    pub rows: usize,
+    /// カーソルの位置（列・行）
+    pub cursor: (usize, usize),
+    /// 代替スクリーンか
+    pub alt_screen: bool,
+    /// 各行の文字（セルごとに1文字、全角文字の2セル目も含む、右端の空白は除く）
+    pub lines: Vec<String>,
+    /// 既定でない色・スタイルのセル
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub styles: Vec<StyleRun>,
+}
+
+/// フィクスチャに書く色の名前（既定の色なら None）
+fn color_name(color: Color, default: Color) -> Option<String> {
+    if color == default {
+        return None;
+    }
+    Some(match color.palette_index() {
+        Some(index) => index.to_string(),
+        None => format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b),
+    })
+}
+
+impl GridSnapshot {
+    /// 表示中のグリッド（代替スクリーンならそちら）を写し取る
+    pub fn capture(terminal: &Terminal) -> Self {
+        let grid = terminal.active_grid();
+        let mut lines = Vec::with_capacity(grid.rows);
+        let mut styles: Vec<StyleRun> = Vec::new();
+        for row in 0..grid.rows {
+            let cells: Vec<_> = (0..grid.cols).filter_map(|col| grid.get(col, row)).collect();
+            let line: String = cells.iter().map(|cell| cell.character).collect();
+            lines.push(line.trim_end_matches(' ').to_string());
+
+            for (col, cell) in cells.iter().enumerate() {
+                let fg = color_name(cell.fg, Color::DEFAULT_FG);
+                let bg = color_name(cell.bg, Color::DEFAULT_BG);
+                let flags: Vec<String> = cell.flags.iter_names().map(|(name, _)| name.to_string()).collect();
+                if fg.is_none() && bg.is_none() && flags.is_empty() {
+                    continue;
+                }
+                // 左隣と同じならつなげる
+                if let Some(last) = styles.last_mut() {
+                    if last.row == row && last.col + last.len == col && last.fg == fg && last.bg == bg && last.flags == flags {
+                        last.len += 1;
+                        continue;
+                    }
+                }
+                styles.push(StyleRun { row, col, len: 1, fg, bg, flags });
+            }
+        }
+        Self {
+            cols: grid.cols,
+            rows: grid.rows,
+            cursor: (terminal.cursor.col, terminal.cursor.row),
+            alt_screen: terminal.is_alt_screen(),
+            lines,
+            styles,
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// フィクスチャ
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 記録中のリサイズ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resize {
+    /// 何バイト目の前で変わったか
+    pub offset: usize,
+    /// 列数
+    pub cols: usize,
+    /// 行数
+    pub rows: usize,
+}
+
+/// size.json の中身
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Size {
+    /// 最初の列数
+    pub cols: usize,
+    /// 最初の行数
+    pub rows: usize,
+    /// 途中のリサイズ（古い順）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resizes: Vec<Resize>,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 記録
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 記録中のペイン（パーサースレッドと共有、記録していなければ None）
+///
+/// グリッドと食い違わないよう、ターミナルをロックしてから触る
+pub type SharedRefRecorder = Arc<Mutex<Option<RefRecorder>>>;
+
+/// PTY の出力とリサイズをフィクスチャに書き出す
+pub struct RefRecorder {
+    /// 書き出し先のディレクトリ
+    dir: PathBuf,
+    /// recording ファイル
+    recording: BufWriter<File>,
+    /// 書いたバイト数
+    written: usize,
+    /// 大きさ
+    size: Size,
+}
+
+impl RefRecorder {
+    /// ディレクトリを作って記録を始める
+    pub fn create(dir: &Path, cols: usize, rows: usize) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("ディレクトリを作れません: {}", dir.display()))?;
+        let path = dir.join("recording");
+        let file = File::create(&path).with_context(|| format!("記録のファイルを作れません: {}", path.display()))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            recording: BufWriter::new(file),
+            written: 0,
+            size: Size { cols, rows, resizes: Vec::new() },
+        })
+    }
+
+    /// PTY の出力を書く（解析する前に呼ぶ）
+    pub fn output(&mut self, data: &[u8]) -> std::io::Result<()> {
+        self.recording.write_all(data)?;
+        self.written += data.len();
+        Ok(())
+    }
+
+    /// リサイズを覚える
+    pub fn resize(&mut self, cols: usize, rows: usize) {
+        self.size.resizes.push(Resize { offset: self.written, cols, rows });
+    }
+
+    /// 記録を終え、size.json と今のグリッドの grid.json を書く
+    pub fn finish(mut self, terminal: &Terminal) -> Result<PathBuf> {
+        self.recording.flush()?;
+        let size = serde_json::to_string_pretty(&self.size)?;
+        fs::write(self.dir.join("size.json"), size + "\n")?;
+        let grid = serde_json::to_string_pretty(&GridSnapshot::capture(terminal))?;
+        fs::write(self.dir.join("grid.json"), grid + "\n")?;
+        Ok(self.dir)
+    }
+}
+
+/// `--ref-test` で指定されたディレクトリ（最初のペインが取り出す）
+static ARMED: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// 次に開くペインを記録するようにする
+pub fn arm(dir: PathBuf) {
+    *ARMED.lock() = Some(dir);
+}
+
+/// 記録するようにしていれば記録を始める（最初の1回だけ）
+pub fn take_armed(cols: usize, rows: usize) -> Option<RefRecorder> {
+    let dir = ARMED.lock().take()?;
+    RefRecorder::create(&dir, cols, rows)
+        .inspect(|_| log::info!("参照テストの記録を始めました: {}", dir.display()))
+        .inspect_err(|e| log::error!("参照テストの記録を始められません: {:#}", e))
+        .ok()
+}
+
+/// 記録中なら終えて書き出す（ターミナルはロックしたものを渡す）
+pub fn finish(recorder: &SharedRefRecorder, terminal: &Terminal) {
+    let Some(recorder) = recorder.lock().take() else { return };
+    match recorder.finish(terminal) {
+        Ok(dir) => log::info!("参照テストのフィクスチャを書き出しました: {}", dir.display()),
+        Err(e) => log::error!("参照テストのフィクスチャを書き出せません: {:#}", e),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::AnsiParser;
+
+    /// 1つの参照テスト
+    #[derive(Debug, Clone)]
+    struct Fixture {
+        /// 名前（ディレクトリ名）
+        name: String,
+        /// PTY の出力
+        recording: Vec<u8>,
+        /// 大きさ
+        size: Size,
+        /// 最後のグリッド
+        grid: GridSnapshot,
+    }
+
+    impl Fixture {
+        /// ディレクトリから読み込む
+        fn load(dir: &Path) -> Result<Self> {
+            let read_json = |file: &str| -> Result<String> {
+                let path = dir.join(file);
+                fs::read_to_string(&path).with_context(|| format!("読み込めません: {}", path.display()))
+            };
+            let recording = dir.join("recording");
+            Ok(Self {
+                name: dir.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default(),
+                recording: fs::read(&recording).with_context(|| format!("読み込めません: {}", recording.display()))?,
+                size: serde_json::from_str(&read_json("size.json")?).context("size.json が読めません")?,
+                grid: serde_json::from_str(&read_json("grid.json")?).context("grid.json が読めません")?,
+            })
+        }
+
+        /// 記録を流し直したグリッド（リサイズは記録したところで行う）
+        fn replay(&self) -> GridSnapshot {
+            let mut terminal = Terminal::new(self.size.cols, self.size.rows);
+            let mut parser = AnsiParser::new();
+            let mut offset = 0;
+            for resize in &self.size.resizes {
+                let end = resize.offset.clamp(offset, self.recording.len());
+                parser.process(&mut terminal, &self.recording[offset..end]);
+                terminal.resize(resize.cols, resize.rows);
+                offset = end;
+            }
+            parser.process(&mut terminal, &self.recording[offset..]);
+            GridSnapshot::capture(&terminal)
+        }
+    }
+
+    /// ディレクトリの下のフィクスチャ（名前順）
+    fn load_all(root: &Path) -> Result<Vec<Fixture>> {
+        let mut dirs: Vec<PathBuf> = fs::read_dir(root)
+            .with_context(|| format!("読み込めません: {}", root.display()))?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.is_dir())
+            .collect();
+        dirs.sort();
+        dirs.iter().map(|dir| Fixture::load(dir)).collect()
+    }
+
+    /// 期待するグリッドとの違い（1つ1行、同じなら空）
+    fn differences(actual: &GridSnapshot, expected: &GridSnapshot) -> Vec<String> {
+        let mut differences = Vec::new();
+        if (actual.cols, actual.rows) != (expected.cols, expected.rows) {
+            differences.push(format!("大きさ: {}x{}（期待 {}x{}）", actual.cols, actual.rows, expected.cols, expected.rows));
+        }
+        if actual.cursor != expected.cursor {
+            differences.push(format!("カーソル: {:?}（期待 {:?}）", actual.cursor, expected.cursor));
+        }
+        if actual.alt_screen != expected.alt_screen {
+            differences.push(format!("代替スクリーン: {}（期待 {}）", actual.alt_screen, expected.alt_screen));
+        }
+        let rows = actual.lines.len().max(expected.lines.len());
+        for row in 0..rows {
+            let (line, wanted) = (actual.lines.get(row), expected.lines.get(row));
+            if line != wanted {
+                differences.push(format!("{}行目: {:?}（期待 {:?}）", row, line, wanted));
+            }
+        }
+        for run in actual.styles.iter().filter(|run| !expected.styles.contains(run)) {
+            differences.push(format!("余分なスタイル: {:?}", run));
+        }
+        for run in expected.styles.iter().filter(|run| !actual.styles.contains(run)) {
+            differences.push(format!("足りないスタイル: {:?}", run));
+        }
+        differences
+    }
+
+    #[test]
+    fn test_ref_fixtures() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/ref");
+        let fixtures = load_all(&root).unwrap();
+        assert!(!fixtures.is_empty());
+        let failures: Vec<String> = fixtures
+            .iter()
+            .filter_map(|fixture| {
+                let differences = differences(&fixture.replay(), &fixture.grid);
+                (!differences.is_empty()).then(|| format!("{}:\n  {}", fixture.name, differences.join("\n  ")))
+            })
+            .collect();
+        assert!(failures.is_empty(), "参照テストのグリッドが違います\n{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let dir = std::env::temp_dir().join(format!("umiterm-reftest-{}", std::process::id()));
+        let mut terminal = Terminal::new(10, 3);
+        let mut parser = AnsiParser::new();
+        let mut recorder = RefRecorder::create(&dir, 10, 3).unwrap();
+
+        // アプリと同じく、解析する前に書き、リサイズはその間に挟む
+        for (data, size) in [(&b"\x1b[1;31mred\x1b[m\r\n"[..], Some((6, 2))), ("あい\r\nok".as_bytes(), None)] {
+            recorder.output(data).unwrap();
+            parser.process(&mut terminal, data);
+            if let Some((cols, rows)) = size {
+                recorder.resize(cols, rows);
+                terminal.resize(cols, rows);
+            }
+        }
+        recorder.finish(&terminal).unwrap();
+
+        let fixture = Fixture::load(&dir).unwrap();
+        assert_eq!(fixture.size.resizes, [Resize { offset: 15, cols: 6, rows: 2 }]);
+        assert_eq!(fixture.grid.lines, ["あ い", "ok"]);
+        assert_eq!(fixture.grid.cursor, (2, 1));
+        assert!(differences(&fixture.replay(), &fixture.grid).is_empty());
+
+        // スタイルは続くセルをまとめ、違いは行ごとに出す
+        let grid = GridSnapshot::capture(&{
+            let mut terminal = Terminal::new(6, 1);
+            parser.process(&mut terminal, b"\x1b[1;31mab\x1b[44mc");
+            terminal
+        });
+        let red = |len, bg: Option<&str>| StyleRun {
+            row: 0,
+            col: if bg.is_some() { 2 } else { 0 },
+            len,
+            fg: Some("1".into()),
+            bg: bg.map(String::from),
+            flags: vec!["BOLD".into()],
+        };
+        assert_eq!(grid.styles, [red(2, None), red(1, Some("4"))]);
+        let expected = GridSnapshot { lines: vec!["abd".into()], styles: vec![red(2, None)], ..grid.clone() };
+        assert_eq!(
+            differences(&grid, &expected),
+            [
+                "0行目: Some(\"abc\")（期待 Some(\"abd\")）".to_string(),
+                format!("余分なスタイル: {:?}", red(1, Some("4"))),
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -9,23 +9,51 @@
 
 use std::collections::HashMap;
 use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use anyhow::{Context, Result};
 use bytemuck::{Pod, Zeroable};
 use fontdue::{Font, FontSettings};
+use parking_lot::Mutex;
 use wgpu::util::DeviceExt;
 
-use crate::explorer::{EntryKind, Explorer};
-use crate::grid::Color;
-use crate::terminal::{CursorShape, Terminal};
+use crate::bookmarks::PlaceKind;
+use crate::boxdraw::{self, BoxRect};
+use crate::config;
+use crate::copy_mode::CopyCursor;
+use crate::explorer::{EntryKind, Explorer, ExplorerFilter, ExplorerLayout, ExplorerPrompt};
+use crate::preview::{PreviewContent, Thumbnail, TokenKind};
+use crate::finder::{Finder, FinderKind};
+use crate::hints::HintLabels;
+use crate::grid::{Color, Palette};
+use crate::pane::{self, MonitorKind, TitleBar};
+use crate::perf::FrameStats;
+use crate::profiling;
+use crate::screenshot::Screenshot;
+use crate::scrollbar::{self, NewLines, Scrollbar};
+use crate::snapshot::TerminalSnapshot;
+use crate::status::StatusLine;
+use crate::tab::{self, TabLabel};
+use crate::terminal::{CursorShape, CursorStyle, UnfocusedCursor};
 
 // ═══════════════════════════════════════════════════════════════════════════
 // フォント読み込み（プラットフォーム対応）
 // ═══════════════════════════════════════════════════════════════════════════
 
-/// システムフォントを読み込む
+/// フォントを読み込む（設定のフォントがあればそれ、なければシステムフォント）
 /// macOS, Linux, Windows に対応
-fn load_system_font() -> Result<Font> {
+fn load_font(custom_path: Option<&str>) -> Result<Font> {
+    // 設定ファイルの [font] path か環境変数 UMITERM_FONT でカスタムフォントを指定可能
+    if let Some(custom_path) = custom_path {
+        let data = fs::read(custom_path)
+            .with_context(|| format!("カスタムフォントの読み込みに失敗: {}", custom_path))?;
+        return Font::from_bytes(data, FontSettings::default())
+            .map_err(|e| anyhow::anyhow!("フォントのパースに失敗: {}", e));
+    }
+
     // 候補フォントパス（優先度順）
     let font_paths = [
         // macOS
@@ -36,34 +64,44 @@ fn load_system_font() -> Result<Font> {
         "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf",
         "/usr/share/fonts/TTF/DejaVuSansMono.ttf",
         "/usr/share/fonts/truetype/liberation/LiberationMono-Regular.ttf",
-        // Windows
-        "C:/Windows/Fonts/consola.ttf",
-        "C:/Windows/Fonts/cour.ttf",
-    ];
-
-    for path in &font_paths {
-        if let Ok(data) = fs::read(path) {
+    ]
+    .map(PathBuf::from)
+    .into_iter()
+    // Windows（Windows Terminal の Cascadia Mono、Consolas、Courier New）
+    .chain(windows_fonts(&["CascadiaMono.ttf", "consola.ttf", "cour.ttf"]));
+
+    for path in font_paths {
+        if let Ok(data) = fs::read(&path) {
             if let Ok(font) = Font::from_bytes(data, FontSettings::default()) {
-                log::info!("フォントを読み込みました: {}", path);
+                log::info!("フォントを読み込みました: {}", path.display());
                 return Ok(font);
             }
         }
     }
 
-    // 環境変数でカスタムフォントを指定可能
-    if let Ok(custom_path) = std::env::var("UMITERM_FONT") {
-        let data = fs::read(&custom_path)
-            .with_context(|| format!("カスタムフォントの読み込みに失敗: {}", custom_path))?;
-        return Font::from_bytes(data, FontSettings::default())
-            .map_err(|e| anyhow::anyhow!("フォントのパースに失敗: {}", e));
-    }
-
     anyhow::bail!(
         "システムフォントが見つかりません。\n\
-         UMITERM_FONT 環境変数でフォントパスを指定してください。"
+         設定ファイルの [font] path か UMITERM_FONT 環境変数でフォントパスを指定してください。"
     )
 }
 
+/// Windows のフォントのパス（システムの %WINDIR%\Fonts と、ユーザーごとにインストールしたフォント）
+fn windows_fonts(names: &[&str]) -> Vec<PathBuf> {
+    if !cfg!(windows) {
+        return Vec::new();
+    }
+    let system = std::env::var_os("WINDIR").map_or_else(|| PathBuf::from(r"C:\Windows"), PathBuf::from).join("Fonts");
+    let user = std::env::var_os("LOCALAPPDATA").map(|dir| PathBuf::from(dir).join(r"Microsoft\Windows\Fonts"));
+    let dirs: Vec<PathBuf> = std::iter::once(system).chain(user).collect();
+    names.iter().flat_map(|name| dirs.iter().map(move |dir| dir.join(name))).collect()
+}
+
+/// 設定のフォント（パス, 大きさ）
+fn font_setting() -> (Option<String>, f32) {
+    let size = config::current().font.size.filter(|size| *size >= 4.0).unwrap_or(DEFAULT_FONT_SIZE);
+    (config::var("UMITERM_FONT"), size)
+}
+
 /// 日本語フォールバックフォントを読み込む
 fn load_japanese_font() -> Option<Font> {
     let font_paths = [
@@ -75,15 +113,16 @@ fn load_japanese_font() -> Option<Font> {
         // Linux
         "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
         "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
-        // Windows
-        "C:/Windows/Fonts/msgothic.ttc",
-        "C:/Windows/Fonts/YuGothM.ttc",
-    ];
-
-    for path in &font_paths {
-        if let Ok(data) = fs::read(path) {
+    ]
+    .map(PathBuf::from)
+    .into_iter()
+    // Windows（MS ゴシック、游ゴシック、メイリオ）
+    .chain(windows_fonts(&["msgothic.ttc", "YuGothM.ttc", "meiryo.ttc"]));
+
+    for path in font_paths {
+        if let Ok(data) = fs::read(&path) {
             if let Ok(font) = Font::from_bytes(data, FontSettings::default()) {
-                log::info!("日本語フォントを読み込みました: {}", path);
+                log::info!("日本語フォントを読み込みました: {}", path.display());
                 return Some(font);
             }
         }
@@ -106,6 +145,12 @@ const ATLAS_SIZE: u32 = 512;
 /// 最大インスタンス数（メモリ最適化、オーバーフロー保護あり）
 const MAX_INSTANCES: usize = 8000;
 
+/// ステージングベルトのチャンクサイズ（背景・テキストのインスタンスバッファ1フレーム分）
+const STAGING_CHUNK_SIZE: u64 = (MAX_INSTANCES * std::mem::size_of::<CellInstance>() * 2) as u64;
+
+/// ヘッドレス描画時のテクスチャフォーマット
+const HEADLESS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
 // ═══════════════════════════════════════════════════════════════════════════
 // 頂点データ（GPU に送るデータ）
 // ═══════════════════════════════════════════════════════════════════════════
@@ -131,6 +176,74 @@ struct CellInstance {
     glyph_size: [f32; 2],
 }
 
+/// 同じ順序で重ねて描画するインスタンス群（背景→テキスト）
+#[derive(Default)]
+struct Layer {
+    /// 背景インスタンス
+    bg: Vec<CellInstance>,
+    /// テキストインスタンス
+    text: Vec<CellInstance>,
+}
+
+impl Layer {
+    /// 中身を空にする（確保済みの容量は残す）
+    fn clear(&mut self) {
+        self.bg.clear();
+        self.text.clear();
+    }
+}
+
+/// 監視の警告の枠の太さ（ピクセル）
+const ALERT_FRAME_WIDTH: f32 = 2.0;
+
+/// レイヤーの描画順（下から）
+const LAYER_TERMINAL: usize = 0;
+const LAYER_BORDERS: usize = 1;
+const LAYER_TABS: usize = 2;
+const LAYER_EXPLORER: usize = 3;
+const LAYER_HUD: usize = 4;
+const LAYER_COUNT: usize = 5;
+
+/// フレームをまたいで使い回す作業用バッファ（毎フレームのメモリ確保を避ける）
+#[derive(Default)]
+struct FrameScratch {
+    /// 下から順に描画するレイヤー
+    layers: [Layer; LAYER_COUNT],
+    /// 罫線・ブロック要素の矩形
+    box_rects: Vec<BoxRect>,
+}
+
+/// ペインの上に重ねて描画するオーバーレイ
+#[derive(Default, Clone, Copy)]
+pub struct Overlays<'a> {
+    /// ファイルエクスプローラー（表示中の場合のみ描画）
+    pub explorer: Option<&'a Explorer>,
+    /// パフォーマンスHUDの各行
+    pub perf_hud: Option<&'a [String]>,
+    /// タブバー（タブが2つ以上のときのみ）
+    pub tab_bar: Option<&'a [TabLabel]>,
+    /// ペインのタイトルバー
+    pub title_bars: Option<&'a [TitleBar]>,
+    /// ステータスバー（最下段）
+    pub status_bar: Option<&'a StatusLine>,
+    /// 確認ダイアログ（1行目が見出し）
+    pub dialog: Option<&'a [String]>,
+    /// スクロールバック検索・コマンドパレット（表示中の場合のみ描画）
+    pub finder: Option<&'a Finder>,
+    /// 監視の警告が出ているペイン（ペイン全体の矩形に枠を描く）
+    pub pane_alerts: Option<&'a [(crate::pane::Rect, MonitorKind)]>,
+    /// コピーモードの仮想カーソル（コピーモード中のみ）
+    pub copy_cursor: Option<&'a CopyCursor>,
+    /// ヒントモードのラベル（ヒントモード中のみ）
+    pub hint_labels: Option<&'a HintLabels>,
+    /// ペインのスクロールバー（表示中のもののみ）
+    pub scrollbars: Option<&'a [Scrollbar]>,
+    /// さかのぼって表示している間に届いた行の数（届いたペインのみ）
+    pub new_lines: Option<&'a [NewLines]>,
+    /// 途中まで押したキー列に続けて押せるキー（1行目が見出し）
+    pub key_hints: Option<&'a [String]>,
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 // グリフキャッシュ
 // ═══════════════════════════════════════════════════════════════════════════
@@ -196,18 +309,15 @@ impl GlyphAtlas {
         }
 
         // メインフォントでラスタライズを試みる
-        let (metrics, bitmap) = if font.has_glyph(c) {
-            font.rasterize(c, font_size)
-        } else if let Some(fb) = fallback_font {
+        let (metrics, bitmap) = match fallback_font {
+            _ if font.has_glyph(c) => font.rasterize(c, font_size),
             // フォールバックフォントを試す
-            if fb.has_glyph(c) {
-                fb.rasterize(c, font_size)
-            } else {
-                // どちらにもない場合はメインフォントで（豆腐になる）
+            Some(fb) if fb.has_glyph(c) => fb.rasterize(c, font_size),
+            // どちらにもない場合はメインフォントで（豆腐になる）
+            _ => {
+                log::warn!("フォントにない文字です: {:?} (U+{:04X})", c, c as u32);
                 font.rasterize(c, font_size)
             }
-        } else {
-            font.rasterize(c, font_size)
         };
 
         if metrics.width == 0 || metrics.height == 0 {
@@ -267,42 +377,141 @@ impl GlyphAtlas {
 
         Some(info)
     }
+
+    /// 使用率（書き込み済みの高さの割合）
+    fn usage(&self) -> f32 {
+        ((self.cursor_y + self.row_height) as f32 / self.height as f32).min(1.0)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// GPUタイマー（プロファイリング用）
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// レンダーパスの開始・終了時刻をタイムスタンプクエリで計測
+///
+/// 読み戻しは非同期で、結果は数フレーム後に tracing のイベントとして出力する
+struct GpuTimer {
+    /// タイムスタンプクエリ（開始・終了の2つ）
+    query_set: wgpu::QuerySet,
+    /// クエリの解決先
+    resolve_buffer: wgpu::Buffer,
+    /// CPUへの読み戻し用
+    read_buffer: wgpu::Buffer,
+    /// 1ティックあたりのナノ秒
+    period: f32,
+    /// 読み戻し中か
+    in_flight: bool,
+    /// マップ完了フラグ（コールバックから設定）
+    mapped: Arc<AtomicBool>,
+}
+
+impl GpuTimer {
+    /// クエリ結果のバイト数（u64 × 2）
+    const SIZE: u64 = 16;
+
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("GPU Timer Queries"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU Timer Resolve Buffer"),
+            size: Self::SIZE,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let read_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU Timer Read Buffer"),
+            size: Self::SIZE,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            query_set,
+            resolve_buffer,
+            read_buffer,
+            period: queue.get_timestamp_period(),
+            in_flight: false,
+            mapped: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// レンダーパスに設定するタイムスタンプ書き込み
+    fn timestamp_writes(&self) -> wgpu::RenderPassTimestampWrites<'_> {
+        wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(0),
+            end_of_pass_write_index: Some(1),
+        }
+    }
+
+    /// クエリを解決して読み戻し用バッファにコピー
+    /// 戻り値: コピーしたか（前回の読み戻し中はスキップ）
+    fn resolve(&self, encoder: &mut wgpu::CommandEncoder) -> bool {
+        if self.in_flight {
+            return false;
+        }
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.read_buffer, 0, Self::SIZE);
+        true
+    }
+
+    /// 送信後に読み戻しを開始
+    fn map(&mut self) {
+        self.in_flight = true;
+        let mapped = self.mapped.clone();
+        self.read_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                if result.is_ok() {
+                    mapped.store(true, Ordering::Release);
+                }
+            });
+    }
+
+    /// 読み戻しが完了していれば結果を出力
+    fn collect(&mut self, device: &wgpu::Device) {
+        if !self.in_flight {
+            return;
+        }
+        let _ = device.poll(wgpu::PollType::Poll);
+        if !self.mapped.swap(false, Ordering::Acquire) {
+            return;
+        }
+
+        {
+            let data = self.read_buffer.slice(..).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            let ticks = timestamps[1].saturating_sub(timestamps[0]);
+            let gpu_us = ticks as f64 * self.period as f64 / 1000.0;
+            tracing::trace!(target: "gpu", gpu_us, "gpu");
+        }
+        self.read_buffer.unmap();
+        self.in_flight = false;
+    }
 }
 
 // ═══════════════════════════════════════════════════════════════════════════
 // レンダラー
 // ═══════════════════════════════════════════════════════════════════════════
 
-/// GPU レンダラー
-pub struct Renderer {
-    /// wgpu サーフェス（内部で保持）
+/// ウィンドウのサーフェスと設定
+struct SurfaceTarget {
+    /// wgpu サーフェス
     surface: wgpu::Surface<'static>,
-    /// wgpu デバイス
-    device: wgpu::Device,
-    /// コマンドキュー
-    queue: wgpu::Queue,
     /// サーフェス設定
-    surface_config: wgpu::SurfaceConfiguration,
-    /// レンダーパイプライン
-    render_pipeline: wgpu::RenderPipeline,
-    /// 背景用パイプライン
-    bg_pipeline: wgpu::RenderPipeline,
-    /// インスタンスバッファ
-    instance_buffer: wgpu::Buffer,
-    /// 背景インスタンスバッファ
-    bg_instance_buffer: wgpu::Buffer,
-    /// グリフアトラステクスチャ
-    atlas_texture: wgpu::Texture,
-    /// テクスチャビュー
-    #[allow(dead_code)]
-    atlas_view: wgpu::TextureView,
-    /// サンプラー
-    #[allow(dead_code)]
-    sampler: wgpu::Sampler,
-    /// バインドグループ
-    bind_group: wgpu::BindGroup,
-    /// ユニフォームバッファ
-    uniform_buffer: wgpu::Buffer,
+    config: wgpu::SurfaceConfiguration,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 共有リソース
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// フォントとグリフアトラス（全ウィンドウで共有）
+struct GlyphCache {
     /// フォント
     font: Font,
     /// フォールバックフォント（日本語等）- 遅延読み込み
@@ -311,16 +520,129 @@ pub struct Renderer {
     fallback_font_tried: bool,
     /// フォントサイズ
     font_size: f32,
+    /// カスタムフォントのパス（None ならシステムフォント）
+    font_path: Option<String>,
     /// セル幅
     cell_width: f32,
     /// セル高さ
     cell_height: f32,
     /// グリフアトラス
-    glyph_atlas: GlyphAtlas,
-    /// 画面の幅
-    width: u32,
-    /// 画面の高さ
-    height: u32,
+    atlas: GlyphAtlas,
+}
+
+impl GlyphCache {
+    /// フォントを読み込んで空のアトラスを用意する
+    fn load(font_path: Option<String>, font_size: f32) -> Result<Self> {
+        let font = load_font(font_path.as_deref())?;
+
+        // セルサイズを計算
+        let metrics = font.metrics('M', font_size);
+        let cell_width = metrics.advance_width.ceil();
+        // 整数のピクセルにそろえる（小数の拡大率でも行の境目がにじまない）
+        let cell_height = (font_size * 1.2).round();
+
+        // 日本語フォールバックフォントは遅延読み込み（起動高速化）
+        Ok(Self {
+            font,
+            fallback_font: None,
+            fallback_font_tried: false,
+            font_size,
+            font_path,
+            cell_width,
+            cell_height,
+            atlas: GlyphAtlas::new(ATLAS_SIZE, ATLAS_SIZE),
+        })
+    }
+
+    /// グリフを取得（必要ならフォールバックフォントを読み込んでラスタライズ）
+    fn glyph(&mut self, c: char) -> Option<GlyphInfo> {
+        self.ensure_fallback_font(c);
+        self.atlas
+            .get_or_insert(c, &self.font, self.fallback_font.as_ref(), self.font_size)
+    }
+
+    /// メインフォントかフォールバックフォントにグリフがあるか（なければ豆腐になる）
+    fn has_glyph(&mut self, c: char) -> bool {
+        self.ensure_fallback_font(c);
+        self.font.has_glyph(c) || self.fallback_font.as_ref().is_some_and(|font| font.has_glyph(c))
+    }
+
+    /// 日本語フォントを遅延読み込み（必要な時のみ）
+    fn ensure_fallback_font(&mut self, c: char) {
+        // ASCII文字はフォールバック不要
+        if c.is_ascii() {
+            return;
+        }
+        // メインフォントにあればフォールバック不要
+        if self.font.has_glyph(c) {
+            return;
+        }
+        // 既に読み込み試行済みならスキップ
+        if self.fallback_font_tried {
+            return;
+        }
+        // 日本語フォントを読み込み
+        self.fallback_font_tried = true;
+        self.fallback_font = load_japanese_font();
+    }
+}
+
+/// ウィンドウ間で共有する描画リソース
+///
+/// デバイス・パイプライン・フォント・グリフアトラスは1つだけ作り、
+/// ウィンドウごとの `Renderer` はサーフェスとバッファだけを持つ
+/// （新しいウィンドウでグリフを再ラスタライズしない）
+/// フォントとグリフアトラスのテクスチャの組
+struct FontAtlas {
+    /// グリフアトラステクスチャ
+    texture: wgpu::Texture,
+    /// テクスチャビュー
+    view: wgpu::TextureView,
+    /// フォントとグリフアトラス
+    glyphs: Mutex<GlyphCache>,
+}
+
+impl FontAtlas {
+    /// 読み込んだフォント用のアトラステクスチャを作成
+    fn new(device: &wgpu::Device, glyphs: GlyphCache) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Glyph Atlas"),
+            size: wgpu::Extent3d {
+                width: ATLAS_SIZE,
+                height: ATLAS_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self { texture, view, glyphs: Mutex::new(glyphs) }
+    }
+}
+
+pub struct RenderContext {
+    /// wgpu デバイス
+    device: wgpu::Device,
+    /// コマンドキュー
+    queue: wgpu::Queue,
+    /// 描画先のテクスチャフォーマット
+    format: wgpu::TextureFormat,
+    /// レンダーパイプライン
+    render_pipeline: wgpu::RenderPipeline,
+    /// 背景用パイプライン
+    bg_pipeline: wgpu::RenderPipeline,
+    /// バインドグループレイアウト
+    bind_group_layout: wgpu::BindGroupLayout,
+    /// サンプラー
+    sampler: wgpu::Sampler,
+    /// フォントとグリフアトラス（設定ファイルでフォントを変えると作り直す）
+    font: FontAtlas,
+    /// 大きさを変えたフォント（プロファイルのフォントの大きさ、使うウィンドウで共有）
+    sized_fonts: Mutex<Vec<Arc<FontAtlas>>>,
 }
 
 /// ユニフォームデータ（シェーダーに渡す定数）
@@ -333,68 +655,134 @@ struct Uniforms {
     cell_size: [f32; 2],
 }
 
-impl Renderer {
-    /// 新しいレンダラーを作成
-    pub async fn new(
-        surface: wgpu::Surface<'static>,
-        width: u32,
-        height: u32,
-        adapter: &wgpu::Adapter,
-    ) -> anyhow::Result<Self> {
+/// デバイスの要求内容（プロファイリング時はタイムスタンプクエリを要求）
+fn device_descriptor(adapter: &wgpu::Adapter) -> wgpu::DeviceDescriptor<'static> {
+    let mut required_features = wgpu::Features::empty();
+    if profiling::enabled() && adapter.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+        required_features |= wgpu::Features::TIMESTAMP_QUERY;
+    }
+    wgpu::DeviceDescriptor {
+        required_features,
+        ..Default::default()
+    }
+}
+
+/// 各レイヤーのインスタンスを1つのバッファに詰めて書き込む
+///
+/// 戻り値: 各レイヤーの描画範囲（MAX_INSTANCES を超えた分は切り捨て）
+fn pack_instances<'a>(
+    belt: &mut wgpu::util::StagingBelt,
+    encoder: &mut wgpu::CommandEncoder,
+    device: &wgpu::Device,
+    target: &wgpu::Buffer,
+    layers: impl Iterator<Item = &'a [CellInstance]> + Clone,
+) -> [Range<u32>; LAYER_COUNT] {
+    let mut ranges: [Range<u32>; LAYER_COUNT] = Default::default();
+    let total = layers.clone().map(|l| l.len()).sum::<usize>().min(MAX_INSTANCES);
+    let stride = std::mem::size_of::<CellInstance>();
+    let Some(size) = wgpu::BufferSize::new((total * stride) as u64) else {
+        return ranges;
+    };
+
+    let mut view = belt.write_buffer(encoder, target, 0, size, device);
+    let mut start = 0;
+    for (range, layer) in ranges.iter_mut().zip(layers) {
+        let count = layer.len().min(total - start);
+        let bytes: &[u8] = bytemuck::cast_slice(&layer[..count]);
+        view[start * stride..start * stride + bytes.len()].copy_from_slice(bytes);
+        *range = start as u32..(start + count) as u32;
+        start += count;
+    }
+    ranges
+}
+
+impl RenderContext {
+    /// ウィンドウ描画用のコンテキストを作成（最初のウィンドウのサーフェスに合わせる）
+    pub async fn new(surface: &wgpu::Surface<'_>, adapter: &wgpu::Adapter) -> Result<Arc<Self>> {
         // デバイスとキューを取得（最新の wgpu 25 API）
         let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor::default())
+            .request_device(&device_descriptor(adapter))
             .await?;
 
-        // サーフェス設定
         let caps = surface.get_capabilities(adapter);
-        let format = caps.formats[0];
-
-        let surface_config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format,
-            width,
-            height,
-            present_mode: wgpu::PresentMode::Fifo, // VSync
-            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
-            view_formats: vec![],
-            desired_maximum_frame_latency: 2,
-        };
-        surface.configure(&device, &surface_config);
+        let format = *caps
+            .formats
+            .first()
+            .context("サーフェスが対応するフォーマットがありません")?;
 
-        // フォントをロード（システムフォントから動的に読み込み）
-        let font = load_system_font()?;
-        // 日本語フォールバックフォントは遅延読み込み（起動高速化）
-        let fallback_font = None;
-        let fallback_font_tried = false;
+        Ok(Arc::new(Self::with_device(device, queue, format)?))
+    }
 
-        let font_size = DEFAULT_FONT_SIZE;
+    /// サーフェスなしのコンテキストを作成（テスト・キャプチャ用）
+    #[allow(dead_code)]
+    pub async fn new_headless() -> Result<Arc<Self>> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await
+            .context("ヘッドレス描画用のGPUアダプターが見つかりません")?;
+        let (device, queue) = adapter
+            .request_device(&device_descriptor(&adapter))
+            .await?;
 
-        // セルサイズを計算
-        let metrics = font.metrics('M', font_size);
-        let cell_width = metrics.advance_width.ceil();
-        let cell_height = font_size * 1.2;
+        Ok(Arc::new(Self::with_device(device, queue, HEADLESS_FORMAT)?))
+    }
 
-        // グリフアトラスを作成
-        let glyph_atlas = GlyphAtlas::new(ATLAS_SIZE, ATLAS_SIZE);
+    /// セルサイズ（幅, 高さ）
+    pub fn cell_size(&self) -> (f32, f32) {
+        let glyphs = self.font.glyphs.lock();
+        (glyphs.cell_width, glyphs.cell_height)
+    }
 
-        // アトラステクスチャを作成
-        let atlas_texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Glyph Atlas"),
-            size: wgpu::Extent3d {
-                width: ATLAS_SIZE,
-                height: ATLAS_SIZE,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::R8Unorm,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            view_formats: &[],
+    /// 大きさを変えたフォント（同じ大きさのものがあれば共有する）
+    fn sized_font(&self, size: f32) -> Result<Arc<FontAtlas>> {
+        let (font_path, _) = font_setting();
+        let mut fonts = self.sized_fonts.lock();
+        // どのウィンドウも使わなくなったものは捨てる
+        fonts.retain(|font| Arc::strong_count(font) > 1);
+        let found = fonts.iter().find(|font| {
+            let glyphs = font.glyphs.lock();
+            glyphs.font_path == font_path && glyphs.font_size == size
         });
+        if let Some(font) = found {
+            return Ok(Arc::clone(font));
+        }
+        let font = Arc::new(FontAtlas::new(&self.device, GlyphCache::load(font_path, size)?));
+        fonts.push(Arc::clone(&font));
+        Ok(font)
+    }
+
+    /// 設定のフォントに読み込み直す（変わっていなければ何もしない）
+    ///
+    /// グリフアトラスは作り直し、各ウィンドウは `Renderer::sync_cell_size` で新しいセルサイズに合わせる
+    pub fn reload_font(&self) -> Result<()> {
+        let (font_path, font_size) = font_setting();
+        let mut glyphs = self.font.glyphs.lock();
+        if glyphs.font_path == font_path && glyphs.font_size == font_size {
+            return Ok(());
+        }
+        *glyphs = GlyphCache::load(font_path, font_size)?;
+        log::info!("フォントを読み込み直しました（{}px）", font_size);
+        Ok(())
+    }
 
-        let atlas_view = atlas_texture.create_view(&wgpu::TextureViewDescriptor::default());
+    /// デバイス取得後の共通初期化（フォント・アトラス・パイプライン）
+    fn with_device(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        format: wgpu::TextureFormat,
+    ) -> Result<Self> {
+        // フォントをロード（設定のフォント、なければシステムフォントから動的に読み込み）
+        let (font_path, font_size) = font_setting();
+        // アトラステクスチャを作成
+        let font = FontAtlas::new(&device, GlyphCache::load(font_path, font_size)?);
 
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             address_mode_u: wgpu::AddressMode::ClampToEdge,
@@ -404,18 +792,6 @@ impl Renderer {
             ..Default::default()
         });
 
-        // ユニフォームバッファ
-        let uniforms = Uniforms {
-            screen_size: [width as f32, height as f32],
-            cell_size: [cell_width, cell_height],
-        };
-
-        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Uniform Buffer"),
-            contents: bytemuck::cast_slice(&[uniforms]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
         // バインドグループレイアウト
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Bind Group Layout"),
@@ -452,25 +828,6 @@ impl Renderer {
             ],
         });
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Bind Group"),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: uniform_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::TextureView(&atlas_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-            ],
-        });
-
         // シェーダーモジュール
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
@@ -567,192 +924,218 @@ impl Renderer {
             cache: None,
         });
 
-        // インスタンスバッファ（メモリ最適化: 8000セル = 約576KB × 2）
-        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Instance Buffer"),
-            size: (MAX_INSTANCES * std::mem::size_of::<CellInstance>()) as u64,
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let bg_instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("BG Instance Buffer"),
-            size: (MAX_INSTANCES * std::mem::size_of::<CellInstance>()) as u64,
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
         Ok(Self {
-            surface,
             device,
             queue,
-            surface_config,
+            format,
             render_pipeline,
             bg_pipeline,
-            instance_buffer,
-            bg_instance_buffer,
-            atlas_texture,
-            atlas_view,
+            bind_group_layout,
             sampler,
-            bind_group,
-            uniform_buffer,
             font,
-            fallback_font,
-            fallback_font_tried,
-            font_size,
-            cell_width,
-            cell_height,
-            glyph_atlas,
-            width,
-            height,
+            sized_fonts: Mutex::new(Vec::new()),
         })
     }
+}
 
-    /// ターミナルを描画
-    pub fn render(&mut self, terminal: &Terminal) -> Result<(), wgpu::SurfaceError> {
-        // インスタンスデータを構築
-        let (instances, bg_instances) = self.build_instances(terminal);
+// ═══════════════════════════════════════════════════════════════════════════
+// レンダラー（ウィンドウごと）
+// ═══════════════════════════════════════════════════════════════════════════
 
-        // グリフアトラスを更新（wgpu 25 の新しい型名を使用）
-        if self.glyph_atlas.dirty {
-            self.queue.write_texture(
-                wgpu::TexelCopyTextureInfo {
-                    texture: &self.atlas_texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d::ZERO,
-                    aspect: wgpu::TextureAspect::All,
-                },
-                &self.glyph_atlas.pixels,
-                wgpu::TexelCopyBufferLayout {
-                    offset: 0,
-                    bytes_per_row: Some(self.glyph_atlas.width),
-                    rows_per_image: Some(self.glyph_atlas.height),
-                },
-                wgpu::Extent3d {
-                    width: self.glyph_atlas.width,
-                    height: self.glyph_atlas.height,
-                    depth_or_array_layers: 1,
-                },
-            );
-            self.glyph_atlas.dirty = false;
+/// GPU レンダラー
+pub struct Renderer {
+    /// 共有リソース
+    context: Arc<RenderContext>,
+    /// このウィンドウだけ大きさを変えたフォント（None なら共有のフォント）
+    font: Option<Arc<FontAtlas>>,
+    /// プロファイルで決めたフォントの大きさ（None なら設定ファイルの大きさ）
+    font_size: Option<f32>,
+    /// フォントの大きさに掛けるウィンドウの拡大率（Wayland の 1.25 倍などの小数も）
+    scale: f32,
+    /// 描画先サーフェス（ヘッドレス時は None）
+    target: Option<SurfaceTarget>,
+    /// インスタンスバッファ
+    instance_buffer: wgpu::Buffer,
+    /// 背景インスタンスバッファ
+    bg_instance_buffer: wgpu::Buffer,
+    /// バインドグループ
+    bind_group: wgpu::BindGroup,
+    /// ユニフォームバッファ
+    uniform_buffer: wgpu::Buffer,
+    /// セル幅
+    cell_width: f32,
+    /// セル高さ
+    cell_height: f32,
+    /// セルの色（設定ファイルの [colors]）
+    palette: Palette,
+    /// カーソルの描き方（設定ファイルの [cursor]）
+    cursor_style: CursorStyle,
+    /// 画面の幅
+    width: u32,
+    /// 画面の高さ
+    height: u32,
+    /// 直前のフレームの統計
+    frame_stats: FrameStats,
+    /// GPU時間の計測（プロファイリング時のみ）
+    gpu_timer: Option<GpuTimer>,
+    /// 作業用バッファ
+    scratch: FrameScratch,
+    /// インスタンスデータの転送用バッファ（マップ済みのバッファに直接書き込む）
+    staging_belt: wgpu::util::StagingBelt,
+}
+
+impl Renderer {
+    /// 新しいレンダラーを作成
+    pub fn new(
+        context: Arc<RenderContext>,
+        surface: wgpu::Surface<'static>,
+        width: u32,
+        height: u32,
+        adapter: &wgpu::Adapter,
+    ) -> Result<Self> {
+        // サーフェス設定（パイプラインは共有のためフォーマットを揃える）
+        let caps = surface.get_capabilities(adapter);
+        if !caps.formats.contains(&context.format) {
+            anyhow::bail!("サーフェスが描画フォーマット {:?} に対応していません", context.format);
         }
 
-        // インスタンスバッファを更新（オーバーフロー防止）
-        let instances = if instances.len() > MAX_INSTANCES {
-            &instances[..MAX_INSTANCES]
-        } else {
-            &instances[..]
+        // 読み戻せるサーフェスなら、スクリーンショットは表示するテクスチャをそのまま写す
+        let mut usage = wgpu::TextureUsages::RENDER_ATTACHMENT;
+        if caps.usages.contains(wgpu::TextureUsages::COPY_SRC) {
+            usage |= wgpu::TextureUsages::COPY_SRC;
+        }
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage,
+            format: context.format,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo, // VSync
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
         };
-        let bg_instances = if bg_instances.len() > MAX_INSTANCES {
-            &bg_instances[..MAX_INSTANCES]
-        } else {
-            &bg_instances[..]
+        surface.configure(&context.device, &surface_config);
+
+        let target = SurfaceTarget {
+            surface,
+            config: surface_config,
         };
-        self.queue
-            .write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(instances));
-        self.queue
-            .write_buffer(&self.bg_instance_buffer, 0, bytemuck::cast_slice(bg_instances));
+        Ok(Self::with_context(context, width, height, Some(target)))
+    }
 
-        // 描画（内部のサーフェスを使用）
-        let output = self.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+    /// サーフェスなしのレンダラーを作成（テスト・キャプチャ用）
+    ///
+    /// 描画結果は `capture_panes` でピクセルとして読み戻す
+    #[allow(dead_code)]
+    pub async fn new_headless(width: u32, height: u32) -> Result<Self> {
+        let context = RenderContext::new_headless().await?;
+        Ok(Self::with_context(context, width, height, None))
+    }
 
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            });
+    /// ウィンドウごとのバッファを作成
+    fn with_context(
+        context: Arc<RenderContext>,
+        width: u32,
+        height: u32,
+        target: Option<SurfaceTarget>,
+    ) -> Self {
+        let device = &context.device;
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
+        // タイムスタンプクエリが有効ならGPU時間を計測
+        let gpu_timer = device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+            .then(|| GpuTimer::new(device, &context.queue));
 
-            // 背景を描画
-            render_pass.set_pipeline(&self.bg_pipeline);
-            render_pass.set_bind_group(0, &self.bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.bg_instance_buffer.slice(..));
-            render_pass.draw(0..4, 0..bg_instances.len() as u32);
+        let (cell_width, cell_height) = context.cell_size();
 
-            // テキストを描画
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
-            render_pass.draw(0..4, 0..instances.len() as u32);
-        }
+        // ユニフォームバッファ
+        let uniforms = Uniforms {
+            screen_size: [width as f32, height as f32],
+            cell_size: [cell_width, cell_height],
+        };
 
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[uniforms]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
 
-        Ok(())
-    }
+        let bind_group = Self::create_bind_group(&context, &uniform_buffer, &context.font);
 
-    /// エクスプローラーオーバーレイを描画（中央ポップアップ）
-    pub fn render_explorer_overlay(
-        &mut self,
-        explorer: &Explorer,
-        screen_cols: usize,
-        screen_rows: usize,
-    ) -> (Vec<CellInstance>, Vec<CellInstance>) {
-        let mut instances = Vec::new();
-        let mut bg_instances = Vec::new();
 
-        // ポップアップのサイズと位置
-        let popup_width = 50.min(screen_cols.saturating_sub(4));
-        let popup_height = 20.min(screen_rows.saturating_sub(4));
-        let start_col = (screen_cols.saturating_sub(popup_width)) / 2;
-        let start_row = (screen_rows.saturating_sub(popup_height)) / 2;
+        // インスタンスバッファ（メモリ最適化: 8000セル = 約576KB × 2）
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instance Buffer"),
+            size: (MAX_INSTANCES * std::mem::size_of::<CellInstance>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
 
-        // 背景（半透明風の暗い色）
-        let bg_color = Color::rgb(25, 30, 40).to_f32_array();
-        let header_bg = Color::rgb(40, 50, 65).to_f32_array();
-        let selected_bg = Color::rgb(180, 60, 60).to_f32_array();  // 赤で選択行を強調
-        let border_color = Color::EMERALD.to_f32_array();
+        let bg_instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("BG Instance Buffer"),
+            size: (MAX_INSTANCES * std::mem::size_of::<CellInstance>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
 
-        // ヘッダー背景
-        let header = " EXPLORER (↑↓:move Enter:open g:cd Esc:close)";
-        for col in 0..popup_width {
-            bg_instances.push(CellInstance {
-                position: [(start_col + col) as f32, start_row as f32],
+
+        Self {
+            context,
+            font: None,
+            font_size: None,
+            scale: 1.0,
+            target,
+            instance_buffer,
+            bg_instance_buffer,
+            bind_group,
+            uniform_buffer,
+            cell_width,
+            cell_height,
+            palette: config::current().active_palette().clone(),
+            cursor_style: config::current().cursor_style,
+            width,
+            height,
+            frame_stats: FrameStats::default(),
+            gpu_timer,
+            scratch: FrameScratch::default(),
+            staging_belt: wgpu::util::StagingBelt::new(STAGING_CHUNK_SIZE),
+        }
+    }
+
+    /// オーバーレイの背景を1行分追加
+    fn push_overlay_bg(layer: &mut Layer, start_col: usize, row: usize, width: usize, color: [f32; 4]) {
+        for col in 0..width {
+            layer.bg.push(CellInstance {
+                position: [(start_col + col) as f32, row as f32],
                 fg_color: [0.0, 0.0, 0.0, 0.0],
-                bg_color: header_bg,
+                bg_color: color,
                 uv_offset: [0.0, 0.0],
                 uv_size: [0.0, 0.0],
                 glyph_offset: [0.0, 0.0],
                 glyph_size: [0.0, 0.0],
             });
         }
-        // ヘッダーテキスト
-        for (i, c) in header.chars().enumerate() {
-            if i >= popup_width { break; }
+    }
+
+    /// オーバーレイのテキストを1行分追加（max_colsで切り詰め）
+    fn push_overlay_text(
+        &mut self,
+        layer: &mut Layer,
+        text: &str,
+        start_col: usize,
+        row: usize,
+        max_cols: usize,
+        color: [f32; 4],
+    ) {
+        let mut glyphs = self.font().glyphs.lock();
+        for (i, c) in text.chars().enumerate() {
+            if i >= max_cols { break; }
             if c != ' ' {
-                self.ensure_fallback_font(c);
-                if let Some(glyph) = self.glyph_atlas.get_or_insert(
-                    c,
-                    &self.font,
-                    self.fallback_font.as_ref(),
-                    self.font_size,
-                ) {
-                    instances.push(CellInstance {
-                        position: [(start_col + i) as f32, start_row as f32],
-                        fg_color: border_color,
+                if let Some(glyph) = glyphs.glyph(c) {
+                    layer.text.push(CellInstance {
+                        position: [(start_col + i) as f32, row as f32],
+                        fg_color: color,
                         bg_color: [0.0, 0.0, 0.0, 0.0],
                         uv_offset: glyph.uv_offset,
                         uv_size: glyph.uv_size,
@@ -762,10 +1145,64 @@ impl Renderer {
                 }
             }
         }
+    }
 
-        // エントリを描画
-        let visible_rows = popup_height - 1;
-        let start = explorer.scroll_offset;
+    /// エクスプローラーオーバーレイを描画（中央ポップアップ）
+    fn render_explorer_overlay(
+        &mut self,
+        layer: &mut Layer,
+        explorer: &Explorer,
+        screen_cols: usize,
+        screen_rows: usize,
+        (reserved_top, reserved_bottom): (usize, usize),
+    ) {
+        let sidebar = explorer.layout == ExplorerLayout::Sidebar;
+        let (popup_width, popup_height, preview_width, start_col, start_row) = if sidebar {
+            // 左端に固定幅（ペインが1割以上残るように）、プレビューは出さない
+            let width = explorer.width.min(screen_cols * 9 / 10);
+            (width, screen_rows.saturating_sub(reserved_top + reserved_bottom), 0, 0, reserved_top)
+        } else {
+            // ポップアップのサイズと位置（幅に余裕があればツリーの右にプレビュー）
+            let popup_width = 50.min(screen_cols.saturating_sub(4));
+            let popup_height = 20.min(screen_rows.saturating_sub(4));
+            let preview_width = 60.min(screen_cols.saturating_sub(4 + popup_width));
+            let preview_width = if preview_width < 20 { 0 } else { preview_width };
+            let start_col = (screen_cols.saturating_sub(popup_width + preview_width)) / 2;
+            let start_row = (screen_rows.saturating_sub(popup_height)) / 2;
+            (popup_width, popup_height, preview_width, start_col, start_row)
+        };
+        if preview_width > 0 {
+            self.render_explorer_preview(layer, explorer, start_col + popup_width, start_row, preview_width, popup_height);
+        }
+
+        // 背景（半透明風の暗い色）
+        let bg_color = Color::rgb(25, 30, 40).to_f32_array();
+        let header_bg = Color::rgb(40, 50, 65).to_f32_array();
+        let selected_bg = Color::rgb(180, 60, 60).to_f32_array();  // 赤で選択行を強調
+        let marked_bg = Color::rgb(50, 60, 95).to_f32_array();  // 印を付けた行
+        let border_color = self.palette.accent.to_f32_array();
+
+        // ヘッダー（サイドバーは狭いのでルートの名前だけ）
+        let header = if sidebar {
+            let name = explorer.root.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+            format!(" {}", name)
+        } else {
+            " EXPLORER (↑↓:move Enter:open g:cd Esc:close)".to_string()
+        };
+        Self::push_overlay_bg(layer, start_col, start_row, popup_width, header_bg);
+        self.push_overlay_text(layer, &header, start_col, start_row, popup_width, border_color);
+
+        // エントリを描画（最下段はファイル操作の入力欄）
+        let visible_rows = popup_height.saturating_sub(2);
+        if let Some(filter) = &explorer.filter {
+            self.render_explorer_filter(layer, filter, start_col, start_row, popup_width, visible_rows);
+            return;
+        }
+        if let Some(selected) = explorer.places {
+            self.render_explorer_places(layer, explorer, selected, start_col, start_row, popup_width, visible_rows);
+            return;
+        }
+        let start = explorer.scroll_offset.min(explorer.entries.len());
         let end = (start + visible_rows).min(explorer.entries.len());
 
         for (idx, entry) in explorer.entries[start..end].iter().enumerate() {
@@ -773,190 +1210,713 @@ impl Renderer {
             let is_selected = start + idx == explorer.selected;
 
             // 背景色
-            let row_bg = if is_selected { selected_bg } else { bg_color };
+            let is_marked = explorer.is_marked(&entry.path);
+            let row_bg = if is_selected { selected_bg } else if is_marked { marked_bg } else { bg_color };
 
-            // アイコンとファイル名
+            // アイコンとファイル名（フォントにアイコンがなければ ▶ / ▼ だけ）
             let indent = "  ".repeat(entry.depth);
-            let icon = match entry.kind {
+            let marker = match entry.kind {
+                _ if is_marked => "✓ ",
                 EntryKind::Directory => if entry.expanded { "▼ " } else { "▶ " },
                 EntryKind::File => "  ",
             };
-            let display = format!(" {}{}{}", indent, icon, entry.name);
+            let icon = entry.icon();
+            let display = if self.font().glyphs.lock().has_glyph(icon) {
+                format!(" {}{}{} {}", indent, marker, icon, entry.name)
+            } else {
+                format!(" {}{}{}", indent, marker, entry.name)
+            };
 
             let fg_color = match entry.kind {
-                EntryKind::Directory => Color::EMERALD.to_f32_array(),
+                EntryKind::Directory => self.palette.accent.to_f32_array(),
                 EntryKind::File => [0.85, 0.85, 0.85, 1.0],
             };
 
-            // 背景を先に描画（bg_instancesに追加）
-            for col in 0..popup_width {
-                let position = [(start_col + col) as f32, row as f32];
-                bg_instances.push(CellInstance {
-                    position,
-                    fg_color: [0.0, 0.0, 0.0, 0.0],
-                    bg_color: row_bg,
-                    uv_offset: [0.0, 0.0],
-                    uv_size: [0.0, 0.0],
-                    glyph_offset: [0.0, 0.0],
-                    glyph_size: [0.0, 0.0],
-                });
+            Self::push_overlay_bg(layer, start_col, row, popup_width, row_bg);
+            self.push_overlay_text(layer, &display, start_col, row, popup_width, fg_color);
+        }
+
+        // 残りの行を背景で埋める
+        for idx in (end - start)..visible_rows {
+            Self::push_overlay_bg(layer, start_col, start_row + idx + 1, popup_width, bg_color);
+        }
+
+        // ファイル操作の入力欄（入力中でなければエラーか操作説明）
+        let footer_row = start_row + visible_rows + 1;
+        let selected_name = match explorer.marked.len() {
+            0 => explorer.selected_entry().map(|entry| entry.name.clone()).unwrap_or_default(),
+            count => format!("{} items", count),
+        };
+        let (footer, footer_color) = match (&explorer.prompt, &explorer.message) {
+            (Some(ExplorerPrompt::NewFile(name)), _) => (format!(" New file: {}_", name), border_color),
+            (Some(ExplorerPrompt::NewDirectory(name)), _) => (format!(" New folder: {}_", name), border_color),
+            (Some(ExplorerPrompt::Rename(name)), _) => (format!(" Rename: {}_", name), border_color),
+            (Some(ExplorerPrompt::ConfirmDelete), _) => {
+                (format!(" Delete {}? (y/n)", selected_name), Color::rgb(240, 120, 120).to_f32_array())
+            }
+            (Some(ExplorerPrompt::ConfirmMove(_)), _) => {
+                (format!(" Move {} here? (y/n)", selected_name), Color::rgb(240, 120, 120).to_f32_array())
             }
+            (None, Some(message)) => (format!(" {}", message), Color::rgb(240, 120, 120).to_f32_array()),
+            // 印を付けている間はまとめて行う操作の説明
+            (None, None) if !explorer.marked.is_empty() => {
+                (format!(" {}  y/p:path m:move d:del Esc:clear", selected_name), border_color)
+            }
+            // プレビューを出せない幅なら、選択中のエントリの大きさと更新日時をここに出す
+            (None, None) => match explorer.selected_metadata().filter(|_| preview_width == 0) {
+                Some((name, metadata)) => (format!(" {}  {}", name, metadata.describe()), [0.6, 0.6, 0.6, 1.0]),
+                None => (" /:filter b/B:marks y/p:path n/N:new r:ren d:del".to_string(), [0.6, 0.6, 0.6, 1.0]),
+            },
+        };
+        Self::push_overlay_bg(layer, start_col, footer_row, popup_width, header_bg);
+        self.push_overlay_text(layer, &footer, start_col, footer_row, popup_width, footer_color);
+    }
 
-            // テキストを描画
-            for (col, c) in display.chars().enumerate() {
-                if col >= popup_width { break; }
-                if c != ' ' {
-                    self.ensure_fallback_font(c);
-                    if let Some(glyph) = self.glyph_atlas.get_or_insert(
-                        c,
-                        &self.font,
-                        self.fallback_font.as_ref(),
-                        self.font_size,
-                    ) {
-                        instances.push(CellInstance {
-                            position: [(start_col + col) as f32, row as f32],
-                            fg_color,
-                            bg_color: [0.0, 0.0, 0.0, 0.0],
-                            uv_offset: glyph.uv_offset,
-                            uv_size: glyph.uv_size,
-                            glyph_offset: glyph.offset,
-                            glyph_size: glyph.size,
-                        });
-                    }
+    /// エクスプローラーのプレビューを描画
+    /// 見出しに選択中のエントリの名前と大きさ・更新日時、本文は色分けしたファイルの先頭の行
+    fn render_explorer_preview(
+        &mut self,
+        layer: &mut Layer,
+        explorer: &Explorer,
+        start_col: usize,
+        start_row: usize,
+        width: usize,
+        height: usize,
+    ) {
+        let bg_color = Color::rgb(20, 24, 32).to_f32_array();
+        let header_bg = Color::rgb(40, 50, 65).to_f32_array();
+        let dim = [0.6, 0.6, 0.6, 1.0];
+        for row in 0..height {
+            Self::push_overlay_bg(layer, start_col, start_row + row, width, if row == 0 { header_bg } else { bg_color });
+        }
+        if let Some((name, metadata)) = explorer.selected_metadata() {
+            self.push_overlay_text(layer, &format!(" {}", name), start_col, start_row, width, dim);
+            let info = format!("{} ", metadata.describe());
+            let info_len = info.chars().count();
+            if name.chars().count() + 2 + info_len < width {
+                self.push_overlay_text(layer, &info, start_col + width - info_len, start_row, info_len, dim);
+            }
+        }
+        let Some(current) = &explorer.preview.current else { return };
+        let lines = match &current.content {
+            PreviewContent::Text(lines) => lines,
+            PreviewContent::Loading => return,
+            PreviewContent::Image(thumbnail) => {
+                let size = format!(" {}×{}", thumbnail.original.0, thumbnail.original.1);
+                self.push_overlay_text(layer, &size, start_col, start_row + 1, width, dim);
+                let area = (width.saturating_sub(2), height.saturating_sub(3));
+                self.render_thumbnail(layer, thumbnail, (start_col + 1, start_row + 2), area, bg_color);
+                return;
+            }
+            PreviewContent::Binary => {
+                self.push_overlay_text(layer, " (binary file)", start_col, start_row + 1, width, dim);
+                return;
+            }
+            PreviewContent::Error(message) => {
+                self.push_overlay_text(layer, &format!(" {}", message), start_col, start_row + 1, width, dim);
+                return;
+            }
+        };
+        for (idx, line) in lines.iter().take(height.saturating_sub(1)).enumerate() {
+            let row = start_row + 1 + idx;
+            // 左端に1文字の余白
+            let mut col = 1;
+            for (kind, text) in line {
+                if col >= width {
+                    break;
                 }
+                let color = match kind {
+                    TokenKind::Plain => [0.85, 0.85, 0.85, 1.0],
+                    TokenKind::Keyword => Color::rgb(120, 180, 255).to_f32_array(),
+                    TokenKind::String => Color::rgb(230, 190, 110).to_f32_array(),
+                    TokenKind::Comment => Color::rgb(110, 120, 130).to_f32_array(),
+                    TokenKind::Number => Color::rgb(200, 140, 230).to_f32_array(),
+                };
+                self.push_overlay_text(layer, text, start_col + col, row, width - col, color);
+                col += text.chars().count();
             }
         }
+    }
 
-        // 残りの行を背景で埋める
-        for idx in (end - start)..visible_rows {
-            let row = start_row + idx + 1;
-            for col in 0..popup_width {
-                bg_instances.push(CellInstance {
-                    position: [(start_col + col) as f32, row as f32],
-                    fg_color: [0.0, 0.0, 0.0, 0.0],
-                    bg_color: bg_color,
-                    uv_offset: [0.0, 0.0],
-                    uv_size: [0.0, 0.0],
-                    glyph_offset: [0.0, 0.0],
-                    glyph_size: [0.0, 0.0],
-                });
+    /// 画像のサムネイルを描画（セルの範囲に収まるよう拡大・縮小し、1ピクセルずつ塗りつぶしの矩形にする）
+    /// 半透明のピクセルは背景色と混ぜる
+    fn render_thumbnail(
+        &self,
+        layer: &mut Layer,
+        thumbnail: &Thumbnail,
+        (col, row): (usize, usize),
+        (cols, rows): (usize, usize),
+        bg_color: [f32; 4],
+    ) {
+        if thumbnail.width == 0 || thumbnail.height == 0 {
+            return;
+        }
+        let scale = (cols as f32 * self.cell_width / thumbnail.width as f32)
+            .min(rows as f32 * self.cell_height / thumbnail.height as f32);
+        if scale <= 0.0 {
+            return;
+        }
+        let (left, top) = (col as f32 * self.cell_width, row as f32 * self.cell_height);
+        // 隣り合う矩形の間に隙間ができないよう、端を整数に揃える
+        let edge = |origin: f32, index: u32| (origin + index as f32 * scale).floor();
+        for (index, pixel) in thumbnail.pixels.iter().enumerate() {
+            if pixel[3] == 0 {
+                continue;
             }
+            let (x, y) = (index as u32 % thumbnail.width, index as u32 / thumbnail.width);
+            let alpha = pixel[3] as f32 / 255.0;
+            let mix = |channel: usize| pixel[channel] as f32 / 255.0 * alpha + bg_color[channel] * (1.0 - alpha);
+            let color = [mix(0), mix(1), mix(2), 1.0];
+            let (x0, y0) = (edge(left, x), edge(top, y));
+            layer.bg.push(CellInstance {
+                position: [0.0, 0.0],
+                fg_color: color,
+                bg_color: color,
+                uv_offset: [0.0, 0.0],
+                uv_size: [0.0, 0.0],
+                glyph_offset: [x0, y0],
+                glyph_size: [edge(left, x + 1) - x0, edge(top, y + 1) - y0],
+            });
         }
+    }
 
-        (instances, bg_instances)
+    /// エクスプローラーの絞り込みの候補と入力欄を描画（ヘッダーの下から）
+    fn render_explorer_filter(
+        &mut self,
+        layer: &mut Layer,
+        filter: &ExplorerFilter,
+        start_col: usize,
+        start_row: usize,
+        popup_width: usize,
+        visible_rows: usize,
+    ) {
+        let bg_color = Color::rgb(25, 30, 40).to_f32_array();
+        let header_bg = Color::rgb(40, 50, 65).to_f32_array();
+        let selected_bg = Color::rgb(180, 60, 60).to_f32_array();
+
+        // 候補（選択中の行が見える範囲）
+        let first = filter.selected.saturating_sub(visible_rows.saturating_sub(1));
+        let mut shown = 0;
+        for (idx, (relative, is_dir)) in filter.results().enumerate().skip(first).take(visible_rows) {
+            let row = start_row + 1 + shown;
+            let row_bg = if idx == filter.selected { selected_bg } else { bg_color };
+            let (display, fg_color) = if is_dir {
+                (format!(" {}/", relative), self.palette.accent.to_f32_array())
+            } else {
+                (format!(" {}", relative), [0.85, 0.85, 0.85, 1.0])
+            };
+            Self::push_overlay_bg(layer, start_col, row, popup_width, row_bg);
+            self.push_overlay_text(layer, &display, start_col, row, popup_width, fg_color);
+            shown += 1;
+        }
+        for idx in shown..visible_rows {
+            Self::push_overlay_bg(layer, start_col, start_row + 1 + idx, popup_width, bg_color);
+        }
+
+        // 入力欄（右端に件数）
+        let footer_row = start_row + visible_rows + 1;
+        let prompt = format!(" / {}_", filter.query);
+        let (matched, total) = filter.counts();
+        let count = format!("{}/{} ", matched, total);
+        Self::push_overlay_bg(layer, start_col, footer_row, popup_width, header_bg);
+        self.push_overlay_text(layer, &prompt, start_col, footer_row, popup_width, self.palette.accent.to_f32_array());
+        let count_len = count.chars().count();
+        if prompt.chars().count() + count_len < popup_width {
+            self.push_overlay_text(layer, &count, start_col + popup_width - count_len, footer_row, count_len, [0.6, 0.6, 0.6, 1.0]);
+        }
     }
 
-    /// 日本語フォントを遅延読み込み（必要な時のみ）
-    fn ensure_fallback_font(&mut self, c: char) {
-        // ASCII文字はフォールバック不要
-        if c.is_ascii() {
+    /// エクスプローラーのブックマークの一覧（ピン留め、続いて最近のディレクトリ）と操作説明を描画（ヘッダーの下から）
+    #[allow(clippy::too_many_arguments)]
+    fn render_explorer_places(
+        &mut self,
+        layer: &mut Layer,
+        explorer: &Explorer,
+        selected: usize,
+        start_col: usize,
+        start_row: usize,
+        popup_width: usize,
+        visible_rows: usize,
+    ) {
+        let bg_color = Color::rgb(25, 30, 40).to_f32_array();
+        let header_bg = Color::rgb(40, 50, 65).to_f32_array();
+        let selected_bg = Color::rgb(180, 60, 60).to_f32_array();
+        let dim = [0.6, 0.6, 0.6, 1.0];
+        let home = config::home_dir();
+
+        // 一覧（選択中の行が見える範囲）
+        let places = explorer.bookmarks.places();
+        let first = selected.saturating_sub(visible_rows.saturating_sub(1));
+        let mut shown = 0;
+        for (idx, (kind, path)) in places.iter().enumerate().skip(first).take(visible_rows) {
+            let row = start_row + 1 + shown;
+            let row_bg = if idx == selected { selected_bg } else { bg_color };
+            let (marker, fg_color) = match kind {
+                PlaceKind::Pinned => ("★", self.palette.accent.to_f32_array()),
+                PlaceKind::Recent => (" ", [0.85, 0.85, 0.85, 1.0]),
+            };
+            let display = format!(" {} {}", marker, pane::shorten_home(path, home.as_deref()));
+            Self::push_overlay_bg(layer, start_col, row, popup_width, row_bg);
+            self.push_overlay_text(layer, &display, start_col, row, popup_width, fg_color);
+            shown += 1;
+        }
+        for idx in shown..visible_rows {
+            Self::push_overlay_bg(layer, start_col, start_row + 1 + idx, popup_width, bg_color);
+        }
+        if places.is_empty() {
+            self.push_overlay_text(layer, " (no bookmarks: B pins a directory)", start_col, start_row + 1, popup_width, dim);
+        }
+
+        // 操作説明
+        let footer_row = start_row + visible_rows + 1;
+        Self::push_overlay_bg(layer, start_col, footer_row, popup_width, header_bg);
+        self.push_overlay_text(layer, " BOOKMARKS  Enter:open x:remove Esc:back", start_col, footer_row, popup_width, dim);
+    }
+
+    /// スクロールバック検索を描画（中央ポップアップ、入力欄・候補・操作説明）
+    fn render_finder(&mut self, layer: &mut Layer, finder: &Finder, screen_cols: usize, screen_rows: usize) {
+        let popup_width = 70.min(screen_cols.saturating_sub(4));
+        let popup_height = 20.min(screen_rows.saturating_sub(4));
+        if popup_width == 0 || popup_height < 3 {
             return;
         }
-        // メインフォントにあればフォールバック不要
-        if self.font.has_glyph(c) {
+        let start_col = screen_cols.saturating_sub(popup_width) / 2;
+        let start_row = screen_rows.saturating_sub(popup_height) / 2;
+
+        let bg_color = Color::rgb(25, 30, 40).to_f32_array();
+        let header_bg = Color::rgb(40, 50, 65).to_f32_array();
+        let selected_bg = Color::rgb(180, 60, 60).to_f32_array();
+        let text_color = [0.85, 0.85, 0.85, 1.0];
+        let dim = [0.6, 0.6, 0.6, 1.0];
+
+        // 入力欄（右端に件数）
+        let prompt = format!(" > {}_", finder.query);
+        let count = format!("{}/{} ", finder.match_count(), finder.line_count());
+        Self::push_overlay_bg(layer, start_col, start_row, popup_width, header_bg);
+        self.push_overlay_text(layer, &prompt, start_col, start_row, popup_width, self.palette.accent.to_f32_array());
+        let count_len = count.chars().count();
+        if prompt.chars().count() + count_len < popup_width {
+            self.push_overlay_text(layer, &count, start_col + popup_width - count_len, start_row, count_len, dim);
+        }
+
+        // 候補（選択中の行が見える範囲）
+        let visible_rows = popup_height - 2;
+        let first = finder.selected.saturating_sub(visible_rows - 1);
+        let mut shown = 0;
+        for (idx, line) in finder.results().enumerate().skip(first).take(visible_rows) {
+            let row = start_row + 1 + shown;
+            let row_bg = if idx == finder.selected { selected_bg } else { bg_color };
+            Self::push_overlay_bg(layer, start_col, row, popup_width, row_bg);
+            self.push_overlay_text(layer, &format!(" {}", line.trim_start()), start_col, row, popup_width, text_color);
+            shown += 1;
+        }
+        for idx in shown..visible_rows {
+            Self::push_overlay_bg(layer, start_col, start_row + 1 + idx, popup_width, bg_color);
+        }
+
+        // 操作説明
+        let footer_row = start_row + popup_height - 1;
+        Self::push_overlay_bg(layer, start_col, footer_row, popup_width, header_bg);
+        let help = match finder.kind {
+            FinderKind::History => " Enter:jump  Shift+Enter:copy  Esc:close",
+            FinderKind::Palette => " Enter:run  Esc:close",
+            FinderKind::ClipboardHistory => " Enter:paste  Esc:close",
+            FinderKind::Log => " Enter:copy  Esc:close",
+        };
+        self.push_overlay_text(layer, help, start_col, footer_row, popup_width, dim);
+    }
+
+    /// コピーモードの仮想カーソルと、ペインの右下の状態表示を描画
+    fn render_copy_cursor(&mut self, layer: &mut Layer, cursor: &CopyCursor) {
+        let rect = &cursor.rect;
+        let col_offset = rect.x * self.width as f32 / self.cell_width;
+        let row_offset = rect.y * self.height as f32 / self.cell_height;
+        let cursor_bg = self.palette.accent.to_f32_array();
+        let cursor_fg = Color::rgb(20, 24, 32).to_f32_array();
+
+        // カーソルは反転したブロック（ターミナルの文字の上に描き直す）
+        let position = [cursor.col as f32 + col_offset, cursor.row as f32 + row_offset];
+        self.push_pane_cell(layer, position, cursor.character, cursor_fg, cursor_bg);
+
+        // 状態はペインの最下段の右端
+        let start_col = col_offset as usize;
+        let end_col = ((rect.x + rect.width) * self.width as f32 / self.cell_width) as usize;
+        let bottom = ((rect.y + rect.height) * self.height as f32 / self.cell_height) as usize;
+        let status = format!(" {} ", cursor.status);
+        let width = status.chars().count().min(end_col.saturating_sub(start_col));
+        if width == 0 || bottom == 0 {
             return;
         }
-        // 既に読み込み試行済みならスキップ
-        if self.fallback_font_tried {
+        let col = end_col - width;
+        Self::push_overlay_bg(layer, col, bottom - 1, width, Color::rgb(40, 50, 65).to_f32_array());
+        self.push_overlay_text(layer, &status, col, bottom - 1, width, self.palette.accent.to_f32_array());
+    }
+
+    /// 届いた行の数をペインの最下段の中央に描画（クリックで最新の表示に戻る）
+    fn render_new_lines(&mut self, layer: &mut Layer, pills: &[NewLines]) {
+        let pill_bg = self.palette.accent.to_f32_array();
+        let pill_fg = Color::rgb(20, 24, 32).to_f32_array();
+
+        for pill in pills {
+            if pill.rows == 0 {
+                continue;
+            }
+            let col_offset = pill.rect.x * self.width as f32 / self.cell_width;
+            let row_offset = pill.rect.y * self.height as f32 / self.cell_height;
+            let label = scrollbar::new_lines_label(pill.count);
+            let columns = scrollbar::new_lines_columns(pill.cols, &label);
+            let row = (pill.rows - 1) as f32 + row_offset;
+            for (col, c) in columns.zip(label.chars()) {
+                self.push_pane_cell(layer, [col as f32 + col_offset, row], c, pill_fg, pill_bg);
+            }
+        }
+    }
+
+    /// ヒントモードのラベルを一致の先頭に重ねて描画（入力済みの文字は暗く）
+    fn render_hint_labels(&mut self, layer: &mut Layer, hints: &HintLabels) {
+        let col_offset = hints.rect.x * self.width as f32 / self.cell_width;
+        let row_offset = hints.rect.y * self.height as f32 / self.cell_height;
+        let label_bg = Color::rgb(240, 180, 60).to_f32_array();
+        let label_fg = Color::rgb(20, 24, 32).to_f32_array();
+        let typed_fg = Color::rgb(120, 90, 30).to_f32_array();
+
+        for (col, row, label, typed) in &hints.labels {
+            for (i, c) in label.chars().enumerate() {
+                let position = [(col + i) as f32 + col_offset, *row as f32 + row_offset];
+                let fg = if i < *typed { typed_fg } else { label_fg };
+                self.push_pane_cell(layer, position, c, fg, label_bg);
+            }
+        }
+    }
+
+    /// ペインの中の1セルを背景ごと描き直す（位置はペインのずれを含むセル座標）
+    fn push_pane_cell(&mut self, layer: &mut Layer, position: [f32; 2], c: char, fg: [f32; 4], bg: [f32; 4]) {
+        layer.bg.push(CellInstance {
+            position,
+            fg_color: [0.0, 0.0, 0.0, 0.0],
+            bg_color: bg,
+            uv_offset: [0.0, 0.0],
+            uv_size: [0.0, 0.0],
+            glyph_offset: [0.0, 0.0],
+            glyph_size: [0.0, 0.0],
+        });
+        if c.is_whitespace() || c == '\0' {
             return;
         }
-        // 日本語フォントを読み込み
-        self.fallback_font_tried = true;
-        self.fallback_font = load_japanese_font();
+        if let Some(glyph) = self.font().glyphs.lock().glyph(c) {
+            layer.text.push(CellInstance {
+                position,
+                fg_color: fg,
+                bg_color: [0.0, 0.0, 0.0, 0.0],
+                uv_offset: glyph.uv_offset,
+                uv_size: glyph.uv_size,
+                glyph_offset: glyph.offset,
+                glyph_size: glyph.size,
+            });
+        }
     }
 
-    /// グリッドからインスタンスデータを構築
-    fn build_instances(&mut self, terminal: &Terminal) -> (Vec<CellInstance>, Vec<CellInstance>) {
-        let grid = terminal.active_grid();
-        let mut instances = Vec::with_capacity(grid.cols * grid.rows);
-        let mut bg_instances = Vec::with_capacity(grid.cols * grid.rows);
+    /// 確認ダイアログを描画（中央ポップアップ、1行目が見出し）
+    fn render_dialog(&mut self, layer: &mut Layer, lines: &[String], screen_cols: usize, screen_rows: usize) {
+        let Some((header, body)) = lines.split_first() else { return };
 
-        for row in 0..grid.rows {
-            for col in 0..grid.cols {
-                let cell = &grid[(col, row)];
+        // 一番長い行に合わせる（左右に1列ずつ余白）
+        let longest = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+        let popup_width = (longest + 2).min(screen_cols.saturating_sub(4)).max(1);
+        let start_col = screen_cols.saturating_sub(popup_width) / 2;
+        let start_row = screen_rows.saturating_sub(lines.len()) / 2;
 
-                let position = [col as f32, row as f32];
+        let bg_color = Color::rgb(25, 30, 40).to_f32_array();
+        let header_bg = Color::rgb(40, 50, 65).to_f32_array();
+        let text_color = [0.85, 0.85, 0.85, 1.0];
 
-                // 背景インスタンス
-                bg_instances.push(CellInstance {
-                    position,
-                    fg_color: cell.fg.to_f32_array(),
-                    bg_color: cell.bg.to_f32_array(),
-                    uv_offset: [0.0, 0.0],
-                    uv_size: [0.0, 0.0],
-                    glyph_offset: [0.0, 0.0],
-                    glyph_size: [0.0, 0.0],
-                });
+        Self::push_overlay_bg(layer, start_col, start_row, popup_width, header_bg);
+        self.push_overlay_text(layer, &format!(" {}", header), start_col, start_row, popup_width, self.palette.accent.to_f32_array());
+        for (idx, line) in body.iter().enumerate() {
+            let row = start_row + idx + 1;
+            Self::push_overlay_bg(layer, start_col, row, popup_width, bg_color);
+            self.push_overlay_text(layer, &format!(" {}", line), start_col, row, popup_width, text_color);
+        }
+    }
 
-                // 空白以外はグリフを描画
-                if cell.character != ' ' {
-                    // 必要に応じて日本語フォントを遅延読み込み
-                    self.ensure_fallback_font(cell.character);
-                    if let Some(glyph) = self.glyph_atlas.get_or_insert(
-                        cell.character,
-                        &self.font,
-                        self.fallback_font.as_ref(),
-                        self.font_size,
-                    ) {
-                        instances.push(CellInstance {
-                            position,
-                            fg_color: cell.fg.to_f32_array(),
-                            bg_color: cell.bg.to_f32_array(),
-                            uv_offset: glyph.uv_offset,
-                            uv_size: glyph.uv_size,
-                            glyph_offset: glyph.offset,
-                            glyph_size: glyph.size,
-                        });
-                    }
-                }
+    /// 続けて押せるキーの一覧（右下、ステータスバーの上）
+    fn render_key_hints(
+        &mut self,
+        layer: &mut Layer,
+        lines: &[String],
+        screen_cols: usize,
+        screen_rows: usize,
+        reserved_bottom: usize,
+    ) {
+        let Some((header, body)) = lines.split_first() else { return };
+
+        let longest = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+        let popup_width = (longest + 2).min(screen_cols).max(1);
+        let start_col = screen_cols.saturating_sub(popup_width + 1);
+        let start_row = screen_rows.saturating_sub(lines.len() + reserved_bottom);
+
+        let bg_color = Color::rgb(25, 30, 40).to_f32_array();
+        let header_bg = Color::rgb(40, 50, 65).to_f32_array();
+        let text_color = [0.85, 0.85, 0.85, 1.0];
+
+        Self::push_overlay_bg(layer, start_col, start_row, popup_width, header_bg);
+        self.push_overlay_text(layer, &format!(" {}", header), start_col, start_row, popup_width, self.palette.accent.to_f32_array());
+        for (idx, line) in body.iter().enumerate() {
+            let row = start_row + idx + 1;
+            Self::push_overlay_bg(layer, start_col, row, popup_width, bg_color);
+            self.push_overlay_text(layer, &format!(" {}", line), start_col, row, popup_width, text_color);
+        }
+    }
+
+    /// コマンドの終了バッジ（成功なら緑の ✓、失敗なら赤の ✗）
+    fn done_badge(succeeded: bool) -> (&'static str, [f32; 4]) {
+        if succeeded {
+            ("✓", Color::rgb(90, 200, 120).to_f32_array())
+        } else {
+            ("✗", Color::rgb(230, 80, 80).to_f32_array())
+        }
+    }
+
+    /// ペインのタイトルバーを描画（各ペインの最上段）
+    /// 左に名前、右端に作業ディレクトリ（入りきらなければ省略）
+    fn render_title_bars(&mut self, layer: &mut Layer, bars: &[TitleBar]) {
+        let focused_bg = Color::rgb(40, 50, 65).to_f32_array();
+        let unfocused_bg = Color::rgb(28, 34, 44).to_f32_array();
+        let dim = [0.6, 0.6, 0.6, 1.0];
+
+        for bar in bars {
+            let rect = &bar.rect;
+            let start_col = (rect.x * self.width as f32 / self.cell_width) as usize;
+            let end_col = ((rect.x + rect.width) * self.width as f32 / self.cell_width) as usize;
+            let row = (rect.y * self.height as f32 / self.cell_height).round() as usize;
+            let width = end_col.saturating_sub(start_col);
+            if width == 0 {
+                continue;
+            }
+
+            let (bg, fg) = if bar.focused {
+                (focused_bg, self.palette.accent.to_f32_array())
+            } else {
+                (unfocused_bg, dim)
+            };
+            Self::push_overlay_bg(layer, start_col, row, width, bg);
+
+            // 見ていない間に終了したコマンドのバッジ（名前の前、終了コード付き）
+            let mut name_col = start_col;
+            if let Some(done) = &bar.done {
+                let (badge, color) = Self::done_badge(done.succeeded());
+                let badge = match done.exit_code {
+                    Some(code) if code != 0 => format!(" {}{}", badge, code),
+                    _ => format!(" {}", badge),
+                };
+                self.push_overlay_text(layer, &badge, name_col, row, width, color);
+                name_col += badge.chars().count();
+            }
+
+            let name = format!(" {}", bar.title.name);
+            // 監視・録画中の印は作業ディレクトリの前
+            let labels: Vec<&str> = bar.monitor.into_iter().chain(bar.recording.then_some("[rec]")).collect();
+            let cwd = if labels.is_empty() {
+                format!("{} ", bar.title.cwd)
+            } else {
+                format!("{} {} ", labels.join(" "), bar.title.cwd)
+            };
+            let (name_len, cwd_len) = (name_col - start_col + name.chars().count(), cwd.chars().count());
+            self.push_overlay_text(layer, &name, name_col, row, (start_col + width).saturating_sub(name_col), fg);
+            if name_len + 1 + cwd_len <= width {
+                self.push_overlay_text(layer, &cwd, start_col + width - cwd_len, row, cwd_len, dim);
             }
         }
+    }
+
+    /// タブバーを描画（最上段）
+    fn render_tab_bar(&mut self, layer: &mut Layer, tabs: &[TabLabel], screen_cols: usize) {
+        let bar_bg = Color::rgb(20, 24, 32).to_f32_array();
+        let active_bg = Color::rgb(40, 50, 65).to_f32_array();
+        let inactive_bg = Color::rgb(28, 34, 44).to_f32_array();
+        let inactive_fg = [0.6, 0.6, 0.6, 1.0];
+        let bell_color = Color::rgb(240, 180, 60).to_f32_array();
+
+        Self::push_overlay_bg(layer, 0, 0, screen_cols, bar_bg);
+
+        for (index, (span, label)) in tab::layout_tabs(tabs.len(), screen_cols).into_iter().zip(tabs).enumerate() {
+            let (bg, fg) = if label.active {
+                (active_bg, self.palette.accent.to_f32_array())
+            } else {
+                (inactive_bg, inactive_fg)
+            };
+            // 右端の1列は区切りとして残す
+            Self::push_overlay_bg(layer, span.start, 0, span.width - 1, bg);
+
+            let text = tab::label_text(index, &label.title, span.text_width());
+            self.push_overlay_text(layer, &text, span.start, 0, span.text_width(), fg);
+
+            // 非表示中のタブのバッジ（ベル > コマンドの終了 > 出力）
+            if label.bell {
+                self.push_overlay_text(layer, "!", span.badge_col(), 0, 1, bell_color);
+            } else if let Some(succeeded) = label.done {
+                let (badge, color) = Self::done_badge(succeeded);
+                self.push_overlay_text(layer, badge, span.badge_col(), 0, 1, color);
+            } else if label.activity {
+                self.push_overlay_text(layer, "●", span.badge_col(), 0, 1, self.palette.accent.to_f32_array());
+            }
+            self.push_overlay_text(layer, "×", span.close_col(), 0, 1, inactive_fg);
+        }
+    }
+
+    /// ステータスバーを描画（最下段、左寄せと右寄せのセグメント）
+    fn render_status_bar(&mut self, layer: &mut Layer, line: &StatusLine, screen_cols: usize, screen_rows: usize) {
+        let bar_bg = Color::rgb(20, 24, 32).to_f32_array();
+        let dim = [0.6, 0.6, 0.6, 1.0];
+        let row = screen_rows.saturating_sub(1);
+
+        Self::push_overlay_bg(layer, 0, row, screen_cols, bar_bg);
+
+        let left = format!(" {}", line.left.join("  "));
+        let right = format!("{} ", line.right.join("  "));
+        let right_len = right.chars().count();
+        // 右寄せの分を残して左寄せを描く（重なるときは右寄せを優先）
+        let left_cols = screen_cols.saturating_sub(right_len + 1);
+        self.push_overlay_text(layer, &left, 0, row, left_cols, self.palette.accent.to_f32_array());
+        if right_len <= screen_cols {
+            self.push_overlay_text(layer, &right, screen_cols - right_len, row, right_len, dim);
+        }
+    }
+
+    /// パフォーマンスHUDを描画（右上）
+    fn render_perf_hud(&mut self, layer: &mut Layer, lines: &[String], screen_cols: usize) {
+        let width = lines
+            .iter()
+            .map(|l| l.chars().count() + 1)
+            .max()
+            .unwrap_or(0)
+            .min(screen_cols);
+        let start_col = screen_cols - width;
+
+        let bg_color = Color::rgb(25, 30, 40).to_f32_array();
+        let header_bg = Color::rgb(40, 50, 65).to_f32_array();
+        let text_color = [0.85, 0.85, 0.85, 1.0];
+
+        for (row, line) in lines.iter().enumerate() {
+            // 1行目は見出し
+            let (bg, fg) = if row == 0 {
+                (header_bg, self.palette.accent.to_f32_array())
+            } else {
+                (bg_color, text_color)
+            };
+            Self::push_overlay_bg(layer, start_col, row, width, bg);
+            self.push_overlay_text(layer, line, start_col, row, width, fg);
+        }
+    }
+
+    /// サイズを変更
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        if let Some(target) = &mut self.target {
+            target.config.width = width;
+            target.config.height = height;
+            target.surface.configure(&self.context.device, &target.config);
+        }
+
+        // ユニフォームを更新
+        let uniforms = Uniforms {
+            screen_size: [width as f32, height as f32],
+            cell_size: [self.cell_width, self.cell_height],
+        };
+        self.context
+            .queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+    }
+
+    /// セルの色（プロファイルのテーマのないペイン）
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    /// セルの色を変える
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    /// カーソルの描き方を変える
+    pub fn set_cursor_style(&mut self, style: CursorStyle) {
+        self.cursor_style = style;
+    }
+
+    /// ユニフォームとアトラスのバインドグループを作成
+    fn create_bind_group(context: &RenderContext, uniform_buffer: &wgpu::Buffer, font: &FontAtlas) -> wgpu::BindGroup {
+        context.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Bind Group"),
+            layout: &context.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&font.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&context.sampler),
+                },
+            ],
+        })
+    }
 
-        // カーソルを追加
-        if terminal.cursor.visible {
-            let cursor_char = match terminal.cursor.shape {
-                CursorShape::Block => '█',
-                CursorShape::Underline => '_',
-                CursorShape::Beam => '│',
-            };
+    /// このウィンドウで使うフォント
+    fn font(&self) -> &FontAtlas {
+        self.font.as_deref().unwrap_or(&self.context.font)
+    }
 
-            self.ensure_fallback_font(cursor_char);
-            if let Some(glyph) = self.glyph_atlas.get_or_insert(
-                cursor_char,
-                &self.font,
-                self.fallback_font.as_ref(),
-                self.font_size,
-            ) {
-                instances.push(CellInstance {
-                    position: [terminal.cursor.col as f32, terminal.cursor.row as f32],
-                    fg_color: Color::EMERALD.to_f32_array(),
-                    bg_color: [0.0, 0.0, 0.0, 0.0],
-                    uv_offset: glyph.uv_offset,
-                    uv_size: glyph.uv_size,
-                    glyph_offset: glyph.offset,
-                    glyph_size: glyph.size,
-                });
-            }
+    /// このウィンドウだけフォントの大きさを変える（None で設定ファイルの大きさ）
+    ///
+    /// 戻り値: セルサイズが変わったか（変わったらペインの大きさを合わせ直す）
+    pub fn set_font_size(&mut self, size: Option<f32>) -> Result<bool> {
+        self.font_size = size;
+        // 拡大率を掛けた大きさでラスタライズする（拡大して描かないのでグリフがにじまない）
+        let size = match size {
+            None if self.scale == 1.0 => None,
+            size => Some(size.unwrap_or_else(|| font_setting().1) * self.scale),
+        };
+        let font = size.map(|size| self.context.sized_font(size)).transpose()?;
+        let unchanged = match (&font, &self.font) {
+            (Some(new), Some(old)) => Arc::ptr_eq(new, old),
+            (None, None) => true,
+            _ => false,
+        };
+        if !unchanged {
+            self.font = font;
+            self.bind_group = Self::create_bind_group(&self.context, &self.uniform_buffer, self.font());
         }
-
-        (instances, bg_instances)
+        Ok(self.sync_cell_size())
     }
 
-    /// サイズを変更
-    pub fn resize(&mut self, width: u32, height: u32) {
-        self.width = width;
-        self.height = height;
-        self.surface_config.width = width;
-        self.surface_config.height = height;
-        self.surface.configure(&self.device, &self.surface_config);
+    /// ウィンドウの拡大率を変える（フォントをその大きさで読み込み直す）
+    ///
+    /// 戻り値: セルサイズが変わったか（変わったらペインの大きさを合わせ直す）
+    pub fn set_scale(&mut self, scale: f32) -> Result<bool> {
+        self.scale = scale;
+        self.set_font_size(self.font_size)
+    }
 
-        // ユニフォームを更新
-        let uniforms = Uniforms {
-            screen_size: [width as f32, height as f32],
-            cell_size: [self.cell_width, self.cell_height],
+    /// フォントを読み込み直したあと、セルサイズをフォントに合わせる
+    ///
+    /// 戻り値: セルサイズが変わったか（変わったらペインの大きさを合わせ直す）
+    pub fn sync_cell_size(&mut self) -> bool {
+        let (cell_width, cell_height) = {
+            let glyphs = self.font().glyphs.lock();
+            (glyphs.cell_width, glyphs.cell_height)
         };
-        self.queue
-            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+        if (cell_width, cell_height) == (self.cell_width, self.cell_height) {
+            return false;
+        }
+        self.cell_width = cell_width;
+        self.cell_height = cell_height;
+        self.resize(self.width, self.height);
+        true
     }
 
     /// ターミナルサイズを計算
@@ -983,229 +1943,374 @@ impl Renderer {
         (self.width, self.height)
     }
 
-    /// 複数のペインを描画
-    pub fn render_panes(&mut self, panes: &[(&crate::terminal::Terminal, crate::pane::Rect, bool)]) -> Result<(), wgpu::SurfaceError> {
-        self.render_panes_with_explorer(panes, None)
+    /// 直前のフレームの統計を取得
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
     }
 
-    pub fn render_panes_with_explorer(
+    /// 複数のペインをウィンドウのサーフェスに描画
+    pub fn render_panes(
         &mut self,
-        panes: &[(&crate::terminal::Terminal, crate::pane::Rect, bool)],
-        explorer: Option<&Explorer>,
+        panes: &[(&TerminalSnapshot, crate::pane::Rect, bool)],
+        overlays: &Overlays,
     ) -> Result<(), wgpu::SurfaceError> {
-        let mut all_instances = Vec::new();
-        let mut all_bg_instances = Vec::new();
-        let mut border_instances = Vec::new();
+        // ヘッドレスの場合は描画先がない
+        let output = match &self.target {
+            Some(target) => {
+                let _span = tracing::trace_span!("acquire").entered();
+                target.surface.get_current_texture()?
+            }
+            None => return Ok(()),
+        };
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
 
-        // 各ペインのインスタンスデータを構築
-        for (terminal, rect, is_focused) in panes {
-            let (instances, bg_instances) = self.build_instances_with_viewport(terminal, rect, *is_focused);
-            all_instances.extend(instances);
-            all_bg_instances.extend(bg_instances);
-        }
+        self.render_to_view(&view, panes, overlays);
 
-        // ペイン境界線を別に収集（後で上書き描画するため）
-        if panes.len() > 1 {
-            self.add_pane_borders(panes, &mut border_instances);
-        }
+        let _span = tracing::trace_span!("present").entered();
+        output.present();
+
+        Ok(())
+    }
+
+    /// 複数のペインをウィンドウのサーフェスに描画し、表示するテクスチャのピクセルを読み戻す
+    ///
+    /// 画面に出す絵そのものを保存する（読み戻せないサーフェスとヘッドレスではオフスクリーンに描画する）
+    pub fn capture_presented(
+        &mut self,
+        panes: &[(&TerminalSnapshot, crate::pane::Rect, bool)],
+        overlays: &Overlays,
+    ) -> Result<Screenshot> {
+        let output = match &self.target {
+            Some(target) if target.config.usage.contains(wgpu::TextureUsages::COPY_SRC) => {
+                target.surface.get_current_texture()?
+            }
+            _ => {
+                log::debug!("サーフェスを読み戻せないため、オフスクリーンに描画して保存します");
+                return self.capture_panes(panes, overlays);
+            }
+        };
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        self.render_to_view(&view, panes, overlays);
+        let shot = self.read_texture(&output.texture);
+        output.present();
+        shot
+    }
+
+    /// 複数のペインをオフスクリーンに描画してピクセルを読み戻す
+    pub fn capture_panes(
+        &mut self,
+        panes: &[(&TerminalSnapshot, crate::pane::Rect, bool)],
+        overlays: &Overlays,
+    ) -> Result<Screenshot> {
+        let texture = self.context.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Capture Texture"),
+            size: wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.context.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.render_to_view(&view, panes, overlays);
+        self.read_texture(&texture)
+    }
 
-        // エクスプローラー用の別バッファ（後から別ドローコールで描画）
-        let mut explorer_instances = Vec::new();
-        let mut explorer_bg_instances = Vec::new();
+    /// テクスチャの内容をRGBAピクセルとして読み戻す
+    fn read_texture(&self, texture: &wgpu::Texture) -> Result<Screenshot> {
+        let (width, height) = (texture.width(), texture.height());
+        // 1行のバイト数はCOPY_BYTES_PER_ROW_ALIGNMENTの倍数にする必要がある
+        let unpadded_bytes_per_row = width * 4;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+        let buffer = self.context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Capture Buffer"),
+            size: (padded_bytes_per_row * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
 
-        // エクスプローラーオーバーレイを構築
-        if let Some(exp) = explorer {
-            if exp.visible {
-                // 画面サイズを取得（最初のペインのターミナルから）
-                let (screen_cols, screen_rows) = if let Some((terminal, _, _)) = panes.first() {
-                    let grid = terminal.active_grid();
-                    (grid.cols, grid.rows)
+        let mut encoder = self
+            .context
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Capture Encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.context.queue.submit(std::iter::once(encoder.finish()));
+
+        // マップ完了まで待機
+        let slice = buffer.slice(..);
+        let (tx, rx) = crossbeam_channel::bounded(1);
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        self.context.device
+            .poll(wgpu::PollType::Wait)
+            .map_err(|e| anyhow::anyhow!("GPUの待機に失敗: {}", e))?;
+        rx.recv()
+            .context("バッファのマップ結果を受信できません")?
+            .map_err(|e| anyhow::anyhow!("バッファのマップに失敗: {}", e))?;
+
+        // 行のパディングを除去してRGBA順に揃える
+        let bgra = matches!(
+            self.context.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(padded_bytes_per_row as usize) {
+                let row = &row[..unpadded_bytes_per_row as usize];
+                if bgra {
+                    for px in row.chunks_exact(4) {
+                        pixels.extend_from_slice(&[px[2], px[1], px[0], px[3]]);
+                    }
                 } else {
-                    (80, 24)
-                };
-                let (exp_instances, exp_bg) = self.render_explorer_overlay(exp, screen_cols, screen_rows);
-                explorer_bg_instances = exp_bg;
-                explorer_instances = exp_instances;
+                    pixels.extend_from_slice(row);
+                }
             }
         }
+        buffer.unmap();
+
+        Ok(Screenshot::new(width, height, pixels))
+    }
+
+    /// 任意のテクスチャビューに複数のペインを描画
+    pub fn render_to_view(
+        &mut self,
+        view: &wgpu::TextureView,
+        panes: &[(&TerminalSnapshot, crate::pane::Rect, bool)],
+        overlays: &Overlays,
+    ) {
+        // 前回までのGPU計測結果を回収
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.collect(&self.context.device);
+        }
+
+        let build_span = tracing::trace_span!("build").entered();
+
+        // 作業用バッファは前フレームの容量を残したまま使い回す
+        let mut scratch = std::mem::take(&mut self.scratch);
+        for layer in &mut scratch.layers {
+            layer.clear();
+        }
+
+        // 1. 各ペインのインスタンスデータを構築
+        for (terminal, rect, is_focused) in panes {
+            self.build_instances_with_viewport(
+                &mut scratch.layers[LAYER_TERMINAL],
+                &mut scratch.box_rects,
+                terminal,
+                rect,
+                *is_focused,
+            );
+        }
 
-        // グリフアトラスを更新
-        if self.glyph_atlas.dirty {
-            self.queue.write_texture(
+        // 2. ペイン境界線（ターミナルの上に描画）
+        if panes.len() > 1 {
+            self.add_pane_borders(panes, &mut scratch.layers[LAYER_BORDERS].bg);
+        }
+
+        // 3. オーバーレイ
+        let (screen_cols, screen_rows) = self.calculate_terminal_size();
+        let (screen_cols, screen_rows) = (screen_cols as usize, screen_rows as usize);
+        if let Some(bars) = overlays.title_bars {
+            self.render_title_bars(&mut scratch.layers[LAYER_BORDERS], bars);
+        }
+        if let Some(alerts) = overlays.pane_alerts {
+            self.add_alert_frames(alerts, &mut scratch.layers[LAYER_BORDERS].bg);
+        }
+        if let Some(bars) = overlays.scrollbars {
+            self.add_scrollbars(bars, &mut scratch.layers[LAYER_BORDERS].bg);
+        }
+        if let Some(pills) = overlays.new_lines {
+            self.render_new_lines(&mut scratch.layers[LAYER_BORDERS], pills);
+        }
+        if let Some(cursor) = overlays.copy_cursor {
+            self.render_copy_cursor(&mut scratch.layers[LAYER_BORDERS], cursor);
+        }
+        if let Some(hints) = overlays.hint_labels {
+            self.render_hint_labels(&mut scratch.layers[LAYER_BORDERS], hints);
+        }
+        if let Some(line) = overlays.status_bar {
+            self.render_status_bar(&mut scratch.layers[LAYER_TABS], line, screen_cols, screen_rows);
+        }
+        if let Some(tabs) = overlays.tab_bar {
+            self.render_tab_bar(&mut scratch.layers[LAYER_TABS], tabs, screen_cols);
+        }
+        if let Some(exp) = overlays.explorer.filter(|e| e.visible) {
+            // サイドバーはタブバーとステータスバーの間
+            let reserved = (overlays.tab_bar.is_some() as usize, overlays.status_bar.is_some() as usize);
+            self.render_explorer_overlay(&mut scratch.layers[LAYER_EXPLORER], exp, screen_cols, screen_rows, reserved);
+        }
+        if let Some(finder) = overlays.finder.filter(|f| f.visible) {
+            self.render_finder(&mut scratch.layers[LAYER_EXPLORER], finder, screen_cols, screen_rows);
+        }
+        if let Some(lines) = overlays.perf_hud {
+            self.render_perf_hud(&mut scratch.layers[LAYER_HUD], lines, screen_cols);
+        }
+        if let Some(lines) = overlays.key_hints {
+            let reserved = overlays.status_bar.is_some() as usize;
+            self.render_key_hints(&mut scratch.layers[LAYER_HUD], lines, screen_cols, screen_rows, reserved);
+        }
+        if let Some(lines) = overlays.dialog {
+            self.render_dialog(&mut scratch.layers[LAYER_HUD], lines, screen_cols, screen_rows);
+        }
+        drop(build_span);
+
+        let upload_span = tracing::trace_span!("upload").entered();
+        // グリフアトラスを更新（共有テクスチャなので同じフォントの全ウィンドウに反映される）
+        let font = self.font();
+        let mut glyphs = font.glyphs.lock();
+        if glyphs.atlas.dirty {
+            self.context.queue.write_texture(
                 wgpu::TexelCopyTextureInfo {
-                    texture: &self.atlas_texture,
+                    texture: &font.texture,
                     mip_level: 0,
                     origin: wgpu::Origin3d::ZERO,
                     aspect: wgpu::TextureAspect::All,
                 },
-                &self.glyph_atlas.pixels,
+                &glyphs.atlas.pixels,
                 wgpu::TexelCopyBufferLayout {
                     offset: 0,
-                    bytes_per_row: Some(self.glyph_atlas.width),
-                    rows_per_image: Some(self.glyph_atlas.height),
+                    bytes_per_row: Some(glyphs.atlas.width),
+                    rows_per_image: Some(glyphs.atlas.height),
                 },
                 wgpu::Extent3d {
-                    width: self.glyph_atlas.width,
-                    height: self.glyph_atlas.height,
+                    width: glyphs.atlas.width,
+                    height: glyphs.atlas.height,
                     depth_or_array_layers: 1,
                 },
             );
-            self.glyph_atlas.dirty = false;
+            glyphs.atlas.dirty = false;
         }
-
-        // インスタンスバッファを更新（オーバーフロー防止）
-        let all_instances = if all_instances.len() > MAX_INSTANCES {
-            &all_instances[..MAX_INSTANCES]
-        } else {
-            &all_instances[..]
-        };
-        let all_bg_instances = if all_bg_instances.len() > MAX_INSTANCES {
-            &all_bg_instances[..MAX_INSTANCES]
-        } else {
-            &all_bg_instances[..]
-        };
-        self.queue
-            .write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(all_instances));
-        self.queue
-            .write_buffer(&self.bg_instance_buffer, 0, bytemuck::cast_slice(all_bg_instances));
-
-        // 描画
-        let output = self.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let (atlas_usage, glyph_count) = (glyphs.atlas.usage(), glyphs.atlas.glyphs.len());
+        drop(glyphs);
 
         let mut encoder = self
+            .context
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
 
+        // レイヤーを1つのバッファに詰めて、マップ済みのステージングバッファに直接書き込む
+        let device = &self.context.device;
+        let bg_ranges = pack_instances(
+            &mut self.staging_belt,
+            &mut encoder,
+            device,
+            &self.bg_instance_buffer,
+            scratch.layers.iter().map(|l| l.bg.as_slice()),
+        );
+        let text_ranges = pack_instances(
+            &mut self.staging_belt,
+            &mut encoder,
+            device,
+            &self.instance_buffer,
+            scratch.layers.iter().map(|l| l.text.as_slice()),
+        );
+        self.staging_belt.finish();
+        self.scratch = scratch;
+
+        let drawn = |ranges: &[Range<u32>]| ranges.iter().map(|r| r.len()).sum::<usize>();
+        self.frame_stats = FrameStats {
+            instances: drawn(&bg_ranges) + drawn(&text_ranges),
+            max_instances: MAX_INSTANCES * 2,
+            atlas_usage,
+            glyphs: glyph_count,
+        };
+        drop(upload_span);
+
+        let _draw_span = tracing::trace_span!("draw").entered();
+
+        // 描画（セルのない余白は背景色で塗る）
+        let [r, g, b, _] = self.palette.background.to_f32_array().map(f64::from);
+        let clear_color = wgpu::Color { r, g, b, a: 1.0 };
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 1.0,
-                        }),
+                        load: wgpu::LoadOp::Clear(clear_color),
                         store: wgpu::StoreOp::Store,
                     },
                 })],
                 depth_stencil_attachment: None,
-                timestamp_writes: None,
+                timestamp_writes: self.gpu_timer.as_ref().map(|t| t.timestamp_writes()),
                 occlusion_query_set: None,
             });
 
-            // 1. ターミナル背景を描画
-            render_pass.set_pipeline(&self.bg_pipeline);
             render_pass.set_bind_group(0, &self.bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.bg_instance_buffer.slice(..));
-            render_pass.draw(0..4, 0..all_bg_instances.len() as u32);
-
-            // 2. ターミナルテキストを描画
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
-            render_pass.draw(0..4, 0..all_instances.len() as u32);
-        }
 
-        // 3. ペイン境界線を別パスで上に描画
-        if !border_instances.is_empty() {
-            let borders = if border_instances.len() > MAX_INSTANCES {
-                &border_instances[..MAX_INSTANCES]
-            } else {
-                &border_instances[..]
-            };
-            self.queue
-                .write_buffer(&self.bg_instance_buffer, 0, bytemuck::cast_slice(borders));
-
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Border Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-
-            render_pass.set_pipeline(&self.bg_pipeline);
-            render_pass.set_bind_group(0, &self.bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.bg_instance_buffer.slice(..));
-            render_pass.draw(0..4, 0..borders.len() as u32);
+            // レイヤーごとに背景→テキストの順で描画
+            for (bg_range, text_range) in bg_ranges.into_iter().zip(text_ranges) {
+                if !bg_range.is_empty() {
+                    render_pass.set_pipeline(&self.context.bg_pipeline);
+                    render_pass.set_vertex_buffer(0, self.bg_instance_buffer.slice(..));
+                    render_pass.draw(0..4, bg_range);
+                }
+                if !text_range.is_empty() {
+                    render_pass.set_pipeline(&self.context.render_pipeline);
+                    render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+                    render_pass.draw(0..4, text_range);
+                }
+            }
         }
 
-        // 4. エクスプローラーを別のドローコールで上に描画
-        if !explorer_bg_instances.is_empty() {
-            // エクスプローラー用のバッファを更新
-            let explorer_bg = if explorer_bg_instances.len() > MAX_INSTANCES {
-                &explorer_bg_instances[..MAX_INSTANCES]
-            } else {
-                &explorer_bg_instances[..]
-            };
-            let explorer_text = if explorer_instances.len() > MAX_INSTANCES {
-                &explorer_instances[..MAX_INSTANCES]
-            } else {
-                &explorer_instances[..]
-            };
-            self.queue
-                .write_buffer(&self.bg_instance_buffer, 0, bytemuck::cast_slice(explorer_bg));
-            self.queue
-                .write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(explorer_text));
-
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Explorer Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load,  // 既存の描画を保持
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-
-            // エクスプローラー背景
-            render_pass.set_pipeline(&self.bg_pipeline);
-            render_pass.set_bind_group(0, &self.bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.bg_instance_buffer.slice(..));
-            render_pass.draw(0..4, 0..explorer_bg.len() as u32);
-
-            // エクスプローラーテキスト
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
-            render_pass.draw(0..4, 0..explorer_text.len() as u32);
+        let timer_copied = self.gpu_timer.as_ref().is_some_and(|t| t.resolve(&mut encoder));
+        self.context.queue.submit(std::iter::once(encoder.finish()));
+        self.staging_belt.recall();
+        if timer_copied {
+            if let Some(timer) = &mut self.gpu_timer {
+                timer.map();
+            }
         }
-
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
-
-        Ok(())
     }
 
     /// ビューポート付きでインスタンスデータを構築
     fn build_instances_with_viewport(
         &mut self,
-        terminal: &Terminal,
+        layer: &mut Layer,
+        box_rects: &mut Vec<BoxRect>,
+        terminal: &TerminalSnapshot,
         viewport: &crate::pane::Rect,
         is_focused: bool,
-    ) -> (Vec<CellInstance>, Vec<CellInstance>) {
-        let grid = terminal.active_grid();
-        let mut instances = Vec::with_capacity(grid.cols * grid.rows);
-        let mut bg_instances = Vec::with_capacity(grid.cols * grid.rows);
+    ) {
+        let Layer { text: instances, bg: bg_instances } = layer;
 
         // ビューポートのピクセル座標を計算
         let vp_x = viewport.x * self.width as f32;
@@ -1215,13 +2320,28 @@ impl Renderer {
         let col_offset = vp_x / self.cell_width;
         let row_offset = vp_y / self.cell_height;
 
-        // 選択ハイライト色（明るい水色背景）
-        let selection_bg = [0.2, 0.5, 0.7, 1.0]; // 選択範囲の背景色
-        let selection_fg = [1.0, 1.0, 1.0, 1.0]; // 選択範囲の前景色
+        // プロファイルのテーマで開いたペインはそのパレット
+        let palette = terminal.palette.as_deref().unwrap_or(&self.palette).clone();
+
+        // 選択ハイライト色（既定は明るい水色背景）
+        let selection_bg = palette.selection_bg.to_f32_array(); // 選択範囲の背景色
+        let selection_fg = palette.selection_fg.to_f32_array(); // 選択範囲の前景色
+
+        // グリフキャッシュは他のウィンドウと共有（フレーム中はロックを保持）
+        let context = Arc::clone(&self.context);
+        let font = self.font.clone();
+        let mut glyphs = font.as_deref().unwrap_or(&context.font).glyphs.lock();
 
-        for row in 0..grid.rows {
-            for col in 0..grid.cols {
-                let cell = &grid[(col, row)];
+        // カーソル（形はアプリケーションの指定がなければ設定の形、フォーカスのないペインは枠だけ）
+        let cursor = &terminal.cursor;
+        let cursor_color = palette.cursor.to_f32_array();
+        let show_cursor = cursor.visible && cursor.col < terminal.cols && cursor.row < terminal.rows;
+        let cursor_shape = (show_cursor && is_focused).then(|| cursor.shape.unwrap_or(self.cursor_style.shape));
+        let hollow_cursor = show_cursor && !is_focused && self.cursor_style.unfocused == UnfocusedCursor::Hollow;
+
+        for row in 0..terminal.rows {
+            for col in 0..terminal.cols {
+                let cell = &terminal[(col, row)];
                 let is_selected = terminal.selection.contains(col, row);
 
                 let position = [col as f32 + col_offset, row as f32 + row_offset];
@@ -1230,7 +2350,16 @@ impl Renderer {
                 let (fg, bg) = if is_selected {
                     (selection_fg, selection_bg)
                 } else {
-                    (cell.fg.to_f32_array(), cell.bg.to_f32_array())
+                    (
+                        palette.resolve(cell.fg).to_f32_array(),
+                        palette.resolve(cell.bg).to_f32_array(),
+                    )
+                };
+                // ブロックのカーソルの下の文字は背景色で描く
+                let (fg, bg) = if cursor_shape == Some(CursorShape::Block) && (col, row) == (cursor.col, cursor.row) {
+                    (bg, cursor_color)
+                } else {
+                    (fg, bg)
                 };
 
                 // 背景インスタンス
@@ -1244,16 +2373,26 @@ impl Renderer {
                     glyph_size: [0.0, 0.0],
                 });
 
-                // 空白以外はグリフを描画
-                if cell.character != ' ' {
+                // 罫線・ブロック要素はセルぴったりの矩形で描画（隣のセルと隙間なくつながる）
+                box_rects.clear();
+                if boxdraw::draw(cell.character, self.cell_width, self.cell_height, box_rects) {
+                    for rect in box_rects.iter() {
+                        // 網掛けは背景色と混ぜる（背景パスはブレンドしない）
+                        let color = std::array::from_fn(|i| bg[i] + (fg[i] - bg[i]) * rect.alpha);
+                        bg_instances.push(CellInstance {
+                            position,
+                            fg_color: fg,
+                            bg_color: color,
+                            uv_offset: [0.0, 0.0],
+                            uv_size: [0.0, 0.0],
+                            glyph_offset: [rect.x, rect.y],
+                            glyph_size: [rect.width, rect.height],
+                        });
+                    }
+                } else if cell.character != ' ' {
+                    // 空白以外はグリフを描画
                     // 必要に応じて日本語フォントを遅延読み込み
-                    self.ensure_fallback_font(cell.character);
-                    if let Some(glyph) = self.glyph_atlas.get_or_insert(
-                        cell.character,
-                        &self.font,
-                        self.fallback_font.as_ref(),
-                        self.font_size,
-                    ) {
+                    if let Some(glyph) = glyphs.glyph(cell.character) {
                         instances.push(CellInstance {
                             position,
                             fg_color: fg,
@@ -1268,43 +2407,93 @@ impl Renderer {
             }
         }
 
-        // カーソルを追加（フォーカスがあるペインのみ）
-        if is_focused && terminal.cursor.visible {
-            let cursor_char = match terminal.cursor.shape {
-                CursorShape::Block => '█',
-                CursorShape::Underline => '_',
-                CursorShape::Beam => '│',
-            };
+        // ビーム・下線・枠のカーソルはセルの中の矩形で描く（ブロックは上で色を入れ替えた）
+        let (w, h) = (self.cell_width, self.cell_height);
+        let t = (w * self.cursor_style.thickness).round().max(1.0);
+        let rects: &[[f32; 4]] = match cursor_shape {
+            Some(CursorShape::Beam) => &[[0.0, 0.0, t, h]],
+            Some(CursorShape::Underline) => &[[0.0, h - t, w, t]],
+            _ if hollow_cursor => &[[0.0, 0.0, w, t], [0.0, h - t, w, t], [0.0, 0.0, t, h], [w - t, 0.0, t, h]],
+            _ => &[],
+        };
+        for &[x, y, width, height] in rects {
+            bg_instances.push(CellInstance {
+                position: [cursor.col as f32 + col_offset, cursor.row as f32 + row_offset],
+                fg_color: cursor_color,
+                bg_color: cursor_color,
+                uv_offset: [0.0, 0.0],
+                uv_size: [0.0, 0.0],
+                glyph_offset: [x, y],
+                glyph_size: [width, height],
+            });
+        }
+    }
 
-            self.ensure_fallback_font(cursor_char);
-            if let Some(glyph) = self.glyph_atlas.get_or_insert(
-                cursor_char,
-                &self.font,
-                self.fallback_font.as_ref(),
-                self.font_size,
-            ) {
-                instances.push(CellInstance {
-                    position: [
-                        terminal.cursor.col as f32 + col_offset,
-                        terminal.cursor.row as f32 + row_offset,
-                    ],
-                    fg_color: Color::EMERALD.to_f32_array(),
-                    bg_color: [0.0, 0.0, 0.0, 0.0],
-                    uv_offset: glyph.uv_offset,
-                    uv_size: glyph.uv_size,
-                    glyph_offset: glyph.offset,
-                    glyph_size: glyph.size,
+    /// 監視の警告が出ているペインを枠で囲む（出力は黄色、無音は紫）
+    fn add_alert_frames(&self, alerts: &[(crate::pane::Rect, MonitorKind)], bg_instances: &mut Vec<CellInstance>) {
+        let (width, height) = (self.width as f32, self.height as f32);
+        for (rect, kind) in alerts {
+            let color = match kind {
+                MonitorKind::Activity => Color::rgb(240, 180, 60),
+                MonitorKind::Silence => Color::rgb(170, 120, 240),
+            }
+            .to_f32_array();
+            let (x, y) = (rect.x * width, rect.y * height);
+            let (w, h) = (rect.width * width, rect.height * height);
+            let t = ALERT_FRAME_WIDTH.min(w / 2.0).min(h / 2.0);
+
+            // 上・下・左・右の4本（セル単位ではなくピクセル単位の矩形）
+            for (offset, size) in [
+                ([x, y], [w, t]),
+                ([x, y + h - t], [w, t]),
+                ([x, y], [t, h]),
+                ([x + w - t, y], [t, h]),
+            ] {
+                bg_instances.push(CellInstance {
+                    position: [0.0, 0.0],
+                    fg_color: color,
+                    bg_color: color,
+                    uv_offset: [0.0, 0.0],
+                    uv_size: [0.0, 0.0],
+                    glyph_offset: offset,
+                    glyph_size: size,
                 });
             }
         }
+    }
 
-        (instances, bg_instances)
+    /// ペインの右端にスクロールバーのつまみを追加（ピクセル単位の矩形）
+    fn add_scrollbars(&self, bars: &[Scrollbar], bg_instances: &mut Vec<CellInstance>) {
+        let (width, height) = (self.width as f32, self.height as f32);
+        for bar in bars {
+            let color = if bar.dragging {
+                Color::rgb(80, 220, 200)
+            } else {
+                Color::rgb(110, 120, 135)
+            }
+            .to_f32_array();
+            let pane_top = bar.rect.y * height;
+            let pane_height = bar.rect.height * height;
+            let x = (bar.rect.x + bar.rect.width) * width - scrollbar::WIDTH - 2.0;
+            // 短すぎるつまみは最小の高さにして、ペインからはみ出さないようにする
+            let h = (bar.height * pane_height).max(scrollbar::MIN_THUMB_HEIGHT).min(pane_height);
+            let y = (pane_top + bar.top * pane_height).min(pane_top + pane_height - h);
+            bg_instances.push(CellInstance {
+                position: [0.0, 0.0],
+                fg_color: color,
+                bg_color: color,
+                uv_offset: [0.0, 0.0],
+                uv_size: [0.0, 0.0],
+                glyph_offset: [x, y],
+                glyph_size: [scrollbar::WIDTH, h],
+            });
+        }
     }
 
     /// ペイン境界線を追加
     fn add_pane_borders(
         &self,
-        panes: &[(&crate::terminal::Terminal, crate::pane::Rect, bool)],
+        panes: &[(&TerminalSnapshot, crate::pane::Rect, bool)],
         bg_instances: &mut Vec<CellInstance>,
     ) {
         let border_color = Color::rgb(80, 220, 200).to_f32_array(); // 明るい水色
@@ -1350,3 +2539,361 @@ impl Renderer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pane::{PaneTitle, Rect};
+    use crate::parser::AnsiParser;
+    use crate::terminal::Terminal;
+
+    /// ヘッドレス描画のレンダラー（GPUアダプターがない環境ではスキップの理由を出して None）
+    ///
+    /// GPU のない CI では何も確かめずに通ってしまうため、これを使うテストは `#[ignore]` にして
+    /// GPU のある環境で `cargo test -- --ignored` で走らせる
+    fn headless(width: u32, height: u32) -> Option<Renderer> {
+        pollster::block_on(Renderer::new_headless(width, height))
+            .inspect_err(|e| eprintln!("ヘッドレス描画をスキップ: {}", e))
+            .ok()
+    }
+
+    #[test]
+    #[ignore = "GPU アダプターが必要（cargo test -- --ignored）"]
+    fn test_headless_capture() {
+        let Some(mut renderer) = headless(320, 120) else { return };
+
+        let mut terminal = Terminal::new(10, 4);
+        AnsiParser::new().process(&mut terminal, b"\x1b[41mX");
+        let mut snapshot = TerminalSnapshot::new();
+        snapshot.update(&mut terminal);
+
+        let shot = renderer
+            .capture_panes(&[(&snapshot, Rect::full(), false)], &Overlays::default())
+            .unwrap();
+        assert_eq!((shot.width, shot.height), (320, 120));
+
+        // 先頭セルの背景は赤で塗られる
+        let (cell_width, cell_height) = renderer.cell_size();
+        let [r, g, b, _] = shot.pixel(1, (cell_height / 2.0) as u32);
+        assert!(r > 200 && g < 50 && b < 50, "unexpected pixel {:?}", (r, g, b));
+        assert!(cell_width > 0.0);
+    }
+
+    #[test]
+    #[ignore = "GPU アダプターが必要（cargo test -- --ignored）"]
+    fn test_overlay_keeps_terminal_layer() {
+        let Some(mut renderer) = headless(640, 240) else { return };
+
+        let mut terminal = Terminal::new(10, 4);
+        AnsiParser::new().process(&mut terminal, b"\x1b[41mX");
+        let mut snapshot = TerminalSnapshot::new();
+        snapshot.update(&mut terminal);
+
+        // HUDを重ねても左上のペイン背景は残る
+        let hud = vec![" PERF".to_string(), " fps 60".to_string()];
+        let overlays = Overlays {
+            perf_hud: Some(&hud),
+            ..Default::default()
+        };
+        let shot = renderer
+            .capture_panes(&[(&snapshot, Rect::full(), false)], &overlays)
+            .unwrap();
+
+        let (_, cell_height) = renderer.cell_size();
+        let [r, g, b, _] = shot.pixel(1, (cell_height / 2.0) as u32);
+        assert!(r > 200 && g < 50 && b < 50, "unexpected pixel {:?}", (r, g, b));
+
+        // HUDは右上（最後の列）に描画される
+        let (cols, _) = renderer.calculate_terminal_size();
+        let (cell_width, _) = renderer.cell_size();
+        let x = (cols as f32 * cell_width) as u32 - 2;
+        let [r, g, b, _] = shot.pixel(x, (cell_height / 2.0) as u32);
+        assert!(b > r && b > 0, "unexpected pixel {:?}", (r, g, b));
+
+        let stats = renderer.frame_stats();
+        assert!(stats.instances > 0 && stats.glyphs > 0);
+    }
+
+    #[test]
+    #[ignore = "GPU アダプターが必要（cargo test -- --ignored）"]
+    fn test_scratch_buffers_reset_between_frames() {
+        let Some(mut renderer) = headless(640, 240) else { return };
+
+        let mut terminal = Terminal::new(10, 4);
+        let mut snapshot = TerminalSnapshot::new();
+        snapshot.update(&mut terminal);
+        let panes = [(&snapshot, Rect::full(), false)];
+        let hud = vec![" PERF".to_string()];
+        let with_hud = Overlays {
+            perf_hud: Some(&hud),
+            ..Default::default()
+        };
+
+        let (cols, _) = renderer.calculate_terminal_size();
+        let (cell_width, cell_height) = renderer.cell_size();
+        let x = (cols as f32 * cell_width) as u32 - 2;
+        let y = (cell_height / 2.0) as u32;
+
+        let first = renderer.capture_panes(&panes, &with_hud).unwrap();
+        let instances = renderer.frame_stats().instances;
+        assert!(first.pixel(x, y)[2] > 0);
+
+        // 前のフレームのオーバーレイは残らない
+        let second = renderer.capture_panes(&panes, &Overlays::default()).unwrap();
+        assert_eq!(second.pixel(x, y)[..3], [0, 0, 0]);
+        assert!(renderer.frame_stats().instances < instances);
+        assert!(renderer.scratch.layers[LAYER_HUD].bg.capacity() > 0);
+    }
+
+    #[test]
+    #[ignore = "GPU アダプターが必要（cargo test -- --ignored）"]
+    fn test_tab_bar() {
+        let Some(mut renderer) = headless(640, 240) else { return };
+
+        let mut terminal = Terminal::new(10, 4);
+        let mut snapshot = TerminalSnapshot::new();
+        snapshot.update(&mut terminal);
+        let panes = [(&snapshot, Rect::full(), false)];
+        let labels = vec![
+            TabLabel { title: "zsh".into(), active: true, activity: false, bell: false, done: None },
+            TabLabel { title: "vim".into(), active: false, activity: true, bell: false, done: None },
+        ];
+        let overlays = Overlays {
+            tab_bar: Some(&labels),
+            ..Default::default()
+        };
+        let shot = renderer.capture_panes(&panes, &overlays).unwrap();
+
+        // 最上段はタブの背景で塗られ、表示中のタブは他より明るい
+        let (cols, _) = renderer.calculate_terminal_size();
+        let (cell_width, cell_height) = renderer.cell_size();
+        let spans = tab::layout_tabs(labels.len(), cols as usize);
+        let y = 1;
+        let active = shot.pixel((spans[0].start as f32 * cell_width) as u32 + 1, y);
+        let inactive = shot.pixel((spans[1].start as f32 * cell_width) as u32 + 1, y);
+        assert!(active[2] > inactive[2] && inactive[2] > 0, "{:?} {:?}", active, inactive);
+
+        // 2行目以降は重ならない
+        assert_eq!(shot.pixel(1, (cell_height * 1.5) as u32)[..3], [0, 0, 0]);
+    }
+
+    #[test]
+    #[ignore = "GPU アダプターが必要（cargo test -- --ignored）"]
+    fn test_title_bars() {
+        let Some(mut renderer) = headless(640, 240) else { return };
+
+        let mut terminal = Terminal::new(10, 4);
+        let mut snapshot = TerminalSnapshot::new();
+        snapshot.update(&mut terminal);
+        let full = Rect::full();
+        let (left, right) = (full.left_half(), full.right_half());
+        let panes = [(&snapshot, left, true), (&snapshot, right, false)];
+        let title = PaneTitle { name: "vim".into(), cwd: "~/src".into() };
+        let bars = vec![
+            TitleBar { rect: left, title: title.clone(), focused: true, done: None, monitor: None, recording: true },
+            TitleBar { rect: right, title, focused: false, done: None, monitor: Some("[act]"), recording: false },
+        ];
+        let overlays = Overlays {
+            title_bars: Some(&bars),
+            ..Default::default()
+        };
+        let shot = renderer.capture_panes(&panes, &overlays).unwrap();
+
+        // 各ペインの最上段が塗られ、フォーカス中のペインは明るい
+        let (_, cell_height) = renderer.cell_size();
+        let focused = shot.pixel(1, 1);
+        let unfocused = shot.pixel((right.x * shot.width as f32) as u32 + 2, 1);
+        assert!(focused[2] > unfocused[2] && unfocused[2] > 0, "{:?} {:?}", focused, unfocused);
+
+        // 2行目以降は重ならない
+        assert_eq!(shot.pixel(1, (cell_height * 1.5) as u32)[..3], [0, 0, 0]);
+    }
+
+    #[test]
+    #[ignore = "GPU アダプターが必要（cargo test -- --ignored）"]
+    fn test_alert_frames() {
+        let Some(mut renderer) = headless(640, 240) else { return };
+
+        let mut terminal = Terminal::new(10, 4);
+        let mut snapshot = TerminalSnapshot::new();
+        snapshot.update(&mut terminal);
+        let full = Rect::full();
+        let (left, right) = (full.left_half(), full.right_half());
+        let panes = [(&snapshot, left, true), (&snapshot, right, false)];
+        let alerts = [(right, MonitorKind::Silence)];
+        let overlays = Overlays {
+            pane_alerts: Some(&alerts),
+            ..Default::default()
+        };
+        let shot = renderer.capture_panes(&panes, &overlays).unwrap();
+
+        // 警告の出たペインの縁だけが塗られる
+        let right_x = (right.x * shot.width as f32) as u32;
+        let mid_y = shot.height / 2;
+        assert!(shot.pixel(shot.width - 1, mid_y)[2] > 0);
+        assert!(shot.pixel(right_x + 20, 0)[2] > 0);
+        assert_eq!(shot.pixel(right_x + 20, mid_y)[..3], [0, 0, 0]);
+        assert_eq!(shot.pixel(0, mid_y)[..3], [0, 0, 0]);
+    }
+
+    #[test]
+    #[ignore = "GPU アダプターが必要（cargo test -- --ignored）"]
+    fn test_key_hints() {
+        let Some(mut renderer) = headless(640, 240) else { return };
+
+        let mut terminal = Terminal::new(10, 4);
+        let mut snapshot = TerminalSnapshot::new();
+        snapshot.update(&mut terminal);
+        let hints = vec!["ctrl+a …".to_string(), "%  split-horizontal".to_string()];
+        let overlays = Overlays {
+            key_hints: Some(&hints),
+            ..Default::default()
+        };
+        let shot = renderer
+            .capture_panes(&[(&snapshot, Rect::full(), true)], &overlays)
+            .unwrap();
+
+        // 右下に出て、左端は塗られない
+        let (cell_width, cell_height) = renderer.cell_size();
+        let x = shot.width - (cell_width * 2.0) as u32;
+        let y = shot.height - (cell_height / 2.0) as u32;
+        assert!(shot.pixel(x, y)[2] > 0);
+        assert_eq!(shot.pixel(1, shot.height / 2)[..3], [0, 0, 0]);
+    }
+
+    #[test]
+    #[ignore = "GPU アダプターが必要（cargo test -- --ignored）"]
+    fn test_copy_cursor() {
+        let Some(mut renderer) = headless(640, 240) else { return };
+
+        let mut terminal = Terminal::new(10, 4);
+        let mut snapshot = TerminalSnapshot::new();
+        snapshot.update(&mut terminal);
+        let cursor = CopyCursor {
+            rect: Rect::full(),
+            col: 2,
+            row: 1,
+            character: ' ',
+            status: "COPY".to_string(),
+        };
+        let overlays = Overlays {
+            copy_cursor: Some(&cursor),
+            ..Default::default()
+        };
+        let shot = renderer.capture_panes(&[(&snapshot, Rect::full(), true)], &overlays).unwrap();
+
+        // 仮想カーソルのセルだけが塗られる
+        let (cell_width, cell_height) = renderer.cell_size();
+        let y = (cell_height * 1.5) as u32;
+        let [r, g, b, _] = shot.pixel((cell_width * 2.5) as u32, y);
+        assert!(g > 150 && g > r && b > r, "unexpected pixel {:?}", (r, g, b));
+        assert_eq!(shot.pixel((cell_width * 0.5) as u32, y)[..3], [0, 0, 0]);
+
+        // 状態は最下段の右端
+        let (cols, rows) = renderer.calculate_terminal_size();
+        let x = ((cols as f32 - 0.5) * cell_width) as u32;
+        let y = ((rows as f32 - 0.5) * cell_height) as u32;
+        assert!(shot.pixel(x, y)[2] > 0);
+    }
+
+    #[test]
+    #[ignore = "GPU アダプターが必要（cargo test -- --ignored）"]
+    fn test_scrollbar() {
+        let Some(mut renderer) = headless(640, 240) else { return };
+
+        let mut terminal = Terminal::new(10, 4);
+        let mut snapshot = TerminalSnapshot::new();
+        snapshot.update(&mut terminal);
+        let bars = [Scrollbar { rect: Rect::full(), top: 0.5, height: 0.25, dragging: false }];
+        let overlays = Overlays {
+            scrollbars: Some(&bars),
+            ..Default::default()
+        };
+        let shot = renderer.capture_panes(&[(&snapshot, Rect::full(), true)], &overlays).unwrap();
+
+        // 右端のつまみの範囲だけが塗られる（240px の 50%〜75%）
+        let x = 640 - 2 - (scrollbar::WIDTH as u32) / 2;
+        assert!(shot.pixel(x, 150)[2] > 0);
+        assert_eq!(shot.pixel(x, 100)[..3], [0, 0, 0]);
+        assert_eq!(shot.pixel(x, 200)[..3], [0, 0, 0]);
+        assert_eq!(shot.pixel(600, 150)[..3], [0, 0, 0]);
+    }
+
+    #[test]
+    #[ignore = "GPU アダプターが必要（cargo test -- --ignored）"]
+    fn test_status_bar() {
+        let Some(mut renderer) = headless(640, 240) else { return };
+
+        let mut terminal = Terminal::new(10, 4);
+        let mut snapshot = TerminalSnapshot::new();
+        snapshot.update(&mut terminal);
+        let panes = [(&snapshot, Rect::full(), false)];
+        let line = StatusLine {
+            left: vec!["~/src".into()],
+            right: vec!["12:34".into()],
+        };
+        let overlays = Overlays {
+            status_bar: Some(&line),
+            ..Default::default()
+        };
+        let shot = renderer.capture_panes(&panes, &overlays).unwrap();
+
+        // 最後の丸ごと入る行が塗られ、その上の行は重ならない
+        let (_, rows) = renderer.calculate_terminal_size();
+        let (_, cell_height) = renderer.cell_size();
+        let bar_y = ((rows - 1) as f32 * cell_height) as u32 + 1;
+        assert!(shot.pixel(shot.width / 2, bar_y)[2] > 0);
+        assert_eq!(shot.pixel(shot.width / 2, bar_y - 2)[..3], [0, 0, 0]);
+    }
+
+    #[test]
+    #[ignore = "GPU アダプターが必要（cargo test -- --ignored）"]
+    fn test_shared_context() {
+        let Some(mut first) = headless(320, 120) else { return };
+        let mut second = Renderer::with_context(Arc::clone(&first.context), 160, 60, None);
+
+        let mut terminal = Terminal::new(10, 4);
+        AnsiParser::new().process(&mut terminal, b"abc");
+        let mut snapshot = TerminalSnapshot::new();
+        snapshot.update(&mut terminal);
+        let panes = [(&snapshot, Rect::full(), false)];
+
+        // 1つ目のウィンドウでラスタライズしたグリフを2つ目でも使う
+        first.capture_panes(&panes, &Overlays::default()).unwrap();
+        let glyphs = first.frame_stats().glyphs;
+        let shot = second.capture_panes(&panes, &Overlays::default()).unwrap();
+        assert_eq!(second.frame_stats().glyphs, glyphs);
+        assert_eq!((shot.width, shot.height), (160, 60));
+    }
+
+    #[test]
+    #[ignore = "GPU アダプターが必要（cargo test -- --ignored）"]
+    fn test_box_drawing_fills_cell_edges() {
+        let Some(mut renderer) = headless(320, 120) else { return };
+
+        let mut terminal = Terminal::new(10, 4);
+        AnsiParser::new().process(&mut terminal, "──█".as_bytes());
+        let mut snapshot = TerminalSnapshot::new();
+        snapshot.update(&mut terminal);
+
+        let shot = renderer
+            .capture_panes(&[(&snapshot, Rect::full(), false)], &Overlays::default())
+            .unwrap();
+
+        // 横線はセルの境界でも途切れない
+        let (cell_width, cell_height) = renderer.cell_size();
+        let boundary = cell_width as u32;
+        let line_y = (0..cell_height as u32)
+            .find(|&y| shot.pixel(1, y)[0] > 128)
+            .expect("横線が描画されていない");
+        for x in [0, boundary - 1, boundary, boundary * 2 - 1] {
+            assert!(shot.pixel(x, line_y)[0] > 128, "gap at x={}", x);
+        }
+
+        // ブロックはセル全体を塗る
+        let [r, ..] = shot.pixel(boundary * 2, 0);
+        assert!(r > 128);
+        let [r, ..] = shot.pixel(boundary * 3 - 1, cell_height as u32 - 1);
+        assert!(r > 128);
+    }
+}
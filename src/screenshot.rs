@@ -0,0 +1,155 @@
+//! スクリーンショットモジュール
+//!
+//! レンダラーが読み戻したピクセルを保持し、PNGとして書き出す
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// 読み戻した画像（RGBA 8bit、行優先）
+pub struct Screenshot {
+    /// 幅（ピクセル）
+    pub width: u32,
+    /// 高さ（ピクセル）
+    pub height: u32,
+    /// RGBAピクセル列
+    pub pixels: Vec<u8>,
+}
+
+impl Screenshot {
+    /// ピクセル列から作成
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        debug_assert_eq!(pixels.len(), (width * height * 4) as usize);
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// 指定位置のピクセルを取得
+    #[allow(dead_code)]
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let idx = ((y * self.width + x) * 4) as usize;
+        [
+            self.pixels[idx],
+            self.pixels[idx + 1],
+            self.pixels[idx + 2],
+            self.pixels[idx + 3],
+        ]
+    }
+
+    /// 指定範囲を切り出す（範囲は画像内にクランプ）
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> Screenshot {
+        let x = x.min(self.width);
+        let y = y.min(self.height);
+        let width = width.min(self.width - x);
+        let height = height.min(self.height - y);
+
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for row in y..y + height {
+            let start = ((row * self.width + x) * 4) as usize;
+            pixels.extend_from_slice(&self.pixels[start..start + (width * 4) as usize]);
+        }
+        Screenshot::new(width, height, pixels)
+    }
+
+    /// PNGにエンコード
+    pub fn encode_png(&self) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut data, self.width, self.height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().context("PNGヘッダーの書き込みに失敗")?;
+            writer
+                .write_image_data(&self.pixels)
+                .context("PNGデータの書き込みに失敗")?;
+        }
+        Ok(data)
+    }
+
+    /// PNGファイルとして保存
+    pub fn save_png(&self, path: &Path) -> Result<()> {
+        let data = self.encode_png()?;
+        fs::write(path, data).with_context(|| format!("PNGの保存に失敗: {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// 保存先ディレクトリ（~/Desktop があればそこ、なければホーム）
+pub fn default_dir() -> PathBuf {
+    let home = std::env::var("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
+    let desktop = home.join("Desktop");
+    if desktop.is_dir() {
+        desktop
+    } else {
+        home
+    }
+}
+
+/// タイムスタンプ（ミリ秒まで）付きのファイル名を生成（同じ名前のファイルがあれば番号を付ける）
+pub fn timestamped_path(dir: &Path) -> PathBuf {
+    let stamp = chrono::Local::now().format("%Y-%m-%d %H.%M.%S%.3f");
+    unused_path(dir, &format!("UmiTerm {}", stamp))
+}
+
+/// まだないファイル名（`名前.png`、あれば `名前 2.png`・`名前 3.png`…）
+fn unused_path(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(format!("{}.png", name));
+    if !path.exists() {
+        return path;
+    }
+    (2..)
+        .map(|n| dir.join(format!("{} {}.png", name, n)))
+        .find(|path| !path.exists())
+        .expect("使っていない番号がある")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_png() {
+        let shot = Screenshot::new(2, 1, vec![255, 0, 0, 255, 0, 0, 255, 255]);
+        let data = shot.encode_png().unwrap();
+
+        // PNGシグネチャで始まる
+        assert_eq!(&data[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(shot.pixel(1, 0), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_timestamped_path() {
+        let dir = std::env::temp_dir().join(format!("umiterm-screenshot-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        // 同じ時刻に撮っても上書きしない
+        let first = unused_path(&dir, "UmiTerm shot");
+        fs::write(&first, b"").unwrap();
+        let second = unused_path(&dir, "UmiTerm shot");
+        assert_eq!(second, dir.join("UmiTerm shot 2.png"));
+
+        // ミリ秒まで入る（"YYYY-MM-DD HH.MM.SS.mmm"）
+        let path = timestamped_path(&dir);
+        let name = path.file_stem().unwrap().to_str().unwrap();
+        assert_eq!(name.len(), "UmiTerm 2026-01-01 00.00.00.000".len(), "{}", name);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_crop() {
+        let pixels = (0..16u8).flat_map(|i| [i, i, i, 255]).collect();
+        let shot = Screenshot::new(4, 4, pixels);
+
+        let cropped = shot.crop(1, 2, 2, 5);
+        assert_eq!((cropped.width, cropped.height), (2, 2));
+        assert_eq!(cropped.pixel(0, 0), [9, 9, 9, 255]);
+        assert_eq!(cropped.pixel(1, 1), [14, 14, 14, 255]);
+    }
+}
@@ -0,0 +1,704 @@
+//! スクロールバックモジュール
+//!
+//! 画面上端から押し出された行を保持する
+//! メモリ使用量・行数に上限を設け、超えた分は古い行から捨てる
+//! （ディスクへの書き出しを有効にすると、捨てずに一時ファイルへ移す）
+//!
+//! # メモリ削減
+//!
+//! 行は圧縮セル（文字 + スタイル番号、8バイト）で保持する
+//! 前景色・背景色・フラグの組み合わせはスタイル表に登録して共有する
+//! （通常のセルは16バイト。画面のグリッドは書き換えが多いので通常のセルのまま）
+//! メモリ上限にはスタイル表の分も含める
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::config::{self, Config};
+use crate::grid::{self, Cell, CellFlags, Color};
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 定数
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// ペインごとのメモリ上限の既定値（MB）
+const DEFAULT_MAX_MB: usize = 16;
+
+/// 1行あたりの管理領域（セル以外のメモリ）
+const LINE_OVERHEAD: usize = std::mem::size_of::<Box<[PackedCell]>>();
+
+/// スタイル表に登録できる最大数（スタイル番号は u16）
+const MAX_STYLES: usize = u16::MAX as usize + 1;
+
+/// ディスクに書き出したセル1つのバイト数（文字 4 + 前景色 4 + 背景色 4 + フラグ 1）
+const SPILLED_CELL_BYTES: usize = 13;
+
+/// 書き出し用の一時ファイルの通し番号
+static SPILL_ID: AtomicU64 = AtomicU64::new(0);
+
+/// メモリ上限の既定値（環境変数 `UMITERM_SCROLLBACK_MB` で変更可能）
+pub fn default_max_bytes() -> usize {
+    let mb = config::var("UMITERM_SCROLLBACK_MB")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_MAX_MB);
+    mb * 1024 * 1024
+}
+
+/// スクロールバックの上限（`[scrollback]`、プロファイルの `scrollback` が優先）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// メモリ上の行のバイト数の上限
+    pub max_bytes: usize,
+    /// メモリ上の行数の上限（None で無制限）
+    pub max_lines: Option<usize>,
+    /// 上限を超えた行をディスクに書き出すか（捨てずに無制限に残す）
+    pub spill: bool,
+}
+
+impl Limits {
+    /// プロファイル（None なら `[scrollback]` だけ）の上限
+    pub fn from_config(config: &Config, profile: Option<&str>) -> Self {
+        let profile = profile.and_then(|name| config.profiles.get(name)).map(|p| &p.scrollback);
+        let max_bytes = match profile.and_then(|s| s.max_mb) {
+            Some(mb) => mb as usize * 1024 * 1024,
+            None => default_max_bytes(),
+        };
+        Self {
+            max_bytes,
+            max_lines: profile.and_then(|s| s.lines).or(config.scrollback.lines),
+            spill: profile.and_then(|s| s.spill).or(config.scrollback.spill).unwrap_or(false),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 圧縮セルとスタイル表
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// セルの見た目（前景色・背景色・フラグ）
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Style {
+    fg: Color,
+    bg: Color,
+    flags: CellFlags,
+}
+
+impl Style {
+    fn of(cell: &Cell) -> Self {
+        Self {
+            fg: cell.fg,
+            bg: cell.bg,
+            flags: cell.flags,
+        }
+    }
+}
+
+/// スクロールバック用の圧縮セル
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PackedCell {
+    /// 表示する文字
+    character: char,
+    /// スタイル表の番号
+    style: u16,
+}
+
+/// スタイルの登録表（番号 0 は既定のスタイル）
+struct StyleTable {
+    /// 番号順のスタイル
+    styles: Vec<Style>,
+    /// スタイルから番号への逆引き
+    index: HashMap<Style, u16>,
+}
+
+impl StyleTable {
+    fn new() -> Self {
+        let mut table = Self {
+            styles: Vec::new(),
+            index: HashMap::new(),
+        };
+        table.intern(Style::of(&Cell::default()));
+        table
+    }
+
+    /// スタイルを登録して番号を返す（満杯なら None）
+    fn intern(&mut self, style: Style) -> Option<u16> {
+        if let Some(&id) = self.index.get(&style) {
+            return Some(id);
+        }
+        if self.styles.len() >= MAX_STYLES {
+            return None;
+        }
+        let id = self.styles.len() as u16;
+        self.styles.push(style);
+        self.index.insert(style, id);
+        Some(id)
+    }
+
+    /// 使用メモリ（概算）
+    fn bytes(&self) -> usize {
+        self.styles.len() * (std::mem::size_of::<Style>() * 2 + std::mem::size_of::<u16>())
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// スクロールバック
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// メモリ使用状況（パフォーマンスHUD用）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScrollbackUsage {
+    /// 保持している行数
+    pub lines: usize,
+    /// 使用中のバイト数（概算）
+    pub bytes: usize,
+    /// 上限のバイト数
+    pub max_bytes: usize,
+    /// ディスクに書き出した行数
+    pub spilled: usize,
+}
+
+impl std::ops::Add for ScrollbackUsage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            lines: self.lines + other.lines,
+            bytes: self.bytes + other.bytes,
+            max_bytes: self.max_bytes + other.max_bytes,
+            spilled: self.spilled + other.spilled,
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ディスクへの書き出し
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// メモリから押し出された行の一時ファイル（古い行が先頭）
+struct Spill {
+    file: File,
+    path: PathBuf,
+    /// 行ごとのファイル上の位置とセル数
+    index: Vec<(u64, u32)>,
+    /// ファイルの末尾
+    end: u64,
+}
+
+impl Spill {
+    /// 一時ディレクトリに新しいファイルを作る
+    fn create() -> io::Result<Self> {
+        let id = SPILL_ID.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("umiterm-scrollback-{}-{}", std::process::id(), id));
+        let file = File::options().read(true).write(true).create_new(true).open(&path)?;
+        Ok(Self { file, path, index: Vec::new(), end: 0 })
+    }
+
+    /// 行を末尾に書き足す（スタイル表に依存しないよう色とフラグをそのまま書く）
+    fn push(&mut self, cells: &[Cell]) -> io::Result<()> {
+        let mut buf = Vec::with_capacity(cells.len() * SPILLED_CELL_BYTES);
+        for cell in cells {
+            buf.extend_from_slice(&(cell.character as u32).to_le_bytes());
+            buf.extend_from_slice(&[cell.fg.r, cell.fg.g, cell.fg.b, cell.fg.a]);
+            buf.extend_from_slice(&[cell.bg.r, cell.bg.g, cell.bg.b, cell.bg.a]);
+            buf.push(cell.flags.bits());
+        }
+        self.file.seek(SeekFrom::Start(self.end))?;
+        self.file.write_all(&buf)?;
+        self.index.push((self.end, cells.len() as u32));
+        self.end += buf.len() as u64;
+        Ok(())
+    }
+
+    /// 古い方から数えた行を読む
+    fn read(&self, index: usize) -> io::Result<Vec<Cell>> {
+        let (offset, count) = self.index[index];
+        let mut buf = vec![0; count as usize * SPILLED_CELL_BYTES];
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut buf)?;
+        Ok(buf
+            .chunks_exact(SPILLED_CELL_BYTES)
+            .map(|c| Cell {
+                character: char::from_u32(u32::from_le_bytes([c[0], c[1], c[2], c[3]])).unwrap_or(' '),
+                fg: Color { r: c[4], g: c[5], b: c[6], a: c[7] },
+                bg: Color { r: c[8], g: c[9], b: c[10], a: c[11] },
+                flags: CellFlags::from_bits_truncate(c[12]),
+            })
+            .collect())
+    }
+
+    /// すべての行を削除
+    fn clear(&mut self) -> io::Result<()> {
+        self.index.clear();
+        self.end = 0;
+        self.file.set_len(0)
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// 新しい方から数えた行（メモリ上の圧縮セル、またはディスクから読んだセル）
+enum Row<'a> {
+    Memory(&'a [PackedCell]),
+    Disk(Vec<Cell>),
+}
+
+impl Row<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Self::Memory(line) => line.len(),
+            Self::Disk(cells) => cells.len(),
+        }
+    }
+}
+
+/// スクロールバックバッファ（古い行が先頭）
+pub struct Scrollback {
+    /// 行（末尾の空白セルは切り詰めて保持）
+    lines: VecDeque<Box<[PackedCell]>>,
+    /// スタイル表（全行で共有）
+    styles: StyleTable,
+    /// 最後に整理したときのスタイル数（表がその倍になるまでは整理しない）
+    compacted_styles: usize,
+    /// 行の使用バイト数（概算）
+    bytes: usize,
+    /// 上限のバイト数
+    max_bytes: usize,
+    /// 上限の行数（None で無制限）
+    max_lines: Option<usize>,
+    /// メモリから押し出された行の書き出し先（None なら捨てる）
+    spill: Option<Spill>,
+    /// これまでに捨てた行数（行の通し番号を保つため）
+    dropped: u64,
+}
+
+impl Scrollback {
+    /// 上限を指定して作成
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            styles: StyleTable::new(),
+            compacted_styles: 1,
+            bytes: 0,
+            max_bytes,
+            max_lines: None,
+            spill: None,
+            dropped: 0,
+        }
+    }
+
+    /// 行を追加（上限を超えたら古い行を捨てるか、ディスクに書き出す）
+    pub fn push(&mut self, row: &[Cell]) {
+        if (self.max_bytes == 0 || self.max_lines == Some(0)) && self.spill.is_none() {
+            self.dropped += 1;
+            return;
+        }
+
+        // 末尾の空白セルは保持しない（表示時に補う）
+        let len = row
+            .iter()
+            .rposition(|cell| *cell != Cell::default())
+            .map_or(0, |i| i + 1);
+        let line: Box<[PackedCell]> = row[..len].iter().map(|cell| self.pack(cell)).collect();
+
+        self.bytes += Self::line_bytes(&line);
+        self.lines.push_back(line);
+        self.evict();
+    }
+
+    /// セルを圧縮（スタイル表が満杯なら整理し、それでも入らなければ既定のスタイル）
+    fn pack(&mut self, cell: &Cell) -> PackedCell {
+        let style = Style::of(cell);
+        let id = match self.styles.intern(style) {
+            Some(id) => id,
+            None => {
+                self.compact_styles();
+                self.styles.intern(style).unwrap_or(0)
+            }
+        };
+        PackedCell {
+            character: cell.character,
+            style: id,
+        }
+    }
+
+    /// 捨てられた行のスタイルを表から取り除く
+    fn compact_styles(&mut self) {
+        let mut table = StyleTable::new();
+        for line in &mut self.lines {
+            for cell in line.iter_mut() {
+                let style = self.styles.styles[cell.style as usize];
+                // 使用中のスタイルは元の表以下の数なので必ず入る
+                cell.style = table.intern(style).unwrap_or(0);
+            }
+        }
+        self.compacted_styles = table.styles.len();
+        self.styles = table;
+    }
+
+    /// 上限を超えた分の古い行を捨てる（書き出し先があればディスクへ移す）
+    fn evict(&mut self) {
+        let mut compacted = false;
+        while self.memory_bytes() > self.max_bytes || self.max_lines.is_some_and(|max| self.lines.len() > max) {
+            // 行だけなら収まるのにスタイル表で超えるなら、捨てた行のスタイルを先に取り除く
+            // （整理は全行をなめるので、前回の整理から表が倍になったときか、最後の1行を捨てる前だけ）
+            let grown = self.styles.styles.len() >= self.compacted_styles * 2 || self.lines.len() <= 1;
+            if !compacted && self.bytes <= self.max_bytes && grown {
+                self.compact_styles();
+                compacted = true;
+                continue;
+            }
+            let Some(old) = self.lines.pop_front() else { break };
+            self.bytes -= Self::line_bytes(&old);
+            if self.spill.is_none() {
+                self.dropped += 1;
+                continue;
+            }
+            let cells: Vec<Cell> = old.iter().map(|cell| self.unpack(*cell)).collect();
+            if let Err(e) = self.spill.as_mut().map_or(Ok(()), |spill| spill.push(&cells)) {
+                // 書けなくなったら書き出しをやめる（書き出した行も捨てる）
+                log::warn!("スクロールバックをディスクに書き出せません: {}", e);
+                self.dropped += 1;
+                self.set_spill(false);
+            }
+        }
+        // 行がなくなれば表のスタイルはどれも使われていない
+        if self.lines.is_empty() && self.styles.styles.len() > 1 {
+            self.styles = StyleTable::new();
+            self.compacted_styles = 1;
+        }
+    }
+
+    /// 圧縮セルを展開
+    fn unpack(&self, cell: PackedCell) -> Cell {
+        let style = self.styles.styles[cell.style as usize];
+        Cell {
+            character: cell.character,
+            fg: style.fg,
+            bg: style.bg,
+            flags: style.flags,
+        }
+    }
+
+    /// 新しい方から数えた行を展開してコピー（0 が最新、足りない分は空白）
+    /// 戻り値: 行が存在したか
+    pub fn copy_line(&self, index: usize, dst: &mut [Cell]) -> bool {
+        let Some(line) = self.line(index) else {
+            dst.fill(Cell::default());
+            return false;
+        };
+        let n = line.len().min(dst.len());
+        match line {
+            Row::Memory(line) => {
+                for (d, s) in dst[..n].iter_mut().zip(&line[..n]) {
+                    *d = self.unpack(*s);
+                }
+            }
+            Row::Disk(cells) => dst[..n].copy_from_slice(&cells[..n]),
+        }
+        dst[n..].fill(Cell::default());
+        true
+    }
+
+    /// 新しい方から数えた行の指定列のセル（0 が最新）
+    pub fn cell(&self, index: usize, col: usize) -> Option<Cell> {
+        let cell = match self.line(index)? {
+            Row::Memory(line) => line.get(col).map(|c| self.unpack(*c)),
+            Row::Disk(cells) => cells.get(col).copied(),
+        };
+        Some(cell.unwrap_or_default())
+    }
+
+    /// 新しい方から数えた行のセル（0 が最新、行の長さのまま）
+    pub fn line_cells(&self, index: usize) -> Option<Vec<Cell>> {
+        Some(match self.line(index)? {
+            Row::Memory(line) => line.iter().map(|c| self.unpack(*c)).collect(),
+            Row::Disk(cells) => cells,
+        })
+    }
+
+    /// 新しい方から数えた行のテキスト（0 が最新、全角文字の後ろの空きは除く）
+    pub fn line_text(&self, index: usize) -> Option<String> {
+        let chars = self.line_chars(index)?;
+        Some(chars.into_iter().filter(|c| *c != '\0').collect())
+    }
+
+    /// 新しい方から数えた行の文字（0 が最新、全角文字の後ろの空きは '\0' のまま）
+    pub fn line_chars(&self, index: usize) -> Option<Vec<char>> {
+        let mut chars: Vec<char> = match self.line(index)? {
+            Row::Memory(line) => line.iter().map(|c| c.character).collect(),
+            Row::Disk(cells) => cells.iter().map(|c| c.character).collect(),
+        };
+        grid::mark_wide_spacers(&mut chars);
+        Some(chars)
+    }
+
+    /// 新しい方から数えた行（0 が最新、メモリ上の行のあとにディスクの行）
+    fn line(&self, index: usize) -> Option<Row<'_>> {
+        let len = self.lines.len();
+        if index < len {
+            return Some(Row::Memory(&self.lines[len - 1 - index]));
+        }
+        let spill = self.spill.as_ref()?;
+        let disk_index = spill.index.len().checked_sub(index - len + 1)?;
+        match spill.read(disk_index) {
+            Ok(cells) => Some(Row::Disk(cells)),
+            Err(e) => {
+                log::warn!("スクロールバックをディスクから読めません: {}", e);
+                None
+            }
+        }
+    }
+
+    /// 行数（ディスクに書き出した行も含む）
+    pub fn len(&self) -> usize {
+        self.lines.len() + self.spilled()
+    }
+
+    /// ディスクに書き出した行数
+    fn spilled(&self) -> usize {
+        self.spill.as_ref().map_or(0, |spill| spill.index.len())
+    }
+
+    /// これまでに捨てた行数（いちばん古い行の通し番号）
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// すべての行を削除
+    pub fn clear(&mut self) {
+        self.dropped += self.len() as u64;
+        if let Some(spill) = &mut self.spill {
+            if let Err(e) = spill.clear() {
+                log::warn!("スクロールバックのファイルを空にできません: {}", e);
+            }
+        }
+        self.lines.clear();
+        self.styles = StyleTable::new();
+        self.compacted_styles = 1;
+        self.bytes = 0;
+    }
+
+    /// メモリ上限を変更
+    #[allow(dead_code)]
+    pub fn set_max_bytes(&mut self, max_bytes: usize) {
+        self.max_bytes = max_bytes;
+        self.evict();
+    }
+
+    /// 上限をまとめて変更
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.max_bytes = limits.max_bytes;
+        self.max_lines = limits.max_lines;
+        self.set_spill(limits.spill);
+    }
+
+    /// ディスクへの書き出しを切り替える（やめるとディスク上の行は捨てる）
+    pub fn set_spill(&mut self, spill: bool) {
+        if spill && self.spill.is_none() {
+            match Spill::create() {
+                Ok(file) => self.spill = Some(file),
+                Err(e) => log::warn!("スクロールバックの一時ファイルを作れません: {}", e),
+            }
+        } else if !spill {
+            self.dropped += self.spilled() as u64;
+            self.spill = None;
+        }
+        self.evict();
+    }
+
+    /// 今の上限
+    pub fn limits(&self) -> Limits {
+        Limits { max_bytes: self.max_bytes, max_lines: self.max_lines, spill: self.spill.is_some() }
+    }
+
+    /// メモリ使用状況
+    pub fn usage(&self) -> ScrollbackUsage {
+        ScrollbackUsage {
+            lines: self.len(),
+            bytes: self.memory_bytes(),
+            max_bytes: self.max_bytes,
+            spilled: self.spilled(),
+        }
+    }
+
+    /// 行とスタイル表の使用バイト数（概算）
+    fn memory_bytes(&self) -> usize {
+        self.bytes + self.styles.bytes()
+    }
+
+    /// 1行のバイト数（概算）
+    fn line_bytes(line: &[PackedCell]) -> usize {
+        std::mem::size_of_val(line) + LINE_OVERHEAD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(text: &str, cols: usize) -> Vec<Cell> {
+        let mut cells = vec![Cell::default(); cols];
+        for (cell, c) in cells.iter_mut().zip(text.chars()) {
+            cell.character = c;
+        }
+        cells
+    }
+
+    #[test]
+    fn test_push_trims_trailing_blanks() {
+        let mut scrollback = Scrollback::new(1024 * 1024);
+        scrollback.push(&row("ab", 80));
+        scrollback.push(&row("", 80));
+
+        assert_eq!(scrollback.len(), 2);
+        assert_eq!(scrollback.line(1).unwrap().len(), 2);
+        assert_eq!(scrollback.line(0).unwrap().len(), 0);
+        assert!(scrollback.line(2).is_none());
+
+        // 展開時は空白で埋める
+        let mut dst = vec![Cell { character: 'x', ..Default::default() }; 4];
+        assert!(scrollback.copy_line(1, &mut dst));
+        assert_eq!(dst.iter().map(|c| c.character).collect::<String>(), "ab  ");
+        assert_eq!(scrollback.cell(1, 70), Some(Cell::default()));
+    }
+
+    #[test]
+    fn test_packed_cells_round_trip() {
+        // 圧縮セルは通常のセルの半分
+        assert_eq!(std::mem::size_of::<PackedCell>(), 8);
+        assert!(std::mem::size_of::<PackedCell>() * 2 <= std::mem::size_of::<Cell>());
+
+        let mut cells = row("RGB", 3);
+        cells[0].fg = Color::RED;
+        cells[1].bg = Color::rgb(1, 2, 3);
+        cells[2].flags = CellFlags::BOLD | CellFlags::UNDERLINE;
+
+        let mut scrollback = Scrollback::new(1024 * 1024);
+        scrollback.push(&cells);
+        scrollback.push(&cells);
+
+        let mut dst = vec![Cell::default(); 3];
+        scrollback.copy_line(0, &mut dst);
+        assert_eq!(dst, cells);
+
+        // 同じスタイルは共有される（既定 + 3種類）
+        assert_eq!(scrollback.styles.styles.len(), 4);
+    }
+
+    #[test]
+    fn test_compact_styles() {
+        let mut scrollback = Scrollback::new(1024 * 1024);
+        scrollback.set_limits(Limits { max_bytes: 1024 * 1024, max_lines: Some(2), spill: false });
+
+        // 捨てられた行のスタイルは整理時に取り除かれる
+        for i in 0..10u8 {
+            let mut cells = row("a", 1);
+            cells[0].fg = Color::rgb(i, 0, 0);
+            scrollback.push(&cells);
+        }
+        scrollback.compact_styles();
+        assert_eq!(scrollback.styles.styles.len(), 3);
+        assert_eq!(scrollback.cell(0, 0).unwrap().fg, Color::rgb(9, 0, 0));
+        assert_eq!(scrollback.cell(1, 0).unwrap().fg, Color::rgb(8, 0, 0));
+    }
+
+    #[test]
+    fn test_budget_drops_oldest() {
+        let line_bytes = Scrollback::line_bytes(&[PackedCell { character: '0', style: 0 }; 10]);
+        let style_bytes = StyleTable::new().bytes();
+        let mut scrollback = Scrollback::new(line_bytes * 3 + style_bytes);
+
+        for i in 0..5 {
+            scrollback.push(&row(&format!("{}123456789", i), 10));
+        }
+
+        // 上限を超えた古い行から捨てられる
+        let usage = scrollback.usage();
+        assert_eq!(usage.lines, 3);
+        assert_eq!(scrollback.bytes, line_bytes * 3);
+        assert_eq!(scrollback.cell(2, 0).unwrap().character, '2');
+
+        scrollback.set_max_bytes(line_bytes + style_bytes);
+        assert_eq!(scrollback.len(), 1);
+        assert_eq!(scrollback.cell(0, 0).unwrap().character, '4');
+
+        // 捨てた行は通し番号のために数えておく
+        assert_eq!(scrollback.dropped(), 4);
+        scrollback.clear();
+        assert_eq!(scrollback.dropped(), 5);
+    }
+
+    #[test]
+    fn test_budget_includes_styles() {
+        let line_bytes = Scrollback::line_bytes(&[PackedCell { character: 'a', style: 0 }]);
+        let style_bytes = StyleTable::new().bytes();
+        let max_bytes = line_bytes * 4 + style_bytes * 8;
+        let mut scrollback = Scrollback::new(max_bytes);
+
+        // 行ごとに違う色でも、スタイル表を含めて上限に収まり、捨てた行のスタイルが新しい行を押し出さない
+        for i in 0..200u8 {
+            let mut cells = row("a", 1);
+            cells[0].fg = Color::rgb(i, 0, 0);
+            scrollback.push(&cells);
+            assert!(scrollback.usage().bytes <= max_bytes, "{} > {}", scrollback.usage().bytes, max_bytes);
+            assert_eq!(scrollback.cell(0, 0).unwrap().fg, Color::rgb(i, 0, 0));
+        }
+
+        // スタイル表だけで上限を超えるなら行をすべて捨て、表も空にする
+        scrollback.set_max_bytes(StyleTable::new().bytes());
+        assert_eq!(scrollback.len(), 0);
+        assert_eq!(scrollback.styles.styles.len(), 1);
+    }
+
+    #[test]
+    fn test_max_lines() {
+        let mut scrollback = Scrollback::new(1024 * 1024);
+        scrollback.set_limits(Limits { max_bytes: 1024 * 1024, max_lines: Some(2), spill: false });
+        for i in 0..5 {
+            scrollback.push(&row(&i.to_string(), 10));
+        }
+        assert_eq!(scrollback.len(), 2);
+        assert_eq!(scrollback.dropped(), 3);
+        assert_eq!(scrollback.line_text(1).as_deref(), Some("3"));
+    }
+
+    #[test]
+    fn test_spill_to_disk() {
+        let mut scrollback = Scrollback::new(1024 * 1024);
+        scrollback.set_limits(Limits { max_bytes: 1024 * 1024, max_lines: Some(2), spill: true });
+        let mut styled = row("x", 3);
+        styled[0].fg = Color::rgb(1, 2, 3);
+        styled[0].flags = CellFlags::BOLD;
+        scrollback.push(&styled);
+        for i in 0..4 {
+            scrollback.push(&row(&format!("line {}", i), 10));
+        }
+
+        // メモリに2行、ディスクに3行（捨てた行はない）
+        assert_eq!(scrollback.len(), 5);
+        assert_eq!(scrollback.usage().spilled, 3);
+        assert_eq!(scrollback.dropped(), 0);
+        assert_eq!(scrollback.line_text(0).as_deref(), Some("line 3"));
+        assert_eq!(scrollback.line_text(2).as_deref(), Some("line 1"));
+        assert_eq!(scrollback.cell(4, 0), Some(styled[0]));
+        let mut dst = vec![Cell::default(); 2];
+        assert!(scrollback.copy_line(3, &mut dst));
+        assert_eq!(dst.iter().map(|c| c.character).collect::<String>(), "li");
+        assert!(scrollback.line(5).is_none());
+
+        // 書き出しをやめるとディスク上の行は捨てる
+        let path = scrollback.spill.as_ref().unwrap().path.clone();
+        scrollback.set_spill(false);
+        assert!(!path.exists());
+        assert_eq!(scrollback.len(), 2);
+        assert_eq!(scrollback.dropped(), 3);
+    }
+}
@@ -0,0 +1,189 @@
+//! スクロールバー
+//!
+//! ペインの右端に細いスクロールバーを出し、スクロールバックのどのあたりを
+//! 見ているかを示す。スクロールしたときやマウスを近づけたときだけ表示し、
+//! しばらく操作がなければ隠す。つまみはドラッグで動かせる
+//!
+//! さかのぼって表示している間に出力が届いたら、ペインの下端に
+//! 「N new lines ↓」を出し、クリックで最新の表示に戻れるようにする
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use crate::pane::{PaneId, Rect};
+
+/// スクロールバーの幅（ピクセル）
+pub const WIDTH: f32 = 6.0;
+
+/// つまみの最小の高さ（ピクセル）
+pub const MIN_THUMB_HEIGHT: f32 = 16.0;
+
+/// 最後の操作からこの時間が過ぎたら隠す
+const HIDE_AFTER: Duration = Duration::from_millis(1500);
+
+// ═══════════════════════════════════════════════════════════════════════════
+// つまみの位置
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// つまみの位置と高さ（ペインの高さに対する割合、上端, 高さ）
+///
+/// history はスクロールバックの行数、rows は画面の行数、offset は表示位置
+/// （0 が最新）。スクロールバックがなければ None
+pub fn thumb(history: usize, rows: usize, offset: usize) -> Option<(f32, f32)> {
+    if history == 0 || rows == 0 {
+        return None;
+    }
+    let total = (history + rows) as f32;
+    let top = history.saturating_sub(offset) as f32 / total;
+    Some((top, rows as f32 / total))
+}
+
+/// つまみの上端をペインの高さに対する割合 top に置いたときの表示位置
+pub fn offset_at(history: usize, rows: usize, top: f32) -> usize {
+    let total = (history + rows) as f32;
+    let line = (top * total).round().clamp(0.0, history as f32) as usize;
+    history - line
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 表示・ドラッグの状態
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// ドラッグ中のつまみ
+#[derive(Debug, Clone, Copy)]
+pub struct Drag {
+    pub pane_id: PaneId,
+    /// つまみの上端からつかんだ位置までの距離（ペインの高さに対する割合）
+    pub grab: f32,
+}
+
+/// ペインごとのスクロールバーの表示状態
+#[derive(Default)]
+pub struct Scrollbars {
+    /// 最後に見た表示位置と、最後に操作した時刻（まだ操作がなければ None）
+    activity: HashMap<PaneId, (usize, Option<Instant>)>,
+    /// ドラッグ中のつまみ
+    pub drag: Option<Drag>,
+}
+
+impl Scrollbars {
+    /// 表示位置の変化を記録し、スクロールバーを表示するか返す
+    pub fn update(&mut self, pane_id: PaneId, offset: usize, now: Instant) -> bool {
+        let entry = self.activity.entry(pane_id).or_insert((offset, None));
+        if entry.0 != offset {
+            *entry = (offset, Some(now));
+        }
+        let dragging = self.drag.is_some_and(|drag| drag.pane_id == pane_id);
+        dragging || entry.1.is_some_and(|time| now - time < HIDE_AFTER)
+    }
+
+    /// 操作があったことにする（マウスを近づけたとき）
+    pub fn touch(&mut self, pane_id: PaneId, now: Instant) {
+        if let Some(entry) = self.activity.get_mut(&pane_id) {
+            entry.1 = Some(now);
+        }
+    }
+
+    /// 閉じたペインの状態を捨てる
+    pub fn retain(&mut self, alive: impl Fn(&PaneId) -> bool) {
+        self.activity.retain(|id, _| alive(id));
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 表示
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 新しい行の数の表示（最下段の中央、ペインの列の範囲）
+pub fn new_lines_label(count: usize) -> String {
+    if count == 1 {
+        " 1 new line ↓ ".to_string()
+    } else {
+        format!(" {} new lines ↓ ", count)
+    }
+}
+
+/// 新しい行の数の表示を置く列の範囲（ペインの最下段の中央）
+pub fn new_lines_columns(cols: usize, label: &str) -> Range<usize> {
+    let width = label.chars().count().min(cols);
+    let start = (cols - width) / 2;
+    start..start + width
+}
+
+/// さかのぼって表示している間に届いた行の数の表示（レンダラーに渡す）
+pub struct NewLines {
+    /// ペインのターミナル部分の矩形
+    pub rect: Rect,
+    /// ペインの列数と行数
+    pub cols: usize,
+    pub rows: usize,
+    /// 届いた行数
+    pub count: usize,
+}
+
+/// ペインのスクロールバーの表示（レンダラーに渡す）
+pub struct Scrollbar {
+    /// ペインのターミナル部分の矩形
+    pub rect: Rect,
+    /// つまみの上端と高さ（ペインの高さに対する割合）
+    pub top: f32,
+    pub height: f32,
+    /// ドラッグ中か
+    pub dragging: bool,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thumb_and_offset() {
+        assert_eq!(thumb(0, 24, 0), None);
+
+        // 最新を表示中なら下端、いちばん古い行なら上端
+        assert_eq!(thumb(76, 24, 0), Some((0.76, 0.24)));
+        assert_eq!(thumb(76, 24, 76), Some((0.0, 0.24)));
+
+        // つまみの位置から表示位置へ（範囲外は端に寄せる）
+        assert_eq!(offset_at(76, 24, 0.76), 0);
+        assert_eq!(offset_at(76, 24, 0.0), 76);
+        assert_eq!(offset_at(76, 24, 0.5), 26);
+        assert_eq!(offset_at(76, 24, -0.2), 76);
+        assert_eq!(offset_at(76, 24, 0.9), 0);
+    }
+
+    #[test]
+    fn test_new_lines_label() {
+        assert_eq!(new_lines_label(1), " 1 new line ↓ ");
+        let label = new_lines_label(12);
+        assert_eq!(label, " 12 new lines ↓ ");
+
+        // 中央に置き、狭いペインでははみ出さない
+        assert_eq!(new_lines_columns(80, &label), 32..48);
+        assert_eq!(new_lines_columns(10, &label), 0..10);
+    }
+
+    #[test]
+    fn test_auto_hide() {
+        let mut bars = Scrollbars::default();
+        let pane = PaneId(1);
+        let start = Instant::now();
+
+        // 最初は隠れていて、スクロールすると表示される
+        assert!(!bars.update(pane, 0, start));
+        assert!(bars.update(pane, 3, start));
+        assert!(bars.update(pane, 3, start + Duration::from_millis(1000)));
+        assert!(!bars.update(pane, 3, start + Duration::from_millis(2000)));
+
+        // マウスを近づけるかドラッグ中なら表示
+        bars.touch(pane, start + Duration::from_millis(2000));
+        assert!(bars.update(pane, 3, start + Duration::from_millis(2500)));
+        bars.drag = Some(Drag { pane_id: pane, grab: 0.0 });
+        assert!(bars.update(pane, 3, start + Duration::from_secs(10)));
+    }
+}
@@ -0,0 +1,758 @@
+//! セッションデーモンモジュール
+//!
+//! PTYをバックグラウンドのデーモンプロセス（`umiterm --daemon`）に持たせ、
+//! GUIを閉じてもシェルを生かしたままにする（tmux のようなデタッチ・再アタッチ）
+//!
+//! GUIとデーモンは Unix ドメインソケットで通信し、1つの接続が1つのセッションに対応する
+//! 接続が切れるとセッションはデタッチ状態になり、次に起動したGUIがアタッチし直す
+//! 環境変数 `UMITERM_DAEMON=1` で有効化
+
+use std::collections::{HashMap, VecDeque};
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::net::UnixStream;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use crossbeam_channel::bounded;
+use parking_lot::Mutex;
+
+use crate::config;
+use crate::private_socket;
+use crate::pty::{ChildExit, ExitSlot, PaneCommand, Pty};
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 定数
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// デーモンとして起動する引数
+pub const DAEMON_ARG: &str = "--daemon";
+
+/// アタッチし直したときに送り直す出力の上限（バイト）
+const REPLAY_LIMIT: usize = 256 * 1024;
+
+/// 1フレームの上限（壊れたデータで巨大な確保をしないため）
+const MAX_FRAME: usize = 16 * 1024 * 1024;
+
+/// クライアントへの書き込みを待つ上限（止まったGUIにセッションを止めさせない）
+const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 実行中のコマンドの問い合わせに応答を待つ上限（閉じる操作を止めないため）
+const QUERY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// デーモンの起動を待つ回数と間隔
+const CONNECT_RETRIES: u32 = 50;
+const CONNECT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// シェルの出力が閉じてから終了コードを待つ回数と間隔
+const EXIT_WAIT_RETRIES: u32 = 20;
+const EXIT_WAIT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// セッションデーモンを使うか（環境変数 `UMITERM_DAEMON`）
+pub fn enabled() -> bool {
+    matches!(std::env::var("UMITERM_DAEMON").as_deref(), Ok("1") | Ok("on"))
+}
+
+/// ソケットのパス（`UMITERM_SOCKET` > 自分専用のディレクトリの `session.sock`）
+fn socket_path() -> PathBuf {
+    match std::env::var_os("UMITERM_SOCKET") {
+        Some(path) => PathBuf::from(path),
+        None => private_socket::default_path("session.sock"),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// プロトコル
+// ═══════════════════════════════════════════════════════════════════════════
+//
+// フレーム: [長さ u32 LE][種類 u8][本体]（長さは種類と本体の合計）
+
+/// クライアント（GUI）からデーモンへの要求
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Request {
+    /// 新しいセッションを作ってアタッチ
+    Spawn { cols: u16, rows: u16, command: PaneCommand, cwd: Option<PathBuf> },
+    /// デタッチ中のセッションにアタッチ
+    Attach { session: u64, cols: u16, rows: u16 },
+    /// デタッチ中のセッションの一覧
+    List,
+    /// キー入力
+    Input(Vec<u8>),
+    /// サイズ変更
+    Resize { cols: u16, rows: u16 },
+    /// セッションを終了（シェルも終了する）
+    Kill,
+    /// セッションでシェル以外に実行中のコマンド（閉じる前の確認用）
+    Running(u64),
+}
+
+/// デーモンからクライアントへの応答
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Reply {
+    /// アタッチした
+    Attached(u64),
+    /// デタッチ中のセッション
+    Sessions(Vec<u64>),
+    /// シェルの出力
+    Output(Vec<u8>),
+    /// 要求に失敗
+    Error(String),
+    /// シェルが終了した（終了コード）
+    Exited(Option<u32>),
+    /// シェル以外に実行中のコマンドの名前（なければ None）
+    Running(Option<String>),
+}
+
+/// 本体の組み立て
+#[derive(Default)]
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn u16(mut self, v: u16) -> Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    fn u64(mut self, v: u64) -> Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    fn bytes(mut self, v: &[u8]) -> Self {
+        self.0.extend_from_slice(v);
+        self
+    }
+
+    /// 長さ（u16）付きのバイト列（長すぎればエラー）
+    fn field(self, v: &[u8]) -> io::Result<Self> {
+        let len = u16::try_from(v.len()).map_err(|_| invalid("フィールドが長すぎます"))?;
+        Ok(self.u16(len).bytes(v))
+    }
+}
+
+/// 本体の読み取り
+struct Decoder<'a>(&'a [u8]);
+
+impl Decoder<'_> {
+    fn take(&mut self, len: usize) -> io::Result<&[u8]> {
+        if self.0.len() < len {
+            return Err(invalid("フレームが短すぎます"));
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn field(&mut self) -> io::Result<Vec<u8>> {
+        let len = self.u16()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    fn rest(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.0).to_vec()
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// 要求の作業ディレクトリ（空なら指定なし）
+fn decode_cwd(bytes: Vec<u8>) -> Option<PathBuf> {
+    (!bytes.is_empty()).then(|| PathBuf::from(OsString::from_vec(bytes)))
+}
+
+impl Request {
+    fn encode(&self) -> io::Result<(u8, Vec<u8>)> {
+        Ok(match self {
+            Self::Spawn { cols, rows, command, cwd } => {
+                // 作業ディレクトリ（空なら指定なし）、続いてコマンド
+                let cwd = cwd.as_ref().map(|cwd| cwd.as_os_str().as_bytes()).unwrap_or_default();
+                let e = Encoder::default().u16(*cols).u16(*rows).field(cwd)?;
+                match command {
+                    PaneCommand::Shell => (1, e.0),
+                    PaneCommand::Run(line) => (1, e.bytes(line.as_bytes()).0),
+                    PaneCommand::Profile(name) => (7, e.bytes(name.as_bytes()).0),
+                    PaneCommand::Container(name) => (8, e.bytes(name.as_bytes()).0),
+                    // 再生はデーモンを使わない（Pty::open が先に扱う）、WSL は Windows のみでデーモンがない
+                    PaneCommand::Play(_) | PaneCommand::Wsl(_) => (1, e.0),
+                }
+            }
+            Self::Attach { session, cols, rows } => (2, Encoder::default().u64(*session).u16(*cols).u16(*rows).0),
+            Self::List => (3, Vec::new()),
+            Self::Input(data) => (4, data.clone()),
+            Self::Resize { cols, rows } => (5, Encoder::default().u16(*cols).u16(*rows).0),
+            Self::Kill => (6, Vec::new()),
+            Self::Running(session) => (9, Encoder::default().u64(*session).0),
+        })
+    }
+
+    fn decode(tag: u8, body: &[u8]) -> io::Result<Self> {
+        let mut d = Decoder(body);
+        Ok(match tag {
+            1 => {
+                let (cols, rows, cwd) = (d.u16()?, d.u16()?, decode_cwd(d.field()?));
+                let line = String::from_utf8(d.rest()).map_err(|_| invalid("コマンドがUTF-8ではありません"))?;
+                let command = if line.is_empty() { PaneCommand::Shell } else { PaneCommand::Run(line) };
+                Self::Spawn { cols, rows, command, cwd }
+            }
+            2 => Self::Attach { session: d.u64()?, cols: d.u16()?, rows: d.u16()? },
+            3 => Self::List,
+            4 => Self::Input(d.rest()),
+            5 => Self::Resize { cols: d.u16()?, rows: d.u16()? },
+            6 => Self::Kill,
+            7 => {
+                let (cols, rows, cwd) = (d.u16()?, d.u16()?, decode_cwd(d.field()?));
+                let name = String::from_utf8(d.rest()).map_err(|_| invalid("プロファイルがUTF-8ではありません"))?;
+                Self::Spawn { cols, rows, command: PaneCommand::Profile(name), cwd }
+            }
+            8 => {
+                let (cols, rows, cwd) = (d.u16()?, d.u16()?, decode_cwd(d.field()?));
+                let name = String::from_utf8(d.rest()).map_err(|_| invalid("コンテナの名前がUTF-8ではありません"))?;
+                Self::Spawn { cols, rows, command: PaneCommand::Container(name), cwd }
+            }
+            9 => Self::Running(d.u64()?),
+            _ => return Err(invalid("不明な要求")),
+        })
+    }
+}
+
+impl Reply {
+    fn encode(&self) -> (u8, Vec<u8>) {
+        match self {
+            Self::Attached(session) => (1, Encoder::default().u64(*session).0),
+            Self::Sessions(ids) => (2, ids.iter().fold(Encoder::default(), |e, id| e.u64(*id)).0),
+            Self::Output(data) => (3, data.clone()),
+            Self::Error(message) => (4, message.as_bytes().to_vec()),
+            Self::Exited(code) => (5, code.map(|c| c.to_le_bytes().to_vec()).unwrap_or_default()),
+            // コマンドの名前は空にならないので、空なら実行中のコマンドなし
+            Self::Running(name) => (6, name.as_deref().unwrap_or_default().as_bytes().to_vec()),
+        }
+    }
+
+    fn decode(tag: u8, body: &[u8]) -> io::Result<Self> {
+        let mut d = Decoder(body);
+        Ok(match tag {
+            1 => Self::Attached(d.u64()?),
+            2 => Self::Sessions((0..body.len() / 8).map(|_| d.u64()).collect::<io::Result<_>>()?),
+            3 => Self::Output(d.rest()),
+            4 => Self::Error(String::from_utf8_lossy(body).into_owned()),
+            5 if body.is_empty() => Self::Exited(None),
+            5 => Self::Exited(Some(u32::from_le_bytes(d.take(4)?.try_into().unwrap()))),
+            6 if body.is_empty() => Self::Running(None),
+            6 => Self::Running(Some(String::from_utf8_lossy(body).into_owned())),
+            _ => return Err(invalid("不明な応答")),
+        })
+    }
+}
+
+/// フレームを書き込む
+fn write_frame(w: &mut impl Write, (tag, body): (u8, Vec<u8>)) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(5 + body.len());
+    frame.extend_from_slice(&(body.len() as u32 + 1).to_le_bytes());
+    frame.push(tag);
+    frame.extend_from_slice(&body);
+    w.write_all(&frame)
+}
+
+/// フレームを読み込む
+fn read_frame(r: &mut impl Read) -> io::Result<(u8, Vec<u8>)> {
+    let mut len = [0u8; 4];
+    r.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len == 0 || len > MAX_FRAME {
+        return Err(invalid("フレームの長さが不正です"));
+    }
+    let mut frame = vec![0u8; len];
+    r.read_exact(&mut frame)?;
+    let body = frame.split_off(1);
+    Ok((frame[0], body))
+}
+
+fn read_request(r: &mut impl Read) -> io::Result<Request> {
+    let (tag, body) = read_frame(r)?;
+    Request::decode(tag, &body)
+}
+
+fn read_reply(r: &mut impl Read) -> io::Result<Reply> {
+    let (tag, body) = read_frame(r)?;
+    Reply::decode(tag, &body)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// デーモン
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// デーモン側の1つのセッション
+struct Session {
+    /// シェルのPTY
+    pty: Mutex<Pty>,
+    /// 出力の送り先（アタッチ中のクライアントと、再アタッチ用の直近の出力）
+    io: Mutex<SessionIo>,
+}
+
+#[derive(Default)]
+struct SessionIo {
+    /// 直近の出力（アタッチし直したときに送り直す）
+    replay: VecDeque<u8>,
+    /// アタッチ中のクライアント
+    client: Option<Client>,
+}
+
+/// アタッチ中のクライアント（書き込みはセッションのロックの外で行う）
+#[derive(Clone)]
+struct Client {
+    /// 接続ごとの番号
+    id: u64,
+    /// 接続
+    stream: Arc<UnixStream>,
+    /// フレームが混ざらないよう、書き込む間持つロック
+    writing: Arc<Mutex<()>>,
+}
+
+impl Client {
+    /// 応答を書く（止まったクライアントには CLIENT_WRITE_TIMEOUT で諦める）
+    fn send(&self, reply: &Reply) -> io::Result<()> {
+        let _writing = self.writing.lock();
+        write_frame(&mut &*self.stream, reply.encode())
+    }
+}
+
+/// セッションの一覧
+type Registry = Arc<Mutex<HashMap<u64, Arc<Session>>>>;
+
+/// デーモンを実行（戻らない：最後のセッションが終わるとプロセスを終了する）
+pub fn run_daemon() -> Result<()> {
+    let path = socket_path();
+    // 前回のデーモンが残したソケットは、応答がなければ作り直す
+    if private_socket::connect(&path).is_ok() {
+        bail!("セッションデーモンはすでに起動しています: {}", path.display());
+    }
+    let listener = private_socket::bind(&path)?;
+    log::info!("セッションデーモンを起動しました: {}", path.display());
+
+    let registry: Registry = Arc::default();
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!("接続の受け付けに失敗: {}", e);
+                continue;
+            }
+        };
+        // ほかのユーザーの接続は断る（キー入力やセッションを渡さない）
+        if let Err(e) = private_socket::check_peer(&stream) {
+            log::warn!("セッションデーモンへの接続を断りました: {:#}", e);
+            continue;
+        }
+        if let Err(e) = stream.set_write_timeout(Some(CLIENT_WRITE_TIMEOUT)) {
+            log::warn!("接続の書き込みの待ち時間を設定できません: {}", e);
+            continue;
+        }
+        let registry = registry.clone();
+        std::thread::Builder::new()
+            .name("session-client".into())
+            .spawn(move || {
+                if let Err(e) = handle_client(stream, &registry) {
+                    log::debug!("クライアントとの通信が終了: {}", e);
+                }
+            })?;
+    }
+    Ok(())
+}
+
+/// 1つの接続を処理（最初の要求でセッションを決め、以後は入力を流す）
+fn handle_client(mut stream: UnixStream, registry: &Registry) -> Result<()> {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    static NEXT_CLIENT: AtomicU64 = AtomicU64::new(1);
+
+    let (id, session) = match read_request(&mut stream)? {
+        Request::List => {
+            // 一覧のロックを持ったまま各セッションのロックを待たない
+            let sessions: Vec<(u64, Arc<Session>)> =
+                registry.lock().iter().map(|(id, session)| (*id, session.clone())).collect();
+            let detached = sessions
+                .into_iter()
+                .filter(|(_, session)| session.io.lock().client.is_none())
+                .map(|(id, _)| id)
+                .collect();
+            write_frame(&mut stream, Reply::Sessions(detached).encode())?;
+            return Ok(());
+        }
+        Request::Running(id) => {
+            let session = registry.lock().get(&id).cloned();
+            let running = session.and_then(|session| session.pty.lock().running_command());
+            write_frame(&mut stream, Reply::Running(running).encode())?;
+            return Ok(());
+        }
+        Request::Spawn { cols, rows, command, cwd } => {
+            let pty = match Pty::spawn(cols, rows, None, &command, cwd.as_deref(), &[]) {
+                Ok(pty) => pty,
+                Err(e) => {
+                    write_frame(&mut stream, Reply::Error(e.to_string()).encode())?;
+                    return Err(e);
+                }
+            };
+            let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+            let session = Arc::new(Session { pty: Mutex::new(pty), io: Mutex::default() });
+            registry.lock().insert(id, session.clone());
+            spawn_pump(id, session.clone(), registry.clone())?;
+            (id, session)
+        }
+        Request::Attach { session: id, cols, rows } => {
+            let Some(session) = registry.lock().get(&id).cloned() else {
+                write_frame(&mut stream, Reply::Error(format!("セッション {} はありません", id)).encode())?;
+                return Ok(());
+            };
+            let _ = session.pty.lock().resize(cols, rows);
+            (id, session)
+        }
+        request => bail!("アタッチ前の要求が不正です: {:?}", request),
+    };
+
+    // アタッチ：直近の出力を送り直してから、以後の出力をこの接続に流す
+    // 書き込みのロックを先に持っておくと、出力スレッドは送り直しが終わるまで待つ（順番が入れ替わらない）
+    let client = Client {
+        id: NEXT_CLIENT.fetch_add(1, Ordering::Relaxed),
+        stream: Arc::new(stream.try_clone()?),
+        writing: Arc::default(),
+    };
+    {
+        let writing = client.writing.clone();
+        let _writing = writing.lock();
+        let replay: Vec<u8> = {
+            let mut io = session.io.lock();
+            // 別のGUIがアタッチ中なら奪う
+            if let Some(old) = io.client.replace(client.clone()) {
+                let _ = old.stream.shutdown(std::net::Shutdown::Both);
+            }
+            io.replay.iter().copied().collect()
+        };
+        write_frame(&mut stream, Reply::Attached(id).encode())?;
+        if !replay.is_empty() {
+            write_frame(&mut stream, Reply::Output(replay).encode())?;
+        }
+    }
+    log::info!("セッション {} にアタッチ", id);
+
+    loop {
+        match read_request(&mut stream) {
+            Ok(Request::Input(data)) => session.pty.lock().write(&data)?,
+            Ok(Request::Resize { cols, rows }) => session.pty.lock().resize(cols, rows)?,
+            Ok(Request::Kill) => {
+                // シェルが終了すると出力スレッドが後片付けをする
+                log::info!("セッション {} を終了", id);
+                session.pty.lock().terminate();
+                return Ok(());
+            }
+            Ok(request) => log::warn!("アタッチ中の要求が不正です: {:?}", request),
+            Err(_) => break,
+        }
+    }
+
+    // 接続が切れたらデタッチ
+    detach(&session, client.id);
+    log::info!("セッション {} からデタッチ", id);
+    Ok(())
+}
+
+/// クライアントをデタッチ（まだそのクライアントがアタッチ中の場合のみ）
+fn detach(session: &Session, client: u64) {
+    let mut io = session.io.lock();
+    if io.client.as_ref().is_some_and(|current| current.id == client) {
+        io.client = None;
+    }
+}
+
+/// シェルの出力をアタッチ中のクライアントへ流すスレッド
+fn spawn_pump(id: u64, session: Arc<Session>, registry: Registry) -> Result<()> {
+    let output = session.pty.lock().output_receiver();
+    std::thread::Builder::new()
+        .name("session-pump".into())
+        .spawn(move || {
+            for chunk in output.iter() {
+                let client = {
+                    let mut io = session.io.lock();
+                    io.replay.extend(&chunk);
+                    let excess = io.replay.len().saturating_sub(REPLAY_LIMIT);
+                    io.replay.drain(..excess);
+                    io.client.clone()
+                };
+                // 書き込みはセッションのロックの外で（止まったGUIで一覧やアタッチを待たせない）
+                if let Some(client) = client {
+                    if client.send(&Reply::Output(chunk)).is_err() {
+                        let _ = client.stream.shutdown(std::net::Shutdown::Both);
+                        detach(&session, client.id);
+                    }
+                }
+            }
+
+            // シェルが終了：終了コードを送ってから接続を切る
+            let exit = wait_exit(&session);
+            log::info!("セッション {} のシェルが終了: {:?}", id, exit);
+            let client = session.io.lock().client.take();
+            if let Some(client) = client {
+                let _ = client.send(&Reply::Exited(exit.and_then(|e| e.code)));
+                let _ = client.stream.shutdown(std::net::Shutdown::Both);
+            }
+            registry.lock().remove(&id);
+            exit_if_empty(&registry);
+        })?;
+    Ok(())
+}
+
+/// 出力が閉じたあと、待機スレッドが終了コードを記録するのを少しだけ待つ
+fn wait_exit(session: &Session) -> Option<ChildExit> {
+    for _ in 0..EXIT_WAIT_RETRIES {
+        if let Some(exit) = session.pty.lock().exit() {
+            return Some(exit);
+        }
+        std::thread::sleep(EXIT_WAIT_INTERVAL);
+    }
+    None
+}
+
+/// セッションがなくなったらデーモンを終了
+fn exit_if_empty(registry: &Registry) {
+    if registry.lock().is_empty() {
+        log::info!("セッションがなくなったのでデーモンを終了します");
+        fs::remove_file(socket_path()).ok();
+        std::process::exit(0);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// クライアント（GUI側）
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// デーモン上のセッションへの接続（Pty が持つ）
+pub struct RemoteSession {
+    /// セッションの番号
+    id: u64,
+    /// 要求を送る接続（読み取りスレッドとは複製を共有）
+    stream: Arc<Mutex<UnixStream>>,
+}
+
+impl RemoteSession {
+    /// シェル以外に実行中のコマンド（別の接続でデーモンに尋ねる、答えがなければ None）
+    pub fn running_command(&self) -> Option<String> {
+        let mut stream = private_socket::connect(&socket_path()).ok()?;
+        stream.set_read_timeout(Some(QUERY_TIMEOUT)).ok()?;
+        write_frame(&mut stream, Request::Running(self.id).encode().ok()?).ok()?;
+        match read_reply(&mut stream) {
+            Ok(Reply::Running(name)) => name,
+            _ => None,
+        }
+    }
+
+    /// サイズ変更を送る
+    pub fn resize(&self, cols: u16, rows: u16) -> Result<()> {
+        write_frame(&mut *self.stream.lock(), Request::Resize { cols, rows }.encode()?)
+            .context("セッションのリサイズに失敗")
+    }
+
+    /// セッションを終了させる（デタッチではなくシェルごと閉じる）
+    pub fn kill(&self) {
+        let _ = Request::Kill.encode().and_then(|frame| write_frame(&mut *self.stream.lock(), frame));
+    }
+}
+
+impl Drop for RemoteSession {
+    fn drop(&mut self) {
+        // 読み取りスレッドの複製も含めて接続を閉じる（デーモン側ではデタッチになる）
+        let _ = self.stream.lock().shutdown(std::net::Shutdown::Both);
+    }
+}
+
+/// デーモンに接続（起動していなければ起動する）
+fn connect() -> Result<UnixStream> {
+    let path = socket_path();
+    if let Ok(stream) = private_socket::connect(&path) {
+        return Ok(stream);
+    }
+
+    // GUIと一緒に終了しないよう、別のプロセスグループで起動
+    let exe = std::env::current_exe().context("実行ファイルのパスを取得できません")?;
+    // 設定ファイル（--config）もデーモンに引き継ぐ
+    let mut command = Command::new(exe);
+    command.arg(DAEMON_ARG);
+    if let Some(path) = config::path() {
+        command.arg("--config").arg(path);
+    }
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .context("セッションデーモンの起動に失敗")?;
+
+    for _ in 0..CONNECT_RETRIES {
+        std::thread::sleep(CONNECT_INTERVAL);
+        if let Ok(stream) = private_socket::connect(&path) {
+            return Ok(stream);
+        }
+    }
+    bail!("セッションデーモンに接続できません: {}", path.display())
+}
+
+/// デタッチ中のセッションの一覧（デーモンが起動していなければ空）
+pub fn detached_sessions() -> Vec<u64> {
+    let Ok(mut stream) = private_socket::connect(&socket_path()) else {
+        return Vec::new();
+    };
+    let result = Request::List
+        .encode()
+        .and_then(|frame| write_frame(&mut stream, frame))
+        .and_then(|_| read_reply(&mut stream));
+    match result {
+        Ok(Reply::Sessions(ids)) => ids,
+        _ => Vec::new(),
+    }
+}
+
+/// デーモン上に新しいセッションを作ってPTYとして開く
+pub fn spawn(cols: u16, rows: u16, command: &PaneCommand, cwd: Option<&Path>) -> Result<Pty> {
+    open(Request::Spawn { cols, rows, command: command.clone(), cwd: cwd.map(Path::to_path_buf) }, cols, rows)
+}
+
+/// デタッチ中のセッションにアタッチしてPTYとして開く
+pub fn attach(session: u64, cols: u16, rows: u16) -> Result<Pty> {
+    open(Request::Attach { session, cols, rows }, cols, rows)
+}
+
+/// 要求を送ってアタッチし、入出力をチャネルにつなぐ
+fn open(request: Request, cols: u16, rows: u16) -> Result<Pty> {
+    let mut stream = connect()?;
+    write_frame(&mut stream, request.encode()?)?;
+    let id = match read_reply(&mut stream)? {
+        Reply::Attached(id) => id,
+        Reply::Error(message) => bail!("セッションを開けません: {}", message),
+        reply => bail!("デーモンの応答が不正です: {:?}", reply),
+    };
+    log::info!("セッション {} を開きました", id);
+
+    let (output_tx, output_rx) = bounded::<Vec<u8>>(256);
+    let (input_tx, input_rx) = bounded::<Vec<u8>>(256);
+
+    // 読み取りスレッド（接続が切れたら出力チャネルを閉じ、終了を記録する）
+    let mut reader = stream.try_clone()?;
+    let exit = ExitSlot::default();
+    let exit_writer = exit.clone();
+    std::thread::Builder::new()
+        .name("session-reader".into())
+        .spawn(move || {
+            let code = loop {
+                match read_reply(&mut reader) {
+                    Ok(Reply::Output(data)) => {
+                        if output_tx.send(data).is_err() {
+                            break None;
+                        }
+                    }
+                    Ok(Reply::Exited(code)) => break code,
+                    Ok(reply) => log::warn!("デーモンの応答が不正です: {:?}", reply),
+                    Err(_) => break None,
+                }
+            };
+            let _ = exit_writer.set(ChildExit { code });
+            crate::idle::notify_output();
+        })?;
+
+    // 書き込みスレッド
+    let stream = Arc::new(Mutex::new(stream));
+    let writer = stream.clone();
+    std::thread::Builder::new()
+        .name("session-writer".into())
+        .spawn(move || {
+            while let Ok(data) = input_rx.recv() {
+                let sent = Request::Input(data).encode().and_then(|frame| write_frame(&mut *writer.lock(), frame));
+                if sent.is_err() {
+                    break;
+                }
+            }
+        })?;
+
+    Ok(Pty::remote(RemoteSession { id, stream }, output_rx, input_tx, exit, cols, rows))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_roundtrip() {
+        let requests = [
+            Request::Spawn { cols: 80, rows: 24, command: PaneCommand::Shell, cwd: None },
+            Request::Spawn { cols: 80, rows: 24, command: PaneCommand::Run("ssh host".into()), cwd: None },
+            Request::Spawn { cols: 80, rows: 24, command: PaneCommand::Profile("prod-ssh".into()), cwd: None },
+            Request::Spawn { cols: 80, rows: 24, command: PaneCommand::Container("web".into()), cwd: None },
+            Request::Spawn { cols: 80, rows: 24, command: PaneCommand::Run("htop".into()), cwd: Some("/tmp".into()) },
+            Request::Attach { session: 7, cols: 120, rows: 40 },
+            Request::List,
+            Request::Input(b"ls\r".to_vec()),
+            Request::Resize { cols: 100, rows: 30 },
+            Request::Kill,
+            Request::Running(7),
+        ];
+        for request in requests {
+            let mut buf = Vec::new();
+            write_frame(&mut buf, request.encode().unwrap()).unwrap();
+            assert_eq!(read_request(&mut buf.as_slice()).unwrap(), request);
+        }
+    }
+
+    #[test]
+    fn test_reply_roundtrip() {
+        let replies = [
+            Reply::Attached(3),
+            Reply::Sessions(vec![1, 2, 5]),
+            Reply::Sessions(Vec::new()),
+            Reply::Output(b"\x1b[31mhello".to_vec()),
+            Reply::Error("失敗".into()),
+            Reply::Exited(Some(127)),
+            Reply::Exited(None),
+            Reply::Running(Some("vim".into())),
+            Reply::Running(None),
+        ];
+        for reply in replies {
+            let mut buf = Vec::new();
+            write_frame(&mut buf, reply.encode()).unwrap();
+            assert_eq!(read_reply(&mut buf.as_slice()).unwrap(), reply);
+        }
+    }
+
+    #[test]
+    fn test_invalid_frame() {
+        // 長さ0・種類不明・本体不足は読み取りエラー
+        assert!(read_frame(&mut [0u8, 0, 0, 0].as_slice()).is_err());
+        let mut buf = Vec::new();
+        write_frame(&mut buf, (99, Vec::new())).unwrap();
+        assert!(read_request(&mut buf.as_slice()).is_err());
+        let mut buf = Vec::new();
+        write_frame(&mut buf, (2, vec![1, 2])).unwrap();
+        assert!(read_request(&mut buf.as_slice()).is_err());
+
+        // u16 に収まらないフィールドは切り詰めずにエラー
+        let cwd = PathBuf::from("/".repeat(usize::from(u16::MAX) + 1));
+        assert!(Request::Spawn { cols: 80, rows: 24, command: PaneCommand::Shell, cwd: Some(cwd) }.encode().is_err());
+    }
+}
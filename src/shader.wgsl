@@ -57,7 +57,11 @@ fn vs_bg(
     let y = f32((vertex_index >> 1u) & 1u);
 
     // ピクセル座標を計算
-    let pixel_pos = (instance.position + vec2<f32>(x, y)) * uniforms.cell_size;
+    // glyph_size が指定されていればセル内の矩形（罫線・ブロック要素）、なければセル全体
+    let has_rect = instance.glyph_size.x > 0.0;
+    let size = select(uniforms.cell_size, instance.glyph_size, has_rect);
+    let offset = select(vec2<f32>(0.0, 0.0), instance.glyph_offset, has_rect);
+    let pixel_pos = instance.position * uniforms.cell_size + offset + vec2<f32>(x, y) * size;
 
     // クリップ座標に変換（-1〜1の範囲）
     let clip_pos = (pixel_pos / uniforms.screen_size) * 2.0 - 1.0;
@@ -96,13 +100,14 @@ fn vs_main(
     // グリフをセル内に配置（ベースラインを考慮）
     // glyph_offset.x = xmin（水平オフセット）
     // glyph_offset.y = ymin（ベースラインからの距離、負の値が多い）
-    let glyph_x = cell_pixel_pos.x + instance.glyph_offset.x + x * instance.glyph_size.x;
+    let glyph_x = cell_pixel_pos.x + instance.glyph_offset.x;
 
     // Y座標: セルの下端からベースラインを設定し、そこからyminを引く
     let baseline_y = cell_pixel_pos.y + uniforms.cell_size.y * 0.85;
-    let glyph_y = baseline_y - instance.glyph_offset.y - instance.glyph_size.y + y * instance.glyph_size.y;
+    let glyph_y = baseline_y - instance.glyph_offset.y - instance.glyph_size.y;
 
-    let adjusted_pos = vec2<f32>(glyph_x, glyph_y);
+    // 左上をピクセルの境目にそろえる（アトラスのピクセルと画面のピクセルを1対1にしてにじませない）
+    let adjusted_pos = round(vec2<f32>(glyph_x, glyph_y)) + vec2<f32>(x, y) * instance.glyph_size;
 
     // クリップ座標に変換
     let clip_pos = (adjusted_pos / uniforms.screen_size) * 2.0 - 1.0;
@@ -0,0 +1,266 @@
+//! シェル統合のモジュール
+//!
+//! bash / zsh / fish のスクリプト（OSC 7 で作業ディレクトリ、OSC 133 でプロンプト・コマンドの区切りと終了コードを送る）を
+//! 設定ディレクトリの `shell-integration/` に書き出す
+//! `umiterm shell-integration install` はシェルの設定ファイルから読み込むようにし、
+//! `[shell] integration = true`（`UMITERM_SHELL_INTEGRATION`）は設定ファイルを変えずにシェルの起動時に読み込ませる
+//!
+//! ```text
+//! umiterm shell-integration install        # $SHELL のシェル
+//! umiterm shell-integration install fish
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::config;
+use crate::editor::shell_escape;
+
+/// 書き出すスクリプト（`shell-integration/` からのパスと中身）
+const FILES: &[(&str, &str)] = &[
+    ("umiterm.bash", include_str!("shell_integration/umiterm.bash")),
+    ("umiterm.zsh", include_str!("shell_integration/umiterm.zsh")),
+    ("umiterm.fish", include_str!("shell_integration/umiterm.fish")),
+    // 起動時に読み込ませるときの入り口
+    ("bash/init.bash", include_str!("shell_integration/bash/init.bash")),
+    ("zsh/.zshenv", include_str!("shell_integration/zsh/.zshenv")),
+    ("fish/vendor_conf.d/umiterm.fish", include_str!("shell_integration/fish/vendor_conf.d/umiterm.fish")),
+];
+
+/// XDG_DATA_DIRS がないときの既定値（fish もこれを探す）
+const DEFAULT_DATA_DIRS: &str = "/usr/local/share:/usr/share";
+
+/// 統合に対応しているシェル
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    /// 名前から取得（bash / zsh / fish）
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bash" => Some(Self::Bash),
+            "zsh" => Some(Self::Zsh),
+            "fish" => Some(Self::Fish),
+            _ => None,
+        }
+    }
+
+    /// シェルのプログラムのパスから取得（`/usr/bin/zsh` や `-zsh` も）
+    pub fn from_program(program: &str) -> Option<Self> {
+        let name = Path::new(program).file_name()?.to_str()?;
+        Self::from_name(name.trim_start_matches('-'))
+    }
+
+    /// 統合のスクリプト（`shell-integration/` からのパス）
+    fn script(self) -> &'static str {
+        match self {
+            Self::Bash => "umiterm.bash",
+            Self::Zsh => "umiterm.zsh",
+            Self::Fish => "umiterm.fish",
+        }
+    }
+}
+
+/// スクリプトを書き出すディレクトリ（設定ファイルと同じ場所の `shell-integration/`）
+pub fn dir() -> Option<PathBuf> {
+    Some(config::path()?.parent()?.join("shell-integration"))
+}
+
+/// スクリプトを書き出す（中身が同じファイルは書き直さない）
+pub fn write_scripts(dir: &Path) -> Result<()> {
+    for (name, contents) in FILES {
+        let path = dir.join(name);
+        if fs::read_to_string(&path).is_ok_and(|current| current == *contents) {
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("{} を作れません", parent.display()))?;
+        }
+        fs::write(&path, contents).with_context(|| format!("{} に書き込めません", path.display()))?;
+    }
+    Ok(())
+}
+
+/// シェルの設定ファイルに足す行（UmiTerm の中でだけ読み込む）
+fn source_line(shell: Shell, script: &Path) -> String {
+    let script = shell_escape(&script.to_string_lossy());
+    match shell {
+        Shell::Bash | Shell::Zsh => format!("[ \"$TERM_PROGRAM\" = UmiTerm ] && . {}", script),
+        Shell::Fish => format!("test \"$TERM_PROGRAM\" = UmiTerm; and source {}", script),
+    }
+}
+
+/// 読み込む行を足す設定ファイル（fish は conf.d に専用のファイルを置く）
+fn rc_file(shell: Shell, home: &Path, var: impl Fn(&str) -> Option<String>) -> PathBuf {
+    match shell {
+        Shell::Bash => home.join(".bashrc"),
+        Shell::Zsh => var("ZDOTDIR").map_or_else(|| home.to_path_buf(), PathBuf::from).join(".zshrc"),
+        Shell::Fish => var("XDG_CONFIG_HOME")
+            .map_or_else(|| home.join(".config"), PathBuf::from)
+            .join("fish/conf.d/umiterm.fish"),
+    }
+}
+
+/// スクリプトを書き出し、シェルの設定ファイルから読み込むようにする
+///
+/// 戻り値: 利用者に見せる結果
+pub fn install(shell: Shell) -> Result<String> {
+    let dir = dir().context("設定ディレクトリがわかりません（HOME がありません）")?;
+    write_scripts(&dir)?;
+    let home = config::home_dir().context("HOME がありません")?;
+    let rc = rc_file(shell, &home, |name| std::env::var(name).ok().filter(|value| !value.is_empty()));
+    let line = source_line(shell, &dir.join(shell.script()));
+
+    let current = fs::read_to_string(&rc).unwrap_or_default();
+    if current.lines().any(|existing| existing.trim() == line) {
+        return Ok(format!("{} からはすでに読み込んでいます", rc.display()));
+    }
+    if let Some(parent) = rc.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("{} を作れません", parent.display()))?;
+    }
+    // 元の内容とは空行で区切る
+    let separator = match current.as_str() {
+        "" => "",
+        text if text.ends_with('\n') => "\n",
+        _ => "\n\n",
+    };
+    let contents = format!("{}{}# UmiTerm のシェル統合（作業ディレクトリとプロンプトの印）\n{}\n", current, separator, line);
+    fs::write(&rc, contents).with_context(|| format!("{} に書き込めません", rc.display()))?;
+    Ok(format!("{} に追加しました。新しいシェルから有効になります", rc.display()))
+}
+
+/// 起動時に読み込ませるための引数と環境変数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Injection {
+    /// シェルに渡す引数（bash はログインシェルの `-l` の代わり）
+    pub args: Vec<String>,
+    /// 追加する環境変数
+    pub env: Vec<(String, String)>,
+}
+
+/// シェルの設定ファイルを変えずに統合を読み込ませる
+///
+/// bash は `--init-file`、zsh は `ZDOTDIR`、fish は `XDG_DATA_DIRS` の vendor_conf.d で読み込ませ、
+/// どれもいつもの設定ファイルを読んでから統合を読み込む
+pub fn injection(shell: Shell, dir: &Path, login: bool, var: impl Fn(&str) -> Option<String>) -> Injection {
+    let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+    let mut args = Vec::new();
+    let mut env = Vec::new();
+    match shell {
+        Shell::Bash => {
+            args.extend(["--init-file".to_string(), path("bash/init.bash")]);
+            // --init-file はログインシェルでは読まれないので、ログインシェルの設定はスクリプトで読む
+            if login {
+                env.push(("UMITERM_BASH_LOGIN".into(), "1".into()));
+            }
+        }
+        Shell::Zsh => {
+            if login {
+                args.push("-l".into());
+            }
+            if let Some(zdotdir) = var("ZDOTDIR") {
+                env.push(("UMITERM_ZDOTDIR".into(), zdotdir));
+            }
+            env.push(("ZDOTDIR".into(), path("zsh")));
+        }
+        Shell::Fish => {
+            if login {
+                args.push("-l".into());
+            }
+            let data_dirs = var("XDG_DATA_DIRS");
+            let rest = data_dirs.clone().unwrap_or_else(|| DEFAULT_DATA_DIRS.into());
+            if let Some(data_dirs) = data_dirs {
+                env.push(("UMITERM_XDG_DATA_DIRS".into(), data_dirs));
+            }
+            env.push(("XDG_DATA_DIRS".into(), format!("{}:{}", path("fish"), rest)));
+        }
+    }
+    Injection { args, env }
+}
+
+/// 起動時に読み込ませるか（`[shell] integration`、`UMITERM_SHELL_INTEGRATION`）
+pub fn enabled() -> bool {
+    config::var("UMITERM_SHELL_INTEGRATION").is_some_and(|on| matches!(on.trim(), "true" | "1" | "on"))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_program() {
+        assert_eq!(Shell::from_program("/usr/bin/zsh"), Some(Shell::Zsh));
+        assert_eq!(Shell::from_program("-bash"), Some(Shell::Bash));
+        assert_eq!(Shell::from_program("/opt/homebrew/bin/fish"), Some(Shell::Fish));
+        assert_eq!(Shell::from_program("/bin/sh"), None);
+        assert_eq!(Shell::from_program("pwsh.exe"), None);
+    }
+
+    #[test]
+    fn test_source_line() {
+        let script = Path::new("/home/umi/.config/umiterm/shell-integration/umiterm.zsh");
+        assert_eq!(
+            source_line(Shell::Zsh, script),
+            "[ \"$TERM_PROGRAM\" = UmiTerm ] && . /home/umi/.config/umiterm/shell-integration/umiterm.zsh"
+        );
+        assert_eq!(
+            source_line(Shell::Fish, Path::new("/my dir/umiterm.fish")),
+            "test \"$TERM_PROGRAM\" = UmiTerm; and source '/my dir/umiterm.fish'"
+        );
+    }
+
+    #[test]
+    fn test_rc_file() {
+        let home = Path::new("/home/umi");
+        let none = |_: &str| None;
+        assert_eq!(rc_file(Shell::Bash, home, none), PathBuf::from("/home/umi/.bashrc"));
+        assert_eq!(rc_file(Shell::Zsh, home, |_| Some("/home/umi/.zsh".into())), PathBuf::from("/home/umi/.zsh/.zshrc"));
+        assert_eq!(rc_file(Shell::Fish, home, none), PathBuf::from("/home/umi/.config/fish/conf.d/umiterm.fish"));
+    }
+
+    #[test]
+    fn test_injection() {
+        let dir = Path::new("/si");
+        let none = |_: &str| None;
+
+        let bash = injection(Shell::Bash, dir, true, none);
+        assert_eq!(bash.args, ["--init-file", "/si/bash/init.bash"]);
+        assert_eq!(bash.env, [("UMITERM_BASH_LOGIN".to_string(), "1".to_string())]);
+        assert!(injection(Shell::Bash, dir, false, none).env.is_empty());
+
+        // 元の ZDOTDIR は .zshenv で戻す
+        let zsh = injection(Shell::Zsh, dir, true, |name| (name == "ZDOTDIR").then(|| "/home/umi/.zsh".into()));
+        assert_eq!(zsh.args, ["-l"]);
+        assert_eq!(
+            zsh.env,
+            [("UMITERM_ZDOTDIR".to_string(), "/home/umi/.zsh".to_string()), ("ZDOTDIR".to_string(), "/si/zsh".to_string())]
+        );
+
+        // XDG_DATA_DIRS がなければ既定値の前に足す
+        let fish = injection(Shell::Fish, dir, false, none);
+        assert!(fish.args.is_empty());
+        assert_eq!(fish.env, [("XDG_DATA_DIRS".to_string(), "/si/fish:/usr/local/share:/usr/share".to_string())]);
+    }
+
+    #[test]
+    fn test_write_scripts() {
+        let dir = std::env::temp_dir().join(format!("umiterm-shell-integration-{}", std::process::id()));
+        write_scripts(&dir).unwrap();
+        for (name, contents) in FILES {
+            assert_eq!(fs::read_to_string(dir.join(name)).unwrap(), *contents);
+        }
+        // 2回目は書き直さなくてもよい
+        write_scripts(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
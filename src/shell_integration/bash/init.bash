@@ -0,0 +1,19 @@
+# UmiTerm のシェル統合の自動読み込み（bash --init-file）
+#
+# --init-file では ~/.bashrc もログインシェルの設定も読まれないので、いつもの設定を読んでから統合を読み込む
+
+if [[ -n "${UMITERM_BASH_LOGIN-}" ]]; then
+    builtin unset UMITERM_BASH_LOGIN
+    [[ -r /etc/profile ]] && builtin source /etc/profile
+    for __umiterm_file in ~/.bash_profile ~/.bash_login ~/.profile; do
+        if [[ -r "$__umiterm_file" ]]; then
+            builtin source "$__umiterm_file"
+            break
+        fi
+    done
+    builtin unset __umiterm_file
+else
+    [[ -r ~/.bashrc ]] && builtin source ~/.bashrc
+fi
+
+builtin source "${BASH_SOURCE[0]%/*}/../umiterm.bash"
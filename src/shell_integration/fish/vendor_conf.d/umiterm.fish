@@ -0,0 +1,12 @@
+# UmiTerm のシェル統合の自動読み込み（XDG_DATA_DIRS の vendor_conf.d）
+#
+# 足した XDG_DATA_DIRS を元に戻してから統合を読み込む
+
+if set -q UMITERM_XDG_DATA_DIRS
+    set -gx XDG_DATA_DIRS (string split : -- $UMITERM_XDG_DATA_DIRS)
+    set -e UMITERM_XDG_DATA_DIRS
+else
+    set -e XDG_DATA_DIRS
+end
+
+source (status dirname)/../../umiterm.fish
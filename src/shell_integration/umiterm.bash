@@ -0,0 +1,38 @@
+# UmiTerm のシェル統合（bash）
+#
+# 作業ディレクトリ（OSC 7）と、プロンプト・コマンドの区切りと終了コード（OSC 133）を UmiTerm に知らせる
+# ~/.bashrc から読み込むか、[shell] integration = true で起動時に読み込ませる
+
+[[ $- == *i* ]] || return 0
+[[ -n "${__umiterm_loaded-}" ]] && return 0
+__umiterm_loaded=1
+
+# 作業ディレクトリ（% と空白などは URL のエスケープ）
+__umiterm_osc7() {
+    local dir="${PWD//%/%25}"
+    dir="${dir// /%20}"
+    builtin printf '\e]7;file://%s%s\a' "${HOSTNAME-}" "$dir"
+}
+
+# 前のコマンドの終了（履歴の番号が進んだときだけ）、作業ディレクトリ、プロンプトの始まり
+__umiterm_prompt() {
+    local status=$?
+    if [[ -n "${__umiterm_histcmd-}" && "$HISTCMD" != "$__umiterm_histcmd" ]]; then
+        builtin printf '\e]133;D;%s\a' "$status"
+    fi
+    __umiterm_histcmd=$HISTCMD
+    __umiterm_osc7
+    builtin printf '\e]133;A\a'
+    # プロンプトを組み立て直すツールもあるので、毎回入力の始まりの印を確かめる
+    [[ "$PS1" == *'\e]133;B'* ]] || PS1="$PS1"'\[\e]133;B\a\]'
+    return $status
+}
+
+# Enter を押したら出力の始まり（PS0 は bash 4.4 から）
+[[ "${PS0-}" == *'\e]133;C'* ]] || PS0="${PS0-}"'\e]133;C\a'
+
+if [[ "$(builtin declare -p PROMPT_COMMAND 2>/dev/null)" == "declare -a"* ]]; then
+    PROMPT_COMMAND=(__umiterm_prompt "${PROMPT_COMMAND[@]}")
+else
+    PROMPT_COMMAND="__umiterm_prompt${PROMPT_COMMAND:+; $PROMPT_COMMAND}"
+fi
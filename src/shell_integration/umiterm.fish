@@ -0,0 +1,37 @@
+# UmiTerm のシェル統合（fish）
+#
+# 作業ディレクトリ（OSC 7）と、プロンプト・コマンドの区切りと終了コード（OSC 133）を UmiTerm に知らせる
+# ~/.config/fish/conf.d から読み込むか、[shell] integration = true で起動時に読み込ませる
+
+status is-interactive; or exit 0
+set -q __umiterm_loaded; and exit 0
+set -g __umiterm_loaded 1
+
+# 作業ディレクトリ（% と空白などは URL のエスケープ）
+function __umiterm_osc7 --on-variable PWD
+    printf '\e]7;file://%s%s\a' $hostname (string replace -a ' ' '%20' -- (string replace -a '%' '%25' -- $PWD))
+end
+
+# 前のコマンドの終了
+function __umiterm_postexec --on-event fish_postexec
+    printf '\e]133;D;%s\a' $status
+end
+
+# Enter を押したら出力の始まり
+function __umiterm_preexec --on-event fish_preexec
+    printf '\e]133;C\a'
+end
+
+# プロンプトの始まり（config.fish で決めたプロンプトも包むよう、最初のプロンプトで包む）
+function __umiterm_prompt --on-event fish_prompt
+    printf '\e]133;A\a'
+    if not functions -q __umiterm_fish_prompt
+        functions -c fish_prompt __umiterm_fish_prompt
+        function fish_prompt
+            __umiterm_fish_prompt
+            printf '\e]133;B\a'
+        end
+    end
+end
+
+__umiterm_osc7
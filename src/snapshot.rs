@@ -0,0 +1,153 @@
+//! 描画用スナップショットモジュール
+//!
+//! ターミナルの表示内容をレンダラー用にコピーしておく
+//! 変更のあった行だけを短時間のロック内でコピーし、描画中はロックを保持しない
+//! （パーサースレッドが描画にブロックされない）
+
+use std::ops::Index;
+use std::sync::Arc;
+
+use crate::grid::{Cell, Palette};
+use crate::scrollback::ScrollbackUsage;
+use crate::terminal::{Cursor, Selection, Terminal};
+
+/// ターミナルの表示内容のコピー
+pub struct TerminalSnapshot {
+    /// セルの配列（行優先で格納）
+    cells: Vec<Cell>,
+    /// 列数
+    pub cols: usize,
+    /// 行数
+    pub rows: usize,
+    /// カーソル
+    pub cursor: Cursor,
+    /// テキスト選択状態
+    pub selection: Selection,
+    /// 代替スクリーンから取得したか（切り替え時は全行をコピーし直す）
+    alt_screen: bool,
+    /// スクロールバックの表示位置
+    pub display_offset: usize,
+    /// スクロールバックのメモリ使用状況
+    pub scrollback: ScrollbackUsage,
+    /// ペインだけの色（プロファイルのテーマ、None ならウィンドウの色）
+    pub palette: Option<Arc<Palette>>,
+}
+
+impl TerminalSnapshot {
+    /// 空のスナップショットを作成（最初の更新で全行コピーされる）
+    pub fn new() -> Self {
+        Self {
+            cells: Vec::new(),
+            cols: 0,
+            rows: 0,
+            cursor: Cursor::default(),
+            selection: Selection::default(),
+            alt_screen: false,
+            display_offset: 0,
+            scrollback: ScrollbackUsage::default(),
+            palette: None,
+        }
+    }
+
+    /// ターミナルから変更行をコピー
+    ///
+    /// グリッドのダーティフラグはここで消費する
+    /// 戻り値: コピーした行数
+    pub fn update(&mut self, terminal: &mut Terminal) -> usize {
+        let alt_screen = terminal.is_alt_screen();
+        let grid = terminal.active_grid();
+        let resized = grid.cols != self.cols || grid.rows != self.rows;
+        // スクロールバック表示中は行の対応がずれるため毎回すべてコピー
+        let full = resized
+            || alt_screen != self.alt_screen
+            || terminal.display_offset > 0
+            || self.display_offset > 0;
+
+        if resized {
+            self.cols = grid.cols;
+            self.rows = grid.rows;
+            self.cells.clear();
+            self.cells.resize(self.cols * self.rows, Cell::default());
+        }
+        self.alt_screen = alt_screen;
+
+        let mut copied = 0;
+        for row in 0..self.rows {
+            if full || grid.is_dirty(row) {
+                let start = row * self.cols;
+                terminal.copy_visible_row(row, &mut self.cells[start..start + self.cols]);
+                copied += 1;
+            }
+        }
+
+        // カーソルは表示位置の分だけ下にずれる
+        self.display_offset = terminal.display_offset;
+        self.cursor = terminal.cursor.clone();
+        self.cursor.row += self.display_offset;
+        if self.cursor.row >= self.rows {
+            self.cursor.visible = false;
+        }
+        self.selection = terminal.screen_selection();
+        self.scrollback = terminal.scrollback.usage();
+        terminal.active_grid_mut().clear_dirty();
+        copied
+    }
+}
+
+// インデックスアクセスを実装（snapshot[(col, row)] でアクセス可能に）
+impl Index<(usize, usize)> for TerminalSnapshot {
+    type Output = Cell;
+
+    fn index(&self, (col, row): (usize, usize)) -> &Self::Output {
+        &self.cells[row * self.cols + col]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::AnsiParser;
+
+    #[test]
+    fn test_copies_only_dirty_rows() {
+        let mut terminal = Terminal::new(10, 4);
+        let mut parser = AnsiParser::new();
+        let mut snapshot = TerminalSnapshot::new();
+
+        // 初回は全行
+        assert_eq!(snapshot.update(&mut terminal), 4);
+        assert_eq!(snapshot.update(&mut terminal), 0);
+
+        // 変更した行だけコピーされる
+        parser.process(&mut terminal, b"\x1b[3;1HZ");
+        assert_eq!(snapshot.update(&mut terminal), 1);
+        assert_eq!(snapshot[(0, 2)].character, 'Z');
+        assert_eq!((snapshot.cursor.col, snapshot.cursor.row), (1, 2));
+
+        // 代替スクリーンへの切り替えは全行コピー
+        parser.process(&mut terminal, b"\x1b[?1049h");
+        assert_eq!(snapshot.update(&mut terminal), 4);
+        assert_eq!(snapshot[(0, 2)].character, ' ');
+    }
+
+    #[test]
+    fn test_display_offset() {
+        let mut terminal = Terminal::new(10, 2);
+        let mut snapshot = TerminalSnapshot::new();
+        AnsiParser::new().process(&mut terminal, b"a\r\nb\r\nc");
+
+        // 1行さかのぼると最上段にスクロールバックの行が表示される
+        terminal.scroll_display(1);
+        snapshot.update(&mut terminal);
+        assert_eq!(snapshot[(0, 0)].character, 'a');
+        assert_eq!(snapshot[(0, 1)].character, 'b');
+        assert!(!snapshot.cursor.visible);
+        assert_eq!(snapshot.scrollback.lines, 1);
+
+        // 最下部に戻すと元の表示
+        terminal.scroll_to_bottom();
+        snapshot.update(&mut terminal);
+        assert_eq!(snapshot[(0, 1)].character, 'c');
+        assert!(snapshot.cursor.visible);
+    }
+}
@@ -0,0 +1,146 @@
+//! SSH の接続先モジュール
+//!
+//! 設定ファイルで `ssh` を書いたプロファイルと `~/.ssh/config` の `Host` を接続先にして、
+//! コマンドパレット（`ssh-launcher`）や `umiterm --ssh <名前>` から新しいタブで `ssh` を起動する
+//! プロファイルの接続先はプロファイルのテーマ・タイトル・環境変数で開く
+//!
+//! ```toml
+//! [profiles.prod]
+//! ssh = "deploy@prod.example.com"
+//! ssh_args = ["-A"]
+//! theme = "dracula"
+//! title = "PROD"
+//! ```
+
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::editor::shell_escape;
+use crate::pty::PaneCommand;
+
+/// 接続先
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshHost {
+    /// ランチャーに出す名前（プロファイルの名前か `~/.ssh/config` のホスト）
+    pub name: String,
+    /// 接続先（`user@host` や `ssh://user@host:port`）
+    pub destination: String,
+}
+
+/// ssh を起動するコマンドライン
+pub fn command_line(destination: &str, args: &[String]) -> String {
+    std::iter::once("ssh")
+        .chain(args.iter().map(String::as_str))
+        .chain(["--", destination])
+        .map(shell_escape)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 名前の接続先を開くコマンド（ssh を書いたプロファイルならそのプロファイル、なければ名前に ssh で接続）
+pub fn launch(config: &Config, name: &str) -> PaneCommand {
+    match config.profiles.get(name) {
+        Some(profile) if profile.ssh.is_some() => PaneCommand::Profile(name.to_string()),
+        _ => PaneCommand::Run(command_line(name, &[])),
+    }
+}
+
+/// ランチャーに出す接続先（プロファイル、`~/.ssh/config` の順、プロファイルと同じ接続先は除く）
+pub fn hosts(config: &Config) -> Vec<SshHost> {
+    let mut hosts: Vec<SshHost> = config
+        .profiles
+        .iter()
+        .filter_map(|(name, profile)| {
+            let destination = profile.ssh.clone()?;
+            Some(SshHost { name: name.clone(), destination })
+        })
+        .collect();
+    if config.ssh.read_config() {
+        for host in config_path().and_then(|path| std::fs::read_to_string(path).ok()).as_deref().map_or_else(Vec::new, config_hosts) {
+            if !hosts.iter().any(|h| h.name == host || h.destination == host) {
+                hosts.push(SshHost { name: host.clone(), destination: host });
+            }
+        }
+    }
+    hosts
+}
+
+/// `~/.ssh/config`
+fn config_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".ssh").join("config"))
+}
+
+/// ssh の設定の `Host` に書いた名前（`*` などのパターンと `!` の否定は除く、`Include` は読まない）
+pub fn config_hosts(text: &str) -> Vec<String> {
+    let mut hosts: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        // キーワードと値は空白か `=` で区切る（キーワードは大文字小文字を区別しない）
+        let Some((keyword, value)) = line.split_once(|c: char| c.is_whitespace() || c == '=') else { continue };
+        if !keyword.eq_ignore_ascii_case("host") {
+            continue;
+        }
+        let names = value.trim_start_matches(|c: char| c.is_whitespace() || c == '=').split_whitespace();
+        for name in names.map(|name| name.trim_matches('"')) {
+            if !name.is_empty() && !name.contains(['*', '?', '!']) && !hosts.iter().any(|h| h == name) {
+                hosts.push(name.to_string());
+            }
+        }
+    }
+    hosts
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_hosts() {
+        let text = "\
+# 踏み台
+Host bastion
+    HostName 10.0.0.1
+    User ec2-user
+
+Host web1 web2 \"db\"
+host=staging
+Host *.internal !secret
+Host *
+    ServerAliveInterval 60
+Match host prod
+HostName ignored
+Host web1
+";
+        assert_eq!(config_hosts(text), ["bastion", "web1", "web2", "db", "staging"]);
+    }
+
+    #[test]
+    fn test_command_line() {
+        assert_eq!(command_line("prod", &[]), "ssh -- prod");
+        assert_eq!(
+            command_line("ssh://deploy@prod:2222", &["-A".into(), "-o".into(), "SendEnv=LANG LC_*".into()]),
+            "ssh -A -o 'SendEnv=LANG LC_*' -- ssh://deploy@prod:2222"
+        );
+    }
+
+    #[test]
+    fn test_launch() {
+        let config = Config::parse(
+            "[ssh]\nread_config = false\n\n\
+             [profiles.prod]\nssh = \"deploy@prod.example.com\"\ntitle = \"PROD\"\n\n\
+             [profiles.local]\ncommand = \"htop\"",
+        )
+        .unwrap();
+        assert_eq!(launch(&config, "prod"), PaneCommand::Profile("prod".into()));
+        assert_eq!(launch(&config, "local"), PaneCommand::Run("ssh -- local".into()));
+        assert_eq!(launch(&config, "web1"), PaneCommand::Run("ssh -- web1".into()));
+        assert_eq!(
+            hosts(&config),
+            [SshHost { name: "prod".into(), destination: "deploy@prod.example.com".into() }]
+        );
+    }
+}
@@ -0,0 +1,364 @@
+//! ステータスバーモジュール
+//!
+//! ウィンドウ最下段に表示するステータスバーと、その中身（セグメント）
+//! セグメントは StatusSegment トレイトを実装すれば追加できる（プラグインの `umiterm.add_status_segment` も）
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use crate::config;
+use crate::pane;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 定数
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 既定のセグメント（`UMITERM_STATUS_BAR=on` のとき）
+const DEFAULT_SEGMENTS: &str = "cwd,git,pane,host,time";
+
+/// 組み込みのセグメントの名前
+const SEGMENT_NAMES: &[&str] = &["cwd", "git", "time", "pane", "host"];
+
+/// 表示内容を作り直す間隔（時刻・ブランチの更新）
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Gitブランチを調べ直す間隔
+const GIT_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+// ═══════════════════════════════════════════════════════════════════════════
+// セグメント
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// セグメントの表示位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+/// セグメントに渡す情報（フォーカス中のペイン）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusContext {
+    /// 作業ディレクトリ（OSC 7）
+    pub cwd: PathBuf,
+    /// タブ内でのペインの番号（0始まり）
+    pub pane_index: usize,
+    /// タブ内のペイン数
+    pub pane_count: usize,
+}
+
+/// ステータスバーの1区画
+pub trait StatusSegment {
+    /// 表示位置
+    fn align(&self) -> Align {
+        Align::Left
+    }
+
+    /// 表示する文字列（None なら表示しない）
+    fn text(&mut self, ctx: &StatusContext) -> Option<String>;
+}
+
+/// 作業ディレクトリ
+struct CwdSegment;
+
+impl StatusSegment for CwdSegment {
+    fn text(&mut self, ctx: &StatusContext) -> Option<String> {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        Some(pane::shorten_home(&ctx.cwd, home.as_deref()))
+    }
+}
+
+/// Gitブランチ（リポジトリの外では表示しない）
+#[derive(Default)]
+struct GitBranchSegment {
+    /// 前回調べたディレクトリ・時刻・結果
+    cache: Option<(PathBuf, Instant, Option<String>)>,
+}
+
+impl StatusSegment for GitBranchSegment {
+    fn text(&mut self, ctx: &StatusContext) -> Option<String> {
+        let now = Instant::now();
+        if let Some((cwd, checked, branch)) = &self.cache {
+            if *cwd == ctx.cwd && now - *checked < GIT_REFRESH_INTERVAL {
+                return branch.clone();
+            }
+        }
+        let branch = git_branch(&ctx.cwd).map(|b| format!("⎇ {}", b));
+        self.cache = Some((ctx.cwd.clone(), now, branch.clone()));
+        branch
+    }
+}
+
+/// 時刻
+struct TimeSegment;
+
+impl StatusSegment for TimeSegment {
+    fn align(&self) -> Align {
+        Align::Right
+    }
+
+    fn text(&mut self, _ctx: &StatusContext) -> Option<String> {
+        Some(chrono::Local::now().format("%H:%M").to_string())
+    }
+}
+
+/// ペインの番号（2/3 のように表示、ペインが1つなら表示しない）
+struct PaneIndexSegment;
+
+impl StatusSegment for PaneIndexSegment {
+    fn align(&self) -> Align {
+        Align::Right
+    }
+
+    fn text(&mut self, ctx: &StatusContext) -> Option<String> {
+        (ctx.pane_count > 1).then(|| format!("{}/{}", ctx.pane_index + 1, ctx.pane_count))
+    }
+}
+
+/// ホスト名（起動時に1回だけ調べる）
+struct HostnameSegment {
+    name: Option<String>,
+}
+
+impl StatusSegment for HostnameSegment {
+    fn align(&self) -> Align {
+        Align::Right
+    }
+
+    fn text(&mut self, _ctx: &StatusContext) -> Option<String> {
+        self.name.clone()
+    }
+}
+
+/// 名前から組み込みのセグメントを作る
+fn builtin_segment(name: &str) -> Option<Box<dyn StatusSegment>> {
+    match name {
+        "cwd" => Some(Box::new(CwdSegment)),
+        "git" => Some(Box::new(GitBranchSegment::default())),
+        "time" => Some(Box::new(TimeSegment)),
+        "pane" => Some(Box::new(PaneIndexSegment)),
+        "host" => Some(Box::new(HostnameSegment { name: hostname() })),
+        _ => None,
+    }
+}
+
+/// `UMITERM_STATUS_BAR` の値が正しいか（設定ファイルを読むときに確かめる）
+pub fn check_names(value: &str) -> anyhow::Result<()> {
+    if matches!(value.trim(), "" | "off" | "0" | "on" | "1") {
+        return Ok(());
+    }
+    for name in value.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        anyhow::ensure!(
+            SEGMENT_NAMES.contains(&name),
+            "不明なステータスバーのセグメント: {}（{}）",
+            name,
+            SEGMENT_NAMES.join(" / ")
+        );
+    }
+    Ok(())
+}
+
+/// ディレクトリを含むGitリポジトリの現在のブランチ（detached HEAD なら短いハッシュ）
+fn git_branch(dir: &Path) -> Option<String> {
+    let dot_git = dir.ancestors().map(|d| d.join(".git")).find(|p| p.exists())?;
+
+    // ワークツリー・サブモジュールでは .git がファイルで、中に実体の場所が書いてある
+    let git_dir = if dot_git.is_file() {
+        let content = fs::read_to_string(&dot_git).ok()?;
+        let target = content.trim().strip_prefix("gitdir:")?.trim();
+        dot_git.parent()?.join(target)
+    } else {
+        dot_git
+    };
+
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    parse_head(&head)
+}
+
+/// HEAD ファイルの中身からブランチ名を取り出す
+fn parse_head(head: &str) -> Option<String> {
+    let head = head.trim();
+    match head.strip_prefix("ref:") {
+        Some(reference) => {
+            let reference = reference.trim();
+            Some(reference.strip_prefix("refs/heads/").unwrap_or(reference).to_string())
+        }
+        None => head.get(..7).map(str::to_string),
+    }
+}
+
+/// ホスト名（ドメイン部分は除く）
+fn hostname() -> Option<String> {
+    let name = std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| {
+            let output = Command::new("hostname").output().ok()?;
+            String::from_utf8(output.stdout).ok()
+        })?;
+    let name = name.trim().split('.').next().unwrap_or_default();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// ステータスバー
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// ステータスバーに表示する内容（レンダラーに渡す）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StatusLine {
+    /// 左寄せのセグメント
+    pub left: Vec<String>,
+    /// 右寄せのセグメント
+    pub right: Vec<String>,
+}
+
+/// ウィンドウのステータスバー
+pub struct StatusBar {
+    /// 左から順に並べるセグメント
+    segments: Vec<Box<dyn StatusSegment>>,
+    /// 現在の表示内容
+    line: StatusLine,
+    /// 前回作り直したときの情報と時刻
+    last_refresh: Option<(StatusContext, Instant)>,
+}
+
+impl StatusBar {
+    /// 環境変数 `UMITERM_STATUS_BAR` から作成（未設定・off なら表示しない）
+    ///
+    /// on で既定のセグメント、またはカンマ区切りで cwd / git / time / pane / host を指定
+    pub fn from_env() -> Option<Self> {
+        let value = config::var("UMITERM_STATUS_BAR")?;
+        match value.trim() {
+            "" | "off" | "0" => None,
+            "on" | "1" => Some(Self::from_names(DEFAULT_SEGMENTS)),
+            names => Some(Self::from_names(names)),
+        }
+    }
+
+    /// カンマ区切りのセグメント名から作成（不明な名前は警告して無視）
+    pub fn from_names(names: &str) -> Self {
+        let segments = names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .filter_map(|name| {
+                let segment = builtin_segment(name);
+                if segment.is_none() {
+                    log::warn!("不明なステータスバーのセグメント: {}", name);
+                }
+                segment
+            })
+            .collect();
+        Self {
+            segments,
+            line: StatusLine::default(),
+            last_refresh: None,
+        }
+    }
+
+    /// セグメントを追加（プラグイン用）
+    pub fn add_segment(&mut self, segment: Box<dyn StatusSegment>) {
+        self.segments.push(segment);
+        self.last_refresh = None;
+    }
+
+    /// 現在の表示内容
+    pub fn line(&self) -> &StatusLine {
+        &self.line
+    }
+
+    /// 表示内容を作り直す（情報が変わったか、一定時間たったときだけ）
+    /// 戻り値: 表示が変わったか
+    pub fn refresh(&mut self, ctx: StatusContext, now: Instant) -> bool {
+        if let Some((last_ctx, last_time)) = &self.last_refresh {
+            if *last_ctx == ctx && now.saturating_duration_since(*last_time) < REFRESH_INTERVAL {
+                return false;
+            }
+        }
+
+        let mut line = StatusLine::default();
+        for segment in &mut self.segments {
+            if let Some(text) = segment.text(&ctx) {
+                match segment.align() {
+                    Align::Left => line.left.push(text),
+                    Align::Right => line.right.push(text),
+                }
+            }
+        }
+        self.last_refresh = Some((ctx, now));
+
+        let changed = line != self.line;
+        self.line = line;
+        changed
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 呼ばれた回数を表示するセグメント
+    struct CounterSegment(usize);
+
+    impl StatusSegment for CounterSegment {
+        fn text(&mut self, _ctx: &StatusContext) -> Option<String> {
+            self.0 += 1;
+            Some(self.0.to_string())
+        }
+    }
+
+    fn context(pane_index: usize) -> StatusContext {
+        StatusContext {
+            cwd: PathBuf::from("/"),
+            pane_index,
+            pane_count: 2,
+        }
+    }
+
+    #[test]
+    fn test_refresh() {
+        let mut bar = StatusBar::from_names("pane, unknown");
+        bar.add_segment(Box::new(CounterSegment(0)));
+        let start = Instant::now();
+
+        assert!(bar.refresh(context(0), start));
+        assert_eq!(bar.line().left, vec!["1"]);
+        assert_eq!(bar.line().right, vec!["1/2"]);
+
+        // 情報が同じなら一定時間は作り直さない
+        assert!(!bar.refresh(context(0), start + Duration::from_millis(500)));
+        assert_eq!(bar.line().left, vec!["1"]);
+
+        // ペインが変わればすぐ作り直す
+        assert!(bar.refresh(context(1), start + Duration::from_millis(600)));
+        assert_eq!(bar.line().right, vec!["2/2"]);
+    }
+
+    #[test]
+    fn test_parse_head() {
+        assert_eq!(parse_head("ref: refs/heads/main\n"), Some("main".to_string()));
+        assert_eq!(parse_head("ref: refs/heads/feature/x"), Some("feature/x".to_string()));
+        assert_eq!(parse_head("0123456789abcdef\n"), Some("0123456".to_string()));
+    }
+
+    #[test]
+    fn test_git_branch() {
+        let root = std::env::temp_dir().join(format!("umiterm-status-{}", std::process::id()));
+        let nested = root.join("src/deep");
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(".git/HEAD"), "ref: refs/heads/topic\n").unwrap();
+
+        // 親ディレクトリをたどってリポジトリを見つける
+        assert_eq!(git_branch(&nested), Some("topic".to_string()));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
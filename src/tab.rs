@@ -0,0 +1,197 @@
+//! タブモジュール
+//!
+//! ウィンドウ内のタブ（ペインレイアウトのまとまり）と、
+//! 画面上部のタブバーの配置・クリック判定
+
+use crate::pane::{FinishedCommand, PaneId, PaneLayout};
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 定数
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 1つのタブの最大幅（列）
+const MAX_TAB_WIDTH: usize = 24;
+
+/// 1つのタブの最小幅（列）
+/// 番号・バッジ・閉じるボタン・区切りが収まる幅
+const MIN_TAB_WIDTH: usize = 8;
+
+// ═══════════════════════════════════════════════════════════════════════════
+// タブ
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// ウィンドウ内の1つのタブ
+pub struct Tab {
+    /// ペインレイアウト
+    pub layout: PaneLayout,
+    /// フォーカス中のペインID
+    pub focused_pane: PaneId,
+    /// 非表示中に出力があったか
+    pub activity: bool,
+    /// 非表示中にベルが鳴ったか
+    pub bell: bool,
+    /// 非表示中に終了した長時間のコマンド
+    pub done: Option<FinishedCommand>,
+}
+
+impl Tab {
+    /// ペイン1つだけのタブを作成
+    pub fn new(pane_id: PaneId) -> Self {
+        Self {
+            layout: PaneLayout::single(pane_id),
+            focused_pane: pane_id,
+            activity: false,
+            bell: false,
+            done: None,
+        }
+    }
+
+    /// レイアウトからタブを作成（最初のペインにフォーカス）
+    pub fn with_layout(layout: PaneLayout) -> Self {
+        let focused_pane = layout.all_pane_ids()[0];
+        Self { layout, ..Self::new(focused_pane) }
+    }
+
+    /// 表示したのでバッジを消す
+    pub fn clear_badges(&mut self) {
+        self.activity = false;
+        self.bell = false;
+        self.done = None;
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// タブバー
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// タブバーに表示する内容（レンダラーに渡す）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TabLabel {
+    /// タイトル（フォーカス中のペインのターミナルタイトル）
+    pub title: String,
+    /// 表示中のタブか
+    pub active: bool,
+    /// 非表示中に出力があったか
+    pub activity: bool,
+    /// 非表示中にベルが鳴ったか
+    pub bell: bool,
+    /// 非表示中に長時間のコマンドが終了したか（成功なら true）
+    pub done: Option<bool>,
+}
+
+/// タブバー上の1つのタブの位置（列）
+///
+/// 右端から順に 区切り・閉じるボタン・バッジ を置き、残りをタイトルに使う
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TabSpan {
+    /// 開始列
+    pub start: usize,
+    /// 幅（区切りの1列を含む）
+    pub width: usize,
+}
+
+impl TabSpan {
+    /// タイトルに使える幅
+    pub fn text_width(&self) -> usize {
+        self.width - 3
+    }
+
+    /// バッジ（出力・ベル）の列
+    pub fn badge_col(&self) -> usize {
+        self.start + self.width - 3
+    }
+
+    /// 閉じるボタンの列
+    pub fn close_col(&self) -> usize {
+        self.start + self.width - 2
+    }
+}
+
+/// タブバー上のクリック位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabHit {
+    /// タブを選択
+    Select(usize),
+    /// タブを閉じる
+    Close(usize),
+}
+
+/// タブを左から等幅で並べる（画面に収まらないタブは表示しない）
+pub fn layout_tabs(count: usize, screen_cols: usize) -> Vec<TabSpan> {
+    if count == 0 {
+        return Vec::new();
+    }
+    let width = (screen_cols / count).clamp(MIN_TAB_WIDTH, MAX_TAB_WIDTH);
+    (0..count)
+        .map(|index| TabSpan { start: index * width, width })
+        .take_while(|span| span.start + span.width <= screen_cols)
+        .collect()
+}
+
+/// タブのタイトル部分の文字列（" 1: title"、幅を超える分は … で省略）
+pub fn label_text(index: usize, title: &str, max_cols: usize) -> String {
+    let text = format!(" {}: {}", index + 1, title);
+    if text.chars().count() <= max_cols {
+        return text;
+    }
+    let mut truncated: String = text.chars().take(max_cols.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// タブバーの列からクリックされたタブを求める
+pub fn hit_test(count: usize, screen_cols: usize, col: usize) -> Option<TabHit> {
+    layout_tabs(count, screen_cols)
+        .into_iter()
+        .enumerate()
+        // 区切りの列はどのタブにも属さない
+        .find(|(_, span)| (span.start..span.start + span.width - 1).contains(&col))
+        .map(|(index, span)| {
+            if col == span.close_col() {
+                TabHit::Close(index)
+            } else {
+                TabHit::Select(index)
+            }
+        })
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_tabs() {
+        // 幅に余裕があれば最大幅
+        let spans = layout_tabs(2, 80);
+        assert_eq!(spans, vec![TabSpan { start: 0, width: 24 }, TabSpan { start: 24, width: 24 }]);
+
+        // 狭ければ等分
+        let spans = layout_tabs(4, 40);
+        assert!(spans.iter().all(|s| s.width == 10));
+
+        // 最小幅でも収まらないタブは表示しない
+        assert_eq!(layout_tabs(10, 40).len(), 5);
+    }
+
+    #[test]
+    fn test_hit_test() {
+        // タブ1: 列0〜23（23は区切り、22は閉じるボタン）
+        assert_eq!(hit_test(2, 80, 0), Some(TabHit::Select(0)));
+        assert_eq!(hit_test(2, 80, 22), Some(TabHit::Close(0)));
+        assert_eq!(hit_test(2, 80, 23), None);
+        assert_eq!(hit_test(2, 80, 30), Some(TabHit::Select(1)));
+        // タブのない右側
+        assert_eq!(hit_test(2, 80, 60), None);
+    }
+
+    #[test]
+    fn test_label_text() {
+        assert_eq!(label_text(0, "zsh", 21), " 1: zsh");
+        assert_eq!(label_text(2, "a very long window title", 10), " 3: a ver…");
+        assert_eq!(label_text(2, "a very long window title", 10).chars().count(), 10);
+    }
+}
@@ -2,10 +2,15 @@
 //!
 //! カーソル位置、スクロール領域、モードなどの状態を管理
 
+use std::collections::VecDeque;
 use std::path::PathBuf;
+
+use regex::Regex;
 use unicode_width::UnicodeWidthChar;
 
-use crate::grid::{Cell, CellFlags, Color, Grid};
+use crate::config;
+use crate::grid::{self, Cell, CellFlags, Color, Grid};
+use crate::scrollback::{self, Limits, Scrollback};
 
 // ═══════════════════════════════════════════════════════════════════════════
 // カーソル
@@ -18,9 +23,10 @@ pub struct Cursor {
     pub col: usize,
     /// 行位置（0始まり）
     pub row: usize,
-    /// カーソルの形状
-    pub shape: CursorShape,
+    /// カーソルの形状（アプリケーションが DECSCUSR で指定、None なら設定ファイルの形状）
+    pub shape: Option<CursorShape>,
     /// 点滅するかどうか
+    #[allow(dead_code)]
     pub blinking: bool,
     /// 表示するかどうか
     pub visible: bool,
@@ -31,7 +37,7 @@ impl Default for Cursor {
         Self {
             col: 0,
             row: 0,
-            shape: CursorShape::Block,
+            shape: None,
             blinking: true,
             visible: true,
         }
@@ -47,11 +53,79 @@ pub enum CursorShape {
     Beam,       // |
 }
 
+impl CursorShape {
+    /// 名前から取得（block / underline / beam）
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "block" => Some(Self::Block),
+            "underline" => Some(Self::Underline),
+            "beam" => Some(Self::Beam),
+            _ => None,
+        }
+    }
+}
+
+/// フォーカスのないペインのカーソル
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum UnfocusedCursor {
+    /// 枠だけのブロック
+    #[default]
+    Hollow,
+    /// 表示しない
+    Hidden,
+}
+
+impl UnfocusedCursor {
+    /// 名前から取得（hollow / none）
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "hollow" => Some(Self::Hollow),
+            "none" => Some(Self::Hidden),
+            _ => None,
+        }
+    }
+}
+
+/// カーソルの太さの既定値（ビーム・下線の太さ、セルの幅に対する割合）
+pub const DEFAULT_CURSOR_THICKNESS: f32 = 0.15;
+
+/// カーソルの描き方（設定ファイルの `[cursor]`）
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CursorStyle {
+    /// アプリケーションが形状を指定していないときの形状
+    pub shape: CursorShape,
+    /// ビーム・下線・枠の太さ（セルの幅に対する割合）
+    pub thickness: f32,
+    /// フォーカスのないペインのカーソル
+    pub unfocused: UnfocusedCursor,
+}
+
+impl Default for CursorStyle {
+    fn default() -> Self {
+        Self {
+            shape: CursorShape::Block,
+            thickness: DEFAULT_CURSOR_THICKNESS,
+            unfocused: UnfocusedCursor::default(),
+        }
+    }
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 // テキスト選択
 // ═══════════════════════════════════════════════════════════════════════════
 
+/// 単語の区切りとみなす文字の既定値（空白は常に区切り）
+pub const DEFAULT_WORD_SEPARATORS: &str = ",│`|:\"'()[]{}<>";
+
+/// 単語の区切りとみなす文字（環境変数 `UMITERM_WORD_SEPARATORS` で変更可能）
+pub fn word_separators() -> String {
+    config::var("UMITERM_WORD_SEPARATORS").unwrap_or_else(|| DEFAULT_WORD_SEPARATORS.to_string())
+}
+
 /// テキスト選択の状態
+///
+/// 行は history_cells の番号（スクロールしても選択した文字についていく）。
+/// 描画用のスナップショットには screen_selection で画面の行に直して渡す
 #[derive(Clone, Debug, Default)]
 pub struct Selection {
     /// 選択開始位置（アンカー）
@@ -60,6 +134,8 @@ pub struct Selection {
     pub end: Option<(usize, usize)>,   // (col, row)
     /// 選択中かどうか
     pub active: bool,
+    /// 矩形選択（各行で開始と終了の列の間だけを選ぶ）
+    pub block: bool,
 }
 
 impl Selection {
@@ -68,6 +144,7 @@ impl Selection {
         self.start = None;
         self.end = None;
         self.active = false;
+        self.block = false;
     }
 
     /// 選択を開始
@@ -75,6 +152,7 @@ impl Selection {
         self.start = Some((col, row));
         self.end = Some((col, row));
         self.active = true;
+        self.block = false;
     }
 
     /// 選択を拡張
@@ -89,18 +167,33 @@ impl Selection {
         self.active = false;
     }
 
+    /// 範囲を選択（ドラッグなし）
+    pub fn select(&mut self, start: (usize, usize), end: (usize, usize)) {
+        self.start = Some(start);
+        self.end = Some(end);
+        self.active = false;
+        self.block = false;
+    }
+
+    /// 開始と終了を正規化（行順、同じ行なら列順）
+    pub fn ordered(&self) -> Option<((usize, usize), (usize, usize))> {
+        match (self.start, self.end) {
+            (Some(s), Some(e)) if s.1 < e.1 || (s.1 == e.1 && s.0 <= e.0) => Some((s, e)),
+            (Some(s), Some(e)) => Some((e, s)),
+            _ => None,
+        }
+    }
+
+    /// 矩形選択の列の範囲（左端, 右端）
+    pub fn block_columns(&self) -> Option<(usize, usize)> {
+        let (s, e) = (self.start?, self.end?);
+        Some((s.0.min(e.0), s.0.max(e.0)))
+    }
+
     /// 指定位置が選択範囲内かどうか
     pub fn contains(&self, col: usize, row: usize) -> bool {
-        let (start, end) = match (self.start, self.end) {
-            (Some(s), Some(e)) => {
-                // 開始と終了を正規化（行順、同じ行なら列順）
-                if s.1 < e.1 || (s.1 == e.1 && s.0 <= e.0) {
-                    (s, e)
-                } else {
-                    (e, s)
-                }
-            }
-            _ => return false,
+        let Some((start, end)) = self.ordered() else {
+            return false;
         };
 
         // 行が範囲内かチェック
@@ -108,6 +201,11 @@ impl Selection {
             return false;
         }
 
+        // 矩形選択: どの行も同じ列の範囲
+        if self.block {
+            return self.block_columns().is_some_and(|(left, right)| (left..=right).contains(&col));
+        }
+
         // 単一行選択
         if start.1 == end.1 {
             return col >= start.0 && col <= end.0;
@@ -185,10 +283,60 @@ pub struct Terminal {
     pub title: String,
     /// 現在の作業ディレクトリ（OSC 7から取得）
     pub cwd: PathBuf,
+    /// シェルが動いている WSL のディストリビューション（OSC 7 の Linux のパスを Windows のパスにする）
+    pub wsl_distro: Option<String>,
     /// テキスト選択状態
     pub selection: Selection,
     /// PTYへの応答バッファ（DSR等の応答用）
     pub response_buffer: Vec<u8>,
+    /// スクロールバック（メイン画面から押し出された行）
+    pub scrollback: Scrollback,
+    /// 表示位置（スクロールバックを何行さかのぼっているか、0 で最下部）
+    pub display_offset: usize,
+    /// さかのぼって表示している間に届いた行数
+    unseen_lines: usize,
+    /// ベル（BEL）が鳴ったか（取り出すまで保持）
+    bell: bool,
+    /// シェル統合のマーク（取り出すまで保持）
+    shell_marks: Vec<ShellMark>,
+    /// メイン画面で受け取ったシェル統合のマークと、その行の通し番号（プロンプト間の移動用）
+    mark_lines: VecDeque<(u64, ShellMark)>,
+    /// トリガーで印を付けた行の通し番号（プロンプト間の移動で止まる）
+    user_marks: VecDeque<u64>,
+    /// take_output_lines で次に取り出す行の通し番号
+    output_line: u64,
+}
+
+/// OSC 133 に対応していないシェルでプロンプトとみなす行の正規表現
+/// （環境変数 `UMITERM_PROMPT_PATTERN` で指定、正しくなければ警告して使わない）
+pub fn prompt_pattern() -> Option<Regex> {
+    let pattern = config::var("UMITERM_PROMPT_PATTERN")?;
+    match Regex::new(&pattern) {
+        Ok(regex) => Some(regex),
+        Err(e) => {
+            log::warn!("プロンプトの正規表現が正しくありません: {:?} ({})", pattern, e);
+            None
+        }
+    }
+}
+
+/// 取り出されないまま溜めておくシェル統合のマークの上限
+const MAX_SHELL_MARKS: usize = 64;
+
+/// 位置を覚えておくシェル統合のマークの上限
+const MAX_MARK_LINES: usize = 4096;
+
+/// シェル統合（OSC 133）のマーク
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellMark {
+    /// プロンプトの表示開始（A）
+    PromptStart,
+    /// コマンド入力の開始（B）
+    CommandStart,
+    /// コマンドの実行開始（C）
+    OutputStart,
+    /// コマンドの終了（D、終了コードは省略されることがある）
+    CommandFinished(Option<i32>),
 }
 
 /// 現在のセルスタイル（新しい文字に適用される）
@@ -215,19 +363,27 @@ impl Terminal {
             saved_cursor: Cursor::default(),
             mode: TerminalMode::AUTO_WRAP,
             current_style: CellStyle {
-                fg: Color::EMERALD,
-                bg: Color::BLACK,
+                fg: Color::DEFAULT_FG,
+                bg: Color::DEFAULT_BG,
                 flags: CellFlags::empty(),
             },
             scroll_top: 0,
             scroll_bottom: rows - 1,
             tabs,
-            title: String::from("BlazeTerm"),
-            cwd: std::env::var("HOME")
-                .map(PathBuf::from)
-                .unwrap_or_else(|_| std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"))),
+            title: String::new(),
+            cwd: crate::config::home_dir()
+                .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"))),
+            wsl_distro: None,
             selection: Selection::default(),
             response_buffer: Vec::new(),
+            scrollback: Scrollback::new(scrollback::default_max_bytes()),
+            display_offset: 0,
+            unseen_lines: 0,
+            bell: false,
+            shell_marks: Vec::new(),
+            mark_lines: VecDeque::new(),
+            user_marks: VecDeque::new(),
+            output_line: 0,
         }
     }
 
@@ -240,6 +396,183 @@ impl Terminal {
         self.response_buffer.extend_from_slice(response);
     }
 
+    /// ベルを鳴らす
+    pub fn ring_bell(&mut self) {
+        self.bell = true;
+    }
+
+    /// ベルが鳴ったかを取り出してクリア
+    pub fn take_bell(&mut self) -> bool {
+        std::mem::take(&mut self.bell)
+    }
+
+    /// シェル統合のマークを記録
+    pub fn push_shell_mark(&mut self, mark: ShellMark) {
+        if self.shell_marks.len() >= MAX_SHELL_MARKS {
+            self.shell_marks.remove(0);
+        }
+        self.shell_marks.push(mark);
+
+        // メイン画面ならカーソルの行を覚えておく（スクロールバックから捨てられた行の分は消す）
+        if !self.is_alt_screen() {
+            let dropped = self.scrollback.dropped();
+            while self.mark_lines.front().is_some_and(|(line, _)| *line < dropped)
+                || self.mark_lines.len() >= MAX_MARK_LINES
+            {
+                self.mark_lines.pop_front();
+            }
+            let line = self.cursor_line();
+            self.mark_lines.push_back((line, mark));
+        }
+    }
+
+    /// メイン画面のカーソルの行の通し番号（スクロールバックから捨てられた行も数える）
+    fn cursor_line(&self) -> u64 {
+        self.scrollback.dropped() + self.scrollback.len() as u64 + self.cursor.row as u64
+    }
+
+    /// 前回から出力し終えた行（カーソルより上の行、通し番号と文字列）
+    ///
+    /// 代替スクリーンの表示中は空
+    pub fn take_output_lines(&mut self) -> Vec<(u64, String)> {
+        let (lines, next) = self.output_lines_from(self.output_line);
+        self.output_line = next;
+        lines
+    }
+
+    /// 通し番号 start から出力し終えた行（通し番号と文字列）と、次に読む通し番号
+    ///
+    /// take_output_lines と違って取り出した印を付けないので、別々に読み進められる
+    /// 代替スクリーンの表示中は空で、次に読む通し番号は start のまま
+    pub fn output_lines_from(&self, start: u64) -> (Vec<(u64, String)>, u64) {
+        if self.is_alt_screen() {
+            return (Vec::new(), start);
+        }
+        let dropped = self.scrollback.dropped();
+        let end = self.cursor_line();
+        let lines = (start.max(dropped)..end)
+            .filter_map(|line| {
+                let chars = self.history_line_chars((line - dropped) as usize)?;
+                let text: String = chars.into_iter().filter(|c| *c != '\0').collect();
+                Some((line, text.trim_end().to_string()))
+            })
+            .collect();
+        (lines, end)
+    }
+
+    /// 行（通し番号）に印を付ける
+    pub fn mark_line(&mut self, line: u64) {
+        let dropped = self.scrollback.dropped();
+        while self.user_marks.front().is_some_and(|marked| *marked < dropped)
+            || self.user_marks.len() >= MAX_MARK_LINES
+        {
+            self.user_marks.pop_front();
+        }
+        if self.user_marks.back().is_none_or(|marked| *marked < line) {
+            self.user_marks.push_back(line);
+        }
+    }
+
+    /// 印を付けた行（history_cells の番号、古い順、代替スクリーンの表示中は空）
+    pub fn marked_lines(&self) -> Vec<usize> {
+        if self.is_alt_screen() {
+            return Vec::new();
+        }
+        let dropped = self.scrollback.dropped();
+        self.user_marks
+            .iter()
+            .filter_map(|line| Some(line.checked_sub(dropped)? as usize))
+            .collect()
+    }
+
+    /// 位置を覚えているシェル統合のマーク（history_cells の番号、古い順）
+    ///
+    /// 代替スクリーンの表示中は空
+    pub fn mark_lines(&self) -> Vec<(usize, ShellMark)> {
+        if self.is_alt_screen() {
+            return Vec::new();
+        }
+        let dropped = self.scrollback.dropped();
+        let end = self.history_base() + self.grid.rows;
+        self.mark_lines
+            .iter()
+            .filter_map(|(line, mark)| Some(((line.checked_sub(dropped)? as usize), *mark)))
+            .filter(|(index, _)| *index < end)
+            .collect()
+    }
+
+    /// プロンプトの行（history_cells の番号、古い順）
+    ///
+    /// OSC 133 のマークがなければ、fallback の正規表現に一致する行をプロンプトとみなす
+    pub fn prompt_lines(&self, fallback: Option<&Regex>) -> Vec<usize> {
+        let mut lines: Vec<usize> = self
+            .mark_lines()
+            .into_iter()
+            .filter(|(_, mark)| *mark == ShellMark::PromptStart)
+            .map(|(index, _)| index)
+            .collect();
+        lines.dedup();
+        if !lines.is_empty() || self.is_alt_screen() {
+            return lines;
+        }
+        let Some(regex) = fallback else { return lines };
+        self.history_text()
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| regex.is_match(line))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// 最後に終わったコマンドの出力の行（history_cells の番号、開始, 終了）
+    ///
+    /// OSC 133 の C（出力の開始）から、次の D（終了）か A（プロンプト）の前の行まで。
+    /// 出力がなければ None
+    pub fn last_output_lines(&self) -> Option<(usize, usize)> {
+        let marks = self.mark_lines();
+        marks.iter().enumerate().rev().find_map(|(i, (start, mark))| {
+            if *mark != ShellMark::OutputStart {
+                return None;
+            }
+            let (end, _) = marks[i + 1..]
+                .iter()
+                .find(|(_, mark)| matches!(mark, ShellMark::CommandFinished(_) | ShellMark::PromptStart))?;
+            (*end > *start).then(|| (*start, *end - 1))
+        })
+    }
+
+    /// 最後に終わったコマンドの出力を選択
+    /// 戻り値: 選択したテキスト（出力がなければ None）
+    pub fn select_last_output(&mut self) -> Option<String> {
+        let (start, end) = self.last_output_lines()?;
+        let cols = self.active_grid().cols;
+        self.selection.select((0, start), (cols.saturating_sub(1), end));
+        self.get_selected_text()
+    }
+
+    /// 前（direction < 0）または次のプロンプトが画面の1行目に来るように表示位置を動かす
+    /// 戻り値: 動いたか
+    pub fn jump_to_prompt(&mut self, prompts: &[usize], direction: isize) -> bool {
+        if self.is_alt_screen() {
+            return false;
+        }
+        let top = self.row_history_line(0);
+        let target = if direction < 0 {
+            prompts.iter().rev().find(|line| **line < top)
+        } else {
+            prompts.iter().find(|line| **line > top)
+        };
+        let Some(&target) = target else { return false };
+        self.begin_scroll_back();
+        self.display_offset = self.history_base().saturating_sub(target);
+        true
+    }
+
+    /// 記録されたシェル統合のマークを取り出してクリア
+    pub fn take_shell_marks(&mut self) -> Vec<ShellMark> {
+        std::mem::take(&mut self.shell_marks)
+    }
+
     /// 応答バッファを取り出してクリア
     pub fn take_response(&mut self) -> Option<Vec<u8>> {
         if self.response_buffer.is_empty() {
@@ -256,6 +589,328 @@ impl Terminal {
         self.queue_response(response.as_bytes());
     }
 
+    // ───────────────────────────────────────────────────────────────────────
+    // スクロールバック
+    // ───────────────────────────────────────────────────────────────────────
+
+    /// 表示位置をスクロール（正の値で過去へ、代替スクリーンでは無効）
+    pub fn scroll_display(&mut self, delta: isize) {
+        if self.is_alt_screen() {
+            return;
+        }
+        self.begin_scroll_back();
+        let max = self.scrollback.len() as isize;
+        self.display_offset = (self.display_offset as isize + delta).clamp(0, max) as usize;
+    }
+
+    /// 最下部から離れるときは、届いた行数を数え直す
+    fn begin_scroll_back(&mut self) {
+        if self.display_offset == 0 {
+            self.unseen_lines = 0;
+        }
+    }
+
+    /// さかのぼって表示している間に届いた行数（最下部を表示中なら 0）
+    pub fn unseen_lines(&self) -> usize {
+        if self.display_offset == 0 { 0 } else { self.unseen_lines }
+    }
+
+    /// 表示位置を最下部に戻す
+    pub fn scroll_to_bottom(&mut self) {
+        self.display_offset = 0;
+    }
+
+    /// スクロールバックの上限を変える（表示位置は残った行に収める）
+    pub fn set_scrollback_limits(&mut self, limits: Limits) {
+        self.scrollback.set_limits(limits);
+        self.display_offset = self.display_offset.min(self.scrollback.len());
+    }
+
+    /// スクロールバックを消去（ED 3）
+    pub fn clear_scrollback(&mut self) {
+        self.scrollback.clear();
+        self.display_offset = 0;
+    }
+
+    /// 履歴（スクロールバックとメイン画面）の全行のテキスト（古い順、末尾の空白は除く）
+    pub fn history_text(&self) -> Vec<String> {
+        let scrollback = (0..self.scrollback.len())
+            .rev()
+            .filter_map(|index| self.scrollback.line_text(index));
+        let screen = (0..self.grid.rows)
+            .map(|row| row_chars(self.grid.row_slice(row)).into_iter().filter(|c| *c != '\0').collect::<String>());
+        scrollback
+            .chain(screen)
+            .map(|line| line.trim_end().to_string())
+            .collect()
+    }
+
+    /// 履歴（スクロールバックとメイン画面）の全行のセル（古い順、history_text と同じ行）
+    pub fn history_rows(&self) -> impl Iterator<Item = Vec<Cell>> + '_ {
+        let scrollback = (0..self.scrollback.len())
+            .rev()
+            .filter_map(|index| self.scrollback.line_cells(index));
+        let screen = (0..self.grid.rows).map(|row| self.grid.row_slice(row).to_vec());
+        scrollback.chain(screen)
+    }
+
+    /// 表示中の画面（代替スクリーンならそちら）の全行のテキスト（末尾の空白は除く）
+    pub fn screen_text(&self) -> Vec<String> {
+        let grid = self.active_grid();
+        (0..grid.rows)
+            .map(|row| row_chars(grid.row_slice(row)).into_iter().filter(|c| *c != '\0').collect::<String>())
+            .map(|line| line.trim_end().to_string())
+            .collect()
+    }
+
+    /// 履歴の行（history_text の番号）が画面の中央に来るように表示位置を合わせる
+    pub fn scroll_to_history_line(&mut self, index: usize) {
+        let len = self.scrollback.len();
+        if self.is_alt_screen() {
+            return;
+        }
+        if index >= len {
+            // メイン画面の行
+            self.display_offset = 0;
+            return;
+        }
+        self.begin_scroll_back();
+        // 新しい方から数えた番号 k の行は、表示位置 offset のとき画面の offset - 1 - k 行目
+        let from_newest = len - 1 - index;
+        let offset = from_newest + 1 + self.grid.rows / 2;
+        self.display_offset = offset.min(len);
+    }
+
+    /// 表示中の画面の履歴（スクロールバックと画面）の全行の文字（古い順、セル単位）
+    ///
+    /// 代替スクリーンではスクロールバックを含まず画面の行だけ
+    pub fn history_cells(&self) -> Vec<Vec<char>> {
+        (0..self.history_base() + self.active_grid().rows)
+            .filter_map(|index| self.history_line_chars(index))
+            .collect()
+    }
+
+    /// 画面に表示中の各行の文字（表示位置を考慮、セル単位）
+    pub fn visible_cells(&self) -> Vec<Vec<char>> {
+        (0..self.active_grid().rows).map(|row| self.visible_row_chars(row)).collect()
+    }
+
+    /// 画面上の行の文字（表示位置を考慮、全角文字の後ろの空きは '\0'）
+    fn visible_row_chars(&self, row: usize) -> Vec<char> {
+        let mut chars: Vec<char> = (0..self.active_grid().cols)
+            .map(|col| self.visible_cell(col, row).character)
+            .collect();
+        grid::mark_wide_spacers(&mut chars);
+        chars
+    }
+
+    /// history_cells で画面の1行目にあたる番号（スクロールバックの行数）
+    pub fn history_base(&self) -> usize {
+        if self.is_alt_screen() { 0 } else { self.scrollback.len() }
+    }
+
+    /// history_cells の0行目の通し番号（スクロールバックから捨てられた行の数、代替スクリーンでは 0）
+    pub fn history_origin(&self) -> u64 {
+        if self.is_alt_screen() { 0 } else { self.scrollback.dropped() }
+    }
+
+    /// history_cells の行が画面の何行目に表示されているか（表示されていなければ None）
+    pub fn history_line_row(&self, index: usize) -> Option<usize> {
+        let row = (index + self.display_offset).checked_sub(self.history_base())?;
+        (row < self.active_grid().rows).then_some(row)
+    }
+
+    /// 画面の行に表示されている history_cells の番号
+    pub fn row_history_line(&self, row: usize) -> usize {
+        (self.history_base() + row).saturating_sub(self.display_offset)
+    }
+
+    /// history_cells の行が画面に入るように、最小限だけ表示位置を動かす
+    pub fn reveal_history_line(&mut self, index: usize) {
+        self.begin_scroll_back();
+        let base = self.history_base();
+        let rows = self.active_grid().rows;
+        // 画面の1行目は base - offset、最終行は base - offset + rows - 1
+        let top = base.saturating_sub(self.display_offset);
+        if index < top {
+            self.display_offset = base - index;
+        } else if index >= top + rows {
+            self.display_offset = (base + rows).saturating_sub(index + 1);
+        }
+    }
+
+    /// 画面上の行をコピー（表示位置を考慮、足りない分は空白）
+    pub fn copy_visible_row(&self, row: usize, dst: &mut [Cell]) {
+        if row < self.display_offset {
+            self.scrollback.copy_line(self.display_offset - 1 - row, dst);
+            return;
+        }
+        let grid = self.active_grid();
+        let row = row - self.display_offset;
+        if row < grid.rows {
+            let src = grid.row_slice(row);
+            let n = src.len().min(dst.len());
+            dst[..n].copy_from_slice(&src[..n]);
+            dst[n..].fill(Cell::default());
+        } else {
+            dst.fill(Cell::default());
+        }
+    }
+
+    /// history_cells の1行の文字（セル単位）
+    fn history_line_chars(&self, index: usize) -> Option<Vec<char>> {
+        let base = self.history_base();
+        if index < base {
+            return self.scrollback.line_chars(base - 1 - index);
+        }
+        let grid = self.active_grid();
+        (index - base < grid.rows).then(|| row_chars(grid.row_slice(index - base)))
+    }
+
+    /// history_cells の行のセル（全角文字の後ろの空きも含む）
+    fn history_line_cells(&self, index: usize) -> Option<Vec<Cell>> {
+        let base = self.history_base();
+        if index < base {
+            return self.scrollback.line_cells(base - 1 - index);
+        }
+        let grid = self.active_grid();
+        (index - base < grid.rows).then(|| grid.row_slice(index - base).to_vec())
+    }
+
+    /// 画面上のセルを取得（表示位置を考慮）
+    pub fn visible_cell(&self, col: usize, row: usize) -> Cell {
+        if row < self.display_offset {
+            return self
+                .scrollback
+                .cell(self.display_offset - 1 - row, col)
+                .unwrap_or_default();
+        }
+        self.active_grid()
+            .get(col, row - self.display_offset)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// 画面上の位置の単語を選択（ダブルクリック）
+    ///
+    /// 空白と separators の文字で区切る。区切り文字の上なら その1文字だけ
+    pub fn select_word(&mut self, col: usize, row: usize, separators: &str) {
+        let chars = self.visible_row_chars(row);
+        let cols = chars.len();
+        if cols == 0 {
+            return;
+        }
+        // 全角文字の後ろの空きは全角文字の一部
+        let mut col = col.min(cols - 1);
+        while col > 0 && chars[col] == '\0' {
+            col -= 1;
+        }
+        let is_separator = |c: char| c.is_whitespace() || separators.contains(c);
+
+        let (mut start, mut end) = (col, col);
+        if !is_separator(chars[col]) {
+            while start > 0 && !is_separator(chars[start - 1]) {
+                start -= 1;
+            }
+            while end + 1 < cols && !is_separator(chars[end + 1]) {
+                end += 1;
+            }
+        }
+        let line = self.row_history_line(row);
+        self.selection.select((start, line), (end, line));
+    }
+
+    /// 画面上の行全体を選択（トリプルクリック）
+    pub fn select_line(&mut self, row: usize) {
+        let cols = self.active_grid().cols;
+        let line = self.row_history_line(row);
+        self.selection.select((0, line), (cols.saturating_sub(1), line));
+    }
+
+    /// 画面上の位置から選択を開始（ドラッグ、block なら矩形選択）
+    pub fn start_selection(&mut self, col: usize, row: usize, block: bool) {
+        let (col, line) = self.selection_point(col, row);
+        self.selection.start_at(col, line);
+        self.selection.block = block;
+    }
+
+    /// 今の選択を画面上の位置まで広げ、続けてドラッグできるようにする（選択がなければ始める）
+    pub fn continue_selection(&mut self, col: usize, row: usize) {
+        if self.selection.start.is_none() {
+            return self.start_selection(col, row, false);
+        }
+        self.selection.active = true;
+        self.extend_selection(col, row);
+    }
+
+    /// 画面上の位置まで選択を広げる（ドラッグ）
+    pub fn extend_selection(&mut self, col: usize, row: usize) {
+        let (col, line) = self.selection_point(col, row);
+        self.selection.extend_to(col, line);
+    }
+
+    /// 画面上の位置を選択の座標（列, history_cells の番号）にする（画面の外は端に寄せる）
+    fn selection_point(&self, col: usize, row: usize) -> (usize, usize) {
+        let grid = self.active_grid();
+        let col = col.min(grid.cols.saturating_sub(1));
+        (col, self.row_history_line(row.min(grid.rows.saturating_sub(1))))
+    }
+
+    /// 画面に表示されている部分の選択（行は画面の行、画面の外に続く端は画面の端まで）
+    pub fn screen_selection(&self) -> Selection {
+        let Some((start, end)) = self.selection.ordered() else {
+            return Selection::default();
+        };
+        let grid = self.active_grid();
+        let top = self.row_history_line(0);
+        let bottom = top + grid.rows.saturating_sub(1);
+        if end.1 < top || start.1 > bottom {
+            return Selection::default();
+        }
+        let (start, end) = match self.selection.block_columns() {
+            // 矩形選択は列を変えずに行だけ画面に収める
+            Some((left, right)) if self.selection.block => (
+                (left, start.1.max(top) - top),
+                (right, end.1.min(bottom) - top),
+            ),
+            _ => (
+                if start.1 < top { (0, 0) } else { (start.0, start.1 - top) },
+                if end.1 > bottom {
+                    (grid.cols.saturating_sub(1), grid.rows.saturating_sub(1))
+                } else {
+                    (end.0, end.1 - top)
+                },
+            ),
+        };
+        Selection {
+            start: Some(start),
+            end: Some(end),
+            active: self.selection.active,
+            block: self.selection.block,
+        }
+    }
+
+    /// ペーストするテキストを PTY に送るバイト列にする
+    ///
+    /// 改行は CR にそろえる。ブラケットペーストモードなら ESC [200~ と ESC [201~ で囲み、
+    /// 中の ESC は取り除く（終わりの印を埋め込んでペーストから抜け出せないように）
+    pub fn paste_bytes(&self, text: &str) -> Vec<u8> {
+        let text = text.replace("\r\n", "\r").replace('\n', "\r");
+        if !self.mode.contains(TerminalMode::BRACKETED_PASTE) {
+            return text.into_bytes();
+        }
+        let mut bytes = b"\x1b[200~".to_vec();
+        bytes.extend(text.bytes().filter(|b| *b != 0x1b));
+        bytes.extend_from_slice(b"\x1b[201~");
+        bytes
+    }
+
+    /// 代替スクリーン表示中か
+    #[inline]
+    pub fn is_alt_screen(&self) -> bool {
+        self.mode.contains(TerminalMode::ALT_SCREEN)
+    }
+
     /// 現在のグリッドを取得
     #[inline]
     pub fn active_grid(&self) -> &Grid {
@@ -287,8 +942,11 @@ impl Terminal {
         // 文字幅を取得（全角は2、半角は1）
         let char_width = c.width().unwrap_or(1);
 
-        // 画面外なら無視
+        // 画面の幅に入りきらない文字（1桁の画面の全角文字）は無視
         let cols = self.active_grid().cols;
+        if char_width > cols {
+            return;
+        }
 
         // 全角文字が入りきらない場合も改行
         if self.cursor.col + char_width > cols {
@@ -331,6 +989,57 @@ impl Terminal {
         self.cursor.col += char_width;
     }
 
+    /// 印字可能なASCII文字列をまとめて入力（パーサーの高速パス）
+    ///
+    /// `input_char` を1文字ずつ呼ぶのと同じ結果になる
+    pub fn input_ascii(&mut self, text: &[u8]) {
+        let template = Cell {
+            character: ' ',
+            fg: self.current_style.fg,
+            bg: self.current_style.bg,
+            flags: self.current_style.flags,
+        };
+
+        let mut rest = text;
+        while !rest.is_empty() {
+            let cols = self.active_grid().cols;
+
+            // 右端に達している場合
+            if self.cursor.col >= cols {
+                if self.mode.contains(TerminalMode::AUTO_WRAP) {
+                    // 自動改行
+                    self.cursor.col = 0;
+                    self.cursor.row += 1;
+                    if self.cursor.row > self.scroll_bottom {
+                        self.scroll_up(1);
+                        self.cursor.row = self.scroll_bottom;
+                    }
+                } else {
+                    // 折り返さない場合は最後の列を上書きし続ける（最後の文字だけが残る）
+                    self.cursor.col = cols - 1;
+                    rest = &rest[rest.len() - 1..];
+                }
+            }
+
+            // 行に収まる分を一度に書き込む
+            let (col, row) = (self.cursor.col, self.cursor.row);
+            let n = rest.len().min(cols - col);
+            let grid = self.active_grid_mut();
+            if row < grid.rows {
+                let cells = &mut grid.row_slice_mut(row)[col..col + n];
+                for (cell, &byte) in cells.iter_mut().zip(&rest[..n]) {
+                    *cell = Cell {
+                        character: byte as char,
+                        ..template
+                    };
+                }
+            }
+
+            self.cursor.col += n;
+            rest = &rest[n..];
+        }
+    }
+
     /// 制御文字を処理
     fn handle_control_char(&mut self, c: char) {
         match c {
@@ -367,9 +1076,11 @@ impl Terminal {
         self.saved_cursor = self.cursor.clone();
     }
 
-    /// カーソルを復元
+    /// カーソルを復元（保存後に画面が縮んでいれば画面内に収める）
     pub fn restore_cursor(&mut self) {
         self.cursor = self.saved_cursor.clone();
+        let (col, row) = (self.cursor.col, self.cursor.row);
+        self.move_cursor_to(col, row);
     }
 
     // ───────────────────────────────────────────────────────────────────────
@@ -418,6 +1129,21 @@ impl Terminal {
         let scroll_top = self.scroll_top;
         let scroll_bottom = self.scroll_bottom;
         let cols = self.active_grid().cols;
+        // 領域の行数より多くはスクロールしない（領域が空になるだけ）
+        let amount = amount.min(scroll_bottom + 1 - scroll_top);
+
+        // メイン画面の上端から押し出される行はスクロールバックへ
+        if scroll_top == 0 && !self.is_alt_screen() {
+            let pushed = amount.min(scroll_bottom + 1);
+            for row in 0..pushed {
+                self.scrollback.push(self.grid.row_slice(row));
+            }
+            // さかのぼって表示中なら表示内容を固定する
+            if self.display_offset > 0 {
+                self.display_offset = (self.display_offset + pushed).min(self.scrollback.len());
+                self.unseen_lines += pushed;
+            }
+        }
 
         // スクロール領域内の行を上にシフト
         for row in scroll_top..=scroll_bottom.saturating_sub(amount) {
@@ -442,6 +1168,8 @@ impl Terminal {
         let scroll_top = self.scroll_top;
         let scroll_bottom = self.scroll_bottom;
         let cols = self.active_grid().cols;
+        // 領域の行数より多くはスクロールしない（領域が空になるだけ）
+        let amount = amount.min(scroll_bottom + 1 - scroll_top);
 
         // スクロール領域内の行を下にシフト
         for row in (scroll_top + amount..=scroll_bottom).rev() {
@@ -517,6 +1245,7 @@ impl Terminal {
     pub fn enter_alt_screen(&mut self) {
         if !self.mode.contains(TerminalMode::ALT_SCREEN) {
             self.mode.insert(TerminalMode::ALT_SCREEN);
+            self.display_offset = 0;
             self.alt_grid.clear();
             self.save_cursor();
         }
@@ -524,6 +1253,7 @@ impl Terminal {
 
     /// メインスクリーンに切り替え
     pub fn exit_alt_screen(&mut self) {
+        self.display_offset = 0;
         if self.mode.contains(TerminalMode::ALT_SCREEN) {
             self.mode.remove(TerminalMode::ALT_SCREEN);
             self.restore_cursor();
@@ -541,41 +1271,31 @@ impl Terminal {
             return None;
         }
 
-        let (start, end) = match (self.selection.start, self.selection.end) {
-            (Some(s), Some(e)) => {
-                // 開始と終了を正規化
-                if s.1 < e.1 || (s.1 == e.1 && s.0 <= e.0) {
-                    (s, e)
-                } else {
-                    (e, s)
-                }
-            }
-            _ => return None,
-        };
+        let (start, end) = self.selection.ordered()?;
+        if self.selection.block {
+            return self.get_block_text(start.1, end.1);
+        }
 
-        let grid = self.active_grid();
         let mut text = String::new();
 
-        for row in start.1..=end.1 {
-            if row >= grid.rows {
+        // 画面の外（スクロールバック）に続く部分も含めて取得
+        for line in start.1..=end.1 {
+            let Some(chars) = self.history_line_chars(line) else {
                 break;
-            }
+            };
 
-            let col_start = if row == start.1 { start.0 } else { 0 };
-            let col_end = if row == end.1 { end.0 } else { grid.cols.saturating_sub(1) };
+            let col_start = if line == start.1 { start.0 } else { 0 };
+            let col_end = if line == end.1 { end.0 } else { chars.len().saturating_sub(1) };
 
-            for col in col_start..=col_end {
-                if col >= grid.cols {
-                    break;
-                }
-                let cell = &grid[(col, row)];
-                if cell.character != '\0' {
-                    text.push(cell.character);
+            for &c in chars.iter().take(col_end + 1).skip(col_start) {
+                if c != '\0' {
+                    text.push(c);
                 }
             }
 
-            // 行末で改行を追加（最後の行以外）
-            if row < end.1 {
+            // 行末で改行を追加（最後の行以外、行末の空白は除く）
+            if line < end.1 {
+                text.truncate(text.trim_end_matches(' ').len());
                 text.push('\n');
             }
         }
@@ -589,10 +1309,56 @@ impl Terminal {
         }
     }
 
+    /// 選択範囲のセル（get_selected_text と同じ範囲の行ごとのセル、色付きでコピーするとき）
+    ///
+    /// 範囲の端で全角文字が切れても区別できるよう、全角文字の後ろの空きは '\0' にする
+    pub fn get_selected_cells(&self) -> Option<Vec<Vec<Cell>>> {
+        if !self.selection.has_selection() {
+            return None;
+        }
+        let (start, end) = self.selection.ordered()?;
+        let block = self.selection.block_columns().filter(|_| self.selection.block);
+        let rows: Vec<Vec<Cell>> = (start.1..=end.1)
+            .map_while(|line| Some((line, self.history_line_cells(line)?)))
+            .map(|(line, mut cells)| {
+                let chars = row_chars(&cells);
+                for (cell, c) in cells.iter_mut().zip(chars) {
+                    cell.character = c;
+                }
+                let (first, last) = match block {
+                    Some(columns) => columns,
+                    None => (
+                        if line == start.1 { start.0 } else { 0 },
+                        if line == end.1 { end.0 } else { usize::MAX },
+                    ),
+                };
+                cells.into_iter().take(last.saturating_add(1)).skip(first).collect()
+            })
+            .collect();
+        (!rows.is_empty()).then_some(rows)
+    }
+
+    /// 矩形選択のテキスト（各行の末尾の空白は削除して改行でつなぐ）
+    fn get_block_text(&self, first_line: usize, last_line: usize) -> Option<String> {
+        let (left, right) = self.selection.block_columns()?;
+        let lines: Vec<String> = (first_line..=last_line)
+            .map_while(|line| self.history_line_chars(line))
+            .map(|chars| {
+                let text: String = chars.iter().take(right + 1).skip(left).filter(|c| **c != '\0').collect();
+                text.trim_end().to_string()
+            })
+            .collect();
+        let text = lines.join("\n").trim_end().to_string();
+        (!text.is_empty()).then_some(text)
+    }
+
     /// サイズを変更
     pub fn resize(&mut self, cols: usize, rows: usize) {
         self.grid.resize(cols, rows);
         self.alt_grid.resize(cols, rows);
+        self.display_offset = 0;
+        // スクロール領域は全画面に戻す
+        self.scroll_top = 0;
         self.scroll_bottom = rows - 1;
 
         // カーソル位置を調整
@@ -609,6 +1375,39 @@ impl Terminal {
             self.tabs.push(i);
         }
     }
+
+    /// 状態の不変条件を確かめる（崩れていたら panic、ファジングとテストから使う）
+    ///
+    /// - カーソルは画面内（列は折り返し待ちの右端の次まで）
+    /// - スクロール領域は `scroll_top <= scroll_bottom < rows`
+    /// - メイン画面と代替画面は同じ大きさ
+    #[allow(dead_code)]
+    pub fn assert_invariants(&self) {
+        let (cols, rows) = (self.grid.cols, self.grid.rows);
+        assert_eq!((self.alt_grid.cols, self.alt_grid.rows), (cols, rows), "代替画面の大きさが違う");
+        assert!(
+            self.cursor.col <= cols && self.cursor.row < rows,
+            "カーソルが画面外: {:?} ({}x{})",
+            (self.cursor.col, self.cursor.row),
+            cols,
+            rows
+        );
+        assert!(
+            self.scroll_top <= self.scroll_bottom && self.scroll_bottom < rows,
+            "スクロール領域が不正: {}..={} ({}行)",
+            self.scroll_top,
+            self.scroll_bottom,
+            rows
+        );
+        assert!(self.display_offset <= self.scrollback.len(), "表示位置がスクロールバックを超えている");
+    }
+}
+
+/// グリッドの行の文字（全角文字の後ろの空きは '\0'）
+fn row_chars(cells: &[Cell]) -> Vec<char> {
+    let mut chars: Vec<char> = cells.iter().map(|cell| cell.character).collect();
+    grid::mark_wide_spacers(&mut chars);
+    chars
 }
 
 #[cfg(test)]
@@ -657,4 +1456,333 @@ mod tests {
         // スクロール後、最初の'1'は消えているはず
         assert_eq!(term.grid[(0, 0)].character, '2');
     }
+
+    #[test]
+    fn test_scrollback_and_display_offset() {
+        let mut term = Terminal::new(10, 2);
+        for c in ['1', '2', '3', '4'] {
+            term.input_char(c);
+            term.linefeed();
+            term.carriage_return();
+        }
+
+        // 画面外に出た行がスクロールバックに入る
+        assert_eq!(term.scrollback.len(), 3);
+        assert_eq!(term.visible_cell(0, 0).character, '4');
+
+        // さかのぼって表示
+        term.scroll_display(2);
+        assert_eq!(term.visible_cell(0, 0).character, '2');
+        assert_eq!(term.visible_cell(0, 1).character, '3');
+
+        // 表示中に出力があっても表示内容は動かない
+        term.input_char('5');
+        term.linefeed();
+        assert_eq!(term.display_offset, 3);
+        assert_eq!(term.visible_cell(0, 0).character, '2');
+
+        // 範囲外にはスクロールしない
+        term.scroll_display(100);
+        assert_eq!(term.display_offset, 4);
+        term.scroll_to_bottom();
+        assert_eq!(term.visible_cell(0, 0).character, '5');
+
+        // 代替スクリーンではスクロールバックに入らない
+        term.enter_alt_screen();
+        term.linefeed();
+        term.linefeed();
+        assert_eq!(term.scrollback.len(), 4);
+    }
+
+    #[test]
+    fn test_history_text() {
+        let mut term = Terminal::new(10, 2);
+        for line in ["one", "two", "three", "four", "five"] {
+            for c in line.chars() {
+                term.input_char(c);
+            }
+            term.linefeed();
+            term.carriage_return();
+        }
+
+        // スクロールバックの後にメイン画面の行が古い順に並ぶ
+        let history = term.history_text();
+        assert_eq!(history, vec!["one", "two", "three", "four", "five", ""]);
+        // セルでも同じ行が並ぶ
+        let rows: Vec<String> = term.history_rows().map(|row| row.iter().map(|cell| cell.character).collect()).collect();
+        assert_eq!(rows.iter().map(|row| row.trim_end()).collect::<Vec<_>>(), history);
+
+        // 選んだ行が画面の中央に来るまでさかのぼる
+        term.scroll_to_history_line(1);
+        assert_eq!(term.visible_cell(0, 0).character, 'o');
+        assert_eq!(term.visible_cell(1, 1).character, 'w');
+
+        // さかのぼれる範囲を超えない
+        term.scroll_to_history_line(0);
+        assert_eq!(term.display_offset, 4);
+
+        // メイン画面の行なら最下部に戻る
+        term.scroll_to_history_line(5);
+        assert_eq!(term.display_offset, 0);
+    }
+
+    #[test]
+    fn test_select_word_and_line() {
+        let mut term = Terminal::new(24, 2);
+        for c in "ls src/main.rs:12 日本語".chars() {
+            term.input_char(c);
+        }
+
+        // 空白と区切り文字で区切る（/ は区切らない）
+        term.select_word(5, 0, DEFAULT_WORD_SEPARATORS);
+        assert_eq!(term.get_selected_text().as_deref(), Some("src/main.rs"));
+
+        // 区切り文字の上なら1文字だけ
+        term.select_word(14, 0, DEFAULT_WORD_SEPARATORS);
+        assert_eq!(term.get_selected_text().as_deref(), Some(":"));
+
+        // 全角文字の後ろの空きをクリックしても全角文字の単語
+        term.select_word(19, 0, DEFAULT_WORD_SEPARATORS);
+        assert_eq!(term.get_selected_text().as_deref(), Some("日本語"));
+
+        // 区切り文字を変えると / でも区切る
+        term.select_word(5, 0, "/:");
+        assert_eq!(term.get_selected_text().as_deref(), Some("src"));
+
+        term.select_line(0);
+        assert_eq!(term.get_selected_text().as_deref().map(str::trim_end), Some("ls src/main.rs:12 日本語"));
+    }
+
+    #[test]
+    fn test_drag_selection_follows_scroll() {
+        let mut term = Terminal::new(4, 2);
+        for line in ["ab", "cd", "ef", "gh"] {
+            for c in line.chars() {
+                term.input_char(c);
+            }
+            term.linefeed();
+            term.carriage_return();
+        }
+
+        // 画面は "gh" と空行。"gh" から選択を始めて上へスクロールしながら広げる
+        term.start_selection(0, 0, false);
+        term.scroll_display(2);
+        term.extend_selection(0, 0);
+        assert_eq!(term.get_selected_text().as_deref(), Some("cd\nef\ng"));
+
+        // 画面に見えている部分だけを描画用の座標にする（"gh" は画面の外）
+        let screen = term.screen_selection();
+        assert_eq!((screen.start, screen.end), (Some((0, 0)), Some((3, 1))));
+
+        // 画面の外の座標は端に寄せる（最終行の右端）
+        term.scroll_to_bottom();
+        term.extend_selection(10, 5);
+        assert_eq!(term.get_selected_text().as_deref(), Some("gh"));
+        let screen = term.screen_selection();
+        assert_eq!((screen.start, screen.end), (Some((0, 0)), Some((3, 1))));
+
+        // 終えた選択はドラッグでは広がらず、クリックした位置まで広げ直せる
+        term.selection.finish();
+        term.extend_selection(0, 0);
+        assert_eq!(term.get_selected_text().as_deref(), Some("gh"));
+        term.continue_selection(0, 0);
+        assert_eq!(term.get_selected_text().as_deref(), Some("g"));
+    }
+
+    #[test]
+    fn test_block_selection() {
+        let mut term = Terminal::new(8, 3);
+        for line in ["abcdef", "gh", "日本語x"] {
+            for c in line.chars() {
+                term.input_char(c);
+            }
+            term.linefeed();
+            term.carriage_return();
+        }
+        term.scroll_display(1);
+
+        // 右下から左上へドラッグしても同じ矩形
+        term.start_selection(4, 2, true);
+        term.extend_selection(1, 0);
+        assert_eq!(term.get_selected_text().as_deref(), Some("bcde\nh\n本語"));
+        let cells = term.get_selected_cells().unwrap();
+        assert_eq!(cells.iter().map(Vec::len).collect::<Vec<_>>(), [4, 4, 4]);
+        assert_eq!(cells[0][0].character, 'b');
+        assert_eq!(cells[2][0].character, '\0');
+
+        let screen = term.screen_selection();
+        assert!(screen.contains(1, 1) && screen.contains(4, 2));
+        assert!(!screen.contains(0, 1) && !screen.contains(5, 0));
+
+        // 通常の選択に戻すと行をまたいで続けて選ぶ
+        term.start_selection(4, 0, false);
+        term.extend_selection(1, 1);
+        assert_eq!(term.get_selected_text().as_deref(), Some("ef\ngh"));
+        let cells = term.get_selected_cells().unwrap();
+        assert_eq!(cells.iter().map(Vec::len).collect::<Vec<_>>(), [2, 2]);
+    }
+
+    #[test]
+    fn test_paste_bytes() {
+        let mut term = Terminal::new(8, 2);
+        assert_eq!(term.paste_bytes("ls\r\npwd\n"), b"ls\rpwd\r");
+
+        term.mode.insert(TerminalMode::BRACKETED_PASTE);
+        assert_eq!(term.paste_bytes("a\x1b[201~b\n"), b"\x1b[200~a[201~b\r\x1b[201~");
+    }
+
+    #[test]
+    fn test_unseen_lines() {
+        let mut term = Terminal::new(4, 2);
+        let feed = |term: &mut Terminal, n: usize| {
+            for _ in 0..n {
+                term.input_char('x');
+                term.linefeed();
+                term.carriage_return();
+            }
+        };
+        feed(&mut term, 4);
+        assert_eq!(term.unseen_lines(), 0);
+
+        // さかのぼっている間に届いた行だけを数える
+        term.scroll_display(1);
+        feed(&mut term, 3);
+        assert_eq!(term.unseen_lines(), 3);
+
+        // 最下部に戻ると 0、もう一度さかのぼると数え直す
+        term.scroll_to_bottom();
+        assert_eq!(term.unseen_lines(), 0);
+        term.scroll_display(2);
+        assert_eq!(term.unseen_lines(), 0);
+        feed(&mut term, 1);
+        assert_eq!(term.unseen_lines(), 1);
+    }
+
+    #[test]
+    fn test_output_lines_and_marks() {
+        let mut term = Terminal::new(8, 2);
+        let write = |term: &mut Terminal, line: &str| {
+            for c in line.chars() {
+                term.input_char(c);
+            }
+            term.linefeed();
+            term.carriage_return();
+        };
+        write(&mut term, "a");
+        write(&mut term, "b");
+        assert_eq!(term.take_output_lines(), vec![(0, "a".to_string()), (1, "b".to_string())]);
+
+        // 書きかけの行はまだ取り出さない
+        term.input_char('c');
+        assert!(term.take_output_lines().is_empty());
+        term.linefeed();
+        assert_eq!(term.take_output_lines(), vec![(2, "c".to_string())]);
+
+        // 取り出した印とは別に、好きな行から読める
+        assert_eq!(term.output_lines_from(1), (vec![(1, "b".to_string()), (2, "c".to_string())], 3));
+        assert_eq!(term.output_lines_from(u64::MAX), (Vec::new(), 3));
+
+        // 印を付けた行は history_cells の番号で返る
+        term.mark_line(1);
+        term.mark_line(1);
+        assert_eq!(term.marked_lines(), vec![1]);
+    }
+
+    #[test]
+    fn test_jump_to_prompt() {
+        let mut term = Terminal::new(8, 3);
+        // プロンプト "$ cmd" と2行の出力を3回
+        for n in 0..3 {
+            term.push_shell_mark(ShellMark::PromptStart);
+            for c in format!("$ cmd{}", n).chars() {
+                term.input_char(c);
+            }
+            term.linefeed();
+            term.carriage_return();
+            term.push_shell_mark(ShellMark::OutputStart);
+            for _ in 0..2 {
+                term.input_char('o');
+                term.linefeed();
+                term.carriage_return();
+            }
+            term.push_shell_mark(ShellMark::CommandFinished(Some(0)));
+        }
+        let prompts = term.prompt_lines(None);
+        assert_eq!(prompts, vec![0, 3, 6]);
+
+        // 前へ: 画面の1行目より上のプロンプト
+        assert_eq!(term.row_history_line(0), 7);
+        assert!(term.jump_to_prompt(&prompts, -1));
+        assert_eq!(term.row_history_line(0), 6);
+        assert!(term.jump_to_prompt(&prompts, -1));
+        assert_eq!(term.row_history_line(0), 3);
+        assert!(term.jump_to_prompt(&prompts, -1));
+        assert!(!term.jump_to_prompt(&prompts, -1));
+        assert_eq!(term.row_history_line(0), 0);
+
+        // 次へ
+        assert!(term.jump_to_prompt(&prompts, 1));
+        assert_eq!(term.row_history_line(0), 3);
+
+        // 最後のコマンドの出力（D の行の前まで）
+        assert_eq!(term.last_output_lines(), Some((7, 8)));
+        assert_eq!(term.select_last_output().as_deref(), Some("o\no"));
+
+        // 出力のないコマンドは飛ばして、その前のコマンド
+        term.push_shell_mark(ShellMark::PromptStart);
+        term.push_shell_mark(ShellMark::OutputStart);
+        term.push_shell_mark(ShellMark::CommandFinished(Some(0)));
+        assert_eq!(term.last_output_lines(), Some((7, 8)));
+
+        // マークがなければ正規表現で探す
+        let mut plain = Terminal::new(8, 2);
+        for line in ["$ ls", "a", "$ pwd"] {
+            for c in line.chars() {
+                plain.input_char(c);
+            }
+            plain.linefeed();
+            plain.carriage_return();
+        }
+        assert!(plain.prompt_lines(None).is_empty());
+        let regex = Regex::new(r"^\$ ").unwrap();
+        assert_eq!(plain.prompt_lines(Some(&regex)), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_history_cells() {
+        let mut term = Terminal::new(4, 2);
+        for line in ["ab", "cd", "ef", "gh"] {
+            for c in line.chars() {
+                term.input_char(c);
+            }
+            term.linefeed();
+            term.carriage_return();
+        }
+
+        // スクロールバックの後に画面の行（セル単位）
+        let cells = term.history_cells();
+        assert_eq!(cells.len(), 5);
+        assert_eq!(cells[0][..2], ['a', 'b']);
+        assert_eq!(cells[3][..2], ['g', 'h']);
+
+        // 最下部では画面の1行目がスクロールバックの直後
+        assert_eq!(term.history_line_row(3), Some(0));
+        assert_eq!(term.history_line_row(1), None);
+        assert_eq!(term.row_history_line(1), 4);
+
+        // 画面より上の行は1行目に来るまでさかのぼる
+        term.reveal_history_line(1);
+        assert_eq!(term.display_offset, 2);
+        assert_eq!(term.history_line_row(1), Some(0));
+        assert_eq!(term.visible_cell(0, 0).character, 'c');
+
+        // 画面より下の行は最終行に来るまで戻す
+        term.reveal_history_line(3);
+        assert_eq!(term.display_offset, 1);
+        assert_eq!(term.history_line_row(3), Some(1));
+
+        // 画面内の行なら動かさない
+        term.reveal_history_line(2);
+        assert_eq!(term.display_offset, 1);
+    }
 }
@@ -0,0 +1,329 @@
+//! 色のテーマ
+//!
+//! 設定ファイルの `[colors] theme = "nord"` で元にするテーマを選ぶ
+//! （`[colors]` に書いた色はテーマの色より優先）
+//!
+//! 組み込みのテーマのほか、設定ファイルと同じ場所の `themes/` に置いた
+//! `<名前>.toml` / `<名前>.yaml` も使える。形式は `[colors]` と同じ:
+//!
+//! ```toml
+//! foreground = "#d8dee9"
+//! background = "#2e3440"
+//! accent = "#88c0d0"
+//! ansi = ["#3b4252", "#bf616a", ...]
+//! ```
+//!
+//! iTerm2・base16・Alacritty の配色ファイルも、そのまま置けば使える（`theme_import`）
+//!
+//! 実行中は `next-theme` の操作で順に切り替えられる
+//!
+//! `[colors] light_theme` / `dark_theme` を書くと、OS のライト / ダークの外観に合わせて
+//! テーマを切り替える（macOS・Windows はウィンドウのイベント、Linux は GNOME の設定を監視）
+
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use parking_lot::RwLock;
+
+use crate::config::{self, ColorsConfig};
+use crate::grid::Palette;
+use crate::theme_import;
+
+/// 既定のテーマ（水色の文字に黒の背景）
+pub const DEFAULT_THEME: &str = "umi";
+
+/// 組み込みのテーマ（名前, テーマファイルと同じ形式の TOML）
+const BUILTIN_THEMES: &[(&str, &str)] = &[
+    (
+        "umi",
+        r##"
+        foreground = "#50dcc8"
+        background = "#000000"
+        cursor = "#50dcc8"
+        selection_foreground = "#ffffff"
+        selection_background = "#3380b3"
+        accent = "#50dcc8"
+        ansi = ["#000000", "#ff0000", "#00ff00", "#ffff00", "#0000ff", "#ff00ff", "#00ffff", "#ffffff",
+                "#808080", "#ff0000", "#00ff00", "#ffff00", "#0000ff", "#ff00ff", "#00ffff", "#ffffff"]
+        "##,
+    ),
+    (
+        "solarized-dark",
+        r##"
+        foreground = "#839496"
+        background = "#002b36"
+        cursor = "#93a1a1"
+        selection_foreground = "#93a1a1"
+        selection_background = "#073642"
+        accent = "#268bd2"
+        ansi = ["#073642", "#dc322f", "#859900", "#b58900", "#268bd2", "#d33682", "#2aa198", "#eee8d5",
+                "#002b36", "#cb4b16", "#586e75", "#657b83", "#839496", "#6c71c4", "#93a1a1", "#fdf6e3"]
+        "##,
+    ),
+    (
+        "solarized-light",
+        r##"
+        foreground = "#657b83"
+        background = "#fdf6e3"
+        cursor = "#586e75"
+        selection_foreground = "#586e75"
+        selection_background = "#eee8d5"
+        accent = "#268bd2"
+        ansi = ["#073642", "#dc322f", "#859900", "#b58900", "#268bd2", "#d33682", "#2aa198", "#eee8d5",
+                "#002b36", "#cb4b16", "#586e75", "#657b83", "#839496", "#6c71c4", "#93a1a1", "#fdf6e3"]
+        "##,
+    ),
+    (
+        "dracula",
+        r##"
+        foreground = "#f8f8f2"
+        background = "#282a36"
+        cursor = "#f8f8f2"
+        selection_foreground = "#f8f8f2"
+        selection_background = "#44475a"
+        accent = "#bd93f9"
+        ansi = ["#21222c", "#ff5555", "#50fa7b", "#f1fa8c", "#bd93f9", "#ff79c6", "#8be9fd", "#f8f8f2",
+                "#6272a4", "#ff6e6e", "#69ff94", "#ffffa5", "#d6acff", "#ff92df", "#a4ffff", "#ffffff"]
+        "##,
+    ),
+    (
+        "nord",
+        r##"
+        foreground = "#d8dee9"
+        background = "#2e3440"
+        cursor = "#d8dee9"
+        selection_foreground = "#eceff4"
+        selection_background = "#434c5e"
+        accent = "#88c0d0"
+        ansi = ["#3b4252", "#bf616a", "#a3be8c", "#ebcb8b", "#81a1c1", "#b48ead", "#88c0d0", "#e5e9f0",
+                "#4c566a", "#bf616a", "#a3be8c", "#ebcb8b", "#81a1c1", "#b48ead", "#8fbcbb", "#eceff4"]
+        "##,
+    ),
+    (
+        "gruvbox-dark",
+        r##"
+        foreground = "#ebdbb2"
+        background = "#282828"
+        cursor = "#ebdbb2"
+        selection_foreground = "#ebdbb2"
+        selection_background = "#504945"
+        accent = "#fabd2f"
+        ansi = ["#282828", "#cc241d", "#98971a", "#d79921", "#458588", "#b16286", "#689d6a", "#a89984",
+                "#928374", "#fb4934", "#b8bb26", "#fabd2f", "#83a598", "#d3869b", "#8ec07c", "#ebdbb2"]
+        "##,
+    ),
+];
+
+/// OS の外観を調べ直す間隔（Linux のみ）
+#[cfg(all(unix, not(target_os = "macos")))]
+const APPEARANCE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 今の OS の外観（分からなければ None）
+static APPEARANCE: RwLock<Option<Appearance>> = RwLock::new(None);
+
+/// テーマファイルの拡張子（この順に探す）
+const EXTENSIONS: &[&str] = &["toml", "yaml", "yml", "itermcolors"];
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 読み込み
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// テーマファイルの中身からパレットを作る（書かなかった色は既定の色）
+///
+/// ほかのターミナルの配色（iTerm2・base16・Alacritty）もそのまま読める
+fn parse(text: &str, ext: &str) -> Result<Palette> {
+    let colors: ColorsConfig = match ext {
+        "itermcolors" => return theme_import::iterm(text),
+        "toml" => match theme_import::from_toml(text)? {
+            Some(palette) => return Ok(palette),
+            None => toml::from_str(text)?,
+        },
+        _ => match theme_import::from_yaml(text)? {
+            Some(palette) => return Ok(palette),
+            None => serde_yaml::from_str(text)?,
+        },
+    };
+    anyhow::ensure!(colors.theme.is_none(), "テーマの中では theme を使えません");
+    colors.apply(Palette::default())
+}
+
+/// テーマファイルを読み込む
+fn load_file(path: &Path) -> Result<Palette> {
+    let text = fs::read_to_string(path).with_context(|| format!("テーマを開けません: {}", path.display()))?;
+    let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("toml");
+    parse(&text, ext).with_context(|| format!("テーマを読み込めません: {}", path.display()))
+}
+
+/// テーマファイルを置く場所（設定ファイルと同じ場所の `themes/`）
+fn themes_dir() -> Option<PathBuf> {
+    Some(config::path()?.parent()?.join("themes"))
+}
+
+/// 名前のテーマを読み込む（`themes/` のファイルを組み込みのテーマより優先）
+pub fn load(name: &str) -> Result<Palette> {
+    let file = themes_dir().and_then(|dir| {
+        EXTENSIONS
+            .iter()
+            .map(|ext| dir.join(format!("{}.{}", name, ext)))
+            .find(|path| path.is_file())
+    });
+    if let Some(path) = file {
+        return load_file(&path);
+    }
+    let (_, text) = BUILTIN_THEMES
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .with_context(|| format!("テーマが見つかりません: {}", name))?;
+    parse(text, "toml")
+}
+
+/// 使えるテーマの名前（組み込みのテーマ、`themes/` のファイルの順）
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = BUILTIN_THEMES.iter().map(|(name, _)| name.to_string()).collect();
+    let mut files: Vec<String> = themes_dir()
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let ext = path.extension()?.to_str()?;
+            EXTENSIONS.contains(&ext).then(|| path.file_stem()?.to_str().map(str::to_string))?
+        })
+        .collect();
+    files.sort();
+    for name in files {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// current の次のテーマの名前（最後まで行ったら最初に戻る）
+pub fn next(names: &[String], current: Option<&str>) -> Option<String> {
+    let current = current.unwrap_or(DEFAULT_THEME);
+    let next = match names.iter().position(|name| name == current) {
+        Some(index) => (index + 1) % names.len(),
+        None => 0,
+    };
+    names.get(next).cloned()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// OS の外観
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// OS の外観（ライト / ダーク）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Appearance {
+    Light,
+    Dark,
+}
+
+impl From<winit::window::Theme> for Appearance {
+    fn from(theme: winit::window::Theme) -> Self {
+        match theme {
+            winit::window::Theme::Light => Self::Light,
+            winit::window::Theme::Dark => Self::Dark,
+        }
+    }
+}
+
+/// 今の OS の外観
+pub fn appearance() -> Option<Appearance> {
+    *APPEARANCE.read()
+}
+
+/// OS の外観を記録する
+///
+/// 戻り値: 変わったか
+pub fn set_appearance(appearance: Appearance) -> bool {
+    APPEARANCE.write().replace(appearance) != Some(appearance)
+}
+
+/// GNOME の `color-scheme` から外観を調べる（gsettings がなければ None）
+#[cfg(all(unix, not(target_os = "macos")))]
+fn query_appearance() -> Option<Appearance> {
+    let output = std::process::Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", "color-scheme"])
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let scheme = String::from_utf8_lossy(&output.stdout);
+    Some(if scheme.contains("dark") { Appearance::Dark } else { Appearance::Light })
+}
+
+/// OS の外観を監視し、変わったら on_change を呼ぶ（監視スレッドから呼ばれる）
+///
+/// winit は Linux で外観の変化を知らせないため、ライト / ダークのテーマを
+/// 書いている間だけ GNOME の設定を定期的に調べる
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn watch_appearance(on_change: impl Fn(Appearance) + Send + 'static) {
+    let spawned = std::thread::Builder::new()
+        .name("appearance".into())
+        .spawn(move || {
+            let mut last = None;
+            loop {
+                if config::current().colors.follows_appearance() {
+                    // gsettings がなければ監視をやめる
+                    let Some(appearance) = query_appearance() else { return };
+                    if last != Some(appearance) {
+                        last = Some(appearance);
+                        on_change(appearance);
+                    }
+                }
+                std::thread::sleep(APPEARANCE_POLL_INTERVAL);
+            }
+        });
+    if let Err(e) = spawned {
+        log::warn!("OS の外観の監視を開始できません: {}", e);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Color;
+
+    #[test]
+    fn test_builtin_themes() {
+        for (name, text) in BUILTIN_THEMES {
+            assert!(parse(text, "toml").is_ok(), "{}", name);
+        }
+        // 既定のテーマはこれまでの色のまま
+        assert_eq!(parse(BUILTIN_THEMES[0].1, "toml").unwrap(), Palette::default());
+        assert_eq!(load("nord").unwrap().background, Color::rgb(0x2e, 0x34, 0x40));
+        assert!(load("no-such-theme").is_err());
+    }
+
+    #[test]
+    fn test_parse_yaml() {
+        let palette = parse("foreground: \"#ffffff\"\naccent: \"#ff0000\"\n", "yaml").unwrap();
+        assert_eq!(palette.foreground, Color::WHITE);
+        assert_eq!(palette.cursor, Color::WHITE);
+        assert_eq!(palette.accent, Color::rgb(255, 0, 0));
+        assert_eq!(palette.background, Palette::default().background);
+
+        assert!(parse("foreground: white\n", "yaml").is_err());
+        assert!(parse("theme = \"nord\"\n", "toml").is_err());
+    }
+
+    #[test]
+    fn test_next() {
+        let names: Vec<String> = ["umi", "nord", "dracula"].iter().map(|name| name.to_string()).collect();
+        assert_eq!(next(&names, None).as_deref(), Some("nord"));
+        assert_eq!(next(&names, Some("dracula")).as_deref(), Some("umi"));
+        assert_eq!(next(&names, Some("removed")).as_deref(), Some("umi"));
+        assert_eq!(next(&[], None), None);
+    }
+}
@@ -0,0 +1,369 @@
+//! ほかのターミナルの配色の読み込み
+//!
+//! `themes/` に置いた次の形式のファイルを、そのままテーマとして使えるようにする
+//!
+//! - iTerm2 の `.itermcolors`（plist）
+//! - base16 の YAML（`base00`〜`base0F`、`palette:` の下に書く新しい形式も）
+//! - Alacritty のテーマ（`[colors.primary]` などを書いた TOML、古い YAML も）
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::grid::{Color, Palette};
+
+/// base16 の16色の名前
+const BASE16_KEYS: [&str; 16] = [
+    "base00", "base01", "base02", "base03", "base04", "base05", "base06", "base07",
+    "base08", "base09", "base0A", "base0B", "base0C", "base0D", "base0E", "base0F",
+];
+
+/// base16 の色を ANSI 16色に割り当てる順（base16-shell と同じ）
+const BASE16_ANSI: [usize; 16] = [
+    0x00, 0x08, 0x0B, 0x0A, 0x0D, 0x0E, 0x0C, 0x05,
+    0x03, 0x08, 0x0B, 0x0A, 0x0D, 0x0E, 0x0C, 0x07,
+];
+
+/// `#rrggbb`・`rrggbb`・`0xrrggbb` の色
+fn hex(value: &str) -> Option<Color> {
+    let value = value.trim();
+    let digits = value
+        .strip_prefix('#')
+        .or_else(|| value.strip_prefix("0x"))
+        .unwrap_or(value);
+    Color::from_hex(&format!("#{}", digits))
+}
+
+/// 色の名前と値から色を読む（読めなければエラー）
+fn parse_color(name: &str, value: &str) -> Result<Color> {
+    hex(value).with_context(|| format!("{} の色が正しくありません: {:?}", name, value))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// iTerm2
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// plist の `<dict>` の中身（キーと値の組）
+fn plist_entries<'a, 'input>(
+    dict: roxmltree::Node<'a, 'input>,
+) -> impl Iterator<Item = (&'a str, roxmltree::Node<'a, 'input>)> {
+    let mut children = dict.children().filter(|node| node.is_element());
+    std::iter::from_fn(move || loop {
+        let key = children.next()?;
+        if key.has_tag_name("key") {
+            return Some((key.text().unwrap_or(""), children.next()?));
+        }
+    })
+}
+
+/// `<dict>` の `Red Component` などから色を作る（0.0〜1.0）
+fn iterm_color(dict: roxmltree::Node) -> Option<Color> {
+    let mut rgb = [None; 3];
+    for (key, value) in plist_entries(dict) {
+        let slot = match key {
+            "Red Component" => 0,
+            "Green Component" => 1,
+            "Blue Component" => 2,
+            _ => continue,
+        };
+        let component: f32 = value.text()?.trim().parse().ok()?;
+        rgb[slot] = Some((component.clamp(0.0, 1.0) * 255.0).round() as u8);
+    }
+    Some(Color::rgb(rgb[0]?, rgb[1]?, rgb[2]?))
+}
+
+/// iTerm2 の `.itermcolors` を読む
+pub fn iterm(text: &str) -> Result<Palette> {
+    // iTerm2 が書き出すファイルには DOCTYPE が入っている
+    let options = roxmltree::ParsingOptions { allow_dtd: true, ..Default::default() };
+    let document = roxmltree::Document::parse_with_options(text, options).context("itermcolors の XML が正しくありません")?;
+    let dict = document
+        .root_element()
+        .children()
+        .find(|node| node.has_tag_name("dict"))
+        .context("itermcolors に色の一覧（dict）がありません")?;
+
+    let mut palette = Palette::default();
+    let mut cursor = None;
+    let mut found = false;
+    for (key, value) in plist_entries(dict) {
+        let color = iterm_color(value).with_context(|| format!("{} の色が正しくありません", key))?;
+        let slot = match key {
+            "Foreground Color" => &mut palette.foreground,
+            "Background Color" => &mut palette.background,
+            "Cursor Color" => cursor.insert(color),
+            "Selected Text Color" => &mut palette.selection_fg,
+            "Selection Color" => &mut palette.selection_bg,
+            _ => match key.strip_prefix("Ansi ").and_then(|rest| rest.strip_suffix(" Color")) {
+                Some(index) => match index.parse::<usize>().ok().and_then(|index| palette.ansi.get_mut(index)) {
+                    Some(slot) => slot,
+                    None => continue,
+                },
+                None => continue,
+            },
+        };
+        *slot = color;
+        found = true;
+    }
+    anyhow::ensure!(found, "itermcolors に色がありません");
+    palette.cursor = cursor.unwrap_or(palette.foreground);
+    palette.accent = palette.ansi[4];
+    Ok(palette)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// base16
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// base16 の形式か（`base00` があるか）
+fn is_base16(value: &serde_yaml::Value) -> bool {
+    let colors = value.get("palette").unwrap_or(value);
+    colors.get("base00").is_some()
+}
+
+/// base16 の YAML を読む
+fn base16(value: &serde_yaml::Value) -> Result<Palette> {
+    let colors = value.get("palette").unwrap_or(value);
+    let mut base = [Color::BLACK; 16];
+    for (slot, key) in base.iter_mut().zip(BASE16_KEYS) {
+        let text = colors
+            .get(key)
+            .and_then(serde_yaml::Value::as_str)
+            .with_context(|| format!("base16 の {} がありません", key))?;
+        *slot = parse_color(key, text)?;
+    }
+    Ok(Palette {
+        foreground: base[0x05],
+        background: base[0x00],
+        cursor: base[0x05],
+        selection_fg: base[0x05],
+        selection_bg: base[0x02],
+        accent: base[0x0D],
+        ansi: BASE16_ANSI.map(|index| base[index]),
+    })
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Alacritty
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Alacritty のテーマファイル（`colors` 以外は読み飛ばす）
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct AlacrittyFile {
+    colors: AlacrittyColors,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct AlacrittyColors {
+    primary: AlacrittyPrimary,
+    cursor: AlacrittyPair,
+    selection: AlacrittyPair,
+    normal: AlacrittyAnsi,
+    bright: AlacrittyAnsi,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct AlacrittyPrimary {
+    foreground: Option<String>,
+    background: Option<String>,
+}
+
+/// `[colors.cursor]`・`[colors.selection]`（`CellForeground` などの指定は読み飛ばす）
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct AlacrittyPair {
+    text: Option<String>,
+    cursor: Option<String>,
+    background: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct AlacrittyAnsi {
+    black: Option<String>,
+    red: Option<String>,
+    green: Option<String>,
+    yellow: Option<String>,
+    blue: Option<String>,
+    magenta: Option<String>,
+    cyan: Option<String>,
+    white: Option<String>,
+}
+
+impl AlacrittyAnsi {
+    fn colors(&self) -> [(&'static str, &Option<String>); 8] {
+        [
+            ("black", &self.black),
+            ("red", &self.red),
+            ("green", &self.green),
+            ("yellow", &self.yellow),
+            ("blue", &self.blue),
+            ("magenta", &self.magenta),
+            ("cyan", &self.cyan),
+            ("white", &self.white),
+        ]
+    }
+}
+
+impl AlacrittyFile {
+    fn palette(&self) -> Result<Palette> {
+        // `CellForeground` のような色以外の指定は、書かなかったのと同じ
+        let color = |name: &str, value: &Option<String>| -> Result<Option<Color>> {
+            match value.as_deref() {
+                Some(value) if value.starts_with("Cell") => Ok(None),
+                Some(value) => parse_color(name, value).map(Some),
+                None => Ok(None),
+            }
+        };
+        let colors = &self.colors;
+        let mut palette = Palette::default();
+        if let Some(foreground) = color("primary.foreground", &colors.primary.foreground)? {
+            palette.foreground = foreground;
+        }
+        if let Some(background) = color("primary.background", &colors.primary.background)? {
+            palette.background = background;
+        }
+        let groups = colors.normal.colors().into_iter().chain(colors.bright.colors());
+        for (slot, (name, value)) in palette.ansi.iter_mut().zip(groups) {
+            if let Some(ansi) = color(name, value)? {
+                *slot = ansi;
+            }
+        }
+        palette.cursor = color("cursor.cursor", &colors.cursor.cursor)?.unwrap_or(palette.foreground);
+        if let Some(text) = color("selection.text", &colors.selection.text)? {
+            palette.selection_fg = text;
+        }
+        if let Some(background) = color("selection.background", &colors.selection.background)? {
+            palette.selection_bg = background;
+        }
+        palette.accent = palette.ansi[4];
+        Ok(palette)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 形式の判定
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// TOML のテーマファイルを読む（UmiTerm の形式なら None）
+pub fn from_toml(text: &str) -> Result<Option<Palette>> {
+    let table: toml::Table = text.parse()?;
+    if !table.contains_key("colors") {
+        return Ok(None);
+    }
+    let file: AlacrittyFile = toml::from_str(text)?;
+    file.palette().map(Some)
+}
+
+/// YAML のテーマファイルを読む（UmiTerm の形式なら None）
+pub fn from_yaml(text: &str) -> Result<Option<Palette>> {
+    let value: serde_yaml::Value = serde_yaml::from_str(text)?;
+    if is_base16(&value) {
+        return base16(&value).map(Some);
+    }
+    if value.get("colors").is_none() {
+        return Ok(None);
+    }
+    let file: AlacrittyFile = serde_yaml::from_value(value)?;
+    file.palette().map(Some)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iterm() {
+        let text = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Ansi 1 Color</key>
+    <dict>
+        <key>Blue Component</key><real>0.0</real>
+        <key>Color Space</key><string>sRGB</string>
+        <key>Green Component</key><real>0.0</real>
+        <key>Red Component</key><real>1</real>
+    </dict>
+    <key>Background Color</key>
+    <dict>
+        <key>Blue Component</key><real>0.2</real>
+        <key>Green Component</key><real>0.1</real>
+        <key>Red Component</key><real>0.0</real>
+    </dict>
+</dict>
+</plist>"#;
+        let palette = iterm(text).unwrap();
+        assert_eq!(palette.ansi[1], Color::rgb(255, 0, 0));
+        assert_eq!(palette.background, Color::rgb(0, 26, 51));
+        // 書かれていない色は既定の色
+        assert_eq!(palette.foreground, Palette::default().foreground);
+        assert_eq!(palette.cursor, palette.foreground);
+
+        assert!(iterm("<plist><dict></dict></plist>").is_err());
+        assert!(iterm("not xml").is_err());
+    }
+
+    #[test]
+    fn test_base16() {
+        let mut text = String::from("scheme: \"Test\"\n");
+        for (index, key) in BASE16_KEYS.iter().enumerate() {
+            text.push_str(&format!("{}: \"{:02x}{:02x}{:02x}\"\n", key, index, index, index));
+        }
+        let palette = from_yaml(&text).unwrap().unwrap();
+        let gray = |index: u8| Color::rgb(index, index, index);
+        assert_eq!(palette.background, gray(0x00));
+        assert_eq!(palette.foreground, gray(0x05));
+        assert_eq!(palette.ansi[1], gray(0x08));
+        assert_eq!(palette.ansi[15], gray(0x07));
+
+        // palette: の下に書く形式
+        let nested = text.replace("base", "  base").replace("scheme: \"Test\"\n", "palette:\n");
+        assert_eq!(from_yaml(&nested).unwrap().unwrap(), palette);
+
+        // 足りない色はエラー
+        assert!(from_yaml("base00: \"000000\"\n").is_err());
+    }
+
+    #[test]
+    fn test_alacritty() {
+        let toml = r##"
+            [colors.primary]
+            foreground = "#ffffff"
+            background = "0x101010"
+
+            [colors.cursor]
+            text = "CellBackground"
+            cursor = "CellForeground"
+
+            [colors.normal]
+            red = "#ff0000"
+
+            [colors.bright]
+            white = "#eeeeee"
+
+            [font]
+            size = 12
+        "##;
+        let palette = from_toml(toml).unwrap().unwrap();
+        assert_eq!(palette.foreground, Color::WHITE);
+        assert_eq!(palette.background, Color::rgb(16, 16, 16));
+        assert_eq!(palette.cursor, Color::WHITE);
+        assert_eq!(palette.ansi[1], Color::rgb(255, 0, 0));
+        assert_eq!(palette.ansi[15], Color::rgb(238, 238, 238));
+
+        let yaml = "colors:\n  primary:\n    background: '#101010'\n";
+        assert_eq!(from_yaml(yaml).unwrap().unwrap().background, Color::rgb(16, 16, 16));
+
+        // UmiTerm の形式は読まない
+        assert_eq!(from_toml("foreground = \"#ffffff\"").unwrap(), None);
+        assert_eq!(from_yaml("foreground: \"#ffffff\"").unwrap(), None);
+        assert!(from_toml("[colors.primary]\nforeground = \"white\"").is_err());
+    }
+}
@@ -0,0 +1,215 @@
+//! 出力のトリガー
+//!
+//! ペインに出力された行を正規表現で調べ、一致したら通知・ベル・コマンドの実行・
+//! 行のマークを行う（例: 「panic」が出たら通知する）
+//!
+//! 規則は環境変数 `UMITERM_TRIGGERS` に1行1つ、`正規表現 => 動作` の形で書く
+//!
+//! ```text
+//! panic => notify
+//! Compilation finished => run say done
+//! ^error => bell
+//! ^FAIL => mark
+//! ```
+
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+
+use crate::config;
+use crate::notification;
+use crate::pane::Pane;
+
+/// 同じ規則で通知・コマンドを続けて実行しない時間（大量の出力で連発させない）
+const COOLDOWN: Duration = Duration::from_secs(1);
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 規則
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 一致したときの動作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TriggerAction {
+    /// デスクトップ通知
+    Notify,
+    /// ベル（非表示のタブにバッジを付ける）
+    Bell,
+    /// シェルでコマンドを実行（一致した行は環境変数 `UMITERM_TRIGGER_LINE`）
+    Run(String),
+    /// 行にマークを付ける（Cmd + ↑ / ↓ の移動先になる）
+    Mark,
+}
+
+impl TriggerAction {
+    /// 名前から取得（notify / bell / run <command> / mark）
+    fn parse(text: &str) -> Option<Self> {
+        let (name, arg) = text.split_once(' ').unwrap_or((text, ""));
+        let arg = arg.trim();
+        match (name, arg.is_empty()) {
+            ("notify", true) => Some(Self::Notify),
+            ("bell", true) => Some(Self::Bell),
+            ("mark", true) => Some(Self::Mark),
+            ("run", false) => Some(Self::Run(arg.to_string())),
+            _ => None,
+        }
+    }
+
+    /// 続けて実行しないよう間隔を空けるか
+    fn throttled(&self) -> bool {
+        matches!(self, Self::Notify | Self::Run(_))
+    }
+}
+
+/// 出力の規則
+pub struct Trigger {
+    regex: Regex,
+    action: TriggerAction,
+    /// 最後に動作した時刻
+    last_fired: Option<Instant>,
+}
+
+/// 規則の一覧（環境変数 `UMITERM_TRIGGERS`）
+pub fn triggers_from_env() -> Vec<Trigger> {
+    parse_triggers(&config::var("UMITERM_TRIGGERS").unwrap_or_default())
+}
+
+/// 規則を読む（正しくない行は警告して飛ばす）
+fn parse_triggers(value: &str) -> Vec<Trigger> {
+    rule_lines(value)
+        .filter_map(|line| parse_rule(line).inspect_err(|e| log::warn!("{:#}", e)).ok())
+        .collect()
+}
+
+/// 規則がすべて正しいか（設定ファイルを読むときに確かめる）
+pub fn check_rules(value: &str) -> Result<()> {
+    rule_lines(value).try_for_each(|line| parse_rule(line).map(drop))
+}
+
+/// 規則の行（空行は除く）
+fn rule_lines(value: &str) -> impl Iterator<Item = &str> {
+    value.lines().map(str::trim).filter(|line| !line.is_empty())
+}
+
+/// 1行の規則を読む
+fn parse_rule(line: &str) -> Result<Trigger> {
+    let Some((pattern, action)) = line.split_once(" => ") else {
+        bail!("トリガーは「正規表現 => 動作」の形で書いてください: {:?}", line);
+    };
+    let Some(action) = TriggerAction::parse(action.trim()) else {
+        bail!("トリガーの動作が正しくありません: {:?}", action);
+    };
+    let regex = Regex::new(pattern.trim())
+        .with_context(|| format!("トリガーの正規表現が正しくありません: {:?}", pattern))?;
+    Ok(Trigger { regex, action, last_fired: None })
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// 照合
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// 一致した規則と行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fired {
+    action: TriggerAction,
+    /// 行の通し番号（Terminal::take_output_lines の番号）
+    pub line: u64,
+    /// 行の文字列
+    pub text: String,
+}
+
+/// 出力された行を規則と照合し、動作させるものを返す
+pub fn scan(triggers: &mut [Trigger], lines: &[(u64, String)], now: Instant) -> Vec<Fired> {
+    let mut fired = Vec::new();
+    for (line, text) in lines {
+        for trigger in triggers.iter_mut() {
+            if !trigger.regex.is_match(text) {
+                continue;
+            }
+            if trigger.action.throttled() {
+                if trigger.last_fired.is_some_and(|last| now - last < COOLDOWN) {
+                    continue;
+                }
+                trigger.last_fired = Some(now);
+            }
+            fired.push(Fired { action: trigger.action.clone(), line: *line, text: text.clone() });
+        }
+    }
+    fired
+}
+
+/// ペインに出力し終えた行（Terminal::take_output_lines）を規則と照合して動作させる
+pub fn apply(triggers: &mut [Trigger], pane: &mut Pane, lines: &[(u64, String)], now: Instant) {
+    for fired in scan(triggers, lines, now) {
+        log::info!("トリガー {:?}: {}", fired.action, fired.text);
+        match &fired.action {
+            TriggerAction::Notify => notification::notify_trigger(&pane.title().name, &fired.text),
+            TriggerAction::Bell => pane.ring_bell(),
+            TriggerAction::Run(command) => run_command(command, &fired.text),
+            TriggerAction::Mark => pane.terminal.lock().mark_line(fired.line),
+        }
+    }
+}
+
+/// トリガーのコマンドをシェルで実行（終了は待たない）
+pub fn run_command(command: &str, line: &str) {
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+    let result = Command::new(shell)
+        .args(["-c", command])
+        .env("UMITERM_TRIGGER_LINE", line)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match result {
+        // ゾンビ化を防ぐため別スレッドで回収
+        Ok(mut child) => {
+            std::thread::spawn(move || {
+                let _ = child.wait();
+            });
+        }
+        Err(e) => log::warn!("トリガーのコマンドの実行に失敗: {} ({})", command, e),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_triggers() {
+        let triggers = parse_triggers("panic => notify\n\nCompilation finished => run say done\n(bad => bell\nx => jump\ny\n^FAIL => mark");
+        let actions: Vec<_> = triggers.iter().map(|t| t.action.clone()).collect();
+        assert_eq!(
+            actions,
+            [TriggerAction::Notify, TriggerAction::Run("say done".to_string()), TriggerAction::Mark]
+        );
+    }
+
+    #[test]
+    fn test_scan() {
+        let mut triggers = parse_triggers("panic => notify\n^FAIL => mark");
+        let lines = [
+            (10, "thread 'main' panicked".to_string()),
+            (11, "FAIL tests/a.rs".to_string()),
+            (12, "panic again".to_string()),
+            (13, "FAIL tests/b.rs".to_string()),
+        ];
+        let start = Instant::now();
+
+        // 通知は続けて出さないが、マークはすべての行に付ける
+        let fired = scan(&mut triggers, &lines, start);
+        let lines: Vec<_> = fired.iter().map(|f| (f.line, f.action.clone())).collect();
+        assert_eq!(lines, [(10, TriggerAction::Notify), (11, TriggerAction::Mark), (13, TriggerAction::Mark)]);
+
+        // 間隔が空けばまた通知する
+        let panic = [(20, "panic".to_string())];
+        assert!(scan(&mut triggers, &panic, start + Duration::from_millis(500)).is_empty());
+        assert_eq!(scan(&mut triggers, &panic, start + Duration::from_secs(2)).len(), 1);
+    }
+}
@@ -0,0 +1,147 @@
+//! WSL のディストリビューションのモジュール（Windows のみ）
+//!
+//! インストールされているディストリビューションを `wsl-launcher` のコマンドパレットに並べ、
+//! 選んだディストリビューションか `wsl` を書いたプロファイルで `wsl.exe -d <名前>` を起動する
+//! 作業ディレクトリは Windows のパス（`C:\Users\umi`）と Linux のパス（`/mnt/c/Users/umi`）を相互に変換する
+//!
+//! ```toml
+//! [profiles.ubuntu]
+//! wsl = "Ubuntu-24.04"
+//! cwd = "/home/umi/src"
+//! ```
+
+use std::path::{Component, Path, PathBuf, Prefix};
+
+use anyhow::Result;
+
+/// 起動するプログラム
+pub const PROGRAM: &str = "wsl.exe";
+
+/// `wsl.exe` に渡す引数（cwd は Linux のパス、line はディストリビューションの既定のシェルで実行するコマンド）
+pub fn args(distro: &str, cwd: Option<&str>, line: Option<&str>) -> Vec<String> {
+    let mut args = vec!["-d".to_string(), distro.to_string(), "--cd".to_string(), cwd.unwrap_or("~").to_string()];
+    if let Some(line) = line {
+        args.extend(["--".to_string(), line.to_string()]);
+    }
+    args
+}
+
+/// インストールされているディストリビューション（`wsl.exe --list --quiet`）
+#[cfg(windows)]
+pub fn distros() -> Result<Vec<String>> {
+    use std::os::windows::process::CommandExt;
+
+    use anyhow::Context;
+
+    /// コンソールのウィンドウを出さない
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let output = std::process::Command::new(PROGRAM)
+        .args(["--list", "--quiet"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .with_context(|| format!("{} を実行できません", PROGRAM))?;
+    anyhow::ensure!(output.status.success(), "WSL のディストリビューションを取得できません（WSL が入っていません）");
+    Ok(parse_distros(&output.stdout))
+}
+
+#[cfg(not(windows))]
+pub fn distros() -> Result<Vec<String>> {
+    anyhow::bail!("WSL は Windows でのみ使えます")
+}
+
+/// `wsl.exe --list --quiet` の出力を読む（UTF-16LE、1行に1つ）
+#[cfg_attr(not(windows), allow(dead_code))]
+pub fn parse_distros(bytes: &[u8]) -> Vec<String> {
+    let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+    String::from_utf16_lossy(&units)
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .map(|line| line.trim_matches(|c: char| c.is_whitespace() || c == '\0'))
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Windows のパスを Linux のパスにする
+///
+/// `C:\Users\umi` は `/mnt/c/Users/umi`、`\\wsl.localhost\Ubuntu\home\umi`（`\\wsl$\` も）は `/home/umi`
+pub fn to_linux_path(path: &Path) -> Option<String> {
+    let mut components = path.components();
+    let Some(Component::Prefix(prefix)) = components.next() else { return None };
+    let root = match prefix.kind() {
+        Prefix::Disk(drive) | Prefix::VerbatimDisk(drive) => format!("/mnt/{}", (drive as char).to_ascii_lowercase()),
+        Prefix::UNC(server, _) | Prefix::VerbatimUNC(server, _)
+            if server.eq_ignore_ascii_case("wsl$") || server.eq_ignore_ascii_case("wsl.localhost") =>
+        {
+            String::new()
+        }
+        _ => return None,
+    };
+    let rest: Vec<String> = components
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+    let path = format!("{}/{}", root, rest.join("/"));
+    Some(if path.len() > 1 { path.trim_end_matches('/').to_string() } else { path })
+}
+
+/// ディストリビューションの Linux のパスを Windows から開けるパスにする
+///
+/// `/mnt/c/Users/umi` は `C:\Users\umi`、それ以外は `\\wsl.localhost\<ディストリビューション>\home\umi`
+pub fn to_windows_path(distro: &str, path: &str) -> PathBuf {
+    let mut parts = path.split('/').filter(|part| !part.is_empty());
+    let drive = path
+        .strip_prefix("/mnt/")
+        .and_then(|rest| rest.split('/').next())
+        .filter(|drive| drive.len() == 1 && drive.as_bytes()[0].is_ascii_alphabetic());
+    let (root, parts): (String, Vec<&str>) = match drive {
+        Some(drive) => (format!("{}:", drive.to_ascii_uppercase()), parts.by_ref().skip(2).collect()),
+        None => (format!(r"\\wsl.localhost\{}", distro), parts.collect()),
+    };
+    PathBuf::from(format!(r"{}\{}", root, parts.join(r"\")))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// テスト
+// ═══════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args() {
+        assert_eq!(args("Ubuntu", None, None), ["-d", "Ubuntu", "--cd", "~"]);
+        assert_eq!(args("Debian", Some("/home/umi"), Some("htop -d 5")), ["-d", "Debian", "--cd", "/home/umi", "--", "htop -d 5"]);
+    }
+
+    #[test]
+    fn test_parse_distros() {
+        let text = "\u{feff}Ubuntu-24.04\r\nDebian\r\n\r\n";
+        let bytes: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(parse_distros(&bytes), ["Ubuntu-24.04", "Debian"]);
+        assert!(parse_distros(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_to_windows_path() {
+        assert_eq!(to_windows_path("Ubuntu", "/mnt/c/Users/umi"), PathBuf::from(r"C:\Users\umi"));
+        assert_eq!(to_windows_path("Ubuntu", "/mnt/d"), PathBuf::from(r"D:\"));
+        assert_eq!(to_windows_path("Ubuntu", "/home/umi/src"), PathBuf::from(r"\\wsl.localhost\Ubuntu\home\umi\src"));
+        assert_eq!(to_windows_path("Ubuntu", "/mnt/wsl"), PathBuf::from(r"\\wsl.localhost\Ubuntu\mnt\wsl"));
+    }
+
+    // パスの Prefix は Windows でのみ読まれる
+    #[cfg(windows)]
+    #[test]
+    fn test_to_linux_path() {
+        assert_eq!(to_linux_path(Path::new(r"C:\Users\umi")).as_deref(), Some("/mnt/c/Users/umi"));
+        assert_eq!(to_linux_path(Path::new(r"D:\")).as_deref(), Some("/mnt/d"));
+        assert_eq!(to_linux_path(Path::new(r"\\wsl.localhost\Ubuntu\home\umi")).as_deref(), Some("/home/umi"));
+        assert_eq!(to_linux_path(Path::new(r"\\wsl$\Ubuntu")).as_deref(), Some("/"));
+        assert_eq!(to_linux_path(Path::new(r"\\server\share\x")), None);
+    }
+}